@@ -0,0 +1,46 @@
+//! Build script.
+//!
+//! With the `grpc` feature, generates the tonic client and server stubs of
+//! `medimg.v1.Compressor` (proto/medimg_compress.proto). The message types
+//! are written by hand in src/grpc/proto.rs, so no `protoc` is needed.
+//!
+//! With the `node` feature, sets up linking of the Node.js addon.
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    grpc::generate();
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
+
+#[cfg(feature = "grpc")]
+mod grpc {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    const CODEC: &str = "tonic::codec::ProstCodec";
+
+    /// A bidirectional streaming method.
+    fn streaming(name: &str, route: &str, input: &str, output: &str) -> Method {
+        Method::builder()
+            .name(name)
+            .route_name(route)
+            .input_type(format!("super::{}", input))
+            .output_type(format!("super::{}", output))
+            .codec_path(CODEC)
+            .client_streaming()
+            .server_streaming()
+            .build()
+    }
+
+    pub fn generate() {
+        println!("cargo:rerun-if-changed=build.rs");
+        let service = Service::builder()
+            .name("Compressor")
+            .package("medimg.v1")
+            .method(streaming("compress", "Compress", "CompressRequest", "CompressResponse"))
+            .method(streaming("analyze", "Analyze", "CompressRequest", "CompressResponse"))
+            .method(streaming("metrics", "Metrics", "MetricsRequest", "MetricsResponse"))
+            .build();
+        Builder::new().compile(&[service]);
+    }
+}
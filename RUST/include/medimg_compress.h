@@ -0,0 +1,92 @@
+/*
+ * C API for medimg_compress.
+ *
+ * Build the shared library with `cargo build --release --features capi`
+ * and link against libmedimg_compress (medimg_compress.dll on Windows).
+ *
+ * Functions returning int return MEDIMG_OK or MEDIMG_ERROR; after an
+ * error, medimg_last_error() describes it. Errors are tracked per thread.
+ */
+
+#ifndef MEDIMG_COMPRESS_H
+#define MEDIMG_COMPRESS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define MEDIMG_OK 0
+#define MEDIMG_ERROR (-1)
+
+#define MEDIMG_CODEC_JPEG2000 0
+#define MEDIMG_CODEC_JPEGLS 1
+#define MEDIMG_CODEC_UNCOMPRESSED 2
+
+/* Opaque compression configuration. */
+typedef struct MedimgConfig MedimgConfig;
+
+/* Summary of a compression. */
+typedef struct MedimgResult {
+    /* Original pixel data size in bytes. */
+    uint64_t original_size;
+    /* Compressed pixel data size in bytes. */
+    uint64_t compressed_size;
+    /* Compression ratio. */
+    double compression_ratio;
+    /* Time taken in milliseconds. */
+    uint64_t compression_time_ms;
+    /* 1 if the compression was lossless, otherwise 0. */
+    int is_lossless;
+} MedimgResult;
+
+/*
+ * Message for the last failed call on this thread, or NULL if none. Valid
+ * until the next failing call on the same thread; do not free.
+ */
+const char *medimg_last_error(void);
+
+/* Lossless configuration for a MEDIMG_CODEC_* codec; NULL if unknown. */
+MedimgConfig *medimg_config_lossless(int codec);
+
+/* Lossy configuration with a target ratio; NULL if the codec is unknown. */
+MedimgConfig *medimg_config_lossy(int codec, float ratio);
+
+/* Configuration from its JSON form; NULL if it does not parse. */
+MedimgConfig *medimg_config_from_json(const char *json);
+
+/* Release a configuration. NULL is ignored. */
+void medimg_config_free(MedimgConfig *config);
+
+/*
+ * Compress the DICOM file at input_path and write the encapsulated result
+ * to output_path, or only analyze it if output_path is NULL. Paths are
+ * UTF-8. result may be NULL.
+ */
+int medimg_compress_file(const MedimgConfig *config,
+                         const char *input_path,
+                         const char *output_path,
+                         MedimgResult *result);
+
+/*
+ * Compress a DICOM file held in memory. On success *out_data and *out_len
+ * describe the encapsulated DICOM file; release it with
+ * medimg_buffer_free. result may be NULL.
+ */
+int medimg_compress_buffer(const MedimgConfig *config,
+                           const uint8_t *data,
+                           size_t len,
+                           uint8_t **out_data,
+                           size_t *out_len,
+                           MedimgResult *result);
+
+/* Release a buffer from medimg_compress_buffer. NULL is ignored. */
+void medimg_buffer_free(uint8_t *data, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MEDIMG_COMPRESS_H */
@@ -0,0 +1,16 @@
+// Copy the addon built by cargo next to index.js.
+//
+// Usage: node build.js <cargo target dir>
+
+'use strict';
+
+const fs = require('fs');
+const path = require('path');
+
+const library = {
+  darwin: 'libmedimg_compress.dylib',
+  win32: 'medimg_compress.dll',
+}[process.platform] || 'libmedimg_compress.so';
+
+const source = path.join(process.argv[2] || '../target/release', library);
+fs.copyFileSync(source, path.join(__dirname, 'medimg_compress.node'));
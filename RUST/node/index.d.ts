@@ -0,0 +1,80 @@
+// Type declarations for the medimg_compress Node.js bindings.
+
+import { EventEmitter } from 'events';
+
+export interface CompressOptions {
+  /** `"jpeg2000"` or `"jpeg-ls"`. */
+  codec?: 'jpeg2000' | 'jpeg-ls';
+  /** Compression mode. */
+  mode?: 'lossless' | 'lossy' | 'near-lossless';
+  /** Target compression ratio (lossy). */
+  ratio?: number;
+  /** Target bits per pixel (lossy). */
+  bpp?: number;
+  /** JPEG-LS NEAR parameter (near-lossless). */
+  near?: number;
+  /** Measure PSNR/SSIM by round-trip decode. */
+  measureQuality?: boolean;
+}
+
+export interface Progress {
+  /** Phase (`"reading"`, `"encoding"`, `"verification"`, `"writing"`, ...). */
+  phase: string;
+  /** Progress within the file (0 to 1). */
+  fraction: number;
+}
+
+export interface QualityMetrics {
+  psnrDb: number;
+  ssim: number;
+  maxError: number;
+  meanError: number;
+  rmse: number;
+  diffPixelsPercent: number;
+  lossless: boolean;
+  overallQuality: string;
+}
+
+export interface CompressResult {
+  sourcePath: string;
+  outputPath?: string;
+  modality: string;
+  originalSize: number;
+  compressedSize: number;
+  compressionRatio: number;
+  compressionTimeMs: number;
+  lossless: boolean;
+  codec: string;
+  pixelSha256: string;
+  sopInstanceUid?: string;
+  warnings: string[];
+  quality?: QualityMetrics;
+}
+
+/** Compress a DICOM file to `output`. */
+export function compress(
+  input: string,
+  output: string,
+  options?: CompressOptions,
+  onProgress?: (progress: Progress) => void,
+): Promise<CompressResult>;
+
+/** Compress a DICOM file without writing it, for the statistics. */
+export function analyze(
+  input: string,
+  options?: CompressOptions,
+  onProgress?: (progress: Progress) => void,
+): Promise<CompressResult>;
+
+/** Compare a compressed DICOM file with its original. */
+export function metrics(original: string, compressed: string): Promise<QualityMetrics>;
+
+/** Compressor with shared options, emitting `progress` events. */
+export class Compressor extends EventEmitter {
+  constructor(options?: CompressOptions);
+  readonly options: CompressOptions;
+  compress(input: string, output: string, options?: CompressOptions): Promise<CompressResult>;
+  analyze(input: string, options?: CompressOptions): Promise<CompressResult>;
+  metrics(original: string, compressed: string): Promise<QualityMetrics>;
+  on(event: 'progress', listener: (progress: Progress & { input: string }) => void): this;
+}
@@ -0,0 +1,47 @@
+// Node.js bindings for medimg_compress.
+//
+// The native functions run on the libuv thread pool and return promises;
+// `Compressor` adds `progress` events on top of them.
+
+'use strict';
+
+const { EventEmitter } = require('events');
+const native = require('./medimg_compress.node');
+
+/**
+ * Compressor with shared options, emitting `progress` events
+ * (`{ phase, fraction, input }`) while files compress.
+ */
+class Compressor extends EventEmitter {
+  constructor(options = {}) {
+    super();
+    this.options = options;
+  }
+
+  compress(input, output, options) {
+    return native.compress(input, output, this.resolve(options), this.forward(input));
+  }
+
+  analyze(input, options) {
+    return native.analyze(input, this.resolve(options), this.forward(input));
+  }
+
+  metrics(original, compressed) {
+    return native.metrics(original, compressed);
+  }
+
+  resolve(options) {
+    return { ...this.options, ...options };
+  }
+
+  forward(input) {
+    return (progress) => this.emit('progress', { ...progress, input });
+  }
+}
+
+module.exports = {
+  Compressor,
+  compress: native.compress,
+  analyze: native.analyze,
+  metrics: native.metrics,
+};
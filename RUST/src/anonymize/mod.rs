@@ -0,0 +1,712 @@
+//! De-identification of DICOM datasets.
+//!
+//! Implements the DICOM PS3.15 Basic Application Level Confidentiality
+//! Profile (Annex E) at every level of the dataset, sequence items
+//! included:
+//!
+//! - attributes with action X in the Annex E table are removed, as are all
+//!   private tags, curve groups (50xx), overlay data and comments
+//!   (60xx,3000 and 60xx,4000), GPS attributes (0016,0070-008E), and
+//!   hanging protocol selector values (0072,005E-007F)
+//! - Type 2 identifiers (action Z) are emptied, and any other person name
+//!   (PN) is removed
+//! - every UID that is neither DICOM-defined (`1.2.840.10008.*`) nor a
+//!   class or transfer syntax reference is replaced with a new `2.25` UID
+//!   (action U), so references inside sequences follow the instances they
+//!   point to
+//!
+//! The same [`Anonymizer`] maps a given source UID to the same
+//! replacement, so studies and series stay linked across the files of one
+//! run.
+//!
+//! Burned-in annotations in the pixel data are not detected or removed.
+
+use std::collections::HashMap;
+
+use dicom::core::header::Header;
+use dicom::core::{DataElement, PrimitiveValue, Tag, VR};
+use dicom::dictionary_std::tags;
+use dicom::object::InMemDicomObject;
+use serde::Serialize;
+
+use crate::dicom::{utils, DicomFile};
+use crate::error::Result;
+
+/// Attributes removed entirely (action X, and X/Z or X/D on Type 3
+/// attributes). Annex E lists retired attributes too, since old files
+/// still carry them.
+#[allow(deprecated)]
+const REMOVE: &[Tag] = &[
+    tags::ACQUISITION_COMMENTS,
+    tags::ACQUISITION_CONTEXT_DESCRIPTION,
+    tags::ACQUISITION_CONTEXT_SEQUENCE,
+    tags::ACQUISITION_DATE,
+    tags::ACQUISITION_DATE_TIME,
+    tags::ACQUISITION_DEVICE_PROCESSING_DESCRIPTION,
+    tags::ACQUISITION_PROTOCOL_DESCRIPTION,
+    tags::ACQUISITION_PROTOCOL_NAME,
+    tags::ACQUISITION_TIME,
+    tags::ACTUAL_HUMAN_PERFORMERS_SEQUENCE,
+    tags::ADDITIONAL_PATIENT_HISTORY,
+    tags::ADMISSION_ID,
+    tags::ADMITTING_DATE,
+    tags::ADMITTING_DIAGNOSES_CODE_SEQUENCE,
+    tags::ADMITTING_DIAGNOSES_DESCRIPTION,
+    tags::ADMITTING_TIME,
+    tags::AFFECTED_SOP_INSTANCE_UID,
+    tags::ALLERGIES,
+    tags::APPLICATOR_DESCRIPTION,
+    tags::ARBITRARY,
+    tags::AUTHOR_OBSERVER_SEQUENCE,
+    tags::BARCODE_VALUE,
+    tags::BEAM_DESCRIPTION,
+    tags::BOLUS_DESCRIPTION,
+    tags::BRANCH_OF_SERVICE,
+    tags::CAMERA_OWNER_NAME,
+    tags::CASSETTE_ID,
+    tags::CERTIFIED_TIMESTAMP,
+    tags::CLINICAL_TRIAL_SERIES_DESCRIPTION,
+    tags::CLINICAL_TRIAL_TIME_POINT_DESCRIPTION,
+    tags::COMMENTS_ON_THE_PERFORMED_PROCEDURE_STEP,
+    tags::COMPENSATOR_DESCRIPTION,
+    tags::CONFIDENTIALITY_CONSTRAINT_ON_PATIENT_DATA_DESCRIPTION,
+    tags::CONSULTING_PHYSICIAN_IDENTIFICATION_SEQUENCE,
+    tags::CONTAINER_DESCRIPTION,
+    tags::CONTENT_CREATOR_IDENTIFICATION_CODE_SEQUENCE,
+    tags::CONTENT_SEQUENCE,
+    tags::CONTRAST_BOLUS_START_TIME,
+    tags::CONTRAST_BOLUS_STOP_TIME,
+    tags::CONTRIBUTION_DESCRIPTION,
+    tags::COUNTRY_OF_RESIDENCE,
+    tags::CURRENT_OBSERVER_TRIAL,
+    tags::CURRENT_PATIENT_LOCATION,
+    tags::CURVE_DATE,
+    tags::CURVE_TIME,
+    tags::CUSTODIAL_ORGANIZATION_SEQUENCE,
+    tags::DATA_SET_TRAILING_PADDING,
+    tags::DATE_OF_LAST_CALIBRATION,
+    tags::DATE_OF_LAST_DETECTOR_CALIBRATION,
+    tags::DATE_OF_SECONDARY_CAPTURE,
+    tags::DATE_TIME_OF_LAST_CALIBRATION,
+    tags::DECOMPOSITION_DESCRIPTION,
+    tags::DERIVATION_DESCRIPTION,
+    tags::DETECTOR_ID,
+    tags::DEVICE_DESCRIPTION,
+    tags::DEVICE_LABEL,
+    tags::DEVICE_SERIAL_NUMBER,
+    tags::DIGITAL_SIGNATURES_SEQUENCE,
+    tags::DIGITAL_SIGNATURE_DATE_TIME,
+    tags::DIGITAL_SIGNATURE_UID,
+    tags::DISCHARGE_DATE,
+    tags::DISCHARGE_DIAGNOSIS_DESCRIPTION,
+    tags::DISCHARGE_TIME,
+    tags::DISTRIBUTION_ADDRESS,
+    tags::DISTRIBUTION_NAME,
+    tags::DOSE_REFERENCE_DESCRIPTION,
+    tags::ENCRYPTED_ATTRIBUTES_SEQUENCE,
+    tags::END_ACQUISITION_DATE_TIME,
+    tags::ENTITY_DESCRIPTION,
+    tags::ENTITY_NAME,
+    tags::EQUIVALENT_CDA_DOCUMENT_SEQUENCE,
+    tags::ETHNIC_GROUP,
+    tags::EXPECTED_COMPLETION_DATE_TIME,
+    tags::FIXATION_DEVICE_DESCRIPTION,
+    tags::FRACTION_GROUP_DESCRIPTION,
+    tags::FRAME_ACQUISITION_DATE_TIME,
+    tags::FRAME_COMMENTS,
+    tags::FRAME_REFERENCE_DATE_TIME,
+    tags::GANTRY_ID,
+    tags::GENERATOR_ID,
+    tags::HUMAN_PERFORMER_NAME,
+    tags::HUMAN_PERFORMER_ORGANIZATION,
+    tags::ICON_IMAGE_SEQUENCE,
+    tags::IDENTICAL_DOCUMENTS_SEQUENCE,
+    tags::IDENTIFYING_COMMENTS,
+    tags::IMAGE_COMMENTS,
+    tags::IMAGE_PRESENTATION_COMMENTS,
+    tags::IMAGING_SERVICE_REQUEST_COMMENTS,
+    tags::IMPRESSIONS,
+    tags::INSTANCE_COERCION_DATE_TIME,
+    tags::INSTANCE_CREATION_DATE,
+    tags::INSTANCE_CREATION_TIME,
+    tags::INSTITUTIONAL_DEPARTMENT_NAME,
+    tags::INSTITUTIONAL_DEPARTMENT_TYPE_CODE_SEQUENCE,
+    tags::INSTITUTION_ADDRESS,
+    tags::INSTITUTION_CODE_SEQUENCE,
+    tags::INSTITUTION_NAME,
+    tags::INSURANCE_PLAN_IDENTIFICATION,
+    tags::INTENDED_RECIPIENTS_OF_RESULTS_IDENTIFICATION_SEQUENCE,
+    tags::INTERPRETATION_APPROVER_SEQUENCE,
+    tags::INTERPRETATION_AUTHOR,
+    tags::INTERPRETATION_DIAGNOSIS_DESCRIPTION,
+    tags::INTERPRETATION_ID_ISSUER,
+    tags::INTERPRETATION_RECORDER,
+    tags::INTERPRETATION_TEXT,
+    tags::INTERPRETATION_TRANSCRIBER,
+    tags::INTERVENTION_DRUG_START_TIME,
+    tags::INTERVENTION_DRUG_STOP_TIME,
+    tags::ISSUER_OF_ACCESSION_NUMBER_SEQUENCE,
+    tags::ISSUER_OF_ADMISSION_ID,
+    tags::ISSUER_OF_ADMISSION_ID_SEQUENCE,
+    tags::ISSUER_OF_PATIENT_ID,
+    tags::ISSUER_OF_SERVICE_EPISODE_ID,
+    tags::ISSUER_OF_SERVICE_EPISODE_ID_SEQUENCE,
+    tags::LAST_MENSTRUAL_DATE,
+    tags::LENS_MAKE,
+    tags::LENS_MODEL,
+    tags::LENS_SERIAL_NUMBER,
+    tags::LENS_SPECIFICATION,
+    tags::LONG_DEVICE_DESCRIPTION,
+    tags::MAC_PARAMETERS_SEQUENCE,
+    tags::MAKER_NOTE,
+    tags::MEDICAL_ALERTS,
+    tags::MEDICAL_RECORD_LOCATOR,
+    tags::MILITARY_RANK,
+    tags::MODIFIED_ATTRIBUTES_SEQUENCE,
+    tags::MODIFIED_IMAGE_DESCRIPTION,
+    tags::MODIFYING_DEVICE_ID,
+    tags::MODIFYING_DEVICE_MANUFACTURER,
+    tags::NAMES_OF_INTENDED_RECIPIENTS_OF_RESULTS,
+    tags::NAME_OF_PHYSICIANS_READING_STUDY,
+    tags::NONCONFORMING_MODIFIED_ATTRIBUTES_SEQUENCE,
+    tags::OCCUPATION,
+    tags::OPERATORS_NAME,
+    tags::OPERATOR_IDENTIFICATION_SEQUENCE,
+    tags::ORDER_CALLBACK_PHONE_NUMBER,
+    tags::ORDER_CALLBACK_TELECOM_INFORMATION,
+    tags::ORDER_ENTERED_BY,
+    tags::ORDER_ENTERER_LOCATION,
+    tags::ORDER_FILLER_IDENTIFIER_SEQUENCE,
+    tags::ORDER_PLACER_IDENTIFIER_SEQUENCE,
+    tags::ORIGINAL_ATTRIBUTES_SEQUENCE,
+    tags::OTHER_PATIENT_I_DS,
+    tags::OTHER_PATIENT_I_DS_SEQUENCE,
+    tags::OTHER_PATIENT_NAMES,
+    tags::OVERLAY_DATE,
+    tags::OVERLAY_TIME,
+    tags::PARTICIPANT_SEQUENCE,
+    tags::PATIENT_ADDRESS,
+    tags::PATIENT_AGE,
+    tags::PATIENT_ALTERNATIVE_CALENDAR,
+    tags::PATIENT_BIRTH_DATE_IN_ALTERNATIVE_CALENDAR,
+    tags::PATIENT_BIRTH_NAME,
+    tags::PATIENT_BIRTH_TIME,
+    tags::PATIENT_COMMENTS,
+    tags::PATIENT_DEATH_DATE_IN_ALTERNATIVE_CALENDAR,
+    tags::PATIENT_INSTITUTION_RESIDENCE,
+    tags::PATIENT_INSURANCE_PLAN_CODE_SEQUENCE,
+    tags::PATIENT_MOTHER_BIRTH_NAME,
+    tags::PATIENT_PRIMARY_LANGUAGE_CODE_SEQUENCE,
+    tags::PATIENT_PRIMARY_LANGUAGE_MODIFIER_CODE_SEQUENCE,
+    tags::PATIENT_RELIGIOUS_PREFERENCE,
+    tags::PATIENT_SEX_NEUTERED,
+    tags::PATIENT_SIZE,
+    tags::PATIENT_STATE,
+    tags::PATIENT_TELECOM_INFORMATION,
+    tags::PATIENT_TELEPHONE_NUMBERS,
+    tags::PATIENT_TRANSPORT_ARRANGEMENTS,
+    tags::PATIENT_WEIGHT,
+    tags::PERFORMED_LOCATION,
+    tags::PERFORMED_PROCEDURE_STEP_DESCRIPTION,
+    tags::PERFORMED_PROCEDURE_STEP_END_DATE,
+    tags::PERFORMED_PROCEDURE_STEP_END_TIME,
+    tags::PERFORMED_PROCEDURE_STEP_ID,
+    tags::PERFORMED_PROCEDURE_STEP_START_DATE,
+    tags::PERFORMED_PROCEDURE_STEP_START_TIME,
+    tags::PERFORMED_PROCEDURE_TYPE_DESCRIPTION,
+    tags::PERFORMED_STATION_AE_TITLE,
+    tags::PERFORMED_STATION_GEOGRAPHIC_LOCATION_CODE_SEQUENCE,
+    tags::PERFORMED_STATION_NAME,
+    tags::PERFORMED_STATION_NAME_CODE_SEQUENCE,
+    tags::PERFORMING_PHYSICIAN_IDENTIFICATION_SEQUENCE,
+    tags::PERFORMING_PHYSICIAN_NAME,
+    tags::PERSON_ADDRESS,
+    tags::PERSON_IDENTIFICATION_CODE_SEQUENCE,
+    tags::PERSON_NAME,
+    tags::PERSON_TELECOM_INFORMATION,
+    tags::PERSON_TELEPHONE_NUMBERS,
+    tags::PHYSICIANS_OF_RECORD,
+    tags::PHYSICIANS_OF_RECORD_IDENTIFICATION_SEQUENCE,
+    tags::PHYSICIANS_READING_STUDY_IDENTIFICATION_SEQUENCE,
+    tags::PHYSICIAN_APPROVING_INTERPRETATION,
+    tags::PLATE_ID,
+    tags::PREDECESSOR_DOCUMENTS_SEQUENCE,
+    tags::PREGNANCY_STATUS,
+    tags::PRESCRIPTION_DESCRIPTION,
+    tags::PRE_MEDICATION,
+    tags::PROCEDURE_CODE_SEQUENCE,
+    tags::PROCEDURE_STEP_CANCELLATION_DATE_TIME,
+    tags::PROTOCOL_NAME,
+    tags::RADIOPHARMACEUTICAL_START_DATE_TIME,
+    tags::RADIOPHARMACEUTICAL_START_TIME,
+    tags::RADIOPHARMACEUTICAL_STOP_DATE_TIME,
+    tags::RADIOPHARMACEUTICAL_STOP_TIME,
+    tags::REASON_FOR_OMISSION_DESCRIPTION,
+    tags::REASON_FOR_REQUESTED_PROCEDURE_CODE_SEQUENCE,
+    tags::REASON_FOR_STUDY,
+    tags::REASON_FOR_THE_IMAGING_SERVICE_REQUEST,
+    tags::REASON_FOR_THE_REQUESTED_PROCEDURE,
+    tags::REFERENCED_DIGITAL_SIGNATURE_SEQUENCE,
+    tags::REFERENCED_PATIENT_ALIAS_SEQUENCE,
+    tags::REFERENCED_PATIENT_PHOTO_SEQUENCE,
+    tags::REFERENCED_PATIENT_SEQUENCE,
+    tags::REFERENCED_PERFORMED_PROCEDURE_STEP_SEQUENCE,
+    tags::REFERENCED_SOP_INSTANCE_MAC_SEQUENCE,
+    tags::REFERENCED_STUDY_SEQUENCE,
+    tags::REFERRING_PHYSICIAN_ADDRESS,
+    tags::REFERRING_PHYSICIAN_IDENTIFICATION_SEQUENCE,
+    tags::REFERRING_PHYSICIAN_TELEPHONE_NUMBERS,
+    tags::REGION_OF_RESIDENCE,
+    tags::REQUESTED_CONTRAST_AGENT,
+    tags::REQUESTED_PROCEDURE_COMMENTS,
+    tags::REQUESTED_PROCEDURE_DESCRIPTION,
+    tags::REQUESTED_PROCEDURE_ID,
+    tags::REQUESTED_PROCEDURE_LOCATION,
+    tags::REQUESTING_PHYSICIAN,
+    tags::REQUESTING_SERVICE,
+    tags::REQUEST_ATTRIBUTES_SEQUENCE,
+    tags::RESPONSIBLE_ORGANIZATION,
+    tags::RESPONSIBLE_PERSON,
+    tags::RESULTS_COMMENTS,
+    tags::RESULTS_DISTRIBUTION_LIST_SEQUENCE,
+    tags::RESULTS_ID_ISSUER,
+    tags::REVIEWER_NAME,
+    tags::REVIEW_DATE,
+    tags::REVIEW_TIME,
+    tags::ROI_DESCRIPTION,
+    tags::ROI_GENERATION_DESCRIPTION,
+    tags::ROI_OBSERVATION_DESCRIPTION,
+    tags::ROI_OBSERVATION_LABEL,
+    tags::ROUTE_OF_ADMISSIONS,
+    tags::RT_PLAN_DATE,
+    tags::RT_PLAN_DESCRIPTION,
+    tags::RT_PLAN_NAME,
+    tags::RT_PLAN_TIME,
+    tags::SCHEDULED_HUMAN_PERFORMERS_SEQUENCE,
+    tags::SCHEDULED_PATIENT_INSTITUTION_RESIDENCE,
+    tags::SCHEDULED_PERFORMING_PHYSICIAN_IDENTIFICATION_SEQUENCE,
+    tags::SCHEDULED_PERFORMING_PHYSICIAN_NAME,
+    tags::SCHEDULED_PROCEDURE_STEP_DESCRIPTION,
+    tags::SCHEDULED_PROCEDURE_STEP_END_DATE,
+    tags::SCHEDULED_PROCEDURE_STEP_END_TIME,
+    tags::SCHEDULED_PROCEDURE_STEP_EXPIRATION_DATE_TIME,
+    tags::SCHEDULED_PROCEDURE_STEP_ID,
+    tags::SCHEDULED_PROCEDURE_STEP_LOCATION,
+    tags::SCHEDULED_PROCEDURE_STEP_MODIFICATION_DATE_TIME,
+    tags::SCHEDULED_PROCEDURE_STEP_START_DATE,
+    tags::SCHEDULED_PROCEDURE_STEP_START_TIME,
+    tags::SCHEDULED_STATION_AE_TITLE,
+    tags::SCHEDULED_STATION_GEOGRAPHIC_LOCATION_CODE_SEQUENCE,
+    tags::SCHEDULED_STATION_NAME,
+    tags::SCHEDULED_STATION_NAME_CODE_SEQUENCE,
+    tags::SCHEDULED_STUDY_LOCATION,
+    tags::SCHEDULED_STUDY_LOCATION_AE_TITLE,
+    tags::SCHEDULED_STUDY_START_DATE,
+    tags::SCHEDULED_STUDY_START_TIME,
+    tags::SCHEDULED_STUDY_STOP_DATE,
+    tags::SCHEDULED_STUDY_STOP_TIME,
+    tags::SECONDARY_CAPTURE_DEVICE_ID,
+    tags::SERIES_DATE,
+    tags::SERIES_DESCRIPTION,
+    tags::SERIES_TIME,
+    tags::SERVICE_EPISODE_DESCRIPTION,
+    tags::SERVICE_EPISODE_ID,
+    tags::SETUP_TECHNIQUE_DESCRIPTION,
+    tags::SHIELDING_DEVICE_DESCRIPTION,
+    tags::SLIDE_IDENTIFIER,
+    tags::SMOKING_STATUS,
+    tags::SOURCE_IMAGE_SEQUENCE,
+    tags::SOURCE_MANUFACTURER,
+    tags::SOURCE_SERIAL_NUMBER,
+    tags::SPECIAL_NEEDS,
+    tags::SPECIMEN_ACCESSION_NUMBER,
+    tags::SPECIMEN_DETAILED_DESCRIPTION,
+    tags::SPECIMEN_SHORT_DESCRIPTION,
+    tags::START_ACQUISITION_DATE_TIME,
+    tags::STATION_NAME,
+    tags::STRUCTURE_SET_DATE,
+    tags::STRUCTURE_SET_DESCRIPTION,
+    tags::STRUCTURE_SET_NAME,
+    tags::STRUCTURE_SET_TIME,
+    tags::STUDY_ARRIVAL_DATE,
+    tags::STUDY_ARRIVAL_TIME,
+    tags::STUDY_COMMENTS,
+    tags::STUDY_COMPLETION_DATE,
+    tags::STUDY_COMPLETION_TIME,
+    tags::STUDY_DESCRIPTION,
+    tags::STUDY_ID_ISSUER,
+    tags::STUDY_READ_DATE,
+    tags::STUDY_READ_TIME,
+    tags::STUDY_VERIFIED_DATE,
+    tags::STUDY_VERIFIED_TIME,
+    tags::TEXT_COMMENTS,
+    tags::TEXT_STRING,
+    tags::TEXT_VALUE,
+    tags::TIMEZONE_OFFSET_FROM_UTC,
+    tags::TIME_OF_LAST_CALIBRATION,
+    tags::TIME_OF_SECONDARY_CAPTURE,
+    tags::TOPIC_AUTHOR,
+    tags::TOPIC_KEYWORDS,
+    tags::TOPIC_SUBJECT,
+    tags::TOPIC_TITLE,
+    tags::TREATMENT_DATE,
+    tags::TREATMENT_SITE,
+    tags::TREATMENT_TIME,
+    tags::VERIFYING_OBSERVER_IDENTIFICATION_CODE_SEQUENCE,
+    tags::VERIFYING_OBSERVER_NAME,
+    tags::VERIFYING_OBSERVER_SEQUENCE,
+    tags::VERIFYING_ORGANIZATION,
+    tags::VISIT_COMMENTS,
+];
+
+/// Attributes kept but emptied (action Z, for Type 2 attributes).
+const EMPTY: &[Tag] = &[
+    tags::ACCESSION_NUMBER,
+    tags::CONTENT_CREATOR_NAME,
+    tags::CONTENT_DATE,
+    tags::CONTENT_TIME,
+    tags::FILLER_ORDER_NUMBER_IMAGING_SERVICE_REQUEST,
+    tags::PATIENT_BIRTH_DATE,
+    tags::PATIENT_ID,
+    tags::PATIENT_NAME,
+    tags::PATIENT_SEX,
+    tags::PLACER_ORDER_NUMBER_IMAGING_SERVICE_REQUEST,
+    tags::REFERRING_PHYSICIAN_NAME,
+    tags::STUDY_DATE,
+    tags::STUDY_ID,
+    tags::STUDY_TIME,
+];
+
+/// UID attributes kept as they are: they name classes and encodings,
+/// not instances.
+const KEEP_UID: &[Tag] = &[
+    tags::CODING_SCHEME_UID,
+    tags::CONTEXT_GROUP_EXTENSION_CREATOR_UID,
+    tags::MAPPING_RESOURCE_UID,
+    tags::REFERENCED_SOP_CLASS_UID,
+    tags::REFERENCED_SOP_CLASS_UID_IN_FILE,
+    tags::REFERENCED_TRANSFER_SYNTAX_UID_IN_FILE,
+    tags::RELATED_GENERAL_SOP_CLASS_UID,
+    tags::SOP_CLASS_UID,
+    tags::TRANSFER_SYNTAX_UID,
+];
+
+/// Root of the UIDs defined by the DICOM standard.
+const DICOM_UID_ROOT: &str = "1.2.840.10008.";
+
+/// Whether the profile removes an element: the X actions, private tags,
+/// curves, overlay data and comments, GPS and selector value ranges, and
+/// person names not emptied.
+fn is_removed(tag: Tag, vr: VR) -> bool {
+    let (group, element) = (tag.group(), tag.element());
+    group % 2 == 1
+        || group & 0xFF00 == 0x5000
+        || (group & 0xFF00 == 0x6000 && matches!(element, 0x3000 | 0x4000))
+        || (group == 0x0016 && (0x0070..=0x008E).contains(&element))
+        || (group == 0x0072 && (0x005E..=0x007F).contains(&element))
+        || (vr == VR::PN && !EMPTY.contains(&tag))
+        || REMOVE.contains(&tag)
+}
+
+/// De-identification profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum DeidentificationProfile {
+    /// Basic Application Level Confidentiality Profile.
+    #[default]
+    Basic,
+    /// Basic profile with the Retain UIDs option (for linking back to
+    /// the source archive).
+    RetainUids,
+}
+
+impl DeidentificationProfile {
+    /// Value recorded in De-identification Method (0012,0063).
+    fn method(&self) -> &'static str {
+        match self {
+            DeidentificationProfile::Basic => "medimg: Basic Profile",
+            DeidentificationProfile::RetainUids => "medimg: Basic Profile, Retain UIDs Option",
+        }
+    }
+}
+
+/// What de-identification changed in one dataset, sequence items
+/// included.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeidentificationSummary {
+    /// Attributes removed (including private tags).
+    pub removed: usize,
+    /// Attributes emptied.
+    pub emptied: usize,
+    /// UIDs replaced.
+    pub replaced_uids: usize,
+}
+
+/// Applies a de-identification profile to DICOM files.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::anonymize::{Anonymizer, DeidentificationProfile};
+///
+/// let mut anonymizer = Anonymizer::new(DeidentificationProfile::Basic);
+/// let mut dicom = DicomFile::open("in.dcm")?;
+/// anonymizer.anonymize(&mut dicom)?;
+/// dicom.inner().write_to_file("out.dcm")?;
+/// ```
+#[derive(Debug, Default)]
+pub struct Anonymizer {
+    /// Profile to apply.
+    profile: DeidentificationProfile,
+
+    /// Source UID to replacement UID, shared across files.
+    uid_map: HashMap<String, String>,
+}
+
+impl Anonymizer {
+    /// Create an anonymizer for the given profile.
+    pub fn new(profile: DeidentificationProfile) -> Self {
+        Self {
+            profile,
+            uid_map: HashMap::new(),
+        }
+    }
+
+    /// De-identify a dataset in place and refresh its metadata.
+    pub fn anonymize(&mut self, dicom: &mut DicomFile) -> Result<DeidentificationSummary> {
+        let mut summary = DeidentificationSummary::default();
+        let object = dicom.inner_mut();
+        self.deidentify(object, &mut summary);
+
+        if let Some(uid) = object
+            .element(tags::SOP_INSTANCE_UID)
+            .ok()
+            .and_then(|e| e.to_str().ok())
+            .map(|s| s.trim_end_matches(['\0', ' ']).to_string())
+        {
+            let meta = object.meta_mut();
+            if meta.media_storage_sop_instance_uid.trim_end_matches(['\0', ' ']) != uid {
+                meta.media_storage_sop_instance_uid = uid;
+                meta.update_information_group_length();
+            }
+        }
+
+        object.put(DataElement::new(
+            tags::PATIENT_IDENTITY_REMOVED,
+            VR::CS,
+            PrimitiveValue::from("YES"),
+        ));
+        object.put(DataElement::new(
+            tags::DEIDENTIFICATION_METHOD,
+            VR::LO,
+            PrimitiveValue::from(self.profile.method()),
+        ));
+
+        dicom.refresh_metadata()?;
+        Ok(summary)
+    }
+
+    /// Apply the profile to one dataset or sequence item, then to the
+    /// items of the sequences it keeps.
+    fn deidentify(&mut self, object: &mut InMemDicomObject, summary: &mut DeidentificationSummary) {
+        let before = object.iter().count();
+        object.retain(|element| !is_removed(element.tag(), element.vr()));
+        summary.removed += before - object.iter().count();
+
+        for &tag in EMPTY {
+            let Ok(element) = object.element(tag) else {
+                continue;
+            };
+            let vr = element.vr();
+            object.put(DataElement::new(tag, vr, PrimitiveValue::Empty));
+            summary.emptied += 1;
+        }
+
+        if self.profile != DeidentificationProfile::RetainUids {
+            let uids: Vec<(Tag, String)> = object
+                .iter()
+                .filter(|e| e.vr() == VR::UI && !KEEP_UID.contains(&e.tag()))
+                .filter_map(|e| {
+                    let value = e.to_str().ok()?;
+                    Some((e.tag(), value.trim_end_matches(['\0', ' ']).to_string()))
+                })
+                .collect();
+            for (tag, value) in uids {
+                let replaced = value
+                    .split('\\')
+                    .map(|uid| self.replacement_uid(uid))
+                    .collect::<Vec<_>>()
+                    .join("\\");
+                if replaced != value {
+                    object.put(DataElement::new(tag, VR::UI, PrimitiveValue::from(replaced.as_str())));
+                    summary.replaced_uids += 1;
+                }
+            }
+        }
+
+        let sequences: Vec<Tag> = object.iter().filter(|e| e.vr() == VR::SQ).map(|e| e.tag()).collect();
+        for tag in sequences {
+            object.update_value(tag, |value| {
+                for item in value.items_mut().into_iter().flatten() {
+                    self.deidentify(item, summary);
+                }
+            });
+        }
+    }
+
+    /// Get (or create) the replacement for a source UID. DICOM-defined
+    /// UIDs identify nothing and are kept.
+    fn replacement_uid(&mut self, uid: &str) -> String {
+        if uid.is_empty() || uid.starts_with(DICOM_UID_ROOT) {
+            return uid.to_string();
+        }
+        self.uid_map
+            .entry(uid.to_string())
+            .or_insert_with(utils::generate_uid)
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dicom::testing;
+    use dicom::core::value::DataSetSequence;
+    use tempfile::TempDir;
+
+    fn open_sample(dir: &TempDir, name: &str) -> DicomFile {
+        let path = dir.path().join(name);
+        testing::write_grayscale(&path, 4, 4, "CR", &[0; 16]);
+        DicomFile::open(&path).unwrap()
+    }
+
+    #[test]
+    fn test_basic_profile() {
+        let dir = TempDir::new().unwrap();
+        let mut dicom = open_sample(&dir, "a.dcm");
+        let original_uid = dicom.metadata.sop_instance_uid.clone();
+        dicom.inner_mut().put(DataElement::new(
+            tags::INSTITUTION_NAME,
+            VR::LO,
+            PrimitiveValue::from("General Hospital"),
+        ));
+        dicom.inner_mut().put(DataElement::new(
+            Tag(0x0009, 0x0010),
+            VR::LO,
+            PrimitiveValue::from("VENDOR"),
+        ));
+
+        let summary = Anonymizer::new(DeidentificationProfile::Basic)
+            .anonymize(&mut dicom)
+            .unwrap();
+        assert_eq!(summary.removed, 2);
+        assert_eq!(summary.emptied, 1);
+        assert_eq!(summary.replaced_uids, 1);
+
+        let object = dicom.inner();
+        assert!(object.element(tags::INSTITUTION_NAME).is_err());
+        assert!(object.element(Tag(0x0009, 0x0010)).is_err());
+        assert_eq!(object.element(tags::PATIENT_ID).unwrap().to_str().unwrap(), "");
+        assert_eq!(dicom.metadata.patient_id.as_deref(), Some(""));
+        assert_ne!(dicom.metadata.sop_instance_uid, original_uid);
+        assert_eq!(
+            Some(object.meta().media_storage_sop_instance_uid.as_str()),
+            dicom.metadata.sop_instance_uid.as_deref()
+        );
+    }
+
+    #[test]
+    fn test_sequence_items() {
+        let dir = TempDir::new().unwrap();
+        let mut dicom = open_sample(&dir, "a.dcm");
+        let source_uid = "1.2.826.0.1.3680043.2.1125.1";
+        let class_uid = "1.2.840.10008.5.1.4.1.1.7";
+        let item = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::REFERENCED_SOP_CLASS_UID, VR::UI, PrimitiveValue::from(class_uid)),
+            DataElement::new(tags::REFERENCED_SOP_INSTANCE_UID, VR::UI, PrimitiveValue::from(source_uid)),
+            DataElement::new(tags::ISSUER_OF_PATIENT_ID, VR::LO, PrimitiveValue::from("GENERAL HOSPITAL")),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("TEST001")),
+            DataElement::new(Tag(0x0040, 0xA123), VR::PN, PrimitiveValue::from("Doe^Jane")),
+            DataElement::new(Tag(0x0011, 0x1001), VR::LO, PrimitiveValue::from("VENDOR")),
+        ]);
+        dicom.inner_mut().put(DataElement::new(
+            tags::SOURCE_IMAGE_SEQUENCE,
+            VR::SQ,
+            DataSetSequence::from(vec![item.clone()]),
+        ));
+        dicom.inner_mut().put(DataElement::new(
+            tags::REFERENCED_IMAGE_SEQUENCE,
+            VR::SQ,
+            DataSetSequence::from(vec![item]),
+        ));
+
+        let summary = Anonymizer::new(DeidentificationProfile::Basic)
+            .anonymize(&mut dicom)
+            .unwrap();
+        // Top level: the whole Source Image Sequence. Item: issuer, name, private tag.
+        assert_eq!(summary.removed, 4);
+        assert_eq!(summary.emptied, 2);
+        assert_eq!(summary.replaced_uids, 2);
+
+        let object = dicom.inner();
+        assert!(object.element(tags::SOURCE_IMAGE_SEQUENCE).is_err());
+        let items = object.element(tags::REFERENCED_IMAGE_SEQUENCE).unwrap().items().unwrap();
+        let item = &items[0];
+        assert!(item.element(tags::ISSUER_OF_PATIENT_ID).is_err());
+        assert!(item.element(Tag(0x0040, 0xA123)).is_err());
+        assert!(item.element(Tag(0x0011, 0x1001)).is_err());
+        assert_eq!(item.element(tags::PATIENT_ID).unwrap().to_str().unwrap(), "");
+        assert_eq!(item.element(tags::REFERENCED_SOP_CLASS_UID).unwrap().to_str().unwrap(), class_uid);
+        // The reference follows the instance it points to
+        let reference = item.element(tags::REFERENCED_SOP_INSTANCE_UID).unwrap().to_str().unwrap();
+        assert_ne!(reference, source_uid);
+        assert_eq!(Some(reference.as_ref()), dicom.metadata.sop_instance_uid.as_deref());
+    }
+
+    #[test]
+    fn test_content_sequence_removed() {
+        let dir = TempDir::new().unwrap();
+        let mut dicom = open_sample(&dir, "a.dcm");
+        let item = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::VALUE_TYPE, VR::CS, PrimitiveValue::from("TEXT")),
+            DataElement::new(tags::TEXT_VALUE, VR::UT, PrimitiveValue::from("Patient Doe seen on 2024-01-02")),
+        ]);
+        dicom.inner_mut().put(DataElement::new(
+            tags::CONTENT_SEQUENCE,
+            VR::SQ,
+            DataSetSequence::from(vec![item.clone()]),
+        ));
+        dicom.inner_mut().put(DataElement::new(
+            tags::TEXT_VALUE,
+            VR::UT,
+            PrimitiveValue::from("Free text"),
+        ));
+
+        Anonymizer::new(DeidentificationProfile::Basic)
+            .anonymize(&mut dicom)
+            .unwrap();
+
+        let object = dicom.inner();
+        assert!(object.element(tags::CONTENT_SEQUENCE).is_err());
+        assert!(object.element(tags::TEXT_VALUE).is_err());
+    }
+
+    #[test]
+    fn test_uid_mapping_is_consistent() {
+        let dir = TempDir::new().unwrap();
+        let mut a = open_sample(&dir, "a.dcm");
+        let mut b = open_sample(&dir, "b.dcm");
+
+        let mut anonymizer = Anonymizer::new(DeidentificationProfile::Basic);
+        anonymizer.anonymize(&mut a).unwrap();
+        anonymizer.anonymize(&mut b).unwrap();
+        // Same source instance, same replacement
+        assert_eq!(a.metadata.sop_instance_uid, b.metadata.sop_instance_uid);
+
+        let mut c = open_sample(&dir, "c.dcm");
+        let original_uid = c.metadata.sop_instance_uid.clone();
+        Anonymizer::new(DeidentificationProfile::RetainUids)
+            .anonymize(&mut c)
+            .unwrap();
+        assert_eq!(c.metadata.sop_instance_uid, original_uid);
+    }
+}
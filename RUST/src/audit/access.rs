@@ -0,0 +1,333 @@
+//! Access trail: which files were read and written, by whom, and which
+//! patients and studies they belong to.
+//!
+//! Install an [`AccessLog`] once at startup; the pipeline and the CLI then
+//! report every DICOM input they read and every output they write to its
+//! sinks. Patient IDs and UIDs are recorded as salted hashes
+//! ([`AccessLog::salt`]), so the trail can answer "who touched this
+//! patient?" without itself holding identifiers.
+//!
+//! ```rust,ignore
+//! AccessLog::new("svc-archive")
+//!     .salt(std::env::var("MEDIMG_AUDIT_SALT")?)
+//!     .sink(JsonLinesSink::open("/var/log/medimg/access.jsonl")?)
+//!     .install();
+//! ```
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::dicom::DicomMetadata;
+use crate::error::{MedImgError, Result};
+use crate::pipeline::AUDIT_LOG_TARGET;
+
+/// The installed access log, if any.
+static INSTALLED: RwLock<Option<Arc<AccessLog>>> = RwLock::new(None);
+
+/// What was done to a resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessAction {
+    /// An input was read.
+    Read,
+    /// An output was written.
+    Write,
+}
+
+/// Hashed identifiers of the data accessed (see [`AccessLog::salt`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HashedIdentifiers {
+    /// Patient ID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub patient_id: Option<String>,
+    /// Study Instance UID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub study_uid: Option<String>,
+    /// SOP Instance UID.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sop_instance_uid: Option<String>,
+}
+
+/// One access to a resource.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessEvent {
+    /// When the access happened (milliseconds since the Unix epoch).
+    pub timestamp_ms: u64,
+    /// Operator or service account that performed the access.
+    pub actor: String,
+    /// What was done.
+    pub action: AccessAction,
+    /// Path or URI of the resource.
+    pub resource: String,
+    /// Identifiers of the data in the resource.
+    pub identifiers: HashedIdentifiers,
+}
+
+/// Destination for access events.
+pub trait AccessSink: Send + Sync {
+    /// Record one event.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event could not be stored; the access it
+    /// describes is not undone.
+    fn record(&self, event: &AccessEvent) -> Result<()>;
+}
+
+/// Writes events to the `medimg::audit` log target.
+#[derive(Debug, Default)]
+pub struct LogSink;
+
+impl AccessSink for LogSink {
+    fn record(&self, event: &AccessEvent) -> Result<()> {
+        let json = serde_json::to_string(event)
+            .map_err(|e| MedImgError::Internal(format!("Failed to serialize access event: {}", e)))?;
+        log::info!(target: AUDIT_LOG_TARGET, "{}", json);
+        Ok(())
+    }
+}
+
+/// Appends events to a file as JSON lines.
+#[derive(Debug)]
+pub struct JsonLinesSink {
+    file: Mutex<File>,
+}
+
+impl JsonLinesSink {
+    /// Open `path` for appending, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Io` if the file cannot be opened.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| MedImgError::from(e).with_file(path))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl AccessSink for JsonLinesSink {
+    fn record(&self, event: &AccessEvent) -> Result<()> {
+        let mut line = serde_json::to_vec(event)
+            .map_err(|e| MedImgError::Internal(format!("Failed to serialize access event: {}", e)))?;
+        line.push(b'\n');
+        // One write per line so concurrent processes appending to the same
+        // file do not interleave events
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        file.write_all(&line)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Keeps events in memory, for embedding applications and tests.
+#[derive(Debug, Default)]
+pub struct MemorySink {
+    events: Mutex<Vec<AccessEvent>>,
+}
+
+impl MemorySink {
+    /// An empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events recorded so far.
+    pub fn events(&self) -> Vec<AccessEvent> {
+        self.events.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+impl AccessSink for MemorySink {
+    fn record(&self, event: &AccessEvent) -> Result<()> {
+        self.events
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(event.clone());
+        Ok(())
+    }
+}
+
+impl<S: AccessSink + ?Sized> AccessSink for Arc<S> {
+    fn record(&self, event: &AccessEvent) -> Result<()> {
+        (**self).record(event)
+    }
+}
+
+/// Records accesses by one actor to a set of sinks.
+pub struct AccessLog {
+    actor: String,
+    salt: Vec<u8>,
+    sinks: Vec<Box<dyn AccessSink>>,
+}
+
+impl AccessLog {
+    /// A log for `actor` (operator or service account) with no sinks.
+    pub fn new(actor: impl Into<String>) -> Self {
+        Self {
+            actor: actor.into(),
+            salt: Vec::new(),
+            sinks: Vec::new(),
+        }
+    }
+
+    /// Salt identifiers with a site secret before hashing.
+    ///
+    /// Patient IDs are short and guessable; without a salt anyone can hash
+    /// candidate IDs and match them against the trail. Keep the salt
+    /// stable: a trail is only searchable with the salt it was written
+    /// with.
+    pub fn salt(mut self, salt: impl AsRef<[u8]>) -> Self {
+        self.salt = salt.as_ref().to_vec();
+        self
+    }
+
+    /// Also record events to `sink`.
+    pub fn sink(mut self, sink: impl AccessSink + 'static) -> Self {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    /// The actor events are attributed to.
+    pub fn actor(&self) -> &str {
+        &self.actor
+    }
+
+    /// Make this the log the pipeline and CLI report to.
+    pub fn install(self) {
+        *INSTALLED.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(self));
+    }
+
+    /// Remove the installed log.
+    pub fn uninstall() {
+        *INSTALLED.write().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+
+    /// The installed log, if any.
+    pub fn installed() -> Option<Arc<AccessLog>> {
+        INSTALLED.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Hash of `value` under this log's salt: 16 hex digits of SHA-256.
+    pub fn hash(&self, value: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(&self.salt);
+        hasher.update([0]);
+        hasher.update(value.trim().as_bytes());
+        hasher.finalize()[..8].iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// Hashed identifiers of a DICOM instance.
+    pub fn identifiers(&self, metadata: &DicomMetadata) -> HashedIdentifiers {
+        let hash = |value: &Option<String>| value.as_deref().map(|value| self.hash(value));
+        HashedIdentifiers {
+            patient_id: hash(&metadata.patient_id),
+            study_uid: hash(&metadata.study_uid),
+            sop_instance_uid: hash(&metadata.sop_instance_uid),
+        }
+    }
+
+    /// Record that `resource` holding the instance described by
+    /// `metadata` was read or written.
+    ///
+    /// Sink failures are logged, not returned: an unavailable audit
+    /// destination does not fail the work it describes.
+    pub fn record(&self, action: AccessAction, resource: impl AsRef<Path>, metadata: &DicomMetadata) {
+        let event = AccessEvent {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            actor: self.actor.clone(),
+            action,
+            resource: resource.as_ref().display().to_string(),
+            identifiers: self.identifiers(metadata),
+        };
+        for sink in &self.sinks {
+            if let Err(e) = sink.record(&event) {
+                log::error!(target: AUDIT_LOG_TARGET, "Failed to record access to {}: {}", event.resource, e);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for AccessLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccessLog")
+            .field("actor", &self.actor)
+            .field("sinks", &self.sinks.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Record a read with the installed log, if any.
+pub fn read(resource: impl AsRef<Path>, metadata: &DicomMetadata) {
+    if let Some(log) = AccessLog::installed() {
+        log.record(AccessAction::Read, resource, metadata);
+    }
+}
+
+/// Record a write with the installed log, if any.
+pub fn written(resource: impl AsRef<Path>, metadata: &DicomMetadata) {
+    if let Some(log) = AccessLog::installed() {
+        log.record(AccessAction::Write, resource, metadata);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn metadata() -> DicomMetadata {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("in.dcm");
+        crate::dicom::testing::write_grayscale(&path, 2, 2, "CT", &[0; 4]);
+        crate::dicom::DicomFile::open(&path).unwrap().metadata
+    }
+
+    #[test]
+    fn test_record_hashes_identifiers() {
+        let dir = TempDir::new().unwrap();
+        let trail = dir.path().join("access.jsonl");
+        let memory = Arc::new(MemorySink::new());
+        let log = AccessLog::new("svc-archive")
+            .salt("site secret")
+            .sink(memory.clone())
+            .sink(JsonLinesSink::open(&trail).unwrap());
+        let metadata = metadata();
+
+        log.record(AccessAction::Read, "/in/a.dcm", &metadata);
+        log.record(AccessAction::Write, "/out/a.dcm", &metadata);
+
+        let events = memory.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].action, events[0].resource.as_str()), (AccessAction::Read, "/in/a.dcm"));
+        assert_eq!(events[1].actor, "svc-archive");
+        let patient = events[0].identifiers.patient_id.as_deref().unwrap();
+        assert_eq!(patient, log.hash("TEST001"));
+        assert_eq!(patient.len(), 16);
+        assert_ne!(patient, AccessLog::new("svc-archive").hash("TEST001"));
+        // No Study Instance UID in the file
+        assert_eq!(events[0].identifiers.study_uid, None);
+
+        let text = std::fs::read_to_string(&trail).unwrap();
+        assert!(!text.contains("TEST001"));
+        let lines: Vec<AccessEvent> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines, events);
+    }
+}
@@ -0,0 +1,12 @@
+//! Audit trails for compliance reviews.
+//!
+//! - [`access`]: which inputs were read and which outputs written, by which
+//!   operator or service account, with hashed patient and study
+//!   identifiers.
+//!
+//! Safety overrides are logged separately under
+//! [`AUDIT_LOG_TARGET`](crate::pipeline::AUDIT_LOG_TARGET).
+
+pub mod access;
+
+pub use access::{AccessEvent, AccessLog, AccessSink};
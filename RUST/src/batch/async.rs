@@ -0,0 +1,76 @@
+//! Async batch processing (`tokio` feature).
+//!
+//! The batch runs on tokio's blocking thread pool, where it keeps its own
+//! rayon parallelism; progress can be consumed as a stream with
+//! [`AsyncProgress`](crate::progress::r#async::AsyncProgress).
+
+use std::path::PathBuf;
+
+use super::BatchProcessor;
+use crate::error::Result;
+use crate::pipeline::BatchStats;
+use crate::progress::ProgressHandler;
+
+impl<P: ProgressHandler + 'static> BatchProcessor<P> {
+    /// Process a directory of DICOM files without blocking the async
+    /// runtime.
+    ///
+    /// Takes the processor by value so the batch can outlive the caller's
+    /// borrow; keep a [`cancellation_token`](Self::cancellation_token) to
+    /// cancel it.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let (progress, mut events) = AsyncProgress::new();
+    /// let batch = BatchProcessor::new(config, progress).process_directory_async(dir);
+    /// let (stats, _) = tokio::join!(batch, async {
+    ///     while let Some(event) = events.next().await {
+    ///         println!("{}", event.message);
+    ///     }
+    /// });
+    /// ```
+    pub async fn process_directory_async(self, input_dir: PathBuf) -> Result<BatchStats> {
+        tokio::task::spawn_blocking(move || self.process_directory(&input_dir)).await?
+    }
+
+    /// Process a list of files without blocking the async runtime.
+    ///
+    /// See [`process_directory_async`](Self::process_directory_async).
+    pub async fn process_files_async(self, files: Vec<PathBuf>) -> Result<BatchStats> {
+        tokio::task::spawn_blocking(move || self.process_files(&files)).await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use crate::config::{CompressionCodec, CompressionConfig};
+    use crate::dicom::testing;
+    use crate::BatchProcessor;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_process_directory_async() {
+        let dir = TempDir::new().unwrap();
+        for name in ["a.dcm", "b.dcm"] {
+            testing::write_grayscale(&dir.path().join(name), 8, 8, "CR", &testing::gradient(8, 8));
+        }
+
+        let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+        let processor = BatchProcessor::without_progress(config).max_parallel(2);
+        let stats = block_on(processor.process_directory_async(dir.path().to_path_buf())).unwrap();
+        assert_eq!(stats.successful, 2);
+
+        let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+        let processor = BatchProcessor::without_progress(config);
+        assert!(block_on(processor.process_files_async(Vec::new())).is_err());
+    }
+}
@@ -3,6 +3,7 @@
 use std::path::{Path, PathBuf};
 
 use crate::error::{MedImgError, Result};
+use crate::storage::{ObjectInfo, Storage};
 
 /// File discovery for finding DICOM files.
 pub struct FileDiscovery {
@@ -76,7 +77,7 @@ impl FileDiscovery {
         }
 
         if !dir.is_dir() {
-            return Err(MedImgError::Validation(format!(
+            return Err(MedImgError::Config(format!(
                 "Not a directory: {}",
                 dir.display()
             )));
@@ -91,6 +92,25 @@ impl FileDiscovery {
         Ok(files)
     }
 
+    /// Discover objects under the `prefix` directory of `storage`.
+    ///
+    /// Depth counts directories below `prefix`, as for
+    /// [`discover`](Self::discover); symbolic links are never followed.
+    pub fn discover_objects(&self, storage: &dyn Storage, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        let prefix = prefix.trim_matches('/');
+        let objects = storage.list(prefix)?;
+        Ok(objects
+            .into_iter()
+            .filter(|object| {
+                let relative = object.key[prefix.len()..].trim_start_matches('/');
+                let depth = relative.matches('/').count();
+                let max_depth = if self.recursive { self.max_depth } else { Some(0) };
+                max_depth.is_none_or(|max| depth <= max)
+                    && self.matches_pattern(Path::new(relative))
+            })
+            .collect())
+    }
+
     /// Recursive file discovery.
     fn discover_recursive(
         &self,
@@ -132,10 +152,8 @@ impl FileDiscovery {
                 if self.recursive {
                     self.discover_recursive(&path, depth + 1, files)?;
                 }
-            } else if metadata.is_file() {
-                if self.matches_pattern(&path) {
-                    files.push(path);
-                }
+            } else if metadata.is_file() && self.matches_pattern(&path) {
+                files.push(path);
             }
         }
 
@@ -284,6 +302,23 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_discover_objects() {
+        let dir = create_test_directory();
+        let storage = crate::storage::LocalStorage::new(dir.path());
+
+        let keys = |discovery: FileDiscovery, prefix| -> Vec<String> {
+            let objects = discovery.discover_objects(&storage, prefix).unwrap();
+            objects.into_iter().map(|object| object.key).collect()
+        };
+        assert_eq!(keys(FileDiscovery::new(), ""), ["test1.dcm", "test2.DCM"]);
+        assert_eq!(
+            keys(FileDiscovery::new().recursive(true), ""),
+            ["subdir/nested.dcm", "test1.dcm", "test2.DCM"]
+        );
+        assert_eq!(keys(FileDiscovery::new(), "subdir"), ["subdir/nested.dcm"]);
+    }
+
     #[test]
     fn test_glob_match() {
         let discovery = FileDiscovery::new();
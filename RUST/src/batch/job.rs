@@ -2,11 +2,13 @@
 
 use std::path::PathBuf;
 
+use serde::{Deserialize, Serialize};
+
 use crate::error::MedImgError;
-use crate::pipeline::CompressionResult;
+use crate::pipeline::{CompressionResult, PhaseTimings};
 
 /// Status of a batch job.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum JobStatus {
     /// Job is waiting to be processed.
     Pending,
@@ -116,6 +118,9 @@ pub struct JobResult {
 
     /// Time taken in milliseconds.
     pub duration_ms: u64,
+
+    /// Per-phase durations (zero for phases that did not complete).
+    pub timings: PhaseTimings,
 }
 
 impl JobResult {
@@ -127,11 +132,11 @@ impl JobResult {
     /// Get the status based on the result.
     pub fn status(&self) -> JobStatus {
         if self.compression_result.is_some() {
-            JobStatus::Completed
-        } else if self.error.is_some() {
-            JobStatus::Failed
-        } else {
-            JobStatus::Cancelled
+            return JobStatus::Completed;
+        }
+        match &self.error {
+            Some(error) if !matches!(error.root(), MedImgError::Cancelled(_)) => JobStatus::Failed,
+            _ => JobStatus::Cancelled,
         }
     }
 
@@ -179,6 +184,7 @@ impl std::fmt::Display for JobResult {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::Modality;
 
     #[test]
     fn test_job_status_terminal() {
@@ -222,25 +228,40 @@ mod tests {
         let compression_result = CompressionResult {
             source_path: PathBuf::from("/test/file.dcm"),
             output_path: None,
+            modality: Modality::CR,
             original_size: 1000,
             compressed_size: 500,
+            output_size: None,
             compression_ratio: 2.0,
             compression_time_ms: 100,
+            timings: PhaseTimings {
+                read_ms: 20,
+                encode_ms: 70,
+                verify_ms: 10,
+                write_ms: 0,
+            },
             is_lossless: true,
             codec_name: "JPEG 2000".into(),
             warnings: vec![],
+            quality: None,
+            pixel_sha256: String::new(),
+            sop_instance_uid: None,
+            verified: false,
         };
 
+        let timings = compression_result.timings;
         let result = JobResult {
             job,
             compression_result: Some(compression_result),
             error: None,
             duration_ms: 100,
+            timings,
         };
 
         assert!(result.is_success());
         assert_eq!(result.status(), JobStatus::Completed);
         assert_eq!(result.compression_ratio(), Some(2.0));
+        assert_eq!(result.timings.total_ms(), 100);
     }
 
     #[test]
@@ -251,9 +272,16 @@ mod tests {
             compression_result: None,
             error: Some(MedImgError::Internal("Test error".into())),
             duration_ms: 50,
+            timings: PhaseTimings::default(),
         };
 
         assert!(!result.is_success());
         assert_eq!(result.status(), JobStatus::Failed);
+
+        let cancelled = JobResult {
+            error: Some(MedImgError::Cancelled("Batch cancelled".into())),
+            ..result
+        };
+        assert_eq!(cancelled.status(), JobStatus::Cancelled);
     }
 }
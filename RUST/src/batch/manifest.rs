@@ -0,0 +1,169 @@
+//! Batch manifests: one JSON record per processed file.
+//!
+//! A manifest is appended to after every batch, so hot-folder runs and
+//! repeated batches into the same file build up a complete history that
+//! [`ManifestSummary`](super::ManifestSummary) can aggregate later.
+
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::config::Modality;
+use crate::error::{ErrorRecord, MedImgError, Result};
+
+use super::{JobResult, JobStatus};
+
+/// Manifest entry for one file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestRecord {
+    /// Source file path.
+    pub source_path: PathBuf,
+    /// Output file path (if written).
+    #[serde(default)]
+    pub output_path: Option<PathBuf>,
+    /// Final job status.
+    pub status: JobStatus,
+    /// Source modality (unknown if the file could not be read).
+    #[serde(default)]
+    pub modality: Option<Modality>,
+    /// Original pixel data size in bytes.
+    #[serde(default)]
+    pub original_size: usize,
+    /// Compressed size in bytes.
+    #[serde(default)]
+    pub compressed_size: usize,
+    /// Processing time in milliseconds.
+    #[serde(default)]
+    pub duration_ms: u64,
+    /// SHA-256 of the source pixel data (if the file was read).
+    #[serde(default)]
+    pub pixel_sha256: Option<String>,
+    /// Error for failed files.
+    #[serde(default, deserialize_with = "deserialize_error")]
+    pub error: Option<ErrorRecord>,
+}
+
+/// Read an error record, or the plain message older manifests stored.
+fn deserialize_error<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<ErrorRecord>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Field {
+        Record(ErrorRecord),
+        Message(String),
+    }
+
+    Ok(Option::<Field>::deserialize(deserializer)?.map(|field| match field {
+        Field::Record(record) => record,
+        Field::Message(message) => ErrorRecord::from_message(message),
+    }))
+}
+
+impl From<&JobResult> for ManifestRecord {
+    fn from(result: &JobResult) -> Self {
+        let compression = result.compression_result.as_ref();
+        Self {
+            source_path: result.job.source_path.clone(),
+            output_path: compression.and_then(|r| r.output_path.clone()),
+            status: result.status(),
+            modality: compression.map(|r| r.modality.clone()),
+            original_size: result.original_size().unwrap_or(0),
+            compressed_size: result.compressed_size().unwrap_or(0),
+            duration_ms: result.duration_ms,
+            pixel_sha256: compression.map(|r| r.pixel_sha256.clone()),
+            error: result
+                .error
+                .as_ref()
+                .map(|e| ErrorRecord::new(e, Some(&result.job.source_path))),
+        }
+    }
+}
+
+/// Append records for a batch's results to a JSONL manifest.
+pub(crate) fn append_manifest(path: &Path, results: &[JobResult]) -> Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    let mut buffer = Vec::new();
+    for result in results {
+        serde_json::to_writer(&mut buffer, &ManifestRecord::from(result))
+            .map_err(|e| MedImgError::Internal(format!("Failed to serialize manifest: {}", e)))?;
+        buffer.push(b'\n');
+    }
+
+    // One write per batch keeps concurrent appenders from interleaving lines
+    file.write_all(&buffer)?;
+    Ok(())
+}
+
+/// Read all records from a JSONL manifest.
+///
+/// Blank lines are ignored.
+///
+/// # Errors
+///
+/// Returns `InvalidFormat` with the line number for malformed records.
+pub fn read_manifest(path: &Path) -> Result<Vec<ManifestRecord>> {
+    let reader = BufReader::new(std::fs::File::open(path)?);
+    let mut records = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line).map_err(|e| {
+            MedImgError::InvalidFormat(format!("{}:{}: {}", path.display(), index + 1, e))
+        })?;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::BatchJob;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("run.jsonl");
+
+        let failed = JobResult {
+            job: BatchJob::new(1, PathBuf::from("/in/a.dcm")),
+            compression_result: None,
+            error: Some(MedImgError::Dicom("bad header".into())),
+            duration_ms: 3,
+            timings: Default::default(),
+        };
+        append_manifest(&path, std::slice::from_ref(&failed)).unwrap();
+        append_manifest(&path, &[failed]).unwrap();
+
+        let records = read_manifest(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].status, JobStatus::Failed);
+        let error = records[0].error.as_ref().unwrap();
+        assert_eq!(error.message, "DICOM error: bad header");
+        assert_eq!(error.code, crate::error::ErrorCode::Dicom);
+        assert_eq!(error.file.as_deref(), Some(Path::new("/in/a.dcm")));
+        assert_eq!(records[0].modality, None);
+
+        // Older manifests stored only the message
+        std::fs::write(
+            &path,
+            "{\"source_path\": \"a.dcm\", \"status\": \"Failed\", \"error\": \"Codec error: x\"}\n",
+        )
+        .unwrap();
+        let legacy = read_manifest(&path).unwrap();
+        assert_eq!(legacy[0].error.as_ref().unwrap().code, crate::error::ErrorCode::Codec);
+        assert_eq!(legacy[0].pixel_sha256, None);
+
+        std::fs::write(&path, "{\"source_path\": \"a.dcm\"}\n").unwrap();
+        assert!(matches!(read_manifest(&path), Err(MedImgError::InvalidFormat(_))));
+    }
+}
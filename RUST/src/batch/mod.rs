@@ -23,25 +23,70 @@
 //! println!("Processed {} files, {} successful", stats.total_files, stats.successful);
 //! ```
 
+#[cfg(feature = "tokio")]
+mod r#async;
 mod job;
+mod manifest;
+mod report;
 mod scheduler;
 mod file_discovery;
+mod throughput;
+mod watch;
 
 pub use job::{BatchJob, JobResult, JobStatus};
+pub use manifest::{read_manifest, ManifestRecord};
+pub use report::{GroupSummary, ManifestSummary};
 pub use scheduler::BatchScheduler;
-pub use file_discovery::FileDiscovery;
+pub use file_discovery::{discover_files, FileDiscovery};
+pub use watch::{FolderWatcher, DEFAULT_POLL_INTERVAL, DEFAULT_REPORT_INTERVAL};
+
+use throughput::ThroughputTracker;
 
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use rayon::prelude::*;
 
-use crate::config::CompressionConfig;
+#[cfg(feature = "catalog")]
+use crate::catalog::Catalog;
+use crate::config::{CompressionCodec, CompressionConfig};
 use crate::error::{MedImgError, Result};
-use crate::pipeline::{BatchStats, CompressionPipeline, CompressionResult};
-use crate::progress::{NullProgress, ProgressEvent, ProgressHandler, ProgressPhase};
+use crate::metrics::{FileQuality, QualityStats};
+#[cfg(feature = "prometheus")]
+use crate::monitoring::OperationalMetrics;
+use crate::pipeline::{BatchStats, CompressionPipeline, CompressionResult, PhaseTimings};
+use crate::progress::{
+    CancellationToken, NullProgress, ProgressEvent, ProgressHandler, ProgressPhase,
+};
+use crate::storage::{LocalStorage, ObjectInfo, Storage};
+
+/// Default number of retries for files that fail with a transient error.
+pub const DEFAULT_RETRIES: u32 = 2;
+
+/// Delay before the first retry; doubled for each further attempt.
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Shared state for a single batch run.
+struct BatchRun<'a> {
+    /// Total files in the run.
+    total_files: usize,
+
+    /// Storage the files are read from.
+    input: &'a dyn Storage,
+
+    /// Storage outputs are written to, if any.
+    output: Option<&'a dyn Storage>,
+
+    /// Base key prefix used to preserve structure in output keys.
+    base: Option<&'a str>,
+
+    /// Per-file quality measurements (when quality gating is enabled).
+    quality_samples: Mutex<Vec<FileQuality>>,
+
+    /// Bytes/files processed across workers.
+    throughput: ThroughputTracker,
+}
 
 /// Batch processor for compressing multiple DICOM files.
 pub struct BatchProcessor<P: ProgressHandler> {
@@ -63,14 +108,37 @@ pub struct BatchProcessor<P: ProgressHandler> {
     /// Output directory.
     output_dir: Option<PathBuf>,
 
+    /// Output storage (overrides `output_dir`).
+    output_storage: Option<Arc<dyn Storage>>,
+
+    /// Output file name template.
+    output_template: Option<String>,
+
     /// Whether to preserve directory structure in output.
     preserve_structure: bool,
 
     /// Whether to skip already compressed files.
     skip_compressed: bool,
 
-    /// Cancellation flag.
-    cancelled: Arc<AtomicBool>,
+    /// Minimum SSIM per file when quality gating is enabled.
+    quality_gate: Option<f64>,
+
+    /// JSONL manifest appended to after each batch.
+    manifest: Option<PathBuf>,
+
+    /// Catalog recorded to after each batch.
+    #[cfg(feature = "catalog")]
+    catalog: Option<Arc<Catalog>>,
+
+    /// Retries for files that fail with a transient error.
+    retries: u32,
+
+    /// Operational metrics updated as files finish.
+    #[cfg(feature = "prometheus")]
+    metrics: Option<OperationalMetrics>,
+
+    /// Cancellation token.
+    cancelled: CancellationToken,
 }
 
 impl<P: ProgressHandler> BatchProcessor<P> {
@@ -83,9 +151,18 @@ impl<P: ProgressHandler> BatchProcessor<P> {
             recursive: false,
             patterns: vec!["*.dcm".to_string(), "*.DCM".to_string()],
             output_dir: None,
+            output_storage: None,
+            output_template: None,
             preserve_structure: true,
             skip_compressed: true,
-            cancelled: Arc::new(AtomicBool::new(false)),
+            quality_gate: None,
+            manifest: None,
+            #[cfg(feature = "catalog")]
+            catalog: None,
+            retries: DEFAULT_RETRIES,
+            #[cfg(feature = "prometheus")]
+            metrics: None,
+            cancelled: CancellationToken::new(),
         }
     }
 
@@ -119,6 +196,24 @@ impl<P: ProgressHandler> BatchProcessor<P> {
         self
     }
 
+    /// Write outputs to `storage` instead of a local output directory.
+    ///
+    /// Output keys follow the same layout as paths under `output_dir`.
+    pub fn output_storage(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.output_storage = Some(storage);
+        self
+    }
+
+    /// Set an output file name template.
+    ///
+    /// Supports `{stem}`, `{ext}`, and `{codec}` placeholders, e.g.
+    /// `"{stem}_{codec}.dcm"`. Only the file name is affected; the directory
+    /// layout still follows `preserve_structure`.
+    pub fn output_template(mut self, template: impl Into<String>) -> Self {
+        self.output_template = Some(template.into());
+        self
+    }
+
     /// Set whether to preserve directory structure.
     pub fn preserve_structure(mut self, preserve: bool) -> Self {
         self.preserve_structure = preserve;
@@ -131,14 +226,64 @@ impl<P: ProgressHandler> BatchProcessor<P> {
         self
     }
 
+    /// Enable quality gating with a minimum SSIM per file.
+    ///
+    /// Each file is decoded after compression and compared against the
+    /// original. Files below `min_ssim` are counted as failed, and the
+    /// per-file metrics are aggregated into `BatchStats::quality`.
+    pub fn quality_gate(mut self, min_ssim: f64) -> Self {
+        self.quality_gate = Some(min_ssim);
+        self
+    }
+
+    /// Append a JSONL record per file to `path` after each batch.
+    pub fn manifest(mut self, path: PathBuf) -> Self {
+        self.manifest = Some(path);
+        self
+    }
+
+    /// Record every file in `catalog` after each batch.
+    #[cfg(feature = "catalog")]
+    pub fn catalog(mut self, catalog: Arc<Catalog>) -> Self {
+        self.catalog = Some(catalog);
+        self
+    }
+
+    /// Set how many times a file is retried after a transient failure
+    /// (see [`MedImgError::is_retryable`]); 0 disables retries.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Record files processed, failures, and queue depth in `metrics`.
+    #[cfg(feature = "prometheus")]
+    pub fn metrics(mut self, metrics: OperationalMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Use a shared cancellation token.
+    ///
+    /// Cancelling any clone of `token` stops the batch after in-flight files.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancelled = token;
+        self
+    }
+
+    /// Get a handle to this processor's cancellation token.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+
     /// Request cancellation of batch processing.
     pub fn cancel(&self) {
-        self.cancelled.store(true, Ordering::SeqCst);
+        self.cancelled.cancel();
     }
 
     /// Check if cancellation was requested.
     fn is_cancelled(&self) -> bool {
-        self.cancelled.load(Ordering::SeqCst) || self.progress.is_cancelled()
+        self.cancelled.is_cancelled() || self.progress.is_cancelled()
     }
 
     /// Process a directory of DICOM files.
@@ -155,7 +300,7 @@ impl<P: ProgressHandler> BatchProcessor<P> {
         let files = discovery.discover(input_dir)?;
 
         if files.is_empty() {
-            return Err(MedImgError::Validation(format!(
+            return Err(MedImgError::Config(format!(
                 "No matching files found in {}",
                 input_dir.display()
             )));
@@ -167,23 +312,107 @@ impl<P: ProgressHandler> BatchProcessor<P> {
     /// Process a list of files.
     pub fn process_files(&self, files: &[PathBuf]) -> Result<BatchStats> {
         if files.is_empty() {
-            return Err(MedImgError::Validation("No files to process".into()));
+            return Err(MedImgError::Config("No files to process".into()));
         }
 
         self.process_files_internal(files, None)
     }
 
+    /// Process the DICOM objects under `prefix` in `input`.
+    ///
+    /// Objects are discovered like files in
+    /// [`process_directory`](Self::process_directory), with `prefix` as the
+    /// directory; output keys preserve the layout below it.
+    pub fn process_storage(&self, input: &dyn Storage, prefix: &str) -> Result<BatchStats> {
+        self.progress.on_progress(&ProgressEvent::discovery(format!(
+            "Scanning {}",
+            input.uri(prefix)
+        )));
+
+        let objects = FileDiscovery::new()
+            .recursive(self.recursive)
+            .patterns(self.patterns.clone())
+            .discover_objects(input, prefix)?;
+
+        if objects.is_empty() {
+            return Err(MedImgError::Config(format!(
+                "No matching files found in {}",
+                input.uri(prefix)
+            )));
+        }
+
+        self.process_objects(input, &objects, Some(prefix))
+    }
+
+    /// Retrieve a study, or one of its series, over WADO-RS and process its
+    /// instances.
+    ///
+    /// Instances are staged in `staging_dir` (created if needed, and left
+    /// for the caller to clean up); output paths follow the usual output
+    /// options, relative to `staging_dir`.
+    #[cfg(feature = "dicomweb")]
+    pub fn process_wado(
+        &self,
+        client: &crate::net::WadoClient,
+        study_uid: &str,
+        series_uid: Option<&str>,
+        staging_dir: &Path,
+    ) -> Result<BatchStats> {
+        self.progress.on_progress(&ProgressEvent::discovery(format!(
+            "Retrieving study {}{}",
+            study_uid,
+            series_uid.map(|s| format!(" series {}", s)).unwrap_or_default()
+        )));
+
+        let files = client.retrieve_to(study_uid, series_uid, staging_dir)?;
+        if files.is_empty() {
+            return Err(MedImgError::Config(format!(
+                "No instances retrieved for study {}",
+                study_uid
+            )));
+        }
+
+        self.process_files_internal(&files, Some(staging_dir))
+    }
+
     /// Internal file processing implementation.
     fn process_files_internal(&self, files: &[PathBuf], base_dir: Option<&Path>) -> Result<BatchStats> {
+        // Keys are the paths themselves
+        let objects: Vec<ObjectInfo> = files
+            .iter()
+            .map(|file| ObjectInfo {
+                key: file.to_string_lossy().into_owned(),
+                size: std::fs::metadata(file).map(|m| m.len()).unwrap_or(0),
+            })
+            .collect();
+        let base = base_dir.map(|dir| dir.to_string_lossy());
+        self.process_objects(&LocalStorage::new(""), &objects, base.as_deref())
+    }
+
+    /// Process objects from `input`.
+    fn process_objects(
+        &self,
+        input: &dyn Storage,
+        objects: &[ObjectInfo],
+        base: Option<&str>,
+    ) -> Result<BatchStats> {
         let start_time = Instant::now();
-        let total_files = files.len();
+        let total_files = objects.len();
+
+        // Counts are recorded as i64, which OpenTelemetry exports as numbers
+        let batch_span = tracing::info_span!(
+            "batch",
+            total_files = total_files as i64,
+            max_parallel = self.max_parallel as i64,
+            successful = tracing::field::Empty,
+            failed = tracing::field::Empty,
+            ratio = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        let _guard = batch_span.enter();
 
         // Calculate total size
-        let total_bytes: u64 = files
-            .iter()
-            .filter_map(|p| std::fs::metadata(p).ok())
-            .map(|m| m.len())
-            .sum();
+        let total_bytes: u64 = objects.iter().map(|object| object.size).sum();
 
         self.progress.on_progress(&ProgressEvent {
             phase: ProgressPhase::Discovery,
@@ -203,29 +432,88 @@ impl<P: ProgressHandler> BatchProcessor<P> {
             .build()
             .map_err(|e| MedImgError::Internal(e.to_string()))?;
 
+        let local_output;
+        let output: Option<&dyn Storage> = match (&self.output_storage, &self.output_dir) {
+            (Some(storage), _) => Some(storage.as_ref()),
+            (None, Some(dir)) => {
+                local_output = LocalStorage::new(dir);
+                Some(&local_output)
+            }
+            (None, None) => None,
+        };
+        let run = BatchRun {
+            total_files,
+            input,
+            output,
+            base,
+            quality_samples: Mutex::new(Vec::new()),
+            throughput: ThroughputTracker::new(total_bytes),
+        };
+        #[cfg(feature = "prometheus")]
+        if let Some(ref metrics) = self.metrics {
+            metrics.set_queue_depth(total_files);
+        }
+
         // Process files in parallel
+        let dispatch = tracing::dispatcher::get_default(|dispatch| dispatch.clone());
         let results: Vec<JobResult> = pool.install(|| {
-            files
+            objects
                 .par_iter()
                 .enumerate()
-                .map(|(idx, file)| {
+                .map(|(idx, object)| {
+                    // Worker threads do not inherit the caller's subscriber
+                    // or entered span, so pass both explicitly
+                    let _dispatch = tracing::dispatcher::set_default(&dispatch);
+                    let file = &PathBuf::from(input.uri(&object.key));
                     if self.is_cancelled() {
                         return JobResult {
                             job: BatchJob::new(idx as u64, file.clone()),
                             compression_result: None,
-                            error: Some(MedImgError::Internal("Cancelled".into())),
+                            error: Some(MedImgError::Cancelled("Batch cancelled".into())),
                             duration_ms: 0,
+                            timings: PhaseTimings::default(),
                         };
                     }
 
-                    self.process_single_file(idx, file, total_files, base_dir)
+                    let _job = tracing::info_span!(parent: &batch_span, "batch_job", job_id = idx as i64)
+                        .entered();
+                    // A panic in one file (e.g. in a codec) must not lose the batch
+                    let start = Instant::now();
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        self.process_single_file(idx, object, file, &run)
+                    }))
+                    .unwrap_or_else(|payload| {
+                        let e = MedImgError::Internal(format!(
+                            "Panic while processing file: {}",
+                            panic_message(payload.as_ref())
+                        ));
+                        self.progress.on_error(&e, Some(file));
+                        JobResult {
+                            job: BatchJob::new(idx as u64, file.clone()),
+                            compression_result: None,
+                            error: Some(e),
+                            duration_ms: start.elapsed().as_millis() as u64,
+                            timings: PhaseTimings::default(),
+                        }
+                    });
+                    #[cfg(feature = "prometheus")]
+                    self.record_metrics(&result, &run);
+                    result
                 })
                 .collect()
         });
 
+        // Cancelled and panicked files never reach the tracker
+        #[cfg(feature = "prometheus")]
+        if let Some(ref metrics) = self.metrics {
+            metrics.set_queue_depth(0);
+        }
+
         // Aggregate statistics
-        let mut stats = BatchStats::default();
-        stats.total_files = total_files;
+        let mut stats = BatchStats {
+            total_files,
+            ..Default::default()
+        };
 
         for result in &results {
             if let Some(ref compression_result) = result.compression_result {
@@ -237,7 +525,31 @@ impl<P: ProgressHandler> BatchProcessor<P> {
             }
         }
 
+        if let Some(min_ssim) = self.quality_gate {
+            let samples = run.quality_samples.into_inner().unwrap_or_default();
+            stats.quality =
+                QualityStats::from_samples(samples, min_ssim, QualityStats::DEFAULT_WORST_COUNT);
+        }
+
+        if let Some(ref path) = self.manifest {
+            // The batch itself succeeded; a manifest failure is reported, not fatal
+            if let Err(e) = manifest::append_manifest(path, &results) {
+                self.progress.on_error(&e, None);
+            }
+        }
+
+        #[cfg(feature = "catalog")]
+        if let Some(ref catalog) = self.catalog {
+            if let Err(e) = catalog.record(&results, &self.config) {
+                self.progress.on_error(&e, None);
+            }
+        }
+
         stats.total_time_ms = start_time.elapsed().as_millis() as u64;
+        batch_span.record("successful", stats.successful as i64);
+        batch_span.record("failed", stats.failed as i64);
+        batch_span.record("ratio", stats.overall_ratio());
+        batch_span.record("duration_ms", stats.total_time_ms as i64);
 
         // Report completion
         self.progress.on_complete(&stats);
@@ -245,103 +557,218 @@ impl<P: ProgressHandler> BatchProcessor<P> {
         Ok(stats)
     }
 
-    /// Process a single file.
+    /// Update operational metrics after a file finishes.
+    #[cfg(feature = "prometheus")]
+    fn record_metrics(&self, result: &JobResult, run: &BatchRun<'_>) {
+        let Some(ref metrics) = self.metrics else {
+            return;
+        };
+        match (&result.compression_result, &result.error) {
+            (Some(r), _) => metrics.record_success(r),
+            (None, Some(e)) => metrics.record_failure(e),
+            (None, None) => {}
+        }
+        metrics.set_queue_depth(run.total_files.saturating_sub(run.throughput.files_done()));
+    }
+
+    /// Process a single file, reported as `file`.
     fn process_single_file(
         &self,
         idx: usize,
+        object: &ObjectInfo,
         file: &Path,
-        total: usize,
-        base_dir: Option<&Path>,
+        run: &BatchRun<'_>,
     ) -> JobResult {
         let job = BatchJob::new(idx as u64, file.to_path_buf());
         let start = Instant::now();
+        let file_bytes = object.size;
+        let (throughput_bps, eta_seconds) = run.throughput.estimate();
 
         // Report progress
-        self.progress.on_progress(&ProgressEvent {
-            phase: ProgressPhase::Reading,
-            current_file: Some(file.to_path_buf()),
-            completed_files: idx,
-            total_files: Some(total),
-            overall_progress: idx as f64 / total as f64,
-            message: format!("Processing {}", file.file_name().unwrap_or_default().to_string_lossy()),
-            ..Default::default()
-        });
-
-        // Determine output path
-        let output_path = self.compute_output_path(file, base_dir);
-
-        // Create output directory if needed
-        if let Some(ref out) = output_path {
-            if let Some(parent) = out.parent() {
-                if let Err(e) = std::fs::create_dir_all(parent) {
-                    return JobResult {
-                        job,
-                        compression_result: None,
-                        error: Some(MedImgError::Io(e)),
-                        duration_ms: start.elapsed().as_millis() as u64,
-                    };
-                }
+        self.progress.on_progress(
+            &ProgressEvent {
+                phase: ProgressPhase::Reading,
+                current_file: Some(file.to_path_buf()),
+                message: format!("Processing {}", file.file_name().unwrap_or_default().to_string_lossy()),
+                ..Default::default()
             }
-        }
+            .with_batch_progress(
+                run.throughput.files_done(),
+                run.total_files,
+                run.throughput.bytes_done(),
+                Some(run.throughput.total_bytes()),
+            )
+            .with_timing(throughput_bps, eta_seconds),
+        );
+
+        // Determine output key
+        let output = run
+            .output
+            .zip(self.compute_output_key(&object.key, run.base));
 
         // Process the file
-        let pipeline = CompressionPipeline::new(self.config.clone());
-        let result = pipeline.compress_file(file);
+        let pipeline = CompressionPipeline::new(self.config.clone())
+            .measure_quality(self.quality_gate.is_some());
+        let mut attempt = 0;
+        let result = loop {
+            let result = match output {
+                Some((storage, ref key)) => pipeline.compress_object_to_with_progress(
+                    run.input,
+                    &object.key,
+                    storage,
+                    key,
+                    &self.progress,
+                ),
+                None => pipeline.compress_object_with_progress(run.input, &object.key, &self.progress),
+            }
+            .and_then(|r| self.check_quality(file, r, &run.quality_samples));
+            match result {
+                Err(e) if e.is_retryable() && attempt < self.retries && !self.is_cancelled() => {
+                    attempt += 1;
+                    let delay = RETRY_BACKOFF * 2u32.pow(attempt - 1);
+                    log::warn!(
+                        "Retrying {} in {} ms (attempt {} of {}): {}",
+                        file.display(),
+                        delay.as_millis(),
+                        attempt,
+                        self.retries,
+                        e
+                    );
+                    std::thread::sleep(delay);
+                }
+                result => break result,
+            }
+        };
 
         let duration_ms = start.elapsed().as_millis() as u64;
+        let files_done = run.throughput.record(file_bytes);
 
         match result {
             Ok(compression_result) => {
-                self.progress.on_progress(&ProgressEvent {
-                    phase: ProgressPhase::Complete,
-                    current_file: Some(file.to_path_buf()),
-                    completed_files: idx + 1,
-                    total_files: Some(total),
-                    overall_progress: (idx + 1) as f64 / total as f64,
-                    message: format!(
-                        "Compressed {} (ratio: {:.2}:1)",
-                        file.file_name().unwrap_or_default().to_string_lossy(),
-                        compression_result.compression_ratio
-                    ),
-                    ..Default::default()
-                });
+                let (throughput_bps, eta_seconds) = run.throughput.estimate();
+                self.progress.on_progress(
+                    &ProgressEvent {
+                        phase: ProgressPhase::Complete,
+                        current_file: Some(file.to_path_buf()),
+                        file_progress: 1.0,
+                        message: format!(
+                            "Compressed {} (ratio: {:.2}:1)",
+                            file.file_name().unwrap_or_default().to_string_lossy(),
+                            compression_result.compression_ratio
+                        ),
+                        ..Default::default()
+                    }
+                    .with_batch_progress(
+                        files_done,
+                        run.total_files,
+                        run.throughput.bytes_done(),
+                        Some(run.throughput.total_bytes()),
+                    )
+                    .with_timing(throughput_bps, eta_seconds)
+                    .with_phase_timings(compression_result.timings),
+                );
 
                 JobResult {
                     job,
+                    timings: compression_result.timings,
                     compression_result: Some(compression_result),
                     error: None,
                     duration_ms,
                 }
             }
             Err(e) => {
+                log::debug!("Failed {} after {} ms: {}", file.display(), duration_ms, e);
                 self.progress.on_error(&e, Some(file));
                 JobResult {
                     job,
                     compression_result: None,
                     error: Some(e),
                     duration_ms,
+                    timings: PhaseTimings::default(),
                 }
             }
         }
     }
 
-    /// Compute output path for a file.
-    fn compute_output_path(&self, file: &Path, base_dir: Option<&Path>) -> Option<PathBuf> {
-        let output_dir = self.output_dir.as_ref()?;
+    /// Record quality metrics and enforce the quality gate.
+    fn check_quality(
+        &self,
+        file: &Path,
+        result: CompressionResult,
+        quality_samples: &Mutex<Vec<FileQuality>>,
+    ) -> Result<CompressionResult> {
+        let (Some(min_ssim), Some(report)) = (self.quality_gate, result.quality.as_ref()) else {
+            return Ok(result);
+        };
+
+        let ssim = report.ssim.ssim;
+        if let Ok(mut samples) = quality_samples.lock() {
+            samples.push(FileQuality {
+                path: file.to_path_buf(),
+                ssim,
+                psnr_db: report.psnr.psnr_db,
+            });
+        }
 
-        if self.preserve_structure {
-            if let Some(base) = base_dir {
-                if let Ok(relative) = file.strip_prefix(base) {
-                    return Some(output_dir.join(relative));
-                }
-            }
+        if ssim < min_ssim {
+            return Err(MedImgError::CompressionConstraint(format!(
+                "Quality gate failed: SSIM {:.4} below minimum {:.4}",
+                ssim, min_ssim
+            )));
         }
 
-        file.file_name()
-            .map(|name| output_dir.join(name))
+        Ok(result)
+    }
+
+    /// Compute the output key for an input key, relative to the output
+    /// directory or storage.
+    fn compute_output_key(&self, key: &str, base: Option<&str>) -> Option<String> {
+        let file = Path::new(key);
+        let relative = base
+            .filter(|_| self.preserve_structure)
+            .and_then(|base| file.strip_prefix(base).ok());
+        let path = match relative {
+            Some(relative) => relative.to_path_buf(),
+            None => PathBuf::from(file.file_name()?),
+        };
+        let path = match self.output_template {
+            Some(ref template) => {
+                path.with_file_name(render_output_name(template, file, self.config.codec))
+            }
+            None => path,
+        };
+
+        let parts: Vec<_> = path.iter().map(|part| part.to_string_lossy()).collect();
+        Some(parts.join("/"))
     }
 }
 
+/// Message of a caught panic (`&str` and `String` payloads).
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic payload")
+}
+
+/// Expand an output file name template for `file`.
+fn render_output_name(template: &str, file: &Path, codec: CompressionCodec) -> String {
+    let part = |s: Option<&std::ffi::OsStr>| {
+        s.map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+    };
+    let codec = match codec {
+        CompressionCodec::Jpeg2000 => "j2k",
+        CompressionCodec::JpegLs => "jls",
+        CompressionCodec::Uncompressed => "raw",
+    };
+
+    template
+        .replace("{stem}", &part(file.file_stem()))
+        .replace("{ext}", &part(file.extension()))
+        .replace("{codec}", codec)
+}
+
 impl BatchProcessor<NullProgress> {
     /// Create a batch processor without progress reporting.
     pub fn without_progress(config: CompressionConfig) -> Self {
@@ -352,8 +779,10 @@ impl BatchProcessor<NullProgress> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::CompressionCodec;
-    use std::sync::atomic::AtomicUsize;
+    use crate::config::Modality;
+    use crate::progress::CallbackProgress;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
     #[test]
     fn test_batch_processor_creation() {
@@ -379,6 +808,123 @@ mod tests {
         assert_eq!(processor.output_dir, Some(PathBuf::from("/output")));
     }
 
+    #[test]
+    fn test_compute_output_path_template() {
+        let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+        let processor = BatchProcessor::without_progress(config)
+            .output_dir(PathBuf::from("/output"))
+            .output_template("{stem}_{codec}.{ext}");
+
+        let key = processor.compute_output_key("/input/study/image.dcm", Some("/input"));
+        assert_eq!(key.as_deref(), Some("study/image_jls.dcm"));
+        let key = processor.compute_output_key("in/study/image.dcm", Some("in"));
+        assert_eq!(key.as_deref(), Some("study/image_jls.dcm"));
+    }
+
+    #[test]
+    fn test_process_storage_writes_outputs() {
+        let input_dir = tempfile::TempDir::new().unwrap();
+        let output_dir = tempfile::TempDir::new().unwrap();
+        let pixels = crate::dicom::testing::gradient(8, 8);
+        for name in ["scans/a.dcm", "scans/study/b.dcm", "other/c.dcm"] {
+            let path = input_dir.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            crate::dicom::testing::write_grayscale(&path, 8, 8, "CR", &pixels);
+        }
+
+        let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+        let stats = BatchProcessor::without_progress(config)
+            .recursive(true)
+            .output_storage(Arc::new(LocalStorage::new(output_dir.path())))
+            .process_storage(&LocalStorage::new(input_dir.path()), "scans")
+            .unwrap();
+
+        assert_eq!(stats.successful, 2);
+        let written = crate::dicom::DicomFile::open(output_dir.path().join("study/b.dcm")).unwrap();
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, pixels);
+        assert!(output_dir.path().join("a.dcm").is_file());
+        assert!(!output_dir.path().join("c.dcm").exists());
+    }
+
+    #[test]
+    fn test_batch_processor_quality_gate() {
+        let config = CompressionConfig::lossless(CompressionCodec::Jpeg2000);
+        let processor = BatchProcessor::without_progress(config).quality_gate(0.98);
+        let samples = Mutex::new(Vec::new());
+
+        let gradient: Vec<u8> = (0..32 * 32).map(|i| (i % 256) as u8).collect();
+        let original = crate::ImageData::new(32, 32, 8, 1, gradient);
+        let degraded = crate::ImageData::new(32, 32, 8, 1, vec![128; 32 * 32]);
+        let report = crate::metrics::ImageComparator::new()
+            .compare(&original, &degraded)
+            .unwrap();
+
+        let result = CompressionResult {
+            source_path: PathBuf::from("/test/a.dcm"),
+            output_path: None,
+            modality: Modality::CR,
+            original_size: 100,
+            compressed_size: 50,
+            output_size: None,
+            compression_ratio: 2.0,
+            compression_time_ms: 1,
+            timings: PhaseTimings::default(),
+            is_lossless: false,
+            codec_name: "JPEG 2000".into(),
+            warnings: vec![],
+            quality: Some(report),
+            pixel_sha256: String::new(),
+            sop_instance_uid: None,
+            verified: false,
+        };
+
+        let checked = processor.check_quality(Path::new("/test/a.dcm"), result, &samples);
+        assert!(matches!(checked, Err(MedImgError::CompressionConstraint(_))));
+
+        let samples = samples.into_inner().unwrap();
+        assert_eq!(samples.len(), 1);
+        assert!(samples[0].ssim < 0.98);
+    }
+
+    #[test]
+    fn test_batch_isolates_panics() {
+        /// Panics when a file named `bad.dcm` starts processing.
+        struct PanicOnBad;
+        impl ProgressHandler for PanicOnBad {
+            fn on_progress(&self, event: &ProgressEvent) {
+                let bad = event.current_file.as_ref().is_some_and(|f| f.ends_with("bad.dcm"));
+                if bad && event.phase == ProgressPhase::Reading {
+                    panic!("codec exploded");
+                }
+            }
+        }
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let files: Vec<_> = ["good.dcm", "bad.dcm"]
+            .iter()
+            .map(|name| {
+                let path = dir.path().join(name);
+                crate::dicom::testing::write_grayscale(&path, 8, 8, "CR", &crate::dicom::testing::gradient(8, 8));
+                path
+            })
+            .collect();
+
+        let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+        let stats = BatchProcessor::new(config, PanicOnBad)
+            .output_dir(dir.path().join("out"))
+            .max_parallel(1)
+            .process_files(&files)
+            .unwrap();
+
+        assert_eq!((stats.successful, stats.failed), (1, 1));
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("bad {}", "pixels")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "bad pixels");
+    }
+
     #[test]
     fn test_batch_processor_cancellation() {
         let config = CompressionConfig::lossless(CompressionCodec::Jpeg2000);
@@ -389,13 +935,26 @@ mod tests {
         assert!(processor.is_cancelled());
     }
 
+    #[test]
+    fn test_batch_processor_shared_cancellation_token() {
+        let config = CompressionConfig::lossless(CompressionCodec::Jpeg2000);
+        let token = CancellationToken::new();
+        let progress = CallbackProgress::new(|_| {}).with_cancellation(token.clone());
+        let processor = BatchProcessor::new(config, progress).with_cancellation(token.clone());
+
+        assert!(!processor.is_cancelled());
+        token.cancel();
+        assert!(processor.is_cancelled());
+        assert!(processor.progress.is_cancelled());
+    }
+
     #[test]
     fn test_batch_processor_with_progress() {
         let config = CompressionConfig::lossless(CompressionCodec::Jpeg2000);
         let count = Arc::new(AtomicUsize::new(0));
         let count_clone = count.clone();
 
-        let progress = crate::progress::CallbackProgress::new(move |_| {
+        let progress = CallbackProgress::new(move |_| {
             count_clone.fetch_add(1, Ordering::SeqCst);
         });
 
@@ -0,0 +1,279 @@
+//! Summaries of batch manifests.
+//!
+//! Aggregates [`ManifestRecord`]s into totals and per-directory and
+//! per-modality breakdowns, rendered as CSV or a standalone HTML page.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use super::{JobStatus, ManifestRecord};
+
+/// Counts and sizes for a group of manifest records.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GroupSummary {
+    /// Files in the group.
+    pub files: usize,
+    /// Files compressed successfully.
+    pub completed: usize,
+    /// Files that failed.
+    pub failed: usize,
+    /// Files cancelled or skipped.
+    pub not_processed: usize,
+    /// Original bytes of completed files.
+    pub original_bytes: u64,
+    /// Compressed bytes of completed files.
+    pub compressed_bytes: u64,
+    /// Overall compression ratio of completed files.
+    pub ratio: f64,
+}
+
+impl GroupSummary {
+    /// Add a record to the group.
+    fn add(&mut self, record: &ManifestRecord) {
+        self.files += 1;
+        match record.status {
+            JobStatus::Completed => {
+                self.completed += 1;
+                self.original_bytes += record.original_size as u64;
+                self.compressed_bytes += record.compressed_size as u64;
+            }
+            JobStatus::Failed => self.failed += 1,
+            _ => self.not_processed += 1,
+        }
+        self.ratio = if self.compressed_bytes == 0 {
+            0.0
+        } else {
+            self.original_bytes as f64 / self.compressed_bytes as f64
+        };
+    }
+}
+
+/// Aggregated view of a batch manifest.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ManifestSummary {
+    /// All records.
+    pub totals: GroupSummary,
+    /// Records grouped by source directory.
+    pub by_directory: BTreeMap<String, GroupSummary>,
+    /// Records grouped by modality (`unknown` if the file was unreadable).
+    pub by_modality: BTreeMap<String, GroupSummary>,
+    /// Failed records.
+    pub failures: Vec<ManifestRecord>,
+}
+
+impl ManifestSummary {
+    /// Aggregate manifest records.
+    pub fn from_records(records: &[ManifestRecord]) -> Self {
+        let mut summary = Self::default();
+
+        for record in records {
+            let directory = record
+                .source_path
+                .parent()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default();
+            let modality = record
+                .modality
+                .as_ref()
+                .map(|m| m.to_string())
+                .unwrap_or_else(|| "unknown".into());
+
+            summary.totals.add(record);
+            summary.by_directory.entry(directory).or_default().add(record);
+            summary.by_modality.entry(modality).or_default().add(record);
+            if record.status == JobStatus::Failed {
+                summary.failures.push(record.clone());
+            }
+        }
+
+        summary
+    }
+
+    /// Render as CSV with one row per group (`total`, `directory`, `modality`).
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from(
+            "group,name,files,completed,failed,not_processed,original_bytes,compressed_bytes,ratio\n",
+        );
+        let mut row = |group: &str, name: &str, s: &GroupSummary| {
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{},{},{},{},{:.3}",
+                group,
+                csv_field(name),
+                s.files,
+                s.completed,
+                s.failed,
+                s.not_processed,
+                s.original_bytes,
+                s.compressed_bytes,
+                s.ratio
+            );
+        };
+
+        row("total", "", &self.totals);
+        for (name, group) in &self.by_directory {
+            row("directory", name, group);
+        }
+        for (name, group) in &self.by_modality {
+            row("modality", name, group);
+        }
+        out
+    }
+
+    /// Render failed records as CSV, one row per file.
+    pub fn failures_to_csv(&self) -> String {
+        let mut out = String::from("file,code,category,context,message\n");
+        for record in &self.failures {
+            let error = record.error.as_ref();
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{}",
+                csv_field(&record.source_path.display().to_string()),
+                error.map(|e| e.code.as_str()).unwrap_or(""),
+                error.map(|e| e.category.as_str()).unwrap_or(""),
+                csv_field(error.and_then(|e| e.context.as_deref()).unwrap_or("")),
+                csv_field(error.map(|e| e.message.as_str()).unwrap_or(""))
+            );
+        }
+        out
+    }
+
+    /// Render as a standalone HTML page.
+    pub fn to_html(&self) -> String {
+        let mut out = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Compression Report</title>\n<style>\n\
+             body { font-family: sans-serif; margin: 2em; }\n\
+             table { border-collapse: collapse; margin-bottom: 2em; }\n\
+             th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: right; }\n\
+             th:first-child, td:first-child { text-align: left; }\n\
+             </style>\n</head>\n<body>\n<h1>Compression Report</h1>\n",
+        );
+
+        let t = &self.totals;
+        let _ = writeln!(
+            out,
+            "<p>{} files: {} completed, {} failed, {} not processed. \
+             Overall ratio {:.2}:1.</p>",
+            t.files, t.completed, t.failed, t.not_processed, t.ratio
+        );
+
+        group_table(&mut out, "By directory", "Directory", &self.by_directory);
+        group_table(&mut out, "By modality", "Modality", &self.by_modality);
+
+        if !self.failures.is_empty() {
+            out.push_str("<h2>Failures</h2>\n<table>\n<tr><th>File</th><th>Code</th><th>Error</th></tr>\n");
+            for record in &self.failures {
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td>{}</td><td style=\"text-align: left\">{}</td></tr>",
+                    html_escape(&record.source_path.display().to_string()),
+                    record.error.as_ref().map(|e| e.code.as_str()).unwrap_or(""),
+                    html_escape(record.error.as_ref().map(|e| e.message.as_str()).unwrap_or(""))
+                );
+            }
+            out.push_str("</table>\n");
+        }
+
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+/// Append an HTML table of group summaries.
+fn group_table(out: &mut String, title: &str, label: &str, groups: &BTreeMap<String, GroupSummary>) {
+    let _ = writeln!(
+        out,
+        "<h2>{}</h2>\n<table>\n<tr><th>{}</th><th>Files</th><th>Completed</th>\
+         <th>Failed</th><th>Original</th><th>Compressed</th><th>Ratio</th></tr>",
+        title, label
+    );
+    for (name, s) in groups {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.2}:1</td></tr>",
+            html_escape(name),
+            s.files,
+            s.completed,
+            s.failed,
+            s.original_bytes,
+            s.compressed_bytes,
+            s.ratio
+        );
+    }
+    out.push_str("</table>\n");
+}
+
+/// Quote a CSV field if it contains separators or quotes.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escape text for HTML element content.
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Modality;
+    use crate::error::{ErrorRecord, MedImgError};
+    use std::path::PathBuf;
+
+    fn record(path: &str, status: JobStatus, modality: Option<Modality>) -> ManifestRecord {
+        let completed = status == JobStatus::Completed;
+        ManifestRecord {
+            source_path: PathBuf::from(path),
+            output_path: None,
+            status,
+            modality,
+            original_size: if completed { 1000 } else { 0 },
+            compressed_size: if completed { 250 } else { 0 },
+            duration_ms: 1,
+            pixel_sha256: None,
+            error: (status == JobStatus::Failed)
+                .then(|| ErrorRecord::new(&MedImgError::Dicom("bad <header>".into()), None)),
+        }
+    }
+
+    #[test]
+    fn test_summary_groups() {
+        let records = [
+            record("/a/1.dcm", JobStatus::Completed, Some(Modality::CT)),
+            record("/a/2.dcm", JobStatus::Completed, Some(Modality::MR)),
+            record("/b,c/3.dcm", JobStatus::Failed, None),
+        ];
+        let summary = ManifestSummary::from_records(&records);
+
+        assert_eq!(summary.totals.files, 3);
+        assert_eq!(summary.totals.failed, 1);
+        assert_eq!(summary.totals.ratio, 4.0);
+        assert_eq!(summary.by_directory["/a"].completed, 2);
+        assert_eq!(summary.by_modality["unknown"].failed, 1);
+        assert_eq!(summary.failures.len(), 1);
+
+        let csv = summary.to_csv();
+        assert!(csv.contains("total,,3,2,1,0,2000,500,4.000"));
+        assert!(csv.contains("directory,\"/b,c\",1,0,1"));
+
+        let failures = summary.failures_to_csv();
+        assert_eq!(failures.lines().count(), 2);
+        assert!(failures.contains("\"/b,c/3.dcm\",MI-DCM-001,input,,DICOM error: bad <header>"), "{}", failures);
+
+        let html = summary.to_html();
+        assert!(html.contains("bad &lt;header&gt;"));
+        assert!(html.contains("<td>MI-DCM-001</td>"));
+        assert!(html.contains("<td>CT</td>"));
+    }
+}
@@ -1,10 +1,12 @@
 //! Batch job scheduler using Rayon.
 
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use rayon::prelude::*;
 
+use crate::progress::CancellationToken;
+
 use super::job::{BatchJob, JobResult};
 
 /// Batch job scheduler for parallel processing.
@@ -12,8 +14,8 @@ pub struct BatchScheduler {
     /// Number of threads to use.
     num_threads: usize,
 
-    /// Cancellation flag.
-    cancelled: Arc<AtomicBool>,
+    /// Cancellation token.
+    cancelled: CancellationToken,
 
     /// Number of jobs completed.
     completed: Arc<AtomicUsize>,
@@ -24,11 +26,22 @@ impl BatchScheduler {
     pub fn new(num_threads: usize) -> Self {
         Self {
             num_threads: num_threads.max(1),
-            cancelled: Arc::new(AtomicBool::new(false)),
+            cancelled: CancellationToken::new(),
             completed: Arc::new(AtomicUsize::new(0)),
         }
     }
 
+    /// Use a shared cancellation token.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancelled = token;
+        self
+    }
+
+    /// Get a handle to the scheduler's cancellation token.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+
     /// Get the number of threads.
     pub fn num_threads(&self) -> usize {
         self.num_threads
@@ -41,17 +54,19 @@ impl BatchScheduler {
 
     /// Request cancellation.
     pub fn cancel(&self) {
-        self.cancelled.store(true, Ordering::SeqCst);
+        self.cancelled.cancel();
     }
 
     /// Check if cancelled.
     pub fn is_cancelled(&self) -> bool {
-        self.cancelled.load(Ordering::SeqCst)
+        self.cancelled.is_cancelled()
     }
 
     /// Reset the scheduler state.
+    ///
+    /// This also clears cancellation for every holder of the shared token.
     pub fn reset(&self) {
-        self.cancelled.store(false, Ordering::SeqCst);
+        self.cancelled.reset();
         self.completed.store(0, Ordering::SeqCst);
     }
 
@@ -82,12 +97,13 @@ impl BatchScheduler {
             jobs.into_par_iter()
                 .map(|job| {
                     // Check for cancellation
-                    if cancelled.load(Ordering::SeqCst) {
+                    if cancelled.is_cancelled() {
                         return JobResult {
                             job: job.clone(),
                             compression_result: None,
-                            error: Some(crate::error::MedImgError::Internal("Cancelled".into())),
+                            error: Some(crate::error::MedImgError::Cancelled("Batch cancelled".into())),
                             duration_ms: 0,
+                            timings: Default::default(),
                         };
                     }
 
@@ -126,12 +142,13 @@ impl BatchScheduler {
         pool.install(|| {
             jobs.into_par_iter()
                 .map(|job| {
-                    if cancelled.load(Ordering::SeqCst) {
+                    if cancelled.is_cancelled() {
                         return JobResult {
                             job: job.clone(),
                             compression_result: None,
-                            error: Some(crate::error::MedImgError::Internal("Cancelled".into())),
+                            error: Some(crate::error::MedImgError::Cancelled("Batch cancelled".into())),
                             duration_ms: 0,
+                            timings: Default::default(),
                         };
                     }
 
@@ -156,7 +173,6 @@ impl Default for BatchScheduler {
 mod tests {
     use super::*;
     use std::path::PathBuf;
-    use std::time::Duration;
 
     #[test]
     fn test_scheduler_creation() {
@@ -201,6 +217,7 @@ mod tests {
             compression_result: None,
             error: None,
             duration_ms: 10,
+            timings: Default::default(),
         });
 
         assert_eq!(results.len(), 5);
@@ -224,6 +241,7 @@ mod tests {
                 compression_result: None,
                 error: None,
                 duration_ms: 10,
+                timings: Default::default(),
             },
             move |_done, _total| {
                 progress_clone.fetch_add(1, Ordering::SeqCst);
@@ -250,6 +268,7 @@ mod tests {
                 compression_result: None,
                 error: None,
                 duration_ms: 0,
+                timings: Default::default(),
             }
         });
 
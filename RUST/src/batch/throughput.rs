@@ -0,0 +1,109 @@
+//! Throughput and ETA tracking across batch workers.
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Instant;
+
+/// Tracks bytes and files processed by concurrent workers.
+pub(crate) struct ThroughputTracker {
+    /// When processing started.
+    start: Instant,
+
+    /// Total bytes to process.
+    total_bytes: u64,
+
+    /// Bytes processed so far.
+    bytes_done: AtomicU64,
+
+    /// Files finished so far (successful or failed).
+    files_done: AtomicUsize,
+}
+
+impl ThroughputTracker {
+    /// Create a tracker for a batch of `total_bytes`.
+    pub(crate) fn new(total_bytes: u64) -> Self {
+        Self {
+            start: Instant::now(),
+            total_bytes,
+            bytes_done: AtomicU64::new(0),
+            files_done: AtomicUsize::new(0),
+        }
+    }
+
+    /// Record a finished file, returning the number of files finished.
+    pub(crate) fn record(&self, bytes: u64) -> usize {
+        self.bytes_done.fetch_add(bytes, Ordering::SeqCst);
+        self.files_done.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Number of files finished so far.
+    pub(crate) fn files_done(&self) -> usize {
+        self.files_done.load(Ordering::SeqCst)
+    }
+
+    /// Bytes processed so far.
+    pub(crate) fn bytes_done(&self) -> u64 {
+        self.bytes_done.load(Ordering::SeqCst)
+    }
+
+    /// Total bytes to process.
+    pub(crate) fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// Current throughput (bytes/second) and ETA (seconds).
+    pub(crate) fn estimate(&self) -> (f64, Option<f64>) {
+        estimate(
+            self.bytes_done(),
+            self.total_bytes,
+            self.start.elapsed().as_secs_f64(),
+        )
+    }
+}
+
+/// Compute throughput and ETA from progress so far.
+///
+/// The ETA is `None` until some bytes have been processed.
+fn estimate(bytes_done: u64, total_bytes: u64, elapsed_secs: f64) -> (f64, Option<f64>) {
+    if bytes_done == 0 || elapsed_secs <= 0.0 {
+        return (0.0, None);
+    }
+
+    let throughput = bytes_done as f64 / elapsed_secs;
+    let remaining = total_bytes.saturating_sub(bytes_done) as f64;
+
+    (throughput, Some(remaining / throughput))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_no_progress() {
+        assert_eq!(estimate(0, 1000, 5.0), (0.0, None));
+        assert_eq!(estimate(100, 1000, 0.0), (0.0, None));
+    }
+
+    #[test]
+    fn test_estimate_halfway() {
+        let (throughput, eta) = estimate(500, 1000, 10.0);
+        assert!((throughput - 50.0).abs() < 1e-9);
+        assert!((eta.unwrap() - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_estimate_done() {
+        let (_, eta) = estimate(1000, 1000, 10.0);
+        assert_eq!(eta, Some(0.0));
+    }
+
+    #[test]
+    fn test_tracker_record() {
+        let tracker = ThroughputTracker::new(300);
+        assert_eq!(tracker.record(100), 1);
+        assert_eq!(tracker.record(100), 2);
+        assert_eq!(tracker.files_done(), 2);
+        assert_eq!(tracker.bytes_done(), 200);
+        assert_eq!(tracker.total_bytes(), 300);
+    }
+}
@@ -0,0 +1,297 @@
+//! Hot-folder mode: watch a directory and compress files as they arrive.
+//!
+//! The watcher polls the input directory and hands new files to a
+//! [`BatchProcessor`] once they have stopped changing, so files still being
+//! copied in are not picked up half-written. A file modified after it was
+//! processed is processed again.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::error::{MedImgError, Result};
+use crate::pipeline::BatchStats;
+use crate::progress::ProgressHandler;
+
+use super::{BatchProcessor, FileDiscovery};
+
+/// Default time between directory scans.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default time between summary reports.
+pub const DEFAULT_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Granularity at which the watch loop checks for cancellation.
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Size and modification time used to detect changing files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileState {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileState {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+}
+
+/// Watches a directory and compresses files as they arrive.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::batch::{BatchProcessor, FolderWatcher};
+///
+/// let processor = BatchProcessor::without_progress(config).output_dir("archive".into());
+/// let token = processor.cancellation_token(); // token.cancel() stops the watch
+/// let stats = FolderWatcher::new(processor, "incoming")
+///     .run(|stats| println!("{} files so far", stats.total_files))?;
+/// ```
+pub struct FolderWatcher<P: ProgressHandler> {
+    /// Processor used for each group of new files.
+    processor: BatchProcessor<P>,
+
+    /// Directory being watched.
+    input_dir: PathBuf,
+
+    /// Time between directory scans.
+    poll_interval: Duration,
+
+    /// Time between summary reports.
+    report_interval: Duration,
+
+    /// Files seen on the last scan but not yet stable.
+    pending: HashMap<PathBuf, FileState>,
+
+    /// Files already processed, with the state they were processed at.
+    processed: HashMap<PathBuf, FileState>,
+}
+
+impl<P: ProgressHandler> FolderWatcher<P> {
+    /// Create a watcher for `input_dir`.
+    pub fn new(processor: BatchProcessor<P>, input_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            processor,
+            input_dir: input_dir.into(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            report_interval: DEFAULT_REPORT_INTERVAL,
+            pending: HashMap::new(),
+            processed: HashMap::new(),
+        }
+    }
+
+    /// Set the time between directory scans.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Set the time between summary reports.
+    pub fn report_interval(mut self, interval: Duration) -> Self {
+        self.report_interval = interval;
+        self
+    }
+
+    /// Scan once and process files that have stopped changing.
+    ///
+    /// Returns `None` if no file was ready.
+    pub fn poll_once(&mut self) -> Result<Option<BatchStats>> {
+        let files = FileDiscovery::new()
+            .recursive(self.processor.recursive)
+            .patterns(self.processor.patterns.clone())
+            .discover(&self.input_dir)?;
+
+        let mut ready = Vec::new();
+        let mut pending = HashMap::new();
+
+        for file in files {
+            // Outputs written under the watched directory are not inputs
+            if let Some(out) = &self.processor.output_dir {
+                if file.starts_with(out) {
+                    continue;
+                }
+            }
+            let Some(state) = FileState::of(&file) else {
+                continue;
+            };
+            if self.processed.get(&file) == Some(&state) {
+                continue;
+            }
+            if self.pending.get(&file) == Some(&state) {
+                ready.push((file, state));
+            } else {
+                pending.insert(file, state);
+            }
+        }
+        self.pending = pending;
+
+        if ready.is_empty() {
+            return Ok(None);
+        }
+
+        let paths: Vec<PathBuf> = ready.iter().map(|(path, _)| path.clone()).collect();
+        log::info!("Processing {} new files from {}", paths.len(), self.input_dir.display());
+        let stats = self
+            .processor
+            .process_files_internal(&paths, Some(&self.input_dir))?;
+
+        self.processed.extend(ready);
+        Ok(Some(stats))
+    }
+
+    /// Watch until cancelled, calling `on_report` with cumulative statistics
+    /// every report interval in which files were processed.
+    ///
+    /// Cancellation (via the processor's token) lets the files in flight
+    /// finish; the cumulative statistics are returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input directory is not a directory.
+    pub fn run<F: FnMut(&BatchStats)>(mut self, mut on_report: F) -> Result<BatchStats> {
+        if !self.input_dir.is_dir() {
+            return Err(MedImgError::Config(format!(
+                "Watch directory {} does not exist",
+                self.input_dir.display()
+            )));
+        }
+
+        let mut total = BatchStats::default();
+        let mut reported_files = 0;
+        let mut last_report = Instant::now();
+
+        while !self.processor.is_cancelled() {
+            match self.poll_once() {
+                Ok(Some(stats)) => accumulate(&mut total, &stats),
+                Ok(None) => {}
+                // The directory may be briefly unavailable (e.g. network shares)
+                Err(e) => log::warn!("Scan of {} failed: {}", self.input_dir.display(), e),
+            }
+
+            if last_report.elapsed() >= self.report_interval {
+                if total.total_files != reported_files {
+                    on_report(&total);
+                    reported_files = total.total_files;
+                }
+                last_report = Instant::now();
+            }
+
+            let wake = Instant::now() + self.poll_interval;
+            while Instant::now() < wake && !self.processor.is_cancelled() {
+                std::thread::sleep(CANCEL_CHECK_INTERVAL.min(self.poll_interval));
+            }
+        }
+
+        log::info!("Watch of {} stopped", self.input_dir.display());
+        Ok(total)
+    }
+}
+
+/// Add one batch's counts to running totals.
+///
+/// Quality distributions are per batch and are not merged.
+fn accumulate(total: &mut BatchStats, stats: &BatchStats) {
+    total.total_files += stats.total_files;
+    total.successful += stats.successful;
+    total.failed += stats.failed;
+    total.skipped += stats.skipped;
+    total.total_original_bytes += stats.total_original_bytes;
+    total.total_compressed_bytes += stats.total_compressed_bytes;
+    total.total_time_ms += stats.total_time_ms;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{transfer_syntax, CompressionCodec, CompressionConfig};
+    use crate::dicom::{testing, DicomFile};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_poll_waits_for_stable_files() {
+        let dir = TempDir::new().unwrap();
+        let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+        let mut watcher = FolderWatcher::new(BatchProcessor::without_progress(config), dir.path());
+
+        assert!(watcher.poll_once().unwrap().is_none());
+
+        testing::write_grayscale(&dir.path().join("a.dcm"), 8, 8, "CR", &testing::gradient(8, 8));
+
+        // First sighting only records the file
+        assert!(watcher.poll_once().unwrap().is_none());
+
+        let stats = watcher.poll_once().unwrap().unwrap();
+        assert_eq!(stats.total_files, 1);
+        assert_eq!(stats.successful, 1);
+
+        // Already processed and unchanged
+        assert!(watcher.poll_once().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_poll_writes_outputs() {
+        let dir = TempDir::new().unwrap();
+        let archive = dir.path().join("archive");
+        let pixels = testing::gradient(8, 8);
+        let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+        let processor = BatchProcessor::without_progress(config).output_dir(archive.clone());
+        let mut watcher = FolderWatcher::new(processor, dir.path());
+
+        testing::write_grayscale(&dir.path().join("a.dcm"), 8, 8, "CR", &pixels);
+        assert!(watcher.poll_once().unwrap().is_none());
+        assert_eq!(watcher.poll_once().unwrap().unwrap().successful, 1);
+
+        let written = DicomFile::open(archive.join("a.dcm")).unwrap();
+        assert_eq!(written.metadata.transfer_syntax, transfer_syntax::JPEG_LS_LOSSLESS);
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, pixels);
+
+        // The output, under the watched directory, is not picked up as input
+        assert!(watcher.poll_once().unwrap().is_none());
+        assert!(watcher.poll_once().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_run_stops_on_cancel() {
+        let dir = TempDir::new().unwrap();
+        let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+        let processor = BatchProcessor::without_progress(config);
+        let token = processor.cancellation_token();
+        token.cancel();
+
+        let stats = FolderWatcher::new(processor, dir.path())
+            .poll_interval(Duration::from_millis(10))
+            .run(|_| panic!("no report expected"))
+            .unwrap();
+        assert_eq!(stats.total_files, 0);
+
+        let missing = FolderWatcher::new(
+            BatchProcessor::without_progress(CompressionConfig::default()),
+            dir.path().join("missing"),
+        );
+        assert!(missing.run(|_| {}).is_err());
+    }
+
+    #[test]
+    fn test_accumulate() {
+        let mut total = BatchStats::default();
+        let batch = BatchStats {
+            total_files: 2,
+            successful: 1,
+            failed: 1,
+            total_original_bytes: 100,
+            total_compressed_bytes: 50,
+            ..Default::default()
+        };
+        accumulate(&mut total, &batch);
+        accumulate(&mut total, &batch);
+        assert_eq!(total.total_files, 4);
+        assert_eq!(total.failed, 2);
+        assert_eq!(total.overall_ratio(), 2.0);
+    }
+}
@@ -0,0 +1,351 @@
+//! C API for embedding the compressor (feature `capi`).
+//!
+//! Declared in `include/medimg_compress.h`. Functions return
+//! [`MEDIMG_OK`] or [`MEDIMG_ERROR`]; after an error,
+//! [`medimg_last_error`] describes it. Configurations are opaque handles
+//! created by the `medimg_config_*` functions and released with
+//! [`medimg_config_free`]. Panics are caught at the boundary and reported
+//! as errors.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+use crate::config::{CompressionCodec, CompressionConfig};
+use crate::error::{MedImgError, Result};
+use crate::pipeline::{CompressionPipeline, CompressionResult};
+
+/// Success.
+pub const MEDIMG_OK: c_int = 0;
+/// Failure; see [`medimg_last_error`].
+pub const MEDIMG_ERROR: c_int = -1;
+
+/// JPEG 2000 codec.
+pub const MEDIMG_CODEC_JPEG2000: c_int = 0;
+/// JPEG-LS codec.
+pub const MEDIMG_CODEC_JPEGLS: c_int = 1;
+/// No compression.
+pub const MEDIMG_CODEC_UNCOMPRESSED: c_int = 2;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Summary of a compression, filled in by the compress functions.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MedimgResult {
+    /// Original pixel data size in bytes.
+    pub original_size: u64,
+    /// Compressed pixel data size in bytes.
+    pub compressed_size: u64,
+    /// Compression ratio.
+    pub compression_ratio: f64,
+    /// Time taken in milliseconds.
+    pub compression_time_ms: u64,
+    /// 1 if the compression was lossless, otherwise 0.
+    pub is_lossless: c_int,
+}
+
+impl From<&CompressionResult> for MedimgResult {
+    fn from(result: &CompressionResult) -> Self {
+        Self {
+            original_size: result.original_size as u64,
+            compressed_size: result.compressed_size as u64,
+            compression_ratio: result.compression_ratio,
+            compression_time_ms: result.compression_time_ms,
+            is_lossless: c_int::from(result.is_lossless),
+        }
+    }
+}
+
+/// Record `message` as this thread's last error.
+fn set_last_error(message: String) {
+    // Interior NULs cannot cross the boundary
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Run `f`, converting errors and panics into [`MEDIMG_ERROR`].
+fn guard(f: impl FnOnce() -> Result<()>) -> c_int {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => MEDIMG_OK,
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            MEDIMG_ERROR
+        }
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".into());
+            set_last_error(format!("Internal error: {}", message));
+            MEDIMG_ERROR
+        }
+    }
+}
+
+/// Run `f` to create a configuration handle, or return null on failure.
+fn new_config(f: impl FnOnce() -> Result<CompressionConfig>) -> *mut CompressionConfig {
+    let mut config = None;
+    guard(|| {
+        config = Some(f()?);
+        Ok(())
+    });
+    config.map_or(ptr::null_mut(), |c| Box::into_raw(Box::new(c)))
+}
+
+/// Map a `MEDIMG_CODEC_*` constant to a codec.
+fn codec(codec: c_int) -> Result<CompressionCodec> {
+    match codec {
+        MEDIMG_CODEC_JPEG2000 => Ok(CompressionCodec::Jpeg2000),
+        MEDIMG_CODEC_JPEGLS => Ok(CompressionCodec::JpegLs),
+        MEDIMG_CODEC_UNCOMPRESSED => Ok(CompressionCodec::Uncompressed),
+        other => Err(MedImgError::Config(format!("Unknown codec {}", other))),
+    }
+}
+
+/// Borrow a non-null, NUL-terminated UTF-8 string argument.
+///
+/// # Safety
+///
+/// `s` must be null or point to a NUL-terminated string.
+unsafe fn str_arg<'a>(s: *const c_char, name: &str) -> Result<&'a str> {
+    if s.is_null() {
+        return Err(MedImgError::Config(format!("{} is null", name)));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| MedImgError::Config(format!("{} is not valid UTF-8", name)))
+}
+
+/// Borrow a configuration handle.
+///
+/// # Safety
+///
+/// `config` must be null or a live handle from a `medimg_config_*`
+/// function.
+unsafe fn config_arg<'a>(config: *const CompressionConfig) -> Result<&'a CompressionConfig> {
+    config
+        .as_ref()
+        .ok_or_else(|| MedImgError::Config("config is null".into()))
+}
+
+/// Build a validated pipeline for `config`.
+fn pipeline(config: &CompressionConfig) -> Result<CompressionPipeline> {
+    config
+        .validate()
+        .map_err(|errors| MedImgError::Config(errors.join("; ")))?;
+    Ok(CompressionPipeline::new(config.clone()))
+}
+
+/// Copy `result` to `out` if it is non-null.
+///
+/// # Safety
+///
+/// `out` must be null or valid for writes.
+unsafe fn write_result(out: *mut MedimgResult, result: &CompressionResult) {
+    if let Some(out) = out.as_mut() {
+        *out = MedimgResult::from(result);
+    }
+}
+
+/// Message for the last failed call on this thread, or null if none.
+///
+/// The string remains valid until the next failing call on the same
+/// thread; it must not be freed.
+#[no_mangle]
+pub extern "C" fn medimg_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |m| m.as_ptr()))
+}
+
+/// Create a lossless configuration for a `MEDIMG_CODEC_*` codec.
+///
+/// Returns null for an unknown codec.
+#[no_mangle]
+pub extern "C" fn medimg_config_lossless(codec_id: c_int) -> *mut CompressionConfig {
+    new_config(|| Ok(CompressionConfig::lossless(codec(codec_id)?)))
+}
+
+/// Create a lossy configuration with a target compression ratio.
+///
+/// Returns null for an unknown codec.
+#[no_mangle]
+pub extern "C" fn medimg_config_lossy(codec_id: c_int, ratio: f32) -> *mut CompressionConfig {
+    new_config(|| Ok(CompressionConfig::lossy(codec(codec_id)?, ratio)))
+}
+
+/// Create a configuration from its JSON form (as serialized by the Rust
+/// library).
+///
+/// Returns null if `json` does not parse.
+///
+/// # Safety
+///
+/// `json` must be null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn medimg_config_from_json(json: *const c_char) -> *mut CompressionConfig {
+    new_config(|| {
+        let json = str_arg(json, "json")?;
+        serde_json::from_str(json).map_err(|e| MedImgError::Config(format!("Invalid configuration: {}", e)))
+    })
+}
+
+/// Release a configuration. Null is ignored.
+///
+/// # Safety
+///
+/// `config` must be null or a handle from a `medimg_config_*` function,
+/// not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn medimg_config_free(config: *mut CompressionConfig) {
+    if !config.is_null() {
+        drop(Box::from_raw(config));
+    }
+}
+
+/// Compress the DICOM file at `input_path`, writing the encapsulated
+/// result to `output_path` (or only analyzing it when `output_path` is
+/// null). `result` may be null.
+///
+/// # Safety
+///
+/// `config` must be a live configuration handle, the paths null or
+/// NUL-terminated strings, and `result` null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn medimg_compress_file(
+    config: *const CompressionConfig,
+    input_path: *const c_char,
+    output_path: *const c_char,
+    result: *mut MedimgResult,
+) -> c_int {
+    guard(|| {
+        let pipeline = pipeline(config_arg(config)?)?;
+        let input = Path::new(str_arg(input_path, "input_path")?);
+        let compressed = if output_path.is_null() {
+            pipeline.compress_file(input)?
+        } else {
+            pipeline.compress_file_to(input, Path::new(str_arg(output_path, "output_path")?))?
+        };
+        write_result(result, &compressed);
+        Ok(())
+    })
+}
+
+/// Compress a DICOM file held in memory.
+///
+/// On success `*out_data` points to the encapsulated DICOM file and
+/// `*out_len` holds its length; release it with [`medimg_buffer_free`].
+/// `result` may be null.
+///
+/// # Safety
+///
+/// `config` must be a live configuration handle, `data` valid for `len`
+/// bytes, `out_data` and `out_len` valid for writes, and `result` null or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn medimg_compress_buffer(
+    config: *const CompressionConfig,
+    data: *const u8,
+    len: usize,
+    out_data: *mut *mut u8,
+    out_len: *mut usize,
+    result: *mut MedimgResult,
+) -> c_int {
+    guard(|| {
+        if data.is_null() || out_data.is_null() || out_len.is_null() {
+            return Err(MedImgError::Config("data, out_data and out_len must not be null".into()));
+        }
+        let pipeline = pipeline(config_arg(config)?)?;
+        let input = std::slice::from_raw_parts(data, len);
+        let mut output = Vec::new();
+        let compressed = pipeline.compress_stream(input, &mut output)?;
+
+        let output = output.into_boxed_slice();
+        *out_len = output.len();
+        *out_data = Box::into_raw(output).cast::<u8>();
+        write_result(result, &compressed);
+        Ok(())
+    })
+}
+
+/// Release a buffer returned by [`medimg_compress_buffer`]. Null is
+/// ignored.
+///
+/// # Safety
+///
+/// `data` and `len` must be exactly as returned, and not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn medimg_buffer_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dicom::{testing, DicomFile};
+    use tempfile::TempDir;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(medimg_last_error()) }.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_compress_file_and_buffer() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let output = dir.path().join("out.dcm");
+        let pixels = testing::gradient(16, 8);
+        testing::write_grayscale(&input, 16, 8, "CT", &pixels);
+
+        let config = medimg_config_lossless(MEDIMG_CODEC_JPEGLS);
+        assert!(!config.is_null());
+        let input_c = CString::new(input.to_str().unwrap()).unwrap();
+        let output_c = CString::new(output.to_str().unwrap()).unwrap();
+        let mut result = MedimgResult::default();
+        let status = unsafe { medimg_compress_file(config, input_c.as_ptr(), output_c.as_ptr(), &mut result) };
+        assert_eq!(status, MEDIMG_OK);
+        assert_eq!(result.is_lossless, 1);
+        assert_eq!(result.original_size, pixels.len() as u64);
+        let written = DicomFile::open(&output).unwrap();
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, pixels);
+
+        let source = std::fs::read(&input).unwrap();
+        let (mut data, mut len) = (ptr::null_mut(), 0);
+        let status =
+            unsafe { medimg_compress_buffer(config, source.as_ptr(), source.len(), &mut data, &mut len, ptr::null_mut()) };
+        assert_eq!(status, MEDIMG_OK);
+        let encoded = unsafe { std::slice::from_raw_parts(data, len) };
+        let written = DicomFile::from_reader(encoded).unwrap();
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, pixels);
+        unsafe {
+            medimg_buffer_free(data, len);
+            medimg_config_free(config);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(medimg_config_lossless(7).is_null());
+        assert!(last_error().contains("Unknown codec 7"));
+
+        let json = CString::new("{\"codec\": 1}").unwrap();
+        assert!(unsafe { medimg_config_from_json(json.as_ptr()) }.is_null());
+        assert!(last_error().contains("Invalid configuration"));
+
+        let config = medimg_config_lossy(MEDIMG_CODEC_JPEG2000, 10.0);
+        let missing = CString::new("/nonexistent/in.dcm").unwrap();
+        let status = unsafe { medimg_compress_file(config, missing.as_ptr(), ptr::null(), ptr::null_mut()) };
+        assert_eq!(status, MEDIMG_ERROR);
+        assert!(!last_error().is_empty());
+        let status = unsafe { medimg_compress_file(ptr::null(), missing.as_ptr(), ptr::null(), ptr::null_mut()) };
+        assert_eq!(status, MEDIMG_ERROR);
+        assert_eq!(last_error(), "Configuration error: config is null");
+        unsafe { medimg_config_free(config) };
+    }
+}
@@ -0,0 +1,440 @@
+//! SQLite compression catalog (`catalog` feature).
+//!
+//! Records every processed instance (SOP Instance UID, hashes, sizes,
+//! configuration, verification status, and time) in a single SQLite
+//! database. Unlike a JSONL manifest it can be queried by instance,
+//! status, or modality without reading the whole history, so it suits
+//! installations that process millions of files.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use medimg_compress::catalog::{Catalog, CatalogQuery};
+//!
+//! let catalog = Arc::new(Catalog::open("catalog.db")?);
+//! BatchProcessor::without_progress(config)
+//!     .catalog(catalog.clone())
+//!     .process_directory(dir)?;
+//!
+//! let failed = catalog.query(&CatalogQuery {
+//!     status: Some(JobStatus::Failed),
+//!     ..Default::default()
+//! })?;
+//! ```
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::batch::{JobResult, JobStatus, ManifestRecord};
+use crate::config::{CompressionConfig, Modality};
+use crate::error::{ErrorRecord, MedImgError, Result};
+
+/// Schema version stored in `PRAGMA user_version`.
+pub const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS instances (
+    id INTEGER PRIMARY KEY,
+    sop_instance_uid TEXT,
+    source_path TEXT NOT NULL,
+    output_path TEXT,
+    status TEXT NOT NULL,
+    modality TEXT,
+    codec TEXT,
+    lossless INTEGER,
+    original_size INTEGER NOT NULL,
+    compressed_size INTEGER NOT NULL,
+    pixel_sha256 TEXT,
+    output_sha256 TEXT,
+    verified INTEGER NOT NULL,
+    ssim REAL,
+    psnr_db REAL,
+    config TEXT,
+    duration_ms INTEGER NOT NULL,
+    error_code TEXT,
+    error TEXT,
+    recorded_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS instances_sop_instance_uid ON instances (sop_instance_uid);
+CREATE INDEX IF NOT EXISTS instances_status ON instances (status);
+CREATE INDEX IF NOT EXISTS instances_recorded_at ON instances (recorded_at);
+";
+
+const COLUMNS: &str = "id, sop_instance_uid, source_path, output_path, status, modality, codec, \
+     lossless, original_size, compressed_size, pixel_sha256, output_sha256, verified, ssim, \
+     psnr_db, config, duration_ms, error, recorded_at";
+
+/// Catalog error.
+fn catalog_error(action: &str, e: rusqlite::Error) -> MedImgError {
+    MedImgError::Storage(format!("Catalog {} failed: {}", action, e))
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Name a job status is stored under (its serialized form).
+fn status_name(status: JobStatus) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// SHA-256 of a local file, if it can be read.
+fn file_sha256(path: &Path) -> Option<String> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buffer).ok()? {
+            0 => break,
+            n => hasher.update(&buffer[..n]),
+        }
+    }
+    Some(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// A catalogued instance.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CatalogEntry {
+    /// Row identifier, increasing in recording order.
+    pub id: i64,
+    /// SOP Instance UID of the source (if it was read).
+    pub sop_instance_uid: Option<String>,
+    /// Paths, status, sizes, pixel hash, and error, as in a manifest.
+    #[serde(flatten)]
+    pub record: ManifestRecord,
+    /// Codec used.
+    pub codec: Option<String>,
+    /// Whether compression was lossless.
+    pub lossless: Option<bool>,
+    /// SHA-256 of the output file (if it was written locally).
+    pub output_sha256: Option<String>,
+    /// Whether the lossless round trip was verified.
+    pub verified: bool,
+    /// SSIM against the source (if quality was measured).
+    pub ssim: Option<f64>,
+    /// PSNR against the source in dB (if quality was measured).
+    pub psnr_db: Option<f64>,
+    /// Compression configuration, as JSON.
+    pub config: Option<String>,
+    /// When the instance was recorded (seconds since the Unix epoch).
+    pub recorded_at: u64,
+}
+
+impl CatalogEntry {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let status: String = row.get("status")?;
+        let error: Option<String> = row.get("error")?;
+        let record = ManifestRecord {
+            source_path: PathBuf::from(row.get::<_, String>("source_path")?),
+            output_path: row.get::<_, Option<String>>("output_path")?.map(PathBuf::from),
+            status: serde_json::from_value(serde_json::Value::String(status))
+                .unwrap_or(JobStatus::Failed),
+            modality: row.get::<_, Option<String>>("modality")?.map(Modality::from),
+            original_size: row.get::<_, i64>("original_size")? as usize,
+            compressed_size: row.get::<_, i64>("compressed_size")? as usize,
+            duration_ms: row.get::<_, i64>("duration_ms")? as u64,
+            pixel_sha256: row.get("pixel_sha256")?,
+            error: error.map(|error| {
+                serde_json::from_str(&error).unwrap_or_else(|_| ErrorRecord::from_message(error))
+            }),
+        };
+        Ok(Self {
+            id: row.get("id")?,
+            sop_instance_uid: row.get("sop_instance_uid")?,
+            record,
+            codec: row.get("codec")?,
+            lossless: row.get("lossless")?,
+            output_sha256: row.get("output_sha256")?,
+            verified: row.get("verified")?,
+            ssim: row.get("ssim")?,
+            psnr_db: row.get("psnr_db")?,
+            config: row.get("config")?,
+            recorded_at: row.get::<_, i64>("recorded_at")? as u64,
+        })
+    }
+}
+
+/// Filter for [`Catalog::query`]; unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct CatalogQuery {
+    /// Only this SOP Instance UID.
+    pub sop_instance_uid: Option<String>,
+    /// Only instances with this status.
+    pub status: Option<JobStatus>,
+    /// Only this modality.
+    pub modality: Option<Modality>,
+    /// Only instances recorded at or after this time (seconds since the
+    /// Unix epoch).
+    pub since: Option<u64>,
+    /// At most this many entries (the most recent).
+    pub limit: Option<usize>,
+}
+
+/// SQLite catalog of processed instances.
+///
+/// Shared between batch workers; writes are serialized on one connection.
+pub struct Catalog {
+    connection: Mutex<Connection>,
+}
+
+impl Catalog {
+    /// Open or create the catalog at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Storage` if the database cannot be opened, or `Config` if
+    /// it was created by a newer version.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let connection = Connection::open(path)
+            .map_err(|e| catalog_error("open", e).with_file(path))?;
+        // Readers (e.g. `report`) do not block a running batch
+        connection
+            .query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))
+            .map_err(|e| catalog_error("open", e))?;
+        Self::init(connection)
+    }
+
+    /// Catalog held in memory, for tests and one-off runs.
+    pub fn in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory().map_err(|e| catalog_error("open", e))?)
+    }
+
+    fn init(connection: Connection) -> Result<Self> {
+        let version: i64 = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(|e| catalog_error("open", e))?;
+        if version > SCHEMA_VERSION {
+            return Err(MedImgError::Config(format!(
+                "Catalog schema version {} is newer than supported version {}",
+                version, SCHEMA_VERSION
+            )));
+        }
+        connection
+            .execute_batch(SCHEMA)
+            .and_then(|_| connection.pragma_update(None, "user_version", SCHEMA_VERSION))
+            .map_err(|e| catalog_error("schema setup", e))?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    fn connection(&self) -> std::sync::MutexGuard<'_, Connection> {
+        // A panic mid-write rolls the transaction back, so the data is fine
+        self.connection.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Record a batch's results, compressed with `config`, in one
+    /// transaction; returns the number of entries added.
+    pub fn record(&self, results: &[JobResult], config: &CompressionConfig) -> Result<usize> {
+        let config = serde_json::to_string(config).ok();
+        let recorded_at = now() as i64;
+        let mut connection = self.connection();
+        let transaction = connection
+            .transaction()
+            .map_err(|e| catalog_error("record", e))?;
+        {
+            let mut insert = transaction
+                .prepare_cached(
+                    "INSERT INTO instances (sop_instance_uid, source_path, output_path, status, \
+                     modality, codec, lossless, original_size, compressed_size, pixel_sha256, \
+                     output_sha256, verified, ssim, psnr_db, config, duration_ms, error_code, \
+                     error, recorded_at) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, \
+                     ?16, ?17, ?18, ?19)",
+                )
+                .map_err(|e| catalog_error("record", e))?;
+            for result in results {
+                let record = ManifestRecord::from(result);
+                let compression = result.compression_result.as_ref();
+                let quality = compression.and_then(|r| r.quality.as_ref());
+                insert
+                    .execute(params![
+                        compression.and_then(|r| r.sop_instance_uid.as_deref()),
+                        record.source_path.to_string_lossy(),
+                        record.output_path.as_ref().map(|p| p.to_string_lossy()),
+                        status_name(record.status),
+                        record.modality.as_ref().map(Modality::code),
+                        compression.map(|r| r.codec_name.as_str()),
+                        compression.map(|r| r.is_lossless),
+                        record.original_size as i64,
+                        record.compressed_size as i64,
+                        record.pixel_sha256,
+                        record.output_path.as_deref().and_then(file_sha256),
+                        compression.is_some_and(|r| r.verified),
+                        quality.map(|q| q.ssim.ssim),
+                        quality.map(|q| q.psnr.psnr_db),
+                        compression.and(config.as_deref()),
+                        record.duration_ms as i64,
+                        record.error.as_ref().map(|e| e.code.as_str()),
+                        record.error.as_ref().and_then(|e| serde_json::to_string(e).ok()),
+                        recorded_at,
+                    ])
+                    .map_err(|e| catalog_error("record", e))?;
+            }
+        }
+        transaction.commit().map_err(|e| catalog_error("record", e))?;
+        Ok(results.len())
+    }
+
+    /// Entries matching `query`, oldest first.
+    pub fn query(&self, query: &CatalogQuery) -> Result<Vec<CatalogEntry>> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if let Some(ref uid) = query.sop_instance_uid {
+            conditions.push("sop_instance_uid = ?");
+            values.push(Value::Text(uid.clone()));
+        }
+        if let Some(status) = query.status {
+            conditions.push("status = ?");
+            values.push(Value::Text(status_name(status)));
+        }
+        if let Some(ref modality) = query.modality {
+            conditions.push("modality = ?");
+            values.push(Value::Text(modality.code().to_string()));
+        }
+        if let Some(since) = query.since {
+            conditions.push("recorded_at >= ?");
+            values.push(Value::Integer(since as i64));
+        }
+
+        let mut sql = format!("SELECT {} FROM instances", COLUMNS);
+        if !conditions.is_empty() {
+            sql += &format!(" WHERE {}", conditions.join(" AND "));
+        }
+        sql += " ORDER BY id DESC";
+        if let Some(limit) = query.limit {
+            sql += &format!(" LIMIT {}", limit);
+        }
+
+        let connection = self.connection();
+        let mut statement = connection
+            .prepare(&sql)
+            .map_err(|e| catalog_error("query", e))?;
+        let mut entries = statement
+            .query_map(params_from_iter(values), CatalogEntry::from_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| catalog_error("query", e))?;
+        entries.reverse();
+        Ok(entries)
+    }
+
+    /// Most recent entry for a SOP Instance UID.
+    pub fn latest(&self, sop_instance_uid: &str) -> Result<Option<CatalogEntry>> {
+        let connection = self.connection();
+        connection
+            .query_row(
+                &format!(
+                    "SELECT {} FROM instances WHERE sop_instance_uid = ?1 ORDER BY id DESC LIMIT 1",
+                    COLUMNS
+                ),
+                [sop_instance_uid],
+                CatalogEntry::from_row,
+            )
+            .optional()
+            .map_err(|e| catalog_error("query", e))
+    }
+
+    /// Number of catalogued entries.
+    pub fn len(&self) -> Result<usize> {
+        self.connection()
+            .query_row("SELECT COUNT(*) FROM instances", [], |row| row.get::<_, i64>(0))
+            .map(|count| count as usize)
+            .map_err(|e| catalog_error("query", e))
+    }
+
+    /// Whether the catalog has no entries.
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batch::BatchJob;
+    use crate::config::CompressionCodec;
+    use crate::dicom::testing;
+    use crate::pipeline::CompressionPipeline;
+
+    #[test]
+    fn test_record_and_query() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let output = dir.path().join("out.dcm");
+        testing::write_grayscale(&input, 8, 8, "CT", &testing::gradient(8, 8));
+
+        let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+        let compressed = CompressionPipeline::new(config.clone())
+            .compress_file_to(&input, &output)
+            .unwrap();
+        let uid = compressed.sop_instance_uid.clone().unwrap();
+        let completed = JobResult {
+            job: BatchJob::new(0, input.clone()),
+            timings: compressed.timings,
+            compression_result: Some(compressed),
+            error: None,
+            duration_ms: 5,
+        };
+        let failed = JobResult {
+            job: BatchJob::new(1, dir.path().join("bad.dcm")),
+            compression_result: None,
+            error: Some(MedImgError::Dicom("bad header".into())),
+            duration_ms: 1,
+            timings: Default::default(),
+        };
+
+        let path = dir.path().join("catalog.db");
+        let catalog = Catalog::open(&path).unwrap();
+        assert_eq!(catalog.record(&[completed, failed], &config).unwrap(), 2);
+        drop(catalog);
+
+        // Entries survive reopening
+        let catalog = Catalog::open(&path).unwrap();
+        assert_eq!(catalog.len().unwrap(), 2);
+
+        let entry = catalog.latest(&uid).unwrap().unwrap();
+        assert_eq!(entry.record.status, JobStatus::Completed);
+        assert_eq!(entry.record.modality, Some(Modality::CT));
+        assert_eq!(entry.record.output_path.as_deref(), Some(output.as_path()));
+        assert_eq!(entry.output_sha256, file_sha256(&output));
+        assert!(entry.verified);
+        assert!(entry.config.unwrap().contains("JpegLs"));
+
+        let failures = catalog
+            .query(&CatalogQuery {
+                status: Some(JobStatus::Failed),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(failures.len(), 1);
+        let error = failures[0].record.error.as_ref().unwrap();
+        assert_eq!(error.code, crate::error::ErrorCode::Dicom);
+        assert_eq!(failures[0].sop_instance_uid, None);
+
+        let modality = CatalogQuery {
+            modality: Some(Modality::MR),
+            ..Default::default()
+        };
+        assert!(catalog.query(&modality).unwrap().is_empty());
+        let recent = CatalogQuery {
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(catalog.query(&recent).unwrap()[0].record.status, JobStatus::Failed);
+    }
+}
@@ -0,0 +1,1032 @@
+//! Configuration file support for the CLI.
+//!
+//! Settings are read from TOML, either from an explicit `--config` path or
+//! from the first file found in the default search locations:
+//!
+//! 1. `./medimg.toml`
+//! 2. `$XDG_CONFIG_HOME/medimg/config.toml` (or `~/.config/medimg/config.toml`)
+//!
+//! A file named `*.yaml` or `*.yml` is read (and saved) as YAML instead,
+//! with the same keys and nesting as the TOML layout below.
+//!
+//! Files carry a schema `version` (currently [`CONFIG_VERSION`]); files
+//! without one predate versioning and are migrated on load. Unknown keys
+//! are logged as warnings and ignored.
+//!
+//! Every field is optional. Settings are layered, later sources winning:
+//!
+//! 1. Configuration file top-level values
+//! 2. The profile selected with `--profile NAME` (`[profiles.NAME]`)
+//! 3. `MEDIMG_*` environment variables (see [`FileConfig::from_env`])
+//! 4. Command-line flags
+//!
+//! ```toml
+//! version = 1
+//! codec = "jpeg-ls"
+//! # encoder_plugin = "/opt/acme/htj2k-plugin"
+//! mode = "lossless"
+//! quality = "diagnostic"
+//! verify = true
+//!
+//! [batch]
+//! jobs = 8
+//! recursive = true
+//! min_ssim = 0.98
+//! output_dir = "/archive"
+//!
+//! [policy]
+//! regulatory_profile = "rcr-uk"
+//! lossless_modalities = ["CT", "DX"]
+//! lossless_sop_classes = ["1.2.840.10008.5.1.4.1.1.1.1"]
+//! max_ratios = { CR = 15.0, US = 10.0 }
+//!
+//! [output]
+//! template = "{stem}_{codec}.dcm"
+//!
+//! # Refuse hostile inputs before decoding them, and queue jobs once
+//! # their images would take more than 8 GiB together
+//! [limits]
+//! max_output_bytes = 1073741824
+//! time_budget_ms = 30000
+//! max_memory_bytes = 8589934592
+//!
+//! # Access trail of files read and written (salt with MEDIMG_AUDIT_SALT)
+//! [audit]
+//! log = "/var/log/medimg/access.jsonl"
+//! actor = "svc-archive"
+//!
+//! # Site quality tiers, selected with `quality = "teaching"` or `--quality teaching`
+//! [quality_presets.teaching]
+//! ratio = 15.0
+//! layers = 4
+//!
+//! [profiles.teaching-lossy]
+//! codec = "jpeg2000"
+//! mode = "lossy"
+//! quality = "standard"
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{CompressionOverrides, DecodeLimits, Modality};
+use crate::error::{MedImgError, Result};
+
+use super::{CodecArg, ModeArg, QualityName, RegulatoryArg};
+
+/// File name searched for in the working directory.
+const LOCAL_CONFIG_FILE: &str = "medimg.toml";
+
+/// Prefix for environment variable overrides.
+const ENV_PREFIX: &str = "MEDIMG_";
+
+/// Current configuration file schema version.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Migrations between schema versions: entry `n` upgrades a version `n`
+/// table to version `n + 1`.
+const MIGRATIONS: [fn(&mut toml::Table); CONFIG_VERSION as usize] = [
+    // 0 -> 1: unversioned files share the version 1 layout
+    |_| {},
+];
+
+/// Syntax of a configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    /// TOML (the default).
+    Toml,
+    /// YAML.
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Format of the file at `path`, by extension.
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml") => Self::Yaml,
+            _ => Self::Toml,
+        }
+    }
+}
+
+/// A versioned configuration file.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConfigFile {
+    /// Schema version of the file as loaded (before migration).
+    #[serde(skip)]
+    pub source_version: u32,
+
+    /// Schema version (always [`CONFIG_VERSION`] once loaded).
+    pub version: u32,
+
+    /// Settings.
+    #[serde(flatten)]
+    pub settings: FileConfig,
+
+    /// Dotted paths of keys that were not recognized and were ignored.
+    #[serde(skip)]
+    pub unknown_fields: Vec<String>,
+}
+
+impl ConfigFile {
+    /// Wrap settings in a file of the current version.
+    pub fn new(settings: FileConfig) -> Self {
+        Self {
+            source_version: CONFIG_VERSION,
+            version: CONFIG_VERSION,
+            settings,
+            unknown_fields: Vec::new(),
+        }
+    }
+
+    /// Parse a configuration file from a TOML string, migrating older
+    /// versions to the current schema.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TOML is malformed, a value has the wrong
+    /// type, or the file was written for a newer schema version.
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let table = toml::from_str(text).map_err(|e| MedImgError::Config(e.to_string()))?;
+        Self::from_table(table)
+    }
+
+    /// Parse a configuration file from a YAML string (see
+    /// [`ConfigFile::from_toml`]).
+    ///
+    /// # Errors
+    ///
+    /// As for TOML; YAML nulls are also rejected, since TOML has none.
+    pub fn from_yaml(text: &str) -> Result<Self> {
+        let table = serde_yaml::from_str(text).map_err(|e| MedImgError::Config(e.to_string()))?;
+        Self::from_table(table)
+    }
+
+    /// Parse a configuration file in `format`.
+    pub fn parse(text: &str, format: ConfigFormat) -> Result<Self> {
+        match format {
+            ConfigFormat::Toml => Self::from_toml(text),
+            ConfigFormat::Yaml => Self::from_yaml(text),
+        }
+    }
+
+    /// Migrate and deserialize a parsed file, recording unknown keys.
+    fn from_table(mut table: toml::Table) -> Result<Self> {
+        let source_version = match table.remove("version") {
+            None => 0,
+            Some(toml::Value::Integer(v)) if (0..=i64::from(u32::MAX)).contains(&v) => v as u32,
+            Some(other) => {
+                return Err(MedImgError::Config(format!(
+                    "Invalid config version {}: expected a non-negative integer",
+                    other
+                )))
+            }
+        };
+        if source_version > CONFIG_VERSION {
+            return Err(MedImgError::Config(format!(
+                "Config version {} is newer than the supported version {}",
+                source_version, CONFIG_VERSION
+            )));
+        }
+        for migrate in &MIGRATIONS[source_version as usize..] {
+            migrate(&mut table);
+        }
+
+        let settings: FileConfig = table
+            .clone()
+            .try_into()
+            .map_err(|e: toml::de::Error| MedImgError::Config(e.to_string()))?;
+
+        // Keys that survive a round trip are the ones the schema knows
+        let known = toml::Table::try_from(&settings)
+            .map_err(|e| MedImgError::Internal(format!("Cannot serialize config: {}", e)))?;
+        let mut unknown_fields = Vec::new();
+        collect_unknown(&table, &known, "", &mut unknown_fields);
+
+        Ok(Self {
+            source_version,
+            version: CONFIG_VERSION,
+            settings,
+            unknown_fields,
+        })
+    }
+
+    /// Load a configuration file, logging migrations and unknown keys.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let file = Self::parse(&text, ConfigFormat::of(path)).map_err(|e| match e {
+            MedImgError::Config(msg) => {
+                MedImgError::Config(format!("Invalid config file {}: {}", path.display(), msg))
+            }
+            other => other,
+        })?;
+
+        if file.source_version < CONFIG_VERSION {
+            log::debug!(
+                "Migrated config file {} from version {} to {}",
+                path.display(),
+                file.source_version,
+                CONFIG_VERSION
+            );
+        }
+        for field in &file.unknown_fields {
+            log::warn!("Ignoring unknown key '{}' in {}", field, path.display());
+        }
+        Ok(file)
+    }
+
+    /// Serialize to TOML at the current schema version.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(&self.current())
+            .map_err(|e| MedImgError::Internal(format!("Cannot serialize config: {}", e)))
+    }
+
+    /// Serialize to YAML at the current schema version.
+    pub fn to_yaml(&self) -> Result<String> {
+        // Through a TOML table, so unset settings are left out rather than
+        // written as nulls
+        let table = toml::Table::try_from(self.current())
+            .map_err(|e| MedImgError::Internal(format!("Cannot serialize config: {}", e)))?;
+        serde_yaml::to_string(&table)
+            .map_err(|e| MedImgError::Internal(format!("Cannot serialize config: {}", e)))
+    }
+
+    /// Write the configuration to `path` at the current schema version, in
+    /// the format its extension names.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = match ConfigFormat::of(path) {
+            ConfigFormat::Toml => self.to_toml()?,
+            ConfigFormat::Yaml => self.to_yaml()?,
+        };
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// This file stamped with the current schema version.
+    fn current(&self) -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            ..self.clone()
+        }
+    }
+}
+
+/// Tables keyed by modality code. Their keys are normalized on load
+/// (`mri` becomes `MR`), so they are not compared key by key.
+const MODALITY_TABLES: &[&str] = &["max_ratios"];
+
+/// Record keys of `table` missing from `known` as dotted paths.
+fn collect_unknown(table: &toml::Table, known: &toml::Table, prefix: &str, out: &mut Vec<String>) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match (value, known.get(key)) {
+            (_, None) => out.push(path),
+            (_, Some(_)) if MODALITY_TABLES.contains(&key.as_str()) => {}
+            (toml::Value::Table(table), Some(toml::Value::Table(known))) => {
+                collect_unknown(table, known, &path, out)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Settings loaded from a configuration file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileConfig {
+    /// Compression codec.
+    pub codec: Option<CodecArg>,
+
+    /// External encoder plugin executable, used instead of the built-in
+    /// codec.
+    pub encoder_plugin: Option<PathBuf>,
+
+    /// Compression mode.
+    pub mode: Option<ModeArg>,
+
+    /// Quality preset (built-in or a `quality_presets` name).
+    pub quality: Option<QualityName>,
+
+    /// Target compression ratio (for lossy mode).
+    pub ratio: Option<f32>,
+
+    /// Target bits per pixel (for lossy mode; alternative to `ratio`).
+    pub bpp: Option<f32>,
+
+    /// Near-lossless error tolerance (JPEG-LS only).
+    pub near: Option<u16>,
+
+    /// Near-lossless error tolerance in rescaled units (e.g. HU), converted
+    /// per file with its Rescale Slope (alternative to `near`).
+    pub near_rescaled: Option<f32>,
+
+    /// Verify lossless compression by round-trip decode.
+    pub verify: Option<bool>,
+
+    /// Batch processing settings.
+    #[serde(default)]
+    pub batch: BatchSection,
+
+    /// Site compression policy.
+    #[serde(default)]
+    pub policy: PolicySection,
+
+    /// Output naming.
+    #[serde(default)]
+    pub output: OutputSection,
+
+    /// Decode resource limits.
+    #[serde(default)]
+    pub limits: LimitsSection,
+
+    /// Access trail.
+    #[serde(default)]
+    pub audit: AuditSection,
+
+    /// Site-defined quality tiers by name.
+    #[serde(default)]
+    pub quality_presets: BTreeMap<String, QualityPresetSection>,
+
+    /// Named profiles layered over the top-level settings.
+    #[serde(default)]
+    pub profiles: BTreeMap<String, FileConfig>,
+}
+
+/// `[batch]` section of the configuration file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchSection {
+    /// Maximum parallel jobs.
+    pub jobs: Option<usize>,
+
+    /// Scan subdirectories recursively.
+    pub recursive: Option<bool>,
+
+    /// Minimum SSIM per file (enables quality gating).
+    pub min_ssim: Option<f64>,
+
+    /// Retries for files that fail with a transient error.
+    pub retries: Option<u32>,
+
+    /// Output directory.
+    pub output_dir: Option<PathBuf>,
+}
+
+/// `[policy]` section of the configuration file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PolicySection {
+    /// Regulatory profile (lossless-only modalities and ratio caps).
+    pub regulatory_profile: Option<RegulatoryArg>,
+
+    /// Modalities restricted to lossless compression by site policy.
+    pub lossless_modalities: Option<Vec<Modality>>,
+
+    /// SOP class UIDs restricted to lossless compression by site policy.
+    pub lossless_sop_classes: Option<Vec<String>>,
+
+    /// Maximum lossy ratio per modality by site policy.
+    pub max_ratios: Option<BTreeMap<Modality, f32>>,
+}
+
+/// `[quality_presets.NAME]` section of the configuration file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QualityPresetSection {
+    /// Target compression ratio for lossy mode.
+    pub ratio: Option<f32>,
+
+    /// JPEG 2000 quality layers (default 1).
+    pub layers: Option<u32>,
+}
+
+/// `[output]` section of the configuration file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OutputSection {
+    /// Output file name template (`{stem}`, `{ext}`, `{codec}`).
+    pub template: Option<String>,
+}
+
+/// `[limits]` section of the configuration file; unset limits keep their
+/// [`DecodeLimits::DEFAULT`] values.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LimitsSection {
+    /// Maximum image width or height in pixels.
+    pub max_dimension: Option<u32>,
+
+    /// Maximum decoded pixel data per image, in bytes.
+    pub max_output_bytes: Option<u64>,
+
+    /// Maximum frames, fragments, or tiles per image.
+    pub max_fragments: Option<usize>,
+
+    /// Maximum time to decode one image, in milliseconds.
+    pub time_budget_ms: Option<u64>,
+
+    /// Ceiling on pixel buffers held by concurrent jobs, in bytes (see
+    /// [`MemoryAccountant`](crate::memory::MemoryAccountant)).
+    pub max_memory_bytes: Option<u64>,
+}
+
+/// `[audit]` section of the configuration file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditSection {
+    /// JSON lines file that records every DICOM input read and output
+    /// written (see [`AccessLog`](crate::audit::AccessLog)).
+    pub log: Option<PathBuf>,
+
+    /// Operator or service account recorded as the actor (defaults to
+    /// `$MEDIMG_OPERATOR`, then the login user).
+    pub actor: Option<String>,
+
+    /// Secret salted into hashed identifiers. Prefer `MEDIMG_AUDIT_SALT`
+    /// over storing it in the file.
+    pub salt: Option<String>,
+}
+
+impl LimitsSection {
+    /// The configured limits over the defaults.
+    pub fn decode_limits(&self) -> DecodeLimits {
+        let default = DecodeLimits::DEFAULT;
+        DecodeLimits {
+            max_dimension: self.max_dimension.unwrap_or(default.max_dimension),
+            max_output_bytes: self.max_output_bytes.unwrap_or(default.max_output_bytes),
+            max_fragments: self.max_fragments.unwrap_or(default.max_fragments),
+            time_budget: self.time_budget_ms.map(Duration::from_millis).or(default.time_budget),
+        }
+    }
+}
+
+impl FileConfig {
+    /// Parse configuration from a TOML string (see [`ConfigFile::from_toml`]).
+    pub fn from_toml(text: &str) -> Result<Self> {
+        ConfigFile::from_toml(text).map(|file| file.settings)
+    }
+
+    /// Load configuration from a file (see [`ConfigFile::load`]).
+    pub fn load(path: &Path) -> Result<Self> {
+        ConfigFile::load(path).map(|file| file.settings)
+    }
+
+    /// Locate and load the configuration file.
+    ///
+    /// An explicit path must exist. Without one, the default locations are
+    /// searched and `None` is returned if no file is found.
+    pub fn discover(explicit: Option<&Path>) -> Result<Option<(PathBuf, Self)>> {
+        if let Some(path) = explicit {
+            return Self::load(path).map(|config| Some((path.to_path_buf(), config)));
+        }
+
+        for path in default_search_paths() {
+            if path.is_file() {
+                return Self::load(&path).map(|config| Some((path, config)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Names of the profiles defined in this file.
+    pub fn profile_names(&self) -> Vec<&str> {
+        self.profiles.keys().map(String::as_str).collect()
+    }
+
+    /// Apply the named profile over the top-level settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the profile is not defined or itself defines
+    /// nested profiles.
+    pub fn select_profile(mut self, name: &str) -> Result<Self> {
+        let profile = self.profiles.remove(name).ok_or_else(|| {
+            MedImgError::Config(format!(
+                "Unknown profile '{}' (available: {})",
+                name,
+                if self.profiles.is_empty() {
+                    "none".to_string()
+                } else {
+                    self.profile_names().join(", ")
+                }
+            ))
+        })?;
+
+        if !profile.profiles.is_empty() {
+            return Err(MedImgError::Config(format!(
+                "Profile '{}' cannot define nested profiles",
+                name
+            )));
+        }
+
+        Ok(self.overlay(profile))
+    }
+
+    /// Read overrides from `MEDIMG_*` environment variables.
+    ///
+    /// | Variable | Setting |
+    /// |----------|---------|
+    /// | `MEDIMG_CODEC` | `codec` |
+    /// | `MEDIMG_ENCODER_PLUGIN` | `encoder_plugin` |
+    /// | `MEDIMG_MODE` | `mode` |
+    /// | `MEDIMG_QUALITY` | `quality` |
+    /// | `MEDIMG_RATIO` | `ratio` |
+    /// | `MEDIMG_BPP` | `bpp` |
+    /// | `MEDIMG_NEAR` | `near` |
+    /// | `MEDIMG_NEAR_RESCALED` | `near_rescaled` |
+    /// | `MEDIMG_VERIFY` | `verify` |
+    /// | `MEDIMG_JOBS` | `batch.jobs` |
+    /// | `MEDIMG_RECURSIVE` | `batch.recursive` |
+    /// | `MEDIMG_MIN_SSIM` | `batch.min_ssim` |
+    /// | `MEDIMG_RETRIES` | `batch.retries` |
+    /// | `MEDIMG_OUTPUT_DIR` | `batch.output_dir` |
+    /// | `MEDIMG_OUTPUT_TEMPLATE` | `output.template` |
+    /// | `MEDIMG_REGULATORY_PROFILE` | `policy.regulatory_profile` |
+    /// | `MEDIMG_LOSSLESS_MODALITIES` | `policy.lossless_modalities` (comma-separated) |
+    /// | `MEDIMG_LOSSLESS_SOP_CLASSES` | `policy.lossless_sop_classes` (comma-separated) |
+    /// | `MEDIMG_MAX_RATIOS` | `policy.max_ratios` (e.g. `CT=10,CR=20`) |
+    /// | `MEDIMG_MAX_DIMENSION` | `limits.max_dimension` |
+    /// | `MEDIMG_MAX_OUTPUT_BYTES` | `limits.max_output_bytes` |
+    /// | `MEDIMG_MAX_FRAGMENTS` | `limits.max_fragments` |
+    /// | `MEDIMG_TIME_BUDGET_MS` | `limits.time_budget_ms` |
+    /// | `MEDIMG_MAX_MEMORY_BYTES` | `limits.max_memory_bytes` |
+    /// | `MEDIMG_AUDIT_LOG` | `audit.log` |
+    /// | `MEDIMG_AUDIT_ACTOR` | `audit.actor` |
+    /// | `MEDIMG_AUDIT_SALT` | `audit.salt` |
+    ///
+    /// `MEDIMG_CONFIG` and `MEDIMG_PROFILE` are handled by the argument
+    /// parser as defaults for `--config` and `--profile`.
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Read overrides through a variable lookup function.
+    fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let get = |key: &str| {
+            lookup(&format!("{}{}", ENV_PREFIX, key)).filter(|value| !value.trim().is_empty())
+        };
+
+        Ok(Self {
+            codec: get("CODEC").map(|v| parse_enum("CODEC", &v)).transpose()?,
+            encoder_plugin: get("ENCODER_PLUGIN").map(PathBuf::from),
+            mode: get("MODE").map(|v| parse_enum("MODE", &v)).transpose()?,
+            quality: get("QUALITY").map(|v| QualityName::from(v.trim().to_string())),
+            ratio: get("RATIO").map(|v| parse_value("RATIO", &v)).transpose()?,
+            bpp: get("BPP").map(|v| parse_value("BPP", &v)).transpose()?,
+            near: get("NEAR").map(|v| parse_value("NEAR", &v)).transpose()?,
+            near_rescaled: get("NEAR_RESCALED")
+                .map(|v| parse_value("NEAR_RESCALED", &v))
+                .transpose()?,
+            verify: get("VERIFY").map(|v| parse_bool("VERIFY", &v)).transpose()?,
+            batch: BatchSection {
+                jobs: get("JOBS").map(|v| parse_value("JOBS", &v)).transpose()?,
+                recursive: get("RECURSIVE").map(|v| parse_bool("RECURSIVE", &v)).transpose()?,
+                min_ssim: get("MIN_SSIM").map(|v| parse_value("MIN_SSIM", &v)).transpose()?,
+                retries: get("RETRIES").map(|v| parse_value("RETRIES", &v)).transpose()?,
+                output_dir: get("OUTPUT_DIR").map(PathBuf::from),
+            },
+            policy: PolicySection {
+                regulatory_profile: get("REGULATORY_PROFILE")
+                    .map(|v| parse_enum("REGULATORY_PROFILE", &v))
+                    .transpose()?,
+                lossless_modalities: get("LOSSLESS_MODALITIES").map(|v| {
+                    v.split(',')
+                        .filter(|code| !code.trim().is_empty())
+                        .map(Modality::from_dicom_string)
+                        .collect()
+                }),
+                lossless_sop_classes: get("LOSSLESS_SOP_CLASSES").map(|v| {
+                    v.split(',')
+                        .map(str::trim)
+                        .filter(|uid| !uid.is_empty())
+                        .map(String::from)
+                        .collect()
+                }),
+                max_ratios: get("MAX_RATIOS")
+                    .map(|v| parse_ratio_caps("MAX_RATIOS", &v))
+                    .transpose()?,
+            },
+            output: OutputSection {
+                template: get("OUTPUT_TEMPLATE"),
+            },
+            limits: LimitsSection {
+                max_dimension: get("MAX_DIMENSION")
+                    .map(|v| parse_value("MAX_DIMENSION", &v))
+                    .transpose()?,
+                max_output_bytes: get("MAX_OUTPUT_BYTES")
+                    .map(|v| parse_value("MAX_OUTPUT_BYTES", &v))
+                    .transpose()?,
+                max_fragments: get("MAX_FRAGMENTS")
+                    .map(|v| parse_value("MAX_FRAGMENTS", &v))
+                    .transpose()?,
+                time_budget_ms: get("TIME_BUDGET_MS")
+                    .map(|v| parse_value("TIME_BUDGET_MS", &v))
+                    .transpose()?,
+                max_memory_bytes: get("MAX_MEMORY_BYTES")
+                    .map(|v| parse_value("MAX_MEMORY_BYTES", &v))
+                    .transpose()?,
+            },
+            audit: AuditSection {
+                log: get("AUDIT_LOG").map(PathBuf::from),
+                actor: get("AUDIT_ACTOR"),
+                salt: get("AUDIT_SALT"),
+            },
+            quality_presets: BTreeMap::new(),
+            profiles: BTreeMap::new(),
+        })
+    }
+
+    /// Layer `over` on top of these settings; values set in `over` win.
+    pub fn overlay(self, over: FileConfig) -> Self {
+        let mut quality_presets = self.quality_presets;
+        quality_presets.extend(over.quality_presets);
+
+        Self {
+            codec: over.codec.or(self.codec),
+            encoder_plugin: over.encoder_plugin.or(self.encoder_plugin),
+            mode: over.mode.or(self.mode),
+            quality: over.quality.or(self.quality),
+            ratio: over.ratio.or(self.ratio),
+            bpp: over.bpp.or(self.bpp),
+            near: over.near.or(self.near),
+            near_rescaled: over.near_rescaled.or(self.near_rescaled),
+            verify: over.verify.or(self.verify),
+            batch: BatchSection {
+                jobs: over.batch.jobs.or(self.batch.jobs),
+                recursive: over.batch.recursive.or(self.batch.recursive),
+                min_ssim: over.batch.min_ssim.or(self.batch.min_ssim),
+                retries: over.batch.retries.or(self.batch.retries),
+                output_dir: over.batch.output_dir.or(self.batch.output_dir),
+            },
+            policy: PolicySection {
+                regulatory_profile: over
+                    .policy
+                    .regulatory_profile
+                    .or(self.policy.regulatory_profile),
+                lossless_modalities: over
+                    .policy
+                    .lossless_modalities
+                    .or(self.policy.lossless_modalities),
+                lossless_sop_classes: over
+                    .policy
+                    .lossless_sop_classes
+                    .or(self.policy.lossless_sop_classes),
+                max_ratios: over.policy.max_ratios.or(self.policy.max_ratios),
+            },
+            output: OutputSection {
+                template: over.output.template.or(self.output.template),
+            },
+            limits: LimitsSection {
+                max_dimension: over.limits.max_dimension.or(self.limits.max_dimension),
+                max_output_bytes: over.limits.max_output_bytes.or(self.limits.max_output_bytes),
+                max_fragments: over.limits.max_fragments.or(self.limits.max_fragments),
+                time_budget_ms: over.limits.time_budget_ms.or(self.limits.time_budget_ms),
+                max_memory_bytes: over.limits.max_memory_bytes.or(self.limits.max_memory_bytes),
+            },
+            audit: AuditSection {
+                log: over.audit.log.or(self.audit.log),
+                actor: over.audit.actor.or(self.audit.actor),
+                salt: over.audit.salt.or(self.audit.salt),
+            },
+            quality_presets,
+            profiles: self.profiles,
+        }
+    }
+
+    /// Compression settings as a layer for
+    /// [`CompressionConfig::merge`](crate::config::CompressionConfig::merge),
+    /// with the quality name resolved against `quality_presets`.
+    pub fn compression_overrides(&self) -> Result<CompressionOverrides> {
+        Ok(CompressionOverrides {
+            codec: self.codec.map(Into::into),
+            encoder_plugin: self.encoder_plugin.clone(),
+            mode: self.mode.map(Into::into),
+            quality: self
+                .quality
+                .clone()
+                .map(|name| super::resolve_quality(Some(name), &self.quality_presets))
+                .transpose()?,
+            target_ratio: self.ratio,
+            target_bpp: self.bpp,
+            near_lossless_error: self.near,
+            near_lossless_rescaled: self.near_rescaled,
+            verify_compression: self.verify,
+            regulatory_profile: self.policy.regulatory_profile.map(Into::into),
+            lossless_modalities: self.policy.lossless_modalities.clone(),
+            lossless_sop_classes: self.policy.lossless_sop_classes.clone(),
+            max_ratios: self
+                .policy
+                .max_ratios
+                .as_ref()
+                .map(|caps| caps.iter().map(|(modality, ratio)| (modality.clone(), *ratio)).collect()),
+            ..Default::default()
+        })
+    }
+}
+
+/// Build the error for a malformed environment variable.
+fn env_error(key: &str, value: &str, reason: impl std::fmt::Display) -> MedImgError {
+    MedImgError::Config(format!(
+        "Invalid {}{} value '{}': {}",
+        ENV_PREFIX, key, value, reason
+    ))
+}
+
+/// Parse a CLI enum value from an environment variable.
+fn parse_enum<T: ValueEnum>(key: &str, value: &str) -> Result<T> {
+    T::from_str(value.trim(), true).map_err(|e| env_error(key, value, e))
+}
+
+/// Parse a numeric value from an environment variable.
+fn parse_value<T>(key: &str, value: &str) -> Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    value.trim().parse().map_err(|e| env_error(key, value, e))
+}
+
+/// Parse a boolean flag from an environment variable.
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(env_error(key, value, "expected true/false")),
+    }
+}
+
+/// Parse `CODE=RATIO` pairs from an environment variable.
+fn parse_ratio_caps(key: &str, value: &str) -> Result<BTreeMap<Modality, f32>> {
+    value
+        .split(',')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (code, ratio) = pair
+                .split_once('=')
+                .ok_or_else(|| env_error(key, value, "expected MODALITY=RATIO pairs"))?;
+            Ok((Modality::from_dicom_string(code), parse_value(key, ratio)?))
+        })
+        .collect()
+}
+
+/// Default configuration file locations, in search order.
+pub fn default_search_paths() -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from(LOCAL_CONFIG_FILE)];
+
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    if let Some(dir) = config_home {
+        paths.push(dir.join("medimg").join("config.toml"));
+    }
+
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_config() {
+        let config = FileConfig::from_toml(
+            r#"
+            codec = "jpeg-ls"
+            mode = "near-lossless"
+            quality = "high-quality"
+            near = 3
+
+            [batch]
+            jobs = 4
+            recursive = true
+            output_dir = "/archive"
+
+            [policy]
+            regulatory_profile = "rcr-uk"
+            lossless_modalities = ["CT", "DX"]
+            max_ratios = { CR = 15.0, us = 10 }
+
+            [output]
+            template = "{stem}_{codec}.dcm"
+
+            [limits]
+            max_output_bytes = 1048576
+            time_budget_ms = 500
+            "#,
+        )
+        .unwrap();
+
+        assert!(matches!(config.codec, Some(CodecArg::JpegLs)));
+        assert!(matches!(config.mode, Some(ModeArg::NearLossless)));
+        assert!(matches!(config.quality, Some(QualityName::Builtin(crate::cli::QualityArg::HighQuality))));
+        assert_eq!(config.near, Some(3));
+        assert_eq!(config.batch.jobs, Some(4));
+        assert_eq!(config.batch.output_dir, Some(PathBuf::from("/archive")));
+        assert_eq!(
+            config.policy.lossless_modalities,
+            Some(vec![Modality::CT, Modality::DX])
+        );
+        assert!(matches!(config.policy.regulatory_profile, Some(RegulatoryArg::RcrUk)));
+        let caps = config.policy.max_ratios.unwrap();
+        assert_eq!(caps[&Modality::CR], 15.0);
+        assert_eq!(caps[&Modality::US], 10.0);
+        assert_eq!(config.output.template.as_deref(), Some("{stem}_{codec}.dcm"));
+        let limits = config.limits.decode_limits();
+        assert_eq!(limits.max_output_bytes, 1 << 20);
+        assert_eq!(limits.time_budget, Some(Duration::from_millis(500)));
+        assert_eq!(limits.max_dimension, DecodeLimits::DEFAULT.max_dimension);
+    }
+
+    #[test]
+    fn test_select_profile() {
+        let config = FileConfig::from_toml(
+            r#"
+            codec = "jpeg-ls"
+            verify = true
+
+            [batch]
+            jobs = 8
+
+            [profiles.teaching-lossy]
+            codec = "jpeg2000"
+            mode = "lossy"
+
+            [profiles.teaching-lossy.batch]
+            recursive = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.profile_names(), vec!["teaching-lossy"]);
+
+        let selected = config.clone().select_profile("teaching-lossy").unwrap();
+        assert!(matches!(selected.codec, Some(CodecArg::Jpeg2000)));
+        assert!(matches!(selected.mode, Some(ModeArg::Lossy)));
+        assert_eq!(selected.verify, Some(true));
+        assert_eq!(selected.batch.jobs, Some(8));
+        assert_eq!(selected.batch.recursive, Some(true));
+
+        let err = config.select_profile("archive").unwrap_err();
+        assert!(err.to_string().contains("available: teaching-lossy"));
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let vars = |name: &str| {
+            match name {
+                "MEDIMG_CODEC" => Some("JPEG-LS"),
+                "MEDIMG_JOBS" => Some("16"),
+                "MEDIMG_RECURSIVE" => Some("yes"),
+                "MEDIMG_OUTPUT_DIR" => Some("/data/out"),
+                "MEDIMG_LOSSLESS_MODALITIES" => Some("ct, mg"),
+                "MEDIMG_MAX_RATIOS" => Some("CT=10, cr=20"),
+                "MEDIMG_LOSSLESS_SOP_CLASSES" => Some("1.2.3, 1.2.4,"),
+                "MEDIMG_MODE" => Some(""),
+                "MEDIMG_MAX_FRAGMENTS" => Some("4096"),
+                "MEDIMG_MAX_MEMORY_BYTES" => Some("1073741824"),
+                "MEDIMG_AUDIT_ACTOR" => Some("svc-archive"),
+                _ => None,
+            }
+            .map(String::from)
+        };
+        let env = FileConfig::from_vars(vars).unwrap();
+
+        assert!(matches!(env.codec, Some(CodecArg::JpegLs)));
+        assert!(env.mode.is_none());
+        assert_eq!(env.batch.jobs, Some(16));
+        assert_eq!(env.batch.recursive, Some(true));
+        assert_eq!(env.batch.output_dir, Some(PathBuf::from("/data/out")));
+        assert_eq!(env.limits.max_fragments, Some(4096));
+        assert_eq!(env.limits.max_memory_bytes, Some(1 << 30));
+        assert_eq!(env.audit.actor.as_deref(), Some("svc-archive"));
+        assert_eq!(
+            env.policy.lossless_modalities,
+            Some(vec![Modality::CT, Modality::MG])
+        );
+        assert_eq!(
+            env.policy.max_ratios,
+            Some([(Modality::CT, 10.0), (Modality::CR, 20.0)].into_iter().collect())
+        );
+        assert_eq!(
+            env.policy.lossless_sop_classes,
+            Some(vec!["1.2.3".to_string(), "1.2.4".to_string()])
+        );
+
+        // Environment values sit above the file
+        let file = FileConfig::from_toml("codec = \"jpeg2000\"\nmode = \"lossy\"").unwrap();
+        let merged = file.overlay(env);
+        assert!(matches!(merged.codec, Some(CodecArg::JpegLs)));
+        assert!(matches!(merged.mode, Some(ModeArg::Lossy)));
+    }
+
+    #[test]
+    fn test_env_invalid_value() {
+        let err = FileConfig::from_vars(|name| {
+            (name == "MEDIMG_JOBS").then(|| "many".to_string())
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("MEDIMG_JOBS"));
+    }
+
+    #[test]
+    fn test_unknown_fields_reported() {
+        let file = ConfigFile::from_toml(
+            "codex = \"jpeg2000\"\n[batch]\njobs = 2\nthreads = 4\n[profiles.a]\nmod = \"lossy\"\n\
+             [policy.max_ratios]\nmri = 5",
+        )
+        .unwrap();
+        assert_eq!(file.unknown_fields, ["batch.threads", "codex", "profiles.a.mod"]);
+        assert_eq!(file.settings.batch.jobs, Some(2));
+
+        // Known keys with the wrong type are still errors
+        let result = FileConfig::from_toml("codec = 5");
+        assert!(matches!(result, Err(MedImgError::Config(_))));
+    }
+
+    #[test]
+    fn test_config_versions() {
+        let legacy = ConfigFile::from_toml("codec = \"jpeg-ls\"").unwrap();
+        assert_eq!(legacy.source_version, 0);
+        assert_eq!(legacy.version, CONFIG_VERSION);
+        assert!(legacy.unknown_fields.is_empty());
+
+        let newer = ConfigFile::from_toml(&format!("version = {}", CONFIG_VERSION + 1));
+        assert!(newer.unwrap_err().to_string().contains("newer"));
+        assert!(ConfigFile::from_toml("version = \"1\"").is_err());
+    }
+
+    #[test]
+    fn test_save_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("medimg.toml");
+        let mut file = ConfigFile::from_toml(
+            "codec = \"jpeg-ls\"\nratio = 12.5\n[policy]\nlossless_modalities = [\"CT\"]\n\
+             [profiles.fast]\nmode = \"lossless\"",
+        )
+        .unwrap();
+        file.settings.batch.jobs = Some(3);
+        file.save(&path).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with(&format!("version = {}", CONFIG_VERSION)));
+        let loaded = ConfigFile::load(&path).unwrap();
+        assert_eq!(loaded.source_version, CONFIG_VERSION);
+        assert!(loaded.unknown_fields.is_empty());
+        assert!(matches!(loaded.settings.codec, Some(CodecArg::JpegLs)));
+        assert_eq!(loaded.settings.ratio, Some(12.5));
+        assert_eq!(loaded.settings.batch.jobs, Some(3));
+        assert_eq!(loaded.settings.policy.lossless_modalities, Some(vec![Modality::CT]));
+        assert_eq!(loaded.settings.profile_names(), vec!["fast"]);
+    }
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("medimg.yaml");
+        std::fs::write(
+            &path,
+            "codec: jpeg-ls\nratio: 12.5\nbatch:\n  jobs: 3\n  threads: 4\npolicy:\n  \
+             lossless_modalities: [CT]\n  max_ratios: {mri: 5.0}\nprofiles:\n  fast:\n    mode: lossless\n",
+        )
+        .unwrap();
+
+        // Unversioned YAML is migrated and checked like TOML
+        let file = ConfigFile::load(&path).unwrap();
+        assert_eq!(file.source_version, 0);
+        assert_eq!(file.unknown_fields, ["batch.threads"]);
+        assert!(matches!(file.settings.codec, Some(CodecArg::JpegLs)));
+        assert_eq!(file.settings.batch.jobs, Some(3));
+        assert_eq!(file.settings.policy.max_ratios.as_ref().unwrap()[&Modality::MR], 5.0);
+        assert!(ConfigFile::from_yaml(&format!("version: {}", CONFIG_VERSION + 1)).is_err());
+        assert!(matches!(ConfigFile::from_yaml("codec: 5"), Err(MedImgError::Config(_))));
+
+        let saved = dir.path().join("saved.yml");
+        file.save(&saved).unwrap();
+        let text = std::fs::read_to_string(&saved).unwrap();
+        assert!(text.contains(&format!("version: {}", CONFIG_VERSION)), "{}", text);
+        assert!(!text.contains("null"), "{}", text);
+        let loaded = ConfigFile::load(&saved).unwrap();
+        assert_eq!(loaded.source_version, CONFIG_VERSION);
+        assert!(loaded.unknown_fields.is_empty());
+        assert_eq!(loaded.settings.ratio, Some(12.5));
+        assert_eq!(loaded.settings.policy.lossless_modalities, Some(vec![Modality::CT]));
+        assert_eq!(loaded.settings.profile_names(), vec!["fast"]);
+        assert_eq!(loaded.to_toml().unwrap(), file.to_toml().unwrap());
+    }
+
+    #[test]
+    fn test_discover_explicit_path() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("custom.toml");
+        std::fs::write(&path, "[batch]\njobs = 2\n").unwrap();
+
+        let (found, config) = FileConfig::discover(Some(&path)).unwrap().unwrap();
+        assert_eq!(found, path);
+        assert_eq!(config.batch.jobs, Some(2));
+
+        let missing = dir.path().join("missing.toml");
+        assert!(FileConfig::discover(Some(&missing)).is_err());
+    }
+}
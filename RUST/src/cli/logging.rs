@@ -0,0 +1,215 @@
+//! Log setup: console output plus an optional structured log file.
+//!
+//! The console keeps the verbosity chosen by `--verbose`/`--quiet`; the log
+//! file always records debug-level output (or `MEDIMG_LOG_FILE_LEVEL`), so
+//! unattended runs keep full diagnostics without cluttering progress output.
+//! Panics are written to the log file with a backtrace.
+
+use std::fs::OpenOptions;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::error::{MedImgError, Result};
+
+/// Environment variable overriding the log file filter.
+const FILE_FILTER_ENV: &str = "MEDIMG_LOG_FILE_LEVEL";
+
+/// Log file record format.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One human-readable line per record
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+/// Forwards each record to the console and file loggers.
+struct TeeLogger {
+    console: Option<env_logger::Logger>,
+    file: Option<env_logger::Logger>,
+}
+
+impl Log for TeeLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.loggers().any(|logger| logger.enabled(metadata))
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        for logger in self.loggers() {
+            if logger.matches(record) {
+                logger.log(record);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        for logger in self.loggers() {
+            logger.flush();
+        }
+    }
+}
+
+impl TeeLogger {
+    fn loggers(&self) -> impl Iterator<Item = &env_logger::Logger> {
+        self.console.iter().chain(self.file.iter())
+    }
+}
+
+/// Install the global logger.
+///
+/// `console_level` is the default console filter (`RUST_LOG` overrides it);
+/// `None` disables console logging.
+pub(super) fn init(
+    console_level: Option<&str>,
+    log_file: Option<&Path>,
+    format: LogFormat,
+) -> Result<()> {
+    let console = console_level.map(|level| {
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level)).build()
+    });
+    let file = log_file.map(|path| file_logger(path, format)).transpose()?;
+
+    let logger = TeeLogger { console, file };
+    let max_level = logger
+        .loggers()
+        .map(|l| l.filter())
+        .max()
+        .unwrap_or(LevelFilter::Off);
+
+    // A logger may already be installed (e.g. when embedding the CLI)
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(max_level);
+        if log_file.is_some() {
+            log_panics();
+        }
+    }
+    Ok(())
+}
+
+/// Build a logger that appends to `path`.
+fn file_logger(path: &Path, format: LogFormat) -> Result<env_logger::Logger> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| {
+            MedImgError::Config(format!("Cannot open log file {}: {}", path.display(), e))
+        })?;
+
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::new().filter_or(FILE_FILTER_ENV, "debug"));
+    builder
+        .target(env_logger::Target::Pipe(Box::new(LineWriter::new(file))))
+        .write_style(env_logger::WriteStyle::Never)
+        .format_timestamp_millis();
+
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let line = serde_json::json!({
+                "timestamp": buf.timestamp_millis().to_string(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+                "file": record.file(),
+                "line": record.line(),
+            });
+            writeln!(buf, "{}", line)
+        });
+    }
+
+    Ok(builder.build())
+}
+
+/// Log panics (with a backtrace) before the default hook prints them.
+fn log_panics() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let backtrace = std::backtrace::Backtrace::force_capture();
+        log::error!(target: "medimg::panic", "{}\n{}", info, backtrace);
+        log::logger().flush();
+        default_hook(info);
+    }));
+}
+
+/// Causes of `error` from its source chain, outermost first.
+///
+/// Causes whose message is already part of the previous message are
+/// skipped, since most errors include their immediate source's text.
+pub fn error_causes(error: &MedImgError) -> Vec<String> {
+    let mut causes = Vec::new();
+    let mut previous = error.to_string();
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        let message = cause.to_string();
+        if !previous.contains(&message) {
+            causes.push(message.clone());
+        }
+        previous = message;
+        source = cause.source();
+    }
+    causes
+}
+
+/// Log a command error with its source chain.
+///
+/// Logged at debug level: the console already shows the error itself.
+pub(super) fn log_error(error: &MedImgError) {
+    let mut message = error.to_string();
+    for cause in error_causes(error) {
+        message.push_str(&format!("\n  caused by: {}", cause));
+    }
+    log::debug!(target: "medimg::error", "Command failed: {}", message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_error_causes() {
+        #[derive(Debug)]
+        struct Truncated(std::io::Error);
+        impl std::fmt::Display for Truncated {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("truncated dataset")
+            }
+        }
+        impl std::error::Error for Truncated {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let error = MedImgError::dicom("Failed to read DICOM file", Truncated(std::io::Error::other("disk gone")))
+            .with_file("/data/1.dcm");
+        // Causes already in the message are not repeated
+        assert_eq!(error_causes(&error), ["disk gone"]);
+        assert!(error_causes(&MedImgError::Config("bad".into())).is_empty());
+    }
+
+    #[test]
+    fn test_json_log_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("run.jsonl");
+        let logger = file_logger(&path, LogFormat::Json).unwrap();
+
+        let record = Record::builder()
+            .args(format_args!("Compressed a.dcm"))
+            .level(log::Level::Debug)
+            .target("medimg_compress::pipeline")
+            .build();
+        assert!(logger.matches(&record));
+        logger.log(&record);
+        logger.flush();
+
+        let line: serde_json::Value =
+            serde_json::from_str(std::fs::read_to_string(&path).unwrap().trim()).unwrap();
+        assert_eq!(line["level"], "DEBUG");
+        assert_eq!(line["message"], "Compressed a.dcm");
+    }
+}
@@ -1,12 +1,51 @@
 //! Command-line interface for the medical image compression tool.
 
 use clap::{Parser, Subcommand, ValueEnum};
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "catalog")]
+use std::sync::Arc;
 
-use crate::config::{CompressionCodec, CompressionConfig, CompressionMode, QualityPreset};
-use crate::dicom::DicomFile;
-use crate::error::Result;
-use crate::pipeline::{CompressionPipeline, CompressionResult};
+use crate::anonymize::{Anonymizer, DeidentificationProfile};
+use crate::audit::access::{self, AccessLog, JsonLinesSink};
+use crate::batch::{BatchProcessor, FolderWatcher, ManifestSummary};
+#[cfg(feature = "catalog")]
+use crate::catalog::{Catalog, CatalogEntry, CatalogQuery};
+#[cfg(feature = "catalog")]
+use crate::config::Modality;
+use crate::config::{
+    CompressionCodec, CompressionConfig, CompressionMode, CompressionOverrides, Jpeg2000Encoder,
+    JpegLsThresholds, OverrideReason, ProgressionOrder, QualityPreset, RegulatoryProfile,
+};
+use crate::codec::bench::{default_scenarios, synthetic_corpus, BenchResult, CodecBenchmark};
+use crate::dicom::{DicomFile, SecondaryCapture};
+use crate::error::{MedImgError, Result};
+use crate::export::{PreviewOptions, Window};
+use crate::import::RawLayout;
+use crate::memory::MemoryAccountant;
+use crate::metrics::{ImageComparator, QualityReport};
+#[cfg(feature = "prometheus")]
+use crate::monitoring::OperationalMetrics;
+#[cfg(feature = "prometheus")]
+use crate::progress::CancellationToken;
+use crate::net::dimse::{StoreScp, StoreScu};
+use crate::pipeline::{BatchStats, CompressionPipeline, CompressionResult};
+use crate::pixel::ResampleFilter;
+use crate::progress::{NullProgress, ProgressHandler, TerminalProgress};
+use crate::queue::{BrokerConfig, QueueWorker};
+use crate::server::CompressionServer;
+use crate::storage;
+use crate::testing::SelfTestCase;
+use crate::Rect;
+
+mod config;
+mod logging;
+mod signal;
+
+pub use config::{ConfigFile, FileConfig, QualityPresetSection, CONFIG_VERSION};
+pub use logging::{error_causes, LogFormat};
 
 /// Medical Image Compression Tool
 ///
@@ -23,239 +62,3376 @@ pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
 
-    /// Enable verbose output
-    #[arg(short, long, global = true)]
-    pub verbose: bool,
+    /// Enable verbose output
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// Suppress all output except errors
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// Disable progress bars (also off with --quiet or when stdout is not a terminal)
+    #[arg(long, global = true)]
+    pub no_progress: bool,
+
+    /// Output format for command results
+    #[arg(long, value_enum, global = true, default_value = "text")]
+    pub format: OutputFormat,
+
+    /// Configuration file (defaults to ./medimg.toml, then the XDG config dir)
+    #[arg(long, global = true, env = "MEDIMG_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Named profile from the configuration file
+    #[arg(long, global = true, env = "MEDIMG_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Append full debug logs to this file (independent of --verbose/--quiet)
+    #[arg(long, global = true, env = "MEDIMG_LOG_FILE")]
+    pub log_file: Option<PathBuf>,
+
+    /// Log file record format
+    #[arg(long, value_enum, global = true, default_value = "text")]
+    pub log_format: LogFormat,
+
+    /// Regulatory profile for lossless-only modalities and lossy ratio caps [default: fda-us]
+    #[arg(long, value_enum, global = true)]
+    pub regulatory_profile: Option<RegulatoryArg>,
+
+    /// Export pipeline and batch spans to this OTLP/gRPC collector (e.g. http://otel-collector:4317)
+    #[cfg(feature = "otlp")]
+    #[arg(long, global = true, env = "OTEL_EXPORTER_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+
+    /// Service name spans are reported under
+    #[cfg(feature = "otlp")]
+    #[arg(long, global = true, env = "OTEL_SERVICE_NAME", default_value = crate::telemetry::DEFAULT_SERVICE_NAME)]
+    pub otlp_service_name: String,
+}
+
+/// Output format argument.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable text
+    Text,
+    /// Structured JSON on stdout (printed even with --quiet)
+    Json,
+}
+
+/// CLI subcommands.
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Compress a DICOM file
+    Compress {
+        /// Input DICOM file path ('-' for stdin)
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output file path ('-' for stdout; optional for analysis mode)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Compression codec to use [default: jpeg2000]
+        #[arg(short, long, value_enum)]
+        codec: Option<CodecArg>,
+
+        /// External encoder plugin executable, used instead of the
+        /// built-in codec (--codec still selects the parameter rules)
+        #[arg(long, value_name = "PATH")]
+        encoder_plugin: Option<PathBuf>,
+
+        /// Compression mode [default: lossless]
+        #[arg(short, long, value_enum)]
+        mode: Option<ModeArg>,
+
+        /// Quality preset (for lossy compression): diagnostic, high-quality, standard,
+        /// preview, or a [quality_presets] name from the config file [default: diagnostic]
+        #[arg(short = 'Q', long)]
+        quality: Option<QualityName>,
+
+        /// Target compression ratio (for lossy mode)
+        #[arg(short = 'r', long)]
+        ratio: Option<f32>,
+
+        /// Target bits per pixel (for lossy mode; alternative to --ratio)
+        #[arg(long, conflicts_with = "ratio")]
+        bpp: Option<f32>,
+
+        /// Verify lossless compression by round-trip decode
+        #[arg(long)]
+        verify: bool,
+
+        /// Dry run - analyze without writing output
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Codec tuning (--j2k-*, --jls-*)
+        #[command(flatten)]
+        tuning: CodecTuningArgs,
+
+        /// Modality safety override (--force, --reason, --operator, --yes)
+        #[command(flatten)]
+        safety: SafetyOverrideArgs,
+    },
+
+    /// Compress a directory of DICOM files
+    Batch {
+        /// Input directory, or a storage URL (s3://, gs://, or az://)
+        #[arg(short, long)]
+        input_dir: PathBuf,
+
+        /// Output directory, or a storage URL (s3://, gs://, or az://)
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+
+        /// Compression codec to use [default: jpeg2000]
+        #[arg(short, long, value_enum)]
+        codec: Option<CodecArg>,
+
+        /// External encoder plugin executable, used instead of the
+        /// built-in codec (--codec still selects the parameter rules)
+        #[arg(long, value_name = "PATH")]
+        encoder_plugin: Option<PathBuf>,
+
+        /// Compression mode [default: lossless]
+        #[arg(short, long, value_enum)]
+        mode: Option<ModeArg>,
+
+        /// Quality preset (for lossy compression): diagnostic, high-quality, standard,
+        /// preview, or a [quality_presets] name from the config file [default: diagnostic]
+        #[arg(short = 'Q', long)]
+        quality: Option<QualityName>,
+
+        /// Target compression ratio (for lossy mode)
+        #[arg(short = 'r', long)]
+        ratio: Option<f32>,
+
+        /// Target bits per pixel (for lossy mode; alternative to --ratio)
+        #[arg(long, conflicts_with = "ratio")]
+        bpp: Option<f32>,
+
+        /// Scan subdirectories recursively
+        #[arg(short = 'R', long)]
+        recursive: bool,
+
+        /// Maximum parallel jobs (defaults to CPU count)
+        #[arg(short = 'j', long)]
+        jobs: Option<usize>,
+
+        /// Minimum SSIM per file (enables quality gating)
+        #[arg(long)]
+        min_ssim: Option<f64>,
+
+        /// Retries for files that fail with a transient I/O error (default 2)
+        #[arg(long)]
+        retries: Option<u32>,
+
+        /// Output file name template ({stem}, {ext}, {codec})
+        #[arg(long)]
+        output_template: Option<String>,
+
+        /// Append a JSONL record per file to this manifest
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+
+        /// Record every file in this SQLite catalog (created if needed)
+        #[cfg(feature = "catalog")]
+        #[arg(long)]
+        catalog: Option<PathBuf>,
+
+        /// Codec tuning (--j2k-*, --jls-*)
+        #[command(flatten)]
+        tuning: CodecTuningArgs,
+
+        /// Modality safety override (--force, --reason, --operator, --yes)
+        #[command(flatten)]
+        safety: SafetyOverrideArgs,
+
+        /// Operational metrics (--metrics-listen, --pushgateway)
+        #[cfg(feature = "prometheus")]
+        #[command(flatten)]
+        metrics: MetricsArgs,
+    },
+
+    /// Watch a directory and compress DICOM files as they arrive
+    Watch {
+        /// Directory to watch
+        #[arg(short, long)]
+        input_dir: PathBuf,
+
+        /// Output directory
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+
+        /// Compression codec to use [default: jpeg2000]
+        #[arg(short, long, value_enum)]
+        codec: Option<CodecArg>,
+
+        /// Compression mode [default: lossless]
+        #[arg(short, long, value_enum)]
+        mode: Option<ModeArg>,
+
+        /// Quality preset (for lossy compression): diagnostic, high-quality, standard,
+        /// preview, or a [quality_presets] name from the config file [default: diagnostic]
+        #[arg(short = 'Q', long)]
+        quality: Option<QualityName>,
+
+        /// Target compression ratio (for lossy mode)
+        #[arg(short = 'r', long)]
+        ratio: Option<f32>,
+
+        /// Target bits per pixel (for lossy mode; alternative to --ratio)
+        #[arg(long, conflicts_with = "ratio")]
+        bpp: Option<f32>,
+
+        /// Watch subdirectories recursively
+        #[arg(short = 'R', long)]
+        recursive: bool,
+
+        /// Maximum parallel jobs (defaults to CPU count)
+        #[arg(short = 'j', long)]
+        jobs: Option<usize>,
+
+        /// Seconds between directory scans
+        #[arg(long, default_value = "5")]
+        poll_interval: u64,
+
+        /// Seconds between summary reports
+        #[arg(long, default_value = "60")]
+        report_interval: u64,
+
+        /// Append a JSONL record per file to this manifest
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+
+        /// Record every file in this SQLite catalog (created if needed)
+        #[cfg(feature = "catalog")]
+        #[arg(long)]
+        catalog: Option<PathBuf>,
+
+        /// Codec tuning (--j2k-*, --jls-*)
+        #[command(flatten)]
+        tuning: CodecTuningArgs,
+
+        /// Modality safety override (--force, --reason, --operator, --yes)
+        #[command(flatten)]
+        safety: SafetyOverrideArgs,
+
+        /// Operational metrics (--metrics-listen, --pushgateway)
+        #[cfg(feature = "prometheus")]
+        #[command(flatten)]
+        metrics: MetricsArgs,
+    },
+
+    /// Summarize a batch manifest or catalog
+    #[command(group(clap::ArgGroup::new("source").required(true)))]
+    Report {
+        /// JSONL manifest written by batch or watch --manifest
+        #[arg(long, group = "source")]
+        manifest: Option<PathBuf>,
+
+        /// SQLite catalog written by batch or watch --catalog
+        #[cfg(feature = "catalog")]
+        #[arg(long, group = "source")]
+        catalog: Option<PathBuf>,
+
+        /// Only catalog entries for this SOP Instance UID
+        #[cfg(feature = "catalog")]
+        #[arg(long, conflicts_with = "manifest")]
+        sop_uid: Option<String>,
+
+        /// Only catalog entries of this modality (e.g. CT)
+        #[cfg(feature = "catalog")]
+        #[arg(long, conflicts_with = "manifest")]
+        modality: Option<String>,
+
+        /// Only catalog entries recorded in the last N days
+        #[cfg(feature = "catalog")]
+        #[arg(long, conflicts_with = "manifest")]
+        days: Option<u64>,
+
+        /// List the matching catalog entries instead of summarizing them
+        #[cfg(feature = "catalog")]
+        #[arg(long, conflicts_with_all = ["manifest", "report_format"])]
+        entries: bool,
+
+        /// Render as CSV or HTML instead of --format text/json
+        #[arg(long, value_enum)]
+        report_format: Option<ReportFormatArg>,
+
+        /// Write the report to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Serve compression over HTTP (POST /compress, POST /analyze, GET /health),
+    /// gRPC (--grpc), or as a job API with a persistent queue (--state-dir)
+    Serve {
+        /// Address to listen on (use 0.0.0.0:PORT to accept remote clients)
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// Default compression codec [default: jpeg2000]
+        #[arg(short, long, value_enum)]
+        codec: Option<CodecArg>,
+
+        /// Default compression mode [default: lossless]
+        #[arg(short, long, value_enum)]
+        mode: Option<ModeArg>,
+
+        /// Default quality preset (for lossy compression): diagnostic, high-quality,
+        /// standard, preview, or a [quality_presets] name [default: diagnostic]
+        #[arg(short = 'Q', long)]
+        quality: Option<QualityName>,
+
+        /// Default target compression ratio (for lossy mode)
+        #[arg(short = 'r', long)]
+        ratio: Option<f32>,
+
+        /// Default target bits per pixel (for lossy mode; alternative to --ratio)
+        #[arg(long, conflicts_with = "ratio")]
+        bpp: Option<f32>,
+
+        /// Codec tuning (--j2k-*, --jls-*)
+        #[command(flatten)]
+        tuning: CodecTuningArgs,
+
+        /// Largest accepted upload in MiB
+        #[arg(long, default_value = "256")]
+        max_body_mb: usize,
+
+        /// Most HTTP connections served at once
+        #[arg(long, default_value_t = crate::server::DEFAULT_MAX_CONNECTIONS)]
+        max_connections: usize,
+
+        /// Serve the gRPC service (proto/medimg_compress.proto) instead of HTTP
+        #[cfg(feature = "grpc")]
+        #[arg(long)]
+        grpc: bool,
+
+        /// Serve the job API (POST /jobs, GET /jobs/{id}, ...), keeping jobs in
+        /// this directory
+        #[cfg(feature = "rest")]
+        #[arg(long)]
+        state_dir: Option<PathBuf>,
+
+        /// Accept job submissions for files under this directory (otherwise
+        /// only uploads)
+        #[cfg(feature = "rest")]
+        #[arg(long, requires = "state_dir")]
+        input_root: Option<PathBuf>,
+
+        /// Number of jobs processed at once [default: number of CPUs]
+        #[cfg(feature = "rest")]
+        #[arg(long, requires = "state_dir")]
+        workers: Option<usize>,
+
+        /// Serve operational metrics at GET /metrics (Prometheus text format)
+        #[cfg(feature = "prometheus")]
+        #[arg(long)]
+        metrics: bool,
+    },
+
+    /// Receive instances over DICOM (C-STORE), compress, and write or forward them
+    Scp {
+        /// AE title to answer to
+        #[arg(long, default_value = "COMPRESS")]
+        aet: String,
+
+        /// Port to listen on
+        #[arg(short, long, default_value = "11112")]
+        port: u16,
+
+        /// Address to listen on
+        #[arg(long, default_value = "0.0.0.0")]
+        listen: String,
+
+        /// Write compressed instances to this directory
+        #[arg(short, long, required_unless_present = "forward")]
+        output_dir: Option<PathBuf>,
+
+        /// Forward compressed instances to HOST:PORT
+        #[arg(long)]
+        forward: Option<String>,
+
+        /// Called AE title of the forward destination
+        #[arg(long, default_value = "ANY-SCP", requires = "forward")]
+        forward_aet: String,
+
+        /// Compression codec [default: jpeg2000]
+        #[arg(short, long, value_enum)]
+        codec: Option<CodecArg>,
+
+        /// Compression mode [default: lossless]
+        #[arg(short, long, value_enum)]
+        mode: Option<ModeArg>,
+
+        /// Quality preset (for lossy compression): diagnostic, high-quality,
+        /// standard, preview, or a [quality_presets] name [default: diagnostic]
+        #[arg(short = 'Q', long)]
+        quality: Option<QualityName>,
+
+        /// Target compression ratio (for lossy mode)
+        #[arg(short = 'r', long)]
+        ratio: Option<f32>,
+
+        /// Target bits per pixel (for lossy mode; alternative to --ratio)
+        #[arg(long, conflicts_with = "ratio")]
+        bpp: Option<f32>,
+
+        /// Codec tuning (--j2k-*, --jls-*)
+        #[command(flatten)]
+        tuning: CodecTuningArgs,
+    },
+
+    /// Consume compression tasks from a message queue (AMQP, NATS, or Kafka)
+    /// and publish result events
+    Worker {
+        /// Broker URL: amqp://..., nats://..., or kafka://host:port[,host:port]
+        #[arg(long, env = "MEDIMG_BROKER")]
+        broker: String,
+
+        /// Queue, subject, or topic to read tasks from
+        #[arg(long, default_value = crate::queue::DEFAULT_TASKS)]
+        tasks: String,
+
+        /// Queue, subject, or topic to publish events to
+        #[arg(long, default_value = crate::queue::DEFAULT_EVENTS)]
+        events: String,
+
+        /// Consumer group shared by the workers (NATS queue group, Kafka group id)
+        #[arg(long, default_value = crate::queue::DEFAULT_GROUP)]
+        group: String,
+
+        /// Leave quality metrics out of the events (skips decoding each result)
+        #[arg(long)]
+        no_quality: bool,
+
+        /// Default compression codec [default: jpeg2000]
+        #[arg(short, long, value_enum)]
+        codec: Option<CodecArg>,
+
+        /// Default compression mode [default: lossless]
+        #[arg(short, long, value_enum)]
+        mode: Option<ModeArg>,
+
+        /// Default quality preset (for lossy compression): diagnostic, high-quality,
+        /// standard, preview, or a [quality_presets] name [default: diagnostic]
+        #[arg(short = 'Q', long)]
+        quality: Option<QualityName>,
+
+        /// Default target compression ratio (for lossy mode)
+        #[arg(short = 'r', long)]
+        ratio: Option<f32>,
+
+        /// Default target bits per pixel (for lossy mode; alternative to --ratio)
+        #[arg(long, conflicts_with = "ratio")]
+        bpp: Option<f32>,
+
+        /// Codec tuning (--j2k-*, --jls-*)
+        #[command(flatten)]
+        tuning: CodecTuningArgs,
+
+        /// Operational metrics (--metrics-listen, --pushgateway)
+        #[cfg(feature = "prometheus")]
+        #[command(flatten)]
+        metrics: MetricsArgs,
+    },
+
+    /// Project compressed archive size per codec and mode without encoding
+    Estimate {
+        /// Input directory
+        #[arg(short, long)]
+        input_dir: PathBuf,
+
+        /// Scan subdirectories recursively
+        #[arg(short = 'R', long)]
+        recursive: bool,
+
+        /// Target compression ratio for the lossy projection [default: 10]
+        #[arg(short = 'r', long)]
+        ratio: Option<f32>,
+
+        /// Error tolerance for the near-lossless projection [default: 2]
+        #[arg(long)]
+        near: Option<u16>,
+    },
+
+    /// Benchmark codec speed, ratio, and verification per codec and mode
+    Bench {
+        /// Directory of DICOM files to benchmark (defaults to a synthetic corpus)
+        #[arg(long)]
+        corpus: Option<PathBuf>,
+
+        /// Scan corpus subdirectories recursively
+        #[arg(short = 'R', long)]
+        recursive: bool,
+
+        /// Timed encode/decode repetitions per image
+        #[arg(long, default_value = "3")]
+        iterations: u32,
+
+        /// Target compression ratio for the lossy scenario [default: 10]
+        #[arg(short = 'r', long)]
+        ratio: Option<f32>,
+
+        /// Error tolerance for the near-lossless scenario [default: 2]
+        #[arg(long)]
+        near: Option<u16>,
+    },
+
+    /// Round-trip synthetic phantoms through every codec and mode and
+    /// verify the reconstruction
+    Selftest {
+        /// Width and height of the phantoms in pixels
+        #[arg(long, default_value = "256")]
+        size: u32,
+
+        /// Target compression ratio for the lossy scenario [default: 10]
+        #[arg(short = 'r', long)]
+        ratio: Option<f32>,
+
+        /// Error tolerance for the near-lossless scenario [default: 2]
+        #[arg(long)]
+        near: Option<u16>,
+    },
+
+    /// Convert a DICOM file to another transfer syntax
+    Transcode {
+        /// Input DICOM file path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output DICOM file path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Target transfer syntax
+        #[arg(long, value_enum)]
+        to: TargetArg,
+
+        /// Target compression ratio (for lossy targets)
+        #[arg(short = 'r', long)]
+        ratio: Option<f32>,
+
+        /// Near-lossless error tolerance (jpegls-near-lossless only, 1-255)
+        #[arg(long, default_value = "2")]
+        near: u16,
+
+        /// Verify lossless targets by round-trip decode
+        #[arg(long)]
+        verify: bool,
+
+        /// Modality safety override (--force, --reason, --operator, --yes)
+        #[command(flatten)]
+        safety: SafetyOverrideArgs,
+    },
+
+    /// Compare a compressed DICOM file against its original
+    Compare {
+        /// Original (reference) DICOM file
+        #[arg(long)]
+        original: PathBuf,
+
+        /// DICOM file to check against the original
+        #[arg(long)]
+        test: PathBuf,
+
+        /// Only compare this region: X,Y,WIDTH,HEIGHT in pixels
+        #[arg(long, value_name = "X,Y,W,H")]
+        region: Option<Rect>,
+
+        /// Print the report as JSON (same as --format json)
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Prove a compressed DICOM file decodes to the original pixels
+    Verify {
+        /// Original (reference) DICOM file
+        #[arg(long)]
+        original: PathBuf,
+
+        /// Compressed DICOM file to decode and check
+        #[arg(long)]
+        compressed: PathBuf,
+    },
+
+    /// Remove patient identifiers from a DICOM file
+    Anonymize {
+        /// Input DICOM file path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output DICOM file path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// De-identification profile (--profile selects a config profile)
+        #[arg(long, value_enum, default_value = "basic")]
+        deid_profile: DeidProfileArg,
+
+        /// Also compress the de-identified image
+        #[arg(long)]
+        compress: bool,
+
+        /// Compression codec (with --compress) [default: jpeg2000]
+        #[arg(short, long, value_enum)]
+        codec: Option<CodecArg>,
+
+        /// Compression mode (with --compress) [default: lossless]
+        #[arg(short, long, value_enum)]
+        mode: Option<ModeArg>,
+
+        /// Modality safety override (--force, --reason, --operator, --yes)
+        #[command(flatten)]
+        safety: SafetyOverrideArgs,
+    },
+
+    /// Wrap a PNG, TIFF, or raw image in a compressed Secondary Capture
+    /// DICOM file
+    Import {
+        /// Input image path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output DICOM file path
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Patient ID
+        #[arg(long, default_value = "")]
+        patient_id: String,
+
+        /// Patient's Name (FAMILY^GIVEN)
+        #[arg(long, default_value = "")]
+        patient_name: String,
+
+        /// Patient's Birth Date (YYYYMMDD)
+        #[arg(long, default_value = "")]
+        birth_date: String,
+
+        /// Patient's Sex (M, F, or O)
+        #[arg(long, default_value = "")]
+        sex: String,
+
+        /// Accession Number
+        #[arg(long, default_value = "")]
+        accession_number: String,
+
+        /// Study Description
+        #[arg(long)]
+        study_description: Option<String>,
+
+        /// Modality
+        #[arg(long, default_value = "OT")]
+        modality: String,
+
+        /// Conversion Type (WSD workstation, SD scanned document, DV video, ...)
+        #[arg(long, default_value = "WSD")]
+        conversion_type: String,
+
+        /// Raw pixel layout (input has no header)
+        #[command(flatten)]
+        raw: RawArgs,
+
+        /// Compression codec [default: jpeg2000]
+        #[arg(short, long, value_enum)]
+        codec: Option<CodecArg>,
+
+        /// Compression mode [default: lossless]
+        #[arg(short, long, value_enum)]
+        mode: Option<ModeArg>,
+
+        /// Modality safety override (--force, --reason, --operator, --yes)
+        #[command(flatten)]
+        safety: SafetyOverrideArgs,
+    },
+
+    /// Convert a NIfTI volume to one Secondary Capture DICOM file per slice
+    #[cfg(feature = "nifti")]
+    Nifti {
+        /// Input volume (.nii, .nii.gz, or .hdr of a .hdr/.img pair)
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output directory for slice_NNNN.dcm files
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Patient ID of the converted study
+        #[arg(long, default_value = "")]
+        patient_id: String,
+
+        /// Patient's Name of the converted study (FAMILY^GIVEN)
+        #[arg(long, default_value = "")]
+        patient_name: String,
+
+        /// Modality of the converted series
+        #[arg(long, default_value = "OT")]
+        modality: String,
+
+        /// Also compress each slice
+        #[arg(long)]
+        compress: bool,
+
+        /// Compression codec (with --compress) [default: jpeg2000]
+        #[arg(short, long, value_enum)]
+        codec: Option<CodecArg>,
+
+        /// Compression mode (with --compress) [default: lossless]
+        #[arg(short, long, value_enum)]
+        mode: Option<ModeArg>,
+
+        /// Modality safety override (--force, --reason, --operator, --yes)
+        #[command(flatten)]
+        safety: SafetyOverrideArgs,
+    },
+
+    /// Print dataset elements of a DICOM file
+    Dump {
+        /// Input DICOM file path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Only print this tag (gggg,eeee or keyword); repeatable
+        #[arg(long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+    },
+
+    /// Write a PNG (8-bit) or TIFF (16-bit) preview of a DICOM image
+    Export {
+        /// Input DICOM file path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output image path (.png or .tiff)
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Zero-based frame index
+        #[arg(long, default_value = "0")]
+        frame: u32,
+
+        /// Window: auto (dataset window or min/max), minmax, or CENTER,WIDTH
+        #[arg(long, default_value = "auto", allow_hyphen_values = true)]
+        window: Window,
+
+        /// Downsample so neither dimension exceeds this many pixels
+        #[arg(long, value_name = "PIXELS")]
+        max_size: Option<u32>,
+
+        /// Downsampling filter: box or lanczos
+        #[arg(long, default_value = "box")]
+        filter: ResampleFilter,
+    },
+
+    /// Show information about a DICOM file
+    Info {
+        /// Input DICOM file path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Show detailed metadata
+        #[arg(long)]
+        detailed: bool,
+    },
+
+    /// Analyze compression potential without modifying files
+    Analyze {
+        /// Input DICOM file path
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Codec to analyze [default: jpeg2000]
+        #[arg(short, long, value_enum)]
+        codec: Option<CodecArg>,
+
+        /// Test both lossless and lossy modes
+        #[arg(long)]
+        all_modes: bool,
+    },
+}
+
+/// Compression codec argument.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum CodecArg {
+    /// JPEG 2000 (recommended for most use cases)
+    Jpeg2000,
+    /// JPEG-LS (faster, good for simple images)
+    JpegLs,
+}
+
+impl From<CodecArg> for CompressionCodec {
+    fn from(arg: CodecArg) -> Self {
+        match arg {
+            CodecArg::Jpeg2000 => CompressionCodec::Jpeg2000,
+            CodecArg::JpegLs => CompressionCodec::JpegLs,
+        }
+    }
+}
+
+/// Regulatory profile argument.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum RegulatoryArg {
+    /// United States (FDA): lossless mammography, no ratio caps
+    FdaUs,
+    /// United Kingdom (Royal College of Radiologists)
+    RcrUk,
+    /// Europe (European Society of Radiology)
+    Esr,
+}
+
+impl From<RegulatoryArg> for RegulatoryProfile {
+    fn from(arg: RegulatoryArg) -> Self {
+        match arg {
+            RegulatoryArg::FdaUs => RegulatoryProfile::FdaUs,
+            RegulatoryArg::RcrUk => RegulatoryProfile::RcrUk,
+            RegulatoryArg::Esr => RegulatoryProfile::Esr,
+        }
+    }
+}
+
+/// JPEG 2000 progression order argument.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ProgressionArg {
+    /// Layer-resolution-component-position (quality progressive)
+    Lrcp,
+    /// Resolution-layer-component-position (resolution progressive)
+    Rlcp,
+    /// Resolution-position-component-layer
+    Rpcl,
+    /// Position-component-resolution-layer
+    Pcrl,
+    /// Component-position-resolution-layer
+    Cprl,
+}
+
+impl From<ProgressionArg> for ProgressionOrder {
+    fn from(arg: ProgressionArg) -> Self {
+        match arg {
+            ProgressionArg::Lrcp => ProgressionOrder::Lrcp,
+            ProgressionArg::Rlcp => ProgressionOrder::Rlcp,
+            ProgressionArg::Rpcl => ProgressionOrder::Rpcl,
+            ProgressionArg::Pcrl => ProgressionOrder::Pcrl,
+            ProgressionArg::Cprl => ProgressionOrder::Cprl,
+        }
+    }
+}
+
+/// JPEG 2000 encoder argument.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Jpeg2000EncoderArg {
+    /// OpenJPEG if available, otherwise the built-in encoder
+    Auto,
+    /// OpenJPEG (libopenjp2; requires the openjpeg feature)
+    Openjpeg,
+    /// Built-in encoder
+    Builtin,
+}
+
+impl From<Jpeg2000EncoderArg> for Jpeg2000Encoder {
+    fn from(arg: Jpeg2000EncoderArg) -> Self {
+        match arg {
+            Jpeg2000EncoderArg::Auto => Jpeg2000Encoder::Auto,
+            Jpeg2000EncoderArg::Openjpeg => Jpeg2000Encoder::OpenJpeg,
+            Jpeg2000EncoderArg::Builtin => Jpeg2000Encoder::Builtin,
+        }
+    }
+}
+
+/// Codec-specific tuning arguments.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct CodecTuningArgs {
+    /// JPEG 2000 tile size in pixels: 0 (single tile) or a power of two of at least 64
+    #[arg(long, help_heading = "Codec tuning")]
+    pub j2k_tile: Option<u32>,
+
+    /// JPEG 2000 wavelet decomposition levels (0-32) [default: 5]
+    #[arg(long, help_heading = "Codec tuning")]
+    pub j2k_levels: Option<u8>,
+
+    /// JPEG 2000 progression order [default: lrcp]
+    #[arg(long, help_heading = "Codec tuning", value_enum)]
+    pub j2k_progression: Option<ProgressionArg>,
+
+    /// JPEG 2000 quality layers [default: from quality preset]
+    #[arg(long, help_heading = "Codec tuning")]
+    pub j2k_layers: Option<u32>,
+
+    /// JPEG 2000 encoder implementation [default: auto]
+    #[arg(long, help_heading = "Codec tuning", value_enum)]
+    pub j2k_encoder: Option<Jpeg2000EncoderArg>,
+
+    /// JPEG-LS NEAR error tolerance in stored values (near-lossless mode, 1-255, at
+    /// most half the sample range) [default: 2]
+    #[arg(long, help_heading = "Codec tuning", visible_alias = "near")]
+    pub jls_near: Option<u16>,
+
+    /// JPEG-LS error tolerance in rescaled units (e.g. HU), converted per file with its
+    /// Rescale Slope
+    #[arg(long, help_heading = "Codec tuning", conflicts_with = "jls_near")]
+    pub jls_near_rescaled: Option<f32>,
+
+    /// JPEG-LS context threshold T1 (requires --jls-t2 and --jls-t3)
+    #[arg(long, help_heading = "Codec tuning", requires_all = ["jls_t2", "jls_t3"])]
+    pub jls_t1: Option<u16>,
+
+    /// JPEG-LS context threshold T2
+    #[arg(long, help_heading = "Codec tuning", requires_all = ["jls_t1", "jls_t3"])]
+    pub jls_t2: Option<u16>,
+
+    /// JPEG-LS context threshold T3
+    #[arg(long, help_heading = "Codec tuning", requires_all = ["jls_t1", "jls_t2"])]
+    pub jls_t3: Option<u16>,
+
+    /// JPEG-LS context reset interval [default: 64]
+    #[arg(long, help_heading = "Codec tuning")]
+    pub jls_reset: Option<u16>,
+}
+
+impl CodecTuningArgs {
+    /// Apply the tuning options to `config`.
+    ///
+    /// Options for a codec other than the selected one are rejected rather
+    /// than silently ignored.
+    fn apply(self, config: &mut CompressionConfig) -> Result<()> {
+        let j2k = self.j2k_tile.is_some()
+            || self.j2k_levels.is_some()
+            || self.j2k_progression.is_some()
+            || self.j2k_layers.is_some()
+            || self.j2k_encoder.is_some();
+        let jls = self.jls_near.is_some()
+            || self.jls_near_rescaled.is_some()
+            || self.jls_t1.is_some()
+            || self.jls_reset.is_some();
+
+        if j2k && config.codec != CompressionCodec::Jpeg2000 {
+            return Err(MedImgError::Config(
+                "--j2k-* options require --codec jpeg2000".into(),
+            ));
+        }
+        if jls && config.codec != CompressionCodec::JpegLs {
+            return Err(MedImgError::Config(
+                "--jls-* options require --codec jpeg-ls".into(),
+            ));
+        }
+
+        if let Some(tile) = self.j2k_tile {
+            config.tile_size = tile;
+        }
+        if let Some(levels) = self.j2k_levels {
+            config.decomposition_levels = levels;
+        }
+        if let Some(order) = self.j2k_progression {
+            config.progression_order = order.into();
+        }
+        if let Some(layers) = self.j2k_layers {
+            config.quality_layers = layers;
+        }
+        if let Some(encoder) = self.j2k_encoder {
+            config.jpeg2000_encoder = encoder.into();
+        }
+        if let Some(near) = self.jls_near {
+            config.near_lossless_error = near;
+            config.near_lossless_rescaled = None;
+        }
+        if let Some(tolerance) = self.jls_near_rescaled {
+            config.near_lossless_error = 0;
+            config.near_lossless_rescaled = Some(tolerance);
+        }
+        if self.jls_t1.is_some() || self.jls_reset.is_some() {
+            let defaults = JpegLsThresholds::default();
+            config.jpegls_thresholds = Some(JpegLsThresholds {
+                t1: self.jls_t1.unwrap_or(defaults.t1),
+                t2: self.jls_t2.unwrap_or(defaults.t2),
+                t3: self.jls_t3.unwrap_or(defaults.t3),
+                reset: self.jls_reset.unwrap_or(defaults.reset),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Transcode target argument.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum TargetArg {
+    /// JPEG-LS Lossless
+    JpeglsLossless,
+    /// JPEG-LS Near-Lossless
+    JpeglsNearLossless,
+    /// JPEG 2000 Lossless
+    Jpeg2000Lossless,
+    /// JPEG 2000 Lossy
+    Jpeg2000Lossy,
+    /// Explicit VR Little Endian (uncompressed)
+    Uncompressed,
+}
+
+impl TargetArg {
+    /// Codec and mode producing this target.
+    pub fn codec_and_mode(self) -> (CompressionCodec, CompressionMode) {
+        match self {
+            Self::JpeglsLossless => (CompressionCodec::JpegLs, CompressionMode::Lossless),
+            Self::JpeglsNearLossless => (CompressionCodec::JpegLs, CompressionMode::NearLossless),
+            Self::Jpeg2000Lossless => (CompressionCodec::Jpeg2000, CompressionMode::Lossless),
+            Self::Jpeg2000Lossy => (CompressionCodec::Jpeg2000, CompressionMode::Lossy),
+            Self::Uncompressed => (CompressionCodec::Uncompressed, CompressionMode::Lossless),
+        }
+    }
+}
+
+/// Report rendering argument.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormatArg {
+    /// Comma-separated values, one row per group
+    Csv,
+    /// Comma-separated values, one row per failed file
+    FailuresCsv,
+    /// Standalone HTML page
+    Html,
+}
+
+/// De-identification profile argument.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum DeidProfileArg {
+    /// DICOM Basic Application Level Confidentiality Profile
+    Basic,
+    /// Basic profile, keeping study/series/instance UIDs
+    RetainUids,
+}
+
+impl From<DeidProfileArg> for DeidentificationProfile {
+    fn from(arg: DeidProfileArg) -> Self {
+        match arg {
+            DeidProfileArg::Basic => DeidentificationProfile::Basic,
+            DeidProfileArg::RetainUids => DeidentificationProfile::RetainUids,
+        }
+    }
+}
+
+/// Compression mode argument.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum ModeArg {
+    /// Lossless compression (exact reconstruction)
+    Lossless,
+    /// Lossy compression (higher ratio, some quality loss)
+    Lossy,
+    /// Near-lossless (JPEG-LS only)
+    NearLossless,
+}
+
+impl From<ModeArg> for CompressionMode {
+    fn from(arg: ModeArg) -> Self {
+        match arg {
+            ModeArg::Lossless => CompressionMode::Lossless,
+            ModeArg::Lossy => CompressionMode::Lossy,
+            ModeArg::NearLossless => CompressionMode::NearLossless,
+        }
+    }
+}
+
+/// Modality safety override arguments.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct SafetyOverrideArgs {
+    /// Override modality safety checks (use with caution; requires --reason)
+    #[arg(long, help_heading = "Safety override", requires = "reason")]
+    pub force: bool,
+
+    /// Why safety checks are overridden (recorded in the audit log and warnings)
+    #[arg(long, help_heading = "Safety override", requires = "force")]
+    pub reason: Option<String>,
+
+    /// Who authorizes the override (defaults to $MEDIMG_OPERATOR, then the login user)
+    #[arg(long, help_heading = "Safety override", requires = "force")]
+    pub operator: Option<String>,
+
+    /// Skip the confirmation prompt for --force
+    #[arg(long, help_heading = "Safety override")]
+    pub yes: bool,
+}
+
+impl SafetyOverrideArgs {
+    /// Apply the override to `config`.
+    ///
+    /// A lossy override asks for confirmation on the terminal unless
+    /// `--yes` was given; without a terminal, `--yes` is required.
+    fn apply(self, config: &mut CompressionConfig) -> Result<()> {
+        if !self.force {
+            return Ok(());
+        }
+
+        let reason = self.reason.unwrap_or_default();
+        let operator = self
+            .operator
+            .or_else(|| std::env::var("MEDIMG_OPERATOR").ok())
+            .or_else(|| std::env::var("USER").ok())
+            .or_else(|| std::env::var("USERNAME").ok())
+            .filter(|operator| !operator.trim().is_empty())
+            .ok_or_else(|| {
+                MedImgError::Config("--force needs --operator to record who authorized it".into())
+            })?;
+        if reason.trim().is_empty() {
+            return Err(MedImgError::Config("--force needs a non-empty --reason".into()));
+        }
+
+        if config.mode != CompressionMode::Lossless && !self.yes {
+            let stdin = std::io::stdin();
+            if !stdin.is_terminal() {
+                return Err(MedImgError::Config(
+                    "--force needs confirmation; pass --yes to confirm non-interactively".into(),
+                ));
+            }
+
+            eprint!(
+                "Safety checks are overridden: modalities that require lossless compression \
+                 will be compressed {:?}.\nReason: {}\nOperator: {}\nContinue? [y/N] ",
+                config.mode, reason, operator
+            );
+            std::io::stderr().flush()?;
+            let mut answer = String::new();
+            stdin.lock().read_line(&mut answer)?;
+            if !matches!(answer.trim(), "y" | "Y" | "yes") {
+                return Err(MedImgError::Config("Safety override not confirmed".into()));
+            }
+        }
+
+        config.override_safety_checks = Some(OverrideReason { reason, operator });
+        Ok(())
+    }
+}
+
+/// Raw pixel file layout arguments.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct RawArgs {
+    /// Width of a raw input in pixels (reads the input as raw pixels)
+    #[arg(long, help_heading = "Raw input", requires = "raw_height")]
+    pub raw_width: Option<u32>,
+
+    /// Height of a raw input in pixels
+    #[arg(long, help_heading = "Raw input", requires = "raw_width")]
+    pub raw_height: Option<u32>,
+
+    /// Bits per sample of a raw input (samples over 8 bits take 2 bytes)
+    #[arg(long, help_heading = "Raw input", default_value = "8")]
+    pub raw_bits: u16,
+
+    /// Samples per pixel of a raw input (1 grayscale, 3 interleaved RGB)
+    #[arg(long, help_heading = "Raw input", default_value = "1")]
+    pub raw_samples: u16,
+
+    /// Raw samples are signed
+    #[arg(long, help_heading = "Raw input")]
+    pub raw_signed: bool,
+
+    /// Raw 2-byte samples are big-endian
+    #[arg(long, help_heading = "Raw input")]
+    pub raw_big_endian: bool,
+}
+
+impl RawArgs {
+    /// The raw layout, if a raw size was given.
+    fn layout(&self) -> Option<RawLayout> {
+        Some(RawLayout {
+            width: self.raw_width?,
+            height: self.raw_height?,
+            bits_per_sample: self.raw_bits,
+            samples_per_pixel: self.raw_samples,
+            signed: self.raw_signed,
+            big_endian: self.raw_big_endian,
+        })
+    }
+}
+
+/// Operational metrics export arguments.
+#[cfg(feature = "prometheus")]
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct MetricsArgs {
+    /// Serve Prometheus metrics at http://ADDR/metrics while running
+    #[arg(long, help_heading = "Metrics", value_name = "ADDR")]
+    pub metrics_listen: Option<String>,
+
+    /// Push metrics to this Prometheus Pushgateway (e.g. http://localhost:9091)
+    #[arg(long, help_heading = "Metrics", value_name = "URL")]
+    pub pushgateway: Option<String>,
+
+    /// Job name for pushed metrics
+    #[arg(long, help_heading = "Metrics", default_value = "medimg")]
+    pub push_job: String,
+
+    /// Seconds between pushes
+    #[arg(long, help_heading = "Metrics", default_value = "15")]
+    pub push_interval: u64,
+}
+
+#[cfg(feature = "prometheus")]
+impl MetricsArgs {
+    /// Start the requested exporters.
+    ///
+    /// Binds the metrics listener up front so a bad address fails before
+    /// any file is processed.
+    fn start(self) -> Result<MetricsExporter> {
+        let mut exporter = MetricsExporter {
+            metrics: None,
+            stop: CancellationToken::new(),
+            threads: Vec::new(),
+        };
+        if self.metrics_listen.is_none() && self.pushgateway.is_none() {
+            return Ok(exporter);
+        }
+        let metrics = OperationalMetrics::new();
+
+        if let Some(ref listen) = self.metrics_listen {
+            let listener = std::net::TcpListener::bind(listen).map_err(|e| {
+                MedImgError::Config(format!("Cannot listen on {}: {}", listen, e))
+            })?;
+            let (metrics, stop) = (metrics.clone(), exporter.stop.clone());
+            exporter.threads.push(std::thread::spawn(move || {
+                if let Err(e) = metrics.serve(listener, &stop) {
+                    log::warn!("Metrics endpoint stopped: {}", e);
+                }
+            }));
+        }
+        if let Some(gateway) = self.pushgateway {
+            let (metrics, stop) = (metrics.clone(), exporter.stop.clone());
+            let interval = std::time::Duration::from_secs(self.push_interval.max(1));
+            exporter.threads.push(std::thread::spawn(move || {
+                metrics.push_periodically(&gateway, &self.push_job, interval, &stop)
+            }));
+        }
+
+        exporter.metrics = Some(metrics);
+        Ok(exporter)
+    }
+}
+
+/// Running metrics exporters; stopped (with a final push) when dropped.
+#[cfg(feature = "prometheus")]
+struct MetricsExporter {
+    metrics: Option<OperationalMetrics>,
+    stop: CancellationToken,
+    threads: Vec<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(feature = "prometheus")]
+impl Drop for MetricsExporter {
+    fn drop(&mut self) {
+        self.stop.cancel();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Quality preset argument.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum QualityArg {
+    /// Diagnostic quality (lossless)
+    Diagnostic,
+    /// High quality lossy
+    HighQuality,
+    /// Standard quality
+    Standard,
+    /// Preview quality
+    Preview,
+}
+
+impl From<QualityArg> for QualityPreset {
+    fn from(arg: QualityArg) -> Self {
+        match arg {
+            QualityArg::Diagnostic => QualityPreset::Diagnostic,
+            QualityArg::HighQuality => QualityPreset::HighQuality,
+            QualityArg::Standard => QualityPreset::Standard,
+            QualityArg::Preview => QualityPreset::Preview,
+        }
+    }
+}
+
+/// Quality preset name: a built-in tier or a `[quality_presets]` entry
+/// from the configuration file (built-in names take precedence).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(from = "String", into = "String")]
+pub enum QualityName {
+    /// Built-in tier.
+    Builtin(QualityArg),
+    /// Site-defined tier.
+    Custom(String),
+}
+
+impl std::str::FromStr for QualityName {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match QualityArg::from_str(s, true) {
+            Ok(arg) => QualityName::Builtin(arg),
+            Err(_) => QualityName::Custom(s.to_string()),
+        })
+    }
+}
+
+impl From<String> for QualityName {
+    fn from(name: String) -> Self {
+        match name.parse() {
+            Ok(name) => name,
+            Err(never) => match never {},
+        }
+    }
+}
+
+impl From<QualityName> for String {
+    fn from(name: QualityName) -> Self {
+        match name {
+            QualityName::Builtin(arg) => arg
+                .to_possible_value()
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default(),
+            QualityName::Custom(name) => name,
+        }
+    }
+}
+
+/// Resolve a quality name against the built-in tiers and the configuration
+/// file's `[quality_presets]`.
+fn resolve_quality(
+    name: Option<QualityName>,
+    presets: &std::collections::BTreeMap<String, QualityPresetSection>,
+) -> Result<QualityPreset> {
+    match name {
+        None => Ok(QualityPreset::Diagnostic),
+        Some(QualityName::Builtin(arg)) => Ok(arg.into()),
+        Some(QualityName::Custom(label)) => {
+            let preset = presets.get(&label).ok_or_else(|| {
+                MedImgError::Config(format!(
+                    "Unknown quality preset '{}' (built-in: diagnostic, high-quality, standard, \
+                     preview; custom: {})",
+                    label,
+                    if presets.is_empty() {
+                        "none".to_string()
+                    } else {
+                        presets.keys().cloned().collect::<Vec<_>>().join(", ")
+                    }
+                ))
+            })?;
+            Ok(QualityPreset::Custom {
+                label,
+                target_ratio: preset.ratio,
+                quality_layers: preset.layers.unwrap_or(1),
+            })
+        }
+    }
+}
+
+/// Single files at least this large get an encode progress bar.
+const LARGE_FILE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Process exit status reported by the CLI.
+///
+/// Argument parsing errors are reported by clap with code 2, which
+/// `InvalidArguments` shares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Command completed successfully.
+    Success = 0,
+    /// Unclassified failure (I/O, codec, internal errors).
+    Failure = 1,
+    /// Invalid arguments, configuration, or input.
+    InvalidArguments = 2,
+    /// Decoded pixel data does not match the original.
+    VerificationFailed = 3,
+    /// Input could not be parsed as supported DICOM.
+    DicomError = 4,
+    /// Modality or compression policy forbids the requested operation.
+    PolicyViolation = 5,
+    /// Batch finished but some files failed.
+    PartialBatchFailure = 6,
+}
+
+impl ExitStatus {
+    /// Classify an error into an exit status.
+    pub fn for_error(error: &MedImgError) -> Self {
+        match error {
+            MedImgError::Config(_) | MedImgError::Validation(_) => ExitStatus::InvalidArguments,
+            MedImgError::VerificationFailed(_) => ExitStatus::VerificationFailed,
+            MedImgError::Dicom(_)
+            | MedImgError::DicomSource { .. }
+            | MedImgError::CodecParse(_)
+            | MedImgError::InvalidFormat(_)
+            | MedImgError::UnsupportedTransferSyntax(_) => ExitStatus::DicomError,
+            MedImgError::CompressionConstraint(_) | MedImgError::PolicyViolation(_) => {
+                ExitStatus::PolicyViolation
+            }
+            MedImgError::Context { source, .. } => Self::for_error(source),
+            _ => ExitStatus::Failure,
+        }
+    }
+
+    /// Numeric process exit code.
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+}
+
+impl From<ExitStatus> for std::process::ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        std::process::ExitCode::from(status.code())
+    }
+}
+
+/// Run the CLI application.
+pub fn run(cli: Cli) -> Result<ExitStatus> {
+    // Initialize logging (batch runs keep per-file log lines out of the progress bar)
+    let console_level = if cli.verbose {
+        Some("debug")
+    } else if cli.quiet {
+        None
+    } else if matches!(cli.command, Commands::Batch { .. }) {
+        Some("warn")
+    } else {
+        Some("info")
+    };
+    logging::init(console_level, cli.log_file.as_deref(), cli.log_format)?;
+
+    // Flushes the remaining spans when dropped, after the command
+    #[cfg(feature = "otlp")]
+    let _telemetry = match &cli.otlp_endpoint {
+        Some(endpoint) => Some(
+            crate::telemetry::OtlpExporter::new(endpoint.as_str())
+                .service_name(cli.otlp_service_name.as_str())
+                .install()?,
+        ),
+        None => None,
+    };
+
+    let result = execute(cli);
+    if let Err(e) = &result {
+        logging::log_error(e);
+    }
+    result
+}
+
+/// Dispatch a parsed command.
+fn execute(cli: Cli) -> Result<ExitStatus> {
+    let format = cli.format;
+    let show_progress = !cli.quiet && !cli.no_progress && std::io::stdout().is_terminal();
+
+    let file_config = load_settings(cli.config.as_deref(), cli.profile.as_deref())?;
+    file_config.limits.decode_limits().install();
+    MemoryAccountant::global().set_ceiling(file_config.limits.max_memory_bytes);
+    if let Some(path) = &file_config.audit.log {
+        let actor = file_config
+            .audit
+            .actor
+            .clone()
+            .or_else(|| std::env::var("MEDIMG_OPERATOR").ok())
+            .or_else(|| std::env::var("USER").ok())
+            .or_else(|| std::env::var("USERNAME").ok())
+            .unwrap_or_else(|| "unknown".into());
+        let mut log = AccessLog::new(actor).sink(JsonLinesSink::open(path)?);
+        if let Some(salt) = &file_config.audit.salt {
+            log = log.salt(salt);
+        }
+        log.install();
+    }
+    let cli_profile: Option<RegulatoryProfile> = cli.regulatory_profile.map(Into::into);
+
+    let result = match cli.command {
+        Commands::Compress {
+            input,
+            output,
+            codec,
+            encoder_plugin,
+            mode,
+            quality,
+            ratio,
+            bpp,
+            verify,
+            dry_run,
+            tuning,
+            safety,
+        } => {
+            let base = CompressionConfig {
+                verify_compression: false,
+                ..Default::default()
+            };
+            let overrides = CompressionOverrides {
+                codec: codec.map(Into::into),
+                encoder_plugin,
+                mode: mode.map(Into::into),
+                quality: cli_quality(quality, &file_config)?,
+                target_ratio: ratio,
+                target_bpp: bpp,
+                verify_compression: verify.then_some(true),
+                regulatory_profile: cli_profile,
+                ..Default::default()
+            };
+            let mut config = layered_config(base, &file_config, &overrides)?;
+            tuning.apply(&mut config)?;
+            safety.apply(&mut config)?;
+            check_config(&config)?;
+            run_compress(input, output, config, dry_run, format, cli.quiet, show_progress)
+        }
+        Commands::Batch {
+            input_dir,
+            output_dir,
+            codec,
+            encoder_plugin,
+            mode,
+            quality,
+            ratio,
+            bpp,
+            recursive,
+            jobs,
+            min_ssim,
+            retries,
+            output_template,
+            manifest,
+            #[cfg(feature = "catalog")]
+            catalog,
+            tuning,
+            safety,
+            #[cfg(feature = "prometheus")]
+            metrics,
+        } => {
+            let overrides = CompressionOverrides {
+                codec: codec.map(Into::into),
+                encoder_plugin,
+                mode: mode.map(Into::into),
+                quality: cli_quality(quality, &file_config)?,
+                target_ratio: ratio,
+                target_bpp: bpp,
+                regulatory_profile: cli_profile,
+                ..Default::default()
+            };
+            let mut config = layered_config(CompressionConfig::default(), &file_config, &overrides)?;
+            tuning.apply(&mut config)?;
+            safety.apply(&mut config)?;
+            check_config(&config)?;
+            #[cfg(feature = "prometheus")]
+            let exporter = metrics.start()?;
+            let batch = file_config.batch;
+            let options = BatchOptions {
+                output_dir: output_dir.or(batch.output_dir),
+                recursive: recursive || batch.recursive.unwrap_or(false),
+                jobs: jobs.or(batch.jobs),
+                min_ssim: min_ssim.or(batch.min_ssim),
+                retries: retries.or(batch.retries),
+                output_template: output_template.or(file_config.output.template),
+                manifest,
+                #[cfg(feature = "catalog")]
+                catalog: catalog.map(|path| Catalog::open(path).map(Arc::new)).transpose()?,
+                #[cfg(feature = "prometheus")]
+                metrics: exporter.metrics.clone(),
+            };
+            return run_batch(input_dir, config, options, format, cli.quiet, show_progress);
+        }
+        Commands::Watch {
+            input_dir,
+            output_dir,
+            codec,
+            mode,
+            quality,
+            ratio,
+            bpp,
+            recursive,
+            jobs,
+            poll_interval,
+            report_interval,
+            manifest,
+            #[cfg(feature = "catalog")]
+            catalog,
+            tuning,
+            safety,
+            #[cfg(feature = "prometheus")]
+            metrics,
+        } => {
+            let overrides = CompressionOverrides {
+                codec: codec.map(Into::into),
+                mode: mode.map(Into::into),
+                quality: cli_quality(quality, &file_config)?,
+                target_ratio: ratio,
+                target_bpp: bpp,
+                regulatory_profile: cli_profile,
+                ..Default::default()
+            };
+            let mut config = layered_config(CompressionConfig::default(), &file_config, &overrides)?;
+            tuning.apply(&mut config)?;
+            safety.apply(&mut config)?;
+            check_config(&config)?;
+            #[cfg(feature = "prometheus")]
+            let exporter = metrics.start()?;
+            let batch = file_config.batch;
+            let options = BatchOptions {
+                output_dir: output_dir.or(batch.output_dir),
+                recursive: recursive || batch.recursive.unwrap_or(false),
+                jobs: jobs.or(batch.jobs),
+                min_ssim: batch.min_ssim,
+                retries: batch.retries,
+                output_template: file_config.output.template,
+                manifest,
+                #[cfg(feature = "catalog")]
+                catalog: catalog.map(|path| Catalog::open(path).map(Arc::new)).transpose()?,
+                #[cfg(feature = "prometheus")]
+                metrics: exporter.metrics.clone(),
+            };
+            let intervals = (
+                std::time::Duration::from_secs(poll_interval.max(1)),
+                std::time::Duration::from_secs(report_interval.max(1)),
+            );
+            return run_watch(input_dir, config, options, intervals, format, cli.quiet);
+        }
+        Commands::Report {
+            manifest,
+            #[cfg(feature = "catalog")]
+            catalog,
+            #[cfg(feature = "catalog")]
+            sop_uid,
+            #[cfg(feature = "catalog")]
+            modality,
+            #[cfg(feature = "catalog")]
+            days,
+            #[cfg(feature = "catalog")]
+            entries,
+            report_format,
+            output,
+        } => {
+            let source = match manifest {
+                Some(path) => ReportSource::Manifest(path),
+                #[cfg(feature = "catalog")]
+                None => {
+                    let since = days.map(|days| {
+                        let now = std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map(|d| d.as_secs())
+                            .unwrap_or_default();
+                        now.saturating_sub(days * 86_400)
+                    });
+                    ReportSource::Catalog {
+                        // The argument group requires --manifest or --catalog
+                        path: catalog.unwrap_or_default(),
+                        query: CatalogQuery {
+                            sop_instance_uid: sop_uid,
+                            modality: modality.map(|code| Modality::from_dicom_string(&code)),
+                            since,
+                            ..Default::default()
+                        },
+                        entries,
+                    }
+                }
+                #[cfg(not(feature = "catalog"))]
+                None => unreachable!("clap requires --manifest"),
+            };
+            run_report(source, report_format, output, format, cli.quiet)
+        }
+        Commands::Serve {
+            listen,
+            codec,
+            mode,
+            quality,
+            ratio,
+            bpp,
+            max_body_mb,
+            max_connections,
+            tuning,
+            #[cfg(feature = "grpc")]
+            grpc,
+            #[cfg(feature = "rest")]
+            state_dir,
+            #[cfg(feature = "rest")]
+            input_root,
+            #[cfg(feature = "rest")]
+            workers,
+            #[cfg(feature = "prometheus")]
+            metrics,
+        } => {
+            let overrides = CompressionOverrides {
+                codec: codec.map(Into::into),
+                mode: mode.map(Into::into),
+                quality: cli_quality(quality, &file_config)?,
+                target_ratio: ratio,
+                target_bpp: bpp,
+                regulatory_profile: cli_profile,
+                ..Default::default()
+            };
+            let mut config = layered_config(CompressionConfig::default(), &file_config, &overrides)?;
+            tuning.apply(&mut config)?;
+            check_config(&config)?;
+            let max_body_bytes = max_body_mb.saturating_mul(1 << 20);
+            #[cfg(feature = "prometheus")]
+            let metrics = metrics.then(OperationalMetrics::new);
+            #[cfg(feature = "rest")]
+            if let Some(state_dir) = state_dir {
+                let mut server = crate::server::JobServer::new(config, state_dir).max_body_bytes(max_body_bytes);
+                if let Some(dir) = input_root {
+                    server = server.input_root(dir);
+                }
+                if let Some(workers) = workers {
+                    server = server.max_parallel(workers);
+                }
+                #[cfg(feature = "prometheus")]
+                if let Some(metrics) = metrics {
+                    server = server.metrics(metrics);
+                }
+                return run_jobs(server, &listen, format, cli.quiet).map(|()| ExitStatus::Success);
+            }
+            #[cfg(feature = "grpc")]
+            if grpc {
+                #[cfg(feature = "prometheus")]
+                if metrics.is_some() {
+                    return Err(MedImgError::Config("--metrics is not supported with --grpc".into()));
+                }
+                return run_grpc(&listen, config, max_body_bytes, format, cli.quiet).map(|()| ExitStatus::Success);
+            }
+            #[cfg_attr(not(feature = "prometheus"), allow(unused_mut))]
+            let mut server = CompressionServer::new(config)
+                .max_body_bytes(max_body_bytes)
+                .max_connections(max_connections);
+            #[cfg(feature = "prometheus")]
+            if let Some(metrics) = metrics {
+                server = server.metrics(metrics);
+            }
+            run_serve(server, &listen, format, cli.quiet)
+        }
+        Commands::Scp {
+            aet,
+            port,
+            listen,
+            output_dir,
+            forward,
+            forward_aet,
+            codec,
+            mode,
+            quality,
+            ratio,
+            bpp,
+            tuning,
+        } => {
+            let overrides = CompressionOverrides {
+                codec: codec.map(Into::into),
+                mode: mode.map(Into::into),
+                quality: cli_quality(quality, &file_config)?,
+                target_ratio: ratio,
+                target_bpp: bpp,
+                regulatory_profile: cli_profile,
+                ..Default::default()
+            };
+            let mut config = layered_config(CompressionConfig::default(), &file_config, &overrides)?;
+            tuning.apply(&mut config)?;
+            check_config(&config)?;
+
+            let mut scp = StoreScp::new(aet.as_str(), config);
+            if let Some(dir) = output_dir {
+                scp = scp.output_dir(dir);
+            }
+            if let Some(address) = forward {
+                scp = scp.forward(
+                    StoreScu::new(address)
+                        .calling_ae_title(aet.as_str())
+                        .called_ae_title(forward_aet),
+                );
+            }
+            run_scp(scp, &aet, (listen.as_str(), port), format, cli.quiet)
+        }
+        Commands::Worker {
+            broker,
+            tasks,
+            events,
+            group,
+            no_quality,
+            codec,
+            mode,
+            quality,
+            ratio,
+            bpp,
+            tuning,
+            #[cfg(feature = "prometheus")]
+            metrics,
+        } => {
+            let overrides = CompressionOverrides {
+                codec: codec.map(Into::into),
+                mode: mode.map(Into::into),
+                quality: cli_quality(quality, &file_config)?,
+                target_ratio: ratio,
+                target_bpp: bpp,
+                regulatory_profile: cli_profile,
+                ..Default::default()
+            };
+            let mut config = layered_config(CompressionConfig::default(), &file_config, &overrides)?;
+            tuning.apply(&mut config)?;
+            check_config(&config)?;
+            #[cfg(feature = "prometheus")]
+            let exporter = metrics.start()?;
+
+            let broker_config = BrokerConfig {
+                url: broker,
+                tasks,
+                events,
+                group,
+            };
+            #[cfg_attr(not(feature = "prometheus"), allow(unused_mut))]
+            let mut worker =
+                QueueWorker::new(config, crate::queue::connect(&broker_config)?).measure_quality(!no_quality);
+            #[cfg(feature = "prometheus")]
+            if let Some(metrics) = exporter.metrics.clone() {
+                worker = worker.metrics(metrics);
+            }
+            return run_worker(worker, &broker_config, format, cli.quiet);
+        }
+        Commands::Estimate {
+            input_dir,
+            recursive,
+            ratio,
+            near,
+        } => {
+            let scenarios = estimate_scenarios(
+                ratio.or(file_config.ratio).unwrap_or(10.0),
+                near.or(file_config.near).unwrap_or(2),
+                cli_profile
+                    .or(file_config.policy.regulatory_profile.map(Into::into))
+                    .unwrap_or_default(),
+                file_config.policy.lossless_modalities.unwrap_or_default(),
+            );
+            let recursive = recursive || file_config.batch.recursive.unwrap_or(false);
+            run_estimate(input_dir, recursive, scenarios, format, cli.quiet)
+        }
+        Commands::Bench {
+            corpus,
+            recursive,
+            iterations,
+            ratio,
+            near,
+        } => {
+            let scenarios = default_scenarios(
+                ratio.or(file_config.ratio).unwrap_or(10.0),
+                near.or(file_config.near).unwrap_or(2),
+            );
+            run_bench(corpus, recursive, scenarios, iterations, format, cli.quiet)
+        }
+        Commands::Selftest { size, ratio, near } => {
+            let scenarios = default_scenarios(
+                ratio.or(file_config.ratio).unwrap_or(10.0),
+                near.or(file_config.near).unwrap_or(2),
+            );
+            run_selftest(size, &scenarios, format, cli.quiet)
+        }
+        Commands::Transcode {
+            input,
+            output,
+            to,
+            ratio,
+            near,
+            verify,
+            safety,
+        } => {
+            let (codec, mode) = to.codec_and_mode();
+            let base = CompressionConfig {
+                verify_compression: false,
+                ..Default::default()
+            };
+            let overrides = CompressionOverrides {
+                codec: Some(codec),
+                mode: Some(mode),
+                target_ratio: ratio,
+                near_lossless_error: (mode == CompressionMode::NearLossless).then_some(near),
+                verify_compression: verify.then_some(true),
+                regulatory_profile: cli_profile,
+                ..Default::default()
+            };
+            let mut config = layered_config(base, &file_config, &overrides)?;
+            // --to names the target transfer syntax, so no plugin replaces it
+            config.encoder_plugin = None;
+            safety.apply(&mut config)?;
+            check_config(&config)?;
+            run_transcode(input, output, config, format, cli.quiet)
+        }
+        Commands::Compare {
+            original,
+            test,
+            region,
+            json,
+        } => {
+            let format = if json { OutputFormat::Json } else { format };
+            run_compare(original, test, region, format, cli.quiet)
+        }
+        Commands::Verify {
+            original,
+            compressed,
+        } => run_verify(original, compressed, format, cli.quiet),
+        Commands::Anonymize {
+            input,
+            output,
+            deid_profile,
+            compress,
+            codec,
+            mode,
+            safety,
+        } => {
+            let mut config = compress
+                .then(|| {
+                    let base = CompressionConfig {
+                        verify_compression: false,
+                        ..Default::default()
+                    };
+                    let overrides = CompressionOverrides {
+                        codec: codec.map(Into::into),
+                        mode: mode.map(Into::into),
+                        regulatory_profile: cli_profile,
+                        ..Default::default()
+                    };
+                    layered_config(base, &file_config, &overrides)
+                })
+                .transpose()?;
+            if let Some(config) = &mut config {
+                safety.apply(config)?;
+                check_config(config)?;
+            }
+            run_anonymize(input, output, deid_profile.into(), config, format, cli.quiet)
+        }
+        Commands::Import {
+            input,
+            output,
+            patient_id,
+            patient_name,
+            birth_date,
+            sex,
+            accession_number,
+            study_description,
+            modality,
+            conversion_type,
+            raw,
+            codec,
+            mode,
+            safety,
+        } => {
+            let base = CompressionConfig {
+                verify_compression: false,
+                ..Default::default()
+            };
+            let overrides = CompressionOverrides {
+                codec: codec.map(Into::into),
+                mode: mode.map(Into::into),
+                regulatory_profile: cli_profile,
+                ..Default::default()
+            };
+            let mut config = layered_config(base, &file_config, &overrides)?;
+            safety.apply(&mut config)?;
+            check_config(&config)?;
+            let mut capture = SecondaryCapture::new()
+                .patient(patient_id, patient_name)
+                .patient_birth_date(birth_date)
+                .patient_sex(sex)
+                .accession_number(accession_number)
+                .modality(modality)
+                .conversion_type(conversion_type);
+            if let Some(description) = study_description {
+                capture = capture.study_description(description);
+            }
+            run_import(input, output, &capture, raw.layout(), config, format, cli.quiet)
+        }
+        #[cfg(feature = "nifti")]
+        Commands::Nifti {
+            input,
+            output,
+            patient_id,
+            patient_name,
+            modality,
+            compress,
+            codec,
+            mode,
+            safety,
+        } => {
+            let mut config = compress
+                .then(|| {
+                    let base = CompressionConfig {
+                        verify_compression: false,
+                        ..Default::default()
+                    };
+                    let overrides = CompressionOverrides {
+                        codec: codec.map(Into::into),
+                        mode: mode.map(Into::into),
+                        regulatory_profile: cli_profile,
+                        ..Default::default()
+                    };
+                    layered_config(base, &file_config, &overrides)
+                })
+                .transpose()?;
+            if let Some(config) = &mut config {
+                safety.apply(config)?;
+                check_config(config)?;
+            }
+            let capture = SecondaryCapture::new()
+                .patient(patient_id, patient_name)
+                .modality(modality);
+            run_nifti(input, output, &capture, config, format, cli.quiet)
+        }
+        Commands::Dump { input, tags } => run_dump(input, &tags, format, cli.quiet),
+        Commands::Export {
+            input,
+            output,
+            frame,
+            window,
+            max_size,
+            filter,
+        } => {
+            let options = PreviewOptions {
+                frame,
+                window,
+                max_size,
+                filter,
+            };
+            run_export(input, output, options, format, cli.quiet)
+        }
+        Commands::Info { input, detailed } => run_info(input, detailed, format, cli.quiet),
+        Commands::Analyze {
+            input,
+            codec,
+            all_modes,
+        } => {
+            let codec = codec.or(file_config.codec).unwrap_or(CodecArg::Jpeg2000);
+            run_analyze(input, codec.into(), all_modes, format, cli.quiet)
+        }
+    };
+
+    result.map(|()| ExitStatus::Success)
+}
+
+/// Resolve file, profile, and environment settings (lowest to highest).
+fn load_settings(config: Option<&Path>, profile: Option<&str>) -> Result<FileConfig> {
+    let file_config = match (FileConfig::discover(config)?, profile) {
+        (Some((path, config)), profile) => {
+            log::debug!("Using config file {}", path.display());
+            match profile {
+                Some(name) => {
+                    log::debug!("Using profile {}", name);
+                    config.select_profile(name)?
+                }
+                None => config,
+            }
+        }
+        (None, Some(name)) => {
+            return Err(MedImgError::Config(format!(
+                "Profile '{}' requested but no configuration file was found",
+                name
+            )));
+        }
+        (None, None) => FileConfig::default(),
+    };
+
+    Ok(file_config.overlay(FileConfig::from_env()?))
+}
+
+/// Layer the config file (with its profile and environment overrides)
+/// and the command-line `overrides` over `base`.
+fn layered_config(
+    base: CompressionConfig,
+    file_config: &FileConfig,
+    overrides: &CompressionOverrides,
+) -> Result<CompressionConfig> {
+    Ok(base
+        .merge(&file_config.compression_overrides()?)
+        .merge(overrides)
+        .with_mode_defaults())
+}
+
+/// Resolve a command-line quality name against the file's presets.
+fn cli_quality(name: Option<QualityName>, file_config: &FileConfig) -> Result<Option<QualityPreset>> {
+    name.map(|name| resolve_quality(Some(name), &file_config.quality_presets))
+        .transpose()
+}
+
+/// Reject contradictory settings before any work starts.
+fn check_config(config: &CompressionConfig) -> Result<()> {
+    config
+        .validate()
+        .map_err(|problems| MedImgError::invalid_config(&problems))
+}
+
+/// Run compression command.
+fn run_compress(
+    input: PathBuf,
+    output: Option<PathBuf>,
+    config: CompressionConfig,
+    dry_run: bool,
+    format: OutputFormat,
+    quiet: bool,
+    show_progress: bool,
+) -> Result<()> {
+    let to_stdout = output.as_deref().is_some_and(is_stdio);
+    let piped = is_stdio(&input) || to_stdout;
+
+    // Stdin without an output is analysis only
+    let dry_run = dry_run || (piped && output.is_none());
+    let pipeline = CompressionPipeline::new(config).dry_run(dry_run);
+
+    let bar = (show_progress && !piped && is_large_file(&input)).then(TerminalProgress::for_file);
+    let progress: &dyn ProgressHandler = match bar {
+        Some(ref bar) => bar,
+        None => &NullProgress,
+    };
+
+    let result = match output {
+        _ if piped => compress_piped(&pipeline, &input, output.as_deref()),
+        Some(ref output) => pipeline.compress_file_to_with_progress(&input, output, progress),
+        None => pipeline.compress_file_with_progress(&input, progress),
+    };
+    if let Some(ref bar) = bar {
+        bar.bar().finish_and_clear();
+    }
+    let result = result?;
+
+    if to_stdout {
+        // Stdout carries the DICOM stream; keep the report out of it
+        log::info!(
+            "Compressed {} to stdout: {:.2}:1 ({} -> {} bytes)",
+            result.source_path.display(),
+            result.compression_ratio,
+            result.original_size,
+            result.compressed_size
+        );
+    } else if format == OutputFormat::Json {
+        print_json(&result)?;
+    } else if !quiet {
+        print_compression_result(&result);
+        if let Some(ref path) = result.output_path {
+            println!("  Output: {}", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Check if a single file is large enough to warrant a progress bar.
+fn is_large_file(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|meta| meta.len() >= LARGE_FILE_BYTES)
+}
+
+/// Check if a path argument names stdin/stdout (`-`).
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Compress with stdin and/or stdout standing in for files.
+fn compress_piped(
+    pipeline: &CompressionPipeline,
+    input: &Path,
+    output: Option<&Path>,
+) -> Result<CompressionResult> {
+    let reader: Box<dyn Read> = if is_stdio(input) {
+        Box::new(std::io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(input)?))
+    };
+
+    match output {
+        Some(path) if is_stdio(path) => pipeline.compress_stream(reader, std::io::stdout().lock()),
+        Some(path) => {
+            // Buffer so a failed run does not leave a partial file behind
+            let mut buffer = Vec::new();
+            let mut result = pipeline.compress_stream(reader, &mut buffer)?;
+            if result.output_path.is_some() {
+                std::fs::write(path, &buffer)?;
+                result.output_path = Some(path.to_path_buf());
+            }
+            Ok(result)
+        }
+        None => pipeline.compress_stream(reader, std::io::sink()),
+    }
+}
+
+/// Run transcode command.
+fn run_transcode(
+    input: PathBuf,
+    output: PathBuf,
+    config: CompressionConfig,
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<()> {
+    let pipeline = CompressionPipeline::new(config);
+    let result = pipeline.transcode_file(&input, &output)?;
+
+    if format == OutputFormat::Json {
+        print_json(&result)?;
+    } else if !quiet {
+        print_compression_result(&result);
+        println!("  Output: {}", output.display());
+    }
+
+    Ok(())
+}
+
+/// Batch command options.
+struct BatchOptions {
+    output_dir: Option<PathBuf>,
+    recursive: bool,
+    jobs: Option<usize>,
+    min_ssim: Option<f64>,
+    retries: Option<u32>,
+    output_template: Option<String>,
+    manifest: Option<PathBuf>,
+    #[cfg(feature = "catalog")]
+    catalog: Option<Arc<Catalog>>,
+    #[cfg(feature = "prometheus")]
+    metrics: Option<OperationalMetrics>,
+}
+
+/// Run batch command.
+fn run_batch(
+    input_dir: PathBuf,
+    config: CompressionConfig,
+    options: BatchOptions,
+    format: OutputFormat,
+    quiet: bool,
+    show_progress: bool,
+) -> Result<ExitStatus> {
+    let stats = if !show_progress {
+        process_batch(BatchProcessor::without_progress(config), &input_dir, options)?
+    } else {
+        let processor = BatchProcessor::new(config, TerminalProgress::new());
+        process_batch(processor, &input_dir, options)?
+    };
+
+    if format == OutputFormat::Json {
+        print_json(&stats)?;
+    } else if !quiet {
+        print_batch_stats(&stats);
+    }
+
+    Ok(if stats.failed > 0 {
+        ExitStatus::PartialBatchFailure
+    } else {
+        ExitStatus::Success
+    })
+}
+
+/// Run watch command.
+fn run_watch(
+    input_dir: PathBuf,
+    config: CompressionConfig,
+    options: BatchOptions,
+    (poll_interval, report_interval): (std::time::Duration, std::time::Duration),
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<ExitStatus> {
+    let processor = configure_batch(BatchProcessor::without_progress(config), options);
+    signal::cancel_on_shutdown(processor.cancellation_token());
+
+    if !quiet && format == OutputFormat::Text {
+        println!("Watching {} (Ctrl+C to stop)", input_dir.display());
+    }
+
+    let stats = FolderWatcher::new(processor, &input_dir)
+        .poll_interval(poll_interval)
+        .report_interval(report_interval)
+        .run(|stats| {
+            if format == OutputFormat::Json {
+                // A failed write only loses an interim report
+                let _ = print_json_line(stats);
+            } else if !quiet {
+                println!(
+                    "[watch] {} files: {} succeeded, {} failed, {:.2}:1 overall",
+                    stats.total_files,
+                    stats.successful,
+                    stats.failed,
+                    stats.overall_ratio()
+                );
+            }
+        })?;
+
+    // Reports and the final summary are one JSON object per line
+    if format == OutputFormat::Json {
+        print_json_line(&stats)?;
+    } else if !quiet {
+        print_batch_stats(&stats);
+    }
+
+    Ok(if stats.failed > 0 {
+        ExitStatus::PartialBatchFailure
+    } else {
+        ExitStatus::Success
+    })
+}
+
+/// Run the HTTP compression service until interrupted.
+fn run_serve(server: CompressionServer, listen: &str, format: OutputFormat, quiet: bool) -> Result<()> {
+    let listener = std::net::TcpListener::bind(listen).map_err(|e| {
+        MedImgError::Config(format!("Cannot listen on {}: {}", listen, e))
+    })?;
+    let address = listener.local_addr()?;
+    signal::cancel_on_shutdown(server.cancellation_token());
+
+    if format == OutputFormat::Json {
+        print_json_line(&serde_json::json!({ "listening": address.to_string() }))?;
+    } else if !quiet {
+        println!("Listening on http://{} (Ctrl+C to stop)", address);
+    }
+
+    server.serve(listener)
+}
+
+/// Run the job API until interrupted.
+#[cfg(feature = "rest")]
+fn run_jobs(server: crate::server::JobServer, listen: &str, format: OutputFormat, quiet: bool) -> Result<()> {
+    let listener = std::net::TcpListener::bind(listen).map_err(|e| {
+        MedImgError::Config(format!("Cannot listen on {}: {}", listen, e))
+    })?;
+    let address = listener.local_addr()?;
+    signal::cancel_on_shutdown(server.cancellation_token());
+
+    if format == OutputFormat::Json {
+        print_json_line(&serde_json::json!({ "listening": address.to_string(), "protocol": "jobs" }))?;
+    } else if !quiet {
+        println!("Job API listening on http://{} (Ctrl+C to stop)", address);
+    }
+
+    server.serve(listener)
+}
+
+/// Run the gRPC compression service until interrupted.
+#[cfg(feature = "grpc")]
+fn run_grpc(
+    listen: &str,
+    config: CompressionConfig,
+    max_input_bytes: usize,
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<()> {
+    let listener = std::net::TcpListener::bind(listen).map_err(|e| {
+        MedImgError::Config(format!("Cannot listen on {}: {}", listen, e))
+    })?;
+    let address = listener.local_addr()?;
+
+    let server = crate::grpc::GrpcServer::new(config).max_input_bytes(max_input_bytes);
+    signal::cancel_on_shutdown(server.cancellation_token());
+
+    if format == OutputFormat::Json {
+        print_json_line(&serde_json::json!({ "listening": address.to_string(), "protocol": "grpc" }))?;
+    } else if !quiet {
+        println!("Listening for gRPC on {} (Ctrl+C to stop)", address);
+    }
+
+    server.serve(listener)
+}
+
+/// Run the Storage SCP until interrupted.
+fn run_scp(
+    scp: StoreScp,
+    aet: &str,
+    listen: (&str, u16),
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<()> {
+    let listener = std::net::TcpListener::bind(listen).map_err(|e| {
+        MedImgError::Config(format!("Cannot listen on {}:{}: {}", listen.0, listen.1, e))
+    })?;
+    let address = listener.local_addr()?;
+    signal::cancel_on_shutdown(scp.cancellation_token());
+
+    if format == OutputFormat::Json {
+        print_json_line(&serde_json::json!({ "listening": address.to_string(), "ae_title": aet }))?;
+    } else if !quiet {
+        println!("Storage SCP {} listening on {} (Ctrl+C to stop)", aet, address);
+    }
+
+    scp.serve(listener)
+}
+
+/// Run the queue worker until interrupted.
+fn run_worker(
+    worker: QueueWorker,
+    broker: &BrokerConfig,
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<ExitStatus> {
+    signal::cancel_on_shutdown(worker.cancellation_token());
+
+    if format == OutputFormat::Json {
+        print_json_line(&serde_json::json!({ "consuming": broker.tasks, "publishing": broker.events }))?;
+    } else if !quiet {
+        println!(
+            "Consuming tasks from {} and publishing events to {} (Ctrl+C to stop)",
+            broker.tasks, broker.events
+        );
+    }
+
+    let stats = worker.run()?;
+
+    if format == OutputFormat::Json {
+        print_json_line(&stats)?;
+    } else if !quiet {
+        print_batch_stats(&stats);
+    }
+
+    Ok(if stats.failed > 0 {
+        ExitStatus::PartialBatchFailure
+    } else {
+        ExitStatus::Success
+    })
+}
+
+/// Configure and run a batch processor.
+///
+/// The input and output directories may be storage URLs.
+fn process_batch<P: ProgressHandler>(
+    processor: BatchProcessor<P>,
+    input_dir: &std::path::Path,
+    mut options: BatchOptions,
+) -> Result<BatchStats> {
+    let output = match options.output_dir.as_deref().and_then(Path::to_str) {
+        Some(location) if storage::is_url(location) => Some(storage::open(location)?),
+        _ => None,
+    };
+    if output.is_some() {
+        options.output_dir = None;
+    }
+    let mut processor = configure_batch(processor, options);
+    if let Some(output) = output {
+        processor = processor.output_storage(output);
+    }
+
+    match input_dir.to_str().filter(|location| storage::is_url(location)) {
+        Some(location) => processor.process_storage(storage::open(location)?.as_ref(), ""),
+        None => processor.process_directory(input_dir),
+    }
+}
+
+/// Apply batch command options to a processor.
+fn configure_batch<P: ProgressHandler>(
+    mut processor: BatchProcessor<P>,
+    options: BatchOptions,
+) -> BatchProcessor<P> {
+    processor = processor.recursive(options.recursive);
+    if let Some(dir) = options.output_dir {
+        processor = processor.output_dir(dir);
+    }
+    if let Some(jobs) = options.jobs {
+        processor = processor.max_parallel(jobs);
+    }
+    if let Some(min_ssim) = options.min_ssim {
+        processor = processor.quality_gate(min_ssim);
+    }
+    if let Some(retries) = options.retries {
+        processor = processor.retries(retries);
+    }
+    if let Some(template) = options.output_template {
+        processor = processor.output_template(template);
+    }
+    if let Some(manifest) = options.manifest {
+        processor = processor.manifest(manifest);
+    }
+    #[cfg(feature = "catalog")]
+    if let Some(catalog) = options.catalog {
+        processor = processor.catalog(catalog);
+    }
+    #[cfg(feature = "prometheus")]
+    if let Some(metrics) = options.metrics {
+        processor = processor.metrics(metrics);
+    }
+    processor
+}
+
+/// Run report command.
+fn run_report(
+    source: ReportSource,
+    report_format: Option<ReportFormatArg>,
+    output: Option<PathBuf>,
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<()> {
+    let (path, records) = match source {
+        ReportSource::Manifest(path) => {
+            let records = crate::batch::read_manifest(&path)?;
+            (path, records)
+        }
+        #[cfg(feature = "catalog")]
+        ReportSource::Catalog {
+            path,
+            query,
+            entries,
+        } => {
+            // Opening would create an empty catalog
+            if !path.is_file() {
+                return Err(MedImgError::Io(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("Catalog not found: {}", path.display()),
+                )));
+            }
+            let found = Catalog::open(&path)?.query(&query)?;
+            if entries {
+                let rendered = match format {
+                    OutputFormat::Json => serde_json::to_string_pretty(&found)
+                        .map_err(|e| MedImgError::Internal(format!("Failed to serialize output: {}", e)))?
+                        + "\n",
+                    OutputFormat::Text => catalog_entries_text(&found),
+                };
+                let terminal_text = format == OutputFormat::Text;
+                return write_report(&rendered, output, format, quiet, terminal_text);
+            }
+            (path, found.into_iter().map(|entry| entry.record).collect())
+        }
+    };
+    let summary = ManifestSummary::from_records(&records);
+
+    let rendered = match (report_format, format) {
+        (Some(ReportFormatArg::Csv), _) => summary.to_csv(),
+        (Some(ReportFormatArg::FailuresCsv), _) => summary.failures_to_csv(),
+        (Some(ReportFormatArg::Html), _) => summary.to_html(),
+        (None, OutputFormat::Json) => serde_json::to_string_pretty(&summary)
+            .map_err(|e| MedImgError::Internal(format!("Failed to serialize output: {}", e)))?
+            + "\n",
+        (None, OutputFormat::Text) => report_text(&path, &summary),
+    };
+    let terminal_text = report_format.is_none() && format == OutputFormat::Text;
+    write_report(&rendered, output, format, quiet, terminal_text)
+}
+
+/// Records a report is built from.
+enum ReportSource {
+    /// JSONL manifest.
+    Manifest(PathBuf),
+    /// Entries of a SQLite catalog matching a query, summarized or listed.
+    #[cfg(feature = "catalog")]
+    Catalog {
+        path: PathBuf,
+        query: CatalogQuery,
+        entries: bool,
+    },
+}
+
+/// Write a rendered report to `output`, or to stdout unless it is
+/// `terminal_text` and `quiet` is set.
+fn write_report(
+    rendered: &str,
+    output: Option<PathBuf>,
+    format: OutputFormat,
+    quiet: bool,
+    terminal_text: bool,
+) -> Result<()> {
+    match output {
+        Some(path) => {
+            std::fs::write(&path, rendered)?;
+            if !quiet && format == OutputFormat::Text {
+                println!("Report written to {}", path.display());
+            }
+        }
+        None if quiet && terminal_text => {}
+        None => std::io::stdout().lock().write_all(rendered.as_bytes())?,
+    }
+
+    Ok(())
+}
+
+/// Render catalog entries as plain text, one per line.
+#[cfg(feature = "catalog")]
+fn catalog_entries_text(entries: &[CatalogEntry]) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    for entry in entries {
+        let record = &entry.record;
+        let _ = write!(
+            out,
+            "{:>12} {:<9} {} {}",
+            entry.recorded_at,
+            format!("{:?}", record.status),
+            entry.sop_instance_uid.as_deref().unwrap_or("-"),
+            record.source_path.display()
+        );
+        let _ = match (&record.error, record.compressed_size) {
+            (Some(error), _) => writeln!(out, " [{}] {}", error.code, error.message),
+            (None, 0) => writeln!(out),
+            (None, compressed) => writeln!(
+                out,
+                " {} -> {} ({:.2}:1){}",
+                format_bytes(record.original_size as u64),
+                format_bytes(compressed as u64),
+                record.original_size as f64 / compressed as f64,
+                if entry.verified { " verified" } else { "" }
+            ),
+        };
+    }
+    out
+}
+
+/// Render a manifest summary as plain text.
+fn report_text(manifest: &Path, summary: &ManifestSummary) -> String {
+    use std::fmt::Write as _;
+
+    let mut out = String::new();
+    let t = &summary.totals;
+    let _ = writeln!(out, "Batch Report: {}", manifest.display());
+    let _ = writeln!(out, "========================================");
+    let _ = writeln!(
+        out,
+        "  Files: {} ({} completed, {} failed, {} not processed)",
+        t.files, t.completed, t.failed, t.not_processed
+    );
+    let _ = writeln!(
+        out,
+        "  Size: {} -> {} ({:.2}:1)",
+        format_bytes(t.original_bytes),
+        format_bytes(t.compressed_bytes),
+        t.ratio
+    );
+
+    for (title, groups) in [("By modality", &summary.by_modality), ("By directory", &summary.by_directory)] {
+        let _ = writeln!(out, "\n{}:", title);
+        for (name, g) in groups {
+            let _ = writeln!(
+                out,
+                "  {:<30} {:>6} files {:>6} failed {:>8.2}:1",
+                name, g.files, g.failed, g.ratio
+            );
+        }
+    }
+
+    if !summary.failures.is_empty() {
+        let _ = writeln!(out, "\nFailures:");
+        for record in &summary.failures {
+            let _ = match &record.error {
+                Some(error) => writeln!(
+                    out,
+                    "  {}: [{}] {}",
+                    record.source_path.display(),
+                    error.code,
+                    error.message
+                ),
+                None => writeln!(out, "  {}: unknown error", record.source_path.display()),
+            };
+        }
+    }
+
+    out
+}
+
+/// Projected archive size for one codec/mode scenario.
+#[derive(Debug, Serialize)]
+struct ScenarioEstimate {
+    codec: CompressionCodec,
+    mode: CompressionMode,
+    estimated_bytes: u64,
+    ratio: f64,
+    savings_percent: f64,
+    /// Files kept lossless because their modality requires it.
+    lossless_fallback_files: usize,
+}
+
+/// Result of the estimate command.
+#[derive(Debug, Serialize)]
+struct EstimateReport {
+    files: usize,
+    failed: usize,
+    original_bytes: u64,
+    scenarios: Vec<ScenarioEstimate>,
+}
+
+/// Benchmark output.
+#[derive(Serialize)]
+struct BenchReport {
+    /// Images in the corpus.
+    images: usize,
+    /// Uncompressed bytes in the corpus.
+    corpus_bytes: u64,
+    /// Timed repetitions per image.
+    iterations: u32,
+    /// Results per scenario.
+    results: Vec<BenchResult>,
+}
+
+/// Run the codec benchmark on a directory of DICOM files or the synthetic
+/// corpus.
+fn run_bench(
+    corpus_dir: Option<PathBuf>,
+    recursive: bool,
+    scenarios: Vec<CompressionConfig>,
+    iterations: u32,
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<()> {
+    let corpus = match &corpus_dir {
+        Some(dir) => {
+            let files = crate::batch::FileDiscovery::new()
+                .recursive(recursive)
+                .discover(dir)?;
+            let images: Vec<_> = files
+                .iter()
+                .filter_map(|path| {
+                    DicomFile::open(path)
+                        .and_then(|dicom| dicom.decode_image_data())
+                        .inspect_err(|e| log::warn!("Skipping {}: {}", path.display(), e))
+                        .ok()
+                })
+                .collect();
+            if images.is_empty() {
+                return Err(MedImgError::Config(format!(
+                    "No readable DICOM images in {}",
+                    dir.display()
+                )));
+            }
+            images
+        }
+        None => synthetic_corpus(),
+    };
+
+    let results = CodecBenchmark::new(scenarios)
+        .iterations(iterations)
+        .run(&corpus);
+    let report = BenchReport {
+        images: corpus.len(),
+        corpus_bytes: corpus.iter().map(|image| image.pixel_data.len() as u64).sum(),
+        iterations: iterations.max(1),
+        results,
+    };
+
+    if format == OutputFormat::Json {
+        print_json(&report)?;
+    } else if !quiet {
+        let source = corpus_dir
+            .as_deref()
+            .map_or("synthetic corpus".to_string(), |dir| dir.display().to_string());
+        println!("Codec Benchmark: {}", source);
+        println!("========================================");
+        println!(
+            "  Images: {} ({}), {} iterations",
+            report.images,
+            format_bytes(report.corpus_bytes),
+            report.iterations
+        );
+        println!();
+        println!(
+            "  {:<10} {:<18} {:>8} {:>12} {:>12} {:>9} {:>8} {:>9}",
+            "Codec", "Mode", "Ratio", "Encode MB/s", "Decode MB/s", "Verified", "Max err", "PSNR dB"
+        );
+        for result in &report.results {
+            let mode = match (result.mode, result.parameter) {
+                (CompressionMode::NearLossless, Some(near)) => format!("near-lossless ({})", near),
+                (CompressionMode::Lossy, Some(ratio)) => format!("lossy ({}:1)", ratio),
+                (mode, _) => format!("{:?}", mode).to_lowercase(),
+            };
+            println!(
+                "  {:<10} {:<18} {:>8.2} {:>12.1} {:>12.1} {:>9} {:>8} {:>9}",
+                result.codec,
+                mode,
+                result.ratio,
+                result.encode_mb_per_s,
+                result.decode_mb_per_s,
+                format!("{}/{}", result.verified, result.verified + result.failed),
+                result.max_error,
+                result
+                    .mean_psnr_db
+                    .map_or("-".to_string(), |psnr| format!("{:.1}", psnr))
+            );
+        }
+    }
 
-    /// Suppress all output except errors
-    #[arg(short, long, global = true)]
-    pub quiet: bool,
+    let failed: usize = report.results.iter().map(|r| r.failed).sum();
+    if failed > 0 {
+        return Err(MedImgError::VerificationFailed(format!(
+            "{} benchmark encodes failed or did not meet their mode's error bound",
+            failed
+        )));
+    }
+    Ok(())
 }
 
-/// CLI subcommands.
-#[derive(Subcommand, Debug)]
-pub enum Commands {
-    /// Compress a DICOM file
-    Compress {
-        /// Input DICOM file path
-        #[arg(short, long)]
-        input: PathBuf,
+/// Self-test output.
+#[derive(Serialize)]
+struct SelfTestReport {
+    /// Library version under test.
+    version: String,
+    /// Phantom width and height in pixels.
+    size: u32,
+    /// Cases that met their mode's guarantee.
+    passed: usize,
+    /// Cases that failed.
+    failed: usize,
+    /// Results per phantom and scenario.
+    cases: Vec<SelfTestCase>,
+}
 
-        /// Output file path (optional for analysis mode)
-        #[arg(short, long)]
-        output: Option<PathBuf>,
+/// Round-trip the synthetic phantoms through every scenario.
+fn run_selftest(
+    size: u32,
+    scenarios: &[CompressionConfig],
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<()> {
+    let phantoms = crate::testing::phantoms(size);
+    let cases = crate::testing::self_test(scenarios, &phantoms);
+    let passed = cases.iter().filter(|case| case.passed).count();
+    let report = SelfTestReport {
+        version: crate::version::full_version(),
+        size: phantoms[0].image.width,
+        passed,
+        failed: cases.len() - passed,
+        cases,
+    };
 
-        /// Compression codec to use
-        #[arg(short, long, value_enum, default_value = "jpeg2000")]
-        codec: CodecArg,
+    if format == OutputFormat::Json {
+        print_json(&report)?;
+    } else if !quiet {
+        println!("Self-test: {}", report.version);
+        println!("========================================");
+        for phantom in &phantoms {
+            println!("  {:<10} {}", phantom.name, phantom.description);
+        }
+        println!();
+        println!(
+            "  {:<10} {:<10} {:<18} {:>8} {:>8} {:>9}  Result",
+            "Phantom", "Codec", "Mode", "Ratio", "Max err", "PSNR dB"
+        );
+        for case in &report.cases {
+            let mode = match (case.mode, case.parameter) {
+                (CompressionMode::NearLossless, Some(near)) => format!("near-lossless ({})", near),
+                (CompressionMode::Lossy, Some(ratio)) => format!("lossy ({}:1)", ratio),
+                (mode, _) => format!("{:?}", mode).to_lowercase(),
+            };
+            println!(
+                "  {:<10} {:<10} {:<18} {:>8.2} {:>8} {:>9}  {}",
+                case.phantom,
+                case.codec,
+                mode,
+                case.ratio,
+                case.max_error,
+                case.psnr_db.map_or("-".to_string(), |psnr| format!("{:.1}", psnr)),
+                match &case.error {
+                    None => "ok".to_string(),
+                    Some(e) => format!("FAILED: {}", e),
+                }
+            );
+        }
+        println!();
+        println!("  {} passed, {} failed", report.passed, report.failed);
+    }
 
-        /// Compression mode
-        #[arg(short, long, value_enum, default_value = "lossless")]
-        mode: ModeArg,
+    if report.failed > 0 {
+        return Err(MedImgError::VerificationFailed(format!(
+            "{} of {} self-test cases failed",
+            report.failed,
+            report.cases.len()
+        )));
+    }
+    Ok(())
+}
 
-        /// Quality preset (for lossy compression)
-        #[arg(short = 'Q', long, value_enum, default_value = "diagnostic")]
-        quality: QualityArg,
+/// Codec/mode combinations projected by the estimate command.
+fn estimate_scenarios(
+    ratio: f32,
+    near: u16,
+    regulatory_profile: RegulatoryProfile,
+    lossless_modalities: Vec<crate::config::Modality>,
+) -> Vec<CompressionConfig> {
+    let near_lossless = CompressionConfig {
+        mode: CompressionMode::NearLossless,
+        near_lossless_error: near,
+        ..CompressionConfig::lossless(CompressionCodec::JpegLs)
+    };
 
-        /// Target compression ratio (for lossy mode)
-        #[arg(short = 'r', long)]
-        ratio: Option<f32>,
+    [
+        CompressionConfig::lossless(CompressionCodec::Jpeg2000),
+        CompressionConfig::lossless(CompressionCodec::JpegLs),
+        near_lossless,
+        CompressionConfig::lossy(CompressionCodec::Jpeg2000, ratio),
+    ]
+    .into_iter()
+    .map(|config| CompressionConfig {
+        regulatory_profile,
+        lossless_modalities: lossless_modalities.clone(),
+        ..config
+    })
+    .collect()
+}
 
-        /// Near-lossless error tolerance (JPEG-LS only, 0-255)
-        #[arg(long, default_value = "0")]
-        near: u8,
+/// Per-file sizes: on-disk bytes and projected bytes per scenario.
+fn estimate_file(path: &Path, scenarios: &[CompressionConfig]) -> Result<(u64, Vec<(u64, bool)>)> {
+    let file_bytes = std::fs::metadata(path)?.len();
+    let dicom = DicomFile::open(path)?;
+    let stored_pixels = if dicom.is_compressed() {
+        dicom.get_encapsulated_data()?.len()
+    } else {
+        dicom.get_pixel_data()?.len()
+    };
+    let header_bytes = file_bytes.saturating_sub(stored_pixels as u64);
+    let image = dicom.decode_image_data()?;
 
-        /// Verify lossless compression by round-trip decode
-        #[arg(long)]
-        verify: bool,
+    let projected = scenarios
+        .iter()
+        .map(|config| {
+            let lossless_sop_class = dicom
+                .metadata
+                .sop_class_uid
+                .as_deref()
+                .is_some_and(|uid| config.requires_lossless_sop_class(uid));
+            let fallback = config.mode != CompressionMode::Lossless
+                && (config.requires_lossless(dicom.modality()) || lossless_sop_class);
+            let size = if fallback {
+                let lossless = CompressionConfig::lossless(config.codec);
+                crate::codec::estimate::estimate_compressed_size(&image, &lossless)
+            } else {
+                crate::codec::estimate::estimate_compressed_size(&image, config)
+            };
+            (header_bytes + size as u64, fallback)
+        })
+        .collect();
 
-        /// Override modality safety checks (use with caution)
-        #[arg(long)]
-        force: bool,
+    Ok((file_bytes, projected))
+}
 
-        /// Dry run - analyze without writing output
-        #[arg(long)]
-        dry_run: bool,
-    },
+/// Run estimate command.
+fn run_estimate(
+    input_dir: PathBuf,
+    recursive: bool,
+    scenarios: Vec<CompressionConfig>,
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<()> {
+    use rayon::prelude::*;
 
-    /// Show information about a DICOM file
-    Info {
-        /// Input DICOM file path
-        #[arg(short, long)]
-        input: PathBuf,
+    let files = crate::batch::FileDiscovery::new()
+        .recursive(recursive)
+        .discover(&input_dir)?;
 
-        /// Show detailed metadata
-        #[arg(long)]
-        detailed: bool,
-    },
+    let results: Vec<_> = files
+        .par_iter()
+        .map(|path| {
+            estimate_file(path, &scenarios).inspect_err(|e| {
+                log::warn!("Skipping {}: {}", path.display(), e);
+            })
+        })
+        .collect();
 
-    /// Analyze compression potential without modifying files
-    Analyze {
-        /// Input DICOM file path
-        #[arg(short, long)]
-        input: PathBuf,
+    let mut report = EstimateReport {
+        files: files.len(),
+        failed: 0,
+        original_bytes: 0,
+        scenarios: scenarios
+            .iter()
+            .map(|config| ScenarioEstimate {
+                codec: config.codec,
+                mode: config.mode,
+                estimated_bytes: 0,
+                ratio: 1.0,
+                savings_percent: 0.0,
+                lossless_fallback_files: 0,
+            })
+            .collect(),
+    };
 
-        /// Codec to analyze
-        #[arg(short, long, value_enum, default_value = "jpeg2000")]
-        codec: CodecArg,
+    for result in results {
+        let Ok((original, projected)) = result else {
+            report.failed += 1;
+            continue;
+        };
+        report.original_bytes += original;
+        for (scenario, (bytes, fallback)) in report.scenarios.iter_mut().zip(projected) {
+            scenario.estimated_bytes += bytes;
+            scenario.lossless_fallback_files += fallback as usize;
+        }
+    }
 
-        /// Test both lossless and lossy modes
-        #[arg(long)]
-        all_modes: bool,
-    },
+    for scenario in &mut report.scenarios {
+        if scenario.estimated_bytes > 0 {
+            scenario.ratio = report.original_bytes as f64 / scenario.estimated_bytes as f64;
+            scenario.savings_percent = (1.0 - 1.0 / scenario.ratio) * 100.0;
+        }
+    }
+
+    if format == OutputFormat::Json {
+        print_json(&report)?;
+    } else if !quiet {
+        println!("Size Estimate: {}", input_dir.display());
+        println!("========================================");
+        println!(
+            "  Files: {} ({} unreadable)",
+            report.files - report.failed,
+            report.failed
+        );
+        println!("  Current size: {}", format_bytes(report.original_bytes));
+        println!();
+        for (scenario, config) in report.scenarios.iter().zip(&scenarios) {
+            let label = match scenario.mode {
+                CompressionMode::Lossless => "lossless".to_string(),
+                CompressionMode::NearLossless => format!("near-lossless (NEAR={})", config.near_lossless_error),
+                CompressionMode::Lossy => format!("lossy ({}:1)", config.target_ratio.unwrap_or(1.0)),
+            };
+            println!(
+                "  {:<10} {:<24} {:>12} ({:.2}:1, {:.1}% saved)",
+                format!("{:?}", scenario.codec),
+                label,
+                format_bytes(scenario.estimated_bytes),
+                scenario.ratio,
+                scenario.savings_percent
+            );
+            if scenario.lossless_fallback_files > 0 {
+                println!(
+                    "  {:<35} {} files kept lossless by modality policy",
+                    "", scenario.lossless_fallback_files
+                );
+            }
+        }
+    }
+
+    Ok(())
 }
 
-/// Compression codec argument.
-#[derive(ValueEnum, Clone, Debug)]
-pub enum CodecArg {
-    /// JPEG 2000 (recommended for most use cases)
-    Jpeg2000,
-    /// JPEG-LS (faster, good for simple images)
-    JpegLs,
+/// Format a byte count with a binary unit.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
 }
 
-impl From<CodecArg> for CompressionCodec {
-    fn from(arg: CodecArg) -> Self {
-        match arg {
-            CodecArg::Jpeg2000 => CompressionCodec::Jpeg2000,
-            CodecArg::JpegLs => CompressionCodec::JpegLs,
+/// Run compare command.
+fn run_compare(
+    original: PathBuf,
+    test: PathBuf,
+    region: Option<Rect>,
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<()> {
+    let reference = DicomFile::open(&original)?.decode_image_data()?;
+    let candidate = DicomFile::open(&test)?.decode_image_data()?;
+    let comparator = ImageComparator::new();
+    let report = match region {
+        Some(region) => comparator.compare_region(&reference, &candidate, region)?,
+        None => comparator.compare(&reference, &candidate)?,
+    };
+
+    if format == OutputFormat::Json {
+        let mut json = compare_json(&original, &test, &report)?;
+        if let Some(region) = region {
+            json["region"] = serde_json::json!({
+                "x": region.x,
+                "y": region.y,
+                "width": region.width,
+                "height": region.height,
+            });
+        }
+        print_json(&json)?;
+    } else if !quiet {
+        println!("Original: {}", original.display());
+        println!("Test: {}", test.display());
+        if let Some(region) = region {
+            println!("Region: {}", region);
+        }
+        println!();
+        print!("{}", report);
+        if !report.meets_diagnostic_quality() {
+            println!();
+            println!("✗ Does not meet diagnostic quality requirements");
         }
     }
+
+    Ok(())
 }
 
-/// Compression mode argument.
-#[derive(ValueEnum, Clone, Debug)]
-pub enum ModeArg {
-    /// Lossless compression (exact reconstruction)
-    Lossless,
-    /// Lossy compression (higher ratio, some quality loss)
-    Lossy,
-    /// Near-lossless (JPEG-LS only)
-    NearLossless,
+/// Run verify command.
+fn run_verify(
+    original: PathBuf,
+    compressed: PathBuf,
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<()> {
+    let reference = DicomFile::open(&original)?.decode_image_data()?;
+    let candidate = DicomFile::open(&compressed)?.decode_image_data()?;
+
+    let verdict = ImageComparator::new().verify_identical(&reference, &candidate);
+
+    if format == OutputFormat::Json {
+        print_json(&serde_json::json!({
+            "original": original.display().to_string(),
+            "compressed": compressed.display().to_string(),
+            "verified": verdict.is_ok(),
+            "error": verdict.as_ref().err().map(|e| e.to_string()),
+            "error_code": verdict.as_ref().err().map(|e| e.code()),
+        }))?;
+    } else if !quiet {
+        match verdict {
+            Ok(()) => println!(
+                "PASS: {} is bit-identical to {} ({} bytes)",
+                compressed.display(),
+                original.display(),
+                reference.pixel_data.len()
+            ),
+            Err(_) => println!(
+                "FAIL: {} does not match {}",
+                compressed.display(),
+                original.display()
+            ),
+        }
+    }
+
+    verdict
 }
 
-impl From<ModeArg> for CompressionMode {
-    fn from(arg: ModeArg) -> Self {
-        match arg {
-            ModeArg::Lossless => CompressionMode::Lossless,
-            ModeArg::Lossy => CompressionMode::Lossy,
-            ModeArg::NearLossless => CompressionMode::NearLossless,
+/// Run anonymize command.
+fn run_anonymize(
+    input: PathBuf,
+    output: PathBuf,
+    profile: DeidentificationProfile,
+    compression: Option<CompressionConfig>,
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<()> {
+    let mut dicom = DicomFile::open(&input)?;
+    access::read(&input, &dicom.metadata);
+    let summary = Anonymizer::new(profile).anonymize(&mut dicom)?;
+
+    let result = match compression {
+        Some(config) => {
+            Some(CompressionPipeline::new(config).compress_dicom_to(dicom, &input, &output)?)
+        }
+        None => {
+            dicom
+                .inner()
+                .write_to_file(&output)
+                .map_err(|e| MedImgError::dicom("Failed to write DICOM file", e))?;
+            access::written(&output, &dicom.metadata);
+            None
+        }
+    };
+
+    if format == OutputFormat::Json {
+        print_json(&serde_json::json!({
+            "input": input.display().to_string(),
+            "output": output.display().to_string(),
+            "profile": profile,
+            "deidentification": summary,
+            "compression": result,
+        }))?;
+    } else if !quiet {
+        println!("De-identified {} ({:?} profile)", input.display(), profile);
+        println!(
+            "  Removed: {}, emptied: {}, new UIDs: {}",
+            summary.removed, summary.emptied, summary.replaced_uids
+        );
+        if let Some(result) = &result {
+            print_compression_result(result);
         }
+        println!("  Output: {}", output.display());
     }
+
+    Ok(())
 }
 
-/// Quality preset argument.
-#[derive(ValueEnum, Clone, Debug)]
-pub enum QualityArg {
-    /// Diagnostic quality (lossless)
-    Diagnostic,
-    /// High quality lossy
-    HighQuality,
-    /// Standard quality
-    Standard,
-    /// Preview quality
-    Preview,
+/// Run import command.
+fn run_import(
+    input: PathBuf,
+    output: PathBuf,
+    capture: &SecondaryCapture,
+    raw: Option<RawLayout>,
+    config: CompressionConfig,
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<()> {
+    let image = crate::import::read_image(&input, raw.as_ref())?;
+    let pipeline = CompressionPipeline::new(config);
+    let result = crate::import::import_image(&image, capture, &pipeline, &input, &output)?;
+
+    if format == OutputFormat::Json {
+        print_json(&serde_json::json!({
+            "input": input.display().to_string(),
+            "output": output.display().to_string(),
+            "study_instance_uid": capture.study_instance_uid(),
+            "series_instance_uid": capture.series_instance_uid(),
+            "compression": result,
+        }))?;
+    } else if !quiet {
+        println!(
+            "Imported {} ({}x{}) as Secondary Capture",
+            input.display(),
+            image.width,
+            image.height
+        );
+        print_compression_result(&result);
+        println!("  Output: {}", output.display());
+    }
+
+    Ok(())
 }
 
-impl From<QualityArg> for QualityPreset {
-    fn from(arg: QualityArg) -> Self {
-        match arg {
-            QualityArg::Diagnostic => QualityPreset::Diagnostic,
-            QualityArg::HighQuality => QualityPreset::HighQuality,
-            QualityArg::Standard => QualityPreset::Standard,
-            QualityArg::Preview => QualityPreset::Preview,
+/// Run nifti command.
+#[cfg(feature = "nifti")]
+fn run_nifti(
+    input: PathBuf,
+    output: PathBuf,
+    capture: &SecondaryCapture,
+    compression: Option<CompressionConfig>,
+    format: OutputFormat,
+    quiet: bool,
+) -> Result<()> {
+    let volume = crate::nifti::NiftiVolume::open(&input)?;
+    let exact = volume.to_image()?.exact;
+    if !exact {
+        tracing::warn!(
+            "{}: voxels do not fit 16 bits and were quantized",
+            input.display()
+        );
+    }
+    std::fs::create_dir_all(&output).map_err(|e| MedImgError::from(e).with_file(&output))?;
+    let pipeline = compression.map(CompressionPipeline::new);
+
+    let mut files = Vec::new();
+    let mut results = Vec::new();
+    for (index, dicom) in volume.to_secondary_captures(capture)?.into_iter().enumerate() {
+        let path = output.join(format!("slice_{:04}.dcm", index + 1));
+        match &pipeline {
+            Some(pipeline) => results.push(pipeline.compress_dicom_to(dicom, &input, &path)?),
+            None => dicom
+                .inner()
+                .write_to_file(&path)
+                .map_err(|e| MedImgError::dicom("Failed to write DICOM file", e))?,
         }
+        files.push(path);
     }
-}
 
-/// Run the CLI application.
-pub fn run(cli: Cli) -> Result<()> {
-    // Initialize logging
-    if cli.verbose {
-        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug"))
-            .init();
-    } else if !cli.quiet {
-        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-            .init();
+    if format == OutputFormat::Json {
+        print_json(&serde_json::json!({
+            "input": input.display().to_string(),
+            "output": output.display().to_string(),
+            "study_instance_uid": capture.study_instance_uid(),
+            "series_instance_uid": capture.series_instance_uid(),
+            "slices": files.len(),
+            "exact": exact,
+            "compression": pipeline.is_some().then_some(&results),
+        }))?;
+    } else if !quiet {
+        println!(
+            "Converted {} ({}x{}, {} slices) to Secondary Capture",
+            input.display(),
+            volume.width(),
+            volume.height(),
+            files.len()
+        );
+        if !exact {
+            println!("  Voxels quantized to 16 bits (see Rescale Slope/Intercept)");
+        }
+        if !results.is_empty() {
+            let original: usize = results.iter().map(|r| r.original_size).sum();
+            let compressed: usize = results.iter().map(|r| r.compressed_size).sum();
+            println!("  Compressed {} -> {} bytes", original, compressed);
+        }
+        println!("  Output: {}", output.display());
     }
 
-    match cli.command {
-        Commands::Compress {
-            input,
-            output,
-            codec,
-            mode,
-            quality,
-            ratio,
-            near,
-            verify,
-            force,
-            dry_run,
-        } => {
-            run_compress(
-                input,
-                output,
-                codec.into(),
-                mode.into(),
-                quality.into(),
-                ratio,
-                near,
-                verify,
-                force,
-                dry_run,
-                cli.quiet,
-            )
-        }
-        Commands::Info { input, detailed } => run_info(input, detailed, cli.quiet),
-        Commands::Analyze {
-            input,
-            codec,
-            all_modes,
-        } => run_analyze(input, codec.into(), all_modes, cli.quiet),
+    Ok(())
+}
+
+/// Run dump command.
+fn run_dump(input: PathBuf, tags: &[String], format: OutputFormat, quiet: bool) -> Result<()> {
+    let filter = tags
+        .iter()
+        .map(|t| crate::dicom::utils::parse_tag(t))
+        .collect::<Result<Vec<_>>>()?;
+    let dicom = DicomFile::open(&input)?;
+    access::read(&input, &dicom.metadata);
+    let elements = dicom.dump(&filter);
+
+    if format == OutputFormat::Json {
+        print_json(&elements)?;
+    } else if !quiet {
+        let mut out = std::io::stdout().lock();
+        for element in &elements {
+            writeln!(out, "{}", element)?;
+        }
     }
+
+    Ok(())
 }
 
-/// Run compression command.
-fn run_compress(
+/// Run export command.
+fn run_export(
     input: PathBuf,
-    _output: Option<PathBuf>,
-    codec: CompressionCodec,
-    mode: CompressionMode,
-    quality: QualityPreset,
-    ratio: Option<f32>,
-    near: u8,
-    verify: bool,
-    force: bool,
-    dry_run: bool,
+    output: PathBuf,
+    options: PreviewOptions,
+    format: OutputFormat,
     quiet: bool,
 ) -> Result<()> {
-    let config = CompressionConfig {
-        codec,
-        mode,
-        quality,
-        target_ratio: ratio.or_else(|| quality.target_ratio()),
-        quality_layers: quality.quality_layers(),
-        near_lossless_error: near,
-        verify_compression: verify,
-        override_safety_checks: force,
-        ..Default::default()
-    };
+    let dicom = DicomFile::open(&input)?;
+    access::read(&input, &dicom.metadata);
+    crate::export::export_preview(&dicom, &output, &options)?;
+    access::written(&output, &dicom.metadata);
 
-    let pipeline = CompressionPipeline::new(config).dry_run(dry_run);
-    let result = pipeline.compress_file(&input)?;
+    if format == OutputFormat::Json {
+        print_json(&serde_json::json!({
+            "input": input.display().to_string(),
+            "output": output.display().to_string(),
+            "frame": options.frame,
+        }))?;
+    } else if !quiet {
+        println!(
+            "Exported frame {} of {} to {}",
+            options.frame,
+            input.display(),
+            output.display()
+        );
+    }
 
-    if !quiet {
-        print_compression_result(&result);
+    Ok(())
+}
+
+/// Build the JSON form of a quality report.
+fn compare_json(
+    original: &Path,
+    test: &Path,
+    report: &QualityReport,
+) -> Result<serde_json::Value> {
+    let mut value = serde_json::to_value(report)
+        .map_err(|e| MedImgError::Internal(format!("Failed to serialize report: {}", e)))?;
+
+    if let Some(fields) = value.as_object_mut() {
+        fields.insert("original".into(), original.display().to_string().into());
+        fields.insert("test".into(), test.display().to_string().into());
+        fields.insert("overall_quality".into(), report.overall_quality().into());
+        fields.insert("lossless".into(), report.is_lossless().into());
+        fields.insert(
+            "meets_diagnostic_quality".into(),
+            report.meets_diagnostic_quality().into(),
+        );
     }
 
+    Ok(value)
+}
+
+/// Print a value as pretty-printed JSON on stdout.
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| MedImgError::Internal(format!("Failed to serialize output: {}", e)))?;
+    // Write instead of println! so a closed pipe is an error rather than a panic
+    writeln!(std::io::stdout().lock(), "{}", json)?;
+    Ok(())
+}
+
+/// Print a value as single-line JSON on stdout.
+fn print_json_line<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| MedImgError::Internal(format!("Failed to serialize output: {}", e)))?;
+    writeln!(std::io::stdout().lock(), "{}", json)?;
     Ok(())
 }
 
 /// Run info command.
-fn run_info(input: PathBuf, detailed: bool, quiet: bool) -> Result<()> {
+fn run_info(input: PathBuf, detailed: bool, format: OutputFormat, quiet: bool) -> Result<()> {
     let dicom = DicomFile::open(&input)?;
+    access::read(&input, &dicom.metadata);
     let metadata = &dicom.metadata;
 
+    if format == OutputFormat::Json {
+        return print_json(&info_json(&input, &dicom, detailed));
+    }
+
     if quiet {
         return Ok(());
     }
@@ -297,7 +3473,7 @@ fn run_info(input: PathBuf, detailed: bool, quiet: bool) -> Result<()> {
     );
     println!();
 
-    println!("Modality: {:?}", metadata.modality);
+    println!("Modality: {}", metadata.modality);
     if metadata.modality.requires_lossless() {
         println!("  Note: This modality requires lossless compression (FDA/ACR)");
     }
@@ -317,6 +3493,12 @@ fn run_info(input: PathBuf, detailed: bool, quiet: bool) -> Result<()> {
         if let Some(ref uid) = metadata.sop_instance_uid {
             println!("  SOP Instance UID: {}", uid);
         }
+        if let Some(ref uid) = metadata.sop_class_uid {
+            match crate::config::sop_class::name(uid) {
+                Some(name) => println!("  SOP Class UID: {} ({})", uid, name),
+                None => println!("  SOP Class UID: {}", uid),
+            }
+        }
     }
 
     // Calculate pixel data size
@@ -328,13 +3510,73 @@ fn run_info(input: PathBuf, detailed: bool, quiet: bool) -> Result<()> {
     Ok(())
 }
 
+/// Build the JSON form of the info command output.
+///
+/// Patient and instance identifiers are only included with `--detailed`,
+/// matching the text output.
+fn info_json(input: &Path, dicom: &DicomFile, detailed: bool) -> serde_json::Value {
+    let metadata = &dicom.metadata;
+    let mut value = serde_json::json!({
+        "file": input.display().to_string(),
+        "width": metadata.width,
+        "height": metadata.height,
+        "bits_stored": metadata.bits_stored,
+        "bits_allocated": metadata.bits_allocated,
+        "samples_per_pixel": metadata.samples_per_pixel,
+        "photometric_interpretation": metadata.photometric_interpretation,
+        "number_of_frames": metadata.number_of_frames,
+        "signed": metadata.pixel_representation == 1,
+        "transfer_syntax": {
+            "uid": metadata.transfer_syntax,
+            "name": crate::dicom::utils::transfer_syntax_name(&metadata.transfer_syntax),
+            "compressed": dicom.is_compressed(),
+        },
+        "modality": metadata.modality,
+        "requires_lossless": metadata.modality.requires_lossless(),
+        "expected_pixel_data_bytes": crate::dicom::utils::calculate_pixel_data_size(metadata),
+    });
+
+    if detailed {
+        value["uids"] = serde_json::json!({
+            "patient_id": metadata.patient_id,
+            "study_uid": metadata.study_uid,
+            "series_uid": metadata.series_uid,
+            "sop_instance_uid": metadata.sop_instance_uid,
+            "sop_class_uid": metadata.sop_class_uid,
+        });
+    }
+
+    value
+}
+
 /// Run analyze command.
 fn run_analyze(
     input: PathBuf,
     codec: CompressionCodec,
     all_modes: bool,
+    format: OutputFormat,
     quiet: bool,
 ) -> Result<()> {
+    if format == OutputFormat::Json {
+        return if all_modes {
+            let mode_json = |config: CompressionConfig| {
+                match CompressionPipeline::new(config).analyze(&input) {
+                    Ok(result) => serde_json::to_value(&result)
+                        .unwrap_or_else(|e| serde_json::json!({ "error": e.to_string() })),
+                    Err(e) => serde_json::json!({ "error": e.to_string(), "error_code": e.code() }),
+                }
+            };
+            print_json(&serde_json::json!({
+                "file": input.display().to_string(),
+                "lossless": mode_json(CompressionConfig::lossless(codec)),
+                "lossy": mode_json(CompressionConfig::lossy(codec, 10.0)),
+            }))
+        } else {
+            let pipeline = CompressionPipeline::new(CompressionConfig::lossless(codec));
+            print_json(&pipeline.analyze(&input)?)
+        };
+    }
+
     if all_modes {
         // Test both lossless and lossy
         let lossless_config = CompressionConfig::lossless(codec);
@@ -378,6 +3620,23 @@ fn run_analyze(
     Ok(())
 }
 
+/// Print batch statistics.
+fn print_batch_stats(stats: &BatchStats) {
+    println!("Batch Result:");
+    println!("  Files: {}", stats.total_files);
+    println!("  Successful: {}", stats.successful);
+    println!("  Failed: {}", stats.failed);
+    println!("  Skipped: {}", stats.skipped);
+    println!("  Overall Ratio: {:.2}:1", stats.overall_ratio());
+    println!("  Space Savings: {:.1}%", stats.overall_savings_percent());
+    println!("  Time: {} ms", stats.total_time_ms);
+
+    if let Some(ref quality) = stats.quality {
+        println!();
+        print!("{}", quality);
+    }
+}
+
 /// Print compression result.
 fn print_compression_result(result: &CompressionResult) {
     println!("Compression Result:");
@@ -415,3 +3674,112 @@ fn print_compression_result(result: &CompressionResult) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_status_for_error() {
+        let cases = [
+            (MedImgError::Config("x".into()), ExitStatus::InvalidArguments),
+            (MedImgError::VerificationFailed("x".into()), ExitStatus::VerificationFailed),
+            (MedImgError::Dicom("x".into()), ExitStatus::DicomError),
+            (MedImgError::Validation("x".into()), ExitStatus::InvalidArguments),
+            (MedImgError::CompressionConstraint("x".into()), ExitStatus::PolicyViolation),
+            (MedImgError::PolicyViolation("x".into()), ExitStatus::PolicyViolation),
+            (MedImgError::Codec("x".into()), ExitStatus::Failure),
+            (MedImgError::Storage("x".into()), ExitStatus::Failure),
+        ];
+
+        for (error, expected) in cases {
+            assert_eq!(ExitStatus::for_error(&error), expected, "{}", error);
+        }
+        assert_eq!(ExitStatus::PartialBatchFailure.code(), 6);
+    }
+
+    #[test]
+    fn test_codec_tuning_validated_against_codec() {
+        let cli = Cli::try_parse_from([
+            "medimg", "compress", "-i", "a.dcm", "-c", "jpeg-ls", "--jls-t1", "4", "--jls-t2",
+            "8", "--jls-t3", "30",
+        ])
+        .unwrap();
+        let Commands::Compress { tuning, .. } = cli.command else {
+            panic!("expected compress");
+        };
+
+        let mut config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+        tuning.clone().apply(&mut config).unwrap();
+        assert_eq!(config.jpegls_thresholds.unwrap().t3, 30);
+        assert_eq!(config.jpegls_thresholds.unwrap().reset, 64);
+
+        let mut j2k = CompressionConfig::lossless(CompressionCodec::Jpeg2000);
+        assert!(matches!(tuning.apply(&mut j2k), Err(MedImgError::Config(_))));
+
+        // T1-T3 must be given together
+        assert!(Cli::try_parse_from(["medimg", "compress", "-i", "a.dcm", "--jls-t1", "4"]).is_err());
+    }
+
+    #[test]
+    fn test_layered_settings() {
+        let file_config = FileConfig::from_toml("quality = \"standard\"\nratio = 15.0\nnear = 3").unwrap();
+        let layered = |overrides: CompressionOverrides| {
+            layered_config(CompressionConfig::default(), &file_config, &overrides).unwrap()
+        };
+
+        // File values apply only in the mode that uses them
+        let lossless = layered(CompressionOverrides::default());
+        assert_eq!((lossless.target_ratio, lossless.near_lossless_error), (None, 0));
+        assert_eq!(lossless.quality_layers, 3);
+
+        let lossy = layered(CompressionOverrides {
+            mode: Some(CompressionMode::Lossy),
+            ..Default::default()
+        });
+        assert_eq!(lossy.target_ratio, Some(15.0));
+
+        // A command-line target replaces the file's
+        let bpp = layered(CompressionOverrides {
+            mode: Some(CompressionMode::Lossy),
+            target_bpp: Some(1.0),
+            ..Default::default()
+        });
+        assert_eq!((bpp.target_ratio, bpp.target_bpp), (None, Some(1.0)));
+        assert!(Cli::try_parse_from(["medimg", "compress", "-i", "a.dcm", "-r", "10", "--bpp", "1"]).is_err());
+
+        let near = layered(CompressionOverrides {
+            codec: Some(CompressionCodec::JpegLs),
+            mode: Some(CompressionMode::NearLossless),
+            ..Default::default()
+        });
+        assert_eq!(near.near_lossless_error, 3);
+        assert_eq!(layered_config(near, &FileConfig::default(), &Default::default()).unwrap().near_lossless_error, 3);
+
+        // Explicit values the mode does not use are reported
+        let explicit = layered(CompressionOverrides {
+            target_ratio: Some(10.0),
+            ..Default::default()
+        });
+        assert!(matches!(check_config(&explicit), Err(MedImgError::Config(_))));
+    }
+
+    #[test]
+    fn test_custom_quality_presets() {
+        let config = FileConfig::from_toml(
+            "quality = \"teaching\"\n[quality_presets.teaching]\nratio = 15.0\nlayers = 4",
+        )
+        .unwrap();
+        let preset = resolve_quality(config.quality, &config.quality_presets).unwrap();
+        assert_eq!(preset.label(), "teaching");
+        assert_eq!(preset.target_ratio(), Some(15.0));
+        assert_eq!(preset.quality_layers(), 4);
+
+        // Built-in names are matched case-insensitively and win over custom ones
+        let builtin = resolve_quality(Some("Standard".parse().unwrap()), &config.quality_presets);
+        assert_eq!(builtin.unwrap(), QualityPreset::Standard);
+
+        let err = resolve_quality(Some("archive".parse().unwrap()), &config.quality_presets);
+        assert!(err.unwrap_err().to_string().contains("custom: teaching"));
+    }
+}
@@ -0,0 +1,38 @@
+//! Shutdown signal handling for long-running commands.
+
+use crate::progress::CancellationToken;
+
+/// Cancel `token` when the process receives SIGINT or SIGTERM.
+///
+/// Signal handlers may only touch async-signal-safe state, so the handler
+/// sets a flag and a helper thread forwards it to the token.
+#[cfg(unix)]
+pub(super) fn cancel_on_shutdown(token: CancellationToken) {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+    extern "C" fn on_signal(_: libc::c_int) {
+        SHUTDOWN.store(true, Ordering::SeqCst);
+    }
+
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+
+    std::thread::spawn(move || {
+        while !SHUTDOWN.load(Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        log::info!("Shutdown requested, finishing files in progress");
+        token.cancel();
+    });
+}
+
+/// Signals are not intercepted on this platform; Ctrl+C terminates.
+#[cfg(not(unix))]
+pub(super) fn cancel_on_shutdown(_token: CancellationToken) {}
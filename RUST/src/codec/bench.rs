@@ -0,0 +1,303 @@
+//! Codec benchmarking harness.
+//!
+//! Encodes and decodes a corpus with each codec/mode scenario, measuring
+//! compression ratio and single-threaded throughput, and checks the decoded
+//! images against each mode's guarantee: exact reconstruction for lossless,
+//! errors within NEAR for near-lossless. Lossy scenarios only need to
+//! decode; their PSNR is reported instead.
+
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::config::{CompressionCodec, CompressionConfig, CompressionMode};
+use crate::error::Result;
+use crate::metrics::ImageComparator;
+use crate::ImageData;
+
+use super::CodecFactory;
+
+/// Benchmark results for one codec/mode scenario.
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchResult {
+    /// Codec name.
+    pub codec: String,
+    /// Compression mode.
+    pub mode: CompressionMode,
+    /// NEAR for near-lossless, target ratio for lossy.
+    pub parameter: Option<f64>,
+    /// Images encoded successfully.
+    pub images: usize,
+    /// Uncompressed bytes of the encoded images.
+    pub original_bytes: u64,
+    /// Compressed bytes of the encoded images.
+    pub compressed_bytes: u64,
+    /// Overall compression ratio.
+    pub ratio: f64,
+    /// Encode throughput in MB/s of uncompressed data.
+    pub encode_mb_per_s: f64,
+    /// Decode throughput in MB/s of uncompressed data.
+    pub decode_mb_per_s: f64,
+    /// Images whose decoded output met the mode's guarantee.
+    pub verified: usize,
+    /// Images that failed to encode, decode, or verify.
+    pub failed: usize,
+    /// Largest absolute pixel error across the corpus.
+    pub max_error: u64,
+    /// Mean PSNR in dB of images with errors (`None` if all were exact).
+    pub mean_psnr_db: Option<f64>,
+}
+
+/// Runs codec/mode scenarios over a corpus of images.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::codec::bench::{default_scenarios, synthetic_corpus, CodecBenchmark};
+///
+/// let results = CodecBenchmark::new(default_scenarios(10.0, 2))
+///     .iterations(5)
+///     .run(&synthetic_corpus());
+/// ```
+pub struct CodecBenchmark {
+    /// Scenarios to run.
+    scenarios: Vec<CompressionConfig>,
+
+    /// Timed encode/decode repetitions per image.
+    iterations: u32,
+}
+
+impl CodecBenchmark {
+    /// Create a benchmark for the given scenarios.
+    pub fn new(scenarios: Vec<CompressionConfig>) -> Self {
+        Self {
+            scenarios,
+            iterations: 3,
+        }
+    }
+
+    /// Set the number of timed repetitions per image (at least 1).
+    pub fn iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations.max(1);
+        self
+    }
+
+    /// Run every scenario over `corpus`.
+    pub fn run(&self, corpus: &[ImageData]) -> Vec<BenchResult> {
+        self.scenarios
+            .iter()
+            .map(|config| self.run_scenario(config, corpus))
+            .collect()
+    }
+
+    /// Run one scenario over the corpus.
+    fn run_scenario(&self, config: &CompressionConfig, corpus: &[ImageData]) -> BenchResult {
+        let codec = CodecFactory::for_config(config);
+        let mut result = BenchResult {
+            codec: match &codec {
+                Ok(codec) => codec.info().name.to_string(),
+                Err(_) => format!("{:?}", config.codec),
+            },
+            mode: config.mode,
+            parameter: match config.mode {
+                CompressionMode::Lossless => None,
+                CompressionMode::NearLossless => Some(config.near_lossless_error as f64),
+                CompressionMode::Lossy => config.target_ratio.map(f64::from),
+            },
+            images: 0,
+            original_bytes: 0,
+            compressed_bytes: 0,
+            ratio: 0.0,
+            encode_mb_per_s: 0.0,
+            decode_mb_per_s: 0.0,
+            verified: 0,
+            failed: 0,
+            max_error: 0,
+            mean_psnr_db: None,
+        };
+        let codec = match codec {
+            Ok(codec) => codec,
+            Err(e) => {
+                log::warn!("{} {:?} failed: {}", result.codec, config.mode, e);
+                result.failed = corpus.len();
+                return result;
+            }
+        };
+
+        let mut encode_time = Duration::ZERO;
+        let mut decode_time = Duration::ZERO;
+        let mut timed_bytes = 0u64;
+        let mut psnr_sum = 0.0;
+        let mut psnr_count = 0;
+
+        for image in corpus {
+            let outcome = (|| -> Result<(Vec<u8>, ImageData)> {
+                let mut encoded = Vec::new();
+                let mut decoded = None;
+                for _ in 0..self.iterations {
+                    let start = Instant::now();
+                    encoded = codec.encode(image, config, None)?;
+                    encode_time += start.elapsed();
+
+                    let start = Instant::now();
+                    decoded = Some(codec.decode(
+                        &encoded,
+                        image.width,
+                        image.height,
+                        image.bits_per_sample,
+                        image.samples_per_pixel,
+                    )?);
+                    decode_time += start.elapsed();
+                }
+                let decoded = decoded.expect("at least one iteration");
+                Ok((encoded, decoded))
+            })();
+
+            let (encoded, mut decoded) = match outcome {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    log::warn!("{} {:?} failed: {}", result.codec, config.mode, e);
+                    result.failed += 1;
+                    continue;
+                }
+            };
+
+            result.images += 1;
+            result.original_bytes += image.pixel_data.len() as u64;
+            result.compressed_bytes += encoded.len() as u64;
+            timed_bytes += image.pixel_data.len() as u64 * self.iterations as u64;
+
+            decoded.is_signed = image.is_signed;
+            match ImageComparator::new().compare(image, &decoded) {
+                Ok(report) => {
+                    result.max_error = result.max_error.max(report.max_error);
+                    if report.max_error > 0 {
+                        psnr_sum += report.psnr.psnr_db;
+                        psnr_count += 1;
+                    }
+                    let verified = match config.mode {
+                        CompressionMode::Lossless => report.max_error == 0,
+                        CompressionMode::NearLossless => {
+                            report.max_error <= config.near_lossless_error as u64
+                        }
+                        CompressionMode::Lossy => true,
+                    };
+                    if verified {
+                        result.verified += 1;
+                    } else {
+                        result.failed += 1;
+                    }
+                }
+                Err(e) => {
+                    log::warn!("{} {:?} verification failed: {}", result.codec, config.mode, e);
+                    result.failed += 1;
+                }
+            }
+        }
+
+        if result.compressed_bytes > 0 {
+            result.ratio = result.original_bytes as f64 / result.compressed_bytes as f64;
+        }
+        result.encode_mb_per_s = throughput(timed_bytes, encode_time);
+        result.decode_mb_per_s = throughput(timed_bytes, decode_time);
+        if psnr_count > 0 {
+            result.mean_psnr_db = Some(psnr_sum / psnr_count as f64);
+        }
+        result
+    }
+}
+
+/// Throughput in MB/s (0 if no time was measured).
+fn throughput(bytes: u64, elapsed: Duration) -> f64 {
+    let seconds = elapsed.as_secs_f64();
+    if seconds > 0.0 {
+        bytes as f64 / seconds / 1e6
+    } else {
+        0.0
+    }
+}
+
+/// Default scenarios: lossless for both codecs, JPEG-LS near-lossless,
+/// and JPEG 2000 lossy.
+pub fn default_scenarios(ratio: f32, near: u16) -> Vec<CompressionConfig> {
+    vec![
+        CompressionConfig::lossless(CompressionCodec::Jpeg2000),
+        CompressionConfig::lossless(CompressionCodec::JpegLs),
+        CompressionConfig {
+            mode: CompressionMode::NearLossless,
+            near_lossless_error: near,
+            ..CompressionConfig::lossless(CompressionCodec::JpegLs)
+        },
+        CompressionConfig::lossy(CompressionCodec::Jpeg2000, ratio),
+    ]
+}
+
+/// Synthetic corpus used when no images are supplied: an 8-bit
+/// radiograph-like image and a 12-bit CT-like slice, both 512x512 with
+/// smooth structure and mild noise.
+pub fn synthetic_corpus() -> Vec<ImageData> {
+    const SIZE: u32 = 512;
+
+    let sample = |x: u32, y: u32, max: f64| {
+        let (fx, fy) = (x as f64 / SIZE as f64 - 0.5, y as f64 / SIZE as f64 - 0.5);
+        let body = (1.0 - (fx * fx + fy * fy) * 3.0).max(0.0);
+        let texture = ((x as f64 * 0.15).sin() * (y as f64 * 0.11).cos() + 1.0) * 0.05;
+        let noise = (hash(y * SIZE + x) % 16) as f64 / 16.0 * 0.02;
+        ((body * 0.8 + texture + noise).min(1.0) * max) as u32
+    };
+
+    let radiograph: Vec<u8> = (0..SIZE * SIZE)
+        .map(|i| sample(i % SIZE, i / SIZE, 255.0) as u8)
+        .collect();
+    let ct: Vec<u8> = (0..SIZE * SIZE)
+        .flat_map(|i| (sample(i % SIZE, i / SIZE, 4095.0) as u16).to_le_bytes())
+        .collect();
+
+    let mut ct = ImageData::new(SIZE, SIZE, 16, 1, ct);
+    ct.photometric_interpretation = "MONOCHROME2".into();
+    let mut radiograph = ImageData::new(SIZE, SIZE, 8, 1, radiograph);
+    radiograph.photometric_interpretation = "MONOCHROME2".into();
+    vec![radiograph, ct]
+}
+
+/// Integer hash (lowbias32) used as a deterministic noise source.
+fn hash(mut h: u32) -> u32 {
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_benchmark_scenarios() {
+        let corpus = vec![ImageData::new(32, 32, 8, 1, (0..1024).map(|i| (i % 251) as u8).collect::<Vec<_>>())];
+        let results = CodecBenchmark::new(default_scenarios(10.0, 2))
+            .iterations(1)
+            .run(&corpus);
+
+        assert_eq!(results.len(), 4);
+        for result in &results {
+            assert_eq!(result.verified + result.failed, 1);
+            assert_eq!(result.original_bytes, 1024);
+        }
+
+        let lossless = &results[1];
+        assert_eq!(lossless.verified, 1);
+        assert_eq!(lossless.max_error, 0);
+        assert_eq!(lossless.mean_psnr_db, None);
+        assert_eq!(results[2].parameter, Some(2.0));
+    }
+
+    #[test]
+    fn test_synthetic_corpus() {
+        let corpus = synthetic_corpus();
+        assert_eq!(corpus.len(), 2);
+        assert!(corpus.iter().all(|image| image.validate().is_ok()));
+    }
+}
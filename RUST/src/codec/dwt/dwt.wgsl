@@ -0,0 +1,89 @@
+// Forward 5/3 and 9/7 lifting, one invocation per line. Mirrors the CPU
+// backend in mod.rs.
+
+struct Params {
+    // Distance between consecutive samples of a line
+    along: u32,
+    // Distance between the first samples of consecutive lines
+    across: u32,
+    // Samples per line
+    len: u32,
+    // Number of lines
+    lines: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> ints: array<i32>;
+@group(0) @binding(2) var<storage, read_write> floats: array<f32>;
+@group(0) @binding(3) var<storage, read_write> int_scratch: array<i32>;
+@group(0) @binding(4) var<storage, read_write> float_scratch: array<f32>;
+
+const ALPHA: f32 = -1.5861343;
+const BETA: f32 = -0.052980118;
+const GAMMA: f32 = 0.8829111;
+const DELTA: f32 = 0.44350687;
+const K: f32 = 1.2301741;
+
+// Index of sample `i` of the line starting at `base`, with whole-sample
+// symmetric extension
+fn at(base: u32, i: i32) -> u32 {
+    let n = i32(params.len);
+    let period = 2 * (n - 1);
+    var j = ((i % period) + period) % period;
+    if (j >= n) {
+        j = period - j;
+    }
+    return base + u32(j) * params.along;
+}
+
+// Position of sample `i` after splitting into low and high halves
+fn split(i: u32) -> u32 {
+    let low = (params.len + 1u) / 2u;
+    return select(low + i / 2u, i / 2u, i % 2u == 0u);
+}
+
+@compute @workgroup_size(64)
+fn forward_53(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.lines || params.len < 2u) {
+        return;
+    }
+    let base = id.x * params.across;
+    let n = i32(params.len);
+    for (var i = 1; i < n; i += 2) {
+        ints[at(base, i)] -= (ints[at(base, i - 1)] + ints[at(base, i + 1)]) >> 1u;
+    }
+    for (var i = 0; i < n; i += 2) {
+        ints[at(base, i)] += (ints[at(base, i - 1)] + ints[at(base, i + 1)] + 2) >> 2u;
+    }
+    for (var i = 0u; i < params.len; i++) {
+        int_scratch[base + split(i) * params.along] = ints[base + i * params.along];
+    }
+    for (var i = 0u; i < params.len; i++) {
+        ints[base + i * params.along] = int_scratch[base + i * params.along];
+    }
+}
+
+fn lift_97(base: u32, first: i32, coefficient: f32) {
+    for (var i = first; i < i32(params.len); i += 2) {
+        floats[at(base, i)] += coefficient * (floats[at(base, i - 1)] + floats[at(base, i + 1)]);
+    }
+}
+
+@compute @workgroup_size(64)
+fn forward_97(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.lines || params.len < 2u) {
+        return;
+    }
+    let base = id.x * params.across;
+    lift_97(base, 1, ALPHA);
+    lift_97(base, 0, BETA);
+    lift_97(base, 1, GAMMA);
+    lift_97(base, 0, DELTA);
+    for (var i = 0u; i < params.len; i++) {
+        let scale = select(K / 2.0, 1.0 / K, i % 2u == 0u);
+        float_scratch[base + split(i) * params.along] = floats[base + i * params.along] * scale;
+    }
+    for (var i = 0u; i < params.len; i++) {
+        floats[base + i * params.along] = float_scratch[base + i * params.along];
+    }
+}
@@ -0,0 +1,278 @@
+//! wgpu compute backend (`gpu` feature).
+
+use std::sync::{mpsc, OnceLock};
+
+use wgpu::util::DeviceExt;
+
+use super::{level_sizes, CpuDwt, DwtBackend};
+use crate::error::{MedImgError, Result};
+
+/// Invocations per workgroup (`@workgroup_size` in `dwt.wgsl`).
+const WORKGROUP: u32 = 64;
+
+/// Buffer bindings of the shader's storage arrays.
+const INTS: u32 = 1;
+const FLOATS: u32 = 2;
+const INT_SCRATCH: u32 = 3;
+const FLOAT_SCRATCH: u32 = 4;
+
+/// Compute pipelines on the first suitable adapter.
+pub(super) struct GpuDwt {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    forward_53: wgpu::ComputePipeline,
+    forward_97: wgpu::ComputePipeline,
+}
+
+/// Shader parameters (`Params` in `dwt.wgsl`).
+struct Params {
+    along: u32,
+    across: u32,
+    len: u32,
+    lines: u32,
+}
+
+impl Params {
+    fn bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16);
+        for word in [self.along, self.across, self.len, self.lines] {
+            bytes.extend_from_slice(&word.to_ne_bytes());
+        }
+        bytes
+    }
+}
+
+impl GpuDwt {
+    /// The process-wide backend, or `None` without a hardware adapter.
+    pub(super) fn shared() -> Option<&'static GpuDwt> {
+        static GPU: OnceLock<Option<GpuDwt>> = OnceLock::new();
+        GPU.get_or_init(|| match Self::new() {
+            Ok(gpu) => Some(gpu),
+            Err(reason) => {
+                log::debug!("Wavelet transform stays on the CPU: {}", reason);
+                None
+            }
+        })
+        .as_ref()
+    }
+
+    fn new() -> std::result::Result<Self, String> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: wgpu::Backends::PRIMARY,
+            ..Default::default()
+        });
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .ok_or("no adapter")?;
+        let info = adapter.get_info();
+        // Software rasterizers are slower than the CPU backend
+        if info.device_type == wgpu::DeviceType::Cpu {
+            return Err(format!("{} is a software adapter", info.name));
+        }
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("medimg dwt"),
+                required_limits: adapter.limits(),
+                ..Default::default()
+            },
+            None,
+        ))
+        .map_err(|e| format!("{}: {}", info.name, e))?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("dwt.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("dwt.wgsl").into()),
+        });
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: None,
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let (forward_53, forward_97) = (pipeline("forward_53"), pipeline("forward_97"));
+        log::info!("Wavelet transform on {} ({:?})", info.name, info.backend);
+        Ok(Self {
+            device,
+            queue,
+            forward_53,
+            forward_97,
+        })
+    }
+
+    /// Whether a buffer of `size` bytes can be bound.
+    fn fits(&self, size: usize) -> bool {
+        size > 0 && size as u64 <= u64::from(self.device.limits().max_storage_buffer_binding_size)
+    }
+
+    fn storage(&self, label: &str, contents: &[u8]) -> wgpu::Buffer {
+        self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        })
+    }
+
+    /// One dispatch of `pipeline` with `params` and the given storage
+    /// buffers.
+    fn dispatch(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::ComputePipeline,
+        params: &Params,
+        buffers: &[(u32, &wgpu::Buffer)],
+        groups: (u32, u32),
+    ) {
+        let uniform = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("dwt params"),
+            contents: &params.bytes(),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let entries: Vec<_> = std::iter::once((0, &uniform))
+            .chain(buffers.iter().copied())
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        let group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &entries,
+        });
+        let mut pass = encoder.begin_compute_pass(&Default::default());
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &group, &[]);
+        pass.dispatch_workgroups(groups.0, groups.1, 1);
+    }
+
+    /// Run every level of a transform on `words`, one row pass and one
+    /// column pass per level.
+    fn transform(
+        &self,
+        pipeline: &wgpu::ComputePipeline,
+        (data, scratch): (u32, u32),
+        words: &[u8],
+        width: usize,
+        height: usize,
+        levels: u8,
+    ) -> Result<Vec<u8>> {
+        let plane = self.storage("dwt plane", words);
+        let scratch_buffer = self.storage("dwt scratch", &vec![0; words.len()]);
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        let width32 = width as u32;
+        for (w, h) in level_sizes(width, height, levels) {
+            let (w, h) = (w as u32, h as u32);
+            let passes = [
+                Params { along: 1, across: width32, len: w, lines: h },
+                Params { along: width32, across: 1, len: h, lines: w },
+            ];
+            for params in passes {
+                let groups = (params.lines.div_ceil(WORKGROUP), 1);
+                let buffers = [(data, &plane), (scratch, &scratch_buffer)];
+                self.dispatch(&mut encoder, pipeline, &params, &buffers, groups);
+            }
+        }
+        self.read(encoder, &plane, words.len())
+    }
+
+    /// Copy `size` bytes of `buffer` back after running `encoder`.
+    fn read(
+        &self,
+        mut encoder: wgpu::CommandEncoder,
+        buffer: &wgpu::Buffer,
+        size: usize,
+    ) -> Result<Vec<u8>> {
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("dwt readback"),
+            size: size as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, size as u64);
+        self.queue.submit([encoder.finish()]);
+
+        let slice = staging.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |mapped| {
+            let _ = sender.send(mapped);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|_| MedImgError::Codec("GPU readback was dropped".into()))?
+            .map_err(|e| MedImgError::Codec(format!("GPU readback failed: {}", e)))?;
+        let bytes = slice.get_mapped_range().to_vec();
+        staging.unmap();
+        Ok(bytes)
+    }
+}
+
+impl DwtBackend for GpuDwt {
+    fn name(&self) -> &'static str {
+        "gpu"
+    }
+
+    fn forward_53(&self, plane: &mut [i32], width: usize, height: usize, levels: u8) -> Result<()> {
+        let words: Vec<u8> = plane.iter().flat_map(|v| v.to_ne_bytes()).collect();
+        if !self.fits(words.len()) {
+            return CpuDwt.forward_53(plane, width, height, levels);
+        }
+        let bytes = self.transform(&self.forward_53, (INTS, INT_SCRATCH), &words, width, height, levels)?;
+        for (value, word) in plane.iter_mut().zip(bytes.chunks_exact(4)) {
+            *value = i32::from_ne_bytes(word.try_into().expect("4 bytes"));
+        }
+        Ok(())
+    }
+
+    fn forward_97(&self, plane: &mut [f32], width: usize, height: usize, levels: u8) -> Result<()> {
+        let words: Vec<u8> = plane.iter().flat_map(|v| v.to_ne_bytes()).collect();
+        if !self.fits(words.len()) {
+            return CpuDwt.forward_97(plane, width, height, levels);
+        }
+        let bytes =
+            self.transform(&self.forward_97, (FLOATS, FLOAT_SCRATCH), &words, width, height, levels)?;
+        for (value, word) in plane.iter_mut().zip(bytes.chunks_exact(4)) {
+            *value = f32::from_ne_bytes(word.try_into().expect("4 bytes"));
+        }
+        Ok(())
+    }
+
+    fn quantize(&self, coefficients: &[f32], step: f32) -> Result<Vec<i32>> {
+        // WGSL division is only accurate to 2.5 ULP, so indices at interval
+        // boundaries would depend on the adapter
+        CpuDwt.quantize(coefficients, step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a hardware GPU adapter"]
+    fn test_matches_cpu() {
+        let gpu = GpuDwt::shared().expect("no hardware GPU adapter");
+        let (width, height) = (37, 21);
+        let original: Vec<i32> = (0..width * height).map(|i| ((i * 7919) % 4096) as i32).collect();
+
+        let (mut cpu_53, mut gpu_53) = (original.clone(), original.clone());
+        CpuDwt.forward_53(&mut cpu_53, width, height, 4).unwrap();
+        gpu.forward_53(&mut gpu_53, width, height, 4).unwrap();
+        assert_eq!(gpu_53, cpu_53);
+
+        let floats: Vec<f32> = original.iter().map(|&v| v as f32).collect();
+        let (mut cpu_97, mut gpu_97) = (floats.clone(), floats);
+        CpuDwt.forward_97(&mut cpu_97, width, height, 4).unwrap();
+        gpu.forward_97(&mut gpu_97, width, height, 4).unwrap();
+        for (a, b) in gpu_97.iter().zip(&cpu_97) {
+            assert!((a - b).abs() <= 1e-2 * b.abs().max(1.0), "{} vs {}", a, b);
+        }
+        assert_eq!(gpu.quantize(&cpu_97, 3.0).unwrap(), CpuDwt.quantize(&cpu_97, 3.0).unwrap());
+    }
+}
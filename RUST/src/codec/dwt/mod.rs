@@ -0,0 +1,329 @@
+//! Discrete wavelet transform and quantization stages of JPEG 2000.
+//!
+//! Planes are transformed in place into the Mallat layout: each level
+//! splits the current low-pass region into `LL | HL` over `LH | HH`, low
+//! half first, with whole-sample symmetric extension at the edges
+//! (ISO/IEC 15444-1 Annex F).
+//!
+//! - 5/3 reversible: integer lifting, exactly invertible (lossless)
+//! - 9/7 irreversible: floating-point lifting with a low-pass DC gain of
+//!   one, followed by deadzone scalar quantization (lossy)
+//!
+//! The forward stages dominate encode time on large images (mammography,
+//! whole-slide), so they run on a [`DwtBackend`]. [`backend_for`] picks the
+//! GPU backend (`gpu` feature, any Vulkan, Metal, or DX12 adapter,
+//! including NVIDIA devices) for planes of at least [`GPU_MIN_SAMPLES`]
+//! when a device is present, and the CPU otherwise. Both produce identical
+//! 5/3 coefficients; 9/7 results may differ in the last float bits.
+//! Quantization always runs on the CPU, since WGSL division is not
+//! correctly rounded and indices would otherwise depend on the adapter.
+
+#[cfg(all(feature = "gpu", not(target_arch = "wasm32")))]
+mod gpu;
+
+use crate::error::Result;
+
+/// Smallest plane (in samples) worth the GPU upload and readback.
+pub const GPU_MIN_SAMPLES: usize = 512 * 512;
+
+/// 9/7 lifting coefficients.
+const ALPHA: f32 = -1.586_134_3;
+const BETA: f32 = -0.052_980_118;
+const GAMMA: f32 = 0.882_911_1;
+const DELTA: f32 = 0.443_506_87;
+/// 9/7 scaling: low-pass samples are divided by `K`, high-pass ones
+/// multiplied by `K / 2`.
+const K: f32 = 1.230_174_1;
+
+/// Device running the forward transform and quantization stages.
+pub trait DwtBackend: Send + Sync {
+    /// Backend name for logs and diagnostics.
+    fn name(&self) -> &'static str;
+
+    /// Forward 5/3 transform of a `width` x `height` plane, in place.
+    fn forward_53(&self, plane: &mut [i32], width: usize, height: usize, levels: u8) -> Result<()>;
+
+    /// Forward 9/7 transform of a `width` x `height` plane, in place.
+    fn forward_97(&self, plane: &mut [f32], width: usize, height: usize, levels: u8) -> Result<()>;
+
+    /// Deadzone quantization: `sign(c) * floor(|c| / step)`.
+    fn quantize(&self, coefficients: &[f32], step: f32) -> Result<Vec<i32>>;
+}
+
+/// Reference backend on the calling thread.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuDwt;
+
+impl DwtBackend for CpuDwt {
+    fn name(&self) -> &'static str {
+        "cpu"
+    }
+
+    fn forward_53(&self, plane: &mut [i32], width: usize, height: usize, levels: u8) -> Result<()> {
+        transform_2d(plane, width, height, levels, lift_53);
+        Ok(())
+    }
+
+    fn forward_97(&self, plane: &mut [f32], width: usize, height: usize, levels: u8) -> Result<()> {
+        transform_2d(plane, width, height, levels, lift_97);
+        Ok(())
+    }
+
+    fn quantize(&self, coefficients: &[f32], step: f32) -> Result<Vec<i32>> {
+        Ok(coefficients.iter().map(|&c| quantize_one(c, step)).collect())
+    }
+}
+
+/// Backend for a plane of `samples` samples: the GPU when one is present
+/// and the plane is large enough, the CPU otherwise.
+pub fn backend_for(samples: usize) -> &'static dyn DwtBackend {
+    #[cfg(all(feature = "gpu", not(target_arch = "wasm32")))]
+    if samples >= GPU_MIN_SAMPLES {
+        if let Some(gpu) = gpu::GpuDwt::shared() {
+            return gpu;
+        }
+    }
+    let _ = samples;
+    &CpuDwt
+}
+
+/// Inverse 5/3 transform of a plane produced by
+/// [`DwtBackend::forward_53`], in place.
+pub fn inverse_53(plane: &mut [i32], width: usize, height: usize, levels: u8) {
+    inverse_2d(plane, width, height, levels, unlift_53);
+}
+
+/// Inverse 9/7 transform of a plane produced by
+/// [`DwtBackend::forward_97`], in place.
+pub fn inverse_97(plane: &mut [f32], width: usize, height: usize, levels: u8) {
+    inverse_2d(plane, width, height, levels, unlift_97);
+}
+
+/// Reconstruct coefficients from [`DwtBackend::quantize`] output, at the
+/// middle of each quantization interval.
+pub fn dequantize(indices: &[i32], step: f32) -> Vec<f32> {
+    indices
+        .iter()
+        .map(|&q| match q {
+            0 => 0.0,
+            q => (q.unsigned_abs() as f32 + 0.5) * step * q.signum() as f32,
+        })
+        .collect()
+}
+
+/// Number of levels that leave a low-pass band of at least one sample,
+/// capped at `levels`.
+pub fn usable_levels(width: usize, height: usize, levels: u8) -> u8 {
+    let mut usable = 0;
+    let (mut w, mut h) = (width, height);
+    while usable < levels && (w > 1 || h > 1) {
+        w = w.div_ceil(2);
+        h = h.div_ceil(2);
+        usable += 1;
+    }
+    usable
+}
+
+fn quantize_one(c: f32, step: f32) -> i32 {
+    (c.abs() / step).floor().copysign(c) as i32
+}
+
+/// Sizes of the low-pass region before each level, from the full plane
+/// down.
+fn level_sizes(width: usize, height: usize, levels: u8) -> Vec<(usize, usize)> {
+    (0..levels)
+        .scan((width, height), |size, _| {
+            let current = *size;
+            *size = (size.0.div_ceil(2), size.1.div_ceil(2));
+            Some(current)
+        })
+        .collect()
+}
+
+/// Apply `lift` to each row, then each column, of every level's low-pass
+/// region, deinterleaving into low and high halves.
+fn transform_2d<T: Copy + Default>(
+    plane: &mut [T],
+    width: usize,
+    height: usize,
+    levels: u8,
+    lift: fn(&mut [T]),
+) {
+    let mut line = Vec::new();
+    for (w, h) in level_sizes(width, height, levels) {
+        for y in 0..h {
+            line.clear();
+            line.extend_from_slice(&plane[y * width..y * width + w]);
+            lift(&mut line);
+            deinterleave(&line, |i, value| plane[y * width + i] = value);
+        }
+        for x in 0..w {
+            line.clear();
+            line.extend((0..h).map(|y| plane[y * width + x]));
+            lift(&mut line);
+            deinterleave(&line, |i, value| plane[i * width + x] = value);
+        }
+    }
+}
+
+/// Undo [`transform_2d`], finest level last.
+fn inverse_2d<T: Copy + Default>(
+    plane: &mut [T],
+    width: usize,
+    height: usize,
+    levels: u8,
+    unlift: fn(&mut [T]),
+) {
+    let mut line = Vec::new();
+    for (w, h) in level_sizes(width, height, levels).into_iter().rev() {
+        for x in 0..w {
+            line.clear();
+            line.extend((0..h).map(|y| plane[y * width + x]));
+            interleave(&mut line);
+            unlift(&mut line);
+            for (y, &value) in line.iter().enumerate() {
+                plane[y * width + x] = value;
+            }
+        }
+        for y in 0..h {
+            let row = &mut plane[y * width..y * width + w];
+            line.clear();
+            line.extend_from_slice(row);
+            interleave(&mut line);
+            unlift(&mut line);
+            row.copy_from_slice(&line);
+        }
+    }
+}
+
+/// Write even samples to the low half and odd ones to the high half.
+fn deinterleave<T: Copy>(line: &[T], mut put: impl FnMut(usize, T)) {
+    let low = line.len().div_ceil(2);
+    for (i, &value) in line.iter().enumerate() {
+        put(if i % 2 == 0 { i / 2 } else { low + i / 2 }, value);
+    }
+}
+
+/// Inverse of [`deinterleave`].
+fn interleave<T: Copy + Default>(line: &mut [T]) {
+    let low = line.len().div_ceil(2);
+    let split: Vec<T> = line.to_vec();
+    for (i, value) in line.iter_mut().enumerate() {
+        *value = if i % 2 == 0 { split[i / 2] } else { split[low + i / 2] };
+    }
+}
+
+/// Sample `i` of `line` with whole-sample symmetric extension.
+fn at<T: Copy>(line: &[T], i: isize) -> T {
+    let n = line.len() as isize;
+    let period = 2 * (n - 1);
+    let mut i = i.rem_euclid(period.max(1));
+    if i >= n {
+        i = period - i;
+    }
+    line[i as usize]
+}
+
+/// One lifting step: `line[i] += f(line[i - 1], line[i + 1])` for every
+/// `i` of the given parity.
+fn lift_step<T: Copy>(line: &mut [T], odd: bool, f: impl Fn(T, T) -> T, add: impl Fn(T, T) -> T) {
+    for i in (usize::from(odd)..line.len()).step_by(2) {
+        let update = f(at(line, i as isize - 1), at(line, i as isize + 1));
+        line[i] = add(line[i], update);
+    }
+}
+
+fn lift_53(line: &mut [i32]) {
+    if line.len() < 2 {
+        return;
+    }
+    lift_step(line, true, |a, b| -((a + b) >> 1), |x, u| x + u);
+    lift_step(line, false, |a, b| (a + b + 2) >> 2, |x, u| x + u);
+}
+
+fn unlift_53(line: &mut [i32]) {
+    if line.len() < 2 {
+        return;
+    }
+    lift_step(line, false, |a, b| (a + b + 2) >> 2, |x, u| x - u);
+    lift_step(line, true, |a, b| -((a + b) >> 1), |x, u| x - u);
+}
+
+fn lift_97(line: &mut [f32]) {
+    if line.len() < 2 {
+        return;
+    }
+    for (odd, coefficient) in [(true, ALPHA), (false, BETA), (true, GAMMA), (false, DELTA)] {
+        lift_step(line, odd, |a, b| coefficient * (a + b), |x, u| x + u);
+    }
+    for (i, value) in line.iter_mut().enumerate() {
+        *value *= if i % 2 == 0 { 1.0 / K } else { K / 2.0 };
+    }
+}
+
+fn unlift_97(line: &mut [f32]) {
+    if line.len() < 2 {
+        return;
+    }
+    for (i, value) in line.iter_mut().enumerate() {
+        *value *= if i % 2 == 0 { K } else { 2.0 / K };
+    }
+    for (odd, coefficient) in [(false, DELTA), (true, GAMMA), (false, BETA), (true, ALPHA)] {
+        lift_step(line, odd, |a, b| coefficient * (a + b), |x, u| x - u);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ramp(width: usize, height: usize) -> Vec<i32> {
+        (0..width * height)
+            .map(|i| ((i % width) * 7 + (i / width) * 13 + (i * i) % 31) as i32)
+            .collect()
+    }
+
+    #[test]
+    fn test_53_is_reversible() {
+        for (width, height) in [(1, 1), (2, 1), (7, 5), (16, 16), (33, 9)] {
+            let original = ramp(width, height);
+            let mut plane = original.clone();
+            let levels = usable_levels(width, height, 5);
+            CpuDwt.forward_53(&mut plane, width, height, levels).unwrap();
+            inverse_53(&mut plane, width, height, levels);
+            assert_eq!(plane, original, "{}x{}", width, height);
+        }
+    }
+
+    #[test]
+    fn test_97_round_trip_and_gain() {
+        let (width, height) = (19, 12);
+        let original: Vec<f32> = ramp(width, height).into_iter().map(|v| v as f32).collect();
+        let mut plane = original.clone();
+        CpuDwt.forward_97(&mut plane, width, height, 3).unwrap();
+        inverse_97(&mut plane, width, height, 3);
+        for (a, b) in plane.iter().zip(&original) {
+            assert!((a - b).abs() < 1e-3, "{} vs {}", a, b);
+        }
+
+        // A flat plane keeps its level in LL and has no detail
+        let mut flat = vec![100.0f32; 64];
+        CpuDwt.forward_97(&mut flat, 8, 8, 1).unwrap();
+        assert!((flat[0] - 100.0).abs() < 1e-3);
+        assert!(flat[4..8].iter().all(|c| c.abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_quantize() {
+        let indices = CpuDwt.quantize(&[-7.9, -0.5, 0.0, 3.9, 4.0, 12.5], 4.0).unwrap();
+        assert_eq!(indices, [-1, 0, 0, 0, 1, 3]);
+        assert_eq!(dequantize(&indices, 4.0), [-6.0, 0.0, 0.0, 0.0, 6.0, 14.0]);
+    }
+
+    #[test]
+    fn test_backend_for_small_planes_is_cpu() {
+        assert_eq!(backend_for(64).name(), "cpu");
+        assert_eq!(usable_levels(1, 1, 5), 0);
+        assert_eq!(usable_levels(64, 2, 5), 5);
+    }
+}
@@ -0,0 +1,182 @@
+//! Fast entropy-based compressed size estimation.
+//!
+//! Predicts each sample from its neighbors with the JPEG-LS median edge
+//! detector and measures the zeroth-order entropy of the residuals. The
+//! entropy is a good lower-bound proxy for what a context-modeling
+//! lossless coder achieves, at a fraction of the cost of a full encode.
+//!
+//! Large images are subsampled by rows, so estimates are approximate and
+//! meant for storage planning rather than exact accounting.
+
+use std::collections::HashMap;
+
+use crate::config::{CompressionCodec, CompressionConfig, CompressionMode};
+use crate::metrics::extract_pixels;
+use crate::ImageData;
+
+/// Maximum number of rows analyzed per image.
+const MAX_SAMPLED_ROWS: u32 = 256;
+
+/// Approximate codestream and header overhead in bytes.
+const CODESTREAM_OVERHEAD: usize = 256;
+
+/// Residual entropy of an image in bits per sample.
+///
+/// With `near > 0`, residuals are quantized as in JPEG-LS near-lossless
+/// mode, bounding the reconstruction error by `near`.
+pub fn residual_entropy(image: &ImageData, near: u16) -> f64 {
+    let samples = signed_samples(image);
+    let spp = image.samples_per_pixel.max(1) as usize;
+    let width = image.width as usize;
+    let row_len = width * spp;
+    if row_len == 0 || samples.len() < row_len {
+        return 0.0;
+    }
+
+    let rows = samples.len() / row_len;
+    let step = (rows as u32).div_ceil(MAX_SAMPLED_ROWS).max(1) as usize;
+    let divisor = 2 * near as i64 + 1;
+
+    let mut histogram: HashMap<i64, u64> = HashMap::new();
+    let mut count = 0u64;
+
+    for y in (0..rows).step_by(step) {
+        for x in 0..width {
+            for c in 0..spp {
+                let at = |yy: usize, xx: usize| samples[yy * row_len + xx * spp + c];
+                let a = if x > 0 { at(y, x - 1) } else if y > 0 { at(y - 1, x) } else { 0 };
+                let b = if y > 0 { at(y - 1, x) } else { a };
+                let c_ = if x > 0 && y > 0 { at(y - 1, x - 1) } else { b };
+
+                let error = at(y, x) - med_predict(a, b, c_);
+                let quantized = if near > 0 {
+                    error.signum() * ((error.abs() + near as i64) / divisor)
+                } else {
+                    error
+                };
+
+                *histogram.entry(quantized).or_default() += 1;
+                count += 1;
+            }
+        }
+    }
+
+    let total = count as f64;
+    histogram
+        .values()
+        .map(|&n| {
+            let p = n as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Estimate the compressed size in bytes of an image under `config`.
+///
+/// Lossy JPEG 2000 is rate-controlled, so its estimate follows the target
+/// ratio but never exceeds the lossless estimate.
+pub fn estimate_compressed_size(image: &ImageData, config: &CompressionConfig) -> usize {
+    let original = image.expected_size();
+
+    let entropy_size = |near: u16| {
+        let samples = image.width as usize
+            * image.height as usize
+            * image.samples_per_pixel as usize
+            * image.number_of_frames.max(1) as usize;
+        let bits = residual_entropy(image, near) * samples as f64;
+        ((bits / 8.0).ceil() as usize + CODESTREAM_OVERHEAD).min(original + CODESTREAM_OVERHEAD)
+    };
+
+    match (config.codec, config.mode) {
+        (CompressionCodec::Uncompressed, _) => original,
+        (_, CompressionMode::Lossless) => entropy_size(0),
+        (CompressionCodec::JpegLs, _) => entropy_size(config.near_lossless_error),
+        (CompressionCodec::Jpeg2000, _) => {
+            let ratio = config
+                .target_ratio_for(image.stored_bits_per_pixel())
+                .or(config.quality.target_ratio())
+                .unwrap_or(1.0)
+                .max(1.0) as f64;
+            ((original as f64 / ratio).ceil() as usize).min(entropy_size(0))
+        }
+    }
+}
+
+/// JPEG-LS median edge detector.
+fn med_predict(a: i64, b: i64, c: i64) -> i64 {
+    if c >= a.max(b) {
+        a.min(b)
+    } else if c <= a.min(b) {
+        a.max(b)
+    } else {
+        a + b - c
+    }
+}
+
+/// Decode samples, interpreting signed data as two's complement.
+fn signed_samples(image: &ImageData) -> Vec<i64> {
+    let samples = if image.is_signed {
+        image.as_i16().map(|buffer| buffer.samples.into_iter().map(i64::from).collect())
+    } else {
+        image.as_u16().map(|buffer| buffer.samples.into_iter().map(i64::from).collect())
+    };
+    samples.unwrap_or_else(|_| extract_pixels(image).into_iter().map(|v| v as i64).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image_from(width: u32, height: u32, f: impl Fn(u32, u32) -> u8) -> ImageData {
+        let pixels: Vec<u8> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y))
+            .collect();
+        ImageData::new(width, height, 8, 1, pixels)
+    }
+
+    #[test]
+    fn test_flat_image_has_zero_entropy() {
+        let image = image_from(32, 32, |_, _| 100);
+        // Only the first sample differs from its (zero) prediction
+        assert!(residual_entropy(&image, 0) < 0.05);
+    }
+
+    #[test]
+    fn test_noise_has_high_entropy() {
+        // Integer hash (lowbias32) as a deterministic noise source
+        let image = image_from(64, 64, |x, y| {
+            let mut h = y * 64 + x;
+            h ^= h >> 16;
+            h = h.wrapping_mul(0x7feb_352d);
+            h ^= h >> 15;
+            h = h.wrapping_mul(0x846c_a68b);
+            h ^= h >> 16;
+            h as u8
+        });
+        assert!(residual_entropy(&image, 0) > 6.0);
+        assert!(residual_entropy(&image, 4) < residual_entropy(&image, 0));
+    }
+
+    #[test]
+    fn test_estimate_by_mode() {
+        let image = image_from(64, 64, |x, y| ((x * 3 + y) % 256) as u8);
+        let original = image.pixel_data.len();
+
+        let lossless = estimate_compressed_size(&image, &CompressionConfig::lossless(CompressionCodec::JpegLs));
+        assert!(lossless < original);
+
+        let lossy = estimate_compressed_size(&image, &CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0));
+        assert!(lossy <= lossless);
+
+        let raw = CompressionConfig::lossless(CompressionCodec::Uncompressed);
+        assert_eq!(estimate_compressed_size(&image, &raw), original);
+    }
+
+    #[test]
+    fn test_signed_samples() {
+        let mut image = ImageData::new(2, 1, 16, 1, vec![0xFF, 0xFF, 0x02, 0x00]);
+        image.is_signed = true;
+        assert_eq!(signed_samples(&image), vec![-1, 2]);
+    }
+}
@@ -1,15 +1,26 @@
 //! JPEG 2000 codec implementation.
 //!
-//! This module provides JPEG 2000 compression and decompression using OpenJPEG.
-//! For Phase 1 MVP, we implement a pure Rust solution with basic J2K support.
-
-use crate::config::{transfer_syntax, CompressionConfig, CompressionMode};
-use crate::error::{MedImgError, Result};
-use crate::ImageData;
-
+//! With the `openjpeg` feature, images are coded with OpenJPEG into
+//! standards-conformant codestreams. Otherwise (or with
+//! [`Jpeg2000Encoder::Builtin`]) the pure Rust encoder writes a J2K marker
+//! structure around its own wavelet coefficient coding, which only this
+//! crate decodes; the pipeline reports a warning for such output.
+//! Codestreams are decoded by whichever implementation wrote them.
+
+use crate::config::{transfer_syntax, CompressionConfig, CompressionMode, DecodeLimits, Jpeg2000Encoder};
+use crate::error::{CodecParseError, MedImgError, Result};
+use crate::pixel;
+use crate::{Bytes, ImageData};
+
+#[cfg(all(feature = "openjpeg", not(target_arch = "wasm32")))]
+use super::openjpeg;
+use super::{dwt, hex};
 use super::traits::{Codec, CodecCapabilities, CodecInfo};
 
-/// JPEG 2000 codec using OpenJPEG.
+/// Codec name used in info and diagnostics.
+const CODEC_NAME: &str = "JPEG 2000";
+
+/// JPEG 2000 codec.
 pub struct Jpeg2000Codec {
     /// Whether to use reversible (5/3) or irreversible (9/7) wavelet transform.
     pub use_reversible: bool,
@@ -38,7 +49,12 @@ impl Jpeg2000Codec {
     }
 
     /// Encode image to JPEG 2000 format.
-    fn encode_j2k(&self, image: &ImageData, config: &CompressionConfig) -> Result<Vec<u8>> {
+    fn encode_j2k(
+        &self,
+        image: &ImageData,
+        config: &CompressionConfig,
+        progress: Option<&dyn Fn(f64)>,
+    ) -> Result<Vec<u8>> {
         // Validate image parameters
         if image.width == 0 || image.height == 0 {
             return Err(MedImgError::ImageData("Invalid image dimensions".into()));
@@ -48,6 +64,13 @@ impl Jpeg2000Codec {
             return Err(MedImgError::ImageData("Empty pixel data".into()));
         }
 
+        if image.number_of_frames > 1 {
+            return Err(MedImgError::ImageData(format!(
+                "Expected one frame, got {} (encode frames separately)",
+                image.number_of_frames
+            )));
+        }
+
         let expected_size = self.calculate_expected_size(image);
         if image.pixel_data.len() < expected_size {
             return Err(MedImgError::ImageData(format!(
@@ -57,9 +80,10 @@ impl Jpeg2000Codec {
             )));
         }
 
-        // For MVP, we create a simple J2K codestream structure
-        // In production, this would use OpenJPEG FFI bindings
-        let codestream = self.create_j2k_codestream(image, config)?;
+        let codestream = match self.encode_openjpeg(image, config, progress)? {
+            Some(codestream) => codestream,
+            None => self.create_j2k_codestream(image, config, progress)?,
+        };
 
         log::debug!(
             "Encoded {}x{} image to {} bytes (ratio: {:.2}:1)",
@@ -72,19 +96,76 @@ impl Jpeg2000Codec {
         Ok(codestream)
     }
 
-    /// Create a JPEG 2000 codestream (simplified for MVP).
+    /// Encode with OpenJPEG if `config` selects it and the build has it;
+    /// `None` leaves the image to the built-in encoder.
+    fn encode_openjpeg(
+        &self,
+        image: &ImageData,
+        config: &CompressionConfig,
+        progress: Option<&dyn Fn(f64)>,
+    ) -> Result<Option<Vec<u8>>> {
+        #[cfg(all(feature = "openjpeg", not(target_arch = "wasm32")))]
+        if config.jpeg2000_encoder != Jpeg2000Encoder::Builtin {
+            return openjpeg::encode(image, config, progress).map(Some);
+        }
+        if config.jpeg2000_encoder == Jpeg2000Encoder::OpenJpeg {
+            return Err(MedImgError::Codec(
+                "OpenJPEG encoder selected but this build lacks the openjpeg feature".into(),
+            ));
+        }
+        let _ = (image, progress);
+        Ok(None)
+    }
+
+    /// Decode with OpenJPEG unless the codestream was written by the
+    /// built-in encoder or the build lacks OpenJPEG (`None`).
+    fn decode_openjpeg(
+        &self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        bits_per_sample: u16,
+        samples_per_pixel: u16,
+    ) -> Result<Option<Vec<u8>>> {
+        #[cfg(all(feature = "openjpeg", not(target_arch = "wasm32")))]
+        if !is_builtin_codestream(data) {
+            return openjpeg::decode(data, width, height, bits_per_sample, samples_per_pixel).map(Some);
+        }
+        let _ = (data, width, height, bits_per_sample, samples_per_pixel);
+        Ok(None)
+    }
+
+    /// Create a JPEG 2000 codestream with the built-in encoder.
+    ///
+    /// The image is split into `config.tile_size` square tiles (one tile
+    /// if 0), each coded independently in raster order.
     fn create_j2k_codestream(
         &self,
         image: &ImageData,
         config: &CompressionConfig,
+        progress: Option<&dyn Fn(f64)>,
     ) -> Result<Vec<u8>> {
+        let tile = tile_dimensions(image.width, image.height, config.tile_size);
+        let regions = tile_regions(image.width, image.height, tile);
+        if regions.len() > usize::from(u16::MAX) {
+            return Err(MedImgError::Codec(format!(
+                "{}x{} image needs {} tiles of {}x{}; at most {} are supported",
+                image.width,
+                image.height,
+                regions.len(),
+                tile.0,
+                tile.1,
+                u16::MAX
+            )));
+        }
+
         let mut codestream = Vec::new();
 
         // SOC (Start of Codestream) marker
         codestream.extend_from_slice(&[0xFF, 0x4F]);
 
         // SIZ (Image and Tile Size) marker segment
-        codestream.extend_from_slice(&self.create_siz_segment(image));
+        codestream.extend_from_slice(&self.create_siz_segment(image, tile));
 
         // COD (Coding Style Default) marker segment
         codestream.extend_from_slice(&self.create_cod_segment(config));
@@ -92,33 +173,45 @@ impl Jpeg2000Codec {
         // QCD (Quantization Default) marker segment
         codestream.extend_from_slice(&self.create_qcd_segment(config));
 
-        // SOT (Start of Tile-Part) marker
-        codestream.extend_from_slice(&[0xFF, 0x90]);
+        for (index, region) in regions.iter().enumerate() {
+            let report = |fraction: f64| {
+                if let Some(report) = progress {
+                    report((index as f64 + fraction) / regions.len() as f64);
+                }
+            };
+            let compressed_data = if regions.len() == 1 {
+                self.compress_tile_data(image, config, &report)?
+            } else {
+                self.compress_tile_data(&extract_tile(image, *region), config, &report)?
+            };
+
+            // SOT (Start of Tile-Part) marker
+            codestream.extend_from_slice(&[0xFF, 0x90]);
 
-        // Lsot: SOT marker segment length (always 10 bytes for the fixed fields)
-        codestream.extend_from_slice(&10u16.to_be_bytes());
+            // Lsot: SOT marker segment length (always 10 bytes for the fixed fields)
+            codestream.extend_from_slice(&10u16.to_be_bytes());
 
-        // Isot: Tile index
-        codestream.extend_from_slice(&[0x00, 0x00]);
+            // Isot: Tile index
+            codestream.extend_from_slice(&(index as u16).to_be_bytes());
 
-        // Psot: Tile-part length (SOT marker + segment + SOD marker + data)
-        // 2 (SOT marker) + 10 (segment) + 2 (SOD marker) + compressed_data.len()
-        let compressed_data = self.compress_tile_data(image, config)?;
-        let psot = 2 + 10 + 2 + compressed_data.len();
-        codestream.extend_from_slice(&(psot as u32).to_be_bytes());
+            // Psot: Tile-part length (SOT marker + segment + SOD marker + data)
+            // 2 (SOT marker) + 10 (segment) + 2 (SOD marker) + compressed_data.len()
+            let psot = 2 + 10 + 2 + compressed_data.len();
+            codestream.extend_from_slice(&(psot as u32).to_be_bytes());
 
-        // TPsot: Tile-part index (0)
-        codestream.push(0x00);
+            // TPsot: Tile-part index (0)
+            codestream.push(0x00);
 
-        // TNsot: Number of tile-parts (1)
-        codestream.push(0x01);
+            // TNsot: Number of tile-parts (1)
+            codestream.push(0x01);
 
-        // SOD (Start of Data) marker
-        codestream.extend_from_slice(&[0xFF, 0x93]);
+            // SOD (Start of Data) marker
+            codestream.extend_from_slice(&[0xFF, 0x93]);
 
-        // For MVP: include compressed representation of pixel data
-        // In production, this would be actual wavelet-transformed data
-        codestream.extend_from_slice(&compressed_data);
+            // For MVP: include compressed representation of pixel data
+            // In production, this would be actual wavelet-transformed data
+            codestream.extend_from_slice(&compressed_data);
+        }
 
         // EOC (End of Codestream) marker
         codestream.extend_from_slice(&[0xFF, 0xD9]);
@@ -127,7 +220,7 @@ impl Jpeg2000Codec {
     }
 
     /// Create SIZ marker segment.
-    fn create_siz_segment(&self, image: &ImageData) -> Vec<u8> {
+    fn create_siz_segment(&self, image: &ImageData, tile: (u32, u32)) -> Vec<u8> {
         let mut segment = Vec::new();
 
         // SIZ marker
@@ -150,15 +243,15 @@ impl Jpeg2000Codec {
         segment.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
 
         // Tile dimensions (same as image for single tile)
-        segment.extend_from_slice(&(image.width).to_be_bytes());
-        segment.extend_from_slice(&(image.height).to_be_bytes());
+        segment.extend_from_slice(&tile.0.to_be_bytes());
+        segment.extend_from_slice(&tile.1.to_be_bytes());
 
         // Tile offset (0, 0)
         segment.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
         segment.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
 
         // Number of components
-        segment.extend_from_slice(&(image.samples_per_pixel as u16).to_be_bytes());
+        segment.extend_from_slice(&image.samples_per_pixel.to_be_bytes());
 
         // Component parameters
         for _ in 0..components {
@@ -191,8 +284,8 @@ impl Jpeg2000Codec {
         // Coding style (no SOP, no EPH)
         segment.push(0x00);
 
-        // Progression order (LRCP)
-        segment.push(0x00);
+        // Progression order
+        segment.push(config.progression_order.code());
 
         // Number of layers
         segment.extend_from_slice(&(config.quality_layers as u16).to_be_bytes());
@@ -201,7 +294,7 @@ impl Jpeg2000Codec {
         segment.push(0x00);
 
         // Decomposition levels
-        segment.push(0x05);
+        segment.push(config.decomposition_levels);
 
         // Code-block size (64x64)
         segment.push(0x04); // 2^(4+2) = 64
@@ -243,92 +336,58 @@ impl Jpeg2000Codec {
         segment
     }
 
-    /// Compress tile data (simplified implementation for MVP).
-    fn compress_tile_data(&self, image: &ImageData, config: &CompressionConfig) -> Result<Vec<u8>> {
-        // For MVP, we use a simple approach:
-        // - Lossless: basic predictive coding simulation
-        // - Lossy: apply simple quantization
+    /// Compress tile data: one wavelet-transformed plane per component.
+    ///
+    /// Coefficients are written as zigzag varints with zero runs, a
+    /// byte-aligned stand-in for EBCOT entropy coding. `report` receives
+    /// the fraction of the tile done after each component's transform and
+    /// coding stages.
+    fn compress_tile_data(
+        &self,
+        image: &ImageData,
+        config: &CompressionConfig,
+        report: &dyn Fn(f64),
+    ) -> Result<Vec<u8>> {
+        let image = image.packed();
+        let (width, height) = (image.width as usize, image.height as usize);
+        let levels = dwt::usable_levels(width, height, config.decomposition_levels);
+        let backend = dwt::backend_for(width * height);
+        let bits = image.bits_per_sample;
+        let planes = component_planes(&image);
+        let stages = 2.0 * planes.len() as f64;
 
         let mut output = Vec::new();
-
         if config.mode == CompressionMode::Lossless {
-            // Mode indicator: 0xFF = lossless
-            output.push(0xFF);
-            // Simple delta encoding for lossless (placeholder for actual wavelet)
-            output.extend(self.lossless_encode(&image.pixel_data, image.bits_per_sample)?);
-        } else {
-            // Mode indicator: 0xFE = lossy
-            output.push(0xFE);
-            // Apply quantization for lossy
-            let ratio = config.target_ratio.unwrap_or(10.0);
-            output.extend(self.lossy_encode(&image.pixel_data, image.bits_per_sample, ratio)?);
-        }
-
-        Ok(output)
-    }
-
-    /// Simple lossless encoding (placeholder for actual wavelet transform).
-    fn lossless_encode(&self, data: &[u8], bits_per_sample: u16) -> Result<Vec<u8>> {
-        let mut output = Vec::with_capacity(data.len());
-
-        if bits_per_sample <= 8 {
-            // 8-bit data: simple delta encoding
-            if !data.is_empty() {
-                output.push(data[0]);
-                for i in 1..data.len() {
-                    let delta = data[i].wrapping_sub(data[i - 1]);
-                    output.push(delta);
-                }
-            }
-        } else {
-            // 16-bit data: delta encoding on 16-bit values
-            let samples = data.len() / 2;
-            if samples > 0 {
-                output.extend_from_slice(&data[0..2]);
-                for i in 1..samples {
-                    let curr = u16::from_le_bytes([data[i * 2], data[i * 2 + 1]]);
-                    let prev = u16::from_le_bytes([data[(i - 1) * 2], data[(i - 1) * 2 + 1]]);
-                    let delta = curr.wrapping_sub(prev);
-                    output.extend_from_slice(&delta.to_le_bytes());
-                }
-            }
-        }
-
-        Ok(output)
-    }
-
-    /// Simple lossy encoding with quantization.
-    fn lossy_encode(&self, data: &[u8], bits_per_sample: u16, target_ratio: f32) -> Result<Vec<u8>> {
-        // Calculate quantization step based on target ratio
-        let quant_bits = ((target_ratio.log2() * 0.5) as u8).min(bits_per_sample as u8 - 1);
-        let shift = quant_bits as usize;
-
-        let mut output = Vec::with_capacity(data.len() >> shift.min(4));
-
-        // Store quantization parameter
-        output.push(quant_bits);
-
-        if bits_per_sample <= 8 {
-            for byte in data {
-                let quantized = byte >> shift.min(7);
-                output.push(quantized);
+            output.extend_from_slice(&[MODE_REVERSIBLE, levels, u8::from(image.is_signed)]);
+            for (c, mut plane) in planes.into_iter().enumerate() {
+                backend.forward_53(&mut plane, width, height, levels)?;
+                report((2 * c + 1) as f64 / stages);
+                write_coefficients(&mut output, &plane);
+                report((2 * c + 2) as f64 / stages);
             }
         } else {
-            for chunk in data.chunks(2) {
-                if chunk.len() == 2 {
-                    let value = u16::from_le_bytes([chunk[0], chunk[1]]);
-                    let quantized = value >> shift.min(15);
-                    output.extend_from_slice(&quantized.to_le_bytes());
-                }
+            let ratio = config.target_ratio_for(image.stored_bits_per_pixel()).unwrap_or(10.0);
+            // Coarser steps for higher ratios, scaled to the sample range
+            let step = (ratio / 2.0).max(0.5) * 2f32.powi(i32::from(bits.saturating_sub(8)));
+            let shift = dc_shift(bits, image.is_signed);
+            output.extend_from_slice(&[MODE_IRREVERSIBLE, levels, u8::from(image.is_signed)]);
+            output.extend_from_slice(&step.to_le_bytes());
+            for (c, plane) in planes.into_iter().enumerate() {
+                let mut plane: Vec<f32> = plane.into_iter().map(|v| (v - shift) as f32).collect();
+                backend.forward_97(&mut plane, width, height, levels)?;
+                report((2 * c + 1) as f64 / stages);
+                write_coefficients(&mut output, &backend.quantize(&plane, step)?);
+                report((2 * c + 2) as f64 / stages);
             }
         }
+        log::trace!("Transformed {}x{} tile on the {} backend", width, height, backend.name());
 
         Ok(output)
     }
 
     /// Calculate expected pixel data size.
     fn calculate_expected_size(&self, image: &ImageData) -> usize {
-        let bytes_per_sample = ((image.bits_per_sample + 7) / 8) as usize;
+        let bytes_per_sample = image.bits_per_sample.div_ceil(8) as usize;
         image.width as usize
             * image.height as usize
             * image.samples_per_pixel as usize
@@ -344,52 +403,41 @@ impl Jpeg2000Codec {
         bits_per_sample: u16,
         samples_per_pixel: u16,
     ) -> Result<Vec<u8>> {
+        let malformed = |offset, expected: &str, found: String| {
+            CodecParseError::new(CODEC_NAME, offset, expected, found)
+        };
+
+        let limits = DecodeLimits::current();
+        limits.check_image(width, height, bits_per_sample, samples_per_pixel, 1)?;
+        let budget = limits.start();
+
         // Validate J2K markers
         if data.len() < 4 {
-            return Err(MedImgError::Codec("Invalid J2K data: too short".into()));
+            return Err(malformed(0, "at least 4 bytes", format!("{} bytes", data.len())).into());
         }
 
         // Check for SOC marker
         if data[0] != 0xFF || data[1] != 0x4F {
-            return Err(MedImgError::Codec("Invalid J2K data: missing SOC marker".into()));
+            return Err(malformed(0, "SOC marker FF4F", hex(&data[..2])).marker(0xFF4F).into());
         }
 
-        // Parse marker segments properly to find SOD
+        // Main header: read the tile size from SIZ up to the first SOT
+        let mut tile = (width, height);
         let mut pos = 2;
-        while pos + 1 < data.len() {
+        while pos + 4 <= data.len() && !(data[pos] == 0xFF && data[pos + 1] == 0x90) {
             if data[pos] != 0xFF {
-                pos += 1;
-                continue;
+                return Err(malformed(pos, "a main header marker", hex(&data[pos..pos + 2])).into());
             }
-
-            let marker = data[pos + 1];
-            pos += 2;
-
-            match marker {
-                0x93 => {
-                    // SOD marker - data follows immediately
-                    break;
-                }
-                0x4F | 0xD9 => {
-                    // SOC or EOC - no length field
-                    continue;
-                }
-                0x90 => {
-                    // SOT marker - has special structure
-                    if pos + 8 <= data.len() {
-                        // Skip the SOT segment (length is in bytes 0-1, tile length in 4-7)
-                        let seg_len = u16::from_be_bytes([data[pos], data[pos + 1]]) as usize;
-                        pos += seg_len;
-                    }
-                }
-                _ => {
-                    // Other markers have a 2-byte length field
-                    if pos + 2 <= data.len() {
-                        let seg_len = u16::from_be_bytes([data[pos], data[pos + 1]]) as usize;
-                        pos += seg_len;
-                    }
-                }
+            let seg_len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+            if data[pos + 1] == 0x51 && pos + 2 + 28 <= data.len() {
+                // SIZ: Lsiz, Rsiz, Xsiz, Ysiz, XOsiz, YOsiz, then XTsiz, YTsiz
+                let field = |offset: usize| {
+                    let at = pos + 2 + offset;
+                    u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+                };
+                tile = (field(20), field(24));
             }
+            pos += 2 + seg_len;
         }
 
         // Find EOC marker
@@ -398,56 +446,187 @@ impl Jpeg2000Codec {
             end = data.len() - 2;
         }
 
-        if pos >= end {
-            return Err(MedImgError::Codec("Invalid J2K data: no tile data found".into()));
+        // Tile-parts: SOT (Lsot, Isot, Psot, TPsot, TNsot), SOD, then the tile data
+        let mut tiles = Vec::new();
+        while pos + 12 <= end && data[pos] == 0xFF && data[pos + 1] == 0x90 {
+            let lsot = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+            let isot = u16::from_be_bytes([data[pos + 4], data[pos + 5]]) as usize;
+            let psot =
+                u32::from_be_bytes([data[pos + 6], data[pos + 7], data[pos + 8], data[pos + 9]])
+                    as usize;
+            // Psot of 0 means the tile-part runs to the end of the codestream
+            let tile_end = if psot == 0 { end } else { pos + psot };
+            let body = pos + 2 + lsot + 2;
+            if body > tile_end || tile_end > end {
+                return Err(malformed(
+                    pos,
+                    &format!("tile-part {} within bytes {}..{}", isot, body, end),
+                    format!("Psot {} (Lsot {})", psot, lsot),
+                )
+                .marker(0xFF90)
+                .into());
+            }
+            if data[body - 2..body] != [0xFF, 0x93] {
+                return Err(malformed(body - 2, "SOD marker FF93", hex(&data[body - 2..body]))
+                    .marker(0xFF90)
+                    .into());
+            }
+            tiles.push((isot, body, &data[body..tile_end]));
+            pos = tile_end;
         }
 
-        let compressed = &data[pos..end];
-
-        // Check mode indicator byte
-        if compressed.is_empty() {
-            return Err(MedImgError::Codec("Invalid J2K data: empty tile data".into()));
+        if tiles.is_empty() {
+            let found = if pos + 2 <= end { hex(&data[pos..pos + 2]) } else { "end of codestream".into() };
+            return Err(malformed(pos, "SOT marker FF90", found).into());
         }
 
-        let mode_indicator = compressed[0];
-        let tile_data = &compressed[1..];
-
-        // Decode based on mode indicator
-        let decoded = if mode_indicator == 0xFF {
-            // Lossless: delta encoded
-            self.lossless_decode(tile_data, bits_per_sample)?
-        } else if mode_indicator == 0xFE {
-            // Lossy: has quantization parameter
-            self.lossy_decode(tile_data, bits_per_sample)?
-        } else {
-            return Err(MedImgError::Codec(format!(
-                "Invalid J2K mode indicator: 0x{:02X}",
-                mode_indicator
-            )));
-        };
-
         // Verify size
         let expected_size = self.calculate_expected_size(&ImageData {
             width,
             height,
             bits_per_sample,
             samples_per_pixel,
-            pixel_data: Vec::new(),
+            number_of_frames: 1,
+            pixel_data: Bytes::new(),
+            row_stride: None,
             photometric_interpretation: String::new(),
             is_signed: false,
         });
 
-        if decoded.len() != expected_size {
-            log::warn!(
-                "Decoded size {} differs from expected {}",
-                decoded.len(),
-                expected_size
-            );
+        // Tiny SIZ tile sizes would split the image into billions of tiles
+        let tile_count = u64::from(width.div_ceil(tile.0.max(1))) * u64::from(height.div_ceil(tile.1.max(1)));
+        limits.check_fragments(usize::try_from(tile_count).unwrap_or(usize::MAX), "tiles")?;
+        let regions = tile_regions(width, height, tile);
+        if tiles.len() == 1 && regions.len() == 1 {
+            let layout = TileLayout {
+                width: width as usize,
+                height: height as usize,
+                samples_per_pixel: usize::from(samples_per_pixel),
+                bits_per_sample,
+            };
+            let decoded = self.decode_tile(tiles[0].2, tiles[0].1, layout)?;
+            if decoded.len() != expected_size {
+                log::warn!(
+                    "Decoded size {} differs from expected {}",
+                    decoded.len(),
+                    expected_size
+                );
+            }
+            return Ok(decoded);
+        }
+
+        // Reassemble the tiles in place
+        let pixel_bytes = bits_per_sample.div_ceil(8) as usize * samples_per_pixel as usize;
+        let stride = width as usize * pixel_bytes;
+        let mut decoded = vec![0u8; expected_size];
+        for (index, offset, tile_data) in tiles {
+            budget.check()?;
+            let &(x, y, w, h) = regions.get(index).ok_or_else(|| {
+                malformed(
+                    offset,
+                    &format!("a tile index below {}", regions.len()),
+                    format!("tile {}", index),
+                )
+                .marker(0xFF90)
+            })?;
+            let layout = TileLayout {
+                width: w as usize,
+                height: h as usize,
+                samples_per_pixel: usize::from(samples_per_pixel),
+                bits_per_sample,
+            };
+            let pixels = self.decode_tile(tile_data, offset, layout)?;
+            let row_bytes = w as usize * pixel_bytes;
+            if pixels.len() < row_bytes * h as usize {
+                return Err(malformed(
+                    offset,
+                    &format!("tile {} to decode to {} bytes", index, row_bytes * h as usize),
+                    format!("{} bytes", pixels.len()),
+                )
+                .into());
+            }
+            for (row, src) in pixels.chunks_exact(row_bytes).take(h as usize).enumerate() {
+                let start = (y as usize + row) * stride + x as usize * pixel_bytes;
+                decoded[start..start + row_bytes].copy_from_slice(src);
+            }
         }
 
         Ok(decoded)
     }
 
+    /// Decode one tile's data (mode indicator followed by the coded samples)
+    /// found at `offset` in the codestream.
+    fn decode_tile(&self, compressed: &[u8], offset: usize, layout: TileLayout) -> Result<Vec<u8>> {
+        // Check mode indicator byte
+        if compressed.is_empty() {
+            return Err(
+                CodecParseError::new(CODEC_NAME, offset, "tile data", "an empty tile-part").into(),
+            );
+        }
+
+        let mode_indicator = compressed[0];
+        let tile_data = &compressed[1..];
+
+        // Decode based on mode indicator
+        match mode_indicator {
+            MODE_REVERSIBLE | MODE_IRREVERSIBLE => self.wavelet_decode(compressed, offset, layout),
+            // Delta-coded tiles of earlier versions
+            0xFF => self.lossless_decode(tile_data, layout.bits_per_sample),
+            0xFE => self.lossy_decode(tile_data, layout.bits_per_sample),
+            _ => Err(CodecParseError::new(
+                CODEC_NAME,
+                offset,
+                "mode indicator FD, FC, FF, or FE",
+                format!("{:02X}", mode_indicator),
+            )
+            .into()),
+        }
+    }
+
+    /// Decode a wavelet-coded tile written by `compress_tile_data`.
+    fn wavelet_decode(&self, compressed: &[u8], offset: usize, layout: TileLayout) -> Result<Vec<u8>> {
+        let truncated = |what: &str| {
+            MedImgError::from(CodecParseError::new(CODEC_NAME, offset, what, "end of tile-part"))
+        };
+        let reversible = compressed[0] == MODE_REVERSIBLE;
+        let header = if reversible { 3 } else { 7 };
+        let head = compressed.get(..header).ok_or_else(|| truncated("tile header"))?;
+        let (levels, signed) = (head[1], head[2] != 0);
+        let step = (!reversible).then(|| f32::from_le_bytes([head[3], head[4], head[5], head[6]]));
+
+        let TileLayout { width, height, samples_per_pixel, bits_per_sample } = layout;
+        let plane_len = width * height;
+        let count = plane_len * samples_per_pixel;
+        let mut coefficients = read_coefficients(&compressed[header..], count)
+            .ok_or_else(|| truncated(&format!("{} coefficients", count)))?;
+
+        let (min, max) = sample_range(bits_per_sample, signed);
+        let mut samples = vec![0i32; count];
+        for (component, plane) in coefficients.chunks_exact_mut(plane_len).enumerate() {
+            let values: Vec<i32> = match step {
+                None => {
+                    dwt::inverse_53(plane, width, height, levels);
+                    plane.to_vec()
+                }
+                Some(step) => {
+                    let shift = dc_shift(bits_per_sample, signed);
+                    let mut values = dwt::dequantize(plane, step);
+                    dwt::inverse_97(&mut values, width, height, levels);
+                    values.into_iter().map(|v| (v.round() as i32 + shift).clamp(min, max)).collect()
+                }
+            };
+            for (i, value) in values.into_iter().enumerate() {
+                samples[i * samples_per_pixel + component] = value;
+            }
+        }
+
+        Ok(if bits_per_sample <= 8 {
+            samples.into_iter().map(|v| v as u8).collect()
+        } else {
+            pixel::encode_le(&samples.into_iter().map(|v| v as u16).collect::<Vec<_>>())
+        })
+    }
+
     /// Decode lossless data.
     fn lossless_decode(&self, data: &[u8], bits_per_sample: u16) -> Result<Vec<u8>> {
         let mut output = Vec::with_capacity(data.len());
@@ -461,15 +640,14 @@ impl Jpeg2000Codec {
                 }
             }
         } else {
-            if data.len() >= 2 {
-                output.extend_from_slice(&data[0..2]);
-                for i in 1..(data.len() / 2) {
-                    let delta = u16::from_le_bytes([data[i * 2], data[i * 2 + 1]]);
-                    let prev = u16::from_le_bytes([output[(i - 1) * 2], output[(i - 1) * 2 + 1]]);
-                    let value = prev.wrapping_add(delta);
-                    output.extend_from_slice(&value.to_le_bytes());
-                }
-            }
+            let values: Vec<u16> = pixel::decode_le::<u16>(data)
+                .into_iter()
+                .scan(0u16, |prev, delta| {
+                    *prev = prev.wrapping_add(delta);
+                    Some(*prev)
+                })
+                .collect();
+            output = pixel::encode_le(&values);
         }
 
         Ok(output)
@@ -493,12 +671,9 @@ impl Jpeg2000Codec {
                 output.push(dequantized);
             }
         } else {
-            for chunk in data.chunks(2) {
-                if chunk.len() == 2 {
-                    let value = u16::from_le_bytes([chunk[0], chunk[1]]);
-                    let dequantized = value << shift.min(15);
-                    output.extend_from_slice(&dequantized.to_le_bytes());
-                }
+            for value in pixel::decode_le::<u16>(data) {
+                let dequantized = value << shift.min(15);
+                output.extend_from_slice(&dequantized.to_le_bytes());
             }
         }
 
@@ -506,6 +681,180 @@ impl Jpeg2000Codec {
     }
 }
 
+/// Mode indicator of 5/3 wavelet-coded tiles.
+const MODE_REVERSIBLE: u8 = 0xFD;
+
+/// Mode indicator of 9/7 wavelet-coded, quantized tiles.
+const MODE_IRREVERSIBLE: u8 = 0xFC;
+
+/// Geometry of a tile being decoded.
+#[derive(Debug, Clone, Copy)]
+struct TileLayout {
+    width: usize,
+    height: usize,
+    samples_per_pixel: usize,
+    bits_per_sample: u16,
+}
+
+/// Whether `data` has the main header the built-in encoder writes: one of
+/// the two fixed QCD segments of `create_qcd_segment`. The reversible one
+/// is malformed (expounded style with an 8-bit value); the irreversible
+/// one is a single step of exponent 0, far coarser than any conformant
+/// encoder picks, even with no decomposition levels. Unparseable headers
+/// count as built-in so its decoder reports where they break.
+#[cfg(all(feature = "openjpeg", not(target_arch = "wasm32")))]
+fn is_builtin_codestream(data: &[u8]) -> bool {
+    let mut pos = 2;
+    while pos + 5 <= data.len() && data[pos] == 0xFF && data[pos + 1] != 0x90 {
+        let length = usize::from(u16::from_be_bytes([data[pos + 2], data[pos + 3]]));
+        if data[pos + 1] == 0x5C {
+            return data
+                .get(pos + 4..pos + 2 + length)
+                .is_none_or(|body| matches!(body, [0x22, 0x00] | [0x42, 0x00, 0x88]));
+        }
+        pos += 2 + length;
+    }
+    true
+}
+
+/// Samples of each component of a packed image, in raster order.
+pub(super) fn component_planes(image: &ImageData) -> Vec<Vec<i32>> {
+    let samples: Vec<i32> = match (image.bits_per_sample > 8, image.is_signed) {
+        (false, false) => image.pixel_data.iter().map(|&v| i32::from(v)).collect(),
+        (false, true) => image.pixel_data.iter().map(|&v| i32::from(v as i8)).collect(),
+        (true, false) => pixel::decode_le::<u16>(&image.pixel_data).into_iter().map(i32::from).collect(),
+        (true, true) => pixel::decode_le::<i16>(&image.pixel_data).into_iter().map(i32::from).collect(),
+    };
+    let components = usize::from(image.samples_per_pixel.max(1));
+    let plane_len = image.width as usize * image.height as usize;
+    (0..components)
+        .map(|c| samples.iter().skip(c).step_by(components).take(plane_len).copied().collect())
+        .collect()
+}
+
+/// Level shift centering unsigned samples on zero before the 9/7 transform.
+fn dc_shift(bits_per_sample: u16, signed: bool) -> i32 {
+    if signed {
+        0
+    } else {
+        1 << (bits_per_sample.clamp(1, 16) - 1)
+    }
+}
+
+/// Smallest and largest sample values.
+fn sample_range(bits_per_sample: u16, signed: bool) -> (i32, i32) {
+    let bits = bits_per_sample.clamp(1, 16);
+    if signed {
+        (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+    } else {
+        (0, (1 << bits) - 1)
+    }
+}
+
+/// Append `values` as zigzag-encoded LEB128 varints, each zero followed
+/// by the number of zeros after it.
+fn write_coefficients(output: &mut Vec<u8>, values: &[i32]) {
+    let mut values = values.iter().copied().peekable();
+    while let Some(value) = values.next() {
+        write_varint(output, ((value << 1) ^ (value >> 31)) as u32);
+        if value == 0 {
+            let mut run = 0;
+            while values.next_if_eq(&0).is_some() {
+                run += 1;
+            }
+            write_varint(output, run);
+        }
+    }
+}
+
+fn write_varint(output: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        output.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+/// Read `count` values written by [`write_coefficients`], or `None` if the
+/// data ends first or a zero run overshoots `count`.
+fn read_coefficients(data: &[u8], count: usize) -> Option<Vec<i32>> {
+    let mut values = Vec::with_capacity(count.min(data.len().saturating_mul(128)));
+    let mut bytes = data.iter();
+    let mut read_varint = || {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = *bytes.next()?;
+            value |= u32::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        Some(value)
+    };
+    while values.len() < count {
+        let zigzag = read_varint()?;
+        let value = (zigzag >> 1) as i32 ^ -((zigzag & 1) as i32);
+        let run = if value == 0 { read_varint()? as usize } else { 0 };
+        if values.len() + 1 + run > count {
+            return None;
+        }
+        values.push(value);
+        values.resize(values.len() + run, 0);
+    }
+    Some(values)
+}
+
+/// Tile region: `(x, y, width, height)` in pixels.
+type TileRegion = (u32, u32, u32, u32);
+
+/// Tile dimensions for `tile_size` (0 = the whole image as one tile).
+fn tile_dimensions(width: u32, height: u32, tile_size: u32) -> (u32, u32) {
+    if tile_size == 0 {
+        (width, height)
+    } else {
+        (tile_size, tile_size)
+    }
+}
+
+/// Regions of the tiles covering a `width` x `height` image, in raster
+/// order (the JPEG 2000 tile index order).
+fn tile_regions(width: u32, height: u32, tile: (u32, u32)) -> Vec<TileRegion> {
+    let (tile_width, tile_height) = (tile.0.max(1), tile.1.max(1));
+    (0..height.div_ceil(tile_height))
+        .flat_map(|ty| {
+            (0..width.div_ceil(tile_width)).map(move |tx| {
+                let (x, y) = (tx * tile_width, ty * tile_height);
+                (x, y, tile_width.min(width - x), tile_height.min(height - y))
+            })
+        })
+        .collect()
+}
+
+/// Copy a tile region out of an image with interleaved samples.
+fn extract_tile(image: &ImageData, (x, y, width, height): TileRegion) -> ImageData {
+    let pixel_bytes = image.bits_per_sample.div_ceil(8) as usize * image.samples_per_pixel as usize;
+    let stride = image.width as usize * pixel_bytes;
+    let row_bytes = width as usize * pixel_bytes;
+
+    let mut pixel_data = Vec::with_capacity(row_bytes * height as usize);
+    for row in y as usize..(y + height) as usize {
+        let start = row * stride + x as usize * pixel_bytes;
+        pixel_data.extend_from_slice(&image.pixel_data[start..start + row_bytes]);
+    }
+
+    ImageData {
+        width,
+        height,
+        bits_per_sample: image.bits_per_sample,
+        samples_per_pixel: image.samples_per_pixel,
+        number_of_frames: 1,
+        pixel_data: pixel_data.into(),
+        row_stride: None,
+        photometric_interpretation: image.photometric_interpretation.clone(),
+        is_signed: image.is_signed,
+    }
+}
+
 impl Default for Jpeg2000Codec {
     fn default() -> Self {
         Self::new()
@@ -513,8 +862,14 @@ impl Default for Jpeg2000Codec {
 }
 
 impl Codec for Jpeg2000Codec {
-    fn encode(&self, image: &ImageData, config: &CompressionConfig) -> Result<Vec<u8>> {
-        self.encode_j2k(image, config)
+    fn encode(
+        &self,
+        image: &ImageData,
+        config: &CompressionConfig,
+        progress: Option<&dyn Fn(f64)>,
+    ) -> Result<Vec<u8>> {
+        // Progress is reported after each tile
+        self.encode_j2k(&image.packed(), config, progress)
     }
 
     fn decode(
@@ -525,23 +880,34 @@ impl Codec for Jpeg2000Codec {
         bits_per_sample: u16,
         samples_per_pixel: u16,
     ) -> Result<ImageData> {
-        let pixel_data = self.decode_j2k(data, width, height, bits_per_sample, samples_per_pixel)?;
+        let openjpeg = self.decode_openjpeg(data, width, height, bits_per_sample, samples_per_pixel)?;
+        let pixel_data = match openjpeg {
+            Some(pixel_data) => pixel_data,
+            None => self.decode_j2k(data, width, height, bits_per_sample, samples_per_pixel)?,
+        };
 
         Ok(ImageData {
             width,
             height,
             bits_per_sample,
             samples_per_pixel,
-            pixel_data,
+            number_of_frames: 1,
+            pixel_data: pixel_data.into(),
+            row_stride: None,
             photometric_interpretation: String::new(),
             is_signed: false,
         })
     }
 
     fn info(&self) -> CodecInfo {
+        // The OpenJPEG version when it encodes by default
+        #[cfg(all(feature = "openjpeg", not(target_arch = "wasm32")))]
+        let version = openjpeg::version();
+        #[cfg(not(all(feature = "openjpeg", not(target_arch = "wasm32"))))]
+        let version = "MVP 0.1";
         CodecInfo {
-            name: "JPEG 2000",
-            version: "MVP 0.1",
+            name: CODEC_NAME,
+            version,
             supports_lossless: true,
             supports_lossy: true,
             supports_progressive: true,
@@ -564,10 +930,10 @@ impl Codec for Jpeg2000Codec {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::CompressionCodec;
+    use crate::config::{CompressionCodec, ProgressionOrder};
 
     fn create_test_image(width: u32, height: u32, bits: u16) -> ImageData {
-        let bytes_per_sample = ((bits + 7) / 8) as usize;
+        let bytes_per_sample = bits.div_ceil(8) as usize;
         let size = width as usize * height as usize * bytes_per_sample;
         let mut pixel_data = Vec::with_capacity(size);
 
@@ -580,7 +946,9 @@ mod tests {
             height,
             bits_per_sample: bits,
             samples_per_pixel: 1,
-            pixel_data,
+            number_of_frames: 1,
+            pixel_data: pixel_data.into(),
+            row_stride: None,
             photometric_interpretation: "MONOCHROME2".into(),
             is_signed: false,
         }
@@ -592,19 +960,220 @@ mod tests {
         let image = create_test_image(64, 64, 8);
         let config = CompressionConfig::lossless(CompressionCodec::Jpeg2000);
 
-        let encoded = codec.encode(&image, &config).unwrap();
+        let encoded = codec.encode(&image, &config, None).unwrap();
         let decoded = codec.decode(&encoded, 64, 64, 8, 1).unwrap();
 
         assert_eq!(image.pixel_data, decoded.pixel_data);
     }
 
+    #[test]
+    fn test_malformed_codestream_diagnostics() {
+        let codec = Jpeg2000Codec::lossless();
+        let image = create_test_image(16, 16, 8);
+        // Diagnostics of the built-in decoder, for its own codestreams
+        let config = CompressionConfig {
+            jpeg2000_encoder: Jpeg2000Encoder::Builtin,
+            ..CompressionConfig::lossless(CompressionCodec::Jpeg2000)
+        };
+        let encoded = codec.encode(&image, &config, None).unwrap();
+        let parse_error = |data: &[u8]| match codec.decode(data, 16, 16, 8, 1) {
+            Err(MedImgError::CodecParse(e)) => e,
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        };
+
+        let mut bad_soc = encoded.clone();
+        bad_soc[1] = 0x00;
+        let e = parse_error(&bad_soc);
+        assert_eq!((e.offset, e.marker, e.found.as_str()), (0, Some(0xFF4F), "FF00"));
+
+        // Truncated: the tile-part's Psot runs past the end
+        let sot = encoded.windows(2).position(|w| w == [0xFF, 0x90]).unwrap();
+        let e = parse_error(&encoded[..encoded.len() - 10]);
+        assert_eq!((e.offset, e.marker), (sot, Some(0xFF90)));
+
+        let mut bad_mode = encoded.clone();
+        let sod = encoded.windows(2).position(|w| w == [0xFF, 0x93]).unwrap();
+        bad_mode[sod + 2] = 0x12;
+        let e = parse_error(&bad_mode);
+        assert_eq!((e.offset, e.found.as_str()), (sod + 2, "12"));
+        assert_eq!(
+            MedImgError::from(e).to_string(),
+            format!(
+                "Codec error: malformed JPEG 2000 codestream at byte {}: \
+                 expected mode indicator FD, FC, FF, or FE, found 12",
+                sod + 2
+            )
+        );
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let codec = Jpeg2000Codec::lossless();
+        let config = CompressionConfig {
+            jpeg2000_encoder: Jpeg2000Encoder::Builtin,
+            ..CompressionConfig::lossless(CompressionCodec::Jpeg2000)
+        };
+        let encoded = codec.encode(&create_test_image(16, 16, 8), &config, None).unwrap();
+        let limit_exceeded = |data: &[u8], width, height| {
+            matches!(codec.decode(data, width, height, 8, 1), Err(MedImgError::LimitExceeded(_)))
+        };
+        assert!(limit_exceeded(&encoded, 70_000, 16));
+
+        // 16.7 million 1x1 tiles
+        let mut tiny_tiles = encoded.clone();
+        let siz = encoded.windows(2).position(|w| w == [0xFF, 0x51]).unwrap();
+        tiny_tiles[siz + 22..siz + 30].copy_from_slice(&[0, 0, 0, 1, 0, 0, 0, 1]);
+        assert!(limit_exceeded(&tiny_tiles, 4096, 4096));
+        assert!(!limit_exceeded(&encoded, 4096, 4096));
+    }
+
+    #[test]
+    fn test_tiled_roundtrip() {
+        let codec = Jpeg2000Codec::lossless();
+        let image = create_test_image(200, 130, 16);
+        let config = CompressionConfig {
+            tile_size: 64,
+            jpeg2000_encoder: Jpeg2000Encoder::Builtin,
+            ..CompressionConfig::lossless(CompressionCodec::Jpeg2000)
+        };
+        assert!(config.validate().is_ok());
+
+        let reports = std::cell::RefCell::new(Vec::new());
+        let progress = |fraction: f64| reports.borrow_mut().push(fraction);
+        let encoded = codec.encode(&image, &config, Some(&progress)).unwrap();
+        // 4 columns x 3 rows of tiles, the last ones partial; each reports
+        // its transform and coding stages
+        let reports = reports.into_inner();
+        assert_eq!(reports.len(), 24);
+        assert!(reports.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!((reports[0], reports[23]), (1.0 / 24.0, 1.0));
+        assert_eq!(encoded.windows(2).filter(|w| w == &[0xFF, 0x90]).count(), 12);
+
+        let decoded = codec.decode(&encoded, 200, 130, 16, 1).unwrap();
+        assert_eq!(image.pixel_data, decoded.pixel_data);
+
+        for tile_size in [32, 100] {
+            let invalid = CompressionConfig { tile_size, ..config.clone() };
+            assert!(invalid.validate().is_err(), "{}", tile_size);
+        }
+        let jpegls = CompressionConfig {
+            tile_size: 64,
+            ..CompressionConfig::lossless(CompressionCodec::JpegLs)
+        };
+        assert!(jpegls.validate().is_err());
+    }
+
+    #[test]
+    fn test_wavelet_coding() {
+        let codec = Jpeg2000Codec::new();
+        let lossless = CompressionConfig::lossless(CompressionCodec::Jpeg2000);
+
+        // Smooth 12-bit content codes to fewer bytes than it occupies
+        let values: Vec<u16> = (0..96 * 80).map(|i| (i % 96 * 20 + i / 96 * 11) as u16).collect();
+        let image = ImageData::new(96, 80, 12, 1, pixel::encode_le(&values));
+        let encoded = codec.encode(&image, &lossless, None).unwrap();
+        assert!(encoded.len() < image.pixel_data.len() * 2 / 3, "{} bytes", encoded.len());
+        assert_eq!(codec.decode(&encoded, 96, 80, 12, 1).unwrap().pixel_data, image.pixel_data);
+
+        // Signed samples and interleaved color survive exactly
+        let signed = ImageData {
+            is_signed: true,
+            ..ImageData::new(5, 3, 16, 1, pixel::encode_le(&[-32768i16, -1, 0, 1, 32767].repeat(3)))
+        };
+        let encoded = codec.encode(&signed, &lossless, None).unwrap();
+        assert_eq!(codec.decode(&encoded, 5, 3, 16, 1).unwrap().pixel_data, signed.pixel_data);
+        let rgb = ImageData::new(7, 6, 8, 3, (0..126).map(|i| (i * 37 % 256) as u8).collect::<Vec<_>>());
+        let encoded = codec.encode(&rgb, &lossless, None).unwrap();
+        assert_eq!(codec.decode(&encoded, 7, 6, 8, 3).unwrap().pixel_data, rgb.pixel_data);
+
+        // Lossy error averages under one quantization step (5 x 16 for 12 bits at 10:1)
+        let lossy = CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0);
+        let encoded = codec.encode(&image, &lossy, None).unwrap();
+        assert!(encoded.len() < image.pixel_data.len() / 4, "{} bytes", encoded.len());
+        let decoded = pixel::decode_le::<u16>(&codec.decode(&encoded, 96, 80, 12, 1).unwrap().pixel_data);
+        let error: u32 = values.iter().zip(&decoded).map(|(a, b)| u32::from(a.abs_diff(*b))).sum();
+        assert!(error / (values.len() as u32) < 80, "{}", error / values.len() as u32);
+
+        // Delta-coded tiles of earlier versions still decode
+        let legacy = [&[0xFF][..], &[10, 1, 1, 0xFF]].concat();
+        assert_eq!(codec.decode_tile(&legacy, 0, TileLayout {
+            width: 4,
+            height: 1,
+            samples_per_pixel: 1,
+            bits_per_sample: 8,
+        })
+        .unwrap(), [10, 11, 12, 11]);
+    }
+
+    #[test]
+    fn test_encoder_selection() {
+        let codec = Jpeg2000Codec::new();
+        let image = create_test_image(64, 64, 8);
+        let builtin = CompressionConfig {
+            jpeg2000_encoder: Jpeg2000Encoder::Builtin,
+            ..CompressionConfig::lossless(CompressionCodec::Jpeg2000)
+        };
+        let encoded = codec.encode(&image, &builtin, None).unwrap();
+        let sod = encoded.windows(2).position(|w| w == [0xFF, 0x93]).unwrap();
+        assert_eq!(encoded[sod + 2], MODE_REVERSIBLE);
+        assert_eq!(codec.decode(&encoded, 64, 64, 8, 1).unwrap().pixel_data, image.pixel_data);
+
+        // Requiring OpenJPEG fails without it rather than falling back
+        let openjpeg = CompressionConfig {
+            jpeg2000_encoder: Jpeg2000Encoder::OpenJpeg,
+            ..builtin
+        };
+        assert_eq!(openjpeg.validate().is_ok(), cfg!(feature = "openjpeg"));
+        match codec.encode(&image, &openjpeg, None) {
+            Ok(encoded) => assert_eq!(encoded[..4], [0xFF, 0x4F, 0xFF, 0x51]),
+            Err(e) => assert!(matches!(e, MedImgError::Codec(_)), "{}", e),
+        }
+    }
+
+    #[cfg(all(feature = "openjpeg", not(target_arch = "wasm32")))]
+    #[test]
+    fn test_builtin_codestream_detection() {
+        let image = create_test_image(64, 64, 8);
+        for mode in [CompressionMode::Lossless, CompressionMode::Lossy] {
+            let config = CompressionConfig {
+                mode,
+                jpeg2000_encoder: Jpeg2000Encoder::Builtin,
+                ..CompressionConfig::lossless(CompressionCodec::Jpeg2000)
+            };
+            let builtin = Jpeg2000Codec::new().encode(&image, &config, None).unwrap();
+            assert!(is_builtin_codestream(&builtin), "{:?}", mode);
+
+            // The fewest subbands a conformant QCD segment can describe
+            for levels in [0, 1] {
+                let config = CompressionConfig {
+                    decomposition_levels: levels,
+                    ..config.clone()
+                };
+                let conformant = openjpeg::encode(&image, &config, None).unwrap();
+                assert!(!is_builtin_codestream(&conformant), "{:?} with {} levels", mode, levels);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cod_segment_tuning() {
+        let config = CompressionConfig {
+            decomposition_levels: 6,
+            progression_order: ProgressionOrder::Rpcl,
+            ..CompressionConfig::lossless(CompressionCodec::Jpeg2000)
+        };
+        let cod = Jpeg2000Codec::lossless().create_cod_segment(&config);
+        assert_eq!(cod[5], 2); // Progression order
+        assert_eq!(cod[9], 6); // Decomposition levels
+    }
+
     #[test]
     fn test_lossy_compression() {
         let codec = Jpeg2000Codec::lossy();
         let image = create_test_image(64, 64, 8);
         let config = CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0);
 
-        let encoded = codec.encode(&image, &config).unwrap();
+        let encoded = codec.encode(&image, &config, None).unwrap();
         let decoded = codec.decode(&encoded, 64, 64, 8, 1).unwrap();
 
         // MVP: Lossy mode works (encodes/decodes) but size reduction
@@ -1,15 +1,25 @@
-//! JPEG-LS codec implementation.
+//! JPEG-LS codec (ITU-T T.87 / ISO/IEC 14495-1).
 //!
-//! This module provides JPEG-LS compression and decompression.
-//! JPEG-LS is particularly efficient for medical images and offers
-//! both lossless and near-lossless modes.
-
-use crate::config::{transfer_syntax, CompressionConfig, CompressionMode};
-use crate::error::{MedImgError, Result};
+//! A complete LOCO-I coder: median edge detection prediction, 365
+//! regular-mode contexts with bias correction, limited-length Golomb-Rice
+//! coding of the mapped residuals, and run mode for flat regions, in
+//! lossless and near-lossless (NEAR > 0) variants. The encoder writes one
+//! scan per component; the decoder also reads line- and sample-interleaved
+//! scans and LSE preset parameters from other implementations.
+
+use crate::config::{
+    transfer_syntax, CompressionConfig, CompressionMode, DecodeBudget, DecodeLimits, JpegLsThresholds,
+};
+use crate::error::{CodecParseError, MedImgError, Result};
+use crate::pixel;
 use crate::ImageData;
 
+use super::hex;
 use super::traits::{Codec, CodecCapabilities, CodecInfo};
 
+/// Codec name used in info and diagnostics.
+const CODEC_NAME: &str = "JPEG-LS";
+
 /// JPEG-LS codec implementation.
 pub struct JpegLsCodec {
     /// Maximum near-lossless error tolerance (0 = lossless).
@@ -33,7 +43,12 @@ impl JpegLsCodec {
     }
 
     /// Encode image to JPEG-LS format.
-    fn encode_jls(&self, image: &ImageData, config: &CompressionConfig) -> Result<Vec<u8>> {
+    fn encode_jls(
+        &self,
+        image: &ImageData,
+        config: &CompressionConfig,
+        progress: Option<&dyn Fn(f64)>,
+    ) -> Result<Vec<u8>> {
         // Validate image parameters
         if image.width == 0 || image.height == 0 {
             return Err(MedImgError::ImageData("Invalid image dimensions".into()));
@@ -43,14 +58,27 @@ impl JpegLsCodec {
             return Err(MedImgError::ImageData("Empty pixel data".into()));
         }
 
+        if image.number_of_frames > 1 {
+            return Err(MedImgError::ImageData(format!(
+                "Expected one frame, got {} (encode frames separately)",
+                image.number_of_frames
+            )));
+        }
+
         let near = if config.mode == CompressionMode::NearLossless {
-            config.near_lossless_error
+            u8::try_from(config.near_lossless_error).map_err(|_| {
+                MedImgError::Codec(format!(
+                    "JPEG-LS NEAR must be at most 255, got {}",
+                    config.near_lossless_error
+                ))
+            })?
         } else {
             0
         };
 
         // Create JPEG-LS codestream
-        let codestream = self.create_jls_codestream(image, near)?;
+        let codestream =
+            self.create_jls_codestream(image, near, config.jpegls_thresholds, progress)?;
 
         log::debug!(
             "JPEG-LS encoded {}x{} image to {} bytes (ratio: {:.2}:1, NEAR={})",
@@ -64,444 +92,1332 @@ impl JpegLsCodec {
         Ok(codestream)
     }
 
-    /// Create a JPEG-LS codestream.
-    fn create_jls_codestream(&self, image: &ImageData, near: u8) -> Result<Vec<u8>> {
-        let mut codestream = Vec::new();
+    /// Create a JPEG-LS codestream with one scan per component.
+    fn create_jls_codestream(
+        &self,
+        image: &ImageData,
+        near: u8,
+        thresholds: Option<JpegLsThresholds>,
+        progress: Option<&dyn Fn(f64)>,
+    ) -> Result<Vec<u8>> {
+        if image.bits_per_sample > 16 {
+            return Err(MedImgError::Codec(format!(
+                "JPEG-LS supports at most 16 bits per sample, got {}",
+                image.bits_per_sample
+            )));
+        }
+        let geometry = (image.width as usize, image.height as usize, image.samples_per_pixel.max(1) as usize);
+        let (width, height, components) = geometry;
+        let count = width * height * components;
+        let samples: Vec<u16> = if image.bits_per_sample <= 8 {
+            image.pixel_data.iter().map(|&value| u16::from(value)).collect()
+        } else {
+            pixel::decode_le(&image.pixel_data)
+        };
+        if samples.len() < count {
+            return Err(MedImgError::ImageData(format!(
+                "Pixel data holds {} samples, expected {}",
+                samples.len(),
+                count
+            )));
+        }
+
+        // Signed samples may be stored sign-extended above BitsStored;
+        // code the whole container then, so the bytes round-trip exactly
+        let bits = image.bits_per_sample.max(2);
+        let largest = samples[..count].iter().copied().max().unwrap_or(0);
+        let precision = if u32::from(largest) >> bits == 0 {
+            bits
+        } else {
+            let container = image.bits_per_sample.div_ceil(8) * 8;
+            log::debug!(
+                "JPEG-LS samples use bits above BitsStored ({}), coding {}-bit samples",
+                image.bits_per_sample,
+                container
+            );
+            container
+        };
+
+        write_codestream(&samples[..count], geometry, precision as u8, near, thresholds, 0, progress)
+    }
 
-        // SOI (Start of Image) marker
-        codestream.extend_from_slice(&[0xFF, 0xD8]);
+    /// Decode JPEG-LS codestream.
+    fn decode_jls(
+        &self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        bits_per_sample: u16,
+        samples_per_pixel: u16,
+    ) -> Result<Vec<u8>> {
+        let limits = DecodeLimits::current();
+        limits.check_image(width, height, bits_per_sample, samples_per_pixel, 1)?;
+        let budget = limits.start();
 
-        // SOF55 (JPEG-LS Start of Frame) marker segment
-        codestream.extend_from_slice(&self.create_sof55_segment(image));
+        // Validate markers
+        if data.len() < 4 {
+            return Err(CodecParseError::new(
+                CODEC_NAME,
+                0,
+                "at least 4 bytes",
+                format!("{} bytes", data.len()),
+            )
+            .into());
+        }
 
-        // LSE (JPEG-LS Preset Parameters) if near-lossless
-        if near > 0 {
-            codestream.extend_from_slice(&self.create_lse_segment(near));
+        if data[0] != 0xFF || data[1] != 0xD8 {
+            return Err(CodecParseError::new(CODEC_NAME, 0, "SOI marker FFD8", hex(&data[..2]))
+                .marker(0xFFD8)
+                .into());
         }
 
-        // SOS (Start of Scan) marker segment
-        codestream.extend_from_slice(&self.create_sos_segment(image, near));
+        let geometry = (width as usize, height as usize, samples_per_pixel.max(1) as usize);
+        let mut samples = vec![0u16; geometry.0 * geometry.1 * geometry.2];
+        let mut decoded = vec![false; geometry.2];
+        let mut frame = None;
+        let mut preset = Preset::default();
+        let mut pos = 2; // Skip SOI
 
-        // Compressed image data
-        let compressed = self.compress_data(image, near)?;
-        codestream.extend_from_slice(&compressed);
+        loop {
+            let Some((at, marker)) = next_marker(data, pos)? else {
+                // A missing EOI after the last scan is tolerated
+                if decoded.iter().all(|&done| done) {
+                    break;
+                }
+                let found = "end of codestream";
+                return Err(CodecParseError::new(CODEC_NAME, data.len(), "SOS marker FFDA", found).into());
+            };
+            if marker == 0xD9 {
+                if decoded.iter().all(|&done| done) {
+                    break;
+                }
+                return Err(CodecParseError::new(CODEC_NAME, at, "SOS marker FFDA", "EOI marker FFD9").into());
+            }
 
-        // EOI (End of Image) marker
-        codestream.extend_from_slice(&[0xFF, 0xD9]);
+            let payload = match marker {
+                0xF7 | 0xF8 | 0xDA | 0xE0..=0xEF | 0xFE => segment(data, at, marker)?,
+                _ => {
+                    return Err(CodecParseError::new(
+                        CODEC_NAME,
+                        at,
+                        "a JPEG-LS marker",
+                        format!("marker {}", hex(&data[at..at + 2])),
+                    )
+                    .into())
+                }
+            };
+            pos = at + 4 + payload.len();
 
-        Ok(codestream)
+            match marker {
+                0xF7 => frame = Some(parse_frame(payload, at, geometry, bits_per_sample)?),
+                0xF8 => preset = parse_preset(payload, at)?,
+                0xDA => {
+                    let Some(frame) = &frame else {
+                        let found = "SOS marker FFDA";
+                        let error = CodecParseError::new(CODEC_NAME, at, "SOF55 marker FFF7", found);
+                        return Err(error.marker(0xFFDA).into());
+                    };
+                    let scan = parse_scan(payload, at, frame, &decoded)?;
+                    let params = Parameters::new(frame.precision, scan.near, preset).map_err(|e| {
+                        CodecParseError::new(CODEC_NAME, at, "valid coding parameters", e).marker(0xFFDA)
+                    })?;
+                    pos = decode_scan(data, pos, geometry, &scan, params, &mut samples, &budget)?;
+                    for &component in &scan.components {
+                        decoded[component] = true;
+                    }
+                }
+                // APPn and COM
+                _ => {}
+            }
+        }
+
+        Ok(if bits_per_sample <= 8 {
+            samples.iter().map(|&value| value as u8).collect()
+        } else {
+            pixel::encode_le(&samples)
+        })
+    }
+}
+
+/// Write a codestream of `samples` (interleaved components of a
+/// `(width, height, components)` image) with interleave mode `ilv`: one
+/// scan per component for 0, a single scan of all components for line (1)
+/// and sample (2) interleaving.
+fn write_codestream(
+    samples: &[u16],
+    geometry: (usize, usize, usize),
+    precision: u8,
+    near: u8,
+    thresholds: Option<JpegLsThresholds>,
+    ilv: u8,
+    progress: Option<&dyn Fn(f64)>,
+) -> Result<Vec<u8>> {
+    let (width, height, components) = geometry;
+    let sizes = (u16::try_from(width), u16::try_from(height), u8::try_from(components));
+    let (Ok(x), Ok(y), Ok(count)) = sizes else {
+        return Err(MedImgError::Codec(format!(
+            "JPEG-LS frames are limited to 65535x65535 pixels and 255 components, got {}x{} with {}",
+            width, height, components
+        )));
+    };
+    let maxval = ((1u32 << precision) - 1) as u16;
+    let preset = thresholds.map_or_else(Preset::default, |t| Preset {
+        maxval,
+        t1: t.t1,
+        t2: t.t2,
+        t3: t.t3,
+        reset: t.reset,
+    });
+    let params = Parameters::new(precision, i32::from(near), preset)
+        .map_err(|e| MedImgError::Codec(format!("Invalid JPEG-LS parameters: {}", e)))?;
+
+    let mut codestream = Vec::new();
+
+    // SOI (Start of Image) marker
+    codestream.extend_from_slice(&[0xFF, 0xD8]);
+
+    // SOF55 (JPEG-LS Start of Frame) marker segment
+    codestream.extend_from_slice(&sof55_segment(precision, x, y, count));
+
+    // LSE (JPEG-LS Preset Parameters) if tuned
+    if let Some(thresholds) = thresholds {
+        codestream.extend_from_slice(&lse_segment(maxval, thresholds));
     }
 
-    /// Create SOF55 (Start of Frame for JPEG-LS) segment.
-    fn create_sof55_segment(&self, image: &ImageData) -> Vec<u8> {
-        let mut segment = Vec::new();
+    let scans: Vec<Vec<usize>> = if ilv == 0 {
+        (0..components).map(|component| vec![component]).collect()
+    } else {
+        vec![(0..components).collect()]
+    };
+    let total_rows = scans.len() * height;
+    for (index, scan) in scans.iter().enumerate() {
+        // SOS (Start of Scan) marker segment, then the coded samples
+        codestream.extend_from_slice(&sos_segment(scan, near, ilv));
+        codestream = encode_scan(codestream, samples, geometry, scan, ilv, params, |y| {
+            report_strip(progress, index * height + y, total_rows)
+        });
+    }
 
-        // SOF55 marker
-        segment.extend_from_slice(&[0xFF, 0xF7]);
+    // EOI (End of Image) marker
+    codestream.extend_from_slice(&[0xFF, 0xD9]);
 
-        // Segment length
-        let length = 8 + 3 * image.samples_per_pixel as usize;
-        segment.extend_from_slice(&(length as u16).to_be_bytes());
+    Ok(codestream)
+}
 
-        // Precision (bits per sample)
-        segment.push(image.bits_per_sample as u8);
+/// SOF55 (Start of Frame for JPEG-LS) segment.
+fn sof55_segment(precision: u8, width: u16, height: u16, components: u8) -> Vec<u8> {
+    let mut segment = Vec::new();
 
-        // Image dimensions
-        segment.extend_from_slice(&(image.height as u16).to_be_bytes());
-        segment.extend_from_slice(&(image.width as u16).to_be_bytes());
+    // SOF55 marker
+    segment.extend_from_slice(&[0xFF, 0xF7]);
 
-        // Number of components
-        segment.push(image.samples_per_pixel as u8);
+    // Segment length
+    let length = 8 + 3 * components as u16;
+    segment.extend_from_slice(&length.to_be_bytes());
 
-        // Component parameters
-        for i in 0..image.samples_per_pixel {
-            segment.push(i as u8 + 1); // Component ID
-            segment.push(0x11);         // Sampling factors (1:1)
-            segment.push(0x00);         // Quantization table (not used)
-        }
+    // Precision (bits per sample)
+    segment.push(precision);
 
-        segment
+    // Image dimensions
+    segment.extend_from_slice(&height.to_be_bytes());
+    segment.extend_from_slice(&width.to_be_bytes());
+
+    // Number of components
+    segment.push(components);
+
+    // Component parameters
+    for i in 0..components {
+        segment.push(i + 1); // Component ID
+        segment.push(0x11); // Sampling factors (1:1)
+        segment.push(0x00); // Quantization table (not used)
     }
 
-    /// Create LSE (JPEG-LS Preset Parameters) segment.
-    fn create_lse_segment(&self, _near: u8) -> Vec<u8> {
-        let mut segment = Vec::new();
+    segment
+}
 
-        // LSE marker
-        segment.extend_from_slice(&[0xFF, 0xF8]);
+/// LSE (JPEG-LS Preset Parameters) segment.
+fn lse_segment(maxval: u16, thresholds: JpegLsThresholds) -> Vec<u8> {
+    let mut segment = Vec::new();
 
-        // Segment length
-        segment.extend_from_slice(&[0x00, 0x0D]);
+    // LSE marker
+    segment.extend_from_slice(&[0xFF, 0xF8]);
 
-        // ID = 1 (preset parameters)
-        segment.push(0x01);
+    // Segment length
+    segment.extend_from_slice(&[0x00, 0x0D]);
 
-        // MAXVAL (default for 8-bit)
-        segment.extend_from_slice(&[0x00, 0xFF]);
+    // ID = 1 (preset parameters)
+    segment.push(0x01);
 
-        // T1, T2, T3 thresholds (defaults)
-        segment.extend_from_slice(&[0x00, 0x03]); // T1
-        segment.extend_from_slice(&[0x00, 0x07]); // T2
-        segment.extend_from_slice(&[0x00, 0x15]); // T3
+    // MAXVAL (largest sample value)
+    segment.extend_from_slice(&maxval.to_be_bytes());
 
-        // RESET
-        segment.extend_from_slice(&[0x00, 0x40]);
+    // T1, T2, T3 thresholds
+    segment.extend_from_slice(&thresholds.t1.to_be_bytes());
+    segment.extend_from_slice(&thresholds.t2.to_be_bytes());
+    segment.extend_from_slice(&thresholds.t3.to_be_bytes());
 
-        segment
-    }
+    // RESET
+    segment.extend_from_slice(&thresholds.reset.to_be_bytes());
 
-    /// Create SOS (Start of Scan) segment.
-    fn create_sos_segment(&self, image: &ImageData, near: u8) -> Vec<u8> {
-        let mut segment = Vec::new();
+    segment
+}
 
-        // SOS marker
-        segment.extend_from_slice(&[0xFF, 0xDA]);
+/// SOS (Start of Scan) segment for the zero-based frame `components`.
+fn sos_segment(components: &[usize], near: u8, ilv: u8) -> Vec<u8> {
+    let mut segment = Vec::new();
 
-        // Segment length
-        let length = 6 + 2 * image.samples_per_pixel as usize;
-        segment.extend_from_slice(&(length as u16).to_be_bytes());
+    // SOS marker
+    segment.extend_from_slice(&[0xFF, 0xDA]);
 
-        // Number of components in scan
-        segment.push(image.samples_per_pixel as u8);
+    // Segment length
+    let length = 6 + 2 * components.len() as u16;
+    segment.extend_from_slice(&length.to_be_bytes());
 
-        // Component selectors
-        for i in 0..image.samples_per_pixel {
-            segment.push(i as u8 + 1); // Component ID
-            segment.push(0x00);         // Mapping table (not used)
-        }
+    // Number of components in scan
+    segment.push(components.len() as u8);
 
-        // NEAR parameter
-        segment.push(near);
+    // Component selectors
+    for &component in components {
+        segment.push(component as u8 + 1); // Component ID
+        segment.push(0x00); // Mapping table (not used)
+    }
+
+    // NEAR parameter
+    segment.push(near);
 
-        // Interleave mode (0 = non-interleaved for grayscale)
-        segment.push(if image.samples_per_pixel > 1 { 2 } else { 0 });
+    // Interleave mode
+    segment.push(ilv);
 
-        // Point transform (not used)
-        segment.push(0x00);
+    // Point transform (not used)
+    segment.push(0x00);
 
-        segment
+    segment
+}
+
+/// Frame header (SOF55) of a codestream being decoded.
+struct Frame {
+    /// Bits per sample (P).
+    precision: u8,
+    /// Component IDs, in frame order.
+    ids: Vec<u8>,
+}
+
+/// Scan header (SOS) of a codestream being decoded.
+struct ScanHeader {
+    /// Zero-based frame components coded in the scan.
+    components: Vec<usize>,
+    near: i32,
+    /// Interleave mode: 0 none, 1 line, 2 sample.
+    ilv: u8,
+}
+
+/// The marker at `pos` (after any fill bytes) and its offset, or `None`
+/// at the end of the codestream.
+fn next_marker(data: &[u8], mut pos: usize) -> Result<Option<(usize, u8)>> {
+    if pos >= data.len() {
+        return Ok(None);
+    }
+    if data[pos] != 0xFF {
+        return Err(CodecParseError::new(CODEC_NAME, pos, "a marker", hex(&data[pos..=pos])).into());
+    }
+    while data.get(pos + 1) == Some(&0xFF) {
+        pos += 1;
+    }
+    match data.get(pos + 1) {
+        Some(&marker) => Ok(Some((pos, marker))),
+        None => Err(CodecParseError::new(CODEC_NAME, pos, "a marker", "end of codestream").into()),
     }
+}
 
-    /// Compress image data using LOCO-I algorithm (simplified for MVP).
-    fn compress_data(&self, image: &ImageData, near: u8) -> Result<Vec<u8>> {
-        let mut output = Vec::new();
-        let bytes_per_sample = ((image.bits_per_sample + 7) / 8) as usize;
+/// Payload of the marker segment at `at`.
+fn segment(data: &[u8], at: usize, marker: u8) -> Result<&[u8]> {
+    let truncated = |expected: String| -> MedImgError {
+        CodecParseError::new(CODEC_NAME, at, expected, format!("end of codestream at byte {}", data.len()))
+            .marker(0xFF00 | u16::from(marker))
+            .into()
+    };
+    let Some(length) = data.get(at + 2..at + 4) else {
+        return Err(truncated("a segment length".into()));
+    };
+    let length = u16::from_be_bytes([length[0], length[1]]) as usize;
+    if length < 2 {
+        return Err(CodecParseError::new(CODEC_NAME, at, "a segment length of at least 2", length.to_string())
+            .marker(0xFF00 | u16::from(marker))
+            .into());
+    }
+    data.get(at + 4..at + 2 + length)
+        .ok_or_else(|| truncated(format!("{} bytes of segment", length)))
+}
 
-        if bytes_per_sample == 1 {
-            self.compress_8bit(&image.pixel_data, image.width as usize, near, &mut output);
-        } else {
-            self.compress_16bit(&image.pixel_data, image.width as usize, near, &mut output);
+/// Parse the SOF55 segment at `at` and check it describes the expected
+/// `(width, height, components)` image.
+fn parse_frame(
+    payload: &[u8],
+    at: usize,
+    (width, height, components): (usize, usize, usize),
+    bits_per_sample: u16,
+) -> Result<Frame> {
+    let error = |expected: String, found: String| -> MedImgError {
+        CodecParseError::new(CODEC_NAME, at, expected, found).marker(0xFFF7).into()
+    };
+    if payload.len() < 6 {
+        return Err(error("a frame header of at least 6 bytes".into(), format!("{} bytes", payload.len())));
+    }
+    let precision = payload[0];
+    let y = u16::from_be_bytes([payload[1], payload[2]]) as usize;
+    let x = u16::from_be_bytes([payload[3], payload[4]]) as usize;
+    let count = payload[5] as usize;
+
+    let container = (bits_per_sample.div_ceil(8) * 8).min(16);
+    if !(2..=container).contains(&u16::from(precision)) {
+        return Err(error(format!("a precision of 2 to {} bits", container), format!("{} bits", precision)));
+    }
+    if (x, y) != (width, height) {
+        return Err(error(format!("a {}x{} frame", width, height), format!("{}x{}", x, y)));
+    }
+    if count != components || payload.len() != 6 + 3 * count {
+        return Err(error(
+            format!("{} components", components),
+            format!("{} components in {} bytes", count, payload.len()),
+        ));
+    }
+    let parameters = payload[6..].chunks(3);
+    if let Some(subsampled) = parameters.clone().find(|component| component[1] != 0x11) {
+        return Err(error(
+            "sampling factors 1x1".into(),
+            format!("{:02X} for component {}", subsampled[1], subsampled[0]),
+        ));
+    }
+    Ok(Frame {
+        precision,
+        ids: parameters.map(|component| component[0]).collect(),
+    })
+}
+
+/// Parse the LSE segment at `at`; only preset coding parameters (ID 1)
+/// are supported.
+fn parse_preset(payload: &[u8], at: usize) -> Result<Preset> {
+    let field = |index: usize| u16::from_be_bytes([payload[index], payload[index + 1]]);
+    let (expected, found) = match payload.first() {
+        Some(1) if payload.len() == 11 => {
+            return Ok(Preset {
+                maxval: field(1),
+                t1: field(3),
+                t2: field(5),
+                t3: field(7),
+                reset: field(9),
+            })
         }
+        Some(1) => ("11 bytes of preset parameters".to_string(), format!("{} bytes", payload.len())),
+        Some(&id) => ("preset parameters (LSE ID 1)".to_string(), format!("LSE ID {}", id)),
+        None => ("an LSE ID".to_string(), "empty segment".to_string()),
+    };
+    Err(CodecParseError::new(CODEC_NAME, at, expected, found).marker(0xFFF8).into())
+}
 
-        Ok(output)
+/// Parse the SOS segment at `at` against `frame`, rejecting components
+/// already `decoded` by an earlier scan.
+fn parse_scan(payload: &[u8], at: usize, frame: &Frame, decoded: &[bool]) -> Result<ScanHeader> {
+    let error = |expected: String, found: String| -> MedImgError {
+        CodecParseError::new(CODEC_NAME, at, expected, found).marker(0xFFDA).into()
+    };
+    let count = payload.first().copied().unwrap_or(0) as usize;
+    if count == 0 || payload.len() != 4 + 2 * count {
+        return Err(error(
+            "a scan header with at least one component".into(),
+            format!("{} components in {} bytes", count, payload.len()),
+        ));
     }
 
-    /// Compress 8-bit data using predictive coding.
-    fn compress_8bit(&self, data: &[u8], width: usize, near: u8, output: &mut Vec<u8>) {
-        let height = data.len() / width;
+    let mut components = Vec::with_capacity(count);
+    for selector in payload[1..1 + 2 * count].chunks(2) {
+        let Some(index) = frame.ids.iter().position(|&id| id == selector[0]) else {
+            return Err(error("a component of the frame".into(), format!("component {}", selector[0])));
+        };
+        if components.contains(&index) || decoded[index] {
+            let found = format!("component {} again", selector[0]);
+            return Err(error("each component in one scan".into(), found));
+        }
+        if selector[1] != 0 {
+            return Err(error("no mapping table".into(), format!("mapping table {}", selector[1])));
+        }
+        components.push(index);
+    }
 
-        // For near-lossless, we need to track reconstructed values to use for prediction
-        // (same as decoder) to prevent prediction drift
-        let mut reconstructed = vec![0u8; data.len()];
+    let [near, ilv, point_transform] = [0, 1, 2].map(|field| payload[1 + 2 * count + field]);
+    if ilv > 2 || (ilv == 0 && count > 1) {
+        return Err(error(
+            "interleave mode 0 for one component, or 1 or 2".into(),
+            format!("interleave mode {} with {} components", ilv, count),
+        ));
+    }
+    if point_transform != 0 {
+        return Err(error("point transform 0".into(), format!("point transform {}", point_transform)));
+    }
+    Ok(ScanHeader {
+        components,
+        near: i32::from(near),
+        ilv,
+    })
+}
 
-        for y in 0..height {
-            for x in 0..width {
-                let idx = y * width + x;
-                let current = data[idx];
-
-                // LOCO-I predictor: predict based on reconstructed neighbors
-                let prediction = if x == 0 && y == 0 {
-                    128u8 // First pixel
-                } else if y == 0 {
-                    reconstructed[idx - 1] // First row: use left neighbor
-                } else if x == 0 {
-                    reconstructed[idx - width] // First column: use above neighbor
-                } else {
-                    // Use median edge detector
-                    let a = reconstructed[idx - 1] as i16;           // Left
-                    let b = reconstructed[idx - width] as i16;       // Above
-                    let c = reconstructed[idx - width - 1] as i16;   // Above-left
-
-                    if c >= a.max(b) {
-                        a.min(b) as u8
-                    } else if c <= a.min(b) {
-                        a.max(b) as u8
-                    } else {
-                        (a + b - c).clamp(0, 255) as u8
-                    }
-                };
-
-                // Calculate prediction error
-                let error = current.wrapping_sub(prediction);
-
-                // Apply near-lossless quantization if needed
-                let quantized_error = if near > 0 {
-                    let e = error as i8 as i16;
-                    let step = 2 * near as i16 + 1;
-                    // Use proper floor division for negative numbers
-                    let q = if e >= 0 {
-                        (e + near as i16) / step
-                    } else {
-                        (e - near as i16) / step
-                    };
-                    (q as i8) as u8
-                } else {
-                    error
-                };
+/// Code the `components` of `samples` as one scan appended to `out`,
+/// calling `on_row` after each row.
+fn encode_scan(
+    out: Vec<u8>,
+    samples: &[u16],
+    (width, height, stride): (usize, usize, usize),
+    components: &[usize],
+    ilv: u8,
+    params: Parameters,
+    mut on_row: impl FnMut(usize),
+) -> Vec<u8> {
+    let mut output = BitWriter::new(out);
+    let mut scan = Scan::new(params);
+    let mut prev = vec![vec![0i32; width + 2]; components.len()];
+    let mut cur = prev.clone();
+    let mut run_index = vec![0; components.len()];
+
+    for y in 0..height {
+        for ((prev, cur), &component) in prev.iter_mut().zip(cur.iter_mut()).zip(components) {
+            start_line(prev, cur);
+            let row = &samples[y * width * stride..(y + 1) * width * stride];
+            for (sample, pixel) in cur[1..=width].iter_mut().zip(row.chunks_exact(stride)) {
+                *sample = i32::from(pixel[component]);
+            }
+        }
+        if ilv == 1 {
+            for ((prev, cur), run_index) in prev.iter().zip(cur.iter_mut()).zip(run_index.iter_mut()) {
+                scan.run_index = *run_index;
+                scan.encode_line(&mut output, std::slice::from_ref(prev), std::slice::from_mut(cur));
+                *run_index = scan.run_index;
+            }
+        } else {
+            scan.encode_line(&mut output, &prev, &mut cur);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+        on_row(y);
+    }
 
-                output.push(quantized_error);
+    output.finish()
+}
 
-                // Reconstruct pixel for future predictions
-                let dequantized_error = if near > 0 {
-                    let e = quantized_error as i8 as i16;
-                    let step = 2 * near as i16 + 1;
-                    (e * step) as i8 as u8
-                } else {
-                    quantized_error
-                };
-                reconstructed[idx] = prediction.wrapping_add(dequantized_error);
+/// Decode the scan whose coded data starts at `start` into the
+/// interleaved `samples`, checking `budget` every strip of rows. Returns
+/// the offset of the marker ending the scan.
+fn decode_scan(
+    data: &[u8],
+    start: usize,
+    (width, height, stride): (usize, usize, usize),
+    header: &ScanHeader,
+    params: Parameters,
+    samples: &mut [u16],
+    budget: &DecodeBudget,
+) -> Result<usize> {
+    let mut input = BitReader::new(data, start);
+    let mut scan = Scan::new(params);
+    let mut prev = vec![vec![0i32; width + 2]; header.components.len()];
+    let mut cur = prev.clone();
+    let mut run_index = vec![0; header.components.len()];
+
+    for y in 0..height {
+        if y.is_multiple_of(PROGRESS_STRIP_ROWS) {
+            budget.check()?;
+        }
+        for (prev, cur) in prev.iter_mut().zip(cur.iter_mut()) {
+            start_line(prev, cur);
+        }
+        if header.ilv == 1 {
+            for ((prev, cur), run_index) in prev.iter().zip(cur.iter_mut()).zip(run_index.iter_mut()) {
+                scan.run_index = *run_index;
+                scan.decode_line(&mut input, std::slice::from_ref(prev), std::slice::from_mut(cur))?;
+                *run_index = scan.run_index;
             }
+        } else {
+            scan.decode_line(&mut input, &prev, &mut cur)?;
         }
+        let row = &mut samples[y * width * stride..(y + 1) * width * stride];
+        for (line, &component) in cur.iter().zip(&header.components) {
+            for (pixel, &sample) in row.chunks_exact_mut(stride).zip(&line[1..=width]) {
+                pixel[component] = sample as u16;
+            }
+        }
+        std::mem::swap(&mut prev, &mut cur);
     }
 
-    /// Compress 16-bit data using predictive coding.
-    fn compress_16bit(&self, data: &[u8], width: usize, near: u8, output: &mut Vec<u8>) {
-        let samples = data.len() / 2;
-        let height = samples / width;
-
-        for y in 0..height {
-            for x in 0..width {
-                let idx = y * width + x;
-                let current = u16::from_le_bytes([data[idx * 2], data[idx * 2 + 1]]);
-
-                let prediction = if x == 0 && y == 0 {
-                    32768u16
-                } else if y == 0 {
-                    u16::from_le_bytes([data[(idx - 1) * 2], data[(idx - 1) * 2 + 1]])
-                } else if x == 0 {
-                    u16::from_le_bytes([data[(idx - width) * 2], data[(idx - width) * 2 + 1]])
-                } else {
-                    let a = u16::from_le_bytes([data[(idx - 1) * 2], data[(idx - 1) * 2 + 1]]) as i32;
-                    let b = u16::from_le_bytes([data[(idx - width) * 2], data[(idx - width) * 2 + 1]]) as i32;
-                    let c = u16::from_le_bytes([data[(idx - width - 1) * 2], data[(idx - width - 1) * 2 + 1]]) as i32;
-
-                    if c >= a.max(b) {
-                        a.min(b) as u16
-                    } else if c <= a.min(b) {
-                        a.max(b) as u16
-                    } else {
-                        (a + b - c).clamp(0, 65535) as u16
-                    }
-                };
+    Ok(input.end())
+}
 
-                let error = current.wrapping_sub(prediction);
+/// Set the edge samples of line buffers holding samples at `1..=width`:
+/// the sample left of the first is the one above it, and the sample
+/// above-right of the last is the one above it (T.87 A.2.1).
+fn start_line(prev: &mut [i32], cur: &mut [i32]) {
+    let width = prev.len() - 2;
+    prev[width + 1] = prev[width];
+    cur[0] = prev[1];
+}
 
-                let quantized_error = if near > 0 {
-                    let n = near as u32 * 256; // Scale for 16-bit
-                    let q = (error as i16 as i32 + n as i32) / (2 * n as i32 + 1);
-                    (q as i16) as u16
-                } else {
-                    error
-                };
+/// Rows encoded between progress reports (and decoded between time
+/// budget checks).
+const PROGRESS_STRIP_ROWS: usize = 64;
 
-                output.extend_from_slice(&quantized_error.to_le_bytes());
-            }
+/// Report progress after row `y` if it completes a strip or the image.
+fn report_strip(progress: Option<&dyn Fn(f64)>, y: usize, height: usize) {
+    if let Some(report) = progress {
+        let rows_done = y + 1;
+        if rows_done.is_multiple_of(PROGRESS_STRIP_ROWS) || rows_done == height {
+            report(rows_done as f64 / height as f64);
         }
     }
+}
 
-    /// Decode JPEG-LS codestream.
-    fn decode_jls(
-        &self,
-        data: &[u8],
-        width: u32,
-        height: u32,
-        bits_per_sample: u16,
-        _samples_per_pixel: u16,
-    ) -> Result<Vec<u8>> {
-        // Validate markers
-        if data.len() < 4 {
-            return Err(MedImgError::Codec("Invalid JPEG-LS data: too short".into()));
+/// Regular-mode contexts: one per quantized gradient triple, with a
+/// triple and its negation sharing a context (T.87 A.3.4).
+const REGULAR_CONTEXTS: usize = 365;
+
+/// Order of the run length block for each run index (T.87 A.7.1.2).
+const J: [u32; 32] = [
+    0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 9, 10, 11, 12, 13, 14, 15,
+];
+
+/// Preset coding parameters of an LSE segment; zero selects the default.
+#[derive(Debug, Clone, Copy, Default)]
+struct Preset {
+    maxval: u16,
+    t1: u16,
+    t2: u16,
+    t3: u16,
+    reset: u16,
+}
+
+/// Coding parameters of a scan (T.87 A.2.1 and C.2.4.1.1).
+#[derive(Debug, Clone, Copy)]
+struct Parameters {
+    maxval: i32,
+    near: i32,
+    t1: i32,
+    t2: i32,
+    t3: i32,
+    reset: i32,
+    /// Number of distinct quantized prediction errors.
+    range: i32,
+    /// Bits of an escaped error value.
+    qbpp: u32,
+    /// Longest Golomb code.
+    limit: u32,
+}
+
+impl Parameters {
+    /// Parameters for `precision`-bit samples coded with `near`, or a
+    /// description of the first invalid one.
+    fn new(precision: u8, near: i32, preset: Preset) -> std::result::Result<Self, String> {
+        let largest = (1i32 << precision) - 1;
+        let maxval = if preset.maxval == 0 { largest } else { i32::from(preset.maxval) };
+        if maxval > largest {
+            return Err(format!("MAXVAL {} exceeds {}-bit samples", maxval, precision));
+        }
+        let max_near = (maxval / 2).min(255);
+        if near > max_near {
+            return Err(format!("NEAR {} exceeds {} for MAXVAL {}", near, max_near, maxval));
         }
 
-        if data[0] != 0xFF || data[1] != 0xD8 {
-            return Err(MedImgError::Codec("Invalid JPEG-LS data: missing SOI marker".into()));
+        let defaults = default_thresholds(maxval, near);
+        let pick = |value: u16, default: i32| if value == 0 { default } else { i32::from(value) };
+        let t1 = pick(preset.t1, defaults.0);
+        let t2 = pick(preset.t2, defaults.1);
+        let t3 = pick(preset.t3, defaults.2);
+        if !(near < t1 && t1 <= t2 && t2 <= t3 && t3 <= maxval) {
+            return Err(format!(
+                "thresholds must satisfy NEAR < T1 <= T2 <= T3 <= MAXVAL \
+                 (NEAR={}, T1={}, T2={}, T3={}, MAXVAL={})",
+                near, t1, t2, t3, maxval
+            ));
+        }
+        let reset = pick(preset.reset, 64);
+        if !(3..=maxval.max(255)).contains(&reset) {
+            return Err(format!("RESET {} is outside 3..={}", reset, maxval.max(255)));
         }
 
-        // Parse header to find NEAR parameter and SOS marker
-        let (near, data_start) = self.parse_jls_header(data)?;
+        let range = (maxval + 2 * near) / (2 * near + 1) + 1;
+        let bpp = bit_length(maxval).max(2);
+        Ok(Self {
+            maxval,
+            near,
+            t1,
+            t2,
+            t3,
+            reset,
+            range,
+            qbpp: bit_length(range - 1),
+            limit: 2 * (bpp + bpp.max(8)),
+        })
+    }
 
-        // Find EOI marker
-        let data_end = if data.len() >= 2 && data[data.len() - 2] == 0xFF && data[data.len() - 1] == 0xD9 {
-            data.len() - 2
-        } else {
-            data.len()
-        };
+    /// Initial A of every context.
+    fn initial_a(&self) -> i64 {
+        i64::from(((self.range + 32) / 64).max(2))
+    }
+
+    /// Signed context of local gradients `d1`, `d2`, `d3`; its sign is the
+    /// sign of the first non-zero quantized gradient (T.87 A.3).
+    fn context(&self, d1: i32, d2: i32, d3: i32) -> i32 {
+        (self.quantize_gradient(d1) * 9 + self.quantize_gradient(d2)) * 9 + self.quantize_gradient(d3)
+    }
 
-        if data_start >= data_end {
-            return Err(MedImgError::Codec("Invalid JPEG-LS data: no image data".into()));
+    fn quantize_gradient(&self, d: i32) -> i32 {
+        if d <= -self.t3 {
+            -4
+        } else if d <= -self.t2 {
+            -3
+        } else if d <= -self.t1 {
+            -2
+        } else if d < -self.near {
+            -1
+        } else if d <= self.near {
+            0
+        } else if d < self.t1 {
+            1
+        } else if d < self.t2 {
+            2
+        } else if d < self.t3 {
+            3
+        } else {
+            4
         }
+    }
 
-        let compressed = &data[data_start..data_end];
+    /// Prediction error `d` quantized for NEAR and reduced modulo RANGE
+    /// (T.87 A.4.4 and A.4.5).
+    fn error_value(&self, d: i32) -> i32 {
+        let near = self.near;
+        let e = if near == 0 {
+            d
+        } else if d > 0 {
+            (d + near) / (2 * near + 1)
+        } else {
+            -((near - d) / (2 * near + 1))
+        };
+        let e = if e < 0 { e + self.range } else { e };
+        if e >= (self.range + 1) / 2 {
+            e - self.range
+        } else {
+            e
+        }
+    }
 
-        // Decompress
-        let bytes_per_sample = ((bits_per_sample + 7) / 8) as usize;
-        let output = if bytes_per_sample == 1 {
-            self.decompress_8bit(compressed, width as usize, height as usize, near)
+    /// Sample reconstructed from prediction `px` and quantized error `e`.
+    fn reconstruct(&self, px: i32, e: i32) -> i32 {
+        let step = 2 * self.near + 1;
+        let value = px + e * step;
+        let value = if value < -self.near {
+            value + self.range * step
+        } else if value > self.maxval + self.near {
+            value - self.range * step
         } else {
-            self.decompress_16bit(compressed, width as usize, height as usize, near)
+            value
         };
+        value.clamp(0, self.maxval)
+    }
+}
 
-        Ok(output)
+/// Default T1, T2, T3 for `maxval` and `near` (T.87 C.2.4.1.1.1).
+fn default_thresholds(maxval: i32, near: i32) -> (i32, i32, i32) {
+    let clamp = |value: i32, low: i32| if value > maxval || value < low { low } else { value };
+    if maxval >= 128 {
+        let factor = (maxval.min(4095) + 128) / 256;
+        let t1 = clamp(factor + 2 + 3 * near, near + 1);
+        let t2 = clamp(factor * 4 + 3 + 5 * near, t1);
+        (t1, t2, clamp(factor * 17 + 4 + 7 * near, t2))
+    } else {
+        let factor = 256 / (maxval + 1);
+        let t1 = clamp((3 / factor + 3 * near).max(2), near + 1);
+        let t2 = clamp((7 / factor + 5 * near).max(3), t1);
+        (t1, t2, clamp((21 / factor + 7 * near).max(4), t2))
     }
+}
 
-    /// Parse JPEG-LS header to extract NEAR parameter and data start position.
-    fn parse_jls_header(&self, data: &[u8]) -> Result<(u8, usize)> {
-        let mut pos = 2; // Skip SOI
-        let mut near = 0u8;
+/// Bits needed to hold `value`.
+fn bit_length(value: i32) -> u32 {
+    32 - (value as u32).leading_zeros()
+}
 
-        while pos < data.len() - 1 {
-            if data[pos] != 0xFF {
-                pos += 1;
-                continue;
-            }
+/// Median edge detector prediction from the left (`ra`), above (`rb`),
+/// and above-left (`rc`) samples.
+fn predict(ra: i32, rb: i32, rc: i32) -> i32 {
+    if rc >= ra.max(rb) {
+        ra.min(rb)
+    } else if rc <= ra.min(rb) {
+        ra.max(rb)
+    } else {
+        ra + rb - rc
+    }
+}
 
-            let marker = data[pos + 1];
-            pos += 2;
+/// Error `e` mapped to a non-negative value: 0, -1, 1, -2, ... to 0, 1,
+/// 2, 3, ...
+fn map_error(e: i32) -> u64 {
+    if e >= 0 {
+        2 * e as u64
+    } else {
+        2 * u64::from(e.unsigned_abs()) - 1
+    }
+}
 
-            match marker {
-                0xDA => {
-                    // SOS marker - extract NEAR and return data start
-                    if pos + 2 > data.len() {
-                        break;
-                    }
-                    let length = u16::from_be_bytes([data[pos], data[pos + 1]]) as usize;
-                    if pos + length > data.len() {
-                        break;
-                    }
+/// Statistics of a regular-mode context (T.87 A.2.2).
+#[derive(Debug, Clone, Copy)]
+struct Context {
+    /// Accumulated error magnitudes.
+    a: i64,
+    /// Accumulated errors, for bias estimation.
+    b: i64,
+    /// Prediction correction.
+    c: i32,
+    /// Occurrences.
+    n: i64,
+}
 
-                    // NEAR is after component selectors
-                    let num_components = data[pos + 2] as usize;
-                    let near_offset = pos + 3 + 2 * num_components;
-                    if near_offset < data.len() {
-                        near = data[near_offset];
-                    }
+impl Context {
+    fn new(a: i64) -> Self {
+        Self { a, b: 0, c: 0, n: 1 }
+    }
 
-                    return Ok((near, pos + length));
-                }
-                0xD9 => break, // EOI
-                0x00 => continue, // Stuffed byte
-                _ => {
-                    // Skip segment
-                    if pos + 2 <= data.len() {
-                        let length = u16::from_be_bytes([data[pos], data[pos + 1]]) as usize;
-                        pos += length;
-                    }
-                }
+    fn golomb_k(&self) -> u32 {
+        let mut k = 0;
+        while (self.n << k) < self.a {
+            k += 1;
+        }
+        k
+    }
+
+    /// -1 if errors are inverted before mapping (lossless, k = 0, negative
+    /// bias; T.87 A.5.2), else 0.
+    fn error_correction(&self, k: u32, near: i32) -> i32 {
+        if k == 0 && near == 0 && 2 * self.b + self.n - 1 < 0 {
+            -1
+        } else {
+            0
+        }
+    }
+
+    /// Update with quantized error `e` (T.87 A.6).
+    fn update(&mut self, e: i32, near: i32, reset: i32) {
+        self.a += i64::from(e.unsigned_abs());
+        self.b += i64::from(e) * i64::from(2 * near + 1);
+        if self.n == i64::from(reset) {
+            self.a >>= 1;
+            self.b >>= 1;
+            self.n >>= 1;
+        }
+        self.n += 1;
+
+        if self.b <= -self.n {
+            self.b += self.n;
+            if self.c > -128 {
+                self.c -= 1;
+            }
+            if self.b <= -self.n {
+                self.b = -self.n + 1;
+            }
+        } else if self.b > 0 {
+            self.b -= self.n;
+            if self.c < 127 {
+                self.c += 1;
+            }
+            if self.b > 0 {
+                self.b = 0;
             }
         }
+    }
+}
 
-        Err(MedImgError::Codec("Could not find SOS marker in JPEG-LS data".into()))
+/// Statistics of a run interruption context (T.87 A.7.2).
+#[derive(Debug, Clone, Copy)]
+struct RunContext {
+    /// 1 if the interruption is predicted from the left sample, 0 if from
+    /// the one above.
+    ri_type: i64,
+    a: i64,
+    n: i64,
+    /// Negative errors seen.
+    nn: i64,
+}
+
+impl RunContext {
+    fn new(ri_type: i64, a: i64) -> Self {
+        Self { ri_type, a, n: 1, nn: 0 }
     }
 
-    /// Decompress 8-bit data.
-    fn decompress_8bit(&self, data: &[u8], width: usize, height: usize, near: u8) -> Vec<u8> {
-        let mut output = vec![0u8; width * height];
+    fn golomb_k(&self) -> u32 {
+        let target = self.a + (self.n >> 1) * self.ri_type;
+        let mut k = 0;
+        while (self.n << k) < target {
+            k += 1;
+        }
+        k
+    }
 
-        for y in 0..height {
-            for x in 0..width {
-                let idx = y * width + x;
-                if idx >= data.len() {
-                    break;
-                }
+    /// Whether error `e` maps to the odd of its two candidate values.
+    fn map(&self, e: i32, k: u32) -> bool {
+        (k == 0 && e > 0 && 2 * self.nn < self.n) || (e < 0 && (2 * self.nn >= self.n || k != 0))
+    }
 
-                let error = data[idx];
+    /// Error mapped to `value` (EMErrval + RItype).
+    fn unmap(&self, value: i64, k: u32) -> i32 {
+        let map = value & 1 == 1;
+        let magnitude = ((value + i64::from(map)) / 2) as i32;
+        if (k != 0 || 2 * self.nn >= self.n) == map {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
 
-                // Reconstruct prediction
-                let prediction = if x == 0 && y == 0 {
-                    128u8
-                } else if y == 0 {
-                    output[idx - 1]
-                } else if x == 0 {
-                    output[idx - width]
-                } else {
-                    let a = output[idx - 1] as i16;
-                    let b = output[idx - width] as i16;
-                    let c = output[idx - width - 1] as i16;
-
-                    if c >= a.max(b) {
-                        a.min(b) as u8
-                    } else if c <= a.min(b) {
-                        a.max(b) as u8
-                    } else {
-                        (a + b - c).clamp(0, 255) as u8
-                    }
-                };
+    /// Update with error `e` coded as `em` (T.87 A.7.2.2).
+    fn update(&mut self, e: i32, em: i64, reset: i32) {
+        if e < 0 {
+            self.nn += 1;
+        }
+        self.a += (em + 1 - self.ri_type) >> 1;
+        if self.n == i64::from(reset) {
+            self.a >>= 1;
+            self.n >>= 1;
+            self.nn >>= 1;
+        }
+        self.n += 1;
+    }
+}
 
-                // Dequantize error if near-lossless
-                let dequantized_error = if near > 0 {
-                    let e = error as i8 as i16;
-                    let step = 2 * near as i16 + 1;
-                    (e * step) as i8 as u8
-                } else {
-                    error
-                };
+/// Context modeling state of one scan, shared by its components.
+struct Scan {
+    params: Parameters,
+    regular: Vec<Context>,
+    run: [RunContext; 2],
+    run_index: usize,
+}
 
-                output[idx] = prediction.wrapping_add(dequantized_error);
+impl Scan {
+    fn new(params: Parameters) -> Self {
+        let a = params.initial_a();
+        Self {
+            params,
+            regular: vec![Context::new(a); REGULAR_CONTEXTS],
+            run: [RunContext::new(0, a), RunContext::new(1, a)],
+            run_index: 0,
+        }
+    }
+
+    /// Context of the sample at `x` from its neighbours.
+    fn context(&self, prev: &[i32], cur: &[i32], x: usize) -> i32 {
+        let (ra, rb, rc, rd) = (cur[x - 1], prev[x], prev[x - 1], prev[x + 1]);
+        self.params.context(rd - rb, rb - rc, rc - ra)
+    }
+
+    /// Longest run at `start` of `remaining` pixels within NEAR of the
+    /// pixel left of it, set to that pixel.
+    fn fill_run(&self, cur: &mut [Vec<i32>], start: usize, remaining: usize) -> usize {
+        let near = self.params.near;
+        let mut length = 0;
+        while length < remaining
+            && cur
+                .iter()
+                .all(|line| (line[start + length] - line[start - 1]).abs() <= near)
+        {
+            for line in cur.iter_mut() {
+                line[start + length] = line[start - 1];
             }
+            length += 1;
         }
+        length
+    }
 
-        output
+    /// Largest value an escaped error code may take.
+    fn max_mapped(&self) -> u64 {
+        2 * self.params.range as u64 + 1
     }
 
-    /// Decompress 16-bit data.
-    fn decompress_16bit(&self, data: &[u8], width: usize, height: usize, near: u8) -> Vec<u8> {
-        let mut output = vec![0u8; width * height * 2];
-        let samples = width * height;
+    /// Encode one line of each of the components in `cur`, replacing its
+    /// samples with their reconstructions.
+    fn encode_line(&mut self, output: &mut BitWriter, prev: &[Vec<i32>], cur: &mut [Vec<i32>]) {
+        let width = cur[0].len() - 2;
+        let mut x = 1;
+        while x <= width {
+            if prev.iter().zip(cur.iter()).all(|(prev, cur)| self.context(prev, cur, x) == 0) {
+                x += self.encode_run(output, prev, cur, x);
+            } else {
+                for (prev, cur) in prev.iter().zip(cur.iter_mut()) {
+                    let qs = self.context(prev, cur, x);
+                    let px = predict(cur[x - 1], prev[x], prev[x - 1]);
+                    cur[x] = self.encode_regular(output, qs, cur[x], px);
+                }
+                x += 1;
+            }
+        }
+    }
 
-        for i in 0..samples {
-            let y = i / width;
-            let x = i % width;
+    fn encode_regular(&mut self, output: &mut BitWriter, qs: i32, sample: i32, px: i32) -> i32 {
+        let p = self.params;
+        let sign = if qs < 0 { -1 } else { 1 };
+        let context = &mut self.regular[qs.unsigned_abs() as usize];
+        let k = context.golomb_k();
+        let px = (px + sign * context.c).clamp(0, p.maxval);
+        let e = p.error_value(sign * (sample - px));
+        output.golomb(k, map_error(e ^ context.error_correction(k, p.near)), p.limit, p.qbpp);
+        context.update(e, p.near, p.reset);
+        p.reconstruct(px, sign * e)
+    }
 
-            if i * 2 + 1 >= data.len() {
-                break;
+    /// Encode the run starting at `start` and the pixel interrupting it;
+    /// returns the pixels coded.
+    fn encode_run(
+        &mut self,
+        output: &mut BitWriter,
+        prev: &[Vec<i32>],
+        cur: &mut [Vec<i32>],
+        start: usize,
+    ) -> usize {
+        let remaining = cur[0].len() - 1 - start;
+        let length = self.fill_run(cur, start, remaining);
+
+        let mut rest = length;
+        while rest >= 1 << J[self.run_index] {
+            output.put(1, 1);
+            rest -= 1 << J[self.run_index];
+            self.run_index = (self.run_index + 1).min(J.len() - 1);
+        }
+        if length == remaining {
+            if rest > 0 {
+                output.put(1, 1);
             }
+            return length;
+        }
+        output.put(rest as u64, J[self.run_index] + 1);
 
-            let error = u16::from_le_bytes([data[i * 2], data[i * 2 + 1]]);
+        let x = start + length;
+        let single = cur.len() == 1;
+        for (prev, cur) in prev.iter().zip(cur.iter_mut()) {
+            cur[x] = self.encode_interruption(output, cur[x], cur[x - 1], prev[x], single);
+        }
+        self.run_index = self.run_index.saturating_sub(1);
+        length + 1
+    }
 
-            let prediction = if x == 0 && y == 0 {
-                32768u16
-            } else if y == 0 {
-                u16::from_le_bytes([output[(i - 1) * 2], output[(i - 1) * 2 + 1]])
-            } else if x == 0 {
-                u16::from_le_bytes([output[(i - width) * 2], output[(i - width) * 2 + 1]])
+    /// Encode a run interruption sample. A single component is predicted
+    /// from the left sample if it is within NEAR of the one above; samples
+    /// of interleaved pixels always from the one above.
+    fn encode_interruption(
+        &mut self,
+        output: &mut BitWriter,
+        sample: i32,
+        ra: i32,
+        rb: i32,
+        single: bool,
+    ) -> i32 {
+        let p = self.params;
+        if single && (ra - rb).abs() <= p.near {
+            let e = p.error_value(sample - ra);
+            self.encode_interruption_error(output, 1, e);
+            p.reconstruct(ra, e)
+        } else {
+            let sign = if rb < ra { -1 } else { 1 };
+            let e = p.error_value(sign * (sample - rb));
+            self.encode_interruption_error(output, 0, e);
+            p.reconstruct(rb, sign * e)
+        }
+    }
+
+    fn encode_interruption_error(&mut self, output: &mut BitWriter, ri_type: usize, e: i32) {
+        let p = self.params;
+        let limit = p.limit - J[self.run_index] - 1;
+        let context = &mut self.run[ri_type];
+        let k = context.golomb_k();
+        let em = 2 * i64::from(e.unsigned_abs()) - context.ri_type - i64::from(context.map(e, k));
+        output.golomb(k, em as u64, limit, p.qbpp);
+        context.update(e, em, p.reset);
+    }
+
+    /// Decode one line of each of the components in `cur`.
+    fn decode_line(&mut self, input: &mut BitReader, prev: &[Vec<i32>], cur: &mut [Vec<i32>]) -> Result<()> {
+        let width = cur[0].len() - 2;
+        let mut x = 1;
+        while x <= width {
+            if prev.iter().zip(cur.iter()).all(|(prev, cur)| self.context(prev, cur, x) == 0) {
+                x += self.decode_run(input, prev, cur, x)?;
             } else {
-                let a = u16::from_le_bytes([output[(i - 1) * 2], output[(i - 1) * 2 + 1]]) as i32;
-                let b = u16::from_le_bytes([output[(i - width) * 2], output[(i - width) * 2 + 1]]) as i32;
-                let c = u16::from_le_bytes([output[(i - width - 1) * 2], output[(i - width - 1) * 2 + 1]]) as i32;
-
-                if c >= a.max(b) {
-                    a.min(b) as u16
-                } else if c <= a.min(b) {
-                    a.max(b) as u16
-                } else {
-                    (a + b - c).clamp(0, 65535) as u16
+                for (prev, cur) in prev.iter().zip(cur.iter_mut()) {
+                    let qs = self.context(prev, cur, x);
+                    let px = predict(cur[x - 1], prev[x], prev[x - 1]);
+                    cur[x] = self.decode_regular(input, qs, px)?;
                 }
-            };
+                x += 1;
+            }
+        }
+        Ok(())
+    }
 
-            let dequantized_error = if near > 0 {
-                let n = near as u32 * 256;
-                let e = error as i16 as i32;
-                (e * (2 * n as i32 + 1)) as i16 as u16
+    fn decode_regular(&mut self, input: &mut BitReader, qs: i32, px: i32) -> Result<i32> {
+        let p = self.params;
+        let max_mapped = self.max_mapped();
+        let sign = if qs < 0 { -1 } else { 1 };
+        let context = &mut self.regular[qs.unsigned_abs() as usize];
+        let k = context.golomb_k();
+        let px = (px + sign * context.c).clamp(0, p.maxval);
+        let mapped = input.golomb(k, p.limit, p.qbpp, max_mapped)? as i32;
+        let e = ((mapped >> 1) ^ -(mapped & 1)) ^ context.error_correction(k, p.near);
+        context.update(e, p.near, p.reset);
+        Ok(p.reconstruct(px, sign * e))
+    }
+
+    /// Decode the run starting at `start` and the pixel interrupting it;
+    /// returns the pixels decoded.
+    fn decode_run(
+        &mut self,
+        input: &mut BitReader,
+        prev: &[Vec<i32>],
+        cur: &mut [Vec<i32>],
+        start: usize,
+    ) -> Result<usize> {
+        let remaining = cur[0].len() - 1 - start;
+        let mut length = 0;
+        while length < remaining && input.bits(1)? == 1 {
+            let block = 1 << J[self.run_index];
+            if block <= remaining - length {
+                length += block;
+                self.run_index = (self.run_index + 1).min(J.len() - 1);
             } else {
-                error
+                length = remaining;
+            }
+        }
+        if length < remaining {
+            length += input.bits(J[self.run_index])? as usize;
+            if length > remaining {
+                return Err(input.error(
+                    format!("a run of at most {} pixels", remaining),
+                    format!("{} pixels", length),
+                ));
+            }
+        }
+        for line in cur.iter_mut() {
+            let ra = line[start - 1];
+            line[start..start + length].fill(ra);
+        }
+        if length == remaining {
+            return Ok(length);
+        }
+
+        let x = start + length;
+        let single = cur.len() == 1;
+        for (prev, cur) in prev.iter().zip(cur.iter_mut()) {
+            cur[x] = self.decode_interruption(input, cur[x - 1], prev[x], single)?;
+        }
+        self.run_index = self.run_index.saturating_sub(1);
+        Ok(length + 1)
+    }
+
+    fn decode_interruption(&mut self, input: &mut BitReader, ra: i32, rb: i32, single: bool) -> Result<i32> {
+        let p = self.params;
+        if single && (ra - rb).abs() <= p.near {
+            let e = self.decode_interruption_error(input, 1)?;
+            Ok(p.reconstruct(ra, e))
+        } else {
+            let sign = if rb < ra { -1 } else { 1 };
+            let e = self.decode_interruption_error(input, 0)?;
+            Ok(p.reconstruct(rb, sign * e))
+        }
+    }
+
+    fn decode_interruption_error(&mut self, input: &mut BitReader, ri_type: usize) -> Result<i32> {
+        let p = self.params;
+        let max_mapped = self.max_mapped();
+        let limit = p.limit - J[self.run_index] - 1;
+        let context = &mut self.run[ri_type];
+        let k = context.golomb_k();
+        let em = input.golomb(k, limit, p.qbpp, max_mapped)? as i64;
+        let e = context.unmap(em + context.ri_type, k);
+        context.update(e, em, p.reset);
+        Ok(e)
+    }
+}
+
+/// Writes scan data MSB first. A 0xFF byte is followed by a zero bit, so
+/// coded data never forms a marker (T.87 A.1).
+struct BitWriter {
+    out: Vec<u8>,
+    /// Byte being filled.
+    byte: u8,
+    /// Bits still free in `byte`.
+    free: u32,
+}
+
+impl BitWriter {
+    /// Writer appending to `out`.
+    fn new(out: Vec<u8>) -> Self {
+        Self { out, byte: 0, free: 8 }
+    }
+
+    /// Write the low `n` bits of `value`.
+    fn put(&mut self, value: u64, mut n: u32) {
+        while n > 0 {
+            let take = n.min(self.free);
+            n -= take;
+            let bits = (value >> n) & ((1 << take) - 1);
+            self.free -= take;
+            self.byte |= (bits << self.free) as u8;
+            if self.free == 0 {
+                self.out.push(self.byte);
+                self.free = if self.byte == 0xFF { 7 } else { 8 };
+                self.byte = 0;
+            }
+        }
+    }
+
+    fn zeros(&mut self, mut n: u32) {
+        while n > 0 {
+            let take = n.min(32);
+            self.put(0, take);
+            n -= take;
+        }
+    }
+
+    /// Write `value` as a Golomb code of order `k` whose length is
+    /// limited to `limit` bits by escaping to `qbpp` bits (T.87 A.5.3).
+    fn golomb(&mut self, k: u32, value: u64, limit: u32, qbpp: u32) {
+        let max = limit - qbpp - 1;
+        let high = value >> k;
+        if high < u64::from(max) {
+            self.zeros(high as u32);
+            self.put(1, 1);
+            self.put(value, k);
+        } else {
+            self.zeros(max);
+            self.put(1, 1);
+            self.put(value - 1, qbpp);
+        }
+    }
+
+    /// Pad the last byte with zeros and return the output.
+    fn finish(mut self) -> Vec<u8> {
+        if self.free < 8 {
+            self.out.push(self.byte);
+        }
+        self.out
+    }
+}
+
+/// Reads scan data written by [`BitWriter`], stopping at the marker that
+/// ends the scan.
+struct BitReader<'a> {
+    data: &'a [u8],
+    /// Next byte to load.
+    pos: usize,
+    /// Loaded bits, the last `count` of which are unread.
+    bits: u64,
+    count: u32,
+    /// Whether the last byte loaded was 0xFF.
+    after_ff: bool,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self {
+            data,
+            pos,
+            bits: 0,
+            count: 0,
+            after_ff: false,
+        }
+    }
+
+    /// Load bytes up to the next marker.
+    fn fill(&mut self) {
+        while self.count <= 56 {
+            let Some(&byte) = self.data.get(self.pos) else {
+                return;
             };
+            if byte == 0xFF && self.data.get(self.pos + 1).is_none_or(|&next| next >= 0x80) {
+                return;
+            }
+            self.pos += 1;
+            if self.after_ff {
+                self.bits = (self.bits << 7) | u64::from(byte & 0x7F);
+                self.count += 7;
+            } else {
+                self.bits = (self.bits << 8) | u64::from(byte);
+                self.count += 8;
+            }
+            self.after_ff = byte == 0xFF;
+        }
+    }
 
-            let value = prediction.wrapping_add(dequantized_error);
-            output[i * 2] = value as u8;
-            output[i * 2 + 1] = (value >> 8) as u8;
+    /// Read `n` bits (at most 56).
+    fn bits(&mut self, n: u32) -> Result<u64> {
+        if n == 0 {
+            return Ok(0);
+        }
+        if self.count < n {
+            self.fill();
+            if self.count < n {
+                return Err(self.exhausted());
+            }
         }
+        self.count -= n;
+        Ok((self.bits >> self.count) & ((1 << n) - 1))
+    }
 
-        output
+    /// Read a Golomb code written by [`BitWriter::golomb`] whose value is
+    /// at most `max_value`.
+    fn golomb(&mut self, k: u32, limit: u32, qbpp: u32, max_value: u64) -> Result<u64> {
+        let max = limit - qbpp - 1;
+        let mut zeros = 0;
+        loop {
+            if self.count == 0 {
+                self.fill();
+                if self.count == 0 {
+                    return Err(self.exhausted());
+                }
+            }
+            let leading = (self.bits << (64 - self.count)).leading_zeros().min(self.count);
+            zeros += leading;
+            if zeros > max {
+                return Err(self.error(
+                    format!("a Golomb code of at most {} bits", limit),
+                    format!("{} leading zeros", zeros),
+                ));
+            }
+            if leading < self.count {
+                self.count -= leading + 1;
+                break;
+            }
+            self.count = 0;
+        }
+
+        let value = if zeros < max {
+            (u64::from(zeros) << k) | self.bits(k)?
+        } else {
+            self.bits(qbpp)? + 1
+        };
+        if value > max_value {
+            return Err(self.error(format!("a coded error of at most {}", max_value), value.to_string()));
+        }
+        Ok(value)
+    }
+
+    /// Offset of the marker ending the scan.
+    fn end(&self) -> usize {
+        (self.pos..self.data.len().saturating_sub(1))
+            .find(|&pos| self.data[pos] == 0xFF && self.data[pos + 1] >= 0x80)
+            .unwrap_or(self.data.len())
+    }
+
+    fn exhausted(&self) -> MedImgError {
+        let found = match self.data.get(self.pos..self.pos + 2) {
+            Some(marker) => format!("marker {}", hex(marker)),
+            None => "end of codestream".into(),
+        };
+        self.error("more scan data", found)
+    }
+
+    fn error(&self, expected: impl Into<String>, found: impl Into<String>) -> MedImgError {
+        CodecParseError::new(CODEC_NAME, self.pos, expected, found).marker(0xFFDA).into()
     }
 }
 
@@ -512,8 +1428,13 @@ impl Default for JpegLsCodec {
 }
 
 impl Codec for JpegLsCodec {
-    fn encode(&self, image: &ImageData, config: &CompressionConfig) -> Result<Vec<u8>> {
-        self.encode_jls(image, config)
+    fn encode(
+        &self,
+        image: &ImageData,
+        config: &CompressionConfig,
+        progress: Option<&dyn Fn(f64)>,
+    ) -> Result<Vec<u8>> {
+        self.encode_jls(&image.packed(), config, progress)
     }
 
     fn decode(
@@ -531,7 +1452,9 @@ impl Codec for JpegLsCodec {
             height,
             bits_per_sample,
             samples_per_pixel,
-            pixel_data,
+            number_of_frames: 1,
+            pixel_data: pixel_data.into(),
+            row_stride: None,
             photometric_interpretation: String::new(),
             is_signed: false,
         })
@@ -539,8 +1462,8 @@ impl Codec for JpegLsCodec {
 
     fn info(&self) -> CodecInfo {
         CodecInfo {
-            name: "JPEG-LS",
-            version: "MVP 0.1",
+            name: CODEC_NAME,
+            version: "ITU-T T.87",
             supports_lossless: true,
             supports_lossy: true, // Near-lossless
             supports_progressive: false,
@@ -566,7 +1489,7 @@ mod tests {
     use crate::config::CompressionCodec;
 
     fn create_test_image(width: u32, height: u32, bits: u16) -> ImageData {
-        let bytes_per_sample = ((bits + 7) / 8) as usize;
+        let bytes_per_sample = bits.div_ceil(8) as usize;
         let size = width as usize * height as usize * bytes_per_sample;
         let mut pixel_data = Vec::with_capacity(size);
 
@@ -579,7 +1502,9 @@ mod tests {
             height,
             bits_per_sample: bits,
             samples_per_pixel: 1,
-            pixel_data,
+            number_of_frames: 1,
+            pixel_data: pixel_data.into(),
+            row_stride: None,
             photometric_interpretation: "MONOCHROME2".into(),
             is_signed: false,
         }
@@ -591,12 +1516,25 @@ mod tests {
         let image = create_test_image(32, 32, 8);
         let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
 
-        let encoded = codec.encode(&image, &config).unwrap();
+        let encoded = codec.encode(&image, &config, None).unwrap();
         let decoded = codec.decode(&encoded, 32, 32, 8, 1).unwrap();
 
         assert_eq!(image.pixel_data, decoded.pixel_data);
     }
 
+    #[test]
+    fn test_jpegls_malformed_codestream() {
+        let codec = JpegLsCodec::lossless();
+        match codec.decode(&[0xFF, 0xD8, 0xFF, 0xD9], 2, 2, 8, 1) {
+            Err(MedImgError::CodecParse(e)) => {
+                assert_eq!(e.offset, 2);
+                assert_eq!(e.expected, "SOS marker FFDA");
+                assert_eq!(e.found, "EOI marker FFD9");
+            }
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+    }
+
     #[test]
     fn test_jpegls_near_lossless() {
         let codec = JpegLsCodec::near_lossless(2);
@@ -617,33 +1555,195 @@ mod tests {
             height: height as u32,
             bits_per_sample: 8,
             samples_per_pixel: 1,
-            pixel_data,
+            number_of_frames: 1,
+            pixel_data: pixel_data.into(),
+            row_stride: None,
             photometric_interpretation: "MONOCHROME2".into(),
             is_signed: false,
         };
 
-        let mut config = CompressionConfig::default();
-        config.mode = CompressionMode::NearLossless;
-        config.near_lossless_error = 2;
+        let config = CompressionConfig {
+            mode: CompressionMode::NearLossless,
+            near_lossless_error: 2,
+            ..Default::default()
+        };
 
-        let encoded = codec.encode(&image, &config).unwrap();
+        let encoded = codec.encode(&image, &config, None).unwrap();
         let decoded = codec.decode(&encoded, 32, 32, 8, 1).unwrap();
 
         // Near-lossless should have bounded differences
-        let max_diff: u8 = image
+        let max_diff: u16 = image
             .pixel_data
             .iter()
             .zip(decoded.pixel_data.iter())
-            .map(|(a, b)| (*a as i16 - *b as i16).unsigned_abs() as u8)
+            .map(|(a, b)| (*a as i16 - *b as i16).unsigned_abs())
             .max()
             .unwrap_or(0);
 
-        // For MVP, verify encoding/decoding works and differences are reasonable
         assert!(
-            max_diff <= 2 * config.near_lossless_error + 1,
+            max_diff <= config.near_lossless_error,
             "Max diff {} exceeds near-lossless bound {}",
             max_diff,
-            2 * config.near_lossless_error + 1
+            config.near_lossless_error
         );
+        assert!(encoded.len() < image.pixel_data.len() / 4);
+    }
+
+    #[test]
+    fn test_jpegls_near_lossless_bound() {
+        let config = CompressionConfig {
+            mode: CompressionMode::NearLossless,
+            near_lossless_error: 3,
+            ..CompressionConfig::lossless(CompressionCodec::JpegLs)
+        };
+        let codec = JpegLsCodec::new();
+        // Alternating extremes, 8-bit RGB and 16-bit grayscale
+        let rgb: Vec<u8> = (0..16 * 16 * 3).map(|i| if (i / 3 + i % 3) % 2 == 0 { 254 } else { 1 }).collect();
+        let gray: Vec<u8> = (0..16 * 16u32)
+            .flat_map(|i| (if i % 3 == 0 { 65534u16 } else { (i * 257) as u16 }).to_le_bytes())
+            .collect();
+        for image in [ImageData::new(16, 16, 8, 3, rgb), ImageData::new(16, 16, 16, 1, gray)] {
+            let encoded = codec.encode(&image, &config, None).unwrap();
+            let decoded = codec
+                .decode(&encoded, 16, 16, image.bits_per_sample, image.samples_per_pixel)
+                .unwrap();
+            let (original, decoded) = (image.stored_values().unwrap(), decoded.stored_values().unwrap());
+            assert_eq!(original.samples.len(), decoded.samples.len());
+            for (a, b) in original.samples.iter().zip(&decoded.samples) {
+                assert!((a - b).abs() <= 3, "{} vs {}", a, b);
+            }
+
+            let lossless = CompressionConfig::lossless(CompressionCodec::JpegLs);
+            let encoded = codec.encode(&image, &lossless, None).unwrap();
+            let decoded = codec
+                .decode(&encoded, 16, 16, image.bits_per_sample, image.samples_per_pixel)
+                .unwrap();
+            assert_eq!(decoded.pixel_data, image.pixel_data);
+        }
+    }
+
+    #[test]
+    fn test_jpegls_encode_progress() {
+        use std::cell::RefCell;
+
+        let codec = JpegLsCodec::lossless();
+        let image = create_test_image(16, 200, 8);
+        let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+
+        let reports = RefCell::new(Vec::new());
+        let record = |fraction: f64| reports.borrow_mut().push(fraction);
+        codec.encode(&image, &config, Some(&record)).unwrap();
+
+        let reports = reports.into_inner();
+        assert_eq!(reports.len(), 4);
+        assert!(reports.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(reports.last(), Some(&1.0));
+    }
+
+    #[test]
+    fn test_jpegls_compresses_smooth_images() {
+        let codec = JpegLsCodec::lossless();
+        let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+
+        // 12-bit ramp with low-amplitude noise, as in CT soft tissue
+        let mut seed = 1u32;
+        let ramp: Vec<u8> = (0..128 * 128u32)
+            .flat_map(|i| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                let value = 1000 + (i % 128) * 8 + (i / 128) * 4 + (seed >> 28);
+                (value as u16).to_le_bytes()
+            })
+            .collect();
+        // Flat background coded in run mode
+        let flat = vec![17u8; 128 * 128];
+        // Signed samples sign-extended above BitsStored
+        let signed: Vec<u8> = (0..128 * 128i32)
+            .flat_map(|i| ((i % 300 - 150) as i16).to_le_bytes())
+            .collect();
+
+        for (image, max_size) in [
+            (ImageData::new(128, 128, 12, 1, ramp), 128 * 128 * 2 / 3),
+            (ImageData::new(128, 128, 8, 1, flat), 128),
+            (ImageData::new(128, 128, 12, 1, signed), 128 * 128 * 2 / 2),
+        ] {
+            let encoded = codec.encode(&image, &config, None).unwrap();
+            assert!(encoded.len() < max_size, "{} bytes, expected under {}", encoded.len(), max_size);
+            let decoded = codec.decode(&encoded, 128, 128, image.bits_per_sample, 1).unwrap();
+            assert_eq!(decoded.pixel_data, image.pixel_data);
+        }
+    }
+
+    #[test]
+    fn test_jpegls_interleave_modes() {
+        let codec = JpegLsCodec::new();
+        let (width, height) = (40usize, 24usize);
+        let samples: Vec<u16> = (0..width * height * 3)
+            .map(|i| {
+                let (pixel, component) = (i / 3, i % 3);
+                let (x, y) = (pixel % width, pixel / width);
+                // Flat left half for run mode, edges and texture on the right
+                if x < width / 2 {
+                    (60 * component) as u16
+                } else {
+                    ((x * 7 + y * 13 + component * 50 + (x * y) % 11) % 256) as u16
+                }
+            })
+            .collect();
+        let pixels: Vec<u8> = samples.iter().map(|&value| value as u8).collect();
+
+        for ilv in 0..=2 {
+            for near in [0u8, 2] {
+                let encoded = write_codestream(&samples, (width, height, 3), 8, near, None, ilv, None).unwrap();
+                let decoded = codec.decode(&encoded, width as u32, height as u32, 8, 3).unwrap();
+                for (a, b) in pixels.iter().zip(decoded.pixel_data.iter()) {
+                    assert!(a.abs_diff(*b) <= near, "ILV {} NEAR {}: {} vs {}", ilv, near, a, b);
+                }
+            }
+        }
+
+        let thresholds = JpegLsThresholds { t1: 2, t2: 5, t3: 9, reset: 32 };
+        let encoded = write_codestream(&samples, (width, height, 3), 8, 0, Some(thresholds), 2, None).unwrap();
+        let decoded = codec.decode(&encoded, width as u32, height as u32, 8, 3).unwrap();
+        assert_eq!(decoded.pixel_data.as_ref(), &pixels[..]);
+    }
+
+    #[test]
+    fn test_jpegls_default_parameters() {
+        let p = Parameters::new(8, 0, Preset::default()).unwrap();
+        assert_eq!((p.t1, p.t2, p.t3, p.reset), (3, 7, 21, 64));
+        assert_eq!((p.range, p.qbpp, p.limit), (256, 8, 32));
+
+        let p = Parameters::new(16, 0, Preset::default()).unwrap();
+        assert_eq!((p.t1, p.t2, p.t3), (18, 67, 276));
+        assert_eq!((p.range, p.qbpp, p.limit), (65536, 16, 64));
+
+        let p = Parameters::new(8, 3, Preset::default()).unwrap();
+        assert_eq!((p.t1, p.t2, p.t3), (12, 22, 42));
+        assert_eq!((p.range, p.qbpp), (38, 6));
+
+        assert!(Parameters::new(4, 0, Preset { t3: 21, ..Preset::default() }).is_err());
+        assert!(Parameters::new(8, 128, Preset::default()).is_err());
+    }
+
+    #[test]
+    fn test_jpegls_corrupt_scan() {
+        let codec = JpegLsCodec::lossless();
+        let image = create_test_image(32, 32, 16);
+        let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+        let encoded = codec.encode(&image, &config, None).unwrap();
+
+        // Truncated scan data
+        let truncated = &encoded[..encoded.len() / 2];
+        assert!(matches!(codec.decode(truncated, 32, 32, 16, 1), Err(MedImgError::CodecParse(_))));
+
+        // Damaged scan data decodes to wrong samples or fails, but never panics
+        for offset in (25..encoded.len() - 2).step_by(7) {
+            let mut damaged = encoded.clone();
+            damaged[offset] ^= 0x5A;
+            let _ = codec.decode(&damaged, 32, 32, 16, 1);
+        }
+
+        // Frame of a different image
+        assert!(matches!(codec.decode(&encoded, 32, 16, 16, 1), Err(MedImgError::CodecParse(_))));
     }
 }
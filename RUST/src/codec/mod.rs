@@ -1,15 +1,24 @@
 //! Codec implementations for medical image compression.
 //!
 //! This module provides the `Codec` trait and implementations for:
-//! - JPEG 2000 (via OpenJPEG)
+//! - JPEG 2000 (built-in, or OpenJPEG with the `openjpeg` feature)
 //! - JPEG-LS (via CharLS)
+//! - External encoder plugins (see [`plugin`])
 
 mod jpeg2000;
 mod jpegls;
+#[cfg(all(feature = "openjpeg", not(target_arch = "wasm32")))]
+mod openjpeg;
 mod traits;
 
+pub mod bench;
+pub mod dwt;
+pub mod estimate;
+pub mod plugin;
+
 pub use jpeg2000::Jpeg2000Codec;
 pub use jpegls::JpegLsCodec;
+pub use plugin::PluginCodec;
 pub use traits::{Codec, CodecCapabilities, CodecInfo};
 
 use crate::config::{CompressionCodec, CompressionConfig};
@@ -28,12 +37,25 @@ impl CodecFactory {
         }
     }
 
-    /// Get the appropriate codec for the given configuration.
-    pub fn for_config(config: &CompressionConfig) -> Box<dyn Codec> {
-        Self::create(config.codec)
+    /// Get the appropriate codec for the given configuration: its encoder
+    /// plugin if it names one, otherwise the built-in codec.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Codec` if the encoder plugin cannot be loaded.
+    pub fn for_config(config: &CompressionConfig) -> Result<Box<dyn Codec>> {
+        match &config.encoder_plugin {
+            Some(path) => Ok(Box::new(PluginCodec::open(path)?)),
+            None => Ok(Self::create(config.codec)),
+        }
     }
 }
 
+/// Bytes as uppercase hex (e.g. `FF4F`), for parse diagnostics.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
 /// Passthrough codec for uncompressed data.
 struct UncompressedCodec;
 
@@ -42,8 +64,12 @@ impl Codec for UncompressedCodec {
         &self,
         image: &crate::ImageData,
         _config: &CompressionConfig,
+        progress: Option<&dyn Fn(f64)>,
     ) -> Result<Vec<u8>> {
-        Ok(image.pixel_data.clone())
+        if let Some(report) = progress {
+            report(1.0);
+        }
+        Ok(image.packed().pixel_data.to_vec())
     }
 
     fn decode(
@@ -59,7 +85,9 @@ impl Codec for UncompressedCodec {
             height,
             bits_per_sample,
             samples_per_pixel,
-            pixel_data: data.to_vec(),
+            number_of_frames: 1,
+            pixel_data: crate::Bytes::copy_from_slice(data),
+            row_stride: None,
             photometric_interpretation: String::new(),
             is_signed: false,
         })
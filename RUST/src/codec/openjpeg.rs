@@ -0,0 +1,415 @@
+//! OpenJPEG backend of the JPEG 2000 codec (`openjpeg` feature).
+//!
+//! libopenjp2 2.x is built from source and linked statically by
+//! `openjpeg-sys`, whose bindings are generated with bindgen from
+//! `openjpeg.h`, so the structure layouts always match the library.
+
+use std::ffi::{c_char, c_int, c_void, CStr};
+
+use openjpeg_sys as opj;
+
+use super::jpeg2000::component_planes;
+use crate::config::{CompressionConfig, CompressionMode, DecodeLimits, ProgressionOrder};
+use crate::error::{MedImgError, Result};
+use crate::{pixel, ImageData};
+
+/// Library version (e.g. `2.5.2`).
+pub(super) fn version() -> &'static str {
+    // SAFETY: opj_version returns a static NUL-terminated string
+    unsafe { CStr::from_ptr(opj::opj_version()) }.to_str().unwrap_or("2")
+}
+
+/// Encode one packed frame to a J2K codestream: 5/3 for lossless mode,
+/// 9/7 with rate allocation to the target ratio otherwise.
+///
+/// `progress` advances as the library starts each tile.
+pub(super) fn encode(
+    image: &ImageData,
+    config: &CompressionConfig,
+    progress: Option<&dyn Fn(f64)>,
+) -> Result<Vec<u8>> {
+    let (width, height) = (image.width, image.height);
+    let components = u32::from(image.samples_per_pixel.max(1));
+    let bits = u32::from(image.bits_per_sample);
+
+    // SAFETY: opj_cparameters_t is plain data, and the library fills in
+    // its defaults
+    let mut parameters: Box<opj::opj_cparameters_t> = Box::new(unsafe { std::mem::zeroed() });
+    unsafe { opj::opj_set_default_encoder_parameters(&mut *parameters) };
+    let (tile_width, tile_height) = if config.tile_size == 0 {
+        (width, height)
+    } else {
+        parameters.tile_size_on = 1;
+        parameters.cp_tdx = config.tile_size as c_int;
+        parameters.cp_tdy = config.tile_size as c_int;
+        (config.tile_size.min(width), config.tile_size.min(height))
+    };
+    // Every resolution must keep at least one sample per tile
+    let levels = u32::from(config.decomposition_levels).min(tile_width.min(tile_height).ilog2());
+    parameters.numresolution = levels as c_int + 1;
+    parameters.prog_order = match config.progression_order {
+        ProgressionOrder::Lrcp => opj::PROG_ORDER::OPJ_LRCP,
+        ProgressionOrder::Rlcp => opj::PROG_ORDER::OPJ_RLCP,
+        ProgressionOrder::Rpcl => opj::PROG_ORDER::OPJ_RPCL,
+        ProgressionOrder::Pcrl => opj::PROG_ORDER::OPJ_PCRL,
+        ProgressionOrder::Cprl => opj::PROG_ORDER::OPJ_CPRL,
+    };
+    parameters.tcp_mct = 0;
+    parameters.cp_disto_alloc = 1;
+    let layers = config.quality_layers.clamp(1, 100) as usize;
+    parameters.tcp_numlayers = layers as c_int;
+    // Layers double in rate up to the last one, which is lossless (0) or
+    // at the target ratio
+    let last = if config.mode == CompressionMode::Lossless {
+        parameters.irreversible = 0;
+        0.0
+    } else {
+        parameters.irreversible = 1;
+        config.target_ratio_for(image.stored_bits_per_pixel()).unwrap_or(10.0)
+    };
+    for (layer, rate) in parameters.tcp_rates[..layers].iter_mut().enumerate() {
+        let doublings = (layers - 1 - layer) as i32;
+        *rate = if doublings == 0 { last } else { last.max(1.0) * 2f32.powi(doublings) };
+    }
+
+    let mut component_parameters: Vec<opj::opj_image_cmptparm_t> = (0..components)
+        .map(|_| opj::opj_image_cmptparm_t {
+            dx: 1,
+            dy: 1,
+            w: width,
+            h: height,
+            x0: 0,
+            y0: 0,
+            prec: bits,
+            bpp: bits,
+            sgnd: u32::from(image.is_signed),
+        })
+        .collect();
+    let color_space = if components == 1 {
+        opj::COLOR_SPACE::OPJ_CLRSPC_GRAY
+    } else {
+        opj::COLOR_SPACE::OPJ_CLRSPC_SRGB
+    };
+    // SAFETY: one parameter block per component
+    let raw = unsafe { opj::opj_image_create(components, component_parameters.as_mut_ptr(), color_space) };
+    let opj_image = Owned::new(raw, opj::opj_image_destroy, "allocate the image")?;
+    // SAFETY: opj_image_create allocated `components` planes of width x height
+    unsafe {
+        let target = &mut *opj_image.0;
+        (target.x0, target.y0, target.x1, target.y1) = (0, 0, width, height);
+        for (c, plane) in component_planes(image).into_iter().enumerate() {
+            let comp = &mut *target.comps.add(c);
+            std::slice::from_raw_parts_mut(comp.data, plane.len()).copy_from_slice(&plane);
+        }
+    }
+
+    let mut messages = Vec::new();
+    let codec = codec(opj::opj_create_compress, &mut messages)?;
+    if let Some(report) = &progress {
+        // SAFETY: `progress` outlives the codec
+        unsafe {
+            let report = (report as *const &dyn Fn(f64)).cast_mut();
+            opj::opj_set_info_handler(codec.0, Some(report_tile), report.cast());
+        }
+    }
+    let mut output = Buffer::default();
+    let stream = stream(&mut output, false)?;
+    // SAFETY: all handles are live; `output` and `messages` outlive them
+    let encoded = unsafe {
+        opj::opj_setup_encoder(codec.0, &mut *parameters, opj_image.0) != 0
+            && opj::opj_start_compress(codec.0, opj_image.0, stream.0) != 0
+            && opj::opj_encode(codec.0, stream.0) != 0
+            && opj::opj_end_compress(codec.0, stream.0) != 0
+    };
+    // The stream flushes its last chunk when destroyed
+    drop(stream);
+    drop(codec);
+    if !encoded {
+        return Err(failure("encode", &messages));
+    }
+    if let Some(report) = progress {
+        report(1.0);
+    }
+    Ok(output.data)
+}
+
+/// Decode a J2K codestream to interleaved little-endian samples.
+pub(super) fn decode(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    bits_per_sample: u16,
+    samples_per_pixel: u16,
+) -> Result<Vec<u8>> {
+    DecodeLimits::current().check_image(width, height, bits_per_sample, samples_per_pixel, 1)?;
+
+    // SAFETY: opj_dparameters_t is plain data, and the library fills in
+    // its defaults
+    let mut parameters: Box<opj::opj_dparameters_t> = Box::new(unsafe { std::mem::zeroed() });
+    let mut messages = Vec::new();
+    let codec = codec(opj::opj_create_decompress, &mut messages)?;
+    let mut input = Buffer {
+        data: data.to_vec(),
+        position: 0,
+    };
+    let stream = stream(&mut input, true)?;
+    let mut raw: *mut opj::opj_image_t = std::ptr::null_mut();
+    // SAFETY: all handles are live; `input` and `messages` outlive them
+    let header = unsafe {
+        opj::opj_set_default_decoder_parameters(&mut *parameters);
+        opj::opj_setup_decoder(codec.0, &mut *parameters) != 0
+            && opj::opj_read_header(stream.0, codec.0, &mut raw) != 0
+    };
+    let opj_image = if raw.is_null() { None } else { Some(Owned(raw, opj::opj_image_destroy)) };
+    let opj_image = match opj_image {
+        Some(image) if header => image,
+        _ => return Err(failure("read the codestream header", &messages)),
+    };
+
+    // Checked before decoding so a forged header cannot allocate more than
+    // the caller expects
+    let comps = opj_image.components();
+    if comps.len() != usize::from(samples_per_pixel)
+        || comps.iter().any(|c| (c.w, c.h, c.dx, c.dy) != (width, height, 1, 1))
+    {
+        return Err(MedImgError::Codec(format!(
+            "OpenJPEG codestream has {} components of {}x{}, expected {} of {}x{}",
+            comps.len(),
+            comps.first().map_or(0, |c| c.w),
+            comps.first().map_or(0, |c| c.h),
+            samples_per_pixel,
+            width,
+            height
+        )));
+    }
+    // SAFETY: as above
+    let decoded = unsafe {
+        opj::opj_decode(codec.0, stream.0, opj_image.0) != 0 && opj::opj_end_decompress(codec.0, stream.0) != 0
+    };
+    if !decoded {
+        return Err(failure("decode", &messages));
+    }
+
+    let plane_len = width as usize * height as usize;
+    let components = usize::from(samples_per_pixel);
+    let mut samples = vec![0i32; plane_len * components];
+    for (c, comp) in opj_image.components().iter().enumerate() {
+        if comp.data.is_null() {
+            return Err(failure("decode all components", &messages));
+        }
+        // SAFETY: opj_decode filled w x h samples of each component
+        let plane = unsafe { std::slice::from_raw_parts(comp.data, plane_len) };
+        for (i, &value) in plane.iter().enumerate() {
+            samples[i * components + c] = value;
+        }
+    }
+    Ok(if bits_per_sample <= 8 {
+        samples.into_iter().map(|v| v as u8).collect()
+    } else {
+        pixel::encode_le(&samples.into_iter().map(|v| v as u16).collect::<Vec<_>>())
+    })
+}
+
+/// A J2K codec whose errors are collected in `messages`.
+fn codec(
+    create: unsafe extern "C" fn(opj::OPJ_CODEC_FORMAT) -> *mut opj::opj_codec_t,
+    messages: &mut Vec<String>,
+) -> Result<Owned<opj::opj_codec_t>> {
+    // SAFETY: J2K is a valid format for both directions
+    let raw = unsafe { create(opj::CODEC_FORMAT::OPJ_CODEC_J2K) };
+    let codec = Owned::new(raw, opj::opj_destroy_codec, "create a codec")?;
+    // SAFETY: `messages` outlives the codec
+    unsafe {
+        opj::opj_set_error_handler(codec.0, Some(collect_message), (messages as *mut Vec<String>).cast());
+    }
+    Ok(codec)
+}
+
+/// A stream reading from or writing to `buffer`.
+fn stream(buffer: &mut Buffer, input: bool) -> Result<Owned<opj::opj_stream_t>> {
+    // SAFETY: the callbacks match the stream API and `buffer` outlives the
+    // stream
+    unsafe {
+        let raw = opj::opj_stream_create(opj::OPJ_J2K_STREAM_CHUNK_SIZE as usize, c_int::from(input));
+        let stream = Owned::new(raw, opj::opj_stream_destroy, "create a stream")?;
+        if input {
+            opj::opj_stream_set_read_function(stream.0, Some(read));
+            opj::opj_stream_set_user_data_length(stream.0, buffer.data.len() as u64);
+        } else {
+            opj::opj_stream_set_write_function(stream.0, Some(write));
+        }
+        opj::opj_stream_set_skip_function(stream.0, Some(skip));
+        opj::opj_stream_set_seek_function(stream.0, Some(seek));
+        opj::opj_stream_set_user_data(stream.0, (buffer as *mut Buffer).cast(), None);
+        Ok(stream)
+    }
+}
+
+/// Library object destroyed when dropped.
+struct Owned<T>(*mut T, unsafe extern "C" fn(*mut T));
+
+impl<T> Owned<T> {
+    fn new(raw: *mut T, destroy: unsafe extern "C" fn(*mut T), what: &str) -> Result<Self> {
+        if raw.is_null() {
+            return Err(MedImgError::Codec(format!("OpenJPEG could not {}", what)));
+        }
+        Ok(Self(raw, destroy))
+    }
+}
+
+impl<T> Drop for Owned<T> {
+    fn drop(&mut self) {
+        // SAFETY: the pointer came from the matching constructor
+        unsafe { (self.1)(self.0) }
+    }
+}
+
+impl Owned<opj::opj_image_t> {
+    /// Components of the image.
+    fn components(&self) -> &[opj::opj_image_comp_t] {
+        // SAFETY: the library keeps `numcomps` components at `comps`
+        unsafe {
+            let image = &*self.0;
+            std::slice::from_raw_parts(image.comps, image.numcomps as usize)
+        }
+    }
+}
+
+/// Codestream read or written through a stream's callbacks.
+#[derive(Default)]
+struct Buffer {
+    data: Vec<u8>,
+    position: usize,
+}
+
+fn failure(what: &str, messages: &[String]) -> MedImgError {
+    let detail = messages.iter().map(|m| m.trim()).collect::<Vec<_>>().join("; ");
+    if detail.is_empty() {
+        MedImgError::Codec(format!("OpenJPEG failed to {}", what))
+    } else {
+        MedImgError::Codec(format!("OpenJPEG failed to {}: {}", what, detail))
+    }
+}
+
+unsafe extern "C" fn collect_message(message: *const c_char, messages: *mut c_void) {
+    if !message.is_null() {
+        let messages = &mut *messages.cast::<Vec<String>>();
+        messages.push(CStr::from_ptr(message).to_string_lossy().into_owned());
+    }
+}
+
+/// Report the tiles finished before the one OpenJPEG announces as
+/// `tile number N / M`.
+unsafe extern "C" fn report_tile(message: *const c_char, report: *mut c_void) {
+    if message.is_null() {
+        return;
+    }
+    let message = CStr::from_ptr(message).to_string_lossy();
+    if let Some((tile, tiles)) = tile_number(&message) {
+        (*report.cast::<&dyn Fn(f64)>())(f64::from(tile - 1) / f64::from(tiles));
+    }
+}
+
+/// The 1-based tile and tile count of a `tile number N / M` message.
+fn tile_number(message: &str) -> Option<(u32, u32)> {
+    let (tile, tiles) = message.trim().strip_prefix("tile number ")?.split_once(" / ")?;
+    let (tile, tiles) = (tile.parse().ok()?, tiles.parse().ok()?);
+    (1..=tiles).contains(&tile).then_some((tile, tiles))
+}
+
+unsafe extern "C" fn read(into: *mut c_void, count: usize, buffer: *mut c_void) -> usize {
+    let buffer = &mut *buffer.cast::<Buffer>();
+    let available = buffer.data.len().saturating_sub(buffer.position);
+    if available == 0 {
+        // (OPJ_SIZE_T)-1 marks the end of the stream
+        return usize::MAX;
+    }
+    let count = count.min(available);
+    std::ptr::copy_nonoverlapping(buffer.data.as_ptr().add(buffer.position), into.cast::<u8>(), count);
+    buffer.position += count;
+    count
+}
+
+unsafe extern "C" fn write(from: *mut c_void, count: usize, buffer: *mut c_void) -> usize {
+    let buffer = &mut *buffer.cast::<Buffer>();
+    let bytes = std::slice::from_raw_parts(from.cast::<u8>(), count);
+    let end = buffer.position + count;
+    if buffer.data.len() < end {
+        buffer.data.resize(end, 0);
+    }
+    buffer.data[buffer.position..end].copy_from_slice(bytes);
+    buffer.position = end;
+    count
+}
+
+unsafe extern "C" fn skip(count: i64, buffer: *mut c_void) -> i64 {
+    let buffer = &mut *buffer.cast::<Buffer>();
+    let target = (buffer.position as i64).saturating_add(count).max(0);
+    let skipped = target - buffer.position as i64;
+    buffer.position = target as usize;
+    skipped
+}
+
+unsafe extern "C" fn seek(position: i64, buffer: *mut c_void) -> c_int {
+    let buffer = &mut *buffer.cast::<Buffer>();
+    match usize::try_from(position) {
+        Ok(position) => {
+            buffer.position = position;
+            1
+        }
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::{Codec, Jpeg2000Codec};
+    use crate::config::{CompressionCodec, Jpeg2000Encoder};
+
+    #[test]
+    fn test_conformant_roundtrip() {
+        assert!(version().starts_with('2'));
+        let values: Vec<u16> = (0..96 * 80).map(|i| (i % 96 * 20 + i / 96 * 11) as u16).collect();
+        let image = ImageData::new(96, 80, 12, 1, pixel::encode_le(&values));
+        let codec = Jpeg2000Codec::new();
+
+        let lossless = CompressionConfig::lossless(CompressionCodec::Jpeg2000);
+        let encoded = codec.encode(&image, &lossless, None).unwrap();
+        // SIZ follows SOC directly in a conformant codestream
+        assert_eq!(encoded[..4], [0xFF, 0x4F, 0xFF, 0x51]);
+        assert_eq!(codec.decode(&encoded, 96, 80, 12, 1).unwrap().pixel_data, image.pixel_data);
+
+        let lossy = CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0);
+        let encoded = codec.encode(&image, &lossy, None).unwrap();
+        assert!(encoded.len() < image.pixel_data.len() / 4, "{} bytes", encoded.len());
+        assert_eq!(codec.decode(&encoded, 96, 80, 12, 1).unwrap().pixel_data.len(), image.pixel_data.len());
+
+        // Built-in codestreams still decode with the built-in decoder
+        let builtin = CompressionConfig {
+            jpeg2000_encoder: Jpeg2000Encoder::Builtin,
+            ..lossless
+        };
+        let encoded = codec.encode(&image, &builtin, None).unwrap();
+        assert_eq!(codec.decode(&encoded, 96, 80, 12, 1).unwrap().pixel_data, image.pixel_data);
+    }
+
+    #[test]
+    fn test_tile_progress() {
+        assert_eq!(tile_number("tile number 3 / 6\n"), Some((3, 6)));
+        assert_eq!(tile_number("tile number 0 / 6\n"), None);
+        assert_eq!(tile_number("Main header has been correctly decoded.\n"), None);
+
+        let pixels: Vec<u8> = (0..128 * 96).map(|i| (i % 251) as u8).collect();
+        let image = ImageData::new(128, 96, 8, 1, pixels);
+        let config = CompressionConfig {
+            tile_size: 64,
+            ..CompressionConfig::lossless(CompressionCodec::Jpeg2000)
+        };
+        let reports = std::cell::RefCell::new(Vec::new());
+        let progress = |fraction: f64| reports.borrow_mut().push(fraction);
+        encode(&image, &config, Some(&progress)).unwrap();
+        // 2 columns x 2 rows of tiles
+        assert_eq!(reports.into_inner(), [0.0, 0.25, 0.5, 0.75, 1.0]);
+    }
+}
@@ -0,0 +1,470 @@
+//! External encoder plugins.
+//!
+//! A plugin is an executable run once per frame, so proprietary encoders can
+//! be used without linking them into the crate. The first argument selects
+//! the command:
+//!
+//! - `handshake`: print a JSON [`PluginInfo`] on stdout, e.g.
+//!   `{"protocol":1,"name":"acme-htj2k","version":"2.1","transfer_syntax_lossless":"1.2.840.10008.1.2.4.201"}`.
+//! - `encode`: read a one-line JSON [`PluginRequest`] header on stdin, then
+//!   `length` bytes of packed little-endian pixels (or read them from
+//!   `pixel_file` with the `shm` transport), and write the codestream to
+//!   stdout.
+//! - `decode`: the same with a codestream in and pixels out, for plugins
+//!   that set `decode`. Other plugins' output is decoded by the built-in
+//!   codec for their transfer syntax, if there is one.
+//!
+//! A plugin exits non-zero on failure, with the reason on stderr. The
+//! handshake runs once per executable and process.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+use super::traits::{Codec, CodecCapabilities, CodecInfo};
+use super::CodecFactory;
+use crate::config::{
+    transfer_syntax, CompressionCodec, CompressionConfig, CompressionMode, DecodeLimits,
+};
+use crate::dicom::utils::codec_for_transfer_syntax;
+use crate::error::{MedImgError, Result};
+use crate::ImageData;
+
+/// Version of the plugin protocol.
+pub const PLUGIN_PROTOCOL: u32 = 1;
+
+/// How pixels reach the plugin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginTransport {
+    /// After the header on stdin.
+    Stdin,
+    /// In a file in shared memory (`/dev/shm` where available), named by
+    /// the header's `pixel_file`.
+    Shm,
+}
+
+/// What a plugin reports in its handshake.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginInfo {
+    /// Protocol version (must be [`PLUGIN_PROTOCOL`]).
+    pub protocol: u32,
+    /// Encoder name.
+    pub name: String,
+    /// Encoder version.
+    pub version: String,
+    /// DICOM transfer syntax UID of lossless codestreams.
+    #[serde(default)]
+    pub transfer_syntax_lossless: Option<String>,
+    /// DICOM transfer syntax UID of lossy codestreams.
+    #[serde(default)]
+    pub transfer_syntax_lossy: Option<String>,
+    /// Maximum bits per sample.
+    #[serde(default = "default_max_bits")]
+    pub max_bits_per_sample: u16,
+    /// Whether signed pixels are accepted.
+    #[serde(default)]
+    pub signed: bool,
+    /// Whether color images are accepted.
+    #[serde(default)]
+    pub color: bool,
+    /// Whether the plugin implements `decode`.
+    #[serde(default)]
+    pub decode: bool,
+    /// Supported pixel transports.
+    #[serde(default = "default_transports")]
+    pub transports: Vec<PluginTransport>,
+}
+
+fn default_max_bits() -> u16 {
+    16
+}
+
+fn default_transports() -> Vec<PluginTransport> {
+    vec![PluginTransport::Stdin]
+}
+
+/// Header line sent before each `encode` or `decode`.
+#[derive(Debug, Serialize)]
+pub struct PluginRequest<'a> {
+    /// Image width in pixels.
+    pub width: u32,
+    /// Image height in pixels.
+    pub height: u32,
+    /// Bits per sample.
+    pub bits_per_sample: u16,
+    /// Samples per pixel.
+    pub samples_per_pixel: u16,
+    /// Whether pixel values are signed.
+    pub signed: bool,
+    /// Photometric interpretation (empty when decoding).
+    pub photometric: &'a str,
+    /// Compression mode (encode only).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<CompressionMode>,
+    /// Target compression ratio (lossy mode).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_ratio: Option<f32>,
+    /// Target bits per pixel (lossy mode).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_bpp: Option<f32>,
+    /// Maximum sample error (near-lossless mode).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub near: Option<u16>,
+    /// Length of the input in bytes.
+    pub length: usize,
+    /// File holding the input (`shm` transport); absent when it follows
+    /// on stdin.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pixel_file: Option<PathBuf>,
+}
+
+/// Codec running an external encoder plugin.
+pub struct PluginCodec {
+    /// Plugin executable.
+    path: PathBuf,
+    /// Handshake, shared by every codec for the executable.
+    info: &'static PluginInfo,
+    /// Built-in codec decoding the plugin's output, if it cannot decode.
+    fallback: Option<CompressionCodec>,
+}
+
+impl PluginCodec {
+    /// Codec for the plugin executable at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Codec` if the plugin cannot be run or its handshake is
+    /// invalid.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let info = handshake(path)?;
+        let fallback = [&info.transfer_syntax_lossless, &info.transfer_syntax_lossy]
+            .into_iter()
+            .flatten()
+            .find_map(|ts| match ts.as_str() {
+                transfer_syntax::EXPLICIT_VR_LITTLE_ENDIAN => Some(CompressionCodec::Uncompressed),
+                ts => codec_for_transfer_syntax(ts),
+            });
+        Ok(Self {
+            path: path.to_path_buf(),
+            info,
+            fallback,
+        })
+    }
+
+    /// The plugin's handshake.
+    pub fn plugin_info(&self) -> &PluginInfo {
+        self.info
+    }
+
+    /// Run `command` with `request` and `input`, returning stdout.
+    fn run(&self, command: &str, mut request: PluginRequest<'_>, input: &[u8]) -> Result<Vec<u8>> {
+        let shm = self.info.transports.contains(&PluginTransport::Shm);
+        let pixel_file = shm.then(SharedFile::new);
+        if let Some(file) = &pixel_file {
+            std::fs::write(&file.0, input)?;
+            request.pixel_file = Some(file.0.clone());
+        }
+        let mut header = serde_json::to_vec(&request)
+            .map_err(|e| MedImgError::Internal(format!("Plugin request: {}", e)))?;
+        header.push(b'\n');
+        let payload = if pixel_file.is_some() { &[][..] } else { input };
+
+        let mut child = Command::new(&self.path)
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| plugin_error(&self.path, format!("cannot start: {}", e)))?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // Write on another thread so a plugin streaming its output before
+        // reading all input cannot deadlock
+        let (written, output) = std::thread::scope(|scope| {
+            let writer = scope.spawn(move || {
+                stdin.write_all(&header)?;
+                stdin.write_all(payload)
+            });
+            let output = child.wait_with_output();
+            (writer.join(), output)
+        });
+        let output = checked(&self.path, command, output?)?;
+        match written {
+            Ok(Ok(())) => {}
+            // The plugin succeeded without reading everything
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_) => return Err(MedImgError::Internal("Plugin input writer panicked".into())),
+        }
+        Ok(output.stdout)
+    }
+}
+
+impl Codec for PluginCodec {
+    fn encode(
+        &self,
+        image: &ImageData,
+        config: &CompressionConfig,
+        progress: Option<&dyn Fn(f64)>,
+    ) -> Result<Vec<u8>> {
+        let image = image.packed();
+        let lossy = config.mode == CompressionMode::Lossy;
+        let request = PluginRequest {
+            width: image.width,
+            height: image.height,
+            bits_per_sample: image.bits_per_sample,
+            samples_per_pixel: image.samples_per_pixel,
+            signed: image.is_signed,
+            photometric: &image.photometric_interpretation,
+            mode: Some(config.mode),
+            target_ratio: config.target_ratio.filter(|_| lossy),
+            target_bpp: config.target_bpp.filter(|_| lossy),
+            near: (config.mode == CompressionMode::NearLossless).then_some(config.near_lossless_error),
+            length: image.pixel_data.len(),
+            pixel_file: None,
+        };
+        let codestream = self.run("encode", request, &image.pixel_data)?;
+        if codestream.is_empty() {
+            return Err(plugin_error(&self.path, "encode produced no output".into()));
+        }
+        if let Some(report) = progress {
+            report(1.0);
+        }
+        Ok(codestream)
+    }
+
+    fn decode(
+        &self,
+        data: &[u8],
+        width: u32,
+        height: u32,
+        bits_per_sample: u16,
+        samples_per_pixel: u16,
+    ) -> Result<ImageData> {
+        if !self.info.decode {
+            let codec = self.fallback.ok_or_else(|| {
+                MedImgError::Codec(format!(
+                    "Encoder plugin {} cannot decode and no built-in codec reads its output",
+                    self.info.name
+                ))
+            })?;
+            return CodecFactory::create(codec).decode(data, width, height, bits_per_sample, samples_per_pixel);
+        }
+        DecodeLimits::current().check_image(width, height, bits_per_sample, samples_per_pixel, 1)?;
+
+        let request = PluginRequest {
+            width,
+            height,
+            bits_per_sample,
+            samples_per_pixel,
+            signed: false,
+            photometric: "",
+            mode: None,
+            target_ratio: None,
+            target_bpp: None,
+            near: None,
+            length: data.len(),
+            pixel_file: None,
+        };
+        let pixels = self.run("decode", request, data)?;
+        let image = ImageData::new(width, height, bits_per_sample, samples_per_pixel, pixels);
+        if image.pixel_data.len() != image.expected_size() {
+            return Err(plugin_error(
+                &self.path,
+                format!(
+                    "decode produced {} bytes, expected {}",
+                    image.pixel_data.len(),
+                    image.expected_size()
+                ),
+            ));
+        }
+        Ok(image)
+    }
+
+    fn info(&self) -> CodecInfo {
+        CodecInfo {
+            name: &self.info.name,
+            version: &self.info.version,
+            supports_lossless: self.info.transfer_syntax_lossless.is_some(),
+            supports_lossy: self.info.transfer_syntax_lossy.is_some(),
+            supports_progressive: false,
+            supports_roi: false,
+            transfer_syntax_lossless: self.info.transfer_syntax_lossless.as_deref(),
+            transfer_syntax_lossy: self.info.transfer_syntax_lossy.as_deref(),
+        }
+    }
+
+    fn capabilities(&self) -> CodecCapabilities {
+        CodecCapabilities {
+            max_bits_per_sample: self.info.max_bits_per_sample,
+            supports_signed: self.info.signed,
+            supports_color: self.info.color,
+            supports_multiframe: true,
+        }
+    }
+}
+
+/// Codec error about the plugin at `path`.
+fn plugin_error(path: &Path, message: String) -> MedImgError {
+    MedImgError::Codec(format!("Encoder plugin '{}' {}", path.display(), message))
+}
+
+/// `output` if the plugin exited successfully, otherwise an error with its
+/// stderr.
+fn checked(path: &Path, command: &str, output: Output) -> Result<Output> {
+    if output.status.success() {
+        return Ok(output);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(plugin_error(
+        path,
+        format!("{} failed ({}): {}", command, output.status, stderr.trim()),
+    ))
+}
+
+/// Run the handshake of the plugin at `path`, once per process.
+fn handshake(path: &Path) -> Result<&'static PluginInfo> {
+    // Leaked so `CodecInfo` can borrow the names; one per executable
+    static PLUGINS: OnceLock<Mutex<HashMap<PathBuf, &'static PluginInfo>>> = OnceLock::new();
+    let mut plugins = PLUGINS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(info) = plugins.get(path) {
+        return Ok(info);
+    }
+
+    let output = Command::new(path)
+        .arg("handshake")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| plugin_error(path, format!("cannot start: {}", e)))?;
+    let output = checked(path, "handshake", output)?;
+    let info: PluginInfo = serde_json::from_slice(&output.stdout)
+        .map_err(|e| plugin_error(path, format!("sent an invalid handshake: {}", e)))?;
+    if info.protocol != PLUGIN_PROTOCOL {
+        return Err(plugin_error(
+            path,
+            format!("speaks protocol {}, expected {}", info.protocol, PLUGIN_PROTOCOL),
+        ));
+    }
+    if info.transfer_syntax_lossless.is_none() && info.transfer_syntax_lossy.is_none() {
+        return Err(plugin_error(path, "declares no transfer syntax".into()));
+    }
+    if info.transports.is_empty() {
+        return Err(plugin_error(path, "declares no pixel transport".into()));
+    }
+    log::debug!("Loaded encoder plugin {} {} from {}", info.name, info.version, path.display());
+
+    let info: &'static PluginInfo = Box::leak(Box::new(info));
+    plugins.insert(path.to_path_buf(), info);
+    Ok(info)
+}
+
+/// Input file of the `shm` transport, removed when dropped.
+struct SharedFile(PathBuf);
+
+impl SharedFile {
+    fn new() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let shm = Path::new("/dev/shm");
+        let dir = if shm.is_dir() { shm.to_path_buf() } else { std::env::temp_dir() };
+        let name = format!(
+            "medimg-plugin-{}-{}.raw",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        );
+        Self(dir.join(name))
+    }
+}
+
+impl Drop for SharedFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::config::CompressionCodec;
+    use crate::dicom::{testing, DicomFile};
+    use crate::pipeline::CompressionPipeline;
+
+    /// Write a shell-script plugin answering the handshake with `info` and
+    /// running `body` for `encode` and `decode`.
+    fn write_plugin(dir: &Path, name: &str, info: &str, body: &str) -> PathBuf {
+        let path = dir.join(name);
+        let script = format!(
+            "#!/bin/sh\ncase \"$1\" in\n  handshake) echo '{}' ;;\n  *) {} ;;\nesac\n",
+            info, body
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_pipeline_round_trip_through_plugin() {
+        let dir = TempDir::new().unwrap();
+        let info = r#"{"protocol":1,"name":"passthrough","version":"0.1","transfer_syntax_lossless":"1.2.840.10008.1.2.1","decode":true}"#;
+        let stdin = write_plugin(dir.path(), "stdin.sh", info, "read -r header; cat");
+        let shm_info = info.replace("\"decode\":true", "\"transports\":[\"shm\"]");
+        let shm = write_plugin(
+            dir.path(),
+            "shm.sh",
+            &shm_info,
+            r#"read -r header; cat "$(echo "$header" | sed 's/.*"pixel_file":"\([^"]*\)".*/\1/')""#,
+        );
+
+        let input = dir.path().join("in.dcm");
+        let pixels = testing::gradient(16, 8);
+        testing::write_grayscale(&input, 16, 8, "CT", &pixels);
+        for (name, plugin) in [("stdin", stdin), ("shm", shm)] {
+            let output = dir.path().join(format!("{}.dcm", name));
+            let config = CompressionConfig {
+                encoder_plugin: Some(plugin),
+                ..CompressionConfig::lossless(CompressionCodec::JpegLs)
+            };
+            // Verification decodes through the plugin or the built-in codec
+            let result = CompressionPipeline::new(config).compress_file_to(&input, &output).unwrap();
+            assert!(result.verified);
+            assert_eq!(result.codec_name, "passthrough");
+
+            let written = DicomFile::open(&output).unwrap();
+            assert_eq!(written.metadata.transfer_syntax, transfer_syntax::EXPLICIT_VR_LITTLE_ENDIAN);
+            assert_eq!(written.decode_image_data().unwrap().pixel_data, pixels);
+        }
+    }
+
+    #[test]
+    fn test_plugin_failures() {
+        let dir = TempDir::new().unwrap();
+        let info = r#"{"protocol":1,"name":"broken","version":"0.1","transfer_syntax_lossy":"1.2.840.10008.1.2.4.91"}"#;
+        let broken = write_plugin(dir.path(), "broken.sh", info, "echo 'license expired' >&2; exit 3");
+        let codec = PluginCodec::open(&broken).unwrap();
+        assert_eq!(codec.transfer_syntax_uid(false), Some(transfer_syntax::JPEG_2000_LOSSY));
+        assert_eq!(codec.fallback, Some(CompressionCodec::Jpeg2000));
+
+        let image = ImageData::new(4, 4, 8, 1, vec![0u8; 16]);
+        let config = CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0);
+        match codec.encode(&image, &config, None) {
+            Err(MedImgError::Codec(message)) => assert!(message.contains("license expired"), "{}", message),
+            other => panic!("expected a codec error, got {:?}", other.map(|data| data.len())),
+        }
+
+        let future = write_plugin(dir.path(), "future.sh", r#"{"protocol":2,"name":"x","version":"1"}"#, "exit 0");
+        assert!(matches!(PluginCodec::open(&future), Err(MedImgError::Codec(_))));
+        assert!(PluginCodec::open(dir.path().join("missing")).is_err());
+    }
+}
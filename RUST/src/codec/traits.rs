@@ -1,6 +1,6 @@
 //! Codec trait definitions.
 
-use crate::config::CompressionConfig;
+use crate::config::{CompressionConfig, DecodeLimits};
 use crate::error::Result;
 use crate::ImageData;
 
@@ -40,15 +40,23 @@ pub struct CodecCapabilities {
 
 /// Trait for image compression/decompression codecs.
 pub trait Codec: Send + Sync {
-    /// Encode image data to compressed format.
+    /// Encode a single-frame image to compressed format.
     ///
     /// # Arguments
-    /// * `image` - The image data to compress
+    /// * `image` - The image data to compress (one frame; see
+    ///   [`encode_frames`](Self::encode_frames) for multi-frame images)
     /// * `config` - Compression configuration
+    /// * `progress` - Optional callback receiving the fraction encoded
+    ///   (0.0 to 1.0) after each tile, strip, or frame
     ///
     /// # Returns
     /// Compressed data as bytes.
-    fn encode(&self, image: &ImageData, config: &CompressionConfig) -> Result<Vec<u8>>;
+    fn encode(
+        &self,
+        image: &ImageData,
+        config: &CompressionConfig,
+        progress: Option<&dyn Fn(f64)>,
+    ) -> Result<Vec<u8>>;
 
     /// Decode compressed data to image.
     ///
@@ -61,6 +69,9 @@ pub trait Codec: Send + Sync {
     ///
     /// # Returns
     /// Decoded image data.
+    ///
+    /// Implementations refuse images over the installed [`DecodeLimits`]
+    /// with `LimitExceeded` before allocating them.
     fn decode(
         &self,
         data: &[u8],
@@ -70,6 +81,59 @@ pub trait Codec: Send + Sync {
         samples_per_pixel: u16,
     ) -> Result<ImageData>;
 
+    /// Encode each frame of an image separately, one codestream per frame
+    /// as DICOM encapsulates them.
+    ///
+    /// `progress` receives the fraction of the whole image encoded.
+    fn encode_frames(
+        &self,
+        image: &ImageData,
+        config: &CompressionConfig,
+        progress: Option<&dyn Fn(f64)>,
+    ) -> Result<Vec<Vec<u8>>> {
+        let frames = image.number_of_frames.max(1);
+        if frames == 1 {
+            return Ok(vec![self.encode(image, config, progress)?]);
+        }
+
+        (0..frames)
+            .map(|index| {
+                let frame = image.frame_data(index)?;
+                let report = |fraction: f64| {
+                    if let Some(report) = progress {
+                        report((index as f64 + fraction) / frames as f64);
+                    }
+                };
+                self.encode(&frame, config, Some(&report))
+            })
+            .collect()
+    }
+
+    /// Decode one codestream per frame into a multi-frame image.
+    ///
+    /// All frames together are held to the installed [`DecodeLimits`].
+    fn decode_frames(
+        &self,
+        frames: &[Vec<u8>],
+        width: u32,
+        height: u32,
+        bits_per_sample: u16,
+        samples_per_pixel: u16,
+    ) -> Result<ImageData> {
+        let limits = DecodeLimits::current();
+        let count = u32::try_from(frames.len()).unwrap_or(u32::MAX);
+        limits.check_image(width, height, bits_per_sample, samples_per_pixel, count)?;
+        let budget = limits.start();
+        let decoded = frames
+            .iter()
+            .map(|frame| {
+                budget.check()?;
+                self.decode(frame, width, height, bits_per_sample, samples_per_pixel)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        ImageData::from_frames(decoded)
+    }
+
     /// Get codec information.
     fn info(&self) -> CodecInfo;
 
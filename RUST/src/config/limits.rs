@@ -0,0 +1,253 @@
+//! Resource limits for decoding untrusted input.
+//!
+//! Every size in a DICOM header or codestream comes from the file: a few
+//! kilobytes can declare a 65535x65535 image with thousands of frames,
+//! split its pixel data into millions of fragments, or describe tiles that
+//! take minutes to decode. The DICOM parser and the codecs check what they
+//! are about to allocate or decode against [`DecodeLimits`] and fail with
+//! [`MedImgError::LimitExceeded`] instead.
+//!
+//! Limits are process-wide: a server installs them once at startup with
+//! [`DecodeLimits::install`], and every decode path reads them with
+//! [`DecodeLimits::current`].
+
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::sync::{PoisonError, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::error::{MedImgError, Result};
+
+/// Limits read by every decode path.
+static INSTALLED: RwLock<DecodeLimits> = RwLock::new(DecodeLimits::DEFAULT);
+
+thread_local! {
+    /// Start and length of the time budget of the decode running on this
+    /// thread.
+    static BUDGET: Cell<Option<(Instant, Duration)>> = const { Cell::new(None) };
+}
+
+/// Bounds on what a decode may allocate and how long it may run.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// DecodeLimits {
+///     max_output_bytes: 512 << 20,
+///     time_budget: Some(Duration::from_secs(10)),
+///     ..DecodeLimits::default()
+/// }
+/// .install();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Maximum width or height in pixels.
+    pub max_dimension: u32,
+    /// Maximum size of decoded pixel data, all frames together. Files
+    /// larger than this are refused before they are read.
+    pub max_output_bytes: u64,
+    /// Maximum number of frames, encapsulated fragments, or codestream
+    /// tiles.
+    pub max_fragments: usize,
+    /// Maximum wall-clock time of one decode (`None` for no limit).
+    pub time_budget: Option<Duration>,
+}
+
+impl DecodeLimits {
+    /// Limits that admit any DICOM image geometry up to 4 GiB of pixels.
+    pub const DEFAULT: Self = Self {
+        max_dimension: 65_535,
+        max_output_bytes: 4 << 30,
+        max_fragments: 1 << 20,
+        time_budget: None,
+    };
+
+    /// No limits.
+    pub const fn unlimited() -> Self {
+        Self {
+            max_dimension: u32::MAX,
+            max_output_bytes: u64::MAX,
+            max_fragments: usize::MAX,
+            time_budget: None,
+        }
+    }
+
+    /// Make these the limits of every later decode in the process.
+    pub fn install(self) {
+        *INSTALLED.write().unwrap_or_else(PoisonError::into_inner) = self;
+    }
+
+    /// The installed limits ([`DEFAULT`](Self::DEFAULT) until
+    /// [`install`](Self::install) is called).
+    pub fn current() -> Self {
+        *INSTALLED.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Check the geometry of an image before decoding it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LimitExceeded` if a dimension is over `max_dimension`, the
+    /// frame count is over `max_fragments`, or the decoded pixels would
+    /// take more than `max_output_bytes`.
+    pub fn check_image(
+        &self,
+        width: u32,
+        height: u32,
+        bits_per_sample: u16,
+        samples_per_pixel: u16,
+        frames: u32,
+    ) -> Result<()> {
+        if width.max(height) > self.max_dimension {
+            return Err(MedImgError::LimitExceeded(format!(
+                "{}x{} image is over the {} pixel dimension limit",
+                width, height, self.max_dimension
+            )));
+        }
+        self.check_fragments(frames as usize, "frames")?;
+        let bytes = u64::from(width)
+            .saturating_mul(u64::from(height))
+            .saturating_mul(u64::from(samples_per_pixel))
+            .saturating_mul(u64::from(bits_per_sample.div_ceil(8)))
+            .saturating_mul(u64::from(frames.max(1)));
+        self.check_output(bytes)
+    }
+
+    /// Check the size of decoded output (or of a file holding it).
+    ///
+    /// # Errors
+    ///
+    /// Returns `LimitExceeded` if `bytes` is over `max_output_bytes`.
+    pub fn check_output(&self, bytes: u64) -> Result<()> {
+        if bytes > self.max_output_bytes {
+            return Err(MedImgError::LimitExceeded(format!(
+                "{} bytes of pixel data is over the {} byte limit",
+                bytes, self.max_output_bytes
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check a count of frames, fragments, or tiles, named by `what`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LimitExceeded` if `count` is over `max_fragments`.
+    pub fn check_fragments(&self, count: usize, what: &str) -> Result<()> {
+        if count > self.max_fragments {
+            return Err(MedImgError::LimitExceeded(format!(
+                "{} {} is over the limit of {}",
+                count, what, self.max_fragments
+            )));
+        }
+        Ok(())
+    }
+
+    /// Start the time budget of a decode on this thread.
+    ///
+    /// A decode started inside another (a codec called by the DICOM
+    /// decoder) shares the outer budget.
+    pub fn start(&self) -> DecodeBudget {
+        let owner = match self.time_budget {
+            Some(budget) if BUDGET.get().is_none() => {
+                BUDGET.set(Some((Instant::now(), budget)));
+                true
+            }
+            _ => false,
+        };
+        DecodeBudget {
+            owner,
+            _thread: PhantomData,
+        }
+    }
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The running time budget of a decode (see [`DecodeLimits::start`]),
+/// ended when the budget that started it is dropped.
+#[must_use = "the budget ends when dropped"]
+pub struct DecodeBudget {
+    owner: bool,
+    // Budgets are per thread
+    _thread: PhantomData<*const ()>,
+}
+
+impl DecodeBudget {
+    /// Check that the decode is still within its time budget.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LimitExceeded` once the budget has run out.
+    pub fn check(&self) -> Result<()> {
+        match BUDGET.get() {
+            Some((started, budget)) if started.elapsed() > budget => Err(MedImgError::LimitExceeded(
+                format!("Decoding took longer than the {:?} time budget", budget),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for DecodeBudget {
+    fn drop(&mut self) {
+        if self.owner {
+            BUDGET.set(None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_image() {
+        let limits = DecodeLimits {
+            max_dimension: 1024,
+            max_output_bytes: 1 << 20,
+            max_fragments: 8,
+            time_budget: None,
+        };
+        assert!(limits.check_image(512, 512, 16, 1, 2).is_ok());
+        assert!(matches!(
+            limits.check_image(2048, 16, 8, 1, 1),
+            Err(MedImgError::LimitExceeded(_))
+        ));
+        // 512x512x2 bytes x 5 frames
+        assert!(limits.check_image(512, 512, 16, 1, 5).is_err());
+        assert!(limits.check_image(1, 1, 8, 1, 9).is_err());
+        assert!(limits.check_fragments(9, "fragments").is_err());
+        // Sizes that overflow saturate instead of wrapping
+        assert!(limits.check_image(1024, 1024, 16, u16::MAX, 8).is_err());
+        assert!(DecodeLimits::unlimited().check_image(u32::MAX, u32::MAX, 16, 3, u32::MAX).is_ok());
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let limits = DecodeLimits {
+            time_budget: Some(Duration::from_millis(20)),
+            ..DecodeLimits::default()
+        };
+        let budget = limits.start();
+        assert!(budget.check().is_ok());
+        // Nested decodes share the outer budget
+        let nested = DecodeLimits {
+            time_budget: Some(Duration::from_secs(60)),
+            ..limits
+        }
+        .start();
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(matches!(nested.check(), Err(MedImgError::LimitExceeded(_))));
+        drop(nested);
+        assert!(budget.check().is_err());
+        drop(budget);
+
+        // Ended with the outer budget
+        assert!(DecodeLimits::DEFAULT.start().check().is_ok());
+    }
+}
@@ -1,7 +1,20 @@
 //! Configuration types for compression settings and modality-specific rules.
 
+use std::collections::HashMap;
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
+use crate::error::MedImgError;
+
+pub mod limits;
+pub mod overrides;
+pub mod regulatory;
+
+pub use limits::{DecodeBudget, DecodeLimits};
+pub use overrides::CompressionOverrides;
+pub use regulatory::RegulatoryProfile;
+
 /// Supported compression codecs.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum CompressionCodec {
@@ -27,7 +40,10 @@ pub enum CompressionMode {
 }
 
 /// Medical imaging modality.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Serialized as the DICOM Modality (0008,0060) code, e.g. `"CT"`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(into = "String", from = "String")]
 pub enum Modality {
     /// Computed Tomography
     CT,
@@ -47,14 +63,33 @@ pub enum Modality {
     PT,
     /// Whole Slide Imaging (Pathology)
     SM,
-    /// Other/Unknown
+    /// X-Ray Angiography
+    XA,
+    /// Radio Fluoroscopy
+    RF,
+    /// Panoramic X-Ray (dental)
+    PX,
+    /// Intra-Oral Radiography (dental)
+    IO,
+    /// Ophthalmic Photography
+    OP,
+    /// Optical Coherence Tomography (ophthalmic)
+    OCT,
+    /// Endoscopy
+    ES,
+    /// External-camera Photography
+    XC,
+    /// Any other code, preserved as written (upper-cased)
+    Custom(String),
+    /// Other/Unknown (`OT` or no modality)
     Other,
 }
 
 impl Modality {
     /// Parse modality from DICOM modality string.
     pub fn from_dicom_string(s: &str) -> Self {
-        match s.trim().to_uppercase().as_str() {
+        let code = s.trim().to_uppercase();
+        match code.as_str() {
             "CT" => Modality::CT,
             "MR" | "MRI" => Modality::MR,
             "CR" => Modality::CR,
@@ -64,13 +99,48 @@ impl Modality {
             "NM" => Modality::NM,
             "PT" | "PET" => Modality::PT,
             "SM" => Modality::SM,
-            _ => Modality::Other,
+            "XA" => Modality::XA,
+            "RF" => Modality::RF,
+            "PX" => Modality::PX,
+            "IO" => Modality::IO,
+            "OP" => Modality::OP,
+            "OCT" => Modality::OCT,
+            "ES" => Modality::ES,
+            "XC" => Modality::XC,
+            "" | "OT" | "OTHER" => Modality::Other,
+            _ => Modality::Custom(code),
         }
     }
 
-    /// Check if modality requires lossless compression (regulatory requirement).
+    /// DICOM modality code (`OT` for [`Modality::Other`]).
+    pub fn code(&self) -> &str {
+        match self {
+            Modality::CT => "CT",
+            Modality::MR => "MR",
+            Modality::CR => "CR",
+            Modality::DX => "DX",
+            Modality::MG => "MG",
+            Modality::US => "US",
+            Modality::NM => "NM",
+            Modality::PT => "PT",
+            Modality::SM => "SM",
+            Modality::XA => "XA",
+            Modality::RF => "RF",
+            Modality::PX => "PX",
+            Modality::IO => "IO",
+            Modality::OP => "OP",
+            Modality::OCT => "OCT",
+            Modality::ES => "ES",
+            Modality::XC => "XC",
+            Modality::Custom(code) => code,
+            Modality::Other => "OT",
+        }
+    }
+
+    /// Check if modality requires lossless compression under the default
+    /// regulatory profile.
     pub fn requires_lossless(&self) -> bool {
-        matches!(self, Modality::MG)
+        RegulatoryProfile::default().requires_lossless(self)
     }
 
     /// Get recommended codec for this modality.
@@ -82,8 +152,26 @@ impl Modality {
     }
 }
 
+impl std::fmt::Display for Modality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl From<String> for Modality {
+    fn from(code: String) -> Self {
+        Modality::from_dicom_string(&code)
+    }
+}
+
+impl From<Modality> for String {
+    fn from(modality: Modality) -> Self {
+        modality.code().to_string()
+    }
+}
+
 /// Quality preset for compression.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub enum QualityPreset {
     /// Maximum quality - lossless
     #[default]
@@ -94,6 +182,15 @@ pub enum QualityPreset {
     Standard,
     /// Lower quality - thumbnails and previews
     Preview,
+    /// Site-defined tier
+    Custom {
+        /// Name of the tier (e.g. `teaching`).
+        label: String,
+        /// Target compression ratio (`None` = lossless).
+        target_ratio: Option<f32>,
+        /// JPEG 2000 quality layers.
+        quality_layers: u32,
+    },
 }
 
 impl QualityPreset {
@@ -104,6 +201,7 @@ impl QualityPreset {
             QualityPreset::HighQuality => Some(10.0),
             QualityPreset::Standard => Some(20.0),
             QualityPreset::Preview => Some(50.0),
+            QualityPreset::Custom { target_ratio, .. } => *target_ratio,
         }
     }
 
@@ -114,6 +212,112 @@ impl QualityPreset {
             QualityPreset::HighQuality => 5,
             QualityPreset::Standard => 3,
             QualityPreset::Preview => 2,
+            QualityPreset::Custom { quality_layers, .. } => *quality_layers,
+        }
+    }
+
+    /// Preset name for reports.
+    pub fn label(&self) -> &str {
+        match self {
+            QualityPreset::Diagnostic => "diagnostic",
+            QualityPreset::HighQuality => "high-quality",
+            QualityPreset::Standard => "standard",
+            QualityPreset::Preview => "preview",
+            QualityPreset::Custom { label, .. } => label,
+        }
+    }
+}
+
+/// JPEG 2000 progression order (COD marker).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum ProgressionOrder {
+    /// Layer-resolution-component-position (quality progressive)
+    #[default]
+    Lrcp,
+    /// Resolution-layer-component-position (resolution progressive)
+    Rlcp,
+    /// Resolution-position-component-layer
+    Rpcl,
+    /// Position-component-resolution-layer (spatially progressive)
+    Pcrl,
+    /// Component-position-resolution-layer
+    Cprl,
+}
+
+impl ProgressionOrder {
+    /// Value of the progression order field in the COD marker.
+    pub fn code(&self) -> u8 {
+        match self {
+            ProgressionOrder::Lrcp => 0,
+            ProgressionOrder::Rlcp => 1,
+            ProgressionOrder::Rpcl => 2,
+            ProgressionOrder::Pcrl => 3,
+            ProgressionOrder::Cprl => 4,
+        }
+    }
+}
+
+/// JPEG 2000 encoder implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum Jpeg2000Encoder {
+    /// OpenJPEG when the `openjpeg` feature is built, the built-in
+    /// encoder otherwise
+    #[default]
+    Auto,
+    /// OpenJPEG only; encoding fails in builds without it
+    OpenJpeg,
+    /// Built-in encoder (codestreams only this crate decodes)
+    Builtin,
+}
+
+impl Jpeg2000Encoder {
+    /// Whether this selection writes standard codestreams in this build,
+    /// that is, encodes with OpenJPEG.
+    pub fn is_conformant(&self) -> bool {
+        cfg!(all(feature = "openjpeg", not(target_arch = "wasm32"))) && *self != Jpeg2000Encoder::Builtin
+    }
+}
+
+/// JPEG-LS coding parameters (LSE marker, ISO 14495-1 C.2.4.1.1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JpegLsThresholds {
+    /// First context quantization threshold.
+    pub t1: u16,
+    /// Second context quantization threshold.
+    pub t2: u16,
+    /// Third context quantization threshold.
+    pub t3: u16,
+    /// Context counter reset interval.
+    pub reset: u16,
+}
+
+impl Default for JpegLsThresholds {
+    /// Default thresholds for 8-bit samples with NEAR = 0.
+    fn default() -> Self {
+        Self {
+            t1: 3,
+            t2: 7,
+            t3: 21,
+            reset: 64,
+        }
+    }
+}
+
+/// Justification recorded with a safety override.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OverrideReason {
+    /// Why the safety checks are bypassed (e.g. a study or ticket reference).
+    pub reason: String,
+    /// Who authorized the override.
+    pub operator: String,
+}
+
+impl OverrideReason {
+    /// Create an override justification.
+    pub fn new(reason: impl Into<String>, operator: impl Into<String>) -> Self {
+        Self {
+            reason: reason.into(),
+            operator: operator.into(),
         }
     }
 }
@@ -123,39 +327,111 @@ impl QualityPreset {
 pub struct CompressionConfig {
     /// Codec to use for compression.
     pub codec: CompressionCodec,
+    /// External encoder run instead of the built-in codec (see
+    /// [`PluginCodec`](crate::codec::PluginCodec)). `codec` still selects
+    /// the parameter rules [`validate`](Self::validate) applies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoder_plugin: Option<PathBuf>,
     /// Compression mode (lossless, lossy, near-lossless).
     pub mode: CompressionMode,
     /// Quality preset.
     pub quality: QualityPreset,
     /// Target compression ratio (for lossy mode).
     pub target_ratio: Option<f32>,
+    /// Target bits per pixel (for lossy mode; alternative to `target_ratio`).
+    #[serde(default)]
+    pub target_bpp: Option<f32>,
     /// JPEG 2000 specific: number of quality layers.
     pub quality_layers: u32,
-    /// JPEG 2000 specific: tile size (0 = no tiling).
+    /// JPEG 2000 specific: tile size (0 = no tiling, otherwise a power of
+    /// two of at least [`MIN_TILE_SIZE`]).
     pub tile_size: u32,
-    /// JPEG-LS specific: near-lossless tolerance (0 = lossless).
-    pub near_lossless_error: u8,
+    /// JPEG 2000 specific: wavelet decomposition levels (0-32).
+    #[serde(default = "default_decomposition_levels")]
+    pub decomposition_levels: u8,
+    /// JPEG 2000 specific: progression order.
+    #[serde(default)]
+    pub progression_order: ProgressionOrder,
+    /// JPEG 2000 specific: encoder implementation.
+    #[serde(default)]
+    pub jpeg2000_encoder: Jpeg2000Encoder,
+    /// JPEG-LS specific: coding parameters (`None` = defaults for the
+    /// sample precision).
+    #[serde(default)]
+    pub jpegls_thresholds: Option<JpegLsThresholds>,
+    /// JPEG-LS specific: near-lossless tolerance in stored sample values
+    /// (0 = lossless).
+    pub near_lossless_error: u16,
+    /// JPEG-LS specific: near-lossless tolerance in rescaled units (e.g. HU
+    /// for CT), converted per file with its Rescale Slope (alternative to
+    /// `near_lossless_error`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub near_lossless_rescaled: Option<f32>,
     /// Preserve original DICOM metadata exactly.
     pub preserve_metadata: bool,
     /// Verify compression by round-trip decode.
     pub verify_compression: bool,
-    /// Override modality safety checks (use with caution).
-    pub override_safety_checks: bool,
+    /// Override modality safety checks (use with caution). The
+    /// justification is recorded with every override.
+    #[serde(default)]
+    pub override_safety_checks: Option<OverrideReason>,
+    /// Regulatory rules (lossless-only modalities and lossy ratio caps).
+    #[serde(default)]
+    pub regulatory_profile: RegulatoryProfile,
+    /// Additional modalities restricted to lossless compression by site
+    /// policy (on top of the regulatory requirements).
+    #[serde(default)]
+    pub lossless_modalities: Vec<Modality>,
+    /// Additional SOP class UIDs restricted to lossless compression by
+    /// site policy, whatever the file's modality.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lossless_sop_classes: Vec<String>,
+    /// Maximum lossy ratio per modality by site policy. The regulatory
+    /// profile's cap still applies where it is lower.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub max_ratios: HashMap<Modality, f32>,
+    /// Settings used instead of this configuration for specific
+    /// modalities (e.g. CT lossless JPEG 2000, US near-lossless JPEG-LS).
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub modality_configs: HashMap<Modality, CompressionConfig>,
+}
+
+/// Largest JPEG-LS NEAR (ITU-T T.87 codes it in one byte).
+pub const MAX_JPEGLS_NEAR: u16 = 255;
+
+/// Smallest JPEG 2000 tile size (one code-block).
+pub const MIN_TILE_SIZE: u32 = 64;
+
+/// Default JPEG 2000 decomposition levels.
+fn default_decomposition_levels() -> u8 {
+    5
 }
 
 impl Default for CompressionConfig {
     fn default() -> Self {
         Self {
             codec: CompressionCodec::Jpeg2000,
+            encoder_plugin: None,
             mode: CompressionMode::Lossless,
             quality: QualityPreset::Diagnostic,
             target_ratio: None,
+            target_bpp: None,
             quality_layers: 1,
             tile_size: 0,
+            decomposition_levels: default_decomposition_levels(),
+            progression_order: ProgressionOrder::Lrcp,
+            jpeg2000_encoder: Jpeg2000Encoder::Auto,
+            jpegls_thresholds: None,
             near_lossless_error: 0,
+            near_lossless_rescaled: None,
             preserve_metadata: true,
             verify_compression: true,
-            override_safety_checks: false,
+            override_safety_checks: None,
+            regulatory_profile: RegulatoryProfile::default(),
+            lossless_modalities: Vec::new(),
+            lossless_sop_classes: Vec::new(),
+            max_ratios: HashMap::new(),
+            modality_configs: HashMap::new(),
         }
     }
 }
@@ -182,24 +458,405 @@ impl CompressionConfig {
         }
     }
 
-    /// Validate configuration against modality constraints.
-    pub fn validate_for_modality(&self, modality: Modality) -> Result<(), String> {
-        if modality.requires_lossless() && self.mode != CompressionMode::Lossless {
-            if self.override_safety_checks {
-                log::warn!(
-                    "Safety check overridden: {:?} typically requires lossless compression",
-                    modality
+    /// Use different settings for specific modalities.
+    ///
+    /// Modalities without an entry use this configuration.
+    pub fn per_modality(mut self, configs: HashMap<Modality, CompressionConfig>) -> Self {
+        self.modality_configs = configs;
+        self
+    }
+
+    /// Resolve the configuration to use for a file of `modality`.
+    ///
+    /// Safety settings (overrides, reason, regulatory profile, site
+    /// lossless modalities and SOP classes, and ratio caps) always come from this
+    /// configuration so a per-modality entry cannot weaken them.
+    pub fn for_modality(&self, modality: &Modality) -> CompressionConfig {
+        match self.modality_configs.get(modality) {
+            Some(config) => CompressionConfig {
+                override_safety_checks: self.override_safety_checks.clone(),
+                regulatory_profile: self.regulatory_profile,
+                lossless_modalities: self.lossless_modalities.clone(),
+                lossless_sop_classes: self.lossless_sop_classes.clone(),
+                max_ratios: self.max_ratios.clone(),
+                modality_configs: HashMap::new(),
+                ..config.clone()
+            },
+            None => CompressionConfig {
+                modality_configs: HashMap::new(),
+                ..self.clone()
+            },
+        }
+    }
+
+    /// Check if a modality must be compressed losslessly under this
+    /// configuration (regulatory requirement or site policy).
+    pub fn requires_lossless(&self, modality: &Modality) -> bool {
+        self.regulatory_profile.requires_lossless(modality)
+            || self.lossless_modalities.contains(modality)
+    }
+
+    /// Check if SOP class `uid` must be compressed losslessly under this
+    /// configuration (regulatory requirement or site policy).
+    pub fn requires_lossless_sop_class(&self, uid: &str) -> bool {
+        self.regulatory_profile.requires_lossless_sop_class(uid)
+            || self.lossless_sop_classes.iter().any(|class| class == uid)
+    }
+
+    /// Maximum lossy ratio for `modality`: the lower of the regulatory
+    /// profile's and the site's caps (`None` if uncapped).
+    pub fn max_ratio(&self, modality: &Modality) -> Option<f32> {
+        let profile = self.regulatory_profile.max_ratio(modality);
+        let site = self.max_ratios.get(modality).copied();
+        match (profile, site) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (cap, None) | (None, cap) => cap,
+        }
+    }
+
+    /// Target ratio of a lossy configuration, falling back to the quality
+    /// preset (`None` for other modes or a bits-per-pixel target).
+    pub fn effective_target_ratio(&self) -> Option<f32> {
+        if self.mode != CompressionMode::Lossy || self.target_bpp.is_some() {
+            return None;
+        }
+        self.target_ratio.or(self.quality.target_ratio())
+    }
+
+    /// Target ratio for an image stored with `bits_per_pixel` bits per
+    /// pixel: `target_ratio`, or the ratio equivalent to `target_bpp`.
+    pub fn target_ratio_for(&self, bits_per_pixel: f32) -> Option<f32> {
+        match self.target_bpp {
+            Some(bpp) => Some(bits_per_pixel / bpp),
+            None => self.target_ratio,
+        }
+    }
+
+    /// Replace a bits-per-pixel target with the equivalent ratio for an
+    /// image stored with `bits_per_pixel` bits per pixel, so ratio caps
+    /// apply to it.
+    pub fn with_bits_per_pixel(mut self, bits_per_pixel: f32) -> Self {
+        if self.target_bpp.is_some() {
+            self.target_ratio = self.target_ratio_for(bits_per_pixel);
+            self.target_bpp = None;
+        }
+        self
+    }
+
+    /// Resolve the near-lossless tolerance for a file with `bits_stored`
+    /// bits per sample and the given Rescale Slope.
+    ///
+    /// A tolerance in rescaled units is converted to stored values
+    /// (rounding down, so the error never exceeds it), and the result is
+    /// checked against half the sample range: larger values would allow
+    /// any reconstruction. For JPEG-LS, a converted tolerance must also
+    /// fit in NEAR.
+    pub fn resolve_near(mut self, bits_stored: u16, rescale_slope: f64) -> Result<Self, String> {
+        if self.mode != CompressionMode::NearLossless {
+            return Ok(self);
+        }
+
+        if let Some(tolerance) = self.near_lossless_rescaled.take() {
+            let slope = rescale_slope.abs();
+            let near = if slope > 0.0 { (f64::from(tolerance) / slope).floor() } else { 0.0 };
+            if near < 1.0 {
+                return Err(format!(
+                    "Near-lossless tolerance {} (rescaled units) is below one stored value \
+                     (Rescale Slope {}); use lossless mode",
+                    tolerance, rescale_slope
+                ));
+            }
+            if self.codec == CompressionCodec::JpegLs && near > f64::from(MAX_JPEGLS_NEAR) {
+                return Err(format!(
+                    "Near-lossless tolerance {} (rescaled units) is {} stored values (Rescale Slope {}); \
+                     JPEG-LS NEAR must be at most {}",
+                    tolerance, near, rescale_slope, MAX_JPEGLS_NEAR
+                ));
+            }
+            self.near_lossless_error = near.min(f64::from(u16::MAX)) as u16;
+        }
+
+        let max = ((1u32 << bits_stored.clamp(1, 16)) - 1) / 2;
+        if u32::from(self.near_lossless_error) > max {
+            return Err(format!(
+                "near_lossless_error={} exceeds {} (half the range of {}-bit samples)",
+                self.near_lossless_error, max, bits_stored
+            ));
+        }
+        Ok(self)
+    }
+
+    /// Check the configuration for contradictory or out-of-range settings.
+    ///
+    /// Returns every problem found (including those in per-modality
+    /// entries, prefixed with the modality) rather than stopping at the
+    /// first one.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        match (self.codec, self.mode) {
+            (CompressionCodec::Jpeg2000, CompressionMode::NearLossless) => problems.push(
+                "Near-lossless mode requires the JPEG-LS codec; use lossy mode for JPEG 2000"
+                    .to_string(),
+            ),
+            (CompressionCodec::Uncompressed, mode) if mode != CompressionMode::Lossless => {
+                problems.push(format!(
+                    "{:?} mode is not available without a codec; use lossless mode",
+                    mode
+                ))
+            }
+            _ => {}
+        }
+
+        if self.near_lossless_error > 0 && self.mode != CompressionMode::NearLossless {
+            problems.push(format!(
+                "near_lossless_error={} only applies to near-lossless mode (mode is {:?})",
+                self.near_lossless_error, self.mode
+            ));
+        }
+        if let Some(tolerance) = self.near_lossless_rescaled {
+            if self.near_lossless_error > 0 {
+                problems.push(
+                    "near_lossless_error and near_lossless_rescaled are mutually exclusive; \
+                     set only one"
+                        .to_string(),
                 );
-            } else {
+            }
+            if self.mode != CompressionMode::NearLossless {
+                problems.push(format!(
+                    "near_lossless_rescaled={} only applies to near-lossless mode (mode is {:?})",
+                    tolerance, self.mode
+                ));
+            } else if !(tolerance.is_finite() && tolerance > 0.0) {
+                problems.push(format!(
+                    "near_lossless_rescaled must be greater than 0, got {}",
+                    tolerance
+                ));
+            }
+        } else if self.mode == CompressionMode::NearLossless && self.near_lossless_error == 0 {
+            problems.push(
+                "Near-lossless mode requires near_lossless_error > 0 (0 is lossless)".to_string(),
+            );
+        }
+        if self.codec == CompressionCodec::JpegLs && self.near_lossless_error > MAX_JPEGLS_NEAR {
+            problems.push(format!(
+                "JPEG-LS NEAR must be at most {}, got {}",
+                MAX_JPEGLS_NEAR, self.near_lossless_error
+            ));
+        }
+
+        if let Some(ratio) = self.target_ratio {
+            if self.mode != CompressionMode::Lossy {
+                problems.push(format!(
+                    "target_ratio={} only applies to lossy mode (mode is {:?})",
+                    ratio, self.mode
+                ));
+            } else if !(ratio.is_finite() && ratio > 1.0) {
+                problems.push(format!("target_ratio must be greater than 1, got {}", ratio));
+            }
+        }
+
+        if let Some(bpp) = self.target_bpp {
+            if self.target_ratio.is_some() {
+                problems.push(
+                    "target_ratio and target_bpp are mutually exclusive; set only one".to_string(),
+                );
+            }
+            if self.mode != CompressionMode::Lossy {
+                problems.push(format!(
+                    "target_bpp={} only applies to lossy mode (mode is {:?})",
+                    bpp, self.mode
+                ));
+            } else if !(bpp.is_finite() && bpp > 0.0) {
+                problems.push(format!("target_bpp must be greater than 0, got {}", bpp));
+            }
+        }
+
+        if self.quality_layers == 0 {
+            problems.push("quality_layers must be at least 1".to_string());
+        }
+
+        if let Some(justification) = &self.override_safety_checks {
+            if justification.reason.trim().is_empty() {
+                problems.push("override_safety_checks requires a non-empty reason".to_string());
+            }
+            if justification.operator.trim().is_empty() {
+                problems.push("override_safety_checks requires a non-empty operator".to_string());
+            }
+        }
+
+        let mut caps: Vec<_> = self.max_ratios.iter().collect();
+        caps.sort_by(|a, b| a.0.code().cmp(b.0.code()));
+        for (modality, cap) in caps {
+            if !(cap.is_finite() && *cap >= 1.0) {
+                problems.push(format!(
+                    "max_ratios: cap for {} must be at least 1, got {}",
+                    modality, cap
+                ));
+            }
+        }
+
+        if let Err(problem) = self.validate_codec_options() {
+            problems.push(problem);
+        }
+
+        let mut modalities: Vec<_> = self.modality_configs.keys().collect();
+        modalities.sort_by(|a, b| a.code().cmp(b.code()));
+        for modality in modalities {
+            if let Err(nested) = self.modality_configs[modality].validate() {
+                problems.extend(
+                    nested
+                        .into_iter()
+                        .map(|problem| format!("{}: {}", modality, problem)),
+                );
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Validate codec tuning parameters.
+    pub fn validate_codec_options(&self) -> Result<(), String> {
+        if self.tile_size != 0 {
+            if self.codec != CompressionCodec::Jpeg2000 {
                 return Err(format!(
-                    "Modality {:?} requires lossless compression (FDA/ACR requirement). \
-                     Set override_safety_checks=true to bypass.",
-                    modality
+                    "tile_size={} only applies to JPEG 2000 (codec is {:?})",
+                    self.tile_size, self.codec
                 ));
             }
+            if !self.tile_size.is_power_of_two() || self.tile_size < MIN_TILE_SIZE {
+                return Err(format!(
+                    "JPEG 2000 tile size must be 0 (single tile) or a power of two of at least {}, got {}",
+                    MIN_TILE_SIZE, self.tile_size
+                ));
+            }
+        }
+
+        if self.jpeg2000_encoder == Jpeg2000Encoder::OpenJpeg && !cfg!(feature = "openjpeg") {
+            return Err("jpeg2000_encoder=OpenJpeg requires a build with the openjpeg feature".to_string());
+        }
+
+        if self.decomposition_levels > 32 {
+            return Err(format!(
+                "Decomposition levels must be at most 32, got {}",
+                self.decomposition_levels
+            ));
         }
+
+        if let Some(t) = &self.jpegls_thresholds {
+            let near = self.near_lossless_error;
+            if !(near < t.t1 && t.t1 <= t.t2 && t.t2 <= t.t3) {
+                return Err(format!(
+                    "JPEG-LS thresholds must satisfy NEAR < T1 <= T2 <= T3 \
+                     (NEAR={}, T1={}, T2={}, T3={})",
+                    near, t.t1, t.t2, t.t3
+                ));
+            }
+            if t.reset < 3 {
+                return Err(format!("JPEG-LS RESET must be at least 3, got {}", t.reset));
+            }
+        }
+
         Ok(())
     }
+
+    /// Validate configuration against modality constraints: lossless-only
+    /// modalities and lossy ratio caps (see [`max_ratio`](Self::max_ratio)).
+    ///
+    /// Returns a warning describing the override (including the recorded
+    /// reason) when safety checks are overridden for this modality.
+    ///
+    /// # Errors
+    ///
+    /// [`MedImgError::PolicyViolation`] if the modality requires lossless
+    /// compression, [`MedImgError::CompressionConstraint`] if the target
+    /// ratio exceeds its cap.
+    pub fn validate_for_modality(
+        &self,
+        modality: &Modality,
+    ) -> crate::error::Result<Option<String>> {
+        self.validate_for_image(modality, None)
+    }
+
+    /// Validate the configuration against a file's modality and SOP class.
+    ///
+    /// Like [`validate_for_modality`](Self::validate_for_modality), but a
+    /// lossless-only SOP class (e.g. digital mammography) is rejected even
+    /// when the Modality tag is missing or permits lossy compression.
+    pub fn validate_for_image(
+        &self,
+        modality: &Modality,
+        sop_class_uid: Option<&str>,
+    ) -> crate::error::Result<Option<String>> {
+        /// Error constructor and message for a violated rule.
+        type Violation = (fn(String) -> MedImgError, String);
+
+        let lossless_sop_class =
+            sop_class_uid.filter(|uid| self.requires_lossless_sop_class(uid));
+
+        let violation: Option<Violation> =
+            if self.mode == CompressionMode::Lossless {
+                None
+            } else if let Some(uid) = lossless_sop_class {
+                let source = if self.regulatory_profile.requires_lossless_sop_class(uid) {
+                    format!("{} profile", self.regulatory_profile)
+                } else {
+                    "site policy".to_string()
+                };
+                let class = sop_class::name(uid).map_or(uid.to_string(), |name| format!("{} ({})", name, uid));
+                Some((
+                    MedImgError::PolicyViolation,
+                    format!("SOP class {} requires lossless compression ({})", class, source),
+                ))
+            } else if self.requires_lossless(modality) {
+                let source = if self.regulatory_profile.requires_lossless(modality) {
+                    format!("{} profile", self.regulatory_profile)
+                } else {
+                    "site policy".to_string()
+                };
+                Some((
+                    MedImgError::PolicyViolation,
+                    format!("Modality {} requires lossless compression ({})", modality, source),
+                ))
+            } else {
+                match (self.effective_target_ratio(), self.max_ratio(modality)) {
+                    (Some(ratio), Some(max)) if ratio > max => {
+                        let source = if self.regulatory_profile.max_ratio(modality) == Some(max) {
+                            format!("{} profile", self.regulatory_profile)
+                        } else {
+                            "site policy".to_string()
+                        };
+                        Some((
+                            MedImgError::CompressionConstraint,
+                            format!(
+                                "Modality {} allows at most {}:1 lossy compression ({}), got {}:1",
+                                modality, max, source, ratio
+                            ),
+                        ))
+                    }
+                    _ => None,
+                }
+            };
+
+        let Some((error, message)) = violation else {
+            return Ok(None);
+        };
+        let Some(justification) = &self.override_safety_checks else {
+            return Err(error(format!(
+                "{}. Set override_safety_checks with a reason and operator to bypass.",
+                message
+            )));
+        };
+
+        Ok(Some(format!(
+            "Safety check overridden: {}; reason: {}; operator: {}",
+            message, justification.reason, justification.operator
+        )))
+    }
 }
 
 /// Transfer syntax UIDs for DICOM.
@@ -212,8 +869,49 @@ pub mod transfer_syntax {
     pub const JPEG_LS_LOSSLESS: &str = "1.2.840.10008.1.2.4.80";
     /// JPEG-LS Near-Lossless
     pub const JPEG_LS_NEAR_LOSSLESS: &str = "1.2.840.10008.1.2.4.81";
+    /// MPEG2 Main Profile / Main Level (video, passed through)
+    pub const MPEG2_MAIN_PROFILE_MAIN_LEVEL: &str = "1.2.840.10008.1.2.4.100";
+    /// MPEG-4 AVC/H.264 High Profile / Level 4.1 (video, passed through)
+    pub const MPEG4_AVC_H264_HIGH_PROFILE: &str = "1.2.840.10008.1.2.4.102";
     /// Explicit VR Little Endian (uncompressed)
     pub const EXPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2.1";
     /// Implicit VR Little Endian (uncompressed)
     pub const IMPLICIT_VR_LITTLE_ENDIAN: &str = "1.2.840.10008.1.2";
 }
+
+/// SOP class UIDs for DICOM.
+pub mod sop_class {
+    /// CT Image Storage
+    pub const CT_IMAGE: &str = "1.2.840.10008.5.1.4.1.1.2";
+    /// Digital Mammography X-Ray Image Storage - For Presentation
+    pub const DIGITAL_MAMMOGRAPHY_FOR_PRESENTATION: &str = "1.2.840.10008.5.1.4.1.1.1.2";
+    /// Digital Mammography X-Ray Image Storage - For Processing
+    pub const DIGITAL_MAMMOGRAPHY_FOR_PROCESSING: &str = "1.2.840.10008.5.1.4.1.1.1.2.1";
+    /// Breast Tomosynthesis Image Storage
+    pub const BREAST_TOMOSYNTHESIS: &str = "1.2.840.10008.5.1.4.1.1.13.1.3";
+    /// Breast Projection X-Ray Image Storage - For Presentation
+    pub const BREAST_PROJECTION_FOR_PRESENTATION: &str = "1.2.840.10008.5.1.4.1.1.13.1.4";
+    /// Breast Projection X-Ray Image Storage - For Processing
+    pub const BREAST_PROJECTION_FOR_PROCESSING: &str = "1.2.840.10008.5.1.4.1.1.13.1.5";
+
+    /// Name of a SOP class listed here (`None` for other UIDs).
+    pub fn name(uid: &str) -> Option<&'static str> {
+        Some(match uid {
+            CT_IMAGE => "CT Image Storage",
+            DIGITAL_MAMMOGRAPHY_FOR_PRESENTATION => {
+                "Digital Mammography X-Ray Image Storage - For Presentation"
+            }
+            DIGITAL_MAMMOGRAPHY_FOR_PROCESSING => {
+                "Digital Mammography X-Ray Image Storage - For Processing"
+            }
+            BREAST_TOMOSYNTHESIS => "Breast Tomosynthesis Image Storage",
+            BREAST_PROJECTION_FOR_PRESENTATION => {
+                "Breast Projection X-Ray Image Storage - For Presentation"
+            }
+            BREAST_PROJECTION_FOR_PROCESSING => {
+                "Breast Projection X-Ray Image Storage - For Processing"
+            }
+            _ => return None,
+        })
+    }
+}
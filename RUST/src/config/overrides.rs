@@ -0,0 +1,208 @@
+//! Layered configuration: partial settings merged over a base
+//! configuration.
+//!
+//! Callers build a [`CompressionConfig`] by merging layers from lowest to
+//! highest precedence (defaults < config file < profile < command line)
+//! instead of re-deriving each field by hand. Per-modality settings
+//! ([`CompressionConfig::per_modality`]) are resolved last, per file.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    CompressionCodec, CompressionConfig, CompressionMode, Jpeg2000Encoder, JpegLsThresholds,
+    Modality, OverrideReason, ProgressionOrder, QualityPreset, RegulatoryProfile,
+};
+
+/// Settings a layer overrides; `None` keeps the lower layer's value.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionOverrides {
+    /// Codec to use for compression.
+    pub codec: Option<CompressionCodec>,
+    /// External encoder run instead of the built-in codec.
+    pub encoder_plugin: Option<PathBuf>,
+    /// Compression mode.
+    pub mode: Option<CompressionMode>,
+    /// Quality preset (also sets `quality_layers` unless given).
+    pub quality: Option<QualityPreset>,
+    /// Target compression ratio (replaces a lower layer's `target_bpp`).
+    pub target_ratio: Option<f32>,
+    /// Target bits per pixel (replaces a lower layer's `target_ratio`).
+    pub target_bpp: Option<f32>,
+    /// JPEG 2000 quality layers.
+    pub quality_layers: Option<u32>,
+    /// JPEG 2000 tile size.
+    pub tile_size: Option<u32>,
+    /// JPEG 2000 wavelet decomposition levels.
+    pub decomposition_levels: Option<u8>,
+    /// JPEG 2000 progression order.
+    pub progression_order: Option<ProgressionOrder>,
+    /// JPEG 2000 encoder implementation.
+    pub jpeg2000_encoder: Option<Jpeg2000Encoder>,
+    /// JPEG-LS coding parameters.
+    pub jpegls_thresholds: Option<JpegLsThresholds>,
+    /// JPEG-LS near-lossless tolerance (replaces a lower layer's
+    /// `near_lossless_rescaled`).
+    pub near_lossless_error: Option<u16>,
+    /// JPEG-LS near-lossless tolerance in rescaled units (replaces a lower
+    /// layer's `near_lossless_error`).
+    pub near_lossless_rescaled: Option<f32>,
+    /// Preserve original DICOM metadata exactly.
+    pub preserve_metadata: Option<bool>,
+    /// Verify compression by round-trip decode.
+    pub verify_compression: Option<bool>,
+    /// Override modality safety checks with this justification. A lower
+    /// layer's override cannot be cleared by a higher one; callers that must
+    /// refuse overrides reset the merged field.
+    pub override_safety_checks: Option<OverrideReason>,
+    /// Regulatory rules.
+    pub regulatory_profile: Option<RegulatoryProfile>,
+    /// Modalities restricted to lossless compression by site policy.
+    pub lossless_modalities: Option<Vec<Modality>>,
+    /// SOP class UIDs restricted to lossless compression by site policy.
+    pub lossless_sop_classes: Option<Vec<String>>,
+    /// Maximum lossy ratio per modality by site policy.
+    pub max_ratios: Option<HashMap<Modality, f32>>,
+}
+
+impl CompressionConfig {
+    /// Layer `overrides` over this configuration.
+    ///
+    /// Every value set in `overrides` replaces this configuration's, with
+    /// three rules for settings that depend on each other:
+    ///
+    /// - A quality preset also sets its quality layers.
+    /// - `target_ratio` and `target_bpp` are alternatives, as are
+    ///   `near_lossless_error` and `near_lossless_rescaled`; setting one
+    ///   clears the other.
+    /// - Ratio, bits-per-pixel, and NEAR values from lower layers are
+    ///   dropped when the merged mode does not use them. Values set in
+    ///   `overrides` itself are kept so [`validate`](Self::validate)
+    ///   reports them.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let config = CompressionConfig::default()
+    ///     .merge(&file_overrides)
+    ///     .merge(&CompressionOverrides { mode: Some(CompressionMode::Lossy), ..Default::default() });
+    /// ```
+    pub fn merge(mut self, overrides: &CompressionOverrides) -> Self {
+        let o = overrides.clone();
+
+        self.codec = o.codec.unwrap_or(self.codec);
+        self.encoder_plugin = o.encoder_plugin.or(self.encoder_plugin);
+        self.mode = o.mode.unwrap_or(self.mode);
+        if let Some(quality) = o.quality {
+            self.quality_layers = quality.quality_layers();
+            self.quality = quality;
+        }
+        if o.target_ratio.is_some() || o.target_bpp.is_some() {
+            self.target_ratio = o.target_ratio;
+            self.target_bpp = o.target_bpp;
+        } else if self.mode != CompressionMode::Lossy {
+            self.target_ratio = None;
+            self.target_bpp = None;
+        }
+        self.quality_layers = o.quality_layers.unwrap_or(self.quality_layers);
+        self.tile_size = o.tile_size.unwrap_or(self.tile_size);
+        self.decomposition_levels = o.decomposition_levels.unwrap_or(self.decomposition_levels);
+        self.progression_order = o.progression_order.unwrap_or(self.progression_order);
+        self.jpeg2000_encoder = o.jpeg2000_encoder.unwrap_or(self.jpeg2000_encoder);
+        self.jpegls_thresholds = o.jpegls_thresholds.or(self.jpegls_thresholds);
+        if o.near_lossless_error.is_some() || o.near_lossless_rescaled.is_some() {
+            self.near_lossless_error = o.near_lossless_error.unwrap_or(0);
+            self.near_lossless_rescaled = o.near_lossless_rescaled;
+        } else if self.mode != CompressionMode::NearLossless {
+            self.near_lossless_error = 0;
+            self.near_lossless_rescaled = None;
+        }
+        self.preserve_metadata = o.preserve_metadata.unwrap_or(self.preserve_metadata);
+        self.verify_compression = o.verify_compression.unwrap_or(self.verify_compression);
+        self.override_safety_checks = o.override_safety_checks.or(self.override_safety_checks);
+        self.regulatory_profile = o.regulatory_profile.unwrap_or(self.regulatory_profile);
+        self.lossless_modalities = o.lossless_modalities.unwrap_or(self.lossless_modalities);
+        self.lossless_sop_classes = o.lossless_sop_classes.unwrap_or(self.lossless_sop_classes);
+        self.max_ratios = o.max_ratios.unwrap_or(self.max_ratios);
+        self
+    }
+
+    /// Fill in what the mode needs but no layer set: the quality preset's
+    /// ratio for lossy mode and NEAR=2 for near-lossless mode.
+    pub fn with_mode_defaults(mut self) -> Self {
+        match self.mode {
+            CompressionMode::Lossy if self.target_ratio.is_none() && self.target_bpp.is_none() => {
+                self.target_ratio = self.quality.target_ratio();
+            }
+            CompressionMode::NearLossless
+                if self.near_lossless_error == 0 && self.near_lossless_rescaled.is_none() =>
+            {
+                self.near_lossless_error = 2;
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_layers() {
+        let file = CompressionOverrides {
+            codec: Some(CompressionCodec::JpegLs),
+            quality: Some(QualityPreset::Standard),
+            target_ratio: Some(15.0),
+            near_lossless_error: Some(3),
+            ..Default::default()
+        };
+        let base = CompressionConfig::default().merge(&file);
+        assert_eq!(base.codec, CompressionCodec::JpegLs);
+        assert_eq!(base.quality_layers, 3);
+        // Kept at the layer that set them, even though lossless ignores them
+        assert_eq!((base.target_ratio, base.near_lossless_error), (Some(15.0), 3));
+
+        // A later layer's mode drops the lower layers' values it does not use
+        let lossy = CompressionOverrides {
+            mode: Some(CompressionMode::Lossy),
+            ..Default::default()
+        };
+        let config = base.clone().merge(&lossy);
+        assert_eq!((config.target_ratio, config.near_lossless_error), (Some(15.0), 0));
+        assert_eq!(config.codec, CompressionCodec::JpegLs);
+
+        let lossless = base.merge(&CompressionOverrides::default());
+        assert_eq!((lossless.target_ratio, lossless.near_lossless_error), (None, 0));
+
+        // Ratio and bits per pixel replace each other
+        let bpp = CompressionOverrides {
+            target_bpp: Some(1.5),
+            ..Default::default()
+        };
+        let config = config.merge(&bpp);
+        assert_eq!((config.target_ratio, config.target_bpp), (None, Some(1.5)));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_mode_defaults() {
+        let lossy = CompressionConfig {
+            mode: CompressionMode::Lossy,
+            quality: QualityPreset::Standard,
+            ..Default::default()
+        };
+        assert_eq!(lossy.with_mode_defaults().target_ratio, Some(20.0));
+
+        let near = CompressionConfig {
+            mode: CompressionMode::NearLossless,
+            codec: CompressionCodec::JpegLs,
+            ..Default::default()
+        };
+        assert_eq!(near.with_mode_defaults().near_lossless_error, 2);
+    }
+}
@@ -0,0 +1,136 @@
+//! Regulatory profiles: lossless-only modalities and maximum lossy ratios
+//! from published guidance, selectable per site.
+//!
+//! The ratio caps are conservative readings of each guideline's
+//! recommendations for diagnostic interpretation. Guidelines are revised
+//! and qualify their figures by body region and codec, so sites should
+//! check the current text before relying on a profile. Mammography is
+//! lossless-only in every profile, both by modality and by SOP class (so
+//! mammograms with a missing or generic Modality tag are still caught).
+
+use serde::{Deserialize, Serialize};
+
+use super::{sop_class, Modality};
+
+/// Selectable set of regulatory rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RegulatoryProfile {
+    /// United States: FDA (MQSA) requires lossless mammography; no
+    /// published ratio limits for other modalities.
+    #[default]
+    FdaUs,
+    /// United Kingdom: Royal College of Radiologists guidance on lossy
+    /// compression for clinical interpretation (2008).
+    RcrUk,
+    /// Europe: European Society of Radiology position on irreversible
+    /// compression (2011), based on the German consensus ratios.
+    Esr,
+}
+
+/// Lossless-only modalities shared by every profile.
+const LOSSLESS_ONLY: &[Modality] = &[Modality::MG];
+
+/// Lossless-only SOP classes shared by every profile.
+const LOSSLESS_ONLY_SOP_CLASSES: &[&str] = &[
+    sop_class::DIGITAL_MAMMOGRAPHY_FOR_PRESENTATION,
+    sop_class::DIGITAL_MAMMOGRAPHY_FOR_PROCESSING,
+    sop_class::BREAST_TOMOSYNTHESIS,
+    sop_class::BREAST_PROJECTION_FOR_PRESENTATION,
+    sop_class::BREAST_PROJECTION_FOR_PROCESSING,
+];
+
+/// RCR-UK maximum lossy ratios.
+const RCR_UK_MAX_RATIOS: &[(Modality, f32)] = &[
+    (Modality::CT, 8.0),
+    (Modality::MR, 24.0),
+    (Modality::CR, 20.0),
+    (Modality::DX, 20.0),
+    (Modality::US, 12.0),
+    (Modality::NM, 11.0),
+];
+
+/// ESR maximum lossy ratios.
+const ESR_MAX_RATIOS: &[(Modality, f32)] = &[
+    (Modality::CT, 8.0),
+    (Modality::MR, 7.0),
+    (Modality::CR, 10.0),
+    (Modality::DX, 10.0),
+    (Modality::XA, 6.0),
+    (Modality::RF, 6.0),
+];
+
+impl RegulatoryProfile {
+    /// Display name (e.g. `FDA-US`).
+    pub fn name(&self) -> &'static str {
+        match self {
+            RegulatoryProfile::FdaUs => "FDA-US",
+            RegulatoryProfile::RcrUk => "RCR-UK",
+            RegulatoryProfile::Esr => "ESR",
+        }
+    }
+
+    /// Modalities that must be compressed losslessly.
+    pub fn lossless_modalities(&self) -> &'static [Modality] {
+        LOSSLESS_ONLY
+    }
+
+    /// SOP classes that must be compressed losslessly, whatever the
+    /// Modality tag says.
+    pub fn lossless_sop_classes(&self) -> &'static [&'static str] {
+        LOSSLESS_ONLY_SOP_CLASSES
+    }
+
+    /// Maximum lossy ratio per modality.
+    pub fn max_ratios(&self) -> &'static [(Modality, f32)] {
+        match self {
+            RegulatoryProfile::FdaUs => &[],
+            RegulatoryProfile::RcrUk => RCR_UK_MAX_RATIOS,
+            RegulatoryProfile::Esr => ESR_MAX_RATIOS,
+        }
+    }
+
+    /// Check if `modality` must be compressed losslessly.
+    pub fn requires_lossless(&self, modality: &Modality) -> bool {
+        self.lossless_modalities().contains(modality)
+    }
+
+    /// Check if SOP class `uid` must be compressed losslessly.
+    pub fn requires_lossless_sop_class(&self, uid: &str) -> bool {
+        self.lossless_sop_classes().contains(&uid)
+    }
+
+    /// Maximum lossy ratio for `modality` (`None` if uncapped).
+    pub fn max_ratio(&self, modality: &Modality) -> Option<f32> {
+        self.max_ratios()
+            .iter()
+            .find(|(capped, _)| capped == modality)
+            .map(|(_, ratio)| *ratio)
+    }
+}
+
+impl std::fmt::Display for RegulatoryProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_rules() {
+        for profile in [RegulatoryProfile::FdaUs, RegulatoryProfile::RcrUk, RegulatoryProfile::Esr] {
+            assert!(profile.requires_lossless(&Modality::MG), "{}", profile);
+            assert!(!profile.requires_lossless(&Modality::CT), "{}", profile);
+            assert_eq!(profile.max_ratio(&Modality::Custom("IVUS".into())), None);
+            assert!(profile.requires_lossless_sop_class(sop_class::DIGITAL_MAMMOGRAPHY_FOR_PRESENTATION));
+            assert!(!profile.requires_lossless_sop_class(sop_class::CT_IMAGE));
+        }
+
+        assert_eq!(RegulatoryProfile::FdaUs.max_ratio(&Modality::CT), None);
+        assert_eq!(RegulatoryProfile::Esr.max_ratio(&Modality::MR), Some(7.0));
+        assert_eq!(RegulatoryProfile::RcrUk.max_ratio(&Modality::DX), Some(20.0));
+    }
+}
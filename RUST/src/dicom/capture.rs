@@ -0,0 +1,326 @@
+//! Secondary Capture datasets for images that did not come from a DICOM
+//! modality (research volumes, scans, screenshots).
+
+use dicom::core::{DataElement, PrimitiveValue, VR};
+use dicom::dictionary_std::tags;
+use dicom::object::{FileMetaTableBuilder, InMemDicomObject};
+
+use super::{utils, DicomFile};
+use crate::config::transfer_syntax;
+use crate::error::{MedImgError, Result};
+use crate::pixel::Photometric;
+use crate::ImageData;
+
+/// Secondary Capture Image Storage.
+pub const SECONDARY_CAPTURE: &str = "1.2.840.10008.5.1.4.1.1.7";
+
+/// Implementation Class UID written to the File Meta Information of
+/// datasets created by this crate.
+const IMPLEMENTATION_CLASS_UID: &str = "2.25.161207146563915836375328346520178203711";
+
+/// Builds Secondary Capture datasets, one image per instance, sharing one
+/// patient, study, and series.
+#[derive(Debug, Clone)]
+pub struct SecondaryCapture {
+    patient_id: String,
+    patient_name: String,
+    patient_birth_date: String,
+    patient_sex: String,
+    accession_number: String,
+    study_description: Option<String>,
+    study_uid: String,
+    series_uid: String,
+    modality: String,
+    conversion_type: String,
+    series_number: u32,
+    series_description: Option<String>,
+    pixel_spacing: Option<(f64, f64)>,
+    rescale: Option<(f64, f64)>,
+}
+
+impl Default for SecondaryCapture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SecondaryCapture {
+    /// A new study and series of `OT` (other) images converted at a
+    /// workstation (`WSD`), with no patient identifiers.
+    pub fn new() -> Self {
+        Self {
+            patient_id: String::new(),
+            patient_name: String::new(),
+            patient_birth_date: String::new(),
+            patient_sex: String::new(),
+            accession_number: String::new(),
+            study_description: None,
+            study_uid: utils::generate_uid(),
+            series_uid: utils::generate_uid(),
+            modality: "OT".into(),
+            conversion_type: "WSD".into(),
+            series_number: 1,
+            series_description: None,
+            pixel_spacing: None,
+            rescale: None,
+        }
+    }
+
+    /// Set the Patient ID and Patient's Name (`FAMILY^GIVEN`).
+    pub fn patient(mut self, id: impl Into<String>, name: impl Into<String>) -> Self {
+        self.patient_id = id.into();
+        self.patient_name = name.into();
+        self
+    }
+
+    /// Set the Patient's Birth Date (`YYYYMMDD`).
+    pub fn patient_birth_date(mut self, date: impl Into<String>) -> Self {
+        self.patient_birth_date = date.into();
+        self
+    }
+
+    /// Set the Patient's Sex (`M`, `F`, or `O`).
+    pub fn patient_sex(mut self, sex: impl Into<String>) -> Self {
+        self.patient_sex = sex.into();
+        self
+    }
+
+    /// Set the Accession Number of the order the images belong to.
+    pub fn accession_number(mut self, number: impl Into<String>) -> Self {
+        self.accession_number = number.into();
+        self
+    }
+
+    /// Set the Study Description.
+    pub fn study_description(mut self, description: impl Into<String>) -> Self {
+        self.study_description = Some(description.into());
+        self
+    }
+
+    /// Add the images to an existing study.
+    pub fn study_uid(mut self, uid: impl Into<String>) -> Self {
+        self.study_uid = uid.into();
+        self
+    }
+
+    /// Add the images to an existing series.
+    pub fn series_uid(mut self, uid: impl Into<String>) -> Self {
+        self.series_uid = uid.into();
+        self
+    }
+
+    /// Set the Modality (e.g. `MR` for a converted MR volume).
+    pub fn modality(mut self, modality: impl Into<String>) -> Self {
+        self.modality = modality.into();
+        self
+    }
+
+    /// Set the Conversion Type (e.g. `SD` for scanned documents).
+    pub fn conversion_type(mut self, conversion_type: impl Into<String>) -> Self {
+        self.conversion_type = conversion_type.into();
+        self
+    }
+
+    /// Set the Series Number and Series Description.
+    pub fn series(mut self, number: u32, description: impl Into<String>) -> Self {
+        self.series_number = number;
+        self.series_description = Some(description.into());
+        self
+    }
+
+    /// Set the Pixel Spacing in mm between rows and between columns.
+    pub fn pixel_spacing(mut self, row: f64, column: f64) -> Self {
+        self.pixel_spacing = Some((row, column));
+        self
+    }
+
+    /// Set the Rescale Slope and Intercept mapping stored values to real
+    /// values.
+    pub fn rescale(mut self, slope: f64, intercept: f64) -> Self {
+        self.rescale = Some((slope, intercept));
+        self
+    }
+
+    /// The Study Instance UID of built instances.
+    pub fn study_instance_uid(&self) -> &str {
+        &self.study_uid
+    }
+
+    /// The Series Instance UID of built instances.
+    pub fn series_instance_uid(&self) -> &str {
+        &self.series_uid
+    }
+
+    /// Build an uncompressed (Explicit VR Little Endian) instance holding
+    /// `image`, with a new SOP Instance UID.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidFormat` if the image has several frames, more than
+    /// 65535 rows or columns, more than 16 bits per sample, or neither one
+    /// nor three samples per pixel, and `Config` if the birth date or sex
+    /// is malformed.
+    pub fn build(&self, image: &ImageData, instance_number: u32) -> Result<DicomFile> {
+        let date = &self.patient_birth_date;
+        if !date.is_empty() && (date.len() != 8 || !date.bytes().all(|b| b.is_ascii_digit())) {
+            return Err(MedImgError::Config(format!(
+                "Patient's Birth Date must be YYYYMMDD, got {:?}",
+                date
+            )));
+        }
+        if !matches!(self.patient_sex.as_str(), "" | "M" | "F" | "O") {
+            return Err(MedImgError::Config(format!(
+                "Patient's Sex must be M, F, or O, got {:?}",
+                self.patient_sex
+            )));
+        }
+        if image.number_of_frames > 1 {
+            return Err(MedImgError::InvalidFormat(format!(
+                "Secondary Capture holds one frame, got {}",
+                image.number_of_frames
+            )));
+        }
+        if image.bits_per_sample == 0 || image.bits_per_sample > 16 {
+            return Err(MedImgError::InvalidFormat(format!(
+                "Secondary Capture supports 1-16 bits per sample, got {}",
+                image.bits_per_sample
+            )));
+        }
+        if image.width > u32::from(u16::MAX) || image.height > u32::from(u16::MAX) {
+            return Err(MedImgError::InvalidFormat(format!(
+                "Image of {}x{} exceeds the DICOM limit of 65535 rows and columns",
+                image.width, image.height
+            )));
+        }
+        let photometric = match (image.samples_per_pixel, image.photometric_interpretation.as_str()) {
+            (1, "") => Photometric::Monochrome2.as_str(),
+            (3, "") => Photometric::Rgb.as_str(),
+            (1 | 3, photometric) => photometric,
+            (samples, _) => {
+                return Err(MedImgError::InvalidFormat(format!(
+                    "Secondary Capture supports 1 or 3 samples per pixel, got {}",
+                    samples
+                )))
+            }
+        };
+        let image = image.packed();
+        let bits_allocated: u16 = if image.bits_per_sample > 8 { 16 } else { 8 };
+        let sop_instance_uid = utils::generate_uid();
+
+        let text = |tag, vr, value: &str| DataElement::new(tag, vr, PrimitiveValue::from(value));
+        let short = |tag, value: u16| DataElement::new(tag, VR::US, PrimitiveValue::from(value));
+        let mut object = InMemDicomObject::from_element_iter([
+            text(tags::SOP_CLASS_UID, VR::UI, SECONDARY_CAPTURE),
+            text(tags::SOP_INSTANCE_UID, VR::UI, &sop_instance_uid),
+            text(tags::STUDY_DATE, VR::DA, ""),
+            text(tags::STUDY_TIME, VR::TM, ""),
+            text(tags::ACCESSION_NUMBER, VR::SH, &self.accession_number),
+            text(tags::MODALITY, VR::CS, &self.modality),
+            text(tags::CONVERSION_TYPE, VR::CS, &self.conversion_type),
+            text(tags::REFERRING_PHYSICIAN_NAME, VR::PN, ""),
+            text(tags::PATIENT_NAME, VR::PN, &self.patient_name),
+            text(tags::PATIENT_ID, VR::LO, &self.patient_id),
+            text(tags::PATIENT_BIRTH_DATE, VR::DA, &self.patient_birth_date),
+            text(tags::PATIENT_SEX, VR::CS, &self.patient_sex),
+            text(tags::STUDY_INSTANCE_UID, VR::UI, &self.study_uid),
+            text(tags::SERIES_INSTANCE_UID, VR::UI, &self.series_uid),
+            text(tags::STUDY_ID, VR::SH, ""),
+            text(tags::SERIES_NUMBER, VR::IS, &self.series_number.to_string()),
+            text(tags::INSTANCE_NUMBER, VR::IS, &instance_number.to_string()),
+            text(tags::PATIENT_ORIENTATION, VR::CS, ""),
+            short(tags::SAMPLES_PER_PIXEL, image.samples_per_pixel),
+            text(tags::PHOTOMETRIC_INTERPRETATION, VR::CS, photometric),
+            short(tags::ROWS, image.height as u16),
+            short(tags::COLUMNS, image.width as u16),
+            short(tags::BITS_ALLOCATED, bits_allocated),
+            short(tags::BITS_STORED, image.bits_per_sample),
+            short(tags::HIGH_BIT, image.bits_per_sample - 1),
+            short(tags::PIXEL_REPRESENTATION, u16::from(image.is_signed)),
+        ]);
+        if image.samples_per_pixel == 3 {
+            object.put(short(tags::PLANAR_CONFIGURATION, 0));
+        }
+        if let Some(description) = &self.study_description {
+            object.put(text(tags::STUDY_DESCRIPTION, VR::LO, description));
+        }
+        if let Some(description) = &self.series_description {
+            object.put(text(tags::SERIES_DESCRIPTION, VR::LO, description));
+        }
+        if let Some((row, column)) = self.pixel_spacing {
+            object.put(text(tags::PIXEL_SPACING, VR::DS, &format!("{}\\{}", row, column)));
+        }
+        if let Some((slope, intercept)) = self.rescale {
+            object.put(text(tags::RESCALE_INTERCEPT, VR::DS, &intercept.to_string()));
+            object.put(text(tags::RESCALE_SLOPE, VR::DS, &slope.to_string()));
+            object.put(text(tags::RESCALE_TYPE, VR::LO, "US"));
+        }
+        let vr = if bits_allocated > 8 { VR::OW } else { VR::OB };
+        object.put(DataElement::new(
+            tags::PIXEL_DATA,
+            vr,
+            PrimitiveValue::from(image.pixel_data.to_vec()),
+        ));
+
+        let object = object
+            .with_meta(
+                FileMetaTableBuilder::new()
+                    .transfer_syntax(transfer_syntax::EXPLICIT_VR_LITTLE_ENDIAN)
+                    .implementation_class_uid(IMPLEMENTATION_CLASS_UID)
+                    .implementation_version_name(format!("MEDIMG_{}", crate::version::VERSION)),
+            )
+            .map_err(|e| MedImgError::dicom("Failed to build File Meta Information", e))?;
+        DicomFile::from_object(object)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::config::{CompressionCodec, CompressionConfig, Modality};
+    use crate::pipeline::CompressionPipeline;
+
+    #[test]
+    fn test_build_and_compress() {
+        let pixels: Vec<u8> = crate::pixel::encode_le::<i16>(&[-1000, -5, 0, 40, 400, 1200]);
+        let image = ImageData {
+            is_signed: true,
+            ..ImageData::new(3, 2, 12, 1, pixels.clone())
+        };
+        let capture = SecondaryCapture::new()
+            .patient("RES-0001", "RESEARCH^SUBJECT")
+            .modality("MR")
+            .series(3, "T1 converted")
+            .pixel_spacing(0.5, 0.75)
+            .rescale(2.0, -10.0);
+        let file = capture.build(&image, 7).unwrap();
+        assert_eq!(file.metadata.sop_class_uid.as_deref(), Some(SECONDARY_CAPTURE));
+        assert_eq!(file.metadata.study_uid.as_deref(), Some(capture.study_instance_uid()));
+        assert_eq!(file.metadata.modality, Modality::MR);
+        assert_eq!((file.metadata.rescale_slope, file.metadata.rescale_intercept), (2.0, -10.0));
+        let round_trip = file.to_image_data().unwrap();
+        assert!(round_trip.is_signed);
+        assert_eq!(round_trip.bits_per_sample, 12);
+        assert_eq!(round_trip.pixel_data, pixels);
+
+        // Another instance of the series gets its own UID
+        let other = capture.build(&image, 8).unwrap();
+        assert_ne!(other.metadata.sop_instance_uid, file.metadata.sop_instance_uid);
+        assert_eq!(other.metadata.series_uid, file.metadata.series_uid);
+
+        let dir = TempDir::new().unwrap();
+        let output = dir.path().join("sc.dcm");
+        CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::JpegLs))
+            .compress_dicom_to(file, "sc", &output)
+            .unwrap();
+        let written = DicomFile::open(&output).unwrap();
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, pixels);
+
+        let frames = ImageData {
+            number_of_frames: 2,
+            ..ImageData::new(2, 1, 8, 1, vec![0u8; 4])
+        };
+        assert!(matches!(capture.build(&frames, 1), Err(MedImgError::InvalidFormat(_))));
+    }
+}
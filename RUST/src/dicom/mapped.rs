@@ -0,0 +1,137 @@
+//! Memory-mapped pixel data (`mmap` feature).
+//!
+//! [`DicomFile::open_mapped`] parses the dataset up to Pixel Data and maps
+//! the file, so images taken from it share the mapping instead of holding
+//! the pixels in memory. Pages are read from disk as codecs and metrics
+//! touch them, which keeps multi-gigabyte whole-slide and enhanced CT
+//! objects out of RAM.
+
+use std::fs::File;
+use std::path::Path;
+
+use dicom::dictionary_std::tags;
+use dicom::object::OpenFileOptions;
+use memmap2::Mmap;
+
+use super::DicomFile;
+use crate::config::{transfer_syntax, DecodeLimits};
+use crate::error::{MedImgError, Result};
+use crate::Bytes;
+
+/// Pixel Data tag as stored little-endian.
+const PIXEL_DATA_TAG: [u8; 4] = [0xE0, 0x7F, 0x10, 0x00];
+
+/// Locate the value of a native Pixel Data element of `length` bytes: the
+/// first element header after `start` with the Pixel Data tag, an OB or OW
+/// VR (explicit VR), and that length, whose value fits in `data`.
+fn find_pixel_data(
+    data: &[u8],
+    start: usize,
+    explicit: bool,
+    length: u64,
+) -> Option<std::ops::Range<usize>> {
+    let length = u32::try_from(length).ok()?.to_le_bytes();
+    let header_len = if explicit { 12 } else { 8 };
+
+    (start..data.len().saturating_sub(header_len)).find_map(|at| {
+        let (tag, rest) = data[at..at + header_len].split_at(4);
+        let matches = tag == PIXEL_DATA_TAG
+            && if explicit {
+                matches!(&rest[..2], b"OB" | b"OW") && rest[2..4] == [0, 0] && rest[4..] == length
+            } else {
+                rest == length
+            };
+        let value = at + header_len..at + header_len + u32::from_le_bytes(length) as usize;
+        (matches && value.end <= data.len()).then_some(value)
+    })
+}
+
+impl DicomFile {
+    /// Open a DICOM file with its native pixel data memory-mapped.
+    ///
+    /// The dataset does not hold the Pixel Data element; read the pixels
+    /// with [`to_image_data`](Self::to_image_data) or
+    /// [`take_image_data`](Self::take_image_data), which share the mapping.
+    /// Files whose pixel data cannot be mapped (encapsulated or big endian)
+    /// are read as by [`open`](Self::open).
+    ///
+    /// The file must not be modified while images from it are alive.
+    ///
+    /// # Errors
+    ///
+    /// As for [`open`](Self::open).
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let mut slide = DicomFile::open_mapped("slide.dcm")?;
+    /// let image = slide.take_image_data()?; // no pixel copy
+    /// ```
+    pub fn open_mapped<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| MedImgError::from(e).with_file(path))?;
+        // SAFETY: the mapping is read-only; the caller keeps the file
+        // unmodified while it is mapped (documented above).
+        let map = unsafe { Mmap::map(&file) }.map_err(|e| MedImgError::from(e).with_file(path))?;
+        let data = Bytes::from_owner(map);
+
+        let start = if data.get(128..132) == Some(b"DICM".as_slice()) { 128 } else { 0 };
+        let object = OpenFileOptions::new()
+            .read_until(tags::PIXEL_DATA)
+            .from_reader(&data[start..])
+            .map_err(|e| MedImgError::dicom("Failed to read DICOM file", e).with_file(path))?;
+        let metadata = Self::extract_metadata(&object).map_err(|e| e.with_file(path))?;
+
+        let explicit = match metadata.transfer_syntax.as_str() {
+            transfer_syntax::EXPLICIT_VR_LITTLE_ENDIAN => true,
+            transfer_syntax::IMPLICIT_VR_LITTLE_ENDIAN => false,
+            _ => return Self::open(path),
+        };
+        let bits = metadata.width as u64
+            * metadata.height as u64
+            * metadata.samples_per_pixel as u64
+            * metadata.number_of_frames.max(1) as u64
+            * metadata.bits_allocated as u64;
+        // Values are padded to even length
+        let length = bits.div_ceil(8).next_multiple_of(2);
+        let Some(value) = find_pixel_data(&data, start, explicit, length) else {
+            log::debug!("{}: Pixel Data not found for mapping, reading the file", path.display());
+            return Self::open(path);
+        };
+
+        let file = Self {
+            object,
+            metadata,
+            mapped_pixel_data: Some(data.slice(value)),
+        };
+        file.check_limits(&DecodeLimits::current()).map_err(|e| e.with_file(path))?;
+        Ok(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dicom::testing;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_open_mapped() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let pixels = testing::gradient(8, 4);
+        testing::write_grayscale(&input, 8, 4, "CR", &pixels);
+
+        let mut mapped = DicomFile::open_mapped(&input).unwrap();
+        assert!(mapped.inner().element(tags::PIXEL_DATA).is_err());
+        assert_eq!(mapped.get_pixel_data().unwrap(), pixels);
+        let image = mapped.take_image_data().unwrap();
+        assert_eq!(image.pixel_data, pixels);
+        assert!(mapped.to_image_data().is_err());
+
+        // Images from both paths agree
+        let read = DicomFile::open(&input).unwrap().to_image_data().unwrap();
+        assert_eq!(image.sha256(), read.sha256());
+        assert!(DicomFile::open_mapped(dir.path().join("missing.dcm")).is_err());
+    }
+}
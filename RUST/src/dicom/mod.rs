@@ -3,13 +3,22 @@
 //! This module handles reading and writing DICOM files, extracting pixel data,
 //! and managing DICOM metadata for compression operations.
 
-use dicom::core::Tag;
-use dicom::dictionary_std::tags;
-use dicom::object::{open_file, DefaultDicomObject};
+use dicom::core::dictionary::{DataDictionary, DataDictionaryEntry};
+use dicom::core::value::{PixelFragmentSequence, Value};
+use dicom::core::header::Header;
+use dicom::core::{DataElement, PrimitiveValue, Tag, VR};
+use dicom::dictionary_std::{tags, StandardDataDictionary};
+use dicom::encoding::TransferSyntaxIndex;
+use dicom::object::{open_file, DefaultDicomObject, OpenFileOptions};
+use dicom::transfer_syntax::TransferSyntaxRegistry;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 
-use crate::config::Modality;
+use crate::codec::CodecFactory;
+use crate::config::{transfer_syntax, CompressionCodec, DecodeLimits, Modality};
 use crate::error::{MedImgError, Result};
-use crate::ImageData;
+use crate::pixel::{BitLayout, Photometric};
+use crate::{Bytes, ImageData};
 
 /// Type alias for the DICOM object returned by open_file.
 type DicomObject = DefaultDicomObject;
@@ -20,6 +29,9 @@ pub struct DicomFile {
     object: DicomObject,
     /// Extracted image metadata.
     pub metadata: DicomMetadata,
+    /// Native pixel data kept outside the dataset (memory-mapped by
+    /// `open_mapped`).
+    mapped_pixel_data: Option<Bytes>,
 }
 
 /// Essential DICOM metadata for compression.
@@ -33,6 +45,8 @@ pub struct DicomMetadata {
     pub series_uid: Option<String>,
     /// SOP Instance UID.
     pub sop_instance_uid: Option<String>,
+    /// SOP Class UID.
+    pub sop_class_uid: Option<String>,
     /// Image modality.
     pub modality: Modality,
     /// Original transfer syntax UID.
@@ -57,17 +71,154 @@ pub struct DicomMetadata {
     pub number_of_frames: u32,
     /// Planar configuration (for color images).
     pub planar_configuration: u16,
+    /// Rescale Slope mapping stored values to output units (e.g. HU).
+    pub rescale_slope: f64,
+    /// Rescale Intercept added after the slope.
+    pub rescale_intercept: f64,
+}
+
+impl DicomMetadata {
+    /// BitsAllocated, BitsStored, and HighBit.
+    pub fn bit_layout(&self) -> BitLayout {
+        BitLayout {
+            bits_allocated: self.bits_allocated,
+            bits_stored: self.bits_stored,
+            high_bit: self.high_bit,
+        }
+    }
+
+    /// Pseudonymous identifier of the study: the first 16 hex digits of
+    /// the SHA-256 of the Study Instance UID.
+    ///
+    /// Lets telemetry correlate work on a study across services without
+    /// exporting the UID itself.
+    pub fn study_uid_hash(&self) -> Option<String> {
+        let digest = Sha256::digest(self.study_uid.as_deref()?.as_bytes());
+        Some(digest[..8].iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+}
+
+/// Longest element value shown in a dump before truncation.
+const MAX_DUMP_VALUE_LEN: usize = 128;
+
+/// A top-level dataset element, rendered for display.
+#[derive(Debug, Clone, Serialize)]
+pub struct DatasetElement {
+    /// Tag as `(gggg,eeee)`.
+    pub tag: String,
+    /// Value representation.
+    pub vr: String,
+    /// Standard dictionary keyword, if known.
+    pub keyword: Option<String>,
+    /// Value as text; binary data and sequences are summarized.
+    pub value: String,
+}
+
+impl std::fmt::Display for DatasetElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {:<32} {}",
+            self.tag,
+            self.vr,
+            self.keyword.as_deref().unwrap_or("?"),
+            self.value
+        )
+    }
 }
 
 impl DicomFile {
     /// Open and parse a DICOM file.
+    ///
+    /// Errors carry the file path as context.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LimitExceeded` if the file or its image is over the
+    /// installed [`DecodeLimits`].
     pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
-        let object = open_file(path)
-            .map_err(|e| MedImgError::Dicom(format!("Failed to read DICOM file: {}", e)))?;
+        let path = path.as_ref();
+        let limits = DecodeLimits::current();
+        let size = std::fs::metadata(path).map_err(|e| MedImgError::from(e).with_file(path))?.len();
+        limits.check_output(size).map_err(|e| e.with_file(path))?;
+        let object = open_file(path).map_err(|e| {
+            MedImgError::dicom("Failed to read DICOM file", e).with_file(path)
+        })?;
+
+        let metadata = Self::extract_metadata(&object).map_err(|e| e.with_file(path))?;
+
+        let file = Self {
+            object,
+            metadata,
+            mapped_pixel_data: None,
+        };
+        file.check_limits(&limits).map_err(|e| e.with_file(path))?;
+        Ok(file)
+    }
+
+    /// Parse a DICOM file from a byte stream (e.g. stdin).
+    ///
+    /// The 128-byte preamble is optional and detected automatically.
+    ///
+    /// # Errors
+    ///
+    /// Returns `LimitExceeded` if the image is over the installed
+    /// [`DecodeLimits`].
+    pub fn from_reader<R: std::io::Read>(reader: R) -> Result<Self> {
+        let object = OpenFileOptions::new()
+            .from_reader(reader)
+            .map_err(|e| MedImgError::dicom("Failed to read DICOM stream", e))?;
 
         let metadata = Self::extract_metadata(&object)?;
 
-        Ok(Self { object, metadata })
+        let file = Self {
+            object,
+            metadata,
+            mapped_pixel_data: None,
+        };
+        file.check_limits(&DecodeLimits::current())?;
+        Ok(file)
+    }
+
+    /// Wrap a dataset built in memory.
+    pub(crate) fn from_object(object: DicomObject) -> Result<Self> {
+        let metadata = Self::extract_metadata(&object)?;
+
+        Ok(Self {
+            object,
+            metadata,
+            mapped_pixel_data: None,
+        })
+    }
+
+    /// Check the declared image geometry and the fragment count against
+    /// `limits`, before any pixel data is decoded.
+    fn check_limits(&self, limits: &DecodeLimits) -> Result<()> {
+        let metadata = &self.metadata;
+        limits.check_image(
+            metadata.width,
+            metadata.height,
+            metadata.bits_allocated,
+            metadata.samples_per_pixel,
+            metadata.number_of_frames,
+        )?;
+        if let Some(fragments) = self
+            .object
+            .element(tags::PIXEL_DATA)
+            .ok()
+            .and_then(|element| element.value().fragments())
+        {
+            limits
+                .check_fragments(fragments.len(), "fragments")
+                .map_err(|e| e.with_tag(tags::PIXEL_DATA))?;
+        }
+        Ok(())
+    }
+
+    /// Re-read metadata after the dataset was modified in place.
+    pub fn refresh_metadata(&mut self) -> Result<()> {
+        self.metadata = Self::extract_metadata(&self.object)?;
+        Ok(())
     }
 
     /// Extract metadata from DICOM object.
@@ -88,18 +239,22 @@ impl DicomFile {
         };
 
         // Required image parameters
+        let missing = |tag: Tag, name: &str| {
+            MedImgError::Dicom(format!("Missing {} tag", name)).with_tag(tag)
+        };
+
         let width = get_u16(tags::COLUMNS)
-            .ok_or_else(|| MedImgError::Dicom("Missing Columns tag".into()))? as u32;
+            .ok_or_else(|| missing(tags::COLUMNS, "Columns"))? as u32;
 
         let height = get_u16(tags::ROWS)
-            .ok_or_else(|| MedImgError::Dicom("Missing Rows tag".into()))? as u32;
+            .ok_or_else(|| missing(tags::ROWS, "Rows"))? as u32;
 
         let bits_allocated = get_u16(tags::BITS_ALLOCATED)
-            .ok_or_else(|| MedImgError::Dicom("Missing BitsAllocated tag".into()))?;
+            .ok_or_else(|| missing(tags::BITS_ALLOCATED, "BitsAllocated"))?;
 
         let bits_stored = get_u16(tags::BITS_STORED).unwrap_or(bits_allocated);
 
-        let high_bit = get_u16(tags::HIGH_BIT).unwrap_or(bits_stored - 1);
+        let high_bit = get_u16(tags::HIGH_BIT).unwrap_or(bits_stored.saturating_sub(1));
 
         let samples_per_pixel = get_u16(tags::SAMPLES_PER_PIXEL).unwrap_or(1);
 
@@ -115,6 +270,17 @@ impl DicomFile {
 
         let planar_configuration = get_u16(tags::PLANAR_CONFIGURATION).unwrap_or(0);
 
+        let rescale_slope = obj
+            .element(tags::RESCALE_SLOPE)
+            .ok()
+            .and_then(|e| e.to_float64().ok())
+            .unwrap_or(1.0);
+        let rescale_intercept = obj
+            .element(tags::RESCALE_INTERCEPT)
+            .ok()
+            .and_then(|e| e.to_float64().ok())
+            .unwrap_or(0.0);
+
         // Transfer syntax from meta header
         let transfer_syntax = obj
             .meta()
@@ -130,6 +296,7 @@ impl DicomFile {
             study_uid: get_string(tags::STUDY_INSTANCE_UID),
             series_uid: get_string(tags::SERIES_INSTANCE_UID),
             sop_instance_uid: get_string(tags::SOP_INSTANCE_UID),
+            sop_class_uid: get_string(tags::SOP_CLASS_UID),
             modality,
             transfer_syntax,
             width,
@@ -142,42 +309,219 @@ impl DicomFile {
             pixel_representation,
             number_of_frames,
             planar_configuration,
+            rescale_slope,
+            rescale_intercept,
         })
     }
 
     /// Extract pixel data from the DICOM file.
     pub fn get_pixel_data(&self) -> Result<Vec<u8>> {
+        if let Some(mapped) = &self.mapped_pixel_data {
+            return Ok(mapped.to_vec());
+        }
         let pixel_data_element = self
             .object
             .element(tags::PIXEL_DATA)
-            .map_err(|_| MedImgError::Dicom("Missing PixelData element".into()))?;
+            .map_err(|_| MedImgError::Dicom("Missing PixelData element".into()).with_tag(tags::PIXEL_DATA))?;
 
         // Get raw bytes
-        let bytes = pixel_data_element
-            .to_bytes()
-            .map_err(|e| MedImgError::Dicom(format!("Failed to extract pixel data: {}", e)))?;
+        let bytes = pixel_data_element.to_bytes().map_err(|e| {
+            MedImgError::dicom("Failed to extract pixel data", e).with_tag(tags::PIXEL_DATA)
+        })?;
 
         Ok(bytes.to_vec())
     }
 
+    /// Extract encapsulated pixel data, concatenating all fragments.
+    pub fn get_encapsulated_data(&self) -> Result<Vec<u8>> {
+        let pixel_data_element = self
+            .object
+            .element(tags::PIXEL_DATA)
+            .map_err(|_| MedImgError::Dicom("Missing PixelData element".into()).with_tag(tags::PIXEL_DATA))?;
+
+        let fragments = pixel_data_element.value().fragments().ok_or_else(|| {
+            MedImgError::Dicom("Pixel data is not encapsulated".into()).with_tag(tags::PIXEL_DATA)
+        })?;
+
+        Ok(fragments.concat())
+    }
+
+    /// Extract encapsulated pixel data, one codestream per frame.
+    ///
+    /// Fragments are assigned to frames one to one when their counts match,
+    /// and by the Basic Offset Table otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` if the fragments cannot be split into
+    /// Number of Frames frames, and `LimitExceeded` if there are more
+    /// frames than the installed [`DecodeLimits`] allow.
+    pub fn get_encapsulated_frames(&self) -> Result<Vec<Vec<u8>>> {
+        let frames = self.metadata.number_of_frames.max(1) as usize;
+        if frames == 1 {
+            return Ok(vec![self.get_encapsulated_data()?]);
+        }
+        DecodeLimits::current()
+            .check_fragments(frames, "frames")
+            .map_err(|e| e.with_tag(tags::NUMBER_OF_FRAMES))?;
+
+        let pixel_data_element = self
+            .object
+            .element(tags::PIXEL_DATA)
+            .map_err(|_| MedImgError::Dicom("Missing PixelData element".into()).with_tag(tags::PIXEL_DATA))?;
+        let (Some(offsets), Some(fragments)) = (
+            pixel_data_element.value().offset_table(),
+            pixel_data_element.value().fragments(),
+        ) else {
+            return Err(MedImgError::Dicom("Pixel data is not encapsulated".into()).with_tag(tags::PIXEL_DATA));
+        };
+
+        if fragments.len() == frames {
+            return Ok(fragments.to_vec());
+        }
+        if offsets.len() != frames {
+            return Err(MedImgError::ImageData(format!(
+                "Cannot split {} fragments into {} frames without a Basic Offset Table",
+                fragments.len(),
+                frames
+            ))
+            .with_tag(tags::PIXEL_DATA));
+        }
+
+        // Offsets count from the first fragment's item tag; each item has an
+        // 8-byte header
+        let mut output = vec![Vec::new(); frames];
+        let mut position = 0u64;
+        for fragment in fragments {
+            let frame = offsets.partition_point(|&offset| u64::from(offset) <= position);
+            output[frame.saturating_sub(1)].extend_from_slice(fragment);
+            position += fragment.len() as u64 + 8;
+        }
+        Ok(output)
+    }
+
+    /// Decode pixel data into an ImageData structure.
+    ///
+    /// Native (uncompressed) pixel data is returned as-is. Encapsulated
+    /// pixel data in a JPEG 2000 or JPEG-LS transfer syntax is decoded
+    /// with the matching codec.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UnsupportedTransferSyntax` if no codec handles the
+    /// source transfer syntax (video frames are decoded with a
+    /// [`FrameExtractor`]), and `LimitExceeded` if decoding runs over the
+    /// installed [`DecodeLimits`].
+    pub fn decode_image_data(&self) -> Result<ImageData> {
+        if !self.is_compressed() {
+            return self.to_image_data();
+        }
+
+        let ts = &self.metadata.transfer_syntax;
+        let codec = utils::codec_for_transfer_syntax(ts)
+            .ok_or_else(|| MedImgError::UnsupportedTransferSyntax(ts.clone()))?;
+
+        // Covers splitting the fragments as well as the codec
+        let _budget = DecodeLimits::current().start();
+        let frames = self.get_encapsulated_frames()?;
+        let mut image = CodecFactory::create(codec).decode_frames(
+            &frames,
+            self.metadata.width,
+            self.metadata.height,
+            self.metadata.bits_stored,
+            self.metadata.samples_per_pixel,
+        )?;
+
+        // Decoders undo the JPEG 2000 component transforms
+        image.photometric_interpretation = match Photometric::from_dicom(&self.metadata.photometric_interpretation) {
+            Some(photometric) => photometric.decoded().as_str().into(),
+            None => self.metadata.photometric_interpretation.clone(),
+        };
+        image.is_signed = self.metadata.pixel_representation == 1;
+        Ok(image)
+    }
+
+    /// Move the pixel data out of the file, decoding compressed sources.
+    ///
+    /// Like [`decode_image_data`](Self::decode_image_data), but native pixel
+    /// data is moved into the image instead of copied, and the Pixel Data
+    /// element is removed from the dataset. Use this when the file is only
+    /// kept to be written with new pixel data, so large images are not held
+    /// in memory twice.
+    pub fn take_image_data(&mut self) -> Result<ImageData> {
+        if self.is_compressed() {
+            let image = self.decode_image_data()?;
+            self.object.remove_element(tags::PIXEL_DATA);
+            return Ok(image);
+        }
+        if let Some(mapped) = self.mapped_pixel_data.take() {
+            return self.image_data(mapped);
+        }
+
+        let element = self
+            .object
+            .take_element(tags::PIXEL_DATA)
+            .map_err(|_| MedImgError::Dicom("Missing PixelData element".into()).with_tag(tags::PIXEL_DATA))?;
+        let pixel_data = match element.into_value() {
+            Value::Primitive(PrimitiveValue::U8(bytes)) => Bytes::from(bytes.into_vec()),
+            Value::Primitive(value) => Bytes::copy_from_slice(&value.to_bytes()),
+            _ => {
+                return Err(MedImgError::Dicom("Native pixel data is encapsulated".into())
+                    .with_tag(tags::PIXEL_DATA))
+            }
+        };
+        self.image_data(pixel_data)
+    }
+
     /// Convert to ImageData structure for compression.
+    ///
+    /// Samples are moved to the low bits of byte-aligned containers when
+    /// BitsAllocated or HighBit place them elsewhere (see
+    /// [`BitLayout::unpack`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` if BitsAllocated, BitsStored, and HighBit are
+    /// inconsistent.
     pub fn to_image_data(&self) -> Result<ImageData> {
+        if let Some(mapped) = &self.mapped_pixel_data {
+            return self.image_data(mapped.clone());
+        }
         let pixel_data = self.get_pixel_data()?;
+        self.image_data(pixel_data.into())
+    }
+
+    /// Image with this file's geometry and native `pixel_data`, normalized
+    /// to the layout [`ImageData`] expects.
+    fn image_data(&self, pixel_data: Bytes) -> Result<ImageData> {
+        let layout = self.metadata.bit_layout();
+        layout.validate().map_err(|e| e.with_tag(tags::BITS_STORED))?;
+        let pixel_data = if layout.is_normalized() {
+            pixel_data
+        } else {
+            let samples = self.metadata.width as usize
+                * self.metadata.height as usize
+                * self.metadata.samples_per_pixel as usize
+                * self.metadata.number_of_frames.max(1) as usize;
+            layout.unpack(&pixel_data, samples)?.into()
+        };
 
         Ok(ImageData {
             width: self.metadata.width,
             height: self.metadata.height,
             bits_per_sample: self.metadata.bits_stored,
             samples_per_pixel: self.metadata.samples_per_pixel,
+            number_of_frames: self.metadata.number_of_frames.max(1),
             pixel_data,
+            row_stride: None,
             photometric_interpretation: self.metadata.photometric_interpretation.clone(),
             is_signed: self.metadata.pixel_representation == 1,
         })
     }
 
     /// Get the modality of the image.
-    pub fn modality(&self) -> Modality {
-        self.metadata.modality
+    pub fn modality(&self) -> &Modality {
+        &self.metadata.modality
     }
 
     /// Check if the image is already compressed.
@@ -188,6 +532,34 @@ impl DicomFile {
         )
     }
 
+    /// Check if the pixel data is MPEG or HEVC video, which is passed
+    /// through rather than decoded (see [`FrameExtractor`]).
+    pub fn is_video(&self) -> bool {
+        utils::is_video_transfer_syntax(&self.metadata.transfer_syntax)
+    }
+
+    /// List top-level dataset elements in tag order.
+    ///
+    /// If `filter` is non-empty, only elements with those tags are listed.
+    /// File meta information (group 0002) is not part of the dataset.
+    pub fn dump(&self, filter: &[Tag]) -> Vec<DatasetElement> {
+        self.object
+            .iter()
+            .filter(|e| filter.is_empty() || filter.contains(&e.tag()))
+            .map(|e| {
+                let tag = e.tag();
+                DatasetElement {
+                    tag: format!("({:04X},{:04X})", tag.group(), tag.element()),
+                    vr: e.vr().to_string().to_owned(),
+                    keyword: StandardDataDictionary
+                        .by_tag(tag)
+                        .map(|entry| entry.alias().to_string()),
+                    value: display_value(e.vr(), e.value()),
+                }
+            })
+            .collect()
+    }
+
     /// Get the underlying DICOM object for modification.
     pub fn inner(&self) -> &DicomObject {
         &self.object
@@ -202,7 +574,6 @@ impl DicomFile {
 /// Builder for creating new DICOM files with compressed pixel data.
 pub struct DicomWriter {
     /// Source DICOM metadata to preserve.
-    #[allow(dead_code)]
     source_metadata: DicomMetadata,
 }
 
@@ -213,40 +584,279 @@ impl DicomWriter {
     }
 
     /// Write compressed DICOM file.
+    ///
+    /// `frames` holds one codestream per frame (or the native pixel data of
+    /// each frame for uncompressed transfer syntaxes).
+    ///
+    /// Copies all attributes from `source`, replaces the pixel data, and
+    /// updates the File Meta Information for the new transfer syntax.
+    /// For lossy transfer syntaxes the lossy compression attributes are set
+    /// and a new SOP Instance UID is assigned. Photometric Interpretation
+    /// is taken from the writer's metadata, so conversions made before
+    /// encoding are recorded.
     pub fn write<P: AsRef<std::path::Path>>(
         &self,
-        _source: &DicomFile,
-        _compressed_data: &[u8],
-        _new_transfer_syntax: &str,
-        _output_path: P,
+        source: &DicomFile,
+        frames: &[Vec<u8>],
+        new_transfer_syntax: &str,
+        output_path: P,
     ) -> Result<()> {
-        // For MVP, we'll implement a simplified version
-        // Full implementation would update transfer syntax and encapsulate pixel data
+        let output_path = output_path.as_ref();
 
         log::info!(
             "Writing DICOM file with transfer syntax: {}",
-            _new_transfer_syntax
+            new_transfer_syntax
         );
 
-        // TODO: Implement full DICOM writing with:
-        // 1. Update File Meta Information
-        // 2. Update Transfer Syntax UID
-        // 3. Encapsulate pixel data in fragments
-        // 4. Write to file
+        let object = self.build(source, frames, new_transfer_syntax)?;
+        object.write_to_file(output_path)?;
 
-        Err(MedImgError::Internal(
-            "DICOM writing not fully implemented in MVP".into(),
-        ))
+        Ok(())
+    }
+
+    /// Write compressed DICOM to a byte stream (e.g. stdout).
+    ///
+    /// Produces the same Part 10 encoding as [`write`](Self::write),
+    /// including the preamble.
+    pub fn write_to<W: std::io::Write>(
+        &self,
+        source: &DicomFile,
+        frames: &[Vec<u8>],
+        new_transfer_syntax: &str,
+        to: W,
+    ) -> Result<()> {
+        log::info!(
+            "Writing DICOM file with transfer syntax: {}",
+            new_transfer_syntax
+        );
+
+        let object = self.build(source, frames, new_transfer_syntax)?;
+        object.write_all(to)?;
+
+        Ok(())
+    }
+
+    /// Build the output DICOM object without writing it.
+    pub fn build(
+        &self,
+        source: &DicomFile,
+        frames: &[Vec<u8>],
+        new_transfer_syntax: &str,
+    ) -> Result<DicomObject> {
+        let ts = TransferSyntaxRegistry
+            .get(new_transfer_syntax)
+            .ok_or_else(|| MedImgError::UnsupportedTransferSyntax(new_transfer_syntax.into()))?;
+
+        let mut object = source.inner().clone();
+
+        // Pixel data is written in the normalized layout it was read into
+        let layout = source.metadata.bit_layout();
+        if !layout.is_normalized() {
+            let normalized = BitLayout::new(layout.bits_stored.div_ceil(8) * 8, layout.bits_stored);
+            object.put(DataElement::new(
+                tags::BITS_ALLOCATED,
+                VR::US,
+                PrimitiveValue::from(normalized.bits_allocated),
+            ));
+            object.put(DataElement::new(tags::HIGH_BIT, VR::US, PrimitiveValue::from(normalized.high_bit)));
+        }
+
+        if !self.source_metadata.photometric_interpretation.is_empty() {
+            object.put(DataElement::new(
+                tags::PHOTOMETRIC_INTERPRETATION,
+                VR::CS,
+                PrimitiveValue::from(self.source_metadata.photometric_interpretation.as_str()),
+            ));
+        }
+
+        // Offset tables of the source describe its fragments, not ours
+        object.remove_element(tags::EXTENDED_OFFSET_TABLE);
+        object.remove_element(tags::EXTENDED_OFFSET_TABLE_LENGTHS);
+        if ts.is_codec_free() {
+            object.put(self.native_pixel_data(&frames.concat()));
+        } else {
+            Self::put_encapsulated_pixel_data(&mut object, frames);
+        }
+
+        if !utils::is_lossless_transfer_syntax(new_transfer_syntax) {
+            let compressed_size = frames.iter().map(Vec::len).sum();
+            self.mark_lossy(&mut object, compressed_size, new_transfer_syntax);
+        }
+
+        object.meta_mut().set_transfer_syntax(ts);
+        Ok(object)
+    }
+
+    /// Create a native (uncompressed) Pixel Data element.
+    fn native_pixel_data(&self, data: &[u8]) -> DataElement<dicom::object::InMemDicomObject> {
+        let vr = if self.source_metadata.bits_stored > 8 {
+            VR::OW
+        } else {
+            VR::OB
+        };
+        DataElement::new(tags::PIXEL_DATA, vr, PrimitiveValue::from(data.to_vec()))
+    }
+
+    /// Put an encapsulated Pixel Data element with one fragment per frame
+    /// into `object`, with the offset tables from [`offset_tables`].
+    fn put_encapsulated_pixel_data(object: &mut DicomObject, frames: &[Vec<u8>]) {
+        let fragments: Vec<Vec<u8>> = frames
+            .iter()
+            .map(|frame| {
+                // Fragments must have even length
+                let mut fragment = frame.clone();
+                if !fragment.len().is_multiple_of(2) {
+                    fragment.push(0);
+                }
+                fragment
+            })
+            .collect();
+        let lengths: Vec<u64> = fragments.iter().map(|fragment| fragment.len() as u64).collect();
+
+        let basic = match offset_tables(&lengths) {
+            OffsetTables::Basic(offsets) => offsets,
+            OffsetTables::Extended(offsets, lengths) => {
+                object.put(DataElement::new(
+                    tags::EXTENDED_OFFSET_TABLE,
+                    VR::OV,
+                    PrimitiveValue::U64(offsets.into()),
+                ));
+                object.put(DataElement::new(
+                    tags::EXTENDED_OFFSET_TABLE_LENGTHS,
+                    VR::OV,
+                    PrimitiveValue::U64(lengths.into()),
+                ));
+                Vec::new()
+            }
+        };
+        let sequence = PixelFragmentSequence::new(basic, fragments);
+        object.put(DataElement::new(tags::PIXEL_DATA, VR::OB, Value::PixelSequence(sequence)));
+    }
+
+    /// Record lossy compression and assign a new SOP Instance UID.
+    fn mark_lossy(&self, object: &mut DicomObject, compressed_size: usize, ts: &str) {
+        let original_size = utils::calculate_pixel_data_size(&self.source_metadata);
+        let ratio = if compressed_size == 0 {
+            0.0
+        } else {
+            original_size as f64 / compressed_size as f64
+        };
+        let method = if ts == transfer_syntax::JPEG_LS_NEAR_LOSSLESS {
+            "ISO_14495_1"
+        } else {
+            "ISO_15444_1"
+        };
+
+        // Previous lossy steps are kept as earlier values of the multi-valued attributes
+        let append = |tag: Tag, value: String| -> String {
+            match object.element(tag).ok().and_then(|e| e.to_str().ok()) {
+                Some(existing) if !existing.trim().is_empty() => {
+                    format!("{}\\{}", existing.trim(), value)
+                }
+                _ => value,
+            }
+        };
+        let ratios = append(tags::LOSSY_IMAGE_COMPRESSION_RATIO, format!("{:.2}", ratio));
+        let methods = append(tags::LOSSY_IMAGE_COMPRESSION_METHOD, method.to_string());
+
+        object.put(DataElement::new(
+            tags::LOSSY_IMAGE_COMPRESSION,
+            VR::CS,
+            PrimitiveValue::from("01"),
+        ));
+        object.put(DataElement::new(
+            tags::LOSSY_IMAGE_COMPRESSION_RATIO,
+            VR::DS,
+            PrimitiveValue::from(ratios),
+        ));
+        object.put(DataElement::new(
+            tags::LOSSY_IMAGE_COMPRESSION_METHOD,
+            VR::CS,
+            PrimitiveValue::from(methods),
+        ));
+
+        // A lossy derived image is a new instance
+        let uid = utils::generate_uid();
+        object.put(DataElement::new(
+            tags::SOP_INSTANCE_UID,
+            VR::UI,
+            PrimitiveValue::from(uid.as_str()),
+        ));
+        let meta = object.meta_mut();
+        meta.media_storage_sop_instance_uid = uid;
+        meta.update_information_group_length();
     }
 }
 
+/// Offset tables of encapsulated Pixel Data (PS3.5 A.4).
+#[derive(Debug, PartialEq)]
+enum OffsetTables {
+    /// Basic Offset Table.
+    Basic(Vec<u32>),
+    /// Extended Offset Table and its lengths, with an empty Basic Offset
+    /// Table.
+    Extended(Vec<u64>, Vec<u64>),
+}
+
+/// Offset tables for one fragment per frame, of `lengths` bytes each: the
+/// extended ones once a fragment starts past the 4 GiB that 32-bit offsets
+/// can reach.
+fn offset_tables(lengths: &[u64]) -> OffsetTables {
+    // Offsets include each item's 8-byte header
+    let offsets: Vec<u64> = lengths
+        .iter()
+        .scan(0u64, |position, &length| {
+            let offset = *position;
+            *position = position.saturating_add(length + 8);
+            Some(offset)
+        })
+        .collect();
+    match offsets.iter().map(|&offset| u32::try_from(offset)).collect() {
+        Ok(basic) => OffsetTables::Basic(basic),
+        Err(_) => OffsetTables::Extended(offsets, lengths.to_vec()),
+    }
+}
+
+/// Render an element value for display.
+fn display_value<I, P: AsRef<[u8]>>(vr: VR, value: &Value<I, P>) -> String {
+    match value {
+        Value::Sequence(seq) => format!("<sequence of {} items>", seq.items().len()),
+        Value::PixelSequence(seq) => format!(
+            "<encapsulated, {} fragments, {} bytes>",
+            seq.fragments().len(),
+            seq.fragments().iter().map(|f| f.as_ref().len()).sum::<usize>()
+        ),
+        Value::Primitive(value) => match vr {
+            VR::OB | VR::OW | VR::OD | VR::OF | VR::OL | VR::OV | VR::UN => {
+                format!("<{} bytes>", value.calculate_byte_len())
+            }
+            _ => {
+                let text = value.to_str();
+                let text = text.trim_end_matches([' ', '\0']);
+                match text.char_indices().nth(MAX_DUMP_VALUE_LEN) {
+                    Some((end, _)) => format!("{}...", &text[..end]),
+                    None => text.to_string(),
+                }
+            }
+        },
+    }
+}
+
+mod capture;
+#[cfg(feature = "mmap")]
+mod mapped;
+mod video;
+
+pub use capture::{SecondaryCapture, SECONDARY_CAPTURE};
+pub use video::FrameExtractor;
+
 /// Utility functions for DICOM operations.
 pub mod utils {
     use super::*;
 
     /// Calculate expected pixel data size from metadata.
     pub fn calculate_pixel_data_size(metadata: &DicomMetadata) -> usize {
-        let bytes_per_sample = ((metadata.bits_allocated + 7) / 8) as usize;
+        let bytes_per_sample = metadata.bits_allocated.div_ceil(8) as usize;
         metadata.width as usize
             * metadata.height as usize
             * metadata.samples_per_pixel as usize
@@ -268,6 +878,73 @@ pub mod utils {
         )
     }
 
+    /// Check if transfer syntax is MPEG-2, MPEG-4 AVC/H.264, or HEVC/H.265
+    /// video (including the fragmentable variants).
+    pub fn is_video_transfer_syntax(ts: &str) -> bool {
+        matches!(
+            ts,
+            "1.2.840.10008.1.2.4.100"       // MPEG2 Main Profile / Main Level
+            | "1.2.840.10008.1.2.4.100.1"
+            | "1.2.840.10008.1.2.4.101"     // MPEG2 Main Profile / High Level
+            | "1.2.840.10008.1.2.4.101.1"
+            | "1.2.840.10008.1.2.4.102"     // MPEG-4 AVC/H.264 High Profile / Level 4.1
+            | "1.2.840.10008.1.2.4.102.1"
+            | "1.2.840.10008.1.2.4.103"     // MPEG-4 AVC/H.264 BD-Compatible High Profile / Level 4.1
+            | "1.2.840.10008.1.2.4.103.1"
+            | "1.2.840.10008.1.2.4.104"     // MPEG-4 AVC/H.264 High Profile / Level 4.2 For 2D Video
+            | "1.2.840.10008.1.2.4.104.1"
+            | "1.2.840.10008.1.2.4.105"     // MPEG-4 AVC/H.264 High Profile / Level 4.2 For 3D Video
+            | "1.2.840.10008.1.2.4.105.1"
+            | "1.2.840.10008.1.2.4.106"     // MPEG-4 AVC/H.264 Stereo High Profile / Level 4.2
+            | "1.2.840.10008.1.2.4.106.1"
+            | "1.2.840.10008.1.2.4.107"     // HEVC/H.265 Main Profile / Level 5.1
+            | "1.2.840.10008.1.2.4.108"     // HEVC/H.265 Main 10 Profile / Level 5.1
+        )
+    }
+
+    /// Get the codec that decodes a compressed transfer syntax.
+    pub fn codec_for_transfer_syntax(ts: &str) -> Option<CompressionCodec> {
+        match ts {
+            transfer_syntax::JPEG_2000_LOSSLESS | transfer_syntax::JPEG_2000_LOSSY => {
+                Some(CompressionCodec::Jpeg2000)
+            }
+            transfer_syntax::JPEG_LS_LOSSLESS | transfer_syntax::JPEG_LS_NEAR_LOSSLESS => {
+                Some(CompressionCodec::JpegLs)
+            }
+            _ => None,
+        }
+    }
+
+    /// Parse a tag as `gggg,eeee`, `(gggg,eeee)`, `ggggeeee`, or a keyword.
+    pub fn parse_tag(text: &str) -> Result<Tag> {
+        StandardDataDictionary
+            .parse_tag(text.trim())
+            .ok_or_else(|| MedImgError::InvalidFormat(format!("Unknown DICOM tag '{}'", text)))
+    }
+
+    /// Generate a new UID under the `2.25` (UUID-derived) root.
+    pub fn generate_uid() -> String {
+        use std::collections::hash_map::RandomState;
+        use std::hash::{BuildHasher, Hasher};
+        use std::time::{SystemTime, UNIX_EPOCH};
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+
+        // Each RandomState is seeded differently, giving 128 unpredictable bits
+        let mut halves = [0u64; 2];
+        for half in &mut halves {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(nanos);
+            hasher.write_u32(std::process::id());
+            *half = hasher.finish();
+        }
+
+        format!("2.25.{}", (u128::from(halves[0]) << 64) | u128::from(halves[1]))
+    }
+
     /// Get human-readable name for transfer syntax.
     pub fn transfer_syntax_name(ts: &str) -> &'static str {
         match ts {
@@ -279,8 +956,370 @@ pub mod utils {
             "1.2.840.10008.1.2.4.81" => "JPEG-LS Near-Lossless",
             "1.2.840.10008.1.2.4.90" => "JPEG 2000 Lossless",
             "1.2.840.10008.1.2.4.91" => "JPEG 2000 Lossy",
+            "1.2.840.10008.1.2.4.100" | "1.2.840.10008.1.2.4.100.1" => "MPEG2 Main Profile / Main Level",
+            "1.2.840.10008.1.2.4.101" | "1.2.840.10008.1.2.4.101.1" => "MPEG2 Main Profile / High Level",
+            "1.2.840.10008.1.2.4.102" | "1.2.840.10008.1.2.4.102.1" => "MPEG-4 AVC/H.264 High Profile",
+            "1.2.840.10008.1.2.4.103" | "1.2.840.10008.1.2.4.103.1" => {
+                "MPEG-4 AVC/H.264 BD-Compatible High Profile"
+            }
+            "1.2.840.10008.1.2.4.104" | "1.2.840.10008.1.2.4.104.1" => {
+                "MPEG-4 AVC/H.264 High Profile For 2D Video"
+            }
+            "1.2.840.10008.1.2.4.105" | "1.2.840.10008.1.2.4.105.1" => {
+                "MPEG-4 AVC/H.264 High Profile For 3D Video"
+            }
+            "1.2.840.10008.1.2.4.106" | "1.2.840.10008.1.2.4.106.1" => {
+                "MPEG-4 AVC/H.264 Stereo High Profile"
+            }
+            "1.2.840.10008.1.2.4.107" => "HEVC/H.265 Main Profile",
+            "1.2.840.10008.1.2.4.108" => "HEVC/H.265 Main 10 Profile",
             "1.2.840.10008.1.2.5" => "RLE Lossless",
             _ => "Unknown",
         }
     }
 }
+
+/// Helpers for building synthetic DICOM files in tests.
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::path::Path;
+
+    use dicom::core::value::{PixelFragmentSequence, Value};
+    use dicom::core::{DataElement, PrimitiveValue, VR};
+    use dicom::dictionary_std::tags;
+    use dicom::object::{FileMetaTableBuilder, InMemDicomObject};
+
+    use super::SECONDARY_CAPTURE;
+    use crate::config::transfer_syntax;
+
+    /// Write an 8-bit grayscale Explicit VR Little Endian DICOM file.
+    pub(crate) fn write_grayscale(path: &Path, width: u16, height: u16, modality: &str, pixels: &[u8]) {
+        write_frames(path, width, height, 1, modality, pixels);
+    }
+
+    /// Write an 8-bit grayscale multi-frame DICOM file, with the frames'
+    /// pixels back to back.
+    pub(crate) fn write_frames(
+        path: &Path,
+        width: u16,
+        height: u16,
+        frames: u32,
+        modality: &str,
+        pixels: &[u8],
+    ) {
+        let mut obj = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::SOP_CLASS_UID, VR::UI, PrimitiveValue::from(SECONDARY_CAPTURE)),
+            DataElement::new(tags::SOP_INSTANCE_UID, VR::UI, PrimitiveValue::from("1.2.826.0.1.3680043.2.1125.1")),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("TEST001")),
+            DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from(modality)),
+            DataElement::new(tags::SAMPLES_PER_PIXEL, VR::US, PrimitiveValue::from(1_u16)),
+            DataElement::new(tags::PHOTOMETRIC_INTERPRETATION, VR::CS, PrimitiveValue::from("MONOCHROME2")),
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(height)),
+            DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(width)),
+            DataElement::new(tags::BITS_ALLOCATED, VR::US, PrimitiveValue::from(8_u16)),
+            DataElement::new(tags::BITS_STORED, VR::US, PrimitiveValue::from(8_u16)),
+            DataElement::new(tags::HIGH_BIT, VR::US, PrimitiveValue::from(7_u16)),
+            DataElement::new(tags::PIXEL_REPRESENTATION, VR::US, PrimitiveValue::from(0_u16)),
+            DataElement::new(tags::PIXEL_DATA, VR::OB, PrimitiveValue::from(pixels.to_vec())),
+        ]);
+        if frames > 1 {
+            obj.put(DataElement::new(
+                tags::NUMBER_OF_FRAMES,
+                VR::IS,
+                PrimitiveValue::from(frames.to_string()),
+            ));
+        }
+
+        obj.with_meta(
+            FileMetaTableBuilder::new()
+                .transfer_syntax(transfer_syntax::EXPLICIT_VR_LITTLE_ENDIAN)
+                .implementation_class_uid("1.2.826.0.1.3680043.2.1125.99"),
+        )
+        .unwrap()
+        .write_to_file(path)
+        .unwrap();
+    }
+
+    /// Write a video instance in transfer syntax `ts` whose pixel data
+    /// holds `fragments`.
+    pub(crate) fn write_video(path: &Path, ts: &str, width: u16, height: u16, frames: u32, fragments: &[&[u8]]) {
+        let fragments = fragments.iter().map(|fragment| fragment.to_vec()).collect::<Vec<_>>();
+        let obj = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::SOP_CLASS_UID, VR::UI, PrimitiveValue::from("1.2.840.10008.5.1.4.1.1.3.1")),
+            DataElement::new(tags::SOP_INSTANCE_UID, VR::UI, PrimitiveValue::from("1.2.826.0.1.3680043.2.1125.2")),
+            DataElement::new(tags::PATIENT_ID, VR::LO, PrimitiveValue::from("TEST001")),
+            DataElement::new(tags::MODALITY, VR::CS, PrimitiveValue::from("US")),
+            DataElement::new(tags::SAMPLES_PER_PIXEL, VR::US, PrimitiveValue::from(3_u16)),
+            DataElement::new(tags::PHOTOMETRIC_INTERPRETATION, VR::CS, PrimitiveValue::from("YBR_PARTIAL_420")),
+            DataElement::new(tags::PLANAR_CONFIGURATION, VR::US, PrimitiveValue::from(0_u16)),
+            DataElement::new(tags::NUMBER_OF_FRAMES, VR::IS, PrimitiveValue::from(frames.to_string())),
+            DataElement::new(tags::ROWS, VR::US, PrimitiveValue::from(height)),
+            DataElement::new(tags::COLUMNS, VR::US, PrimitiveValue::from(width)),
+            DataElement::new(tags::BITS_ALLOCATED, VR::US, PrimitiveValue::from(8_u16)),
+            DataElement::new(tags::BITS_STORED, VR::US, PrimitiveValue::from(8_u16)),
+            DataElement::new(tags::HIGH_BIT, VR::US, PrimitiveValue::from(7_u16)),
+            DataElement::new(tags::PIXEL_REPRESENTATION, VR::US, PrimitiveValue::from(0_u16)),
+            DataElement::new(
+                tags::PIXEL_DATA,
+                VR::OB,
+                Value::PixelSequence(PixelFragmentSequence::new(Vec::<u32>::new(), fragments)),
+            ),
+        ]);
+
+        obj.with_meta(
+            FileMetaTableBuilder::new()
+                .transfer_syntax(ts)
+                .implementation_class_uid("1.2.826.0.1.3680043.2.1125.99"),
+        )
+        .unwrap()
+        .write_to_file(path)
+        .unwrap();
+    }
+
+    /// A smooth 8-bit gradient image of the given size.
+    pub(crate) fn gradient(width: u16, height: u16) -> Vec<u8> {
+        (0..height as usize)
+            .flat_map(|y| (0..width as usize).map(move |x| ((x + y) * 2 % 256) as u8))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_writer_encapsulates_and_decodes() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let output = dir.path().join("out.dcm");
+        let pixels = testing::gradient(16, 16);
+        testing::write_grayscale(&input, 16, 16, "CR", &pixels);
+
+        let source = DicomFile::open(&input).unwrap();
+        let image = source.decode_image_data().unwrap();
+        let codec = CodecFactory::create(CompressionCodec::JpegLs);
+        let config = crate::config::CompressionConfig::lossless(CompressionCodec::JpegLs);
+        let encoded = codec.encode(&image, &config, None).unwrap();
+
+        DicomWriter::new(source.metadata.clone())
+            .write(&source, &[encoded], transfer_syntax::JPEG_LS_LOSSLESS, &output)
+            .unwrap();
+
+        let written = DicomFile::open(&output).unwrap();
+        assert_eq!(written.metadata.transfer_syntax, transfer_syntax::JPEG_LS_LOSSLESS);
+        assert!(written.is_compressed());
+        assert_eq!(written.metadata.sop_instance_uid, source.metadata.sop_instance_uid);
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, pixels);
+    }
+
+    #[test]
+    fn test_take_image_data() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let output = dir.path().join("out.dcm");
+        let pixels = testing::gradient(8, 8);
+        testing::write_grayscale(&input, 8, 8, "CR", &pixels);
+
+        let mut source = DicomFile::open(&input).unwrap();
+        let image = source.take_image_data().unwrap();
+        assert_eq!(image.pixel_data, pixels);
+        assert!(source.inner().element(tags::PIXEL_DATA).is_err());
+        assert!(source.take_image_data().is_err());
+
+        // The writer only needs the remaining dataset
+        DicomWriter::new(source.metadata.clone())
+            .write(&source, std::slice::from_ref(&pixels), transfer_syntax::EXPLICIT_VR_LITTLE_ENDIAN, &output)
+            .unwrap();
+        assert_eq!(DicomFile::open(&output).unwrap().to_image_data().unwrap().pixel_data, pixels);
+    }
+
+    #[test]
+    fn test_bit_layout_normalized() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let output = dir.path().join("out.dcm");
+        testing::write_grayscale(&input, 2, 1, "CR", &[0, 0]);
+        let mut file = DicomFile::open(&input).unwrap();
+
+        // 12 stored bits at the top of 16-bit containers
+        file.metadata.bits_allocated = 16;
+        file.metadata.bits_stored = 12;
+        file.metadata.high_bit = 15;
+        for (tag, value) in [(tags::BITS_ALLOCATED, 16u16), (tags::BITS_STORED, 12), (tags::HIGH_BIT, 15)] {
+            file.inner_mut().put(DataElement::new(tag, VR::US, PrimitiveValue::from(value)));
+        }
+        let shifted = crate::pixel::encode_le::<u16>(&[0xFFF0, 0x0010]);
+        file.inner_mut().put(DataElement::new(
+            tags::PIXEL_DATA,
+            VR::OW,
+            PrimitiveValue::from(shifted),
+        ));
+        let image = file.to_image_data().unwrap();
+        assert_eq!(image.as_u16().unwrap().samples, vec![4095, 1]);
+
+        // Written back with HighBit at the top of the stored bits
+        DicomWriter::new(file.metadata.clone())
+            .write(&file, std::slice::from_ref(&image.pixel_data.to_vec()), transfer_syntax::EXPLICIT_VR_LITTLE_ENDIAN, &output)
+            .unwrap();
+        let written = DicomFile::open(&output).unwrap();
+        assert_eq!(written.metadata.high_bit, 11);
+        assert_eq!(written.to_image_data().unwrap().pixel_data, image.pixel_data);
+
+        file.metadata.high_bit = 16;
+        assert!(file.to_image_data().is_err());
+    }
+
+    #[test]
+    fn test_study_uid_hash() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        testing::write_grayscale(&input, 2, 1, "CT", &[0, 0]);
+        let mut metadata = DicomFile::open(&input).unwrap().metadata;
+        assert_eq!(metadata.study_uid_hash(), None);
+
+        metadata.study_uid = Some("1.2.3".into());
+        assert_eq!(metadata.study_uid_hash().as_deref(), Some("c47f5b18b8a430e6"));
+    }
+
+    #[test]
+    fn test_encapsulated_frames_by_offset_table() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        testing::write_frames(&input, 2, 2, 2, "XA", &[0; 8]);
+        let mut file = DicomFile::open(&input).unwrap();
+
+        // Frame 0 spans two fragments; each item adds an 8-byte header
+        let fragments = vec![vec![1; 4], vec![2; 4], vec![3; 6]];
+        let sequence = PixelFragmentSequence::new(vec![0, 24], fragments.clone());
+        file.inner_mut()
+            .put(DataElement::new(tags::PIXEL_DATA, VR::OB, Value::PixelSequence(sequence)));
+        assert_eq!(
+            file.get_encapsulated_frames().unwrap(),
+            vec![vec![1, 1, 1, 1, 2, 2, 2, 2], vec![3; 6]]
+        );
+
+        let sequence = PixelFragmentSequence::new(vec![], fragments);
+        file.inner_mut()
+            .put(DataElement::new(tags::PIXEL_DATA, VR::OB, Value::PixelSequence(sequence)));
+        assert!(file.get_encapsulated_frames().is_err());
+    }
+
+    #[test]
+    fn test_offset_tables() {
+        assert_eq!(offset_tables(&[4, 6, 2]), OffsetTables::Basic(vec![0, 12, 26]));
+
+        // The third frame starts at 4 GiB, out of reach of the Basic Offset Table
+        let lengths = [2 << 30, (2 << 30) - 16, 4];
+        assert_eq!(
+            offset_tables(&lengths),
+            OffsetTables::Extended(vec![0, (2 << 30) + 8, 4 << 30], lengths.to_vec())
+        );
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        testing::write_frames(&input, 16, 16, 4, "XA", &[0; 1024]);
+        let file = DicomFile::open(&input).unwrap();
+        assert!(file.check_limits(&DecodeLimits::DEFAULT).is_ok());
+        let limits = DecodeLimits {
+            max_output_bytes: 512,
+            ..DecodeLimits::DEFAULT
+        };
+        assert!(matches!(file.check_limits(&limits), Err(MedImgError::LimitExceeded(_))));
+        let limits = DecodeLimits {
+            max_fragments: 3,
+            ..DecodeLimits::DEFAULT
+        };
+        assert!(file.check_limits(&limits).is_err());
+
+        // A small file declaring more pixels than the default limit allows
+        let bomb = dir.path().join("bomb.dcm");
+        testing::write_frames(&bomb, 65535, 65535, 64, "XA", &[0; 16]);
+        let err = DicomFile::open(&bomb).err().unwrap();
+        assert!(matches!(err.root(), MedImgError::LimitExceeded(_)));
+        assert_eq!(err.context().unwrap().file.as_deref(), Some(bomb.as_path()));
+    }
+
+    #[test]
+    fn test_writer_marks_lossy() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        testing::write_grayscale(&input, 16, 16, "CR", &testing::gradient(16, 16));
+        let source = DicomFile::open(&input).unwrap();
+
+        let object = DicomWriter::new(source.metadata.clone())
+            .build(&source, &[vec![0u8; 32]], transfer_syntax::JPEG_2000_LOSSY)
+            .unwrap();
+
+        let get = |tag| object.element(tag).unwrap().to_str().unwrap().trim().to_string();
+        assert_eq!(get(tags::LOSSY_IMAGE_COMPRESSION), "01");
+        assert_eq!(get(tags::LOSSY_IMAGE_COMPRESSION_RATIO), "8.00");
+        assert_eq!(get(tags::LOSSY_IMAGE_COMPRESSION_METHOD), "ISO_15444_1");
+
+        let uid = get(tags::SOP_INSTANCE_UID);
+        assert!(uid.starts_with("2.25."));
+        assert_ne!(Some(uid.clone()), source.metadata.sop_instance_uid);
+        assert_eq!(object.meta().media_storage_sop_instance_uid(), uid);
+    }
+
+    #[test]
+    fn test_open_error_names_file() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("not-dicom.dcm");
+        std::fs::write(&input, b"plain text").unwrap();
+
+        let err = DicomFile::open(&input).err().unwrap();
+        assert_eq!(err.context().unwrap().file.as_deref(), Some(input.as_path()));
+        assert!(err.to_string().starts_with(&input.display().to_string()));
+
+        // The dicom crate's error stays in the source chain
+        let root = err.root();
+        assert!(matches!(root, MedImgError::DicomSource { .. }));
+        assert!(std::error::Error::source(root).is_some());
+    }
+
+    #[test]
+    fn test_writer_rejects_unknown_transfer_syntax() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        testing::write_grayscale(&input, 4, 4, "CR", &[0; 16]);
+        let source = DicomFile::open(&input).unwrap();
+
+        let result = DicomWriter::new(source.metadata.clone()).build(&source, &[vec![0; 16]], "1.2.3");
+        assert!(matches!(result, Err(MedImgError::UnsupportedTransferSyntax(_))));
+    }
+
+    #[test]
+    fn test_dump_filters_and_summarizes() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        testing::write_grayscale(&input, 4, 4, "CR", &[0; 16]);
+        let source = DicomFile::open(&input).unwrap();
+
+        let all = source.dump(&[]);
+        assert!(all.len() > 10);
+        let pixel = all.iter().find(|e| e.tag == "(7FE0,0010)").unwrap();
+        assert_eq!(pixel.value, "<16 bytes>");
+
+        let filter = [utils::parse_tag("0008,0060").unwrap(), utils::parse_tag("PatientID").unwrap()];
+        let filtered = source.dump(&filter);
+        assert_eq!(filtered.len(), 2);
+        assert_eq!(filtered[0].keyword.as_deref(), Some("Modality"));
+        assert_eq!(filtered[0].value, "CR");
+        assert_eq!(filtered[1].value, "TEST001");
+
+        assert!(utils::parse_tag("NotATag").is_err());
+    }
+
+    #[test]
+    fn test_generate_uid_unique() {
+        let a = utils::generate_uid();
+        let b = utils::generate_uid();
+        assert_ne!(a, b);
+        assert!(a.len() <= 64);
+    }
+}
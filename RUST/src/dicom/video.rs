@@ -0,0 +1,213 @@
+//! Frames of MPEG-2, MPEG-4 AVC/H.264, and HEVC/H.265 video.
+//!
+//! Video pixel data (ultrasound clips, endoscopy) is already compressed by
+//! a lossy video codec and is passed through the pipeline unchanged. For
+//! previews and quality metrics, [`FrameExtractor`] decodes selected frames
+//! with an external `ffmpeg` executable, so no video decoder is linked
+//! into the crate; without one, extraction fails and everything else still
+//! works.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use super::DicomFile;
+use crate::config::DecodeLimits;
+use crate::error::{MedImgError, Result};
+use crate::pixel::Photometric;
+use crate::ImageData;
+
+/// Decodes frames of video pixel data to [`ImageData`].
+///
+/// ```rust,ignore
+/// let dicom = DicomFile::open("echo.dcm")?;
+/// let frames = FrameExtractor::new().extract(&dicom, &[0, 30, 60])?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct FrameExtractor {
+    /// ffmpeg executable.
+    ffmpeg: PathBuf,
+}
+
+impl FrameExtractor {
+    /// Extractor running `ffmpeg` from the `PATH`.
+    pub fn new() -> Self {
+        Self {
+            ffmpeg: PathBuf::from("ffmpeg"),
+        }
+    }
+
+    /// Run the ffmpeg executable at `path`.
+    pub fn ffmpeg(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ffmpeg = path.into();
+        self
+    }
+
+    /// Decode the zero-based `frames` of a video instance, in the order
+    /// given.
+    ///
+    /// Frames are 8-bit RGB, or MONOCHROME2 for monochrome sources.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UnsupportedTransferSyntax` if the file is not video,
+    /// `ImageData` if a frame is out of range, `LimitExceeded` if the
+    /// frames are over the installed [`DecodeLimits`], and `Codec` if
+    /// ffmpeg cannot be run or fails.
+    pub fn extract(&self, dicom: &DicomFile, frames: &[u32]) -> Result<Vec<ImageData>> {
+        let metadata = &dicom.metadata;
+        if !dicom.is_video() {
+            return Err(MedImgError::UnsupportedTransferSyntax(format!(
+                "{} is not a video transfer syntax",
+                metadata.transfer_syntax
+            )));
+        }
+        let count = metadata.number_of_frames.max(1);
+        if let Some(frame) = frames.iter().find(|&&frame| frame >= count) {
+            return Err(MedImgError::ImageData(format!(
+                "Frame {} out of range (video has {} frames)",
+                frame, count
+            )));
+        }
+
+        let mut wanted = frames.to_vec();
+        wanted.sort_unstable();
+        wanted.dedup();
+        let monochrome = Photometric::from_dicom(&metadata.photometric_interpretation)
+            .is_some_and(|photometric| photometric.is_monochrome());
+        let samples: u16 = if monochrome { 1 } else { 3 };
+        DecodeLimits::current().check_image(metadata.width, metadata.height, 8, samples, wanted.len() as u32)?;
+        if wanted.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let stream = dicom.get_encapsulated_data()?;
+        let decoded = self.run(&stream, &wanted, monochrome)?;
+        let frame_size = metadata.width as usize * metadata.height as usize * samples as usize;
+        if decoded.len() != frame_size * wanted.len() {
+            return Err(MedImgError::Codec(format!(
+                "ffmpeg produced {} bytes, expected {} frames of {}x{}",
+                decoded.len(),
+                wanted.len(),
+                metadata.width,
+                metadata.height
+            )));
+        }
+
+        let photometric = if monochrome { "MONOCHROME2" } else { "RGB" };
+        Ok(frames
+            .iter()
+            .map(|frame| {
+                let index = wanted.binary_search(frame).expect("requested frames are extracted");
+                let pixels = decoded[index * frame_size..(index + 1) * frame_size].to_vec();
+                let mut image = ImageData::new(metadata.width, metadata.height, 8, samples, pixels);
+                image.photometric_interpretation = photometric.into();
+                image
+            })
+            .collect())
+    }
+
+    /// Decode the sorted frame indices `wanted` from `stream` to packed
+    /// raw frames.
+    fn run(&self, stream: &[u8], wanted: &[u32], monochrome: bool) -> Result<Vec<u8>> {
+        let select = wanted
+            .iter()
+            .map(|frame| format!("eq(n\\,{})", frame))
+            .collect::<Vec<_>>()
+            .join("+");
+        let mut child = Command::new(&self.ffmpeg)
+            .args(["-nostdin", "-v", "error", "-i", "pipe:0", "-vf"])
+            .arg(format!("select={}", select))
+            .args(["-fps_mode", "passthrough", "-f", "rawvideo", "-pix_fmt"])
+            .arg(if monochrome { "gray" } else { "rgb24" })
+            .arg("pipe:1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                MedImgError::Codec(format!(
+                    "Cannot run '{}' to decode video frames: {}",
+                    self.ffmpeg.display(),
+                    e
+                ))
+            })?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // Written on another thread: ffmpeg emits frames while it reads
+        let (written, output) = std::thread::scope(|scope| {
+            let writer = scope.spawn(move || stdin.write_all(stream));
+            let output = child.wait_with_output();
+            (writer.join(), output)
+        });
+        let output = output?;
+        if !output.status.success() {
+            return Err(MedImgError::Codec(format!(
+                "'{}' failed to decode video frames ({}): {}",
+                self.ffmpeg.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        match written {
+            Ok(Ok(())) => {}
+            // ffmpeg stops reading once the last selected frame is out
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+            Ok(Err(e)) => return Err(e.into()),
+            Err(_) => return Err(MedImgError::Internal("Video stream writer panicked".into())),
+        }
+        Ok(output.stdout)
+    }
+}
+
+impl Default for FrameExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::config::transfer_syntax;
+    use crate::dicom::testing;
+
+    #[test]
+    fn test_extract_selected_frames() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("video.dcm");
+        testing::write_video(&input, transfer_syntax::MPEG4_AVC_H264_HIGH_PROFILE, 4, 2, 10, &[b"stream"]);
+        let dicom = DicomFile::open(&input).unwrap();
+
+        // Stands in for ffmpeg: echoes the select filter's frame numbers as
+        // the pixel values of 4x2 RGB frames
+        let fake = dir.path().join("ffmpeg");
+        let script = r#"#!/bin/sh
+cat > /dev/null
+for arg; do case "$arg" in select=*) filter="$arg" ;; esac; done
+for n in $(echo "$filter" | tr -c '0-9' ' '); do
+  i=0; while [ $i -lt 24 ]; do printf "\\$(printf %o "$n")"; i=$((i + 1)); done
+done
+"#;
+        std::fs::write(&fake, script).unwrap();
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let frames = FrameExtractor::new().ffmpeg(&fake).extract(&dicom, &[7, 2, 7]).unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].pixel_data.as_ref(), &[7; 24][..]);
+        assert_eq!(frames[1].pixel_data.as_ref(), &[2; 24][..]);
+        assert_eq!(frames[2].photometric_interpretation, "RGB");
+
+        assert!(matches!(
+            FrameExtractor::new().ffmpeg(&fake).extract(&dicom, &[10]),
+            Err(MedImgError::ImageData(_))
+        ));
+        assert!(matches!(
+            FrameExtractor::new().ffmpeg(dir.path().join("missing")).extract(&dicom, &[0]),
+            Err(MedImgError::Codec(_))
+        ));
+    }
+}
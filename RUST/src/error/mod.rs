@@ -1,21 +1,44 @@
 //! Error types for the medical image compression library.
 
+use std::path::{Path, PathBuf};
+
+use dicom::core::Tag;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Result type alias for the library.
 pub type Result<T> = std::result::Result<T, MedImgError>;
 
 /// Main error type for the medical image compression library.
+///
+/// New variants may be added as features land, so matches outside this
+/// crate need a wildcard arm; [`code`](Self::code) and
+/// [`category`](Self::category) classify every variant.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum MedImgError {
     /// Error reading or parsing DICOM file.
     #[error("DICOM error: {0}")]
     Dicom(String),
 
+    /// Error from the `dicom` crate, kept as the source.
+    #[error("DICOM error: {message}: {source}")]
+    DicomSource {
+        /// What was being done (e.g. `Failed to read DICOM file`).
+        message: String,
+        /// The `dicom` crate's error.
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
     /// Error during image compression/decompression.
     #[error("Codec error: {0}")]
     Codec(String),
 
+    /// Malformed codestream found while decoding.
+    #[error("Codec error: {0}")]
+    CodecParse(CodecParseError),
+
     /// Invalid or unsupported image format.
     #[error("Invalid image format: {0}")]
     InvalidFormat(String),
@@ -32,7 +55,7 @@ pub enum MedImgError {
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
 
-    /// Validation error (e.g., regulatory constraints violated).
+    /// Validation error (e.g., input that fails a precondition).
     #[error("Validation error: {0}")]
     Validation(String),
 
@@ -40,23 +63,628 @@ pub enum MedImgError {
     #[error("Image data error: {0}")]
     ImageData(String),
 
+    /// Decoding would exceed a [`DecodeLimits`](crate::config::DecodeLimits)
+    /// limit (dimensions, output size, fragments, or time).
+    #[error("Decode limit exceeded: {0}")]
+    LimitExceeded(String),
+
+    /// Decoded pixel data does not match the original.
+    #[error("Verification failed: {0}")]
+    VerificationFailed(String),
+
     /// Compression ratio constraint violation.
     #[error("Compression constraint violation: {0}")]
     CompressionConstraint(String),
 
+    /// Modality or SOP class policy forbids the requested compression.
+    #[error("Policy violation: {0}")]
+    PolicyViolation(String),
+
+    /// An operation did not finish within its time limit.
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    /// The operation was cancelled before it finished.
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    /// Error writing output to storage.
+    #[error("Storage error: {0}")]
+    Storage(String),
+
     /// Generic internal error.
     #[error("Internal error: {0}")]
     Internal(String),
+
+    /// Another error, with the file, element, or byte offset it occurred at.
+    #[error("{context}: {source}")]
+    Context {
+        /// Where the error occurred.
+        context: ErrorContext,
+        /// The underlying error.
+        #[source]
+        source: Box<MedImgError>,
+    },
+}
+
+/// Where and how a codestream is malformed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodecParseError {
+    /// Codec name (e.g. `JPEG 2000`).
+    pub codec: &'static str,
+    /// Marker being parsed, if any (e.g. `0xFF90` for a JPEG 2000 SOT).
+    pub marker: Option<u16>,
+    /// Byte offset in the codestream.
+    pub offset: usize,
+    /// What the decoder expected.
+    pub expected: String,
+    /// What it found instead.
+    pub found: String,
+}
+
+impl CodecParseError {
+    /// Malformed `codec` codestream at `offset`.
+    pub fn new(
+        codec: &'static str,
+        offset: usize,
+        expected: impl Into<String>,
+        found: impl Into<String>,
+    ) -> Self {
+        Self {
+            codec,
+            marker: None,
+            offset,
+            expected: expected.into(),
+            found: found.into(),
+        }
+    }
+
+    /// Set the marker being parsed.
+    pub fn marker(mut self, marker: u16) -> Self {
+        self.marker = Some(marker);
+        self
+    }
+}
+
+impl std::fmt::Display for CodecParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "malformed {} codestream at byte {}", self.codec, self.offset)?;
+        if let Some(marker) = self.marker {
+            write!(f, " (marker {:04X})", marker)?;
+        }
+        write!(f, ": expected {}, found {}", self.expected, self.found)
+    }
+}
+
+impl From<CodecParseError> for MedImgError {
+    fn from(err: CodecParseError) -> Self {
+        MedImgError::CodecParse(err)
+    }
+}
+
+/// Where an error occurred; see [`MedImgError::with_file`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// File being processed.
+    pub file: Option<PathBuf>,
+    /// DICOM element being read or written.
+    pub tag: Option<Tag>,
+    /// Byte offset in the file or codestream.
+    pub offset: Option<u64>,
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(file) = &self.file {
+            parts.push(file.display().to_string());
+        }
+        if let Some(tag) = self.tag {
+            parts.push(format!("element {}", tag));
+        }
+        if let Some(offset) = self.offset {
+            parts.push(format!("at byte {}", offset));
+        }
+        f.write_str(&parts.join(" "))
+    }
+}
+
+impl MedImgError {
+    /// Configuration error listing every problem found by
+    /// [`CompressionConfig::validate`](crate::config::CompressionConfig::validate).
+    pub fn invalid_config(problems: &[String]) -> Self {
+        MedImgError::Config(problems.join("; "))
+    }
+
+    /// DICOM error caused by `source`, which stays reachable through
+    /// [`std::error::Error::source`].
+    pub fn dicom(
+        message: impl Into<String>,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        MedImgError::DicomSource {
+            message: message.into(),
+            source: Box::new(source),
+        }
+    }
+
+    /// Record the file this error occurred in.
+    ///
+    /// Context accumulates on one wrapper, and context already recorded
+    /// (closer to the failure) is kept.
+    pub fn with_file(self, file: impl Into<PathBuf>) -> Self {
+        let file = file.into();
+        self.with_context(|context| {
+            context.file.get_or_insert(file);
+        })
+    }
+
+    /// Record the DICOM element this error occurred in.
+    pub fn with_tag(self, tag: Tag) -> Self {
+        self.with_context(|context| {
+            context.tag.get_or_insert(tag);
+        })
+    }
+
+    /// Record the byte offset this error occurred at.
+    pub fn with_offset(self, offset: u64) -> Self {
+        self.with_context(|context| {
+            context.offset.get_or_insert(offset);
+        })
+    }
+
+    /// Update this error's context, wrapping it if it has none yet.
+    fn with_context(self, update: impl FnOnce(&mut ErrorContext)) -> Self {
+        let (mut context, source) = match self {
+            MedImgError::Context { context, source } => (context, source),
+            error => (ErrorContext::default(), Box::new(error)),
+        };
+        update(&mut context);
+        MedImgError::Context { context, source }
+    }
+
+    /// Where this error occurred, if recorded.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            MedImgError::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// The underlying error, without context.
+    pub fn root(&self) -> &MedImgError {
+        match self {
+            MedImgError::Context { source, .. } => source.root(),
+            error => error,
+        }
+    }
+
+    /// Stable machine-readable code for this error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            MedImgError::Dicom(_) | MedImgError::DicomSource { .. } => ErrorCode::Dicom,
+            MedImgError::Codec(_) => ErrorCode::Codec,
+            MedImgError::CodecParse(_) => ErrorCode::CodecParse,
+            MedImgError::InvalidFormat(_) => ErrorCode::InvalidFormat,
+            MedImgError::UnsupportedTransferSyntax(_) => ErrorCode::UnsupportedTransferSyntax,
+            MedImgError::Config(_) => ErrorCode::Config,
+            MedImgError::Io(_) => ErrorCode::Io,
+            MedImgError::Validation(_) => ErrorCode::Validation,
+            MedImgError::ImageData(_) => ErrorCode::ImageData,
+            MedImgError::LimitExceeded(_) => ErrorCode::LimitExceeded,
+            MedImgError::VerificationFailed(_) => ErrorCode::VerificationFailed,
+            MedImgError::CompressionConstraint(_) => ErrorCode::CompressionConstraint,
+            MedImgError::PolicyViolation(_) => ErrorCode::PolicyViolation,
+            MedImgError::Timeout(_) => ErrorCode::Timeout,
+            MedImgError::Cancelled(_) => ErrorCode::Cancelled,
+            MedImgError::Storage(_) => ErrorCode::Storage,
+            MedImgError::Internal(_) => ErrorCode::Internal,
+            MedImgError::Context { source, .. } => source.code(),
+        }
+    }
+
+    /// Broad category of this error.
+    pub fn category(&self) -> ErrorCategory {
+        self.code().category()
+    }
+
+    /// Check if retrying the operation might succeed.
+    ///
+    /// Only transient failures qualify: timeouts, interrupted or
+    /// would-block calls, dropped connections, and files locked by another
+    /// process. Corrupt input, codec failures, and policy violations fail the
+    /// same way every time.
+    pub fn is_retryable(&self) -> bool {
+        use std::io::ErrorKind;
+
+        let err = match self.root() {
+            MedImgError::Timeout(_) => return true,
+            MedImgError::Io(err) => err,
+            _ => return false,
+        };
+        if matches!(
+            err.kind(),
+            ErrorKind::TimedOut
+                | ErrorKind::Interrupted
+                | ErrorKind::WouldBlock
+                | ErrorKind::ResourceBusy
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+        ) {
+            return true;
+        }
+        // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION: another process
+        // has the file open
+        cfg!(windows) && matches!(err.raw_os_error(), Some(32) | Some(33))
+    }
+}
+
+/// Broad error category, for integrations that only need to know where a
+/// failure came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum ErrorCategory {
+    /// The input file: DICOM parsing, formats, and pixel data.
+    Input,
+    /// Encoding, decoding, and round-trip verification.
+    Codec,
+    /// Settings and command-line arguments.
+    Config,
+    /// Modality and compression policy.
+    Policy,
+    /// Filesystem and network I/O, storage, and timeouts.
+    Io,
+    /// Work stopped at the caller's request.
+    Cancelled,
+    /// Bugs and unexpected states.
+    Internal,
+}
+
+impl ErrorCategory {
+    /// The category as it appears in reports (e.g. `policy`).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCategory::Input => "input",
+            ErrorCategory::Codec => "codec",
+            ErrorCategory::Config => "config",
+            ErrorCategory::Policy => "policy",
+            ErrorCategory::Io => "io",
+            ErrorCategory::Cancelled => "cancelled",
+            ErrorCategory::Internal => "internal",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Stable error code (e.g. `MI-DCM-001`).
+///
+/// Codes are never reused or renumbered, so reports and integrations can
+/// branch on them instead of matching error messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// DICOM read or parse error.
+    #[serde(rename = "MI-DCM-001")]
+    Dicom,
+    /// Invalid or unsupported image format.
+    #[serde(rename = "MI-DCM-002")]
+    InvalidFormat,
+    /// Unsupported transfer syntax.
+    #[serde(rename = "MI-DCM-003")]
+    UnsupportedTransferSyntax,
+    /// Image dimensions or data mismatch.
+    #[serde(rename = "MI-IMG-001")]
+    ImageData,
+    /// Decode limit exceeded.
+    #[serde(rename = "MI-IMG-002")]
+    LimitExceeded,
+    /// Compression or decompression error.
+    #[serde(rename = "MI-COD-001")]
+    Codec,
+    /// Decoded pixel data does not match the original.
+    #[serde(rename = "MI-COD-002")]
+    VerificationFailed,
+    /// Malformed codestream.
+    #[serde(rename = "MI-COD-003")]
+    CodecParse,
+    /// Configuration error.
+    #[serde(rename = "MI-CFG-001")]
+    Config,
+    /// Regulatory or policy validation error.
+    #[serde(rename = "MI-POL-001")]
+    Validation,
+    /// Compression ratio constraint violation.
+    #[serde(rename = "MI-POL-002")]
+    CompressionConstraint,
+    /// Lossless-only modality or SOP class.
+    #[serde(rename = "MI-POL-003")]
+    PolicyViolation,
+    /// I/O error.
+    #[serde(rename = "MI-IO-001")]
+    Io,
+    /// Operation timed out.
+    #[serde(rename = "MI-IO-002")]
+    Timeout,
+    /// Output could not be written.
+    #[serde(rename = "MI-IO-003")]
+    Storage,
+    /// Operation cancelled.
+    #[serde(rename = "MI-CAN-001")]
+    Cancelled,
+    /// Internal error.
+    #[serde(rename = "MI-INT-001")]
+    Internal,
+}
+
+impl ErrorCode {
+    /// The code as a string (e.g. `MI-DCM-001`).
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::Dicom => "MI-DCM-001",
+            ErrorCode::InvalidFormat => "MI-DCM-002",
+            ErrorCode::UnsupportedTransferSyntax => "MI-DCM-003",
+            ErrorCode::ImageData => "MI-IMG-001",
+            ErrorCode::LimitExceeded => "MI-IMG-002",
+            ErrorCode::Codec => "MI-COD-001",
+            ErrorCode::VerificationFailed => "MI-COD-002",
+            ErrorCode::CodecParse => "MI-COD-003",
+            ErrorCode::Config => "MI-CFG-001",
+            ErrorCode::Validation => "MI-POL-001",
+            ErrorCode::CompressionConstraint => "MI-POL-002",
+            ErrorCode::PolicyViolation => "MI-POL-003",
+            ErrorCode::Io => "MI-IO-001",
+            ErrorCode::Timeout => "MI-IO-002",
+            ErrorCode::Storage => "MI-IO-003",
+            ErrorCode::Cancelled => "MI-CAN-001",
+            ErrorCode::Internal => "MI-INT-001",
+        }
+    }
+
+    /// Category this code belongs to.
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ErrorCode::Dicom
+            | ErrorCode::InvalidFormat
+            | ErrorCode::UnsupportedTransferSyntax
+            | ErrorCode::ImageData
+            | ErrorCode::LimitExceeded => ErrorCategory::Input,
+            ErrorCode::Codec | ErrorCode::VerificationFailed | ErrorCode::CodecParse => {
+                ErrorCategory::Codec
+            }
+            ErrorCode::Config => ErrorCategory::Config,
+            ErrorCode::Validation
+            | ErrorCode::CompressionConstraint
+            | ErrorCode::PolicyViolation => ErrorCategory::Policy,
+            ErrorCode::Io | ErrorCode::Timeout | ErrorCode::Storage => ErrorCategory::Io,
+            ErrorCode::Cancelled => ErrorCategory::Cancelled,
+            ErrorCode::Internal => ErrorCategory::Internal,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Serializable summary of an error for reports, logs, and progress
+/// events.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorRecord {
+    /// Stable error code.
+    pub code: ErrorCode,
+    /// Error category.
+    pub category: ErrorCategory,
+    /// Error message, without the file and element context.
+    pub message: String,
+    /// File the error occurred in.
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    /// DICOM element and byte offset, if recorded (e.g.
+    /// `element (0028,0011) at byte 132`).
+    #[serde(default)]
+    pub context: Option<String>,
+}
+
+impl ErrorRecord {
+    /// Record `error`, falling back to `file` if the error does not name one.
+    pub fn new(error: &MedImgError, file: Option<&Path>) -> Self {
+        let context = error.context();
+        let location = context.map(|context| ErrorContext {
+            file: None,
+            ..context.clone()
+        });
+        Self {
+            code: error.code(),
+            category: error.category(),
+            message: error.root().to_string(),
+            file: context
+                .and_then(|context| context.file.clone())
+                .or_else(|| file.map(Path::to_path_buf)),
+            context: location
+                .filter(|location| *location != ErrorContext::default())
+                .map(|location| location.to_string()),
+        }
+    }
+
+    /// Record for an error known only by its message (e.g. from an older
+    /// manifest), classified by the message prefix.
+    pub fn from_message(message: impl Into<String>) -> Self {
+        let message = message.into();
+        let code = [
+            MedImgError::Dicom(String::new()),
+            MedImgError::Codec(String::new()),
+            MedImgError::InvalidFormat(String::new()),
+            MedImgError::UnsupportedTransferSyntax(String::new()),
+            MedImgError::Config(String::new()),
+            MedImgError::Validation(String::new()),
+            MedImgError::ImageData(String::new()),
+            MedImgError::LimitExceeded(String::new()),
+            MedImgError::VerificationFailed(String::new()),
+            MedImgError::CompressionConstraint(String::new()),
+            MedImgError::PolicyViolation(String::new()),
+            MedImgError::Timeout(String::new()),
+            MedImgError::Cancelled(String::new()),
+            MedImgError::Storage(String::new()),
+            MedImgError::Io(std::io::Error::other("")),
+        ]
+        .iter()
+        .find(|error| message.starts_with(&error.to_string()))
+        .map_or(ErrorCode::Internal, MedImgError::code);
+        Self {
+            code,
+            category: code.category(),
+            message,
+            file: None,
+            context: None,
+        }
+    }
+}
+
+impl From<&MedImgError> for ErrorRecord {
+    fn from(error: &MedImgError) -> Self {
+        Self::new(error, None)
+    }
+}
+
+impl std::fmt::Display for ErrorRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] ", self.code)?;
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        }
+        if let Some(context) = &self.context {
+            write!(f, "{}: ", context)?;
+        }
+        f.write_str(&self.message)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl From<tokio::task::JoinError> for MedImgError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        if err.is_cancelled() {
+            MedImgError::Cancelled("Background task was aborted".into())
+        } else {
+            MedImgError::Internal(format!("Background task panicked: {}", err))
+        }
+    }
 }
 
 impl From<dicom::object::ReadError> for MedImgError {
     fn from(err: dicom::object::ReadError) -> Self {
-        MedImgError::Dicom(err.to_string())
+        MedImgError::dicom("Failed to read DICOM data", err)
     }
 }
 
 impl From<dicom::object::WriteError> for MedImgError {
     fn from(err: dicom::object::WriteError) -> Self {
-        MedImgError::Dicom(err.to_string())
+        MedImgError::dicom("Failed to write DICOM data", err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes() {
+        let error = MedImgError::UnsupportedTransferSyntax("1.2.3".into());
+        assert_eq!(error.code().as_str(), "MI-DCM-003");
+        assert_eq!(error.category(), ErrorCategory::Input);
+
+        let error = MedImgError::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert_eq!(error.code(), ErrorCode::Io);
+
+        // Serialized form matches the displayed code
+        let code = MedImgError::CompressionConstraint("cap".into()).code();
+        assert_eq!(serde_json::to_value(code).unwrap(), code.as_str());
+        assert_eq!(code.category(), ErrorCategory::Policy);
+        assert_eq!(serde_json::to_value(code.category()).unwrap(), code.category().as_str());
+    }
+
+    #[test]
+    fn test_retryable() {
+        let io = |kind| MedImgError::from(std::io::Error::from(kind));
+        assert!(io(std::io::ErrorKind::TimedOut).is_retryable());
+        assert!(io(std::io::ErrorKind::ResourceBusy).is_retryable());
+        assert!(!io(std::io::ErrorKind::NotFound).is_retryable());
+        assert!(!MedImgError::Dicom("Missing Columns tag".into()).is_retryable());
+        assert!(!MedImgError::CompressionConstraint("MG".into()).is_retryable());
+        assert!(io(std::io::ErrorKind::TimedOut).with_file("/a.dcm").is_retryable());
+        assert!(MedImgError::Timeout("webhook".into()).is_retryable());
+        assert!(!MedImgError::Cancelled("batch".into()).is_retryable());
+    }
+
+    #[test]
+    fn test_capability_variants() {
+        let cases = [
+            (MedImgError::PolicyViolation("MG".into()), "MI-POL-003", ErrorCategory::Policy),
+            (MedImgError::Timeout("webhook".into()), "MI-IO-002", ErrorCategory::Io),
+            (MedImgError::Storage("disk full".into()), "MI-IO-003", ErrorCategory::Io),
+            (MedImgError::Cancelled("batch".into()), "MI-CAN-001", ErrorCategory::Cancelled),
+        ];
+        for (error, code, category) in cases {
+            assert_eq!(error.code().as_str(), code, "{}", error);
+            assert_eq!(error.category(), category, "{}", error);
+            // Older manifests stored only the message
+            assert_eq!(ErrorRecord::from_message(error.to_string()).code, error.code());
+        }
+    }
+
+    #[test]
+    fn test_error_context() {
+        use dicom::dictionary_std::tags;
+
+        let error = MedImgError::Dicom("Missing Columns tag".into())
+            .with_tag(tags::COLUMNS)
+            .with_file("/data/1.dcm");
+        assert_eq!(
+            error.to_string(),
+            "/data/1.dcm element (0028,0011): DICOM error: Missing Columns tag"
+        );
+        assert!(matches!(error.root(), MedImgError::Dicom(_)));
+        assert_eq!(error.code(), ErrorCode::Dicom);
+
+        // Context accumulates in one wrapper, keeping the source reachable
+        let context = error.context().unwrap();
+        assert_eq!(context.tag, Some(tags::COLUMNS));
+        assert_eq!(context.offset, None);
+        let source = std::error::Error::source(&error).unwrap();
+        assert_eq!(source.to_string(), "DICOM error: Missing Columns tag");
+    }
+
+    #[test]
+    fn test_error_record() {
+        use dicom::dictionary_std::tags;
+
+        let error = MedImgError::Dicom("Missing Columns tag".into())
+            .with_tag(tags::COLUMNS)
+            .with_file("/data/1.dcm");
+        let record = ErrorRecord::new(&error, Some(Path::new("/other.dcm")));
+        assert_eq!(record.code, ErrorCode::Dicom);
+        assert_eq!(record.category, ErrorCategory::Input);
+        assert_eq!(record.message, "DICOM error: Missing Columns tag");
+        assert_eq!(record.file.as_deref(), Some(Path::new("/data/1.dcm")));
+        assert_eq!(record.context.as_deref(), Some("element (0028,0011)"));
+
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["code"], "MI-DCM-001");
+        assert_eq!(json["category"], "input");
+
+        let plain = ErrorRecord::new(&MedImgError::Internal("boom".into()), Some(Path::new("/a.dcm")));
+        assert_eq!(plain.file.as_deref(), Some(Path::new("/a.dcm")));
+        assert_eq!(plain.context, None);
+
+        let legacy = ErrorRecord::from_message("Compression constraint violation: MG");
+        assert_eq!(legacy.code, ErrorCode::CompressionConstraint);
+        assert_eq!(ErrorRecord::from_message("something else").code, ErrorCode::Internal);
     }
 }
@@ -0,0 +1,420 @@
+//! Preview export to standard image formats.
+//!
+//! Renders a single frame of a DICOM image for review outside DICOM
+//! viewers. Grayscale images go through the modality LUT (rescale
+//! slope/intercept), a VOI window ([`WindowLevel`]), and MONOCHROME1
+//! inversion.
+//! Color images are converted to RGB and scaled to the output depth.
+//! Previews can be downsampled to a maximum size
+//! ([`PreviewOptions::max_size`]).
+//!
+//! The output format follows the file extension:
+//!
+//! - `.png`: 8-bit
+//! - `.tif` / `.tiff`: 16-bit
+
+use std::io::Cursor;
+use std::path::Path;
+use std::str::FromStr;
+
+use dicom::dictionary_std::tags;
+use image::{DynamicImage, ImageBuffer, ImageFormat, Luma, Rgb};
+
+use crate::dicom::{DicomFile, FrameExtractor};
+use crate::error::{MedImgError, Result};
+use crate::pixel::{ybr_to_rgb, Photometric, ResampleFilter, VoiFunction, WindowLevel};
+use crate::ImageData;
+
+/// VOI window selection.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Window {
+    /// Use the dataset's Window Center/Width, falling back to `MinMax`.
+    #[default]
+    Auto,
+    /// Stretch the frame's value range to the full output range.
+    MinMax,
+    /// Explicit window in rescaled units (e.g. HU for CT).
+    Explicit {
+        /// Window center.
+        center: f64,
+        /// Window width.
+        width: f64,
+    },
+}
+
+impl FromStr for Window {
+    type Err = String;
+
+    /// Parse `auto`, `minmax`, or `CENTER,WIDTH` (e.g. `40,400`).
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(Window::Auto),
+            "minmax" => Ok(Window::MinMax),
+            other => {
+                let (center, width) = other
+                    .split_once(',')
+                    .ok_or_else(|| format!("expected auto, minmax, or CENTER,WIDTH: {}", s))?;
+                let center: f64 = center.trim().parse().map_err(|e| format!("center: {}", e))?;
+                let width: f64 = width.trim().parse().map_err(|e| format!("width: {}", e))?;
+                if width < 1.0 {
+                    return Err(format!("window width must be at least 1, got {}", width));
+                }
+                Ok(Window::Explicit { center, width })
+            }
+        }
+    }
+}
+
+/// Output bit depth of a preview.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewDepth {
+    /// 8 bits per sample.
+    Eight,
+    /// 16 bits per sample.
+    Sixteen,
+}
+
+impl PreviewDepth {
+    /// Maximum output sample value.
+    fn max_value(self) -> f64 {
+        match self {
+            PreviewDepth::Eight => u8::MAX as f64,
+            PreviewDepth::Sixteen => u16::MAX as f64,
+        }
+    }
+}
+
+/// Options for rendering a preview.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PreviewOptions {
+    /// Zero-based frame index.
+    pub frame: u32,
+
+    /// VOI window.
+    pub window: Window,
+
+    /// Downsample so neither dimension exceeds this many pixels.
+    pub max_size: Option<u32>,
+
+    /// Filter used when downsampling.
+    pub filter: ResampleFilter,
+}
+
+/// Grayscale value transform (modality LUT plus VOI window).
+#[derive(Debug, Clone, Copy)]
+struct Transform {
+    slope: f64,
+    intercept: f64,
+    /// VOI window; `None` stretches the frame's value range.
+    window: Option<WindowLevel>,
+    invert: bool,
+}
+
+/// Render a frame of a DICOM file and write it to `path`.
+///
+/// # Errors
+///
+/// Returns `InvalidFormat` for unsupported output extensions, and an error
+/// if the frame does not exist or the pixel data cannot be decoded.
+pub fn export_preview(dicom: &DicomFile, path: &Path, options: &PreviewOptions) -> Result<()> {
+    let (format, depth) = output_format(path)?;
+    let image = render_preview(dicom, depth, options)?;
+
+    image
+        .save_with_format(path, format)
+        .map_err(|e| MedImgError::Storage(format!("Failed to write {}: {}", path.display(), e)))
+}
+
+/// Render a frame of a DICOM file as an 8-bit PNG in memory.
+///
+/// Used where there is no file system, e.g. the browser decoder.
+pub fn encode_png(dicom: &DicomFile, options: &PreviewOptions) -> Result<Vec<u8>> {
+    let image = render_preview(dicom, PreviewDepth::Eight, options)?;
+    let mut png = Cursor::new(Vec::new());
+    image
+        .write_to(&mut png, ImageFormat::Png)
+        .map_err(|e| MedImgError::Storage(format!("Failed to encode PNG: {}", e)))?;
+    Ok(png.into_inner())
+}
+
+/// Render a frame of a DICOM file at the given output depth.
+pub fn render_preview(
+    dicom: &DicomFile,
+    depth: PreviewDepth,
+    options: &PreviewOptions,
+) -> Result<DynamicImage> {
+    let (mut image, index) = if dicom.is_video() {
+        // Only the requested frame is decoded from video
+        let mut frames = FrameExtractor::new().extract(dicom, &[options.frame])?;
+        (frames.remove(0), 0)
+    } else {
+        (dicom.decode_image_data()?, options.frame)
+    };
+    if image.photometric() == Some(Photometric::YbrFull422) {
+        // Upsample native chroma so frames have the full-resolution layout
+        image = image.convert_photometric(Photometric::YbrFull)?;
+    }
+    let mut frame = image.frame_data(index)?;
+    if let Some(max_size) = options.max_size {
+        let factor = frame.width.max(frame.height).div_ceil(max_size.max(1));
+        if factor > 1 && frame.samples_per_pixel > 1 && dicom.metadata.planar_configuration == 1 {
+            return Err(MedImgError::ImageData(
+                "Cannot downsample planar color pixel data".into(),
+            ));
+        }
+        frame = frame.downsample_with(factor.max(1), options.filter)?;
+    }
+
+    let window = match options.window {
+        Window::Auto => dataset_window(dicom),
+        Window::MinMax => None,
+        Window::Explicit { center, width } => Some(WindowLevel::new(center, width)),
+    };
+    let transform = Transform {
+        slope: dataset_f64(dicom, tags::RESCALE_SLOPE).unwrap_or(1.0),
+        intercept: dataset_f64(dicom, tags::RESCALE_INTERCEPT).unwrap_or(0.0),
+        window,
+        invert: frame.photometric() == Some(Photometric::Monochrome1),
+    };
+
+    render_frame(&frame, dicom.metadata.planar_configuration, depth, &transform)
+}
+
+/// Pick the image format and depth from the file extension.
+fn output_format(path: &Path) -> Result<(ImageFormat, PreviewDepth)> {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "png" => Ok((ImageFormat::Png, PreviewDepth::Eight)),
+        "tif" | "tiff" => Ok((ImageFormat::Tiff, PreviewDepth::Sixteen)),
+        _ => Err(MedImgError::InvalidFormat(format!(
+            "Unsupported preview format '{}' (use .png or .tiff)",
+            path.display()
+        ))),
+    }
+}
+
+/// Read the first window from Window Center/Width and VOI LUT Function,
+/// if present and valid.
+fn dataset_window(dicom: &DicomFile) -> Option<WindowLevel> {
+    let function = dicom
+        .inner()
+        .element(tags::VOILUT_FUNCTION)
+        .ok()
+        .and_then(|e| e.to_str().ok())
+        .and_then(|value| VoiFunction::from_dicom(&value))
+        .unwrap_or_default();
+    let window = WindowLevel {
+        center: dataset_f64(dicom, tags::WINDOW_CENTER)?,
+        width: dataset_f64(dicom, tags::WINDOW_WIDTH)?,
+        function,
+    };
+    window.validate().is_ok().then_some(window)
+}
+
+/// Read the first numeric value of a dataset element.
+fn dataset_f64(dicom: &DicomFile, tag: dicom::core::Tag) -> Option<f64> {
+    dicom
+        .inner()
+        .element(tag)
+        .ok()?
+        .to_multi_float64()
+        .ok()?
+        .first()
+        .copied()
+}
+
+/// Apply the grayscale transform and scale to the output depth.
+fn render_frame(
+    frame: &ImageData,
+    planar_configuration: u16,
+    depth: PreviewDepth,
+    transform: &Transform,
+) -> Result<DynamicImage> {
+    let (w, h) = (frame.width, frame.height);
+    let out_max = depth.max_value();
+
+    let samples: Vec<f64> = match frame.samples_per_pixel {
+        1 => {
+            let rescaled = frame.apply_rescale(transform.slope, transform.intercept)?;
+            window_grayscale(&rescaled.to_f64(), transform)
+                .into_iter()
+                .map(|v| v * out_max)
+                .collect()
+        }
+        3 => {
+            let values: Vec<f64> = frame.stored_values()?.samples.into_iter().map(|v| v as f64).collect();
+            let interleaved = if planar_configuration == 1 {
+                interleave_planes(&values)
+            } else {
+                values
+            };
+            let in_max = ((1u64 << frame.bits_per_sample) - 1) as f64;
+            let ybr = frame.photometric().is_some_and(Photometric::is_ybr);
+            interleaved
+                .chunks_exact(3)
+                .flat_map(|p| {
+                    let p = [p[0], p[1], p[2]];
+                    let rgb = if ybr { ybr_to_rgb(p, in_max) } else { p };
+                    rgb.map(|v| (v / in_max).clamp(0.0, 1.0) * out_max)
+                })
+                .collect()
+        }
+        n => {
+            return Err(MedImgError::ImageData(format!(
+                "Cannot render {} samples per pixel",
+                n
+            )))
+        }
+    };
+
+    let too_short = || MedImgError::ImageData("Pixel data shorter than frame".into());
+    let image = match (depth, frame.samples_per_pixel) {
+        (PreviewDepth::Eight, 1) => DynamicImage::ImageLuma8(
+            ImageBuffer::<Luma<u8>, _>::from_raw(w, h, quantize(&samples)).ok_or_else(too_short)?,
+        ),
+        (PreviewDepth::Eight, _) => DynamicImage::ImageRgb8(
+            ImageBuffer::<Rgb<u8>, _>::from_raw(w, h, quantize(&samples)).ok_or_else(too_short)?,
+        ),
+        (PreviewDepth::Sixteen, 1) => DynamicImage::ImageLuma16(
+            ImageBuffer::<Luma<u16>, _>::from_raw(w, h, quantize(&samples)).ok_or_else(too_short)?,
+        ),
+        (PreviewDepth::Sixteen, _) => DynamicImage::ImageRgb16(
+            ImageBuffer::<Rgb<u16>, _>::from_raw(w, h, quantize(&samples)).ok_or_else(too_short)?,
+        ),
+    };
+
+    Ok(image)
+}
+
+/// Map rescaled grayscale values to display values in `[0, 1]`.
+fn window_grayscale(rescaled: &[f64], transform: &Transform) -> Vec<f64> {
+    let window = transform
+        .window
+        .unwrap_or_else(|| WindowLevel::min_max(rescaled));
+
+    rescaled
+        .iter()
+        .map(|&v| {
+            let y = window.apply(v);
+            if transform.invert {
+                1.0 - y
+            } else {
+                y
+            }
+        })
+        .collect()
+}
+
+/// Convert planar (RRR...GGG...BBB) samples to interleaved (RGBRGB...).
+fn interleave_planes(values: &[f64]) -> Vec<f64> {
+    let plane = values.len() / 3;
+    (0..plane)
+        .flat_map(|i| [values[i], values[plane + i], values[2 * plane + i]])
+        .collect()
+}
+
+/// Round scaled samples into an integer buffer.
+fn quantize<T: TryFrom<u32> + Default>(samples: &[f64]) -> Vec<T> {
+    samples
+        .iter()
+        .map(|&v| T::try_from(v.round().max(0.0) as u32).unwrap_or_default())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dicom::testing;
+
+    fn transform(window: Option<WindowLevel>, invert: bool) -> Transform {
+        Transform {
+            slope: 1.0,
+            intercept: 0.0,
+            window,
+            invert,
+        }
+    }
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!("auto".parse::<Window>(), Ok(Window::Auto));
+        assert_eq!("MinMax".parse::<Window>(), Ok(Window::MinMax));
+        assert_eq!(
+            "40, 400".parse::<Window>(),
+            Ok(Window::Explicit {
+                center: 40.0,
+                width: 400.0
+            })
+        );
+        assert!("40".parse::<Window>().is_err());
+        assert!("40,0".parse::<Window>().is_err());
+    }
+
+    #[test]
+    fn test_window_grayscale_explicit_and_invert() {
+        let values = [0.0, 100.0, 200.0];
+        let window = Some(WindowLevel::new(100.5, 101.0));
+
+        let display = window_grayscale(&values, &transform(window, false));
+        assert_eq!(display, vec![0.0, 0.5, 1.0]);
+
+        let inverted = window_grayscale(&values, &transform(window, true));
+        assert_eq!(inverted, vec![1.0, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn test_render_frame_out_of_range() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        testing::write_frames(&input, 2, 2, 2, "XA", &[0, 0, 0, 0, 9, 9, 9, 9]);
+        let dicom = DicomFile::open(&input).unwrap();
+
+        let second = PreviewOptions {
+            frame: 1,
+            ..PreviewOptions::default()
+        };
+        assert!(render_preview(&dicom, PreviewDepth::Eight, &second).is_ok());
+        let missing = PreviewOptions {
+            frame: 2,
+            ..PreviewOptions::default()
+        };
+        assert!(render_preview(&dicom, PreviewDepth::Eight, &missing).is_err());
+    }
+
+    #[test]
+    fn test_export_png_and_tiff() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        testing::write_grayscale(&input, 16, 8, "CR", &testing::gradient(16, 8));
+        let dicom = DicomFile::open(&input).unwrap();
+        let options = PreviewOptions::default();
+
+        let png = dir.path().join("out.png");
+        export_preview(&dicom, &png, &options).unwrap();
+        let decoded = image::open(&png).unwrap();
+        assert!(matches!(decoded, DynamicImage::ImageLuma8(_)));
+        assert_eq!((decoded.width(), decoded.height()), (16, 8));
+
+        let icon = PreviewOptions { max_size: Some(5), ..options };
+        export_preview(&dicom, &png, &icon).unwrap();
+        assert_eq!(image::image_dimensions(&png).unwrap(), (4, 2));
+
+        let encoded = encode_png(&dicom, &options).unwrap();
+        let decoded = image::load_from_memory_with_format(&encoded, ImageFormat::Png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (16, 8));
+
+        let tiff = dir.path().join("out.tiff");
+        export_preview(&dicom, &tiff, &options).unwrap();
+        assert!(matches!(image::open(&tiff).unwrap(), DynamicImage::ImageLuma16(_)));
+
+        let bmp = dir.path().join("out.bmp");
+        assert!(matches!(
+            export_preview(&dicom, &bmp, &options),
+            Err(MedImgError::InvalidFormat(_))
+        ));
+    }
+}
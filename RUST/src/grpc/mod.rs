@@ -0,0 +1,544 @@
+//! gRPC compression service (`grpc` feature).
+//!
+//! Serves the `medimg.v1.Compressor` service defined in
+//! `proto/medimg_compress.proto`, so services in other languages can
+//! generate typed clients:
+//!
+//! - `Compress`: streams a DICOM file in, streams progress, the result, and
+//!   the compressed file out
+//! - `Analyze`: same input; streams progress and the result without the
+//!   compressed file
+//! - `Metrics`: streams an original and a compressed file in; streams
+//!   progress and the quality metrics out
+//!
+//! Files are sent as byte chunks so no message exceeds the gRPC message
+//! size limit. As with the HTTP service, per-request options override the
+//! server defaults, and modality safety checks always apply.
+//!
+//! Failures are returned as gRPC statuses, with the library error code in
+//! the `medimg-error-code` metadata entry.
+
+pub mod proto;
+
+use std::io::Cursor;
+use std::net::TcpListener;
+use std::path::Path;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::Stream;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio_stream::wrappers::{TcpListenerStream, UnboundedReceiverStream};
+use tonic::metadata::MetadataValue;
+use tonic::{Code, Request, Response, Status, Streaming};
+
+use crate::config::{CompressionCodec, CompressionConfig, CompressionMode, CompressionOverrides};
+use crate::dicom::DicomFile;
+use crate::error::{MedImgError, Result};
+use crate::metrics::{ImageComparator, QualityReport};
+use crate::pipeline::{BatchStats, CompressionResult, PipelineBuilder};
+use crate::progress::{CancellationToken, ProgressEvent, ProgressHandler};
+use proto::compressor_server::{Compressor, CompressorServer};
+use proto::{
+    compress_response, metrics_request, metrics_response, CompressOptions, CompressRequest,
+    CompressResponse, MetricsRequest, MetricsResponse,
+};
+
+/// Default maximum size of each received file (1 GiB).
+pub const DEFAULT_MAX_INPUT_BYTES: usize = 1 << 30;
+
+/// Size of the chunks the compressed file is returned in.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Interval at which the server checks for cancellation.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Metadata key carrying the library error code of a failed call.
+const ERROR_CODE_KEY: &str = "medimg-error-code";
+
+/// Stream of responses to a call.
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = std::result::Result<T, Status>> + Send>>;
+
+/// gRPC status for a library error.
+fn status_for_error(error: &MedImgError) -> Status {
+    let code = match error {
+        MedImgError::Dicom(_)
+        | MedImgError::DicomSource { .. }
+        | MedImgError::CodecParse(_)
+        | MedImgError::InvalidFormat(_)
+        | MedImgError::ImageData(_)
+        | MedImgError::Config(_)
+        | MedImgError::Validation(_) => Code::InvalidArgument,
+        MedImgError::LimitExceeded(_) => Code::ResourceExhausted,
+        MedImgError::UnsupportedTransferSyntax(_) => Code::Unimplemented,
+        MedImgError::CompressionConstraint(_) | MedImgError::PolicyViolation(_) => Code::FailedPrecondition,
+        MedImgError::Timeout(_) => Code::Unavailable,
+        MedImgError::Cancelled(_) => Code::Cancelled,
+        MedImgError::Context { source, .. } => return with_error_code(status_for_error(source), error),
+        _ => Code::Internal,
+    };
+    with_error_code(Status::new(code, error.to_string()), error)
+}
+
+/// Attach the library error code to a status.
+fn with_error_code(mut status: Status, error: &MedImgError) -> Status {
+    status
+        .metadata_mut()
+        .insert(ERROR_CODE_KEY, MetadataValue::from_static(error.code().as_str()));
+    status
+}
+
+/// Progress message for a pipeline phase.
+fn progress(phase: &str, fraction: f64) -> proto::Progress {
+    proto::Progress {
+        phase: phase.to_string(),
+        fraction,
+    }
+}
+
+/// Forwards pipeline progress to a response stream.
+struct StreamProgress<T> {
+    sender: UnboundedSender<std::result::Result<T, Status>>,
+    wrap: fn(proto::Progress) -> T,
+}
+
+impl<T: Send> ProgressHandler for StreamProgress<T> {
+    fn on_progress(&self, event: &ProgressEvent) {
+        let phase = format!("{:?}", event.phase).to_ascii_lowercase();
+        let _ = self.sender.send(Ok((self.wrap)(progress(&phase, event.file_progress))));
+    }
+
+    fn on_error(&self, _error: &MedImgError, _file: Option<&Path>) {}
+
+    fn on_complete(&self, _stats: &BatchStats) {}
+
+    fn is_cancelled(&self) -> bool {
+        // The client went away
+        self.sender.is_closed()
+    }
+}
+
+/// gRPC compression server.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::grpc::GrpcServer;
+///
+/// let listener = std::net::TcpListener::bind("127.0.0.1:50051")?;
+/// GrpcServer::new(CompressionConfig::default()).serve(listener)?;
+/// ```
+pub struct GrpcServer {
+    /// Default compression settings.
+    config: CompressionConfig,
+
+    /// Largest accepted input file.
+    max_input_bytes: usize,
+
+    /// Cancellation token; stops the server.
+    cancelled: CancellationToken,
+}
+
+impl GrpcServer {
+    /// Create a server with default compression settings.
+    pub fn new(config: CompressionConfig) -> Self {
+        Self {
+            config,
+            max_input_bytes: DEFAULT_MAX_INPUT_BYTES,
+            cancelled: CancellationToken::new(),
+        }
+    }
+
+    /// Set the largest accepted input file.
+    pub fn max_input_bytes(mut self, bytes: usize) -> Self {
+        self.max_input_bytes = bytes;
+        self
+    }
+
+    /// Use a shared cancellation token.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancelled = token;
+        self
+    }
+
+    /// Get a handle to this server's cancellation token.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+
+    /// The tonic service, for mounting on an existing
+    /// `tonic::transport::Server` next to other services.
+    pub fn service(&self) -> CompressorServer<CompressorService> {
+        CompressorServer::new(CompressorService {
+            config: self.config.clone(),
+            max_input_bytes: self.max_input_bytes,
+        })
+    }
+
+    /// Serve requests until cancelled.
+    ///
+    /// Runs its own multi-threaded tokio runtime. On cancellation, no new
+    /// calls are accepted and in-flight calls are finished before
+    /// returning.
+    pub fn serve(&self, listener: TcpListener) -> Result<()> {
+        listener.set_nonblocking(true)?;
+        let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+
+        runtime.block_on(async {
+            let listener = tokio::net::TcpListener::from_std(listener)?;
+            let cancelled = self.cancelled.clone();
+            let shutdown = async move {
+                while !cancelled.is_cancelled() {
+                    tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+                }
+            };
+
+            tonic::transport::Server::builder()
+                .add_service(self.service())
+                .serve_with_incoming_shutdown(TcpListenerStream::new(listener), shutdown)
+                .await
+                .map_err(|e| MedImgError::Internal(format!("gRPC server failed: {}", e)))
+        })
+    }
+}
+
+/// Implementation of the `medimg.v1.Compressor` service.
+///
+/// Created by [`GrpcServer::service`].
+pub struct CompressorService {
+    config: CompressionConfig,
+    max_input_bytes: usize,
+}
+
+impl CompressorService {
+    /// Receive a chunked file, returning the options of the first message.
+    async fn receive_file(
+        &self,
+        mut stream: Streaming<CompressRequest>,
+    ) -> std::result::Result<(Option<CompressOptions>, Vec<u8>), Status> {
+        let mut options = None;
+        let mut data = Vec::new();
+        let mut first = true;
+        while let Some(message) = stream.message().await? {
+            if first {
+                options = message.options;
+                first = false;
+            }
+            self.append(&mut data, &message.chunk)?;
+        }
+        if data.is_empty() {
+            return Err(Status::invalid_argument("No DICOM data received"));
+        }
+        Ok((options, data))
+    }
+
+    /// Append a chunk, enforcing the input size limit.
+    #[allow(clippy::result_large_err)] // `Status` is the service's error type
+    fn append(&self, data: &mut Vec<u8>, chunk: &[u8]) -> std::result::Result<(), Status> {
+        if data.len() + chunk.len() > self.max_input_bytes {
+            return Err(Status::resource_exhausted(format!(
+                "Input exceeds {} bytes",
+                self.max_input_bytes
+            )));
+        }
+        data.extend_from_slice(chunk);
+        Ok(())
+    }
+
+    /// Apply request options to the default settings.
+    fn request_config(&self, options: Option<CompressOptions>) -> Result<CompressionConfig> {
+        let options = options.unwrap_or_default();
+        let invalid = |name: &str, value: i32| {
+            MedImgError::Config(format!("Invalid value {} for '{}'", value, name))
+        };
+
+        let codec = match proto::Codec::try_from(options.codec).map_err(|_| invalid("codec", options.codec))? {
+            proto::Codec::Unspecified => None,
+            proto::Codec::Jpeg2000 => Some(CompressionCodec::Jpeg2000),
+            proto::Codec::JpegLs => Some(CompressionCodec::JpegLs),
+        };
+        let mode = match proto::Mode::try_from(options.mode).map_err(|_| invalid("mode", options.mode))? {
+            proto::Mode::Unspecified => None,
+            proto::Mode::Lossless => Some(CompressionMode::Lossless),
+            proto::Mode::Lossy => Some(CompressionMode::Lossy),
+            proto::Mode::NearLossless => Some(CompressionMode::NearLossless),
+        };
+        let near_lossless_error = options
+            .near
+            .map(|near| {
+                u16::try_from(near).map_err(|_| MedImgError::Config(format!("Invalid value {} for 'near'", near)))
+            })
+            .transpose()?;
+        let overrides = CompressionOverrides {
+            codec,
+            mode,
+            target_ratio: options.ratio,
+            target_bpp: options.bpp,
+            near_lossless_error,
+            ..Default::default()
+        };
+
+        // A mode override drops the server's ratio/NEAR unless given again
+        let mut config = self.config.clone().merge(&overrides).with_mode_defaults();
+        // Safety overrides are a local operator decision, never a remote one
+        config.override_safety_checks = None;
+        Ok(config)
+    }
+
+    /// Run the pipeline on a blocking thread, streaming progress, the
+    /// result, and (unless `dry_run`) the compressed file.
+    fn run(config: CompressionConfig, data: Vec<u8>, dry_run: bool) -> ResponseStream<CompressResponse> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let event = |event| CompressResponse { event: Some(event) };
+
+        tokio::task::spawn_blocking(move || {
+            let handler = StreamProgress {
+                sender: sender.clone(),
+                wrap: |p| CompressResponse {
+                    event: Some(compress_response::Event::Progress(p)),
+                },
+            };
+            handler.on_progress(&ProgressEvent::reading(Path::new("-")));
+
+            let pipeline = PipelineBuilder::new().config(config).dry_run(dry_run).build();
+            let mut output = Vec::new();
+            match pipeline.compress_stream_with_progress(Cursor::new(data), &mut output, &handler) {
+                Ok(result) => {
+                    let _ = sender.send(Ok(event(compress_response::Event::Progress(progress("complete", 1.0)))));
+                    let _ = sender.send(Ok(event(compress_response::Event::Result(result_message(&result)))));
+                    for chunk in output.chunks(CHUNK_SIZE) {
+                        let _ = sender.send(Ok(event(compress_response::Event::Chunk(chunk.to_vec()))));
+                    }
+                }
+                Err(e) => {
+                    let _ = sender.send(Err(status_for_error(&e)));
+                }
+            }
+        });
+
+        Box::pin(UnboundedReceiverStream::new(receiver))
+    }
+}
+
+/// Result message for a pipeline result.
+fn result_message(result: &CompressionResult) -> proto::CompressResult {
+    proto::CompressResult {
+        original_size: result.original_size as u64,
+        compressed_size: result.compressed_size as u64,
+        compression_ratio: result.compression_ratio,
+        lossless: result.is_lossless,
+        codec: result.codec_name.clone(),
+        modality: result.modality.to_string(),
+        compression_time_ms: result.compression_time_ms,
+        pixel_sha256: result.pixel_sha256.clone(),
+        warnings: result.warnings.clone(),
+    }
+}
+
+/// Metrics message for a quality report.
+fn metrics_message(report: &QualityReport) -> proto::QualityMetrics {
+    proto::QualityMetrics {
+        psnr_db: report.psnr.psnr_db,
+        ssim: report.ssim.ssim,
+        max_error: report.max_error,
+        mean_error: report.mean_error,
+        rmse: report.rmse,
+        diff_pixels_percent: report.diff_pixels_percent,
+        lossless: report.is_lossless(),
+        overall_quality: report.overall_quality().to_string(),
+    }
+}
+
+/// Decode and compare an original and a compressed file.
+fn compare(original: &[u8], compressed: &[u8]) -> Result<QualityReport> {
+    let original = DicomFile::from_reader(original)?.decode_image_data()?;
+    let compressed = DicomFile::from_reader(compressed)?.decode_image_data()?;
+    ImageComparator::new().compare(&original, &compressed)
+}
+
+#[tonic::async_trait]
+impl Compressor for CompressorService {
+    type CompressStream = ResponseStream<CompressResponse>;
+    type AnalyzeStream = ResponseStream<CompressResponse>;
+    type MetricsStream = ResponseStream<MetricsResponse>;
+
+    async fn compress(
+        &self,
+        request: Request<Streaming<CompressRequest>>,
+    ) -> std::result::Result<Response<Self::CompressStream>, Status> {
+        let (options, data) = self.receive_file(request.into_inner()).await?;
+        let config = self.request_config(options).map_err(|e| status_for_error(&e))?;
+        Ok(Response::new(Self::run(config, data, false)))
+    }
+
+    async fn analyze(
+        &self,
+        request: Request<Streaming<CompressRequest>>,
+    ) -> std::result::Result<Response<Self::AnalyzeStream>, Status> {
+        let (options, data) = self.receive_file(request.into_inner()).await?;
+        let config = self.request_config(options).map_err(|e| status_for_error(&e))?;
+        Ok(Response::new(Self::run(config, data, true)))
+    }
+
+    async fn metrics(
+        &self,
+        request: Request<Streaming<MetricsRequest>>,
+    ) -> std::result::Result<Response<Self::MetricsStream>, Status> {
+        let mut stream = request.into_inner();
+        let (mut original, mut compressed) = (Vec::new(), Vec::new());
+        while let Some(message) = stream.message().await? {
+            match message.chunk {
+                Some(metrics_request::Chunk::Original(chunk)) => self.append(&mut original, &chunk)?,
+                Some(metrics_request::Chunk::Compressed(chunk)) => self.append(&mut compressed, &chunk)?,
+                None => {}
+            }
+        }
+        if original.is_empty() || compressed.is_empty() {
+            return Err(Status::invalid_argument("Both an original and a compressed file are required"));
+        }
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let event = |event| MetricsResponse { event: Some(event) };
+        tokio::task::spawn_blocking(move || {
+            let _ = sender.send(Ok(event(metrics_response::Event::Progress(progress("reading", 0.0)))));
+            match compare(&original, &compressed) {
+                Ok(report) => {
+                    let _ = sender.send(Ok(event(metrics_response::Event::Progress(progress("complete", 1.0)))));
+                    let _ = sender.send(Ok(event(metrics_response::Event::Metrics(metrics_message(&report)))));
+                }
+                Err(e) => {
+                    let _ = sender.send(Err(status_for_error(&e)));
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(UnboundedReceiverStream::new(receiver))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dicom::testing;
+    use proto::compressor_client::CompressorClient;
+
+    #[test]
+    fn test_compress_analyze_metrics() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let pixels = testing::gradient(16, 8);
+        testing::write_grayscale(&input, 16, 8, "CT", &pixels);
+        let original = std::fs::read(&input).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = GrpcServer::new(CompressionConfig::default());
+        let token = server.cancellation_token();
+
+        std::thread::scope(|scope| {
+            let handle = scope.spawn(|| server.serve(listener));
+            let client = scope.spawn(|| {
+                let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+                runtime.block_on(async {
+                    let mut client = loop {
+                        match CompressorClient::connect(url.clone()).await {
+                            Ok(client) => break client,
+                            Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+                        }
+                    };
+
+                    // Two chunks, with options on the first
+                    let (head, tail) = original.split_at(100);
+                    let requests = vec![
+                        CompressRequest {
+                            options: Some(CompressOptions {
+                                codec: proto::Codec::JpegLs as i32,
+                                ..Default::default()
+                            }),
+                            chunk: head.to_vec(),
+                        },
+                        CompressRequest {
+                            options: None,
+                            chunk: tail.to_vec(),
+                        },
+                    ];
+                    let mut responses = client
+                        .compress(tokio_stream::iter(requests.clone()))
+                        .await
+                        .unwrap()
+                        .into_inner();
+                    let (mut phases, mut result, mut compressed) = (Vec::new(), None, Vec::new());
+                    while let Some(response) = responses.message().await.unwrap() {
+                        match response.event.unwrap() {
+                            compress_response::Event::Progress(p) => phases.push(p.phase),
+                            compress_response::Event::Result(r) => result = Some(r),
+                            compress_response::Event::Chunk(chunk) => compressed.extend(chunk),
+                        }
+                    }
+                    assert_eq!(phases.first().map(String::as_str), Some("reading"));
+                    assert_eq!(phases.last().map(String::as_str), Some("complete"));
+                    let result = result.unwrap();
+                    assert!(result.lossless);
+                    assert!(result.compressed_size > 0);
+                    let decoded = DicomFile::from_reader(compressed.as_slice()).unwrap().decode_image_data().unwrap();
+                    assert_eq!(decoded.pixel_data, pixels);
+
+                    // Analyze returns the result only
+                    let mut responses = client
+                        .analyze(tokio_stream::iter(requests))
+                        .await
+                        .unwrap()
+                        .into_inner();
+                    let mut chunks = 0;
+                    while let Some(response) = responses.message().await.unwrap() {
+                        chunks += matches!(response.event, Some(compress_response::Event::Chunk(_))) as usize;
+                    }
+                    assert_eq!(chunks, 0);
+
+                    let requests = vec![
+                        MetricsRequest {
+                            chunk: Some(metrics_request::Chunk::Original(original.clone())),
+                        },
+                        MetricsRequest {
+                            chunk: Some(metrics_request::Chunk::Compressed(compressed)),
+                        },
+                    ];
+                    let mut responses = client.metrics(tokio_stream::iter(requests)).await.unwrap().into_inner();
+                    let mut metrics = None;
+                    while let Some(response) = responses.message().await.unwrap() {
+                        if let Some(metrics_response::Event::Metrics(m)) = response.event {
+                            metrics = Some(m);
+                        }
+                    }
+                    assert!(metrics.unwrap().lossless);
+
+                    // Errors carry the library error code
+                    let mut responses = client
+                        .compress(tokio_stream::iter(vec![CompressRequest {
+                            options: None,
+                            chunk: b"not a dicom file".to_vec(),
+                        }]))
+                        .await
+                        .unwrap()
+                        .into_inner();
+                    let status = loop {
+                        match responses.message().await {
+                            Ok(response) => assert!(response.is_some(), "expected an error"),
+                            Err(status) => break status,
+                        }
+                    };
+                    assert_eq!(status.code(), Code::InvalidArgument);
+                    assert!(status.metadata().get(ERROR_CODE_KEY).is_some());
+                });
+            });
+
+            // The client's runtime is gone, so its connection is closed and
+            // graceful shutdown can finish
+            let outcome = client.join();
+            token.cancel();
+            handle.join().unwrap().unwrap();
+            if let Err(panic) = outcome {
+                std::panic::resume_unwind(panic);
+            }
+        });
+    }
+}
@@ -0,0 +1,147 @@
+//! Messages and stubs of the `medimg.v1` package.
+//!
+//! The message types mirror proto/medimg_compress.proto field for field;
+//! the client and server stubs are generated by build.rs.
+
+#![allow(missing_docs)]
+
+include!(concat!(env!("OUT_DIR"), "/medimg.v1.Compressor.rs"));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Codec {
+    Unspecified = 0,
+    Jpeg2000 = 1,
+    JpegLs = 2,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Mode {
+    Unspecified = 0,
+    Lossless = 1,
+    Lossy = 2,
+    NearLossless = 3,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CompressOptions {
+    #[prost(enumeration = "Codec", tag = "1")]
+    pub codec: i32,
+    #[prost(enumeration = "Mode", tag = "2")]
+    pub mode: i32,
+    #[prost(float, optional, tag = "3")]
+    pub ratio: Option<f32>,
+    #[prost(float, optional, tag = "4")]
+    pub bpp: Option<f32>,
+    #[prost(uint32, optional, tag = "5")]
+    pub near: Option<u32>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CompressRequest {
+    #[prost(message, optional, tag = "1")]
+    pub options: Option<CompressOptions>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub chunk: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Progress {
+    #[prost(string, tag = "1")]
+    pub phase: String,
+    #[prost(double, tag = "2")]
+    pub fraction: f64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CompressResult {
+    #[prost(uint64, tag = "1")]
+    pub original_size: u64,
+    #[prost(uint64, tag = "2")]
+    pub compressed_size: u64,
+    #[prost(double, tag = "3")]
+    pub compression_ratio: f64,
+    #[prost(bool, tag = "4")]
+    pub lossless: bool,
+    #[prost(string, tag = "5")]
+    pub codec: String,
+    #[prost(string, tag = "6")]
+    pub modality: String,
+    #[prost(uint64, tag = "7")]
+    pub compression_time_ms: u64,
+    #[prost(string, tag = "8")]
+    pub pixel_sha256: String,
+    #[prost(string, repeated, tag = "9")]
+    pub warnings: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CompressResponse {
+    #[prost(oneof = "compress_response::Event", tags = "1, 2, 3")]
+    pub event: Option<compress_response::Event>,
+}
+
+pub mod compress_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "1")]
+        Progress(super::Progress),
+        #[prost(message, tag = "2")]
+        Result(super::CompressResult),
+        #[prost(bytes, tag = "3")]
+        Chunk(Vec<u8>),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MetricsRequest {
+    #[prost(oneof = "metrics_request::Chunk", tags = "1, 2")]
+    pub chunk: Option<metrics_request::Chunk>,
+}
+
+pub mod metrics_request {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Chunk {
+        #[prost(bytes, tag = "1")]
+        Original(Vec<u8>),
+        #[prost(bytes, tag = "2")]
+        Compressed(Vec<u8>),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct QualityMetrics {
+    #[prost(double, tag = "1")]
+    pub psnr_db: f64,
+    #[prost(double, tag = "2")]
+    pub ssim: f64,
+    #[prost(uint64, tag = "3")]
+    pub max_error: u64,
+    #[prost(double, tag = "4")]
+    pub mean_error: f64,
+    #[prost(double, tag = "5")]
+    pub rmse: f64,
+    #[prost(double, tag = "6")]
+    pub diff_pixels_percent: f64,
+    #[prost(bool, tag = "7")]
+    pub lossless: bool,
+    #[prost(string, tag = "8")]
+    pub overall_quality: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MetricsResponse {
+    #[prost(oneof = "metrics_response::Event", tags = "1, 2")]
+    pub event: Option<metrics_response::Event>,
+}
+
+pub mod metrics_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "1")]
+        Progress(super::Progress),
+        #[prost(message, tag = "2")]
+        Metrics(super::QualityMetrics),
+    }
+}
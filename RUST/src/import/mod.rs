@@ -0,0 +1,258 @@
+//! Import of plain images as Secondary Capture DICOM.
+//!
+//! Scanned documents, screenshots, and photographs enter the archive as
+//! Secondary Capture instances ([`SecondaryCapture`]) compressed like any
+//! other image. Inputs are:
+//!
+//! - PNG and TIFF files (other formats the `image` crate reads work too).
+//!   8- and 16-bit grayscale and RGB are kept as they are; alpha channels
+//!   are dropped and other pixel types are converted to 8- or 16-bit RGB.
+//! - Raw pixel files, described by a [`RawLayout`].
+
+use std::path::Path;
+
+use image::{DynamicImage, ImageReader};
+
+use crate::dicom::SecondaryCapture;
+use crate::error::{MedImgError, Result};
+use crate::pipeline::{CompressionPipeline, CompressionResult};
+use crate::pixel::{encode_le, Photometric};
+use crate::ImageData;
+
+/// Layout of a raw pixel file: one frame of interleaved samples, rows top
+/// to bottom, with no header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawLayout {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Bits per sample (1-16); samples of more than 8 bits take 2 bytes.
+    pub bits_per_sample: u16,
+    /// Samples per pixel (1 for grayscale, 3 for RGB).
+    pub samples_per_pixel: u16,
+    /// Whether samples are signed.
+    pub signed: bool,
+    /// Whether 2-byte samples are big-endian.
+    pub big_endian: bool,
+}
+
+impl RawLayout {
+    /// Unsigned little-endian grayscale.
+    pub fn new(width: u32, height: u32, bits_per_sample: u16) -> Self {
+        Self {
+            width,
+            height,
+            bits_per_sample,
+            samples_per_pixel: 1,
+            signed: false,
+            big_endian: false,
+        }
+    }
+
+    /// Image from raw pixel bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidFormat` if the layout is unsupported or `bytes` is
+    /// not exactly one frame.
+    pub fn decode(&self, bytes: &[u8]) -> Result<ImageData> {
+        if self.bits_per_sample == 0 || self.bits_per_sample > 16 {
+            return Err(MedImgError::InvalidFormat(format!(
+                "Raw images have 1-16 bits per sample, got {}",
+                self.bits_per_sample
+            )));
+        }
+        let photometric = match self.samples_per_pixel {
+            1 => Photometric::Monochrome2,
+            3 => Photometric::Rgb,
+            samples => {
+                return Err(MedImgError::InvalidFormat(format!(
+                    "Raw images have 1 or 3 samples per pixel, got {}",
+                    samples
+                )))
+            }
+        };
+        let sample_bytes = if self.bits_per_sample > 8 { 2 } else { 1 };
+        let expected = (self.width as u64)
+            * (self.height as u64)
+            * u64::from(self.samples_per_pixel)
+            * sample_bytes;
+        if bytes.len() as u64 != expected {
+            return Err(MedImgError::InvalidFormat(format!(
+                "{}x{} raw image needs {} bytes, got {}",
+                self.width,
+                self.height,
+                expected,
+                bytes.len()
+            )));
+        }
+        let mut pixels = bytes.to_vec();
+        if self.big_endian && sample_bytes == 2 {
+            for sample in pixels.chunks_exact_mut(2) {
+                sample.swap(0, 1);
+            }
+        }
+        Ok(ImageData {
+            photometric_interpretation: photometric.as_str().into(),
+            is_signed: self.signed,
+            ..ImageData::new(
+                self.width,
+                self.height,
+                self.bits_per_sample,
+                self.samples_per_pixel,
+                pixels,
+            )
+        })
+    }
+}
+
+/// Read a PNG, TIFF, or (with `raw`) raw pixel file.
+///
+/// # Errors
+///
+/// Returns `Io` if the file cannot be read and `InvalidFormat` if it
+/// cannot be decoded.
+pub fn read_image(path: impl AsRef<Path>, raw: Option<&RawLayout>) -> Result<ImageData> {
+    let path = path.as_ref();
+    let bytes = std::fs::read(path).map_err(|e| MedImgError::from(e).with_file(path))?;
+    match raw {
+        Some(layout) => layout.decode(&bytes),
+        None => decode_image(&bytes),
+    }
+    .map_err(|e| e.with_file(path))
+}
+
+/// Decode a PNG or TIFF (format detected from the content).
+///
+/// # Errors
+///
+/// Returns `InvalidFormat` if the format is unknown or the image is
+/// corrupt.
+pub fn decode_image(bytes: &[u8]) -> Result<ImageData> {
+    let image = ImageReader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()?
+        .decode()
+        .map_err(|e| MedImgError::InvalidFormat(format!("Cannot decode image: {}", e)))?;
+    Ok(from_dynamic(image))
+}
+
+/// Wrap `image` in a Secondary Capture instance built by `capture`,
+/// compress it with `pipeline`, and write it to `output`.
+///
+/// `source` names the original file in the result.
+///
+/// # Errors
+///
+/// Returns the errors of [`SecondaryCapture::build`] and of compression.
+pub fn import_image(
+    image: &ImageData,
+    capture: &SecondaryCapture,
+    pipeline: &CompressionPipeline,
+    source: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+) -> Result<CompressionResult> {
+    let dicom = capture.build(image, 1)?;
+    pipeline.compress_dicom_to(dicom, source, output)
+}
+
+/// Grayscale or RGB image of 8 or 16 bits from any decoded image.
+fn from_dynamic(image: DynamicImage) -> ImageData {
+    let (width, height) = (image.width(), image.height());
+    let color = image.color();
+    let deep = color.bytes_per_pixel() / color.channel_count() > 1;
+    let (samples, bits, pixels) = match (color.has_color(), deep) {
+        (false, false) => (1, 8, image.into_luma8().into_raw()),
+        (false, true) => (1, 16, encode_le(&image.into_luma16().into_raw())),
+        (true, false) => (3, 8, image.into_rgb8().into_raw()),
+        (true, true) => (3, 16, encode_le(&image.into_rgb16().into_raw())),
+    };
+    let photometric = if samples == 3 { Photometric::Rgb } else { Photometric::Monochrome2 };
+    ImageData {
+        photometric_interpretation: photometric.as_str().into(),
+        ..ImageData::new(width, height, bits, samples, pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{ImageBuffer, ImageFormat, Luma, Rgba};
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::config::{CompressionCodec, CompressionConfig};
+    use crate::dicom::{DicomFile, SECONDARY_CAPTURE};
+
+    fn encode(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, format).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_decode_image() {
+        // 16-bit TIFF keeps its depth
+        let gray = ImageBuffer::from_raw(2, 2, vec![0u16, 1000, 40000, 65535]).unwrap();
+        let tiff = encode(DynamicImage::ImageLuma16(gray), ImageFormat::Tiff);
+        let image = decode_image(&tiff).unwrap();
+        assert_eq!((image.bits_per_sample, image.samples_per_pixel), (16, 1));
+        assert_eq!(image.pixel_data, encode_le(&[0u16, 1000, 40000, 65535]));
+
+        // Alpha is dropped from RGBA screenshots
+        let rgba = ImageBuffer::from_pixel(3, 1, Rgba([10u8, 20, 30, 128]));
+        let png = encode(DynamicImage::ImageRgba8(rgba), ImageFormat::Png);
+        let image = decode_image(&png).unwrap();
+        assert_eq!((image.bits_per_sample, image.samples_per_pixel), (8, 3));
+        assert_eq!(image.photometric_interpretation, "RGB");
+        assert_eq!(image.pixel_data, [10, 20, 30].repeat(3));
+
+        assert!(matches!(decode_image(b"not an image"), Err(MedImgError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_raw_layout() {
+        let layout = RawLayout {
+            signed: true,
+            big_endian: true,
+            ..RawLayout::new(2, 1, 12)
+        };
+        let image = layout.decode(&[0xFF, 0x38, 0x01, 0x00]).unwrap();
+        assert!(image.is_signed);
+        assert_eq!(image.pixel_data, encode_le(&[-200i16, 256]));
+        assert!(matches!(layout.decode(&[0; 3]), Err(MedImgError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_import_image() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("scan.png");
+        let page = ImageBuffer::from_fn(16, 8, |x, y| Luma([(x * 16 + y) as u8]));
+        DynamicImage::ImageLuma8(page).save(&input).unwrap();
+
+        let capture = SecondaryCapture::new()
+            .patient("MRN-42", "DOE^JANE")
+            .patient_birth_date("19800101")
+            .patient_sex("F")
+            .accession_number("ACC-7")
+            .study_description("Referral letter")
+            .conversion_type("SD");
+        let pipeline = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::JpegLs));
+        let output = dir.path().join("scan.dcm");
+        let image = read_image(&input, None).unwrap();
+        let result = import_image(&image, &capture, &pipeline, &input, &output).unwrap();
+        assert!(result.compressed_size > 0);
+
+        let written = DicomFile::open(&output).unwrap();
+        assert_eq!(written.metadata.sop_class_uid.as_deref(), Some(SECONDARY_CAPTURE));
+        assert_eq!(written.metadata.patient_id.as_deref(), Some("MRN-42"));
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, image.pixel_data);
+
+        let malformed = capture.patient_birth_date("1 Jan 1980");
+        assert!(matches!(
+            import_image(&image, &malformed, &pipeline, &input, &output),
+            Err(MedImgError::Config(_))
+        ));
+    }
+}
@@ -10,6 +10,9 @@
 //! - **DICOM Compliance**: Full support for DICOM transfer syntaxes
 //! - **Regulatory Aware**: Enforces FDA/ACR guidelines for modality-specific requirements
 //! - **Memory Safe**: Built in Rust for reliability and security
+//! - **WebAssembly**: Decode and PNG preview compile to `wasm32-unknown-unknown`
+//!   for browser viewers (the `wasm` module); the CLI, batch processing, and
+//!   server are native-only
 //!
 //! # Quick Start
 //!
@@ -34,37 +37,110 @@
 //! - **Mammography (MG)**: Only lossless compression is allowed (FDA requirement)
 //! - **Other modalities**: Both lossless and lossy compression are available
 //!
+//! Rules come from the configured [`config::RegulatoryProfile`] (FDA-US by
+//! default; RCR-UK and ESR add maximum lossy ratios per modality) plus any
+//! site-specific lossless modalities.
+//!
 //! To override safety checks (not recommended for production):
 //!
 //! ```rust,ignore
 //! let mut config = CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0);
-//! config.override_safety_checks = true;
+//! config.override_safety_checks = Some(OverrideReason::new("research export, IRB-1234", "jdoe"));
 //! ```
+//!
+//! Each override is logged under the `medimg::audit` target with the file,
+//! reason, and operator, and reported in the result's warnings.
 
 #![warn(missing_docs)]
 #![warn(clippy::all)]
 
+pub mod anonymize;
+pub mod audit;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod batch;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(all(feature = "catalog", not(target_arch = "wasm32")))]
+pub mod catalog;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod cli;
 pub mod codec;
 pub mod config;
 pub mod dicom;
 pub mod error;
+pub mod export;
+pub mod import;
+#[cfg(all(feature = "grpc", not(target_arch = "wasm32")))]
+pub mod grpc;
+pub mod memory;
 pub mod metrics;
+#[cfg(all(feature = "prometheus", not(target_arch = "wasm32")))]
+pub mod monitoring;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod net;
+#[cfg(all(feature = "nifti", not(target_arch = "wasm32")))]
+pub mod nifti;
+#[cfg(all(feature = "node", not(target_arch = "wasm32")))]
+pub mod node;
 pub mod pipeline;
+pub mod pixel;
 pub mod progress;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod queue;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod server;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod storage;
+#[cfg(all(feature = "otlp", not(target_arch = "wasm32")))]
+pub mod telemetry;
+pub mod testing;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
 
 // Re-export commonly used types
+#[cfg(not(target_arch = "wasm32"))]
 pub use batch::{BatchJob, BatchProcessor, BatchScheduler, FileDiscovery, JobResult, JobStatus};
 pub use codec::{Codec, CodecFactory, CodecInfo, Jpeg2000Codec, JpegLsCodec};
 pub use config::{CompressionCodec, CompressionConfig, CompressionMode, Modality, QualityPreset};
 pub use dicom::{DicomFile, DicomMetadata};
-pub use error::{MedImgError, Result};
+pub use error::{
+    CodecParseError, ErrorCategory, ErrorCode, ErrorContext, ErrorRecord, MedImgError, Result,
+};
 pub use metrics::{ImageComparator, PsnrResult, QualityReport, SsimConfig, SsimResult};
-pub use pipeline::{BatchStats, CompressionPipeline, CompressionResult, PipelineBuilder};
-pub use progress::{CallbackProgress, ChannelProgress, NullProgress, ProgressEvent, ProgressHandler, ProgressPhase};
+pub use pixel::{PixelBuffer, Rescaled, Sample};
+pub use pipeline::{
+    BatchStats, CompressionPipeline, CompressionResult, PhaseTimings, PipelineBuilder,
+};
+pub use bytes::Bytes;
+pub use progress::{
+    CallbackProgress, CancellationToken, ChannelProgress, FileLogProgress, MultiProgress,
+    NullProgress, ProgressEvent, ProgressHandler, ProgressPhase, ThrottledProgress,
+    TracingProgress,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use progress::TerminalProgress;
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use pixel::Photometric;
 
 /// Image data structure for compression.
+///
+/// Pixel data is reference-counted [`Bytes`], so cloning an image or
+/// taking one of its frames ([`frame_data`](Self::frame_data)) does not
+/// copy the pixels.
+///
+/// Multi-frame images (e.g. a cine loop or an enhanced CT series) store
+/// their frames back to back in `pixel_data`, each
+/// [`frame_size`](Self::frame_size) bytes long. Codecs and metrics work on
+/// one frame at a time; use [`frame`](Self::frame) or
+/// [`frames`](Self::frames) to address them.
+///
+/// Rows are packed unless [`row_stride`](Self::row_stride) is set, which
+/// lets padded buffers and cropped views ([`crop`](Self::crop)) share their
+/// pixels. Codecs and metrics see [`packed`](Self::packed) data; iterate
+/// with [`rows`](Self::rows) to read strided data directly.
 #[derive(Debug, Clone)]
 pub struct ImageData {
     /// Image width in pixels.
@@ -75,14 +151,125 @@ pub struct ImageData {
     pub bits_per_sample: u16,
     /// Samples per pixel (1 for grayscale, 3 for RGB).
     pub samples_per_pixel: u16,
+    /// Number of frames in `pixel_data` (1 for single-frame images).
+    pub number_of_frames: u32,
     /// Raw pixel data.
-    pub pixel_data: Vec<u8>,
+    pub pixel_data: Bytes,
+    /// Bytes from the start of one row to the next in `pixel_data`, at
+    /// least [`row_size`](Self::row_size); `None` if rows are packed.
+    /// Frames start `height` rows apart.
+    pub row_stride: Option<usize>,
     /// Photometric interpretation (e.g., "MONOCHROME2", "RGB").
     pub photometric_interpretation: String,
     /// Whether pixel values are signed.
     pub is_signed: bool,
 }
 
+/// A single frame of an [`ImageData`], borrowing its pixel data.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageDataView<'a> {
+    /// Frame index (0-based).
+    pub index: u32,
+    /// Image width in pixels.
+    pub width: u32,
+    /// Image height in pixels.
+    pub height: u32,
+    /// Bits per sample.
+    pub bits_per_sample: u16,
+    /// Samples per pixel.
+    pub samples_per_pixel: u16,
+    /// The frame's pixel data, from its first row to the end of its last.
+    pub pixel_data: &'a [u8],
+    /// Bytes from the start of one row to the next.
+    pub row_stride: usize,
+    /// Photometric interpretation.
+    pub photometric_interpretation: &'a str,
+    /// Whether pixel values are signed.
+    pub is_signed: bool,
+}
+
+impl<'a> ImageDataView<'a> {
+    /// Iterate over the frame's rows, without padding.
+    pub fn rows(&self) -> impl Iterator<Item = &'a [u8]> {
+        let data = self.pixel_data;
+        let row_size = self.width as usize
+            * self.samples_per_pixel as usize
+            * self.bits_per_sample.div_ceil(8) as usize;
+        let stride = self.row_stride;
+        (0..self.height as usize).map_while(move |y| data.get(y * stride..y * stride + row_size))
+    }
+
+    /// Copy the frame into a single-frame, packed [`ImageData`].
+    pub fn to_image_data(&self) -> ImageData {
+        let mut pixel_data = Vec::with_capacity(self.pixel_data.len());
+        for row in self.rows() {
+            pixel_data.extend_from_slice(row);
+        }
+        ImageData {
+            width: self.width,
+            height: self.height,
+            bits_per_sample: self.bits_per_sample,
+            samples_per_pixel: self.samples_per_pixel,
+            number_of_frames: 1,
+            pixel_data: pixel_data.into(),
+            row_stride: None,
+            photometric_interpretation: self.photometric_interpretation.to_string(),
+            is_signed: self.is_signed,
+        }
+    }
+}
+
+/// A rectangular region of an image, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rect {
+    /// Left edge.
+    pub x: u32,
+    /// Top edge.
+    pub y: u32,
+    /// Width.
+    pub width: u32,
+    /// Height.
+    pub height: u32,
+}
+
+impl Rect {
+    /// A `width` x `height` region at (`x`, `y`).
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    /// Whether the region is non-empty and lies within a `width` x
+    /// `height` image.
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        let fits = |start: u32, len: u32, limit: u32| {
+            len > 0 && start.checked_add(len).is_some_and(|end| end <= limit)
+        };
+        fits(self.x, self.width, width) && fits(self.y, self.height, height)
+    }
+}
+
+impl FromStr for Rect {
+    type Err = String;
+
+    /// Parse `X,Y,WIDTH,HEIGHT` (e.g. `0,0,512,512`).
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<u32>().map_err(|e| format!("{}: {}", v.trim(), e)))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        match values[..] {
+            [x, y, width, height] => Ok(Rect::new(x, y, width, height)),
+            _ => Err(format!("expected X,Y,WIDTH,HEIGHT: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for Rect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{} at ({}, {})", self.width, self.height, self.x, self.y)
+    }
+}
+
 impl ImageData {
     /// Create a new ImageData instance.
     pub fn new(
@@ -90,36 +277,494 @@ impl ImageData {
         height: u32,
         bits_per_sample: u16,
         samples_per_pixel: u16,
-        pixel_data: Vec<u8>,
+        pixel_data: impl Into<Bytes>,
     ) -> Self {
         Self {
             width,
             height,
             bits_per_sample,
             samples_per_pixel,
-            pixel_data,
+            number_of_frames: 1,
+            pixel_data: pixel_data.into(),
+            row_stride: None,
             photometric_interpretation: String::new(),
             is_signed: false,
         }
     }
 
-    /// Calculate the expected size of pixel data in bytes.
+    /// Join decoded single-frame images into one multi-frame image.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` if `frames` is empty or the frames differ in
+    /// size or format.
+    pub fn from_frames(frames: Vec<ImageData>) -> Result<Self> {
+        let first = frames
+            .first()
+            .ok_or_else(|| MedImgError::ImageData("No frames to join".into()))?;
+        if frames.len() == 1 {
+            return Ok(frames.into_iter().next().expect("one frame"));
+        }
+
+        let geometry = |img: &ImageData| {
+            (img.width, img.height, img.bits_per_sample, img.samples_per_pixel, img.frame_size())
+        };
+        let mut pixel_data = Vec::with_capacity(first.frame_size() * frames.len());
+        for (index, frame) in frames.iter().enumerate() {
+            let frame = frame.packed();
+            if geometry(&frame) != geometry(first) || frame.pixel_data.len() != frame.frame_size() {
+                return Err(MedImgError::ImageData(format!(
+                    "Frame {} differs from frame 0: {}x{} {}-bit x{} ({} bytes)",
+                    index,
+                    frame.width,
+                    frame.height,
+                    frame.bits_per_sample,
+                    frame.samples_per_pixel,
+                    frame.pixel_data.len()
+                )));
+            }
+            pixel_data.extend_from_slice(&frame.pixel_data);
+        }
+
+        Ok(ImageData {
+            number_of_frames: frames.len() as u32,
+            pixel_data: pixel_data.into(),
+            row_stride: None,
+            ..first.clone()
+        })
+    }
+
+    /// Size in bytes of one packed row.
+    pub fn row_size(&self) -> usize {
+        let bytes_per_sample = self.bits_per_sample.div_ceil(8) as usize;
+        self.width as usize * self.samples_per_pixel as usize * bytes_per_sample
+    }
+
+    /// Bytes from the start of one row to the next in `pixel_data`.
+    pub fn stride(&self) -> usize {
+        self.row_stride.unwrap_or_else(|| self.row_size())
+    }
+
+    /// Whether rows are stored without padding.
+    pub fn is_packed(&self) -> bool {
+        self.stride() == self.row_size()
+    }
+
+    /// Size in bytes of one packed frame, the stride between frames in
+    /// packed `pixel_data`.
+    pub fn frame_size(&self) -> usize {
+        self.row_size() * self.height as usize
+    }
+
+    /// Calculate the expected size of packed pixel data in bytes, over all
+    /// frames.
     pub fn expected_size(&self) -> usize {
-        let bytes_per_sample = ((self.bits_per_sample + 7) / 8) as usize;
-        self.width as usize
-            * self.height as usize
-            * self.samples_per_pixel as usize
-            * bytes_per_sample
+        self.frame_size() * self.number_of_frames.max(1) as usize
+    }
+
+    /// Bytes `pixel_data` must hold to cover every row: all strides but
+    /// the last, which needs no padding.
+    fn stored_size(&self) -> usize {
+        let rows = self.height as usize * self.number_of_frames.max(1) as usize;
+        match rows {
+            0 => 0,
+            rows => self.stride() * (rows - 1) + self.row_size(),
+        }
+    }
+
+    /// Iterate over the rows of every frame, without padding, stopping at
+    /// the first one the pixel data does not cover.
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        let (stride, row_size) = (self.stride(), self.row_size());
+        let rows = self.height as usize * self.number_of_frames.max(1) as usize;
+        (0..rows).map_while(move |y| self.pixel_data.get(y * stride..y * stride + row_size))
+    }
+
+    /// The image with packed rows, borrowed if it already is.
+    pub fn packed(&self) -> Cow<'_, ImageData> {
+        if self.is_packed() {
+            return Cow::Borrowed(self);
+        }
+        let mut pixel_data = Vec::with_capacity(self.expected_size());
+        for row in self.rows() {
+            pixel_data.extend_from_slice(row);
+        }
+        Cow::Owned(ImageData {
+            pixel_data: pixel_data.into(),
+            row_stride: None,
+            ..self.clone()
+        })
+    }
+
+    /// The pixels of `region`.
+    ///
+    /// Single-frame crops are views sharing this image's pixel data (see
+    /// [`row_stride`](Self::row_stride)); multi-frame images are cropped
+    /// frame by frame into a packed copy.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` for empty regions, regions outside the image, or
+    /// a pixel data size mismatch.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let tissue = image.crop(Rect::new(256, 256, 512, 512))?;
+    /// let encoded = codec.encode(&tissue, &config, None)?;
+    /// ```
+    pub fn crop(&self, region: Rect) -> Result<ImageData> {
+        if !region.fits(self.width, self.height) {
+            return Err(MedImgError::ImageData(format!(
+                "Crop {} is outside the {}x{} image",
+                region, self.width, self.height
+            )));
+        }
+        self.validate()?;
+
+        if self.number_of_frames > 1 {
+            let mut pixel_data = Vec::new();
+            for index in 0..self.number_of_frames {
+                let frame = self.frame_data(index)?.crop(region)?;
+                for row in frame.rows() {
+                    pixel_data.extend_from_slice(row);
+                }
+            }
+            return Ok(ImageData {
+                width: region.width,
+                height: region.height,
+                pixel_data: pixel_data.into(),
+                row_stride: None,
+                ..self.clone()
+            });
+        }
+
+        let cropped = ImageData {
+            width: region.width,
+            height: region.height,
+            row_stride: Some(self.stride()),
+            ..self.clone()
+        };
+        let pixel_size = self.row_size() / self.width as usize;
+        let start = region.y as usize * self.stride() + region.x as usize * pixel_size;
+        Ok(ImageData {
+            pixel_data: self.pixel_data.slice(start..start + cropped.stored_size()),
+            ..cropped
+        })
+    }
+
+    /// Borrow frame `index` (0-based).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` if the frame is out of range or the pixel data
+    /// ends before it.
+    pub fn frame(&self, index: u32) -> Result<ImageDataView<'_>> {
+        let frames = self.number_of_frames.max(1);
+        if index >= frames {
+            return Err(MedImgError::ImageData(format!(
+                "Frame {} out of range (image has {} frames)",
+                index, frames
+            )));
+        }
+
+        let frame_size = match self.height {
+            0 => 0,
+            height => self.stride() * (height as usize - 1) + self.row_size(),
+        };
+        let start = index as usize * self.stride() * self.height as usize;
+        let pixel_data = self.pixel_data.get(start..start + frame_size).ok_or_else(|| {
+            MedImgError::ImageData(format!(
+                "Pixel data too short for frame {} ({} bytes)",
+                index,
+                self.pixel_data.len()
+            ))
+        })?;
+
+        Ok(ImageDataView {
+            index,
+            width: self.width,
+            height: self.height,
+            bits_per_sample: self.bits_per_sample,
+            samples_per_pixel: self.samples_per_pixel,
+            pixel_data,
+            row_stride: self.stride(),
+            photometric_interpretation: &self.photometric_interpretation,
+            is_signed: self.is_signed,
+        })
+    }
+
+    /// Frame `index` as a single-frame image sharing this image's pixel
+    /// data.
+    ///
+    /// # Errors
+    ///
+    /// As for [`frame`](Self::frame).
+    pub fn frame_data(&self, index: u32) -> Result<ImageData> {
+        let view = self.frame(index)?;
+        Ok(ImageData {
+            number_of_frames: 1,
+            pixel_data: self.pixel_data.slice_ref(view.pixel_data),
+            ..self.clone()
+        })
+    }
+
+    /// Iterate over the frames, stopping at the first one the pixel data
+    /// does not cover (see [`validate`](Self::validate)).
+    pub fn frames(&self) -> impl Iterator<Item = ImageDataView<'_>> {
+        (0..self.number_of_frames.max(1)).map_while(|index| self.frame(index).ok())
+    }
+
+    /// Bits per pixel as stored (sample container size times samples per
+    /// pixel), the reference for bits-per-pixel targets.
+    pub fn stored_bits_per_pixel(&self) -> f32 {
+        f32::from(self.bits_per_sample.div_ceil(8) * 8 * self.samples_per_pixel)
+    }
+
+    /// Samples as `u8`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` if samples are wider than 8 bits or the pixel
+    /// data size does not match (see [`validate`](Self::validate)).
+    pub fn as_u8(&self) -> Result<PixelBuffer<u8>> {
+        self.pixel_buffer("u8", 8, |bytes| bytes[0])
+    }
+
+    /// Samples as `u16`, widening 8-bit data. Values are returned as
+    /// stored, including any bits above `bits_per_sample`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` if samples are wider than 16 bits or the pixel
+    /// data size does not match.
+    pub fn as_u16(&self) -> Result<PixelBuffer<u16>> {
+        self.pixel_buffer("u16", 16, |bytes| match *bytes {
+            [low] => u16::from(low),
+            _ => <u16 as Sample>::from_le(bytes),
+        })
+    }
+
+    /// Signed samples as `i16`, sign-extended from `bits_per_sample` (so a
+    /// 12-bit `0xFFF` is -1).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` if the image is unsigned, samples are wider than
+    /// 16 bits, or the pixel data size does not match.
+    pub fn as_i16(&self) -> Result<PixelBuffer<i16>> {
+        if !self.is_signed {
+            return Err(MedImgError::ImageData(
+                "Cannot read unsigned pixel data as i16".into(),
+            ));
+        }
+        let unused = 16 - u32::from(self.bits_per_sample.min(16));
+        self.pixel_buffer("i16", 16, |bytes| {
+            let raw = match *bytes {
+                [low] => u16::from(low),
+                _ => <u16 as Sample>::from_le(bytes),
+            };
+            ((raw << unused) as i16) >> unused
+        })
+    }
+
+    /// Stored values of every sample, masked to `bits_per_sample` and
+    /// sign-extended for signed images.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` if samples are wider than 32 bits or the pixel
+    /// data size does not match.
+    pub fn stored_values(&self) -> Result<PixelBuffer<i64>> {
+        if self.bits_per_sample == 0 || self.bits_per_sample > 32 {
+            return Err(MedImgError::ImageData(format!(
+                "Cannot read {}-bit stored values",
+                self.bits_per_sample
+            )));
+        }
+        self.validate()?;
+
+        let bits = u32::from(self.bits_per_sample);
+        let bytes = bits.div_ceil(8) as usize;
+        let unused = 64 - bits;
+        let samples = self
+            .rows()
+            .flat_map(|row| row.chunks_exact(bytes))
+            .map(|chunk| {
+                let raw = chunk
+                    .iter()
+                    .rev()
+                    .fold(0u64, |acc, &b| acc << 8 | u64::from(b));
+                if self.is_signed {
+                    ((raw << unused) as i64) >> unused
+                } else {
+                    ((raw << unused) >> unused) as i64
+                }
+            })
+            .collect();
+
+        Ok(PixelBuffer {
+            width: self.width,
+            height: self.height,
+            samples_per_pixel: self.samples_per_pixel,
+            number_of_frames: self.number_of_frames.max(1),
+            samples,
+        })
     }
 
-    /// Validate that pixel data size matches expected size.
+    /// Apply a modality rescale (`value * slope + intercept`, e.g. to
+    /// Hounsfield Units) to the stored values.
+    ///
+    /// A unit slope with an integral intercept only shifts the values, so
+    /// the result stays exact as [`Rescaled::Shifted`]; any other rescale
+    /// gives [`Rescaled::Float`].
+    ///
+    /// # Errors
+    ///
+    /// As for [`stored_values`](Self::stored_values).
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let meta = &dicom.metadata;
+    /// let hu = image.apply_rescale(meta.rescale_slope, meta.rescale_intercept)?.to_f64();
+    /// ```
+    pub fn apply_rescale(&self, slope: f64, intercept: f64) -> Result<Rescaled> {
+        let stored = self.stored_values()?;
+        let shift = (slope == 1.0 && intercept.fract() == 0.0 && intercept.abs() <= i32::MAX as f64)
+            .then_some(intercept as i64);
+        if let Some(shift) = shift {
+            let shifted: Option<Vec<i32>> = stored
+                .samples
+                .iter()
+                .map(|&v| i32::try_from(v + shift).ok())
+                .collect();
+            if let Some(samples) = shifted {
+                return Ok(Rescaled::Shifted(stored.with_samples(samples)));
+            }
+        }
+
+        let samples = stored
+            .samples
+            .iter()
+            .map(|&v| v as f64 * slope + intercept)
+            .collect();
+        Ok(Rescaled::Float(stored.with_samples(samples)))
+    }
+
+    /// Decode every sample with `decode`, given its container bytes.
+    fn pixel_buffer<T: Sample>(
+        &self,
+        name: &str,
+        max_bits: u16,
+        decode: impl Fn(&[u8]) -> T,
+    ) -> Result<PixelBuffer<T>> {
+        if self.bits_per_sample == 0 || self.bits_per_sample > max_bits {
+            return Err(MedImgError::ImageData(format!(
+                "Cannot read {}-bit samples as {}",
+                self.bits_per_sample, name
+            )));
+        }
+        self.validate()?;
+
+        let bytes = self.bits_per_sample.div_ceil(8) as usize;
+        Ok(PixelBuffer {
+            width: self.width,
+            height: self.height,
+            samples_per_pixel: self.samples_per_pixel,
+            number_of_frames: self.number_of_frames.max(1),
+            samples: self
+                .rows()
+                .flat_map(|row| row.chunks_exact(bytes))
+                .map(decode)
+                .collect(),
+        })
+    }
+
+    /// Largest width or height accepted by [`validate`](Self::validate),
+    /// the limit of DICOM Rows and Columns (US).
+    pub const MAX_DIMENSION: u32 = u16::MAX as u32;
+
+    /// [`expected_size`](Self::expected_size), or `None` if it overflows
+    /// `usize`.
+    pub fn checked_expected_size(&self) -> Option<usize> {
+        let bytes_per_sample = self.bits_per_sample.div_ceil(8) as usize;
+        (self.width as usize)
+            .checked_mul(self.samples_per_pixel as usize)?
+            .checked_mul(bytes_per_sample)?
+            .checked_mul(self.height as usize)?
+            .checked_mul(self.number_of_frames.max(1) as usize)
+    }
+
+    /// Validate the header and that pixel data size matches expected size.
+    ///
+    /// Checks that the dimensions are between 1 and
+    /// [`MAX_DIMENSION`](Self::MAX_DIMENSION), bits per sample is 1-32,
+    /// samples per pixel is 1, 3, or 4, a recognized photometric
+    /// interpretation agrees with the sample count, and the pixel data
+    /// size fits `usize`. Strided data may also hold the last row's
+    /// padding.
     pub fn validate(&self) -> Result<()> {
-        let expected = self.expected_size();
-        if self.pixel_data.len() != expected {
+        let problem = if !(1..=Self::MAX_DIMENSION).contains(&self.width)
+            || !(1..=Self::MAX_DIMENSION).contains(&self.height)
+        {
+            Some(format!(
+                "Dimensions {}x{} are outside 1-{}",
+                self.width,
+                self.height,
+                Self::MAX_DIMENSION
+            ))
+        } else if !(1..=32).contains(&self.bits_per_sample) {
+            Some(format!("Bits per sample {} is outside 1-32", self.bits_per_sample))
+        } else if ![1, 3, 4].contains(&self.samples_per_pixel) {
+            Some(format!(
+                "Samples per pixel {} is not 1, 3, or 4",
+                self.samples_per_pixel
+            ))
+        } else if let Some(expected) = self
+            .photometric()
+            .map(|p| if p.is_monochrome() || p == Photometric::PaletteColor { 1 } else { 3 })
+            .filter(|&samples| samples != self.samples_per_pixel)
+        {
+            Some(format!(
+                "Photometric interpretation {} needs {} samples per pixel, got {}",
+                self.photometric_interpretation.trim(),
+                expected,
+                self.samples_per_pixel
+            ))
+        } else if self.checked_expected_size().is_none()
+            || self.row_stride.is_some_and(|stride| {
+                (self.height as usize)
+                    .checked_mul(self.number_of_frames.max(1) as usize)
+                    .and_then(|rows| rows.checked_mul(stride))
+                    .is_none()
+            })
+        {
+            Some(format!(
+                "Pixel data size overflows: {}x{} x{} samples of {} bits, {} frames",
+                self.width, self.height, self.samples_per_pixel, self.bits_per_sample, self.number_of_frames
+            ))
+        } else {
+            None
+        };
+        if let Some(problem) = problem {
+            return Err(MedImgError::ImageData(problem));
+        }
+
+        if self.stride() < self.row_size() {
+            return Err(MedImgError::ImageData(format!(
+                "Row stride {} is shorter than a row ({} bytes)",
+                self.stride(),
+                self.row_size()
+            )));
+        }
+        let expected = self.stored_size();
+        let padding = self.stride() - self.row_size();
+        let len = self.pixel_data.len();
+        if len < expected || len > expected + padding {
             return Err(MedImgError::ImageData(format!(
                 "Pixel data size mismatch: expected {} bytes, got {}",
-                expected,
-                self.pixel_data.len()
+                expected, len
             )));
         }
         Ok(())
@@ -143,6 +788,7 @@ pub mod version {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::OverrideReason;
 
     #[test]
     fn test_image_data_expected_size() {
@@ -150,6 +796,65 @@ mod tests {
         assert_eq!(image.expected_size(), 512 * 512 * 2);
     }
 
+    #[test]
+    fn test_validate_header() {
+        let image = ImageData::new(2, 2, 8, 1, vec![0; 4]);
+        assert!(image.validate().is_ok());
+        let invalid = [
+            ImageData { width: 0, ..image.clone() },
+            ImageData { height: ImageData::MAX_DIMENSION + 1, ..image.clone() },
+            ImageData { bits_per_sample: 33, ..image.clone() },
+            ImageData { samples_per_pixel: 2, ..image.clone() },
+            ImageData { photometric_interpretation: "RGB".into(), ..image.clone() },
+        ];
+        for image in &invalid {
+            assert!(image.validate().is_err(), "{:?}", image);
+        }
+
+        let mut rgb = ImageData::new(1, 1, 8, 3, vec![0; 3]);
+        rgb.photometric_interpretation = "YBR_FULL".into();
+        assert!(rgb.validate().is_ok());
+        rgb.photometric_interpretation = "MONOCHROME2".into();
+        assert!(rgb.validate().is_err());
+
+        // Sizes that do not fit usize are rejected rather than wrapping
+        let huge = ImageData {
+            number_of_frames: u32::MAX,
+            ..ImageData::new(65_535, 65_535, 32, 4, Vec::new())
+        };
+        assert_eq!(huge.checked_expected_size(), None);
+        assert!(huge.validate().is_err());
+    }
+
+    #[test]
+    fn test_image_data_frames() {
+        let pixels: Vec<u8> = (0..3 * 16).map(|i| i as u8).collect();
+        let image = ImageData {
+            number_of_frames: 3,
+            ..ImageData::new(4, 4, 8, 1, pixels)
+        };
+        assert_eq!(image.frame_size(), 16);
+        assert_eq!(image.expected_size(), 48);
+        assert!(image.validate().is_ok());
+
+        let frame = image.frame(2).unwrap();
+        assert_eq!(frame.pixel_data, &image.pixel_data[32..]);
+        assert_eq!(frame.to_image_data().number_of_frames, 1);
+        // Frame images share the buffer
+        let shared = image.frame_data(2).unwrap();
+        assert_eq!(shared.pixel_data.as_ptr(), image.pixel_data[32..].as_ptr());
+        assert_eq!(shared.number_of_frames, 1);
+        assert!(image.frame(3).is_err());
+        assert_eq!(image.frames().count(), 3);
+
+        // Splitting and joining round-trips
+        let joined = ImageData::from_frames(image.frames().map(|f| f.to_image_data()).collect()).unwrap();
+        assert_eq!(joined.number_of_frames, 3);
+        assert_eq!(joined.pixel_data, image.pixel_data);
+        let mismatched = vec![ImageData::new(4, 4, 8, 1, vec![0; 16]), ImageData::new(2, 2, 8, 1, vec![0; 4])];
+        assert!(ImageData::from_frames(mismatched).is_err());
+    }
+
     #[test]
     fn test_image_data_validation() {
         let image = ImageData::new(64, 64, 8, 1, vec![0; 64 * 64]);
@@ -159,6 +864,67 @@ mod tests {
         assert!(bad_image.validate().is_err());
     }
 
+    #[test]
+    fn test_apply_rescale() {
+        // Signed 12-bit values sign-extend; bits above the depth are ignored
+        let mut image = ImageData::new(3, 1, 12, 1, vec![0xFF, 0x0F, 0x05, 0x00, 0x00, 0xF8]);
+        image.is_signed = true;
+        assert_eq!(image.stored_values().unwrap().samples, vec![-1, 5, -2048]);
+
+        // CT: unit slope and integral intercept shift exactly to HU
+        let ct = ImageData::new(2, 1, 16, 1, vec![0x00, 0x00, 0x18, 0x04]);
+        let hu = ct.apply_rescale(1.0, -1024.0).unwrap();
+        assert!(matches!(&hu, Rescaled::Shifted(buffer) if buffer.samples == vec![-1024, 24]));
+        assert_eq!(hu.to_f64(), vec![-1024.0, 24.0]);
+
+        let scaled = ct.apply_rescale(0.5, -10.5).unwrap();
+        assert!(matches!(scaled, Rescaled::Float(_)));
+        assert_eq!(scaled.to_f64(), vec![-10.5, 513.5]);
+
+        assert!(ImageData::new(1, 1, 64, 1, vec![0; 8]).apply_rescale(1.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_row_stride_and_crop() {
+        // 3x2 rows padded to 4 bytes; the last row's padding is optional
+        let padded = ImageData {
+            row_stride: Some(4),
+            ..ImageData::new(3, 2, 8, 1, vec![1, 2, 3, 0, 4, 5, 6, 0])
+        };
+        assert!(padded.validate().is_ok());
+        assert!(!padded.is_packed());
+        assert_eq!(padded.rows().collect::<Vec<_>>(), vec![&[1, 2, 3][..], &[4, 5, 6][..]]);
+        assert_eq!(padded.packed().pixel_data, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(padded.as_u8().unwrap().samples, vec![1, 2, 3, 4, 5, 6]);
+        let trimmed = ImageData { pixel_data: padded.pixel_data.slice(..7), ..padded.clone() };
+        assert!(trimmed.validate().is_ok());
+        assert!(ImageData { row_stride: Some(2), ..padded.clone() }.validate().is_err());
+
+        // Crops share the buffer and pack on demand
+        let image = ImageData::new(4, 3, 8, 1, (0..12).collect::<Vec<u8>>());
+        let crop = image.crop(Rect::new(1, 1, 2, 2)).unwrap();
+        assert_eq!(crop.pixel_data.as_ptr(), image.pixel_data[5..].as_ptr());
+        assert!(crop.validate().is_ok());
+        assert_eq!(crop.packed().pixel_data, vec![5, 6, 9, 10]);
+        assert_eq!(crop.frame(0).unwrap().to_image_data().pixel_data, vec![5, 6, 9, 10]);
+        assert!(image.crop(Rect::new(3, 0, 2, 1)).is_err());
+        assert!(image.crop(Rect::new(0, 0, 0, 1)).is_err());
+        assert_eq!(crop.crop(Rect::new(1, 0, 1, 2)).unwrap().packed().pixel_data, vec![6, 10]);
+
+        // Multi-frame crops are copied frame by frame
+        let frames = ImageData { number_of_frames: 2, ..ImageData::new(2, 2, 8, 1, (0..8).collect::<Vec<u8>>()) };
+        let column = frames.crop(Rect::new(1, 0, 1, 2)).unwrap();
+        assert_eq!((column.number_of_frames, column.pixel_data.to_vec()), (2, vec![1, 3, 5, 7]));
+        assert_eq!("1, 2,3,4".parse::<Rect>(), Ok(Rect::new(1, 2, 3, 4)));
+        assert!("1,2,3".parse::<Rect>().is_err());
+
+        // Codecs encode the packed pixels
+        let codec = CodecFactory::create(CompressionCodec::JpegLs);
+        let config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+        let encoded = codec.encode(&crop, &config, None).unwrap();
+        assert_eq!(codec.decode(&encoded, 2, 2, 8, 1).unwrap().pixel_data, vec![5, 6, 9, 10]);
+    }
+
     #[test]
     fn test_modality_detection() {
         assert_eq!(Modality::from_dicom_string("CT"), Modality::CT);
@@ -168,13 +934,241 @@ mod tests {
         assert!(!Modality::CT.requires_lossless());
     }
 
+    #[test]
+    fn test_custom_modality_codes() {
+        assert_eq!(Modality::from_dicom_string("oct"), Modality::OCT);
+        assert_eq!(Modality::from_dicom_string(" XA "), Modality::XA);
+        assert_eq!(Modality::from_dicom_string("ot"), Modality::Other);
+        assert_eq!(Modality::from_dicom_string(""), Modality::Other);
+
+        let custom = Modality::from_dicom_string("ivus");
+        assert_eq!(custom, Modality::Custom("IVUS".into()));
+        assert_eq!(custom.to_string(), "IVUS");
+
+        // Serialized as the DICOM code, including custom and legacy values
+        assert_eq!(serde_json::to_string(&custom).unwrap(), "\"IVUS\"");
+        assert_eq!(serde_json::to_string(&Modality::ES).unwrap(), "\"ES\"");
+        let parsed: Vec<Modality> = serde_json::from_str(r#"["CT", "IVUS", "Other"]"#).unwrap();
+        assert_eq!(parsed, [Modality::CT, custom, Modality::Other]);
+    }
+
     #[test]
     fn test_compression_config_validation() {
         let config = CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0);
-        assert!(config.validate_for_modality(Modality::MG).is_err());
-        assert!(config.validate_for_modality(Modality::CT).is_ok());
+        assert!(config.validate_for_modality(&Modality::MG).is_err());
+        assert!(config.validate_for_modality(&Modality::CT).is_ok());
 
         let lossless = CompressionConfig::lossless(CompressionCodec::Jpeg2000);
-        assert!(lossless.validate_for_modality(Modality::MG).is_ok());
+        assert!(lossless.validate_for_modality(&Modality::MG).is_ok());
+    }
+
+    #[test]
+    fn test_safety_override_records_reason() {
+        let config = CompressionConfig {
+            override_safety_checks: Some(OverrideReason::new("teaching file", "dr-lee")),
+            ..CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0)
+        };
+        let warning = config.validate_for_modality(&Modality::MG).unwrap().unwrap();
+        assert!(warning.contains("MG"));
+        assert!(warning.contains("reason: teaching file; operator: dr-lee"));
+        assert_eq!(config.validate_for_modality(&Modality::CT).unwrap(), None);
+        assert!(config.validate().is_ok());
+
+        let unjustified = CompressionConfig {
+            override_safety_checks: Some(OverrideReason::new(" ", "dr-lee")),
+            ..config
+        };
+        let problems = unjustified.validate().unwrap_err();
+        assert!(problems.iter().any(|p| p.contains("non-empty reason")), "{:?}", problems);
+    }
+
+    #[test]
+    fn test_codec_option_validation() {
+        let mut config = CompressionConfig::lossless(CompressionCodec::JpegLs);
+        assert!(config.validate_codec_options().is_ok());
+
+        config.decomposition_levels = 33;
+        assert!(config.validate_codec_options().is_err());
+
+        config.decomposition_levels = 5;
+        config.near_lossless_error = 3;
+        config.jpegls_thresholds = Some(config::JpegLsThresholds::default());
+        // T1 = 3 is not above NEAR = 3
+        assert!(config.validate_codec_options().is_err());
+    }
+
+    #[test]
+    fn test_site_policy_lossless_modalities() {
+        let config = CompressionConfig {
+            lossless_modalities: vec![Modality::CT],
+            ..CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0)
+        };
+        let err = config.validate_for_modality(&Modality::CT).unwrap_err();
+        assert!(matches!(err, MedImgError::PolicyViolation(_)));
+        assert!(err.to_string().contains("site policy"));
+        assert!(config.validate_for_modality(&Modality::US).is_ok());
+    }
+
+    #[test]
+    fn test_per_modality_keeps_safety_settings() {
+        let mg = CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0);
+        let config = CompressionConfig {
+            lossless_modalities: vec![Modality::CT],
+            ..CompressionConfig::lossless(CompressionCodec::JpegLs)
+        }
+        .per_modality([(Modality::MG, mg)].into_iter().collect());
+
+        let resolved = config.for_modality(&Modality::MG);
+        assert_eq!(resolved.codec, CompressionCodec::Jpeg2000);
+        assert_eq!(resolved.lossless_modalities, vec![Modality::CT]);
+        assert!(resolved.validate_for_modality(&Modality::MG).is_err());
+        assert_eq!(config.for_modality(&Modality::US).codec, CompressionCodec::JpegLs);
+    }
+
+    #[test]
+    fn test_config_validate_lists_problems() {
+        assert!(CompressionConfig::lossless(CompressionCodec::Jpeg2000).validate().is_ok());
+        assert!(CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0).validate().is_ok());
+
+        let config = CompressionConfig {
+            mode: CompressionMode::NearLossless,
+            near_lossless_error: 2,
+            target_ratio: Some(10.0),
+            ..CompressionConfig::lossless(CompressionCodec::Jpeg2000)
+        };
+        let problems = config.validate().unwrap_err();
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("JPEG-LS"));
+        assert!(problems[1].contains("target_ratio"));
+
+        let nested = CompressionConfig::default().per_modality(
+            [(Modality::US, CompressionConfig { near_lossless_error: 2, ..Default::default() })]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(
+            nested.validate().unwrap_err(),
+            ["US: near_lossless_error=2 only applies to near-lossless mode (mode is Lossless)"]
+        );
+    }
+
+    #[test]
+    fn test_lossless_sop_classes() {
+        use config::sop_class;
+
+        let config = CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0);
+        // Mammography SOP classes are lossless-only whatever the Modality tag says
+        let err = config
+            .validate_for_image(&Modality::Other, Some(sop_class::DIGITAL_MAMMOGRAPHY_FOR_PRESENTATION))
+            .unwrap_err();
+        assert!(matches!(err, MedImgError::PolicyViolation(_)));
+        assert!(err.to_string().contains("Digital Mammography X-Ray Image Storage - For Presentation"));
+        assert_eq!(config.validate_for_image(&Modality::CT, Some(sop_class::CT_IMAGE)).unwrap(), None);
+
+        let site = CompressionConfig {
+            lossless_sop_classes: vec!["1.2.840.10008.5.1.4.1.1.1.1".into()],
+            ..config
+        };
+        let err = site
+            .validate_for_image(&Modality::DX, Some("1.2.840.10008.5.1.4.1.1.1.1"))
+            .unwrap_err();
+        assert!(err.to_string().contains("(site policy)"), "{}", err);
+    }
+
+    #[test]
+    fn test_near_for_bit_depth() {
+        let near = |near_lossless_error, near_lossless_rescaled| CompressionConfig {
+            mode: CompressionMode::NearLossless,
+            near_lossless_error,
+            near_lossless_rescaled,
+            ..CompressionConfig::lossless(CompressionCodec::JpegLs)
+        };
+
+        // Tolerances wider than one byte suit 16-bit data but not JPEG-LS
+        assert!(near(300, None).validate().is_err());
+        assert_eq!(near(300, None).resolve_near(16, 1.0).unwrap().near_lossless_error, 300);
+
+        // At most half the range of the stored samples
+        assert!(near(127, None).resolve_near(8, 1.0).is_ok());
+        let err = near(128, None).resolve_near(8, 1.0).unwrap_err();
+        assert!(err.contains("half the range of 8-bit samples"), "{}", err);
+
+        // Rescaled tolerances round down to stored values
+        let hu = near(0, Some(5.0));
+        assert!(hu.validate().is_ok());
+        let resolved = hu.clone().resolve_near(12, 2.0).unwrap();
+        assert_eq!((resolved.near_lossless_error, resolved.near_lossless_rescaled), (2, None));
+        assert!(hu.resolve_near(12, 10.0).unwrap_err().contains("below one stored value"));
+        // A small Rescale Slope can push the stored tolerance past NEAR's range
+        let err = near(0, Some(200.0)).resolve_near(16, 0.5).unwrap_err();
+        assert!(err.contains("is 400 stored values (Rescale Slope 0.5)"), "{}", err);
+        assert!(err.contains("at most 255"), "{}", err);
+    }
+
+    #[test]
+    fn test_target_bpp() {
+        let config = CompressionConfig {
+            target_bpp: Some(2.0),
+            ..CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0)
+        };
+        let problems = config.validate().unwrap_err();
+        assert!(problems[0].contains("mutually exclusive"), "{:?}", problems);
+
+        let config = CompressionConfig { target_ratio: None, ..config };
+        assert!(config.validate().is_ok());
+        assert_eq!(config.effective_target_ratio(), None);
+        assert_eq!(config.target_ratio_for(16.0), Some(8.0));
+
+        // The equivalent ratio is subject to the ratio caps
+        let resolved = CompressionConfig {
+            regulatory_profile: config::RegulatoryProfile::Esr,
+            ..config.with_bits_per_pixel(16.0)
+        };
+        assert_eq!((resolved.target_ratio, resolved.target_bpp), (Some(8.0), None));
+        assert!(resolved.validate_for_modality(&Modality::MR).is_err());
+
+        let image = ImageData::new(4, 4, 12, 1, vec![0; 32]);
+        assert_eq!(image.stored_bits_per_pixel(), 16.0);
+    }
+
+    #[test]
+    fn test_regulatory_profile_ratio_caps() {
+        let config = CompressionConfig {
+            regulatory_profile: config::RegulatoryProfile::Esr,
+            ..CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0)
+        };
+        let err = config.validate_for_modality(&Modality::MR).unwrap_err();
+        assert!(matches!(err, MedImgError::CompressionConstraint(_)));
+        assert!(err.to_string().contains("at most 7:1 lossy compression (ESR profile)"), "{}", err);
+        assert_eq!(config.validate_for_modality(&Modality::CR).unwrap(), None);
+
+        // The default profile has no ratio caps
+        let fda = CompressionConfig::lossy(CompressionCodec::Jpeg2000, 50.0);
+        assert_eq!(fda.validate_for_modality(&Modality::MR).unwrap(), None);
+    }
+
+    #[test]
+    fn test_site_ratio_caps() {
+        let config = CompressionConfig {
+            regulatory_profile: config::RegulatoryProfile::Esr,
+            max_ratios: [(Modality::CT, 10.0), (Modality::CR, 5.0)].into_iter().collect(),
+            ..CompressionConfig::lossy(CompressionCodec::Jpeg2000, 50.0)
+        };
+        // The lower of the profile and site caps applies
+        assert_eq!(config.max_ratio(&Modality::CT), Some(8.0));
+        assert_eq!(config.max_ratio(&Modality::CR), Some(5.0));
+        assert_eq!(config.max_ratio(&Modality::US), None);
+
+        let err = config.validate_for_modality(&Modality::CR).unwrap_err();
+        assert!(matches!(err, MedImgError::CompressionConstraint(_)));
+        assert!(err.to_string().contains("at most 5:1 lossy compression (site policy)"));
+        assert_eq!(config.validate_for_modality(&Modality::US).unwrap(), None);
+
+        let overridden = CompressionConfig {
+            override_safety_checks: Some(OverrideReason::new("archive migration", "pacs-admin")),
+            ..config
+        };
+        let warning = overridden.validate_for_modality(&Modality::CT).unwrap().unwrap();
+        assert!(warning.contains("reason: archive migration"));
     }
 }
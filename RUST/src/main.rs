@@ -3,18 +3,33 @@
 //! A command-line utility for compressing DICOM medical images using
 //! JPEG 2000 and JPEG-LS codecs.
 
+#[cfg(not(target_arch = "wasm32"))]
 use clap::Parser;
-use medimg_compress::cli::{run, Cli};
+#[cfg(not(target_arch = "wasm32"))]
+use medimg_compress::cli::{error_causes, run, Cli, ExitStatus};
+#[cfg(not(target_arch = "wasm32"))]
 use std::process::ExitCode;
 
+#[cfg(not(target_arch = "wasm32"))]
 fn main() -> ExitCode {
     let cli = Cli::parse();
+    let verbose = cli.verbose;
 
     match run(cli) {
-        Ok(()) => ExitCode::SUCCESS,
+        Ok(status) => status.into(),
         Err(e) => {
-            eprintln!("Error: {}", e);
-            ExitCode::FAILURE
+            eprintln!("Error [{}]: {}", e.code(), e);
+            if verbose {
+                for cause in error_causes(&e) {
+                    eprintln!("  caused by: {}", cause);
+                }
+            }
+            ExitStatus::for_error(&e).into()
         }
     }
 }
+
+/// The CLI is not available on wasm32; the library exposes the decoder
+/// through `medimg_compress::wasm` instead.
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -0,0 +1,229 @@
+//! Process-wide accounting of pixel buffers.
+//!
+//! Batch workers, server requests, and queue consumers each hold a decoded
+//! image, its codestream, and (when verifying) a second decode at once. On
+//! a shared host, a few large studies arriving together can add up to more
+//! memory than the machine has. The [`MemoryAccountant`] tracks what every
+//! running job has reserved and, once a ceiling is set, makes new jobs wait
+//! until their reservation fits under it.
+//!
+//! Jobs are admitted in the order they asked, so a large study is not
+//! starved by a stream of small ones. A job larger than the ceiling on its
+//! own is admitted when nothing else is running rather than never.
+//!
+//! ```rust,ignore
+//! MemoryAccountant::global().set_ceiling(Some(8 << 30));
+//!
+//! let _reservation = MemoryAccountant::global().reserve(working_set);
+//! // ... decode and encode; released when dropped
+//! ```
+
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+/// Reservations and waiting jobs under a ceiling.
+#[derive(Debug)]
+pub struct MemoryAccountant {
+    state: Mutex<State>,
+    released: Condvar,
+}
+
+#[derive(Debug)]
+struct State {
+    /// Ceiling on reserved bytes (`None` admits everything at once).
+    ceiling: Option<u64>,
+    /// Bytes held by admitted jobs.
+    reserved: u64,
+    /// Ticket handed to the next job that asks.
+    next_ticket: u64,
+    /// Ticket of the job admitted next.
+    serving: u64,
+}
+
+impl State {
+    /// Whether `bytes` can be reserved now.
+    fn fits(&self, bytes: u64) -> bool {
+        match self.ceiling {
+            None => true,
+            Some(ceiling) => self.reserved == 0 || self.reserved.saturating_add(bytes) <= ceiling,
+        }
+    }
+}
+
+/// The process-wide accountant.
+static GLOBAL: MemoryAccountant = MemoryAccountant::new();
+
+impl MemoryAccountant {
+    /// An accountant without a ceiling.
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                ceiling: None,
+                reserved: 0,
+                next_ticket: 0,
+                serving: 0,
+            }),
+            released: Condvar::new(),
+        }
+    }
+
+    /// The accountant shared by every pipeline in the process.
+    pub fn global() -> &'static MemoryAccountant {
+        &GLOBAL
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Set the ceiling on reserved bytes; `None` removes it. Waiting jobs
+    /// are re-checked against the new ceiling.
+    pub fn set_ceiling(&self, ceiling: Option<u64>) {
+        self.state().ceiling = ceiling;
+        self.released.notify_all();
+    }
+
+    /// The ceiling on reserved bytes.
+    pub fn ceiling(&self) -> Option<u64> {
+        self.state().ceiling
+    }
+
+    /// Bytes currently reserved by running jobs.
+    pub fn reserved(&self) -> u64 {
+        self.state().reserved
+    }
+
+    /// Jobs waiting for a reservation.
+    pub fn waiting(&self) -> u64 {
+        let state = self.state();
+        state.next_ticket - state.serving
+    }
+
+    /// Reserve `bytes`, blocking until they fit under the ceiling and every
+    /// job that asked earlier has been admitted.
+    pub fn reserve(&self, bytes: u64) -> Reservation<'_> {
+        let mut state = self.state();
+        let ticket = state.next_ticket;
+        state.next_ticket += 1;
+        if ticket != state.serving || !state.fits(bytes) {
+            log::debug!(
+                "Waiting for {} bytes of memory ({} of {:?} reserved)",
+                bytes,
+                state.reserved,
+                state.ceiling
+            );
+            state = self
+                .released
+                .wait_while(state, |state| ticket != state.serving || !state.fits(bytes))
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        self.admit(state, bytes)
+    }
+
+    /// Reserve `bytes` if they fit now and no job is waiting.
+    pub fn try_reserve(&self, bytes: u64) -> Option<Reservation<'_>> {
+        let mut state = self.state();
+        if state.next_ticket != state.serving || !state.fits(bytes) {
+            return None;
+        }
+        state.next_ticket += 1;
+        Some(self.admit(state, bytes))
+    }
+
+    fn admit(&self, mut state: MutexGuard<'_, State>, bytes: u64) -> Reservation<'_> {
+        if state.ceiling.is_some_and(|ceiling| bytes > ceiling) {
+            log::warn!(
+                "Job needs {} bytes, over the {} byte memory ceiling; running it alone",
+                bytes,
+                state.ceiling.unwrap_or_default()
+            );
+        }
+        state.serving += 1;
+        state.reserved += bytes;
+        drop(state);
+        // The next job in line may fit as well
+        self.released.notify_all();
+        Reservation {
+            accountant: self,
+            bytes,
+        }
+    }
+}
+
+impl Default for MemoryAccountant {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bytes reserved with a [`MemoryAccountant`], released when dropped.
+#[derive(Debug)]
+#[must_use = "the reservation is released when dropped"]
+pub struct Reservation<'a> {
+    accountant: &'a MemoryAccountant,
+    bytes: u64,
+}
+
+impl Reservation<'_> {
+    /// Reserved bytes.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.accountant.state().reserved -= self.bytes;
+        self.accountant.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_ceiling_blocks_until_released() {
+        let accountant = MemoryAccountant::new();
+        accountant.set_ceiling(Some(100));
+        let first = accountant.reserve(60);
+        assert!(accountant.try_reserve(50).is_none());
+        let small = accountant.try_reserve(40).unwrap();
+        assert_eq!(accountant.reserved(), 100);
+
+        let (sender, receiver) = mpsc::channel();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let reservation = accountant.reserve(70);
+                sender.send(reservation.bytes()).unwrap();
+            });
+            assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+            // Queued ahead of anything that would fit now
+            while accountant.waiting() == 0 {
+                std::thread::yield_now();
+            }
+            drop(small);
+            assert!(accountant.try_reserve(10).is_none());
+            drop(first);
+            assert_eq!(receiver.recv().unwrap(), 70);
+        });
+        assert_eq!(accountant.reserved(), 0);
+    }
+
+    #[test]
+    fn test_oversized_job_runs_alone() {
+        let accountant = MemoryAccountant::new();
+        accountant.set_ceiling(Some(100));
+        let large = accountant.try_reserve(500).unwrap();
+        assert!(accountant.try_reserve(1).is_none());
+        drop(large);
+
+        // Without a ceiling everything is admitted
+        accountant.set_ceiling(None);
+        let _a = accountant.reserve(500);
+        let _b = accountant.reserve(500);
+        assert_eq!(accountant.reserved(), 1000);
+    }
+}
@@ -0,0 +1,321 @@
+//! Color-aware quality metrics (CIEDE2000).
+//!
+//! PSNR and SSIM on interleaved samples treat each channel independently and
+//! ignore how color differences are perceived. For color images (dermatology,
+//! pathology), this module converts pixels to CIE L*a*b* and measures the
+//! CIEDE2000 color difference (ΔE00) per pixel.
+//!
+//! - ΔE00 < 1.0: Not perceptible by human eyes
+//! - ΔE00 1-2: Perceptible through close observation
+//! - ΔE00 2-10: Perceptible at a glance
+//! - ΔE00 > 10: Colors are clearly different
+
+use serde::Serialize;
+
+use crate::error::{MedImgError, Result};
+use crate::pixel::ybr_to_rgb;
+use crate::ImageData;
+
+use super::{extract_pixels, max_pixel_value, validate_images};
+
+/// Result of CIEDE2000 color difference calculation.
+#[derive(Debug, Clone, Serialize)]
+pub struct ColorDifferenceResult {
+    /// Mean ΔE00 across all pixels.
+    pub mean_delta_e: f64,
+
+    /// Maximum ΔE00 of any pixel.
+    pub max_delta_e: f64,
+
+    /// 95th-percentile ΔE00.
+    pub p95_delta_e: f64,
+
+    /// Percentage of pixels with a perceptible difference (ΔE00 >= 1.0).
+    pub perceptible_percent: f64,
+}
+
+impl ColorDifferenceResult {
+    /// Check if no pixel has a perceptible color difference.
+    pub fn is_imperceptible(&self) -> bool {
+        self.max_delta_e < 1.0
+    }
+
+    /// Get a quality rating based on mean ΔE00.
+    pub fn quality_rating(&self) -> &'static str {
+        if self.max_delta_e == 0.0 {
+            "Identical"
+        } else if self.mean_delta_e < 1.0 {
+            "Imperceptible"
+        } else if self.mean_delta_e < 2.0 {
+            "Close observation"
+        } else if self.mean_delta_e < 10.0 {
+            "Perceptible"
+        } else {
+            "Different colors"
+        }
+    }
+}
+
+impl std::fmt::Display for ColorDifferenceResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "ΔE00: mean {:.3}, p95 {:.3}, max {:.3} ({})",
+            self.mean_delta_e,
+            self.p95_delta_e,
+            self.max_delta_e,
+            self.quality_rating()
+        )
+    }
+}
+
+/// Calculate CIEDE2000 color difference between two color images.
+///
+/// Supports interleaved RGB and YBR_FULL images with 3 samples per pixel.
+/// YBR images are converted to RGB before the Lab transform.
+///
+/// # Errors
+///
+/// Returns an error if the images are not 3-sample color images or have
+/// different dimensions or formats.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::metrics::calculate_delta_e2000;
+///
+/// let result = calculate_delta_e2000(&original, &compressed)?;
+/// println!("Mean ΔE00: {:.3}", result.mean_delta_e);
+/// ```
+pub fn calculate_delta_e2000(
+    original: &ImageData,
+    compressed: &ImageData,
+) -> Result<ColorDifferenceResult> {
+    validate_images(original, compressed)?;
+
+    if original.samples_per_pixel != 3 {
+        return Err(MedImgError::ImageData(format!(
+            "CIEDE2000 requires 3 samples per pixel, got {}",
+            original.samples_per_pixel
+        )));
+    }
+
+    let max_value = max_pixel_value(original.bits_per_sample);
+    let is_ybr = original.photometric_interpretation.starts_with("YBR");
+
+    let original_pixels = extract_pixels(original);
+    let compressed_pixels = extract_pixels(compressed);
+
+    let mut deltas: Vec<f64> = original_pixels
+        .chunks_exact(3)
+        .zip(compressed_pixels.chunks_exact(3))
+        .map(|(o, c)| {
+            let lab1 = to_lab(o, max_value, is_ybr);
+            let lab2 = to_lab(c, max_value, is_ybr);
+            delta_e2000(lab1, lab2)
+        })
+        .collect();
+
+    if deltas.is_empty() {
+        return Ok(ColorDifferenceResult {
+            mean_delta_e: 0.0,
+            max_delta_e: 0.0,
+            p95_delta_e: 0.0,
+            perceptible_percent: 0.0,
+        });
+    }
+
+    let n = deltas.len();
+    let mean_delta_e = deltas.iter().sum::<f64>() / n as f64;
+    let perceptible = deltas.iter().filter(|&&d| d >= 1.0).count();
+
+    deltas.sort_by(|a, b| a.total_cmp(b));
+    let p95_rank = ((n as f64) * 0.95).ceil().max(1.0) as usize;
+
+    Ok(ColorDifferenceResult {
+        mean_delta_e,
+        max_delta_e: deltas[n - 1],
+        p95_delta_e: deltas[p95_rank - 1],
+        perceptible_percent: perceptible as f64 / n as f64 * 100.0,
+    })
+}
+
+/// Convert a pixel (RGB or YBR_FULL) to CIE L*a*b* (D65 white point).
+fn to_lab(pixel: &[f64], max_value: f64, is_ybr: bool) -> (f64, f64, f64) {
+    let pixel = [pixel[0], pixel[1], pixel[2]];
+    let [r, g, b] = if is_ybr { ybr_to_rgb(pixel, max_value) } else { pixel };
+
+    // sRGB to linear RGB
+    let linearize = |v: f64| {
+        let v = (v / max_value).clamp(0.0, 1.0);
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linearize(r), linearize(g), linearize(b));
+
+    // Linear RGB to XYZ, normalized to the D65 reference white
+    let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / 0.95047;
+    let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+    let z = (0.0193339 * r + 0.1191920 * g + 0.9503041 * b) / 1.08883;
+
+    let f = |t: f64| {
+        const EPSILON: f64 = 216.0 / 24389.0;
+        const KAPPA: f64 = 24389.0 / 27.0;
+        if t > EPSILON {
+            t.cbrt()
+        } else {
+            (KAPPA * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+/// CIEDE2000 color difference between two Lab colors (kL = kC = kH = 1).
+fn delta_e2000(lab1: (f64, f64, f64), lab2: (f64, f64, f64)) -> f64 {
+    let (l1, a1, b1) = lab1;
+    let (l2, a2, b2) = lab2;
+
+    let c1 = a1.hypot(b1);
+    let c2 = a2.hypot(b2);
+    let c_bar = (c1 + c2) / 2.0;
+
+    let c_bar7 = c_bar.powi(7);
+    let g = 0.5 * (1.0 - (c_bar7 / (c_bar7 + 25f64.powi(7))).sqrt());
+
+    let a1p = (1.0 + g) * a1;
+    let a2p = (1.0 + g) * a2;
+    let c1p = a1p.hypot(b1);
+    let c2p = a2p.hypot(b2);
+
+    let hue = |b: f64, ap: f64| {
+        if b == 0.0 && ap == 0.0 {
+            0.0
+        } else {
+            b.atan2(ap).to_degrees().rem_euclid(360.0)
+        }
+    };
+    let h1p = hue(b1, a1p);
+    let h2p = hue(b2, a2p);
+
+    let dlp = l2 - l1;
+    let dcp = c2p - c1p;
+
+    let dhp = if c1p * c2p == 0.0 {
+        0.0
+    } else if (h2p - h1p).abs() <= 180.0 {
+        h2p - h1p
+    } else if h2p - h1p > 180.0 {
+        h2p - h1p - 360.0
+    } else {
+        h2p - h1p + 360.0
+    };
+    let dhp_big = 2.0 * (c1p * c2p).sqrt() * (dhp / 2.0).to_radians().sin();
+
+    let lp_bar = (l1 + l2) / 2.0;
+    let cp_bar = (c1p + c2p) / 2.0;
+
+    let hp_bar = if c1p * c2p == 0.0 {
+        h1p + h2p
+    } else if (h1p - h2p).abs() <= 180.0 {
+        (h1p + h2p) / 2.0
+    } else if h1p + h2p < 360.0 {
+        (h1p + h2p + 360.0) / 2.0
+    } else {
+        (h1p + h2p - 360.0) / 2.0
+    };
+
+    let t = 1.0 - 0.17 * (hp_bar - 30.0).to_radians().cos()
+        + 0.24 * (2.0 * hp_bar).to_radians().cos()
+        + 0.32 * (3.0 * hp_bar + 6.0).to_radians().cos()
+        - 0.20 * (4.0 * hp_bar - 63.0).to_radians().cos();
+
+    let d_theta = 30.0 * (-((hp_bar - 275.0) / 25.0).powi(2)).exp();
+    let cp_bar7 = cp_bar.powi(7);
+    let rc = 2.0 * (cp_bar7 / (cp_bar7 + 25f64.powi(7))).sqrt();
+    let lp_50 = (lp_bar - 50.0).powi(2);
+    let sl = 1.0 + 0.015 * lp_50 / (20.0 + lp_50).sqrt();
+    let sc = 1.0 + 0.045 * cp_bar;
+    let sh = 1.0 + 0.015 * cp_bar * t;
+    let rt = -(2.0 * d_theta).to_radians().sin() * rc;
+
+    let dl = dlp / sl;
+    let dc = dcp / sc;
+    let dh = dhp_big / sh;
+
+    (dl * dl + dc * dc + dh * dh + rt * dc * dh).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_rgb_image(width: u32, height: u32, rgb: [u8; 3]) -> ImageData {
+        let pixel_data = rgb
+            .iter()
+            .copied()
+            .cycle()
+            .take(width as usize * height as usize * 3)
+            .collect();
+
+        ImageData {
+            width,
+            height,
+            bits_per_sample: 8,
+            samples_per_pixel: 3,
+            number_of_frames: 1,
+            pixel_data,
+            row_stride: None,
+            photometric_interpretation: "RGB".into(),
+            is_signed: false,
+        }
+    }
+
+    #[test]
+    fn test_delta_e2000_reference_pairs() {
+        // Reference data from Sharma, Wu & Dalal (2005)
+        let pairs = [
+            ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+            ((50.0, 3.1571, -77.2803), (50.0, 0.0, -82.7485), 2.8615),
+            ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+            ((60.2574, -34.0099, 36.2677), (60.4626, -34.1751, 39.4387), 1.2644),
+            ((22.7233, 20.0904, -46.6940), (23.0331, 14.9730, -42.5619), 2.0373),
+        ];
+
+        for (lab1, lab2, expected) in pairs {
+            let de = delta_e2000(lab1, lab2);
+            assert!((de - expected).abs() < 1e-4, "expected {}, got {}", expected, de);
+        }
+    }
+
+    #[test]
+    fn test_identical_color_images() {
+        let img = create_rgb_image(8, 8, [200, 120, 80]);
+        let result = calculate_delta_e2000(&img, &img).unwrap();
+
+        assert_eq!(result.max_delta_e, 0.0);
+        assert!(result.is_imperceptible());
+        assert_eq!(result.quality_rating(), "Identical");
+    }
+
+    #[test]
+    fn test_different_color_images() {
+        let img1 = create_rgb_image(8, 8, [200, 120, 80]);
+        let img2 = create_rgb_image(8, 8, [120, 200, 80]);
+        let result = calculate_delta_e2000(&img1, &img2).unwrap();
+
+        assert!(result.mean_delta_e > 10.0);
+        assert!((result.perceptible_percent - 100.0).abs() < 0.001);
+        assert!(!result.is_imperceptible());
+    }
+
+    #[test]
+    fn test_grayscale_rejected() {
+        let img = ImageData::new(8, 8, 8, 1, vec![0; 64]);
+        assert!(calculate_delta_e2000(&img, &img).is_err());
+    }
+}
@@ -1,15 +1,23 @@
 //! Image comparator for comprehensive quality analysis.
 //!
-//! Combines multiple quality metrics (PSNR, SSIM, and error statistics)
-//! into a unified quality report.
+//! Combines multiple quality metrics (PSNR, SSIM, CIEDE2000 for color
+//! images, and error statistics) into a unified quality report.
 
-use crate::error::Result;
-use crate::ImageData;
+use serde::Serialize;
 
-use super::{calculate_psnr, calculate_ssim, extract_pixels, PsnrResult, SsimConfig, SsimResult};
+use crate::error::{MedImgError, Result};
+use crate::{ImageData, Rect};
+
+use super::{
+    calculate_delta_e2000, calculate_psnr, calculate_ssim, extract_pixels, ColorDifferenceResult,
+    PsnrResult, SsimConfig, SsimResult,
+};
 
 /// Comprehensive quality report combining multiple metrics.
-#[derive(Debug, Clone)]
+///
+/// Serializes with PSNR as `null` when the images are identical, since JSON
+/// has no representation for infinity.
+#[derive(Debug, Clone, Serialize)]
 pub struct QualityReport {
     /// PSNR analysis result.
     pub psnr: PsnrResult,
@@ -17,6 +25,9 @@ pub struct QualityReport {
     /// SSIM analysis result.
     pub ssim: SsimResult,
 
+    /// CIEDE2000 color difference (3-sample color images only).
+    pub color: Option<ColorDifferenceResult>,
+
     /// Maximum absolute difference between any two pixels.
     pub max_error: u64,
 
@@ -82,6 +93,9 @@ impl std::fmt::Display for QualityReport {
         writeln!(f)?;
         writeln!(f, "{}", self.psnr)?;
         writeln!(f, "{}", self.ssim)?;
+        if let Some(ref color) = self.color {
+            writeln!(f, "{}", color)?;
+        }
         writeln!(f)?;
         writeln!(f, "Error Statistics:")?;
         writeln!(f, "  Max Error: {}", self.max_error)?;
@@ -143,9 +157,14 @@ impl ImageComparator {
     ///
     /// A `QualityReport` containing all quality metrics.
     ///
+    /// Multi-frame images are compared frame by frame: PSNR and error
+    /// statistics are pooled over all pixels, SSIM and mean ΔE00 are
+    /// averaged over frames, and maxima are taken over frames.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the images have different dimensions or formats.
+    /// Returns an error if the images have different dimensions, formats,
+    /// or numbers of frames.
     ///
     /// # Example
     ///
@@ -157,9 +176,51 @@ impl ImageComparator {
     /// println!("{}", report);
     /// ```
     pub fn compare(&self, original: &ImageData, compressed: &ImageData) -> Result<QualityReport> {
+        if original.number_of_frames != compressed.number_of_frames {
+            return Err(MedImgError::ImageData(format!(
+                "Frame count mismatch: {} vs {}",
+                original.number_of_frames, compressed.number_of_frames
+            )));
+        }
+        if original.number_of_frames > 1 {
+            let reports = (0..original.number_of_frames)
+                .map(|index| {
+                    self.compare_frame(&original.frame_data(index)?, &compressed.frame_data(index)?)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            return Ok(combine_frames(reports));
+        }
+        self.compare_frame(original, compressed)
+    }
+
+    /// Compare `region` of two images, e.g. a lesion or the tissue area of
+    /// a slide, so a large unchanged background does not mask local loss.
+    ///
+    /// # Errors
+    ///
+    /// As for [`compare`](Self::compare), and `ImageData` if the region
+    /// does not fit both images.
+    pub fn compare_region(
+        &self,
+        original: &ImageData,
+        compressed: &ImageData,
+        region: Rect,
+    ) -> Result<QualityReport> {
+        self.compare(&original.crop(region)?, &compressed.crop(region)?)
+    }
+
+    /// Compare two single-frame images.
+    fn compare_frame(&self, original: &ImageData, compressed: &ImageData) -> Result<QualityReport> {
+        let (original, compressed) = (original.packed(), compressed.packed());
+        let (original, compressed) = (&*original, &*compressed);
         // Calculate PSNR and SSIM
         let psnr = calculate_psnr(original, compressed)?;
         let ssim = calculate_ssim(original, compressed, &self.ssim_config)?;
+        let color = if original.samples_per_pixel == 3 {
+            Some(calculate_delta_e2000(original, compressed)?)
+        } else {
+            None
+        };
 
         // Calculate error statistics
         let original_pixels = extract_pixels(original);
@@ -169,6 +230,7 @@ impl ImageComparator {
         Ok(QualityReport {
             psnr,
             ssim,
+            color,
             max_error: error_stats.max_error,
             mean_error: error_stats.mean_error,
             rmse: error_stats.rmse,
@@ -180,16 +242,135 @@ impl ImageComparator {
 
     /// Quick comparison that only calculates PSNR (faster than full comparison).
     pub fn quick_compare(&self, original: &ImageData, compressed: &ImageData) -> Result<PsnrResult> {
-        calculate_psnr(original, compressed)
+        calculate_psnr(&original.packed(), &compressed.packed())
     }
 
     /// Check if two images are identical (lossless comparison).
     pub fn is_identical(&self, original: &ImageData, compressed: &ImageData) -> Result<bool> {
+        let (original, compressed) = (original.packed(), compressed.packed());
+        let (original, compressed) = (&*original, &*compressed);
         if original.pixel_data.len() != compressed.pixel_data.len() {
             return Ok(false);
         }
         Ok(original.pixel_data == compressed.pixel_data)
     }
+
+    /// Require two images to be bit-identical.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MedImgError::VerificationFailed` describing the first
+    /// difference if the geometry, format, or pixel data differ.
+    pub fn verify_identical(&self, original: &ImageData, compressed: &ImageData) -> Result<()> {
+        let geometry = |img: &ImageData| {
+            (img.width, img.height, img.bits_per_sample, img.samples_per_pixel, img.number_of_frames)
+        };
+        if geometry(original) != geometry(compressed) {
+            return Err(MedImgError::VerificationFailed(format!(
+                "image format differs: {}x{} {}-bit x{} ({} frames) vs {}x{} {}-bit x{} ({} frames)",
+                original.width,
+                original.height,
+                original.bits_per_sample,
+                original.samples_per_pixel,
+                original.number_of_frames,
+                compressed.width,
+                compressed.height,
+                compressed.bits_per_sample,
+                compressed.samples_per_pixel,
+                compressed.number_of_frames
+            )));
+        }
+
+        let (original, compressed) = (original.packed(), compressed.packed());
+        let (original, compressed) = (&*original, &*compressed);
+        if original.pixel_data.len() != compressed.pixel_data.len() {
+            return Err(MedImgError::VerificationFailed(format!(
+                "pixel data length differs: {} vs {} bytes",
+                original.pixel_data.len(),
+                compressed.pixel_data.len()
+            )));
+        }
+
+        let mut differing = original
+            .pixel_data
+            .iter()
+            .zip(&compressed.pixel_data)
+            .enumerate()
+            .filter(|(_, (a, b))| a != b);
+
+        if let Some((offset, _)) = differing.next() {
+            let frame_size = original.frame_size().max(1);
+            let location = if original.number_of_frames > 1 {
+                format!("byte {} of frame {}", offset % frame_size, offset / frame_size)
+            } else {
+                format!("byte {}", offset)
+            };
+            return Err(MedImgError::VerificationFailed(format!(
+                "pixel data differs at {} ({} of {} bytes differ)",
+                location,
+                differing.count() + 1,
+                original.pixel_data.len()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Pool per-frame reports of equally sized frames into one report.
+fn combine_frames(reports: Vec<QualityReport>) -> QualityReport {
+    let frames = reports.len() as f64;
+    let mean = |f: &dyn Fn(&QualityReport) -> f64| reports.iter().map(f).sum::<f64>() / frames;
+
+    let max_value = reports[0].psnr.max_value;
+    let mse = mean(&|r| r.psnr.mse);
+    let psnr = PsnrResult {
+        psnr_db: if mse == 0.0 {
+            f64::INFINITY
+        } else {
+            10.0 * (max_value * max_value / mse).log10()
+        },
+        mse,
+        max_value,
+        per_component: None,
+    };
+    let ssim = SsimResult {
+        ssim: mean(&|r| r.ssim.ssim),
+        ssim_map: None,
+        map_dimensions: None,
+        per_component: None,
+        luminance: mean(&|r| r.ssim.luminance),
+        contrast: mean(&|r| r.ssim.contrast),
+        structure: mean(&|r| r.ssim.structure),
+    };
+    let color = reports
+        .iter()
+        .map(|r| r.color.clone())
+        .collect::<Option<Vec<_>>>()
+        .map(|colors| ColorDifferenceResult {
+            mean_delta_e: colors.iter().map(|c| c.mean_delta_e).sum::<f64>() / frames,
+            max_delta_e: colors.iter().map(|c| c.max_delta_e).fold(0.0, f64::max),
+            p95_delta_e: colors.iter().map(|c| c.p95_delta_e).fold(0.0, f64::max),
+            perceptible_percent: colors.iter().map(|c| c.perceptible_percent).sum::<f64>() / frames,
+        });
+
+    let diff_pixel_count = reports.iter().map(|r| r.diff_pixel_count).sum();
+    let total_pixels: usize = reports.iter().map(|r| r.total_pixels).sum();
+    QualityReport {
+        psnr,
+        ssim,
+        color,
+        max_error: reports.iter().map(|r| r.max_error).max().unwrap_or(0),
+        mean_error: mean(&|r| r.mean_error),
+        rmse: mean(&|r| r.rmse * r.rmse).sqrt(),
+        diff_pixels_percent: if total_pixels == 0 {
+            0.0
+        } else {
+            diff_pixel_count as f64 / total_pixels as f64 * 100.0
+        },
+        diff_pixel_count,
+        total_pixels,
+    }
 }
 
 /// Error statistics calculated between two images.
@@ -255,7 +436,9 @@ mod tests {
             height,
             bits_per_sample: bits,
             samples_per_pixel: 1,
-            pixel_data: values,
+            number_of_frames: 1,
+            pixel_data: values.into(),
+            row_stride: None,
             photometric_interpretation: "MONOCHROME2".into(),
             is_signed: false,
         }
@@ -296,12 +479,12 @@ mod tests {
 
     #[test]
     fn test_comparator_partial_difference() {
-        let mut data1 = vec![100u8; 64 * 64];
+        let data1 = vec![100u8; 64 * 64];
         let mut data2 = vec![100u8; 64 * 64];
 
         // Make half the pixels different
-        for i in 0..(64 * 32) {
-            data2[i] = 110;
+        for value in data2.iter_mut().take(64 * 32) {
+            *value = 110;
         }
 
         let img1 = create_test_image(64, 64, 8, data1);
@@ -314,6 +497,42 @@ mod tests {
         assert_eq!(report.max_error, 10);
         assert_eq!(report.diff_pixel_count, 64 * 32);
         assert!((report.diff_pixels_percent - 50.0).abs() < 0.001);
+
+        // Regions see only their own pixels
+        let top = comparator.compare_region(&img1, &img2, Rect::new(0, 0, 64, 32)).unwrap();
+        assert_eq!(top.diff_pixel_count, 64 * 32);
+        assert!((top.diff_pixels_percent - 100.0).abs() < 0.001);
+        let bottom = comparator.compare_region(&img1, &img2, Rect::new(8, 32, 16, 16)).unwrap();
+        assert!(bottom.is_lossless());
+        assert!(comparator.compare_region(&img1, &img2, Rect::new(0, 60, 8, 8)).is_err());
+    }
+
+    #[test]
+    fn test_comparator_multiframe() {
+        let original = ImageData {
+            number_of_frames: 2,
+            ..create_test_image(16, 16, 8, vec![100u8; 2 * 256])
+        };
+        // Only the second frame differs
+        let mut data = vec![100u8; 2 * 256];
+        data[256..].fill(104);
+        let compressed = ImageData {
+            pixel_data: data.into(),
+            ..original.clone()
+        };
+
+        let comparator = ImageComparator::new();
+        let report = comparator.compare(&original, &compressed).unwrap();
+        assert_eq!(report.max_error, 4);
+        assert_eq!((report.diff_pixel_count, report.total_pixels), (256, 512));
+        assert!((report.diff_pixels_percent - 50.0).abs() < 0.001);
+        assert!((report.psnr.mse - 8.0).abs() < 1e-9);
+
+        let err = comparator.verify_identical(&original, &compressed).unwrap_err();
+        assert!(err.to_string().contains("byte 0 of frame 1"), "{}", err);
+
+        let single = create_test_image(16, 32, 8, vec![100u8; 512]);
+        assert!(comparator.compare(&original, &single).is_err());
     }
 
     #[test]
@@ -358,6 +577,24 @@ mod tests {
         assert!(display.contains("SSIM:"));
     }
 
+    #[test]
+    fn test_comparator_color_metrics() {
+        let rgb: Vec<u8> = (0..16 * 16 * 3).map(|i| (i % 256) as u8).collect();
+        let mut img1 = create_test_image(16, 16, 8, rgb.clone());
+        img1.samples_per_pixel = 3;
+        let mut img2 = create_test_image(16, 16, 8, rgb);
+        img2.samples_per_pixel = 3;
+
+        let comparator = ImageComparator::new();
+        let report = comparator.compare(&img1, &img2).unwrap();
+        let color = report.color.expect("color metrics for RGB image");
+        assert_eq!(color.max_delta_e, 0.0);
+
+        let gray = create_test_image(16, 16, 8, vec![128u8; 16 * 16]);
+        let report = comparator.compare(&gray, &gray).unwrap();
+        assert!(report.color.is_none());
+    }
+
     #[test]
     fn test_overall_quality_ratings() {
         // Create a lossless report
@@ -368,4 +605,42 @@ mod tests {
         let report = comparator.compare(&img, &img).unwrap();
         assert_eq!(report.overall_quality(), "Lossless (identical)");
     }
+
+    #[test]
+    fn test_verify_identical() {
+        let img1 = create_test_image(8, 8, 8, vec![10u8; 64]);
+        let mut img2 = img1.clone();
+        let comparator = ImageComparator::new();
+
+        assert!(comparator.verify_identical(&img1, &img2).is_ok());
+
+        let mut pixels = img2.pixel_data.to_vec();
+        pixels[5] = 11;
+        pixels[9] = 12;
+        img2.pixel_data = pixels.into();
+        match comparator.verify_identical(&img1, &img2) {
+            Err(MedImgError::VerificationFailed(msg)) => {
+                assert!(msg.contains("byte 5 (2 of 64"), "{}", msg)
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let img3 = create_test_image(4, 16, 8, vec![10u8; 64]);
+        assert!(matches!(
+            comparator.verify_identical(&img1, &img3),
+            Err(MedImgError::VerificationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_report_serializes_to_json() {
+        let img = create_test_image(16, 16, 8, vec![42u8; 16 * 16]);
+        let report = ImageComparator::new().compare(&img, &img).unwrap();
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert!(json["psnr"]["psnr_db"].is_null());
+        assert_eq!(json["ssim"]["ssim"], 1.0);
+        assert_eq!(json["total_pixels"], 256);
+        assert!(json["ssim"].get("ssim_map").is_none());
+    }
 }
@@ -3,6 +3,8 @@
 //! This module provides tools to measure compression quality:
 //! - **PSNR** (Peak Signal-to-Noise Ratio): Measures pixel-level fidelity
 //! - **SSIM** (Structural Similarity Index): Measures perceptual quality
+//! - **CIEDE2000** (ΔE00): Measures perceived color difference for color images
+//! - **Batch statistics**: Aggregates per-file metrics into distributions
 //!
 //! # Example
 //!
@@ -18,11 +20,15 @@
 
 mod psnr;
 mod ssim;
+mod color;
 mod comparator;
+mod stats;
 
 pub use psnr::{calculate_psnr, PsnrResult};
 pub use ssim::{calculate_ssim, SsimConfig, SsimResult};
+pub use color::{calculate_delta_e2000, ColorDifferenceResult};
 pub use comparator::{ImageComparator, QualityReport};
+pub use stats::{FileQuality, QualityStats};
 
 use crate::error::{MedImgError, Result};
 use crate::ImageData;
@@ -68,35 +74,22 @@ pub(crate) fn max_pixel_value(bits_per_sample: u16) -> f64 {
 
 /// Extract pixel values as f64 from raw byte data.
 pub(crate) fn extract_pixels(image: &ImageData) -> Vec<f64> {
-    let bytes_per_sample = ((image.bits_per_sample + 7) / 8) as usize;
-    let num_samples = image.pixel_data.len() / bytes_per_sample;
-    let mut pixels = Vec::with_capacity(num_samples);
-
-    if bytes_per_sample == 1 {
-        for &byte in &image.pixel_data {
-            pixels.push(byte as f64);
-        }
-    } else if bytes_per_sample == 2 {
-        for i in 0..num_samples {
-            let value = u16::from_le_bytes([
-                image.pixel_data[i * 2],
-                image.pixel_data[i * 2 + 1],
-            ]);
-            pixels.push(value as f64);
-        }
-    } else {
-        // For other bit depths, handle as needed
-        for i in 0..num_samples {
-            let start = i * bytes_per_sample;
-            let mut value: u64 = 0;
-            for j in 0..bytes_per_sample {
-                value |= (image.pixel_data[start + j] as u64) << (j * 8);
-            }
-            pixels.push(value as f64);
-        }
+    if let Ok(buffer) = image.as_u16() {
+        return buffer.samples.into_iter().map(f64::from).collect();
     }
 
-    pixels
+    // Wider samples, or sizes the caller has already reported
+    let bytes_per_sample = image.bits_per_sample.div_ceil(8).max(1) as usize;
+    image
+        .rows()
+        .flat_map(|row| row.chunks_exact(bytes_per_sample))
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u64, |acc, (i, &b)| acc | (b as u64) << (8 * i)) as f64
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -104,7 +97,7 @@ mod tests {
     use super::*;
 
     fn create_test_image(width: u32, height: u32, bits: u16, value: u8) -> ImageData {
-        let bytes_per_sample = ((bits + 7) / 8) as usize;
+        let bytes_per_sample = bits.div_ceil(8) as usize;
         let size = width as usize * height as usize * bytes_per_sample;
         let pixel_data = vec![value; size];
 
@@ -113,7 +106,9 @@ mod tests {
             height,
             bits_per_sample: bits,
             samples_per_pixel: 1,
-            pixel_data,
+            number_of_frames: 1,
+            pixel_data: pixel_data.into(),
+            row_stride: None,
             photometric_interpretation: "MONOCHROME2".into(),
             is_signed: false,
         }
@@ -143,32 +138,32 @@ mod tests {
     #[test]
     fn test_extract_pixels_8bit() {
         let mut image = create_test_image(4, 4, 8, 0);
-        for i in 0..16 {
-            image.pixel_data[i] = i as u8;
-        }
+        image.pixel_data = (0..16u8).collect();
         let pixels = extract_pixels(&image);
         assert_eq!(pixels.len(), 16);
-        for i in 0..16 {
-            assert_eq!(pixels[i], i as f64);
+        for (i, &pixel) in pixels.iter().enumerate() {
+            assert_eq!(pixel, i as f64);
         }
     }
 
     #[test]
     fn test_extract_pixels_16bit() {
-        let mut image = ImageData {
+        // Pixel values: 256, 512, 768, 1024
+        let pixel_data: Vec<u8> = [256u16, 512, 768, 1024]
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let image = ImageData {
             width: 2,
             height: 2,
             bits_per_sample: 16,
             samples_per_pixel: 1,
-            pixel_data: vec![0; 8],
+            number_of_frames: 1,
+            pixel_data: pixel_data.into(),
+            row_stride: None,
             photometric_interpretation: "MONOCHROME2".into(),
             is_signed: false,
         };
-        // Set pixel values: 256, 512, 768, 1024
-        image.pixel_data[0..2].copy_from_slice(&256u16.to_le_bytes());
-        image.pixel_data[2..4].copy_from_slice(&512u16.to_le_bytes());
-        image.pixel_data[4..6].copy_from_slice(&768u16.to_le_bytes());
-        image.pixel_data[6..8].copy_from_slice(&1024u16.to_le_bytes());
 
         let pixels = extract_pixels(&image);
         assert_eq!(pixels, vec![256.0, 512.0, 768.0, 1024.0]);
@@ -8,13 +8,15 @@
 //! - Good quality: PSNR 30-40 dB
 //! - Acceptable: PSNR 20-30 dB
 
+use serde::Serialize;
+
 use crate::error::Result;
 use crate::ImageData;
 
 use super::{extract_pixels, max_pixel_value, validate_images};
 
 /// Result of PSNR calculation.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PsnrResult {
     /// PSNR value in decibels (higher = better quality).
     /// Returns f64::INFINITY for identical images (lossless).
@@ -186,7 +188,9 @@ mod tests {
             height,
             bits_per_sample: bits,
             samples_per_pixel: 1,
-            pixel_data: values,
+            number_of_frames: 1,
+            pixel_data: values.into(),
+            row_stride: None,
             photometric_interpretation: "MONOCHROME2".into(),
             is_signed: false,
         }
@@ -244,7 +248,9 @@ mod tests {
             height: 32,
             bits_per_sample: 16,
             samples_per_pixel: 1,
-            pixel_data: data1,
+            number_of_frames: 1,
+            pixel_data: data1.into(),
+            row_stride: None,
             photometric_interpretation: "MONOCHROME2".into(),
             is_signed: false,
         };
@@ -254,7 +260,9 @@ mod tests {
             height: 32,
             bits_per_sample: 16,
             samples_per_pixel: 1,
-            pixel_data: data2,
+            number_of_frames: 1,
+            pixel_data: data2.into(),
+            row_stride: None,
             photometric_interpretation: "MONOCHROME2".into(),
             is_signed: false,
         };
@@ -9,6 +9,8 @@
 //! - SSIM > 0.90: Good quality
 //! - SSIM > 0.80: Acceptable quality
 
+use serde::Serialize;
+
 use crate::error::Result;
 use crate::ImageData;
 
@@ -65,16 +67,18 @@ impl SsimConfig {
 }
 
 /// Result of SSIM calculation.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SsimResult {
     /// SSIM value (0.0 to 1.0, where 1.0 = identical).
     pub ssim: f64,
 
     /// SSIM map showing local similarity across the image.
     /// Only populated if `config.generate_map` is true.
+    #[serde(skip)]
     pub ssim_map: Option<Vec<f64>>,
 
     /// Map dimensions (width, height) if map is generated.
+    #[serde(skip)]
     pub map_dimensions: Option<(usize, usize)>,
 
     /// Per-component SSIM for multi-channel images.
@@ -243,6 +247,7 @@ pub fn calculate_ssim(
 }
 
 /// Compute SSIM components using sliding window approach.
+#[allow(clippy::too_many_arguments)]
 fn compute_ssim_components(
     original: &[f64],
     compressed: &[f64],
@@ -290,6 +295,7 @@ fn compute_ssim_components(
 }
 
 /// Compute SSIM for a single window.
+#[allow(clippy::too_many_arguments)]
 fn compute_window_ssim(
     original: &[f64],
     compressed: &[f64],
@@ -418,7 +424,9 @@ mod tests {
             height,
             bits_per_sample: bits,
             samples_per_pixel: 1,
-            pixel_data: values,
+            number_of_frames: 1,
+            pixel_data: values.into(),
+            row_stride: None,
             photometric_interpretation: "MONOCHROME2".into(),
             is_signed: false,
         }
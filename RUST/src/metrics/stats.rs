@@ -0,0 +1,177 @@
+//! Aggregate quality statistics across a batch.
+//!
+//! Collects per-file quality measurements into distributions so a batch
+//! migration run can be signed off without inspecting every file.
+
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+/// Quality measurement for a single file in a batch.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileQuality {
+    /// Source file path.
+    pub path: PathBuf,
+
+    /// SSIM between original and decoded image.
+    pub ssim: f64,
+
+    /// PSNR in decibels (infinity for identical images).
+    pub psnr_db: f64,
+}
+
+/// Distribution of quality metrics across a batch.
+#[derive(Debug, Clone, Serialize)]
+pub struct QualityStats {
+    /// Number of files with quality measurements.
+    pub files_measured: usize,
+
+    /// Lowest SSIM in the batch.
+    pub min_ssim: f64,
+
+    /// Median SSIM.
+    pub median_ssim: f64,
+
+    /// Mean SSIM.
+    pub mean_ssim: f64,
+
+    /// 95th-percentile SSIM, counted from the worst file: 95% of files
+    /// meet or exceed this value.
+    pub p95_ssim: f64,
+
+    /// Lowest PSNR in the batch (infinity if every file was lossless).
+    pub min_psnr_db: f64,
+
+    /// Number of files that fell below the quality gate.
+    pub below_gate: usize,
+
+    /// Files with the lowest SSIM, worst first.
+    pub worst_offenders: Vec<FileQuality>,
+}
+
+impl QualityStats {
+    /// Default number of files kept in the worst offenders list.
+    pub const DEFAULT_WORST_COUNT: usize = 10;
+
+    /// Aggregate per-file measurements.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Per-file quality measurements
+    /// * `min_ssim` - Quality gate threshold used to count failing files
+    /// * `worst_count` - Number of files to keep in the worst offenders list
+    ///
+    /// Returns `None` if no files were measured.
+    pub fn from_samples(
+        mut samples: Vec<FileQuality>,
+        min_ssim: f64,
+        worst_count: usize,
+    ) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        samples.sort_by(|a, b| a.ssim.total_cmp(&b.ssim));
+
+        let n = samples.len();
+        let ssims: Vec<f64> = samples.iter().map(|s| s.ssim).collect();
+
+        let median_ssim = if n.is_multiple_of(2) {
+            (ssims[n / 2 - 1] + ssims[n / 2]) / 2.0
+        } else {
+            ssims[n / 2]
+        };
+
+        // Nearest-rank percentile from the low end of the distribution
+        let p95_rank = ((n as f64) * 0.05).ceil().max(1.0) as usize;
+        let p95_ssim = ssims[p95_rank - 1];
+
+        let min_psnr_db = samples
+            .iter()
+            .map(|s| s.psnr_db)
+            .fold(f64::INFINITY, f64::min);
+
+        Some(Self {
+            files_measured: n,
+            min_ssim: ssims[0],
+            median_ssim,
+            mean_ssim: ssims.iter().sum::<f64>() / n as f64,
+            p95_ssim,
+            min_psnr_db,
+            below_gate: ssims.iter().filter(|&&s| s < min_ssim).count(),
+            worst_offenders: samples.into_iter().take(worst_count).collect(),
+        })
+    }
+}
+
+impl std::fmt::Display for QualityStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Batch Quality ({} files)", self.files_measured)?;
+        writeln!(f, "  SSIM min: {:.4}", self.min_ssim)?;
+        writeln!(f, "  SSIM p95: {:.4}", self.p95_ssim)?;
+        writeln!(f, "  SSIM median: {:.4}", self.median_ssim)?;
+        writeln!(f, "  SSIM mean: {:.4}", self.mean_ssim)?;
+        if self.min_psnr_db.is_infinite() {
+            writeln!(f, "  PSNR min: Infinity (lossless)")?;
+        } else {
+            writeln!(f, "  PSNR min: {:.2} dB", self.min_psnr_db)?;
+        }
+        writeln!(f, "  Below gate: {}", self.below_gate)?;
+        if !self.worst_offenders.is_empty() {
+            writeln!(f, "  Worst offenders:")?;
+            for file in &self.worst_offenders {
+                writeln!(f, "    {:.4}  {}", file.ssim, file.path.display())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(name: &str, ssim: f64) -> FileQuality {
+        FileQuality {
+            path: PathBuf::from(name),
+            ssim,
+            psnr_db: 40.0 + ssim,
+        }
+    }
+
+    #[test]
+    fn test_quality_stats_empty() {
+        assert!(QualityStats::from_samples(vec![], 0.98, 10).is_none());
+    }
+
+    #[test]
+    fn test_quality_stats_distribution() {
+        let samples: Vec<FileQuality> = (0..20)
+            .map(|i| sample(&format!("{}.dcm", i), 0.90 + i as f64 * 0.005))
+            .collect();
+
+        let stats = QualityStats::from_samples(samples, 0.9475, 3).unwrap();
+
+        assert_eq!(stats.files_measured, 20);
+        assert!((stats.min_ssim - 0.90).abs() < 1e-9);
+        assert!((stats.p95_ssim - 0.90).abs() < 1e-9);
+        assert!((stats.median_ssim - 0.9475).abs() < 1e-9);
+        assert_eq!(stats.below_gate, 10);
+        assert_eq!(stats.worst_offenders.len(), 3);
+        assert_eq!(stats.worst_offenders[0].path, PathBuf::from("0.dcm"));
+    }
+
+    #[test]
+    fn test_quality_stats_lossless_psnr() {
+        let samples = vec![FileQuality {
+            path: PathBuf::from("a.dcm"),
+            ssim: 1.0,
+            psnr_db: f64::INFINITY,
+        }];
+
+        let stats = QualityStats::from_samples(samples, 0.98, 10).unwrap();
+        assert!(stats.min_psnr_db.is_infinite());
+        assert_eq!(stats.below_gate, 0);
+        assert!(stats.to_string().contains("Infinity"));
+    }
+}
@@ -0,0 +1,312 @@
+//! Operational metrics for Prometheus (`prometheus` feature).
+//!
+//! [`OperationalMetrics`] counts what batch runs and the servers do, for
+//! monitoring long-running migrations:
+//!
+//! - `medimg_files_processed_total`: files compressed
+//! - `medimg_failures_total{code}`: failed files, by error code
+//! - `medimg_bytes_in_total` / `medimg_bytes_out_total`: original and
+//!   compressed pixel data sizes
+//! - `medimg_phase_duration_seconds{phase}`: per-phase latency histograms
+//!   (`read`, `encode`, `verify`, `write`)
+//! - `medimg_queue_depth`: files or jobs waiting to be processed
+//!
+//! Metrics are scraped from a `/metrics` endpoint (the HTTP and job
+//! servers add one, or see [`OperationalMetrics::serve`]) or pushed to a
+//! Pushgateway for batch runs that end before a scrape would see them.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use medimg_compress::monitoring::OperationalMetrics;
+//!
+//! let metrics = OperationalMetrics::new();
+//! let stats = BatchProcessor::new(config, progress)
+//!     .metrics(metrics.clone())
+//!     .process_directory(&input_dir)?;
+//! metrics.push("http://pushgateway:9091", "migration")?;
+//! ```
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+
+use crate::error::{MedImgError, Result};
+use crate::pipeline::CompressionResult;
+use crate::progress::CancellationToken;
+
+/// Interval at which the exporter and pusher check for cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Time to wait for a slow scraper before dropping the connection.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Upper bounds of the phase latency buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 12] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Content type of the Prometheus text exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Counters, histograms, and gauges for compression operations.
+///
+/// Cheap to clone; clones share the same metrics.
+#[derive(Clone)]
+pub struct OperationalMetrics {
+    registry: Registry,
+    files_processed: IntCounter,
+    failures: IntCounterVec,
+    bytes_in: IntCounter,
+    bytes_out: IntCounter,
+    phase_duration: HistogramVec,
+    queue_depth: IntGauge,
+}
+
+impl Default for OperationalMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OperationalMetrics {
+    /// Create a set of metrics in its own registry.
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("medimg".into()), None)
+            .expect("registry prefix is valid");
+        let files_processed = IntCounter::new("files_processed_total", "Files compressed")
+            .expect("metric options are valid");
+        let failures = IntCounterVec::new(Opts::new("failures_total", "Failed files by error code"), &["code"])
+            .expect("metric options are valid");
+        let bytes_in = IntCounter::new("bytes_in_total", "Original pixel data bytes of compressed files")
+            .expect("metric options are valid");
+        let bytes_out = IntCounter::new("bytes_out_total", "Compressed pixel data bytes")
+            .expect("metric options are valid");
+        let phase_duration = HistogramVec::new(
+            HistogramOpts::new("phase_duration_seconds", "Time per compression phase")
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            &["phase"],
+        )
+        .expect("metric options are valid");
+        let queue_depth = IntGauge::new("queue_depth", "Files or jobs waiting to be processed")
+            .expect("metric options are valid");
+
+        for collector in [
+            Box::new(files_processed.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(failures.clone()),
+            Box::new(bytes_in.clone()),
+            Box::new(bytes_out.clone()),
+            Box::new(phase_duration.clone()),
+            Box::new(queue_depth.clone()),
+        ] {
+            registry.register(collector).expect("metric names are unique");
+        }
+
+        Self {
+            registry,
+            files_processed,
+            failures,
+            bytes_in,
+            bytes_out,
+            phase_duration,
+            queue_depth,
+        }
+    }
+
+    /// Record a compressed file.
+    pub fn record_success(&self, result: &CompressionResult) {
+        self.files_processed.inc();
+        self.bytes_in.inc_by(result.original_size as u64);
+        self.bytes_out.inc_by(result.compressed_size as u64);
+        let timings = result.timings;
+        for (phase, ms) in [
+            ("read", timings.read_ms),
+            ("encode", timings.encode_ms),
+            ("verify", timings.verify_ms),
+            ("write", timings.write_ms),
+        ] {
+            self.phase_duration.with_label_values(&[phase]).observe(ms as f64 / 1000.0);
+        }
+    }
+
+    /// Record a failed file.
+    pub fn record_failure(&self, error: &MedImgError) {
+        self.failures.with_label_values(&[error.code().as_str()]).inc();
+    }
+
+    /// Record the outcome of compressing a file.
+    pub fn record(&self, outcome: &Result<CompressionResult>) {
+        match outcome {
+            Ok(result) => self.record_success(result),
+            Err(e) => self.record_failure(e),
+        }
+    }
+
+    /// Set the number of files or jobs waiting to be processed.
+    pub fn set_queue_depth(&self, depth: usize) {
+        self.queue_depth.set(depth as i64);
+    }
+
+    /// Render all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        // Encoding only fails for malformed metric families, which the
+        // registry never produces
+        let _ = TextEncoder::new().encode(&self.registry.gather(), &mut buffer);
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+
+    /// Replace this job's metrics on a Pushgateway (`PUT
+    /// {gateway}/metrics/job/{job}`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Storage` if the gateway cannot be reached or rejects the
+    /// push.
+    pub fn push(&self, gateway: &str, job: &str) -> Result<()> {
+        let url = format!("{}/metrics/job/{}", gateway.trim_end_matches('/'), job);
+        ureq::put(&url)
+            .set("Content-Type", CONTENT_TYPE)
+            .send_string(&self.render())
+            .map(|_| ())
+            .map_err(|e| MedImgError::Storage(format!("Push to {} failed: {}", url, e)))
+    }
+
+    /// Push to a Pushgateway every `interval` until `stop` is cancelled,
+    /// then once more so the final values are recorded.
+    ///
+    /// Failed pushes are logged and retried at the next interval.
+    pub fn push_periodically(&self, gateway: &str, job: &str, interval: Duration, stop: &CancellationToken) {
+        let mut last = Instant::now();
+        while !stop.is_cancelled() {
+            std::thread::sleep(POLL_INTERVAL);
+            if last.elapsed() >= interval {
+                if let Err(e) = self.push(gateway, job) {
+                    log::warn!("{}", e);
+                }
+                last = Instant::now();
+            }
+        }
+        if let Err(e) = self.push(gateway, job) {
+            log::warn!("{}", e);
+        }
+    }
+
+    /// Serve `GET /metrics` until `stop` is cancelled.
+    ///
+    /// For modes without an HTTP server of their own, such as batch runs.
+    pub fn serve(&self, listener: TcpListener, stop: &CancellationToken) -> Result<()> {
+        listener.set_nonblocking(true)?;
+        while !stop.is_cancelled() {
+            match listener.accept() {
+                Ok((stream, peer)) => {
+                    if let Err(e) = self.respond(stream) {
+                        log::debug!("Metrics request from {} failed: {}", peer, e);
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => std::thread::sleep(POLL_INTERVAL),
+                Err(e) => log::warn!("Accept failed: {}", e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer one scrape request.
+    fn respond(&self, stream: TcpStream) -> std::io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // Skip the headers; scrapes carry no body
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+
+        let target = request_line.split_whitespace().nth(1).unwrap_or_default();
+        let (status, content_type, body) = if target == "/metrics" || target.starts_with("/metrics?") {
+            ("200 OK", CONTENT_TYPE, self.render())
+        } else {
+            ("404 Not Found", "text/plain", "Not found\n".to_string())
+        };
+        write!(
+            writer,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        )?;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Modality;
+    use crate::pipeline::PhaseTimings;
+    use std::io::Read;
+
+    fn result() -> CompressionResult {
+        CompressionResult {
+            source_path: "a.dcm".into(),
+            output_path: None,
+            modality: Modality::CT,
+            original_size: 1000,
+            compressed_size: 400,
+            output_size: None,
+            compression_ratio: 2.5,
+            compression_time_ms: 30,
+            timings: PhaseTimings {
+                read_ms: 5,
+                encode_ms: 20,
+                verify_ms: 0,
+                write_ms: 5,
+            },
+            is_lossless: true,
+            codec_name: "JPEG-LS".into(),
+            warnings: Vec::new(),
+            quality: None,
+            pixel_sha256: String::new(),
+            sop_instance_uid: None,
+            verified: false,
+        }
+    }
+
+    #[test]
+    fn test_render_and_serve() {
+        let metrics = OperationalMetrics::new();
+        metrics.record(&Ok(result()));
+        metrics.record(&Err(MedImgError::Codec("boom".into())));
+        metrics.set_queue_depth(3);
+
+        let text = metrics.render();
+        assert!(text.contains("medimg_files_processed_total 1"));
+        assert!(text.contains("medimg_bytes_in_total 1000"));
+        assert!(text.contains("medimg_bytes_out_total 400"));
+        assert!(text.contains("medimg_failures_total{code=\"MI-COD-001\"} 1"));
+        assert!(text.contains("medimg_phase_duration_seconds_count{phase=\"encode\"} 1"));
+        assert!(text.contains("medimg_queue_depth 3"));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stop = CancellationToken::new();
+        std::thread::scope(|scope| {
+            let server = scope.spawn(|| metrics.serve(listener, &stop));
+
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: test\r\n\r\n").unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            assert!(response.starts_with("HTTP/1.1 200"));
+            assert!(response.contains("medimg_queue_depth 3"));
+
+            stop.cancel();
+            server.join().unwrap().unwrap();
+        });
+    }
+}
@@ -0,0 +1,243 @@
+//! DIMSE networking (DICOM upper layer, PS3.7 and PS3.8).
+//!
+//! Associations are handled by `dicom::ul`; this module encodes and decodes
+//! the command sets of the C-ECHO and C-STORE services on top of it, for a
+//! sender ([`StoreScu`]) and a compress-on-ingest receiver ([`StoreScp`]).
+
+use dicom::core::{DataElement, PrimitiveValue, VR};
+use dicom::dictionary_std::tags;
+use dicom::object::InMemDicomObject;
+use dicom::transfer_syntax::entries::IMPLICIT_VR_LITTLE_ENDIAN;
+use dicom::ul::pdu::{PDataValue, PDataValueType, Pdu};
+
+use crate::error::{MedImgError, Result};
+
+pub mod scp;
+pub mod scu;
+
+pub use scp::StoreScp;
+pub use scu::{StoreOutcome, StoreScu};
+
+/// Verification SOP Class (C-ECHO).
+pub const VERIFICATION_SOP_CLASS: &str = "1.2.840.10008.1.1";
+
+/// Command Field values (PS3.7 section E.1).
+pub(crate) mod command_field {
+    /// C-STORE-RQ.
+    pub const C_STORE_RQ: u16 = 0x0001;
+    /// C-STORE-RSP.
+    pub const C_STORE_RSP: u16 = 0x8001;
+    /// C-ECHO-RQ.
+    pub const C_ECHO_RQ: u16 = 0x0030;
+    /// C-ECHO-RSP.
+    pub const C_ECHO_RSP: u16 = 0x8030;
+}
+
+/// Command Data Set Type when no data set follows.
+const NO_DATA_SET: u16 = 0x0101;
+
+/// Whether a DIMSE status is Success or Warning (PS3.7 annex C).
+pub fn is_success_status(status: u16) -> bool {
+    status == 0x0000 || status & 0xF000 == 0xB000 || status == 0x0107 || status == 0x0116
+}
+
+/// A DIMSE command set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Command {
+    /// Command Field (see [`command_field`]).
+    pub field: u16,
+    /// Message ID, or Message ID Being Responded To for responses.
+    pub message_id: u16,
+    /// Affected SOP Class UID.
+    pub sop_class_uid: String,
+    /// Affected SOP Instance UID.
+    pub sop_instance_uid: Option<String>,
+    /// Status (responses only).
+    pub status: Option<u16>,
+    /// Whether a data set follows the command.
+    pub has_data_set: bool,
+}
+
+impl Command {
+    /// Whether this is a response.
+    fn is_response(&self) -> bool {
+        self.field & 0x8000 != 0
+    }
+
+    /// Encode in Implicit VR Little Endian, with Command Group Length.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut object = InMemDicomObject::from_element_iter([
+            DataElement::new(tags::AFFECTED_SOP_CLASS_UID, VR::UI, uid(&self.sop_class_uid)),
+            DataElement::new(tags::COMMAND_FIELD, VR::US, PrimitiveValue::from(self.field)),
+            DataElement::new(
+                tags::COMMAND_DATA_SET_TYPE,
+                VR::US,
+                PrimitiveValue::from(if self.has_data_set { 0x0000 } else { NO_DATA_SET }),
+            ),
+        ]);
+        if self.is_response() {
+            object.put(DataElement::new(
+                tags::MESSAGE_ID_BEING_RESPONDED_TO,
+                VR::US,
+                PrimitiveValue::from(self.message_id),
+            ));
+        } else {
+            object.put(DataElement::new(tags::MESSAGE_ID, VR::US, PrimitiveValue::from(self.message_id)));
+        }
+        if self.field == command_field::C_STORE_RQ {
+            // Medium priority
+            object.put(DataElement::new(tags::PRIORITY, VR::US, PrimitiveValue::from(0u16)));
+        }
+        if let Some(instance) = &self.sop_instance_uid {
+            object.put(DataElement::new(tags::AFFECTED_SOP_INSTANCE_UID, VR::UI, uid(instance)));
+        }
+        if let Some(status) = self.status {
+            object.put(DataElement::new(tags::STATUS, VR::US, PrimitiveValue::from(status)));
+        }
+
+        let ts = IMPLICIT_VR_LITTLE_ENDIAN.erased();
+        let mut body = Vec::new();
+        object.write_dataset_with_ts(&mut body, &ts)?;
+        let group_length = InMemDicomObject::from_element_iter([DataElement::new(
+            tags::COMMAND_GROUP_LENGTH,
+            VR::UL,
+            PrimitiveValue::from(body.len() as u32),
+        )]);
+        let mut encoded = Vec::with_capacity(body.len() + 12);
+        group_length.write_dataset_with_ts(&mut encoded, &ts)?;
+        encoded.extend_from_slice(&body);
+        Ok(encoded)
+    }
+
+    /// Decode from Implicit VR Little Endian.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let ts = IMPLICIT_VR_LITTLE_ENDIAN.erased();
+        let object = InMemDicomObject::read_dataset_with_ts(data, &ts)?;
+        let us = |tag| -> Option<u16> { object.element(tag).ok()?.to_int::<u16>().ok() };
+        let string = |tag| -> Option<String> {
+            let value = object.element(tag).ok()?.to_str().ok()?;
+            Some(value.trim_end_matches(['\0', ' ']).to_string())
+        };
+
+        let field = us(tags::COMMAND_FIELD)
+            .ok_or_else(|| MedImgError::Dicom("DIMSE command without Command Field".into()))?;
+        let message_id = if field & 0x8000 != 0 {
+            us(tags::MESSAGE_ID_BEING_RESPONDED_TO)
+        } else {
+            us(tags::MESSAGE_ID)
+        };
+        Ok(Self {
+            field,
+            message_id: message_id.unwrap_or(0),
+            sop_class_uid: string(tags::AFFECTED_SOP_CLASS_UID).unwrap_or_default(),
+            sop_instance_uid: string(tags::AFFECTED_SOP_INSTANCE_UID),
+            status: us(tags::STATUS),
+            has_data_set: us(tags::COMMAND_DATA_SET_TYPE).is_some_and(|t| t != NO_DATA_SET),
+        })
+    }
+
+    /// P-DATA-TF PDU carrying this command on a presentation context.
+    pub fn to_pdu(&self, presentation_context_id: u8) -> Result<Pdu> {
+        Ok(Pdu::PData {
+            data: vec![PDataValue {
+                presentation_context_id,
+                value_type: PDataValueType::Command,
+                is_last: true,
+                data: self.encode()?,
+            }],
+        })
+    }
+}
+
+/// A UID value, padded to even length.
+fn uid(value: &str) -> PrimitiveValue {
+    let mut value = value.to_string();
+    if value.len() % 2 == 1 {
+        value.push('\0');
+    }
+    PrimitiveValue::from(value)
+}
+
+/// Read P-DATA-TF PDUs from `receive` until a complete message fragment of
+/// `value_type` has arrived, returning its presentation context and bytes.
+///
+/// # Errors
+///
+/// Returns `Storage` if the peer aborts or releases the association, or
+/// sends something other than P-DATA.
+pub(crate) fn receive_fragments<E: std::error::Error>(
+    mut receive: impl FnMut() -> std::result::Result<Pdu, E>,
+    value_type: PDataValueType,
+) -> Result<(u8, Vec<u8>)> {
+    let mut data = Vec::new();
+    loop {
+        let pdu = receive().map_err(|e| network_error("Failed to receive DIMSE message", e))?;
+        let values = match pdu {
+            Pdu::PData { data } => data,
+            Pdu::AbortRQ { .. } => return Err(MedImgError::Storage("Association aborted by peer".into())),
+            other => {
+                return Err(MedImgError::Storage(format!(
+                    "Unexpected PDU while waiting for a DIMSE message: {}",
+                    other.short_description()
+                )))
+            }
+        };
+        for value in values {
+            if value.value_type != value_type {
+                return Err(MedImgError::Storage("Unexpected DIMSE message fragment".into()));
+            }
+            data.extend_from_slice(&value.data);
+            if value.is_last {
+                return Ok((value.presentation_context_id, data));
+            }
+        }
+    }
+}
+
+/// Classify a network error, so timeouts are reported as such.
+pub(crate) fn network_error(message: &str, error: impl std::error::Error) -> MedImgError {
+    let message = format!("{}: {}", message, error);
+    let mut source = error.source();
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            if matches!(io.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock) {
+                return MedImgError::Timeout(message);
+            }
+        }
+        source = err.source();
+    }
+    MedImgError::Storage(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_round_trip() {
+        let request = Command {
+            field: command_field::C_STORE_RQ,
+            message_id: 7,
+            sop_class_uid: "1.2.840.10008.5.1.4.1.1.7".into(),
+            sop_instance_uid: Some("1.2.3".into()),
+            status: None,
+            has_data_set: true,
+        };
+        let encoded = request.encode().unwrap();
+        // Command Group Length covers everything after it
+        assert_eq!(&encoded[..8], &[0, 0, 0, 0, 4, 0, 0, 0]);
+        assert_eq!(u32::from_le_bytes(encoded[8..12].try_into().unwrap()) as usize, encoded.len() - 12);
+        assert_eq!(Command::decode(&encoded).unwrap(), request);
+
+        let response = Command {
+            field: command_field::C_ECHO_RSP,
+            message_id: 7,
+            sop_class_uid: VERIFICATION_SOP_CLASS.into(),
+            sop_instance_uid: None,
+            status: Some(0),
+            has_data_set: false,
+        };
+        assert_eq!(Command::decode(&response.encode().unwrap()).unwrap(), response);
+        assert!(is_success_status(0xB000) && !is_success_status(0xA700));
+    }
+}
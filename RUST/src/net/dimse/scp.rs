@@ -0,0 +1,491 @@
+//! C-STORE SCP: a compression gateway.
+//!
+//! Accepts instances from modalities over DIMSE, compresses each one with
+//! the pipeline (policy checks included), and writes the result to an
+//! output directory and/or forwards it with a [`StoreScu`]. The C-STORE
+//! response is sent only after the instance has been written or
+//! forwarded, so the sender keeps its copy until the gateway has one.
+
+use std::collections::VecDeque;
+use std::io::Cursor;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use dicom::object::FileMetaTableBuilder;
+use dicom::ul::association::server::{ServerAssociation, ServerAssociationOptions};
+use dicom::ul::pdu::{PDataValue, PDataValueType, Pdu};
+
+use super::{command_field, network_error, Command, StoreScu, VERIFICATION_SOP_CLASS};
+use crate::config::{transfer_syntax, CompressionConfig, DecodeLimits};
+use crate::error::{MedImgError, Result};
+use crate::pipeline::CompressionPipeline;
+use crate::progress::CancellationToken;
+
+/// Default AE title of the gateway.
+pub const DEFAULT_AE_TITLE: &str = "COMPRESS";
+
+/// Interval at which the accept loop checks for cancellation.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Time to wait for a silent peer before dropping the association.
+const READ_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Largest DIMSE command accepted; commands are a few hundred bytes.
+const MAX_COMMAND_SIZE: u64 = 64 << 10;
+
+/// Transfer syntaxes accepted from senders: the uncompressed ones, plus
+/// the ones this crate decodes (these are transcoded per the policy).
+const ACCEPTED_TRANSFER_SYNTAXES: [&str; 6] = [
+    transfer_syntax::EXPLICIT_VR_LITTLE_ENDIAN,
+    transfer_syntax::IMPLICIT_VR_LITTLE_ENDIAN,
+    transfer_syntax::JPEG_LS_LOSSLESS,
+    transfer_syntax::JPEG_LS_NEAR_LOSSLESS,
+    transfer_syntax::JPEG_2000_LOSSLESS,
+    transfer_syntax::JPEG_2000_LOSSY,
+];
+
+/// Status: Success.
+const STATUS_SUCCESS: u16 = 0x0000;
+/// Status: Refused, out of resources (the data set is over the size
+/// limit, or the gateway could not store or forward the result).
+const STATUS_OUT_OF_RESOURCES: u16 = 0xA700;
+/// Status: Error, cannot understand (the instance could not be
+/// compressed under the policy).
+const STATUS_CANNOT_UNDERSTAND: u16 = 0xC000;
+/// Status: Refused, SOP class not supported.
+const STATUS_SOP_CLASS_NOT_SUPPORTED: u16 = 0x0122;
+/// Status: Unrecognized operation.
+const STATUS_UNRECOGNIZED_OPERATION: u16 = 0x0211;
+
+/// Storage SCP that compresses on ingest.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::net::dimse::{StoreScp, StoreScu};
+///
+/// let listener = std::net::TcpListener::bind("0.0.0.0:11112")?;
+/// StoreScp::new("COMPRESS", config)
+///     .output_dir("archive".into())
+///     .forward(StoreScu::new("pacs:104").called_ae_title("ARCHIVE"))
+///     .serve(listener)?;
+/// ```
+pub struct StoreScp {
+    /// AE title the gateway answers to.
+    ae_title: String,
+
+    /// Compression settings.
+    config: CompressionConfig,
+
+    /// Directory compressed instances are written to.
+    output_dir: Option<PathBuf>,
+
+    /// Node compressed instances are forwarded to.
+    forward: Option<StoreScu>,
+
+    /// Largest data set accepted, in bytes (`None` for the installed
+    /// decode limits' `max_output_bytes`).
+    max_instance_size: Option<u64>,
+
+    /// Cancellation token; stops the accept loop.
+    cancelled: CancellationToken,
+}
+
+impl StoreScp {
+    /// Create a gateway answering to `ae_title`.
+    pub fn new(ae_title: impl Into<String>, config: CompressionConfig) -> Self {
+        Self {
+            ae_title: ae_title.into(),
+            config,
+            output_dir: None,
+            forward: None,
+            max_instance_size: None,
+            cancelled: CancellationToken::new(),
+        }
+    }
+
+    /// Write compressed instances to `dir` as `{SOP Instance UID}.dcm`.
+    pub fn output_dir(mut self, dir: PathBuf) -> Self {
+        self.output_dir = Some(dir);
+        self
+    }
+
+    /// Forward compressed instances to another node.
+    pub fn forward(mut self, scu: StoreScu) -> Self {
+        self.forward = Some(scu);
+        self
+    }
+
+    /// Refuse data sets larger than `bytes` with an out-of-resources
+    /// status, instead of buffering them.
+    pub fn max_instance_size(mut self, bytes: u64) -> Self {
+        self.max_instance_size = Some(bytes);
+        self
+    }
+
+    /// Use a shared cancellation token.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancelled = token;
+        self
+    }
+
+    /// Get a handle to this gateway's cancellation token.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+
+    /// Accept associations until cancelled.
+    ///
+    /// Each association is handled on its own thread. On cancellation, no
+    /// new associations are accepted and open ones are finished before
+    /// returning.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Config` if neither an output directory nor a forward
+    /// destination is set.
+    pub fn serve(&self, listener: TcpListener) -> Result<()> {
+        if self.output_dir.is_none() && self.forward.is_none() {
+            return Err(MedImgError::Config(
+                "Storage SCP needs an output directory or a forward destination".into(),
+            ));
+        }
+        if let Some(dir) = &self.output_dir {
+            std::fs::create_dir_all(dir).map_err(|e| MedImgError::from(e).with_file(dir))?;
+        }
+        listener.set_nonblocking(true)?;
+
+        std::thread::scope(|scope| {
+            while !self.cancelled.is_cancelled() {
+                match listener.accept() {
+                    Ok((stream, peer)) => {
+                        log::debug!("Connection from {}", peer);
+                        scope.spawn(move || {
+                            if let Err(e) = self.handle(stream) {
+                                log::warn!("Association from {} failed: {}", peer, e);
+                            }
+                        });
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                    }
+                    Err(e) => log::warn!("Accept failed: {}", e),
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Negotiate an association and serve its requests until release.
+    fn handle(&self, stream: TcpStream) -> Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        let mut options = ServerAssociationOptions::new()
+            .accept_called_ae_title()
+            .ae_title(self.ae_title.as_str())
+            // Any storage SOP class; other services are answered with a failure
+            .promiscuous(true)
+            .with_abstract_syntax(VERIFICATION_SOP_CLASS);
+        for uid in ACCEPTED_TRANSFER_SYNTAXES {
+            options = options.with_transfer_syntax(uid);
+        }
+        let mut association = options
+            .establish(stream)
+            .map_err(|e| network_error("Association negotiation failed", e))?;
+        let calling = association.client_ae_title().to_string();
+        log::info!("Association from {} accepted", calling);
+
+        let max_instance_size = self
+            .max_instance_size
+            .unwrap_or_else(|| DecodeLimits::current().max_output_bytes);
+        let mut reader = MessageReader::default();
+        while let Some((context_id, command)) = reader.command(&mut association)? {
+            let data_set = if command.has_data_set {
+                reader.fragments(&mut association, PDataValueType::Data, max_instance_size)?
+            } else {
+                Some(Vec::new())
+            };
+            let transfer_syntax = association
+                .presentation_contexts()
+                .iter()
+                .find(|pc| pc.id == context_id)
+                .map(|pc| pc.transfer_syntax.clone())
+                .unwrap_or_default();
+
+            let (field, status) = match command.field {
+                command_field::C_ECHO_RQ => (command_field::C_ECHO_RSP, STATUS_SUCCESS),
+                command_field::C_STORE_RQ => {
+                    let status = match &data_set {
+                        Some(data_set) => self.store(&calling, &command, &transfer_syntax, data_set),
+                        None => {
+                            log::warn!(
+                                "Refused {} from {}: data set over the {} byte limit",
+                                command.sop_instance_uid.as_deref().unwrap_or_default(),
+                                calling,
+                                max_instance_size
+                            );
+                            STATUS_OUT_OF_RESOURCES
+                        }
+                    };
+                    (command_field::C_STORE_RSP, status)
+                }
+                other => (other | 0x8000, STATUS_UNRECOGNIZED_OPERATION),
+            };
+            let response = Command {
+                field,
+                status: Some(status),
+                has_data_set: false,
+                ..command
+            };
+            association
+                .send(&response.to_pdu(context_id)?)
+                .map_err(|e| network_error("Failed to send DIMSE response", e))?;
+        }
+        log::info!("Association from {} released", calling);
+        Ok(())
+    }
+
+    /// Compress a received instance and write/forward it, returning the
+    /// C-STORE status.
+    fn store(&self, calling: &str, command: &Command, transfer_syntax: &str, data_set: &[u8]) -> u16 {
+        let instance_uid = command.sop_instance_uid.clone().unwrap_or_default();
+        if command.sop_class_uid == VERIFICATION_SOP_CLASS {
+            return STATUS_SOP_CLASS_NOT_SUPPORTED;
+        }
+        match self.compress_and_deliver(command, transfer_syntax, data_set) {
+            Ok(ratio) => {
+                log::info!("Stored {} from {} ({:.2}:1)", instance_uid, calling, ratio);
+                STATUS_SUCCESS
+            }
+            Err(e) => {
+                log::warn!("Failed to store {} from {}: {}", instance_uid, calling, e);
+                match e {
+                    MedImgError::Io(_) | MedImgError::Storage(_) | MedImgError::Timeout(_) => {
+                        STATUS_OUT_OF_RESOURCES
+                    }
+                    _ => STATUS_CANNOT_UNDERSTAND,
+                }
+            }
+        }
+    }
+
+    /// Compress a received instance, then write and/or forward the result,
+    /// returning the compression ratio.
+    fn compress_and_deliver(&self, command: &Command, transfer_syntax: &str, data_set: &[u8]) -> Result<f64> {
+        let instance_uid = command.sop_instance_uid.as_deref().unwrap_or_default();
+        let input = part10(&command.sop_class_uid, instance_uid, transfer_syntax, data_set)?;
+
+        let mut output = Vec::new();
+        let result = CompressionPipeline::new(self.config.clone()).compress_stream(Cursor::new(input), &mut output)?;
+
+        if let Some(dir) = &self.output_dir {
+            let name = if !instance_uid.is_empty() && instance_uid.chars().all(|c| c.is_ascii_digit() || c == '.') {
+                format!("{}.dcm", instance_uid)
+            } else {
+                format!("{:016x}.dcm", xxhash_rust::xxh3::xxh3_64(&output))
+            };
+            let path = dir.join(name);
+            std::fs::write(&path, &output).map_err(|e| MedImgError::from(e).with_file(&path))?;
+        }
+        if let Some(scu) = &self.forward {
+            let outcome = scu
+                .store(&[output])?
+                .pop()
+                .ok_or_else(|| MedImgError::Internal("No C-STORE response".into()))?;
+            if !outcome.is_success() {
+                return Err(MedImgError::Storage(format!(
+                    "Forward destination refused the instance (status {:04X}H)",
+                    outcome.status
+                )));
+            }
+        }
+        Ok(result.compression_ratio)
+    }
+}
+
+/// Build a DICOM file (preamble, file meta group, data set) around a
+/// received data set.
+fn part10(sop_class_uid: &str, sop_instance_uid: &str, transfer_syntax: &str, data_set: &[u8]) -> Result<Vec<u8>> {
+    let meta = FileMetaTableBuilder::new()
+        .media_storage_sop_class_uid(sop_class_uid)
+        .media_storage_sop_instance_uid(sop_instance_uid)
+        .transfer_syntax(transfer_syntax)
+        .build()
+        .map_err(|e| MedImgError::dicom("Failed to build file meta information", e))?;
+    let mut file = Vec::with_capacity(data_set.len() + 512);
+    file.extend_from_slice(&[0; 128]);
+    file.extend_from_slice(b"DICM");
+    meta.write(&mut file)
+        .map_err(|e| MedImgError::dicom("Failed to write file meta information", e))?;
+    file.extend_from_slice(data_set);
+    Ok(file)
+}
+
+/// Reads DIMSE messages from P-DATA-TF PDUs, which may carry fragments of
+/// the command and the data set together.
+#[derive(Default)]
+struct MessageReader {
+    pending: VecDeque<PDataValue>,
+}
+
+impl MessageReader {
+    /// Next message fragment, or `None` once the peer releases the
+    /// association.
+    fn next(&mut self, association: &mut ServerAssociation) -> Result<Option<PDataValue>> {
+        while self.pending.is_empty() {
+            match association
+                .receive()
+                .map_err(|e| network_error("Failed to receive DIMSE message", e))?
+            {
+                Pdu::PData { data } => self.pending.extend(data),
+                Pdu::ReleaseRQ => {
+                    association
+                        .send(&Pdu::ReleaseRP)
+                        .map_err(|e| network_error("Failed to release association", e))?;
+                    return Ok(None);
+                }
+                Pdu::AbortRQ { .. } => return Err(MedImgError::Storage("Association aborted by peer".into())),
+                other => {
+                    return Err(MedImgError::Storage(format!(
+                        "Unexpected PDU: {}",
+                        other.short_description()
+                    )))
+                }
+            }
+        }
+        Ok(self.pending.pop_front())
+    }
+
+    /// Next command and its presentation context, or `None` on release.
+    fn command(&mut self, association: &mut ServerAssociation) -> Result<Option<(u8, Command)>> {
+        let Some(first) = self.next(association)? else {
+            return Ok(None);
+        };
+        let context_id = first.presentation_context_id;
+        let data = self
+            .collect(association, first, PDataValueType::Command, MAX_COMMAND_SIZE)?
+            .ok_or_else(|| MedImgError::Storage("DIMSE command too large".into()))?;
+        Ok(Some((context_id, Command::decode(&data)?)))
+    }
+
+    /// All fragments of the next `value_type` message, or `None` if they
+    /// add up to more than `limit` bytes.
+    fn fragments(
+        &mut self,
+        association: &mut ServerAssociation,
+        value_type: PDataValueType,
+        limit: u64,
+    ) -> Result<Option<Vec<u8>>> {
+        let first = self
+            .next(association)?
+            .ok_or_else(|| MedImgError::Storage("Association released mid-message".into()))?;
+        self.collect(association, first, value_type, limit)
+    }
+
+    /// Concatenate fragments from `first` up to the last one, or `None` if
+    /// they add up to more than `limit` bytes. Fragments past the limit are
+    /// read and dropped, so the next message starts where it should.
+    fn collect(
+        &mut self,
+        association: &mut ServerAssociation,
+        first: PDataValue,
+        value_type: PDataValueType,
+        limit: u64,
+    ) -> Result<Option<Vec<u8>>> {
+        let mut value = first;
+        let mut data = Some(Vec::new());
+        let mut total = 0u64;
+        loop {
+            if value.value_type != value_type {
+                return Err(MedImgError::Storage("Unexpected DIMSE message fragment".into()));
+            }
+            total += value.data.len() as u64;
+            if total > limit {
+                data = None;
+            }
+            if let Some(data) = &mut data {
+                data.extend_from_slice(&value.data);
+            }
+            if value.is_last {
+                return Ok(data);
+            }
+            value = self
+                .next(association)?
+                .ok_or_else(|| MedImgError::Storage("Association released mid-message".into()))?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CompressionCodec;
+    use crate::dicom::{testing, DicomFile};
+
+    #[test]
+    fn test_compress_on_ingest() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let output_dir = dir.path().join("archive");
+        let pixels = testing::gradient(16, 8);
+        testing::write_grayscale(&input, 16, 8, "CT", &pixels);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let scp = StoreScp::new(DEFAULT_AE_TITLE, CompressionConfig::lossless(CompressionCodec::JpegLs))
+            .output_dir(output_dir.clone());
+        let token = scp.cancellation_token();
+
+        std::thread::scope(|scope| {
+            let server = scope.spawn(|| scp.serve(listener));
+
+            let scu = StoreScu::new(address).called_ae_title(DEFAULT_AE_TITLE);
+            scu.echo().unwrap();
+            let outcomes = scu.store_files(&[&input]).unwrap();
+            assert!(outcomes[0].is_success(), "status {:04X}", outcomes[0].status);
+
+            token.cancel();
+            server.join().unwrap().unwrap();
+        });
+
+        let stored = std::fs::read_dir(&output_dir).unwrap().next().unwrap().unwrap().path();
+        let written = DicomFile::open(&stored).unwrap();
+        assert_eq!(written.metadata.transfer_syntax, transfer_syntax::JPEG_LS_LOSSLESS);
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, pixels);
+    }
+
+    #[test]
+    fn test_oversized_data_set_refused() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let large = dir.path().join("large.dcm");
+        let small = dir.path().join("small.dcm");
+        testing::write_grayscale(&large, 64, 64, "CT", &testing::gradient(64, 64));
+        testing::write_grayscale(&small, 16, 8, "CT", &testing::gradient(16, 8));
+        let output_dir = dir.path().join("archive");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let scp = StoreScp::new(DEFAULT_AE_TITLE, CompressionConfig::lossless(CompressionCodec::JpegLs))
+            .output_dir(output_dir.clone())
+            .max_instance_size(4096);
+        let token = scp.cancellation_token();
+
+        std::thread::scope(|scope| {
+            let server = scope.spawn(|| scp.serve(listener));
+
+            let scu = StoreScu::new(address).called_ae_title(DEFAULT_AE_TITLE);
+            let outcomes = scu.store_files(&[&large, &small]).unwrap();
+            assert_eq!(outcomes[0].status, STATUS_OUT_OF_RESOURCES);
+            // The association stays usable after a refused instance
+            assert!(outcomes[1].is_success(), "status {:04X}", outcomes[1].status);
+
+            token.cancel();
+            server.join().unwrap().unwrap();
+        });
+
+        assert_eq!(std::fs::read_dir(&output_dir).unwrap().count(), 1);
+    }
+}
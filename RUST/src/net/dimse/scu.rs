@@ -0,0 +1,414 @@
+//! C-STORE SCU: forward instances to a DICOM node over DIMSE.
+//!
+//! Each call negotiates one association. A presentation context is
+//! proposed per SOP class and transfer syntax present, offering only the
+//! instance's own (compressed) transfer syntax, so the receiver stores the
+//! encoding as is. Instances are sent without re-encoding: the data set is
+//! the file's bytes after its meta group.
+
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use dicom::ul::association::client::{ClientAssociation, ClientAssociationOptions};
+use dicom::ul::pdu::PDataValueType;
+
+use super::{command_field, is_success_status, network_error, receive_fragments, Command, VERIFICATION_SOP_CLASS};
+use crate::config::transfer_syntax;
+use crate::error::{MedImgError, Result};
+use crate::net::file_meta;
+use crate::pipeline::{CompressionPipeline, CompressionResult};
+
+/// Result of storing one instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreOutcome {
+    /// SOP Instance UID of the instance.
+    pub sop_instance_uid: String,
+    /// DIMSE status returned by the receiver.
+    pub status: u16,
+}
+
+impl StoreOutcome {
+    /// Whether the instance was stored (Success or Warning status).
+    pub fn is_success(&self) -> bool {
+        is_success_status(self.status)
+    }
+}
+
+/// An instance split into its identifiers and encoded data set.
+struct Instance<'a> {
+    sop_class_uid: String,
+    sop_instance_uid: String,
+    transfer_syntax: String,
+    data_set: &'a [u8],
+}
+
+impl<'a> Instance<'a> {
+    /// Split a DICOM file held in memory.
+    fn parse(data: &'a [u8]) -> Result<Self> {
+        let meta = file_meta(data)?;
+        let start = if data.get(128..132) == Some(b"DICM") { 128 } else { 0 };
+        // "DICM", then File Meta Information Group Length (12 bytes), then the group
+        let offset = start + 16 + meta.information_group_length as usize;
+        let data_set = data
+            .get(offset..)
+            .ok_or_else(|| MedImgError::Dicom("File meta group extends past the end of the file".into()))?;
+        let trim = |uid: &str| uid.trim_end_matches('\0').to_string();
+        Ok(Self {
+            sop_class_uid: trim(meta.media_storage_sop_class_uid()),
+            sop_instance_uid: trim(meta.media_storage_sop_instance_uid()),
+            transfer_syntax: trim(meta.transfer_syntax()),
+            data_set,
+        })
+    }
+}
+
+/// C-STORE service class user.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::net::dimse::StoreScu;
+///
+/// let scu = StoreScu::new("pacs.example.org:104").called_ae_title("ARCHIVE");
+/// scu.echo()?;
+/// let outcomes = scu.store_files(&compressed_paths)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct StoreScu {
+    address: String,
+    calling_ae_title: String,
+    called_ae_title: String,
+    timeout: Option<Duration>,
+}
+
+impl StoreScu {
+    /// Default calling AE title.
+    pub const DEFAULT_CALLING_AE_TITLE: &'static str = "MEDIMG-SCU";
+
+    /// Default called AE title.
+    pub const DEFAULT_CALLED_AE_TITLE: &'static str = "ANY-SCP";
+
+    /// Create a sender for the node at `address` (`host:port`).
+    pub fn new(address: impl Into<String>) -> Self {
+        Self {
+            address: address.into(),
+            calling_ae_title: Self::DEFAULT_CALLING_AE_TITLE.into(),
+            called_ae_title: Self::DEFAULT_CALLED_AE_TITLE.into(),
+            timeout: None,
+        }
+    }
+
+    /// Set this node's AE title.
+    pub fn calling_ae_title(mut self, ae_title: impl Into<String>) -> Self {
+        self.calling_ae_title = ae_title.into();
+        self
+    }
+
+    /// Set the receiver's AE title.
+    pub fn called_ae_title(mut self, ae_title: impl Into<String>) -> Self {
+        self.called_ae_title = ae_title.into();
+        self
+    }
+
+    /// Set the socket read and write timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Open an association proposing `contexts` (abstract syntax, transfer
+    /// syntax).
+    fn associate(&self, contexts: &[(String, String)]) -> Result<ClientAssociation> {
+        let mut options = ClientAssociationOptions::new()
+            .calling_ae_title(self.calling_ae_title.as_str())
+            .called_ae_title(self.called_ae_title.as_str());
+        for (abstract_syntax, transfer_syntax) in contexts {
+            options = options.with_presentation_context(abstract_syntax.as_str(), vec![transfer_syntax.as_str()]);
+        }
+        if let Some(timeout) = self.timeout {
+            options = options.read_timeout(timeout).write_timeout(timeout);
+        }
+        options
+            .establish(self.address.as_str())
+            .map_err(|e| network_error(&format!("Association with {} failed", self.address), e))
+    }
+
+    /// Verify that the receiver is reachable and accepts associations
+    /// (C-ECHO).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Timeout` if the receiver does not answer in time, and
+    /// `Storage` if the association is rejected or the echo fails.
+    pub fn echo(&self) -> Result<()> {
+        let contexts = [(
+            VERIFICATION_SOP_CLASS.to_string(),
+            transfer_syntax::IMPLICIT_VR_LITTLE_ENDIAN.to_string(),
+        )];
+        let mut association = self.associate(&contexts)?;
+        let context_id = association.presentation_contexts()[0].id;
+        let request = Command {
+            field: command_field::C_ECHO_RQ,
+            message_id: 1,
+            sop_class_uid: VERIFICATION_SOP_CLASS.into(),
+            sop_instance_uid: None,
+            status: None,
+            has_data_set: false,
+        };
+        let response = send_command(&mut association, context_id, &request, command_field::C_ECHO_RSP);
+        finish(association, response.as_ref().is_ok());
+
+        let status = response?.status.unwrap_or(0xFFFF);
+        if !is_success_status(status) {
+            return Err(MedImgError::Storage(format!("C-ECHO failed with status {:04X}H", status)));
+        }
+        Ok(())
+    }
+
+    /// Send DICOM files held in memory over one association, returning the
+    /// receiver's status for each.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Dicom` if an instance has no readable file meta group,
+    /// `Storage` if the receiver does not accept an instance's SOP class
+    /// in its transfer syntax or the association fails, and `Timeout` if
+    /// the receiver stops answering.
+    pub fn store(&self, instances: &[Vec<u8>]) -> Result<Vec<StoreOutcome>> {
+        let instances = instances
+            .iter()
+            .map(|data| Instance::parse(data))
+            .collect::<Result<Vec<_>>>()?;
+        if instances.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut contexts: Vec<(String, String)> = Vec::new();
+        for instance in &instances {
+            let context = (instance.sop_class_uid.clone(), instance.transfer_syntax.clone());
+            if !contexts.contains(&context) {
+                contexts.push(context);
+            }
+        }
+        let mut association = self.associate(&contexts)?;
+
+        // Proposed contexts have IDs 1, 3, 5, ... in order; only accepted
+        // ones are listed
+        let context_id = |instance: &Instance| -> Result<u8> {
+            let index = contexts
+                .iter()
+                .position(|(class, ts)| *class == instance.sop_class_uid && *ts == instance.transfer_syntax)
+                .unwrap_or_default();
+            let id = (2 * index + 1) as u8;
+            association
+                .presentation_contexts()
+                .iter()
+                .any(|pc| pc.id == id)
+                .then_some(id)
+                .ok_or_else(|| {
+                    MedImgError::Storage(format!(
+                        "{} does not accept SOP class {} in transfer syntax {}",
+                        self.address, instance.sop_class_uid, instance.transfer_syntax
+                    ))
+                })
+        };
+        let ids = instances.iter().map(context_id).collect::<Result<Vec<_>>>();
+        let ids = match ids {
+            Ok(ids) => ids,
+            Err(e) => {
+                finish(association, true);
+                return Err(e);
+            }
+        };
+
+        let mut outcomes = Vec::with_capacity(instances.len());
+        let mut result = Ok(());
+        for (index, (instance, id)) in instances.iter().zip(ids).enumerate() {
+            match store_one(&mut association, id, index as u16 + 1, instance) {
+                Ok(outcome) => outcomes.push(outcome),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        finish(association, result.is_ok());
+        result.map(|()| outcomes)
+    }
+
+    /// Send DICOM files over one association.
+    pub fn store_files<P: AsRef<Path>>(&self, paths: &[P]) -> Result<Vec<StoreOutcome>> {
+        let instances = paths
+            .iter()
+            .map(|path| {
+                let path = path.as_ref();
+                std::fs::read(path).map_err(|e| MedImgError::from(e).with_file(path))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.store(&instances)
+    }
+
+    /// Compress a DICOM file with `pipeline` and send the result, without
+    /// writing it to disk.
+    pub fn compress_and_store<P: AsRef<Path>>(
+        &self,
+        pipeline: &CompressionPipeline,
+        input_path: P,
+    ) -> Result<(CompressionResult, StoreOutcome)> {
+        let input_path = input_path.as_ref();
+        let input = std::fs::File::open(input_path).map_err(|e| MedImgError::from(e).with_file(input_path))?;
+        let mut output = Vec::new();
+        let mut result = pipeline.compress_stream(input, &mut output)?;
+        result.source_path = input_path.to_path_buf();
+        result.output_path = None;
+
+        let outcome = self
+            .store(&[output])?
+            .pop()
+            .ok_or_else(|| MedImgError::Internal("No C-STORE response".into()))?;
+        Ok((result, outcome))
+    }
+}
+
+/// Send a command without a data set and wait for its response.
+fn send_command(
+    association: &mut ClientAssociation,
+    context_id: u8,
+    command: &Command,
+    response_field: u16,
+) -> Result<Command> {
+    association
+        .send(&command.to_pdu(context_id)?)
+        .map_err(|e| network_error("Failed to send DIMSE command", e))?;
+    receive_response(association, command.message_id, response_field)
+}
+
+/// Wait for the `response_field` response to message `message_id`.
+fn receive_response(association: &mut ClientAssociation, message_id: u16, response_field: u16) -> Result<Command> {
+    let (_, data) = receive_fragments(|| association.receive(), PDataValueType::Command)?;
+    let response = Command::decode(&data)?;
+    if response.field != response_field || response.message_id != message_id {
+        return Err(MedImgError::Storage(format!(
+            "Unexpected response (command {:04X}H to message {}) while waiting for {:04X}H to message {}",
+            response.field, response.message_id, response_field, message_id
+        )));
+    }
+    Ok(response)
+}
+
+/// Send one instance (C-STORE-RQ and its data set).
+fn store_one(
+    association: &mut ClientAssociation,
+    context_id: u8,
+    message_id: u16,
+    instance: &Instance,
+) -> Result<StoreOutcome> {
+    let request = Command {
+        field: command_field::C_STORE_RQ,
+        message_id,
+        sop_class_uid: instance.sop_class_uid.clone(),
+        sop_instance_uid: Some(instance.sop_instance_uid.clone()),
+        status: None,
+        has_data_set: true,
+    };
+    association
+        .send(&request.to_pdu(context_id)?)
+        .map_err(|e| network_error("Failed to send C-STORE request", e))?;
+    let mut pdata = association.send_pdata(context_id);
+    pdata.write_all(instance.data_set)?;
+    pdata.finish()?;
+
+    let response = receive_response(association, message_id, command_field::C_STORE_RSP)?;
+    Ok(StoreOutcome {
+        sop_instance_uid: instance.sop_instance_uid.clone(),
+        status: response.status.unwrap_or(0xFFFF),
+    })
+}
+
+/// Release the association after success, or abort it after a failure.
+fn finish(association: ClientAssociation, release: bool) {
+    let closed = if release {
+        association.release()
+    } else {
+        association.abort()
+    };
+    if let Err(e) = closed {
+        log::debug!("Failed to close association: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::thread;
+
+    use dicom::ul::association::server::ServerAssociationOptions;
+    use dicom::ul::pdu::Pdu;
+
+    use super::*;
+    use crate::config::{CompressionCodec, CompressionConfig};
+    use crate::dicom::testing;
+
+    #[test]
+    fn test_echo_and_store() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        testing::write_grayscale(&input, 8, 8, "CT", &testing::gradient(8, 8));
+        let pipeline = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::JpegLs));
+        let mut compressed = Vec::new();
+        pipeline.compress_stream(std::fs::File::open(&input).unwrap(), &mut compressed).unwrap();
+        let expected = Instance::parse(&compressed).unwrap().data_set.to_vec();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let scp = thread::spawn(move || {
+            let options = ServerAssociationOptions::new()
+                .accept_any()
+                .with_abstract_syntax(VERIFICATION_SOP_CLASS)
+                .with_abstract_syntax("1.2.840.10008.5.1.4.1.1.7")
+                .with_transfer_syntax(transfer_syntax::IMPLICIT_VR_LITTLE_ENDIAN)
+                .with_transfer_syntax(transfer_syntax::JPEG_LS_LOSSLESS);
+
+            // C-ECHO
+            let mut association = options.establish(listener.accept().unwrap().0).unwrap();
+            let (id, data) = receive_fragments(|| association.receive(), PDataValueType::Command).unwrap();
+            let echo = Command::decode(&data).unwrap();
+            assert_eq!(echo.field, command_field::C_ECHO_RQ);
+            let response = Command {
+                field: command_field::C_ECHO_RSP,
+                status: Some(0),
+                ..echo
+            };
+            association.send(&response.to_pdu(id).unwrap()).unwrap();
+            assert_eq!(association.receive().unwrap(), Pdu::ReleaseRQ);
+            association.send(&Pdu::ReleaseRP).unwrap();
+
+            // C-STORE
+            let mut association = options.establish(listener.accept().unwrap().0).unwrap();
+            let (id, data) = receive_fragments(|| association.receive(), PDataValueType::Command).unwrap();
+            let store = Command::decode(&data).unwrap();
+            let (_, data_set) = receive_fragments(|| association.receive(), PDataValueType::Data).unwrap();
+            let response = Command {
+                field: command_field::C_STORE_RSP,
+                status: Some(0),
+                has_data_set: false,
+                ..store.clone()
+            };
+            association.send(&response.to_pdu(id).unwrap()).unwrap();
+            assert_eq!(association.receive().unwrap(), Pdu::ReleaseRQ);
+            association.send(&Pdu::ReleaseRP).unwrap();
+            (store, data_set)
+        });
+
+        let scu = StoreScu::new(address).timeout(Duration::from_secs(10));
+        scu.echo().unwrap();
+        let outcomes = scu.store(&[compressed]).unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert!(outcomes[0].is_success());
+
+        let (store, data_set) = scp.join().unwrap();
+        assert_eq!(store.sop_instance_uid.as_deref(), Some(outcomes[0].sop_instance_uid.as_str()));
+        assert_eq!(data_set, expected);
+    }
+}
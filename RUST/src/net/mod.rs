@@ -0,0 +1,34 @@
+//! Network transfer of DICOM instances.
+//!
+//! - [`dimse`]: C-ECHO and C-STORE over the DICOM upper layer
+//! - [`stow`]: DICOMweb STOW-RS upload (`dicomweb` feature)
+//! - [`wado`]: DICOMweb WADO-RS retrieval as a batch input (`dicomweb`
+//!   feature)
+
+use dicom::object::FileMetaTable;
+
+use crate::error::{MedImgError, Result};
+
+pub mod dimse;
+#[cfg(feature = "dicomweb")]
+pub mod stow;
+#[cfg(feature = "dicomweb")]
+pub mod wado;
+
+#[cfg(feature = "dicomweb")]
+pub use stow::{StowClient, StowClientBuilder, StowResponse};
+#[cfg(feature = "dicomweb")]
+pub use wado::{WadoClient, WadoClientBuilder};
+
+/// Read the Transfer Syntax UID from the file meta group of a DICOM file
+/// held in memory (with or without the 128-byte preamble).
+pub fn transfer_syntax_of(data: &[u8]) -> Result<String> {
+    Ok(file_meta(data)?.transfer_syntax().trim_end_matches('\0').to_string())
+}
+
+/// Parse the file meta group of a DICOM file held in memory.
+fn file_meta(data: &[u8]) -> Result<FileMetaTable> {
+    let start = if data.get(128..132) == Some(b"DICM") { 128 } else { 0 };
+    FileMetaTable::from_reader(&data[start..])
+        .map_err(|e| MedImgError::dicom("Failed to read file meta information", e))
+}
@@ -0,0 +1,329 @@
+//! DICOMweb STOW-RS client.
+//!
+//! Uploads instances to a DICOMweb server as `multipart/related` requests
+//! (PS3.18 section 10.5). Each part is labelled with its transfer syntax
+//! (`application/dicom; transfer-syntax=...`) so servers accept the
+//! compressed encoding as is. Instances can be compressed and sent
+//! straight from memory, without writing intermediate files.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+
+use super::transfer_syntax_of;
+use crate::error::{MedImgError, Result};
+use crate::pipeline::{CompressionPipeline, CompressionResult};
+
+/// Referenced SOP Sequence (0008,1199): stored instances.
+const REFERENCED_SOP_SEQUENCE: &str = "00081199";
+/// Failed SOP Sequence (0008,1198): rejected instances.
+const FAILED_SOP_SEQUENCE: &str = "00081198";
+/// Referenced SOP Instance UID (0008,1155).
+const REFERENCED_SOP_INSTANCE_UID: &str = "00081155";
+/// Failure Reason (0008,1197).
+const FAILURE_REASON: &str = "00081197";
+
+/// Outcome of a STOW-RS request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StowResponse {
+    /// HTTP status: 200 (all stored), 202 (some failed or with warnings),
+    /// or 409 (none stored).
+    pub status: u16,
+    /// SOP Instance UIDs the server stored.
+    pub stored: Vec<String>,
+    /// SOP Instance UIDs the server rejected, with their Failure Reason.
+    pub failed: Vec<(String, u16)>,
+}
+
+impl StowResponse {
+    /// Whether every instance was stored.
+    pub fn is_success(&self) -> bool {
+        self.status == 200 && self.failed.is_empty()
+    }
+}
+
+/// Client for a DICOMweb STOW-RS endpoint.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::net::StowClient;
+///
+/// let client = StowClient::builder("https://pacs.example.org/dicomweb")
+///     .bearer_token(token)
+///     .build();
+///
+/// let (result, response) = client.compress_and_store(&pipeline, "input.dcm")?;
+/// ```
+pub struct StowClient {
+    /// `{base}/studies`, or `{base}/studies/{uid}` for a single study.
+    url: String,
+    headers: Vec<(String, String)>,
+    agent: ureq::Agent,
+}
+
+/// Builder for [`StowClient`].
+pub struct StowClientBuilder {
+    base_url: String,
+    study_uid: Option<String>,
+    headers: Vec<(String, String)>,
+    timeout: Duration,
+}
+
+impl StowClientBuilder {
+    /// Authenticate with an OAuth bearer token.
+    pub fn bearer_token(self, token: impl AsRef<str>) -> Self {
+        self.header("Authorization", format!("Bearer {}", token.as_ref()))
+    }
+
+    /// Add an HTTP header to every request.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Store into a single study (`/studies/{uid}`); the server rejects
+    /// instances from other studies.
+    pub fn study(mut self, study_uid: impl Into<String>) -> Self {
+        self.study_uid = Some(study_uid.into());
+        self
+    }
+
+    /// Timeout for each HTTP request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Build the client.
+    pub fn build(self) -> StowClient {
+        let base = self.base_url.trim_end_matches('/');
+        let url = match &self.study_uid {
+            Some(uid) => format!("{}/studies/{}", base, uid),
+            None => format!("{}/studies", base),
+        };
+        StowClient {
+            url,
+            headers: self.headers,
+            agent: ureq::AgentBuilder::new().timeout(self.timeout).build(),
+        }
+    }
+}
+
+impl StowClient {
+    /// Default timeout for each HTTP request.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+    /// Start building a client for the DICOMweb service at `base_url`
+    /// (the STOW-RS path `/studies` is appended).
+    pub fn builder(base_url: impl Into<String>) -> StowClientBuilder {
+        StowClientBuilder {
+            base_url: base_url.into(),
+            study_uid: None,
+            headers: Vec::new(),
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Upload DICOM files held in memory in one request. Each part's
+    /// transfer syntax is read from its file meta group.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Dicom` if an instance has no readable file meta group,
+    /// `Timeout` if the request times out, and `Storage` if the server
+    /// cannot be reached or answers with an error other than 409.
+    pub fn store(&self, instances: &[Vec<u8>]) -> Result<StowResponse> {
+        let parts = instances
+            .iter()
+            .map(|data| Ok((transfer_syntax_of(data)?, data.as_slice())))
+            .collect::<Result<Vec<_>>>()?;
+        let boundary = boundary(&parts);
+        let body = multipart_body(&parts, &boundary);
+
+        let mut request = self
+            .agent
+            .post(&self.url)
+            .set(
+                "Content-Type",
+                &format!("multipart/related; type=\"application/dicom\"; boundary={}", boundary),
+            )
+            .set("Accept", "application/dicom+json");
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+
+        let (status, text) = match request.send_bytes(&body) {
+            Ok(response) => (response.status(), response.into_string()?),
+            // 409: the request was understood but no instance was stored
+            Err(ureq::Error::Status(409, response)) => (409, response.into_string()?),
+            Err(ureq::Error::Status(code, response)) => {
+                return Err(MedImgError::Storage(format!(
+                    "STOW-RS request to {} failed: {} {}",
+                    self.url,
+                    code,
+                    response.status_text()
+                )))
+            }
+            Err(e) => return Err(request_error(&self.url, e)),
+        };
+        parse_response(status, &text)
+    }
+
+    /// Upload a DICOM file.
+    pub fn store_file<P: AsRef<Path>>(&self, path: P) -> Result<StowResponse> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|e| MedImgError::from(e).with_file(path))?;
+        self.store(&[data])
+    }
+
+    /// Compress a DICOM file with `pipeline` and upload the result, without
+    /// writing it to disk.
+    pub fn compress_and_store<P: AsRef<Path>>(
+        &self,
+        pipeline: &CompressionPipeline,
+        input_path: P,
+    ) -> Result<(CompressionResult, StowResponse)> {
+        let input_path = input_path.as_ref();
+        let input = std::fs::File::open(input_path).map_err(|e| MedImgError::from(e).with_file(input_path))?;
+        let mut output = Vec::new();
+        let mut result = pipeline.compress_stream(input, &mut output)?;
+        result.source_path = input_path.to_path_buf();
+        result.output_path = Some(self.url.clone().into());
+
+        let response = self.store(&[output])?;
+        Ok((result, response))
+    }
+}
+
+/// Classify a failed request, so timeouts are reported as such.
+fn request_error(url: &str, error: ureq::Error) -> MedImgError {
+    let message = format!("STOW-RS request to {} failed: {}", url, error);
+    let mut source = std::error::Error::source(&error);
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            if matches!(io.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock) {
+                return MedImgError::Timeout(message);
+            }
+        }
+        source = err.source();
+    }
+    MedImgError::Storage(message)
+}
+
+/// Pick a multipart boundary that occurs in none of the parts.
+fn boundary(parts: &[(String, &[u8])]) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    loop {
+        let seed = nanos ^ COUNTER.fetch_add(1, Ordering::Relaxed).rotate_left(32);
+        let boundary = format!("medimg-{:016x}", xxhash_rust::xxh3::xxh3_64(&seed.to_le_bytes()));
+        let needle = boundary.as_bytes();
+        if parts.iter().all(|(_, data)| !data.windows(needle.len()).any(|w| w == needle)) {
+            return boundary;
+        }
+    }
+}
+
+/// Build a `multipart/related` body of `application/dicom` parts.
+fn multipart_body(parts: &[(String, &[u8])], boundary: &str) -> Vec<u8> {
+    let size: usize = parts.iter().map(|(_, data)| data.len() + 128).sum();
+    let mut body = Vec::with_capacity(size + boundary.len() + 8);
+    for (transfer_syntax, data) in parts {
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        body.extend_from_slice(
+            format!("Content-Type: application/dicom; transfer-syntax={}\r\n\r\n", transfer_syntax).as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+/// Parse the Store Instances Response Module (`application/dicom+json`).
+fn parse_response(status: u16, text: &str) -> Result<StowResponse> {
+    let mut response = StowResponse {
+        status,
+        ..Default::default()
+    };
+    if text.trim().is_empty() {
+        return Ok(response);
+    }
+    let json: Value = serde_json::from_str(text)
+        .map_err(|e| MedImgError::Storage(format!("Invalid STOW-RS response: {}", e)))?;
+    // Servers answer with either a dataset or a one-element array
+    let dataset = json.as_array().and_then(|a| a.first()).unwrap_or(&json);
+
+    let items = |tag: &str| -> Vec<Value> {
+        dataset[tag]["Value"].as_array().cloned().unwrap_or_default()
+    };
+    let first = |item: &Value, tag: &str| -> Option<Value> { item[tag]["Value"].get(0).cloned() };
+
+    response.stored = items(REFERENCED_SOP_SEQUENCE)
+        .iter()
+        .filter_map(|item| first(item, REFERENCED_SOP_INSTANCE_UID)?.as_str().map(String::from))
+        .collect();
+    response.failed = items(FAILED_SOP_SEQUENCE)
+        .iter()
+        .filter_map(|item| {
+            let uid = first(item, REFERENCED_SOP_INSTANCE_UID)?.as_str()?.to_string();
+            let reason = first(item, FAILURE_REASON).and_then(|r| r.as_u64()).unwrap_or(0);
+            Some((uid, reason as u16))
+        })
+        .collect();
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{transfer_syntax, CompressionCodec, CompressionConfig};
+    use crate::dicom::testing;
+
+    #[test]
+    fn test_multipart_body() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        testing::write_grayscale(&input, 8, 8, "CT", &testing::gradient(8, 8));
+        let pipeline = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::JpegLs));
+        let mut output = Vec::new();
+        pipeline.compress_stream(std::fs::File::open(&input).unwrap(), &mut output).unwrap();
+
+        let parts = vec![(transfer_syntax_of(&output).unwrap(), output.as_slice())];
+        assert_eq!(parts[0].0, transfer_syntax::JPEG_LS_LOSSLESS);
+        let boundary = boundary(&parts);
+        let body = multipart_body(&parts, &boundary);
+
+        let header = format!(
+            "--{}\r\nContent-Type: application/dicom; transfer-syntax=1.2.840.10008.1.2.4.80\r\n\r\n",
+            boundary
+        );
+        assert!(body.starts_with(header.as_bytes()));
+        assert_eq!(&body[header.len()..header.len() + output.len()], output.as_slice());
+        assert!(body.ends_with(format!("\r\n--{}--\r\n", boundary).as_bytes()));
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let text = r#"[{
+            "00081199": {"vr": "SQ", "Value": [
+                {"00081155": {"vr": "UI", "Value": ["1.2.3.1"]}}
+            ]},
+            "00081198": {"vr": "SQ", "Value": [
+                {"00081155": {"vr": "UI", "Value": ["1.2.3.2"]}, "00081197": {"vr": "US", "Value": [49442]}}
+            ]}
+        }]"#;
+        let response = parse_response(202, text).unwrap();
+        assert_eq!(response.stored, vec!["1.2.3.1"]);
+        assert_eq!(response.failed, vec![("1.2.3.2".to_string(), 0xC122)]);
+        assert!(!response.is_success());
+
+        assert!(parse_response(200, "").unwrap().is_success());
+        assert!(parse_response(200, "<html>").is_err());
+    }
+}
@@ -0,0 +1,277 @@
+//! DICOMweb WADO-RS client.
+//!
+//! Retrieves the instances of a study or series (PS3.18 section 10.4) as
+//! `multipart/related` DICOM, negotiating the transfer syntax through the
+//! `Accept` header. Instances are staged as files so they can be fed to
+//! [`BatchProcessor::process_wado`](crate::batch::BatchProcessor::process_wado).
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::file_meta;
+use crate::config::transfer_syntax;
+use crate::error::{MedImgError, Result};
+
+/// Client for a DICOMweb WADO-RS endpoint.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::net::WadoClient;
+///
+/// let client = WadoClient::builder("https://pacs.example.org/dicomweb")
+///     .bearer_token(token)
+///     .build();
+///
+/// let stats = BatchProcessor::new(config, progress)
+///     .output_dir("archive".into())
+///     .process_wado(&client, study_uid, Some(series_uid), staging.path())?;
+/// ```
+pub struct WadoClient {
+    base_url: String,
+    headers: Vec<(String, String)>,
+    transfer_syntaxes: Vec<String>,
+    agent: ureq::Agent,
+}
+
+/// Builder for [`WadoClient`].
+pub struct WadoClientBuilder {
+    base_url: String,
+    headers: Vec<(String, String)>,
+    transfer_syntaxes: Vec<String>,
+    timeout: Duration,
+}
+
+impl WadoClientBuilder {
+    /// Authenticate with an OAuth bearer token.
+    pub fn bearer_token(self, token: impl AsRef<str>) -> Self {
+        self.header("Authorization", format!("Bearer {}", token.as_ref()))
+    }
+
+    /// Add an HTTP header to every request.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Add an acceptable transfer syntax, in order of preference. `*`
+    /// accepts whatever the server stores.
+    ///
+    /// Defaults to Explicit VR Little Endian, so instances arrive
+    /// uncompressed and are encoded once, by this crate.
+    pub fn transfer_syntax(mut self, uid: impl Into<String>) -> Self {
+        self.transfer_syntaxes.push(uid.into());
+        self
+    }
+
+    /// Timeout for each HTTP request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Build the client.
+    pub fn build(mut self) -> WadoClient {
+        if self.transfer_syntaxes.is_empty() {
+            self.transfer_syntaxes.push(transfer_syntax::EXPLICIT_VR_LITTLE_ENDIAN.into());
+        }
+        WadoClient {
+            base_url: self.base_url.trim_end_matches('/').to_string(),
+            headers: self.headers,
+            transfer_syntaxes: self.transfer_syntaxes,
+            agent: ureq::AgentBuilder::new().timeout(self.timeout).build(),
+        }
+    }
+}
+
+impl WadoClient {
+    /// Default timeout for each HTTP request.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(600);
+
+    /// Start building a client for the DICOMweb service at `base_url`.
+    pub fn builder(base_url: impl Into<String>) -> WadoClientBuilder {
+        WadoClientBuilder {
+            base_url: base_url.into(),
+            headers: Vec::new(),
+            transfer_syntaxes: Vec::new(),
+            timeout: Self::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Retrieve the instances of a study, or of one of its series, as DICOM
+    /// files held in memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Timeout` if the request times out, `Storage` if the server
+    /// cannot be reached or answers with an error (e.g. 406 when no
+    /// accepted transfer syntax is available), and `InvalidFormat` for a
+    /// malformed multipart response.
+    pub fn retrieve(&self, study_uid: &str, series_uid: Option<&str>) -> Result<Vec<Vec<u8>>> {
+        let url = match series_uid {
+            Some(series) => format!("{}/studies/{}/series/{}", self.base_url, study_uid, series),
+            None => format!("{}/studies/{}", self.base_url, study_uid),
+        };
+        let mut request = self.agent.get(&url).set("Accept", &accept_header(&self.transfer_syntaxes));
+        for (name, value) in &self.headers {
+            request = request.set(name, value);
+        }
+
+        let response = match request.call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(code, response)) => {
+                return Err(MedImgError::Storage(format!(
+                    "WADO-RS request to {} failed: {} {}",
+                    url,
+                    code,
+                    response.status_text()
+                )))
+            }
+            Err(e) => return Err(request_error(&url, e)),
+        };
+        let content_type = response.header("Content-Type").unwrap_or_default().to_string();
+        let boundary = boundary_of(&content_type).ok_or_else(|| {
+            MedImgError::InvalidFormat(format!("WADO-RS response is not multipart: {}", content_type))
+        })?;
+        let mut body = Vec::new();
+        response.into_reader().read_to_end(&mut body)?;
+        parse_multipart(&body, &boundary)
+    }
+
+    /// Retrieve a study or series and write each instance to `dir` as
+    /// `{SOP Instance UID}.dcm`, returning the written paths.
+    pub fn retrieve_to(&self, study_uid: &str, series_uid: Option<&str>, dir: &Path) -> Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(dir).map_err(|e| MedImgError::from(e).with_file(dir))?;
+        self.retrieve(study_uid, series_uid)?
+            .iter()
+            .enumerate()
+            .map(|(index, data)| {
+                let uid = file_meta(data)?
+                    .media_storage_sop_instance_uid()
+                    .trim_end_matches('\0')
+                    .to_string();
+                let name = if is_file_name_safe(&uid) {
+                    format!("{}.dcm", uid)
+                } else {
+                    format!("instance_{:05}.dcm", index)
+                };
+                let path = dir.join(name);
+                std::fs::write(&path, data).map_err(|e| MedImgError::from(e).with_file(&path))?;
+                Ok(path)
+            })
+            .collect()
+    }
+}
+
+/// `Accept` header listing each transfer syntax in order of preference.
+fn accept_header(transfer_syntaxes: &[String]) -> String {
+    let count = transfer_syntaxes.len();
+    transfer_syntaxes
+        .iter()
+        .enumerate()
+        .map(|(i, uid)| {
+            let q = 1.0 - i as f64 / count as f64 * 0.5;
+            format!(
+                "multipart/related; type=\"application/dicom\"; transfer-syntax={}; q={:.2}",
+                uid, q
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Whether a UID can be used as a file name as is.
+fn is_file_name_safe(uid: &str) -> bool {
+    !uid.is_empty() && uid.chars().all(|c| c.is_ascii_digit() || c == '.')
+}
+
+/// Classify a failed request, so timeouts are reported as such.
+fn request_error(url: &str, error: ureq::Error) -> MedImgError {
+    let message = format!("WADO-RS request to {} failed: {}", url, error);
+    let mut source = std::error::Error::source(&error);
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            if matches!(io.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock) {
+                return MedImgError::Timeout(message);
+            }
+        }
+        source = err.source();
+    }
+    MedImgError::Storage(message)
+}
+
+/// The `boundary` parameter of a `multipart/*` content type.
+fn boundary_of(content_type: &str) -> Option<String> {
+    let mut params = content_type.split(';');
+    if !params.next()?.trim().to_ascii_lowercase().starts_with("multipart/") {
+        return None;
+    }
+    params.find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+/// Position of `needle` in `haystack` at or after `from`.
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| i + from)
+}
+
+/// Split a `multipart/related` body into its part contents.
+fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<Vec<u8>>> {
+    let malformed = |what: &str| MedImgError::InvalidFormat(format!("Malformed multipart response: {}", what));
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let next_delimiter = format!("\r\n--{}", boundary).into_bytes();
+
+    let mut cursor = find(body, &delimiter, 0).ok_or_else(|| malformed("no boundary"))? + delimiter.len();
+    let mut parts = Vec::new();
+    while !body[cursor..].starts_with(b"--") {
+        let headers_end = find(body, b"\r\n\r\n", cursor).ok_or_else(|| malformed("unterminated part headers"))?;
+        let content = headers_end + 4;
+        let end = find(body, &next_delimiter, content).ok_or_else(|| malformed("missing closing boundary"))?;
+        parts.push(body[content..end].to_vec());
+        cursor = end + next_delimiter.len();
+    }
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multipart() {
+        let content_type = "multipart/related; type=\"application/dicom\"; boundary=\"abc123\"";
+        let boundary = boundary_of(content_type).unwrap();
+        assert_eq!(boundary, "abc123");
+        assert_eq!(boundary_of("application/dicom"), None);
+
+        let body = b"preamble\r\n--abc123\r\nContent-Type: application/dicom\r\n\r\nfirst\r\n\
+            --abc123\r\nContent-Type: application/dicom\r\n\r\nsec\r\nond\r\n--abc123--\r\n";
+        let parts = parse_multipart(body, &boundary).unwrap();
+        assert_eq!(parts, vec![b"first".to_vec(), b"sec\r\nond".to_vec()]);
+        assert!(parse_multipart(b"--abc123\r\n\r\ntruncated", &boundary).is_err());
+    }
+
+    #[test]
+    fn test_accept_header() {
+        let header = accept_header(&[
+            transfer_syntax::EXPLICIT_VR_LITTLE_ENDIAN.to_string(),
+            "*".to_string(),
+        ]);
+        assert_eq!(
+            header,
+            "multipart/related; type=\"application/dicom\"; transfer-syntax=1.2.840.10008.1.2.1; q=1.00, \
+             multipart/related; type=\"application/dicom\"; transfer-syntax=*; q=0.75"
+        );
+        assert!(is_file_name_safe("1.2.840.1"));
+        assert!(!is_file_name_safe("../x"));
+    }
+}
@@ -0,0 +1,679 @@
+//! NIfTI volume input (`nifti` feature).
+//!
+//! Reads NIfTI-1 and NIfTI-2 volumes (`.nii`, `.nii.gz`, and `.hdr`/`.img`
+//! pairs) so research datasets go through the same codecs and quality
+//! gates as DICOM:
+//!
+//! - [`NiftiVolume::to_image`]: a multi-frame [`ImageData`] with one frame
+//!   per slice (volumes of a time series follow each other)
+//! - [`NiftiVolume::to_secondary_captures`]: one Secondary Capture
+//!   instance per slice, ready for
+//!   [`CompressionPipeline::compress_dicom_to`](crate::pipeline::CompressionPipeline::compress_dicom_to)
+//!
+//! 8- and 16-bit integer and RGB24 voxels are kept as they are. Wider
+//! integers and floating-point voxels are mapped linearly to 16 bits, with
+//! the mapping recorded as Rescale Slope and Intercept; integer volumes
+//! spanning at most 65536 values convert exactly, others lose precision
+//! ([`NiftiImage::exact`]).
+//!
+//! Rows are flipped so slices display with +y (anterior or superior) up,
+//! as NIfTI viewers show them.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::read::GzDecoder;
+
+use crate::config::DecodeLimits;
+use crate::dicom::{DicomFile, SecondaryCapture};
+use crate::error::{MedImgError, Result};
+use crate::pixel::Photometric;
+use crate::ImageData;
+
+/// Size of a NIfTI-1 header.
+const NIFTI1_HEADER_SIZE: i32 = 348;
+
+/// Size of a NIfTI-2 header.
+const NIFTI2_HEADER_SIZE: i32 = 540;
+
+/// Largest value of the 16-bit samples wide voxels are mapped to.
+const MAX_STORED: f64 = u16::MAX as f64;
+
+/// Voxel data type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NiftiDataType {
+    /// Unsigned 8-bit integer.
+    U8,
+    /// Signed 8-bit integer.
+    I8,
+    /// Unsigned 16-bit integer.
+    U16,
+    /// Signed 16-bit integer.
+    I16,
+    /// Unsigned 32-bit integer.
+    U32,
+    /// Signed 32-bit integer.
+    I32,
+    /// Unsigned 64-bit integer.
+    U64,
+    /// Signed 64-bit integer.
+    I64,
+    /// 32-bit float.
+    F32,
+    /// 64-bit float.
+    F64,
+    /// Interleaved 8-bit red, green, and blue.
+    Rgb24,
+}
+
+impl NiftiDataType {
+    /// Type of a header `datatype` code.
+    fn from_code(code: i16) -> Option<Self> {
+        Some(match code {
+            2 => Self::U8,
+            4 => Self::I16,
+            8 => Self::I32,
+            16 => Self::F32,
+            64 => Self::F64,
+            128 => Self::Rgb24,
+            256 => Self::I8,
+            512 => Self::U16,
+            768 => Self::U32,
+            1024 => Self::I64,
+            1280 => Self::U64,
+            _ => return None,
+        })
+    }
+
+    /// Bytes per voxel.
+    pub fn size(self) -> usize {
+        match self {
+            Self::U8 | Self::I8 => 1,
+            Self::U16 | Self::I16 => 2,
+            Self::Rgb24 => 3,
+            Self::U32 | Self::I32 | Self::F32 => 4,
+            Self::U64 | Self::I64 | Self::F64 => 8,
+        }
+    }
+
+    /// Bytes per swapped unit (one component for RGB24).
+    fn word_size(self) -> usize {
+        match self {
+            Self::Rgb24 => 1,
+            other => other.size(),
+        }
+    }
+}
+
+/// Header fields used for conversion.
+#[derive(Debug, Clone)]
+pub struct NiftiHeader {
+    /// Format version (1 or 2).
+    pub version: u8,
+    /// Voxel data type.
+    pub datatype: NiftiDataType,
+    /// Size of dimensions 1-7 (x, y, z, t, ...); 1 for unused ones.
+    pub dim: [usize; 7],
+    /// Voxel spacing of dimensions 1-7 (mm for x, y, and z).
+    pub pixdim: [f64; 7],
+    /// Data scaling slope (0 = none).
+    pub scl_slope: f64,
+    /// Data scaling offset.
+    pub scl_inter: f64,
+    /// Free-text description.
+    pub description: String,
+    /// Offset of the voxel data in a single-file volume.
+    vox_offset: usize,
+    /// Whether the voxels are in the same file as the header.
+    single_file: bool,
+    /// Whether the file is big-endian.
+    big_endian: bool,
+}
+
+impl NiftiHeader {
+    /// Parse the header at the start of `bytes`.
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        let size = bytes
+            .get(..4)
+            .map(|b| [b[0], b[1], b[2], b[3]])
+            .ok_or_else(|| invalid("file is shorter than a header".into()))?;
+        let (version, big_endian) = match (i32::from_le_bytes(size), i32::from_be_bytes(size)) {
+            (NIFTI1_HEADER_SIZE, _) => (1, false),
+            (_, NIFTI1_HEADER_SIZE) => (1, true),
+            (NIFTI2_HEADER_SIZE, _) => (2, false),
+            (_, NIFTI2_HEADER_SIZE) => (2, true),
+            _ => return Err(invalid("not a NIfTI-1 or NIfTI-2 header".into())),
+        };
+        let header_size = if version == 1 { NIFTI1_HEADER_SIZE } else { NIFTI2_HEADER_SIZE } as usize;
+        if bytes.len() < header_size {
+            return Err(invalid("file is shorter than a header".into()));
+        }
+        let fields = Fields { bytes, big_endian };
+
+        let (magic, datatype, dims, pixdim, vox_offset, scl_slope, scl_inter, description) = if version == 1 {
+            (
+                &bytes[344..347],
+                fields.i16(70),
+                std::array::from_fn::<i64, 8, _>(|i| i64::from(fields.i16(40 + 2 * i))),
+                std::array::from_fn::<f64, 8, _>(|i| f64::from(fields.f32(76 + 4 * i))),
+                f64::from(fields.f32(108)),
+                f64::from(fields.f32(112)),
+                f64::from(fields.f32(116)),
+                &bytes[148..228],
+            )
+        } else {
+            (
+                &bytes[4..7],
+                fields.i16(12),
+                std::array::from_fn(|i| fields.i64(16 + 8 * i)),
+                std::array::from_fn(|i| fields.f64(104 + 8 * i)),
+                fields.i64(168) as f64,
+                fields.f64(176),
+                fields.f64(184),
+                &bytes[240..320],
+            )
+        };
+        let single_file = match (magic, version) {
+            (b"n+1", 1) | (b"n+2", 2) => true,
+            (b"ni1", 1) | (b"ni2", 2) => false,
+            _ => return Err(invalid("bad magic".into())),
+        };
+        let datatype = NiftiDataType::from_code(datatype)
+            .ok_or_else(|| invalid(format!("unsupported datatype {}", datatype)))?;
+
+        let rank = dims[0];
+        if !(1..=7).contains(&rank) {
+            return Err(invalid(format!("{} dimensions", rank)));
+        }
+        let mut dim = [1usize; 7];
+        for (axis, size) in dim.iter_mut().enumerate().take(rank as usize) {
+            *size = usize::try_from(dims[axis + 1])
+                .ok()
+                .filter(|&size| size > 0)
+                .ok_or_else(|| invalid(format!("dimension {} has size {}", axis + 1, dims[axis + 1])))?;
+        }
+        if !(0.0..=usize::MAX as f64).contains(&vox_offset) {
+            return Err(invalid(format!("voxel offset {}", vox_offset)));
+        }
+
+        Ok(Self {
+            version,
+            datatype,
+            dim,
+            pixdim: std::array::from_fn(|i| pixdim[i + 1]),
+            scl_slope: if scl_slope.is_finite() { scl_slope } else { 0.0 },
+            scl_inter: if scl_inter.is_finite() { scl_inter } else { 0.0 },
+            description: String::from_utf8_lossy(description)
+                .trim_end_matches('\0')
+                .trim()
+                .to_string(),
+            vox_offset: (vox_offset as usize).max(header_size),
+            single_file,
+            big_endian,
+        })
+    }
+
+    /// Number of voxels.
+    fn voxels(&self) -> Result<usize> {
+        self.dim
+            .iter()
+            .try_fold(1usize, |count, &size| count.checked_mul(size))
+            .ok_or_else(|| invalid("volume is too large".into()))
+    }
+}
+
+/// Reads header fields with the file's byte order.
+struct Fields<'a> {
+    bytes: &'a [u8],
+    big_endian: bool,
+}
+
+impl Fields<'_> {
+    fn array<const N: usize>(&self, at: usize) -> [u8; N] {
+        let mut array: [u8; N] = self.bytes[at..at + N].try_into().expect("N bytes");
+        if self.big_endian {
+            array.reverse();
+        }
+        array
+    }
+
+    fn i16(&self, at: usize) -> i16 {
+        i16::from_le_bytes(self.array(at))
+    }
+
+    fn i64(&self, at: usize) -> i64 {
+        i64::from_le_bytes(self.array(at))
+    }
+
+    fn f32(&self, at: usize) -> f32 {
+        f32::from_le_bytes(self.array(at))
+    }
+
+    fn f64(&self, at: usize) -> f64 {
+        f64::from_le_bytes(self.array(at))
+    }
+}
+
+/// A volume converted to stored sample values.
+#[derive(Debug, Clone)]
+pub struct NiftiImage {
+    /// One frame per slice.
+    pub image: ImageData,
+    /// Rescale Slope mapping stored values to voxel values.
+    pub rescale_slope: f64,
+    /// Rescale Intercept added after the slope.
+    pub rescale_intercept: f64,
+    /// Whether the stored values represent every voxel exactly.
+    pub exact: bool,
+}
+
+/// A NIfTI volume.
+#[derive(Debug, Clone)]
+pub struct NiftiVolume {
+    /// Header.
+    pub header: NiftiHeader,
+    /// Voxels, little-endian.
+    data: Vec<u8>,
+}
+
+impl NiftiVolume {
+    /// Read a `.nii` or `.nii.gz` file, or the `.hdr` of a `.hdr`/`.img`
+    /// pair (either may be gzipped).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Io` if a file cannot be read, `InvalidFormat` if the
+    /// volume is malformed or uses an unsupported data type, and
+    /// `LimitExceeded` if a gzipped file expands past the decode limits.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = read_maybe_gzipped(path).map_err(|e| e.with_file(path))?;
+        let header = NiftiHeader::parse(&bytes).map_err(|e| e.with_file(path))?;
+        if header.single_file {
+            let offset = header.vox_offset;
+            return Self::with_data(header, bytes.get(offset..).unwrap_or_default());
+        }
+
+        let image = image_path(path)
+            .ok_or_else(|| invalid(format!("no image file next to header {}", path.display())))?;
+        let data = read_maybe_gzipped(&image).map_err(|e| e.with_file(&image))?;
+        Self::with_data(header, &data)
+    }
+
+    /// Parse a single-file volume (`.nii`, optionally gzipped) from memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidFormat` if the volume is malformed, uses an
+    /// unsupported data type, or is the header of a `.hdr`/`.img` pair, and
+    /// `LimitExceeded` if it is gzipped and expands past the decode limits.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let bytes = gunzip(bytes.to_vec(), &DecodeLimits::current())?;
+        let header = NiftiHeader::parse(&bytes)?;
+        if !header.single_file {
+            return Err(invalid("header of a .hdr/.img pair; open it from a file".into()));
+        }
+        let offset = header.vox_offset;
+        Self::with_data(header, bytes.get(offset..).unwrap_or_default())
+    }
+
+    /// Volume with the voxels at the start of `data`.
+    fn with_data(header: NiftiHeader, data: &[u8]) -> Result<Self> {
+        let length = header
+            .voxels()?
+            .checked_mul(header.datatype.size())
+            .ok_or_else(|| invalid("volume is too large".into()))?;
+        let mut data = data
+            .get(..length)
+            .ok_or_else(|| invalid(format!("{} bytes of voxels, expected {}", data.len(), length)))?
+            .to_vec();
+        if header.big_endian {
+            for word in data.chunks_exact_mut(header.datatype.word_size()) {
+                word.reverse();
+            }
+        }
+        Ok(Self { header, data })
+    }
+
+    /// Slice width (dimension 1).
+    pub fn width(&self) -> usize {
+        self.header.dim[0]
+    }
+
+    /// Slice height (dimension 2).
+    pub fn height(&self) -> usize {
+        self.header.dim[1]
+    }
+
+    /// Number of slices over all higher dimensions.
+    pub fn slices(&self) -> usize {
+        self.header.dim[2..].iter().product()
+    }
+
+    /// Convert the volume to a multi-frame image with one frame per slice.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidFormat` if a slice is larger than an [`ImageData`]
+    /// can describe.
+    pub fn to_image(&self) -> Result<NiftiImage> {
+        let (width, height, slices) = (self.width(), self.height(), self.slices());
+        let too_large = || invalid(format!("{}x{}x{} volume is too large", width, height, slices));
+        let width32 = u32::try_from(width).map_err(|_| too_large())?;
+        let height32 = u32::try_from(height).map_err(|_| too_large())?;
+        let frames = u32::try_from(slices).map_err(|_| too_large())?;
+
+        let (mut slope, mut intercept) = match self.header.scl_slope {
+            slope if slope != 0.0 => (slope, self.header.scl_inter),
+            _ => (1.0, 0.0),
+        };
+        let (bits, samples, signed, exact, mut pixels) = match self.header.datatype {
+            NiftiDataType::U8 => (8, 1, false, true, self.data.clone()),
+            NiftiDataType::I8 => (8, 1, true, true, self.data.clone()),
+            NiftiDataType::U16 => (16, 1, false, true, self.data.clone()),
+            NiftiDataType::I16 => (16, 1, true, true, self.data.clone()),
+            NiftiDataType::Rgb24 => (8, 3, false, true, self.data.clone()),
+            wide => {
+                let values = self.wide_values(wide);
+                let quantized = quantize(&values);
+                // Stored values map to voxels, which map to real values
+                intercept += slope * quantized.intercept;
+                slope *= quantized.slope;
+                (16, 1, false, quantized.exact, quantized.pixels)
+            }
+        };
+
+        // Flip each slice vertically
+        let row = width * usize::from(samples) * usize::from(bits / 8);
+        for slice in pixels.chunks_exact_mut(row * height) {
+            for y in 0..height / 2 {
+                let (top, bottom) = slice.split_at_mut((height - 1 - y) * row);
+                top[y * row..(y + 1) * row].swap_with_slice(&mut bottom[..row]);
+            }
+        }
+
+        let photometric = if samples == 3 { Photometric::Rgb } else { Photometric::Monochrome2 };
+        let image = ImageData {
+            number_of_frames: frames,
+            photometric_interpretation: photometric.as_str().into(),
+            is_signed: signed,
+            ..ImageData::new(width32, height32, bits, samples, pixels)
+        };
+        Ok(NiftiImage {
+            image,
+            rescale_slope: slope,
+            rescale_intercept: intercept,
+            exact,
+        })
+    }
+
+    /// Convert the volume to a multi-frame image with one frame per slice,
+    /// dropping the rescale.
+    pub fn to_image_data(&self) -> Result<ImageData> {
+        Ok(self.to_image()?.image)
+    }
+
+    /// Convert each slice to a Secondary Capture instance of `capture`'s
+    /// series, numbered from 1, with the volume's pixel spacing and
+    /// rescale.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidFormat` if the slices cannot be stored in DICOM
+    /// (e.g. more than 65535 rows).
+    pub fn to_secondary_captures(&self, capture: &SecondaryCapture) -> Result<Vec<DicomFile>> {
+        let converted = self.to_image()?;
+        let mut capture = capture.clone();
+        let [column, row, ..] = self.header.pixdim;
+        if row > 0.0 && column > 0.0 {
+            capture = capture.pixel_spacing(row, column);
+        }
+        if (converted.rescale_slope, converted.rescale_intercept) != (1.0, 0.0) {
+            capture = capture.rescale(converted.rescale_slope, converted.rescale_intercept);
+        }
+        (0..converted.image.number_of_frames)
+            .map(|index| capture.build(&converted.image.frame_data(index)?, index + 1))
+            .collect()
+    }
+
+    /// Voxels of a type wider than 16 bits as `f64`.
+    fn wide_values(&self, datatype: NiftiDataType) -> Vec<f64> {
+        let words = self.data.chunks_exact(datatype.size()).map(|word| {
+            let mut array = [0u8; 8];
+            array[..word.len()].copy_from_slice(word);
+            array
+        });
+        let half = |w: [u8; 8]| -> [u8; 4] { w[..4].try_into().expect("4 bytes") };
+        match datatype {
+            NiftiDataType::U32 => words.map(|w| f64::from(u32::from_le_bytes(half(w)))).collect(),
+            NiftiDataType::I32 => words.map(|w| f64::from(i32::from_le_bytes(half(w)))).collect(),
+            NiftiDataType::F32 => words.map(|w| f64::from(f32::from_le_bytes(half(w)))).collect(),
+            NiftiDataType::U64 => words.map(|w| u64::from_le_bytes(w) as f64).collect(),
+            NiftiDataType::I64 => words.map(|w| i64::from_le_bytes(w) as f64).collect(),
+            NiftiDataType::F64 => words.map(f64::from_le_bytes).collect(),
+            _ => unreachable!("narrow types are stored directly"),
+        }
+    }
+}
+
+/// Wide voxels mapped to 16 bits.
+struct Quantized {
+    pixels: Vec<u8>,
+    slope: f64,
+    intercept: f64,
+    exact: bool,
+}
+
+/// Map `values` linearly onto 0-65535. Integers spanning at most 65536
+/// values are only offset; non-finite values become the minimum.
+fn quantize(values: &[f64]) -> Quantized {
+    let finite = values.iter().copied().filter(|v| v.is_finite());
+    let min = finite.clone().fold(f64::INFINITY, f64::min);
+    let max = finite.fold(f64::NEG_INFINITY, f64::max);
+    let (min, max) = if min <= max { (min, max) } else { (0.0, 0.0) };
+    let integers = values.iter().all(|v| v.fract() == 0.0);
+    let (slope, exact) = if integers && max - min <= MAX_STORED {
+        (1.0, true)
+    } else {
+        ((max - min) / MAX_STORED, false)
+    };
+
+    let pixels = values
+        .iter()
+        .flat_map(|&v| {
+            let stored = if v.is_finite() && slope > 0.0 { ((v - min) / slope).round() } else { 0.0 };
+            (stored.clamp(0.0, MAX_STORED) as u16).to_le_bytes()
+        })
+        .collect();
+    Quantized {
+        pixels,
+        slope: if slope > 0.0 { slope } else { 1.0 },
+        intercept: min,
+        exact: exact && values.iter().all(|v| v.is_finite()),
+    }
+}
+
+/// Invalid NIfTI volume.
+fn invalid(message: String) -> MedImgError {
+    MedImgError::InvalidFormat(format!("NIfTI: {}", message))
+}
+
+/// Read a file, decompressing it if it is gzipped.
+fn read_maybe_gzipped(path: &Path) -> Result<Vec<u8>> {
+    gunzip(std::fs::read(path)?, &DecodeLimits::current())
+}
+
+/// `bytes`, decompressed if they are gzipped.
+///
+/// # Errors
+///
+/// Returns `LimitExceeded` if the stream expands to more than a header
+/// and `max_output_bytes` of voxels.
+fn gunzip(bytes: Vec<u8>, limits: &DecodeLimits) -> Result<Vec<u8>> {
+    if !bytes.starts_with(&[0x1F, 0x8B]) {
+        return Ok(bytes);
+    }
+    let limit = limits.max_output_bytes.saturating_add(NIFTI2_HEADER_SIZE as u64);
+    let mut data = Vec::new();
+    GzDecoder::new(bytes.as_slice())
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut data)
+        .map_err(|e| invalid(format!("bad gzip stream: {}", e)))?;
+    if data.len() as u64 > limit {
+        return Err(MedImgError::LimitExceeded(format!(
+            "gzipped volume expands past the {} byte limit",
+            limit
+        )));
+    }
+    Ok(data)
+}
+
+/// The `.img` (or `.img.gz`) file next to a `.hdr` header.
+fn image_path(header: &Path) -> Option<PathBuf> {
+    let name = header.file_name()?.to_str()?;
+    let stem = name.strip_suffix(".gz").unwrap_or(name);
+    let stem = stem.strip_suffix(".hdr").or_else(|| stem.strip_suffix(".HDR"))?;
+    ["img", "img.gz", "IMG"]
+        .iter()
+        .map(|ext| header.with_file_name(format!("{}.{}", stem, ext)))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::config::{CompressionCodec, CompressionConfig};
+    use crate::pipeline::CompressionPipeline;
+
+    /// A NIfTI-1 file of `dim` voxels of type `code`, little- or
+    /// big-endian, with the voxels in `data` (little-endian).
+    fn nifti1(dim: &[i16], code: i16, data: &[u8], word: usize, big_endian: bool) -> Vec<u8> {
+        let mut header = vec![0u8; 352];
+        let put = |header: &mut Vec<u8>, at: usize, bytes: &[u8]| {
+            let mut bytes = bytes.to_vec();
+            if big_endian {
+                bytes.reverse();
+            }
+            header[at..at + bytes.len()].copy_from_slice(&bytes);
+        };
+        put(&mut header, 0, &348i32.to_le_bytes());
+        put(&mut header, 40, &(dim.len() as i16).to_le_bytes());
+        for (i, size) in dim.iter().enumerate() {
+            put(&mut header, 42 + 2 * i, &size.to_le_bytes());
+        }
+        put(&mut header, 70, &code.to_le_bytes());
+        put(&mut header, 80, &0.5f32.to_le_bytes());
+        put(&mut header, 84, &0.75f32.to_le_bytes());
+        put(&mut header, 108, &352f32.to_le_bytes());
+        header[148..152].copy_from_slice(b"test");
+        header[344..348].copy_from_slice(b"n+1\0");
+        for chunk in data.chunks(word) {
+            header.extend(chunk.iter().rev().filter(|_| big_endian));
+            header.extend(chunk.iter().filter(|_| !big_endian));
+        }
+        header
+    }
+
+    #[test]
+    fn test_int16_volume() {
+        // 3x2 slices, 2 slices; rows are flipped
+        let voxels: Vec<i16> = vec![-1000, 0, 1000, 1, 2, 3, 10, 20, 30, 40, 50, 60];
+        let data = crate::pixel::encode_le(&voxels);
+        for big_endian in [false, true] {
+            let volume = NiftiVolume::from_bytes(&nifti1(&[3, 2, 2], 4, &data, 2, big_endian)).unwrap();
+            assert_eq!((volume.width(), volume.height(), volume.slices()), (3, 2, 2));
+            assert_eq!(volume.header.description, "test");
+            let converted = volume.to_image().unwrap();
+            assert!(converted.exact);
+            assert_eq!(converted.image.number_of_frames, 2);
+            assert!(converted.image.is_signed);
+            let flipped: Vec<i16> = vec![1, 2, 3, -1000, 0, 1000, 40, 50, 60, 10, 20, 30];
+            assert_eq!(converted.image.pixel_data, crate::pixel::encode_le(&flipped));
+        }
+    }
+
+    #[test]
+    fn test_float_volume_is_quantized() {
+        let voxels: [f32; 4] = [0.0, 0.25, 0.5, 1.0];
+        let data: Vec<u8> = voxels.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let gz = {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&nifti1(&[2, 2], 16, &data, 4, false)).unwrap();
+            encoder.finish().unwrap()
+        };
+        let converted = NiftiVolume::from_bytes(&gz).unwrap().to_image().unwrap();
+        assert!(!converted.exact);
+        assert_eq!(converted.image.bits_per_sample, 16);
+        let stored = converted.image.pixel_data.chunks(2).map(|w| u16::from_le_bytes([w[0], w[1]]));
+        // Last row first; each stored value maps back within half a step
+        for (stored, original) in stored.zip([0.5, 1.0, 0.0, 0.25]) {
+            let value = f64::from(stored) * converted.rescale_slope + converted.rescale_intercept;
+            assert!((value - original).abs() <= converted.rescale_slope / 2.0);
+        }
+
+        // Integers spanning less than 16 bits only get an offset
+        let labels: Vec<u8> = [70_000i32, 70_001, 70_002, 70_003].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let volume = NiftiVolume::from_bytes(&nifti1(&[2, 2], 8, &labels, 4, false)).unwrap();
+        let converted = volume.to_image().unwrap();
+        assert!(converted.exact);
+        assert_eq!((converted.rescale_slope, converted.rescale_intercept), (1.0, 70_000.0));
+    }
+
+    #[test]
+    fn test_gunzip_limit() {
+        let voxels = vec![0u8; 64 * 64];
+        let gz = {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&nifti1(&[64, 64], 2, &voxels, 1, false)).unwrap();
+            encoder.finish().unwrap()
+        };
+        let limits = |max_output_bytes| DecodeLimits {
+            max_output_bytes,
+            ..DecodeLimits::default()
+        };
+        // The header and extension block do not count against the limit
+        assert_eq!(gunzip(gz.clone(), &limits(64 * 64)).unwrap().len(), 352 + 64 * 64);
+        assert!(matches!(gunzip(gz, &limits(1024)), Err(MedImgError::LimitExceeded(_))));
+    }
+
+    #[test]
+    fn test_secondary_captures_compress() {
+        let dir = TempDir::new().unwrap();
+        let (width, height) = (16, 8);
+        let voxels: Vec<u8> = (0..width * height * 3).map(|i| (i % 251) as u8).collect();
+        let path = dir.path().join("volume.nii");
+        std::fs::write(&path, nifti1(&[16, 8, 3], 2, &voxels, 1, false)).unwrap();
+
+        let volume = NiftiVolume::open(&path).unwrap();
+        let image = volume.to_image_data().unwrap();
+        let slices = volume
+            .to_secondary_captures(&SecondaryCapture::new().modality("MR"))
+            .unwrap();
+        assert_eq!(slices.len(), 3);
+        assert_eq!(slices[0].metadata.study_uid, slices[2].metadata.study_uid);
+
+        let pipeline = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::JpegLs));
+        for (index, slice) in slices.into_iter().enumerate() {
+            let output = dir.path().join(format!("slice{}.dcm", index));
+            let result = pipeline.compress_dicom_to(slice, &path, &output).unwrap();
+            assert!(result.verified);
+            let decoded = DicomFile::open(&output).unwrap().decode_image_data().unwrap();
+            assert_eq!(decoded.pixel_data, image.frame_data(index as u32).unwrap().pixel_data);
+        }
+
+        // A .hdr/.img pair reads the same voxels
+        let mut pair = nifti1(&[16, 8, 3], 2, &[], 1, false);
+        pair[344..348].copy_from_slice(b"ni1\0");
+        std::fs::write(dir.path().join("pair.hdr"), &pair).unwrap();
+        std::fs::write(dir.path().join("pair.img"), &voxels).unwrap();
+        let paired = NiftiVolume::open(dir.path().join("pair.hdr")).unwrap();
+        assert_eq!(paired.to_image_data().unwrap().pixel_data, image.pixel_data);
+        assert!(matches!(NiftiVolume::from_bytes(&pair), Err(MedImgError::InvalidFormat(_))));
+    }
+}
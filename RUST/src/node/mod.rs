@@ -0,0 +1,403 @@
+//! Node.js bindings (`node` feature).
+//!
+//! Built as the crate's cdylib and loaded by the package in `node/`, which
+//! wraps progress callbacks in an `EventEmitter`. Every function runs on
+//! the libuv thread pool and returns a `Promise`:
+//!
+//! - `compress(input, output, options?, onProgress?)`: compress a DICOM
+//!   file and write the result
+//! - `analyze(input, options?, onProgress?)`: compress without writing,
+//!   for the statistics
+//! - `metrics(original, compressed)`: PSNR/SSIM and error statistics of
+//!   two DICOM files
+//!
+//! Options override the default configuration as in the HTTP service
+//! (`codec`, `mode`, `ratio`, `bpp`, `near`), and modality safety checks
+//! always apply. Library errors reject the promise with their message;
+//! panics are caught at the boundary and rejected the same way.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+use napi::bindgen_prelude::AsyncTask;
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{Env, Task};
+use napi_derive::napi;
+
+use crate::config::{CompressionCodec, CompressionConfig, CompressionMode, CompressionOverrides};
+use crate::dicom::DicomFile;
+use crate::error::{MedImgError, Result};
+use crate::metrics::{ImageComparator, QualityReport};
+use crate::pipeline::{BatchStats, CompressionPipeline, CompressionResult, PipelineBuilder};
+use crate::progress::{ProgressEvent, ProgressHandler};
+
+/// Progress callback, called with a [`Progress`] on the JavaScript thread.
+type ProgressCallback = ThreadsafeFunction<Progress, ErrorStrategy::Fatal>;
+
+/// Compression options.
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct CompressOptions {
+    /// `"jpeg2000"` or `"jpeg-ls"`.
+    pub codec: Option<String>,
+    /// `"lossless"`, `"lossy"`, or `"near-lossless"`.
+    pub mode: Option<String>,
+    /// Target compression ratio (lossy).
+    pub ratio: Option<f64>,
+    /// Target bits per pixel (lossy).
+    pub bpp: Option<f64>,
+    /// JPEG-LS NEAR parameter (near-lossless).
+    pub near: Option<u32>,
+    /// Measure PSNR/SSIM by round-trip decode.
+    pub measure_quality: Option<bool>,
+}
+
+impl CompressOptions {
+    /// Apply the options to the default configuration.
+    fn config(&self) -> Result<CompressionConfig> {
+        let invalid = |name: &str, value: &str| {
+            MedImgError::Config(format!("Invalid value '{}' for '{}'", value, name))
+        };
+
+        let codec = match self.codec.as_deref() {
+            None => None,
+            Some("jpeg2000") => Some(CompressionCodec::Jpeg2000),
+            Some("jpeg-ls") => Some(CompressionCodec::JpegLs),
+            Some(other) => return Err(invalid("codec", other)),
+        };
+        let mode = match self.mode.as_deref() {
+            None => None,
+            Some("lossless") => Some(CompressionMode::Lossless),
+            Some("lossy") => Some(CompressionMode::Lossy),
+            Some("near-lossless") => Some(CompressionMode::NearLossless),
+            Some(other) => return Err(invalid("mode", other)),
+        };
+        let near_lossless_error = self
+            .near
+            .map(|near| u16::try_from(near).map_err(|_| invalid("near", &near.to_string())))
+            .transpose()?;
+        let overrides = CompressionOverrides {
+            codec,
+            mode,
+            target_ratio: self.ratio.map(|ratio| ratio as f32),
+            target_bpp: self.bpp.map(|bpp| bpp as f32),
+            near_lossless_error,
+            ..Default::default()
+        };
+        Ok(CompressionConfig::default().merge(&overrides).with_mode_defaults())
+    }
+
+    /// Pipeline for the options.
+    fn pipeline(&self) -> Result<CompressionPipeline> {
+        Ok(PipelineBuilder::new()
+            .config(self.config()?)
+            .measure_quality(self.measure_quality.unwrap_or(false))
+            .build())
+    }
+}
+
+/// Progress of a compression, passed to the progress callback.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct Progress {
+    /// Phase (`"reading"`, `"encoding"`, `"verification"`, `"writing"`,
+    /// ...).
+    pub phase: String,
+    /// Progress within the file (0 to 1).
+    pub fraction: f64,
+}
+
+/// Quality metrics of a compressed image against its original.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct QualityMetrics {
+    /// Peak signal-to-noise ratio in dB (`Infinity` if identical).
+    pub psnr_db: f64,
+    /// Structural similarity (0 to 1).
+    pub ssim: f64,
+    /// Largest absolute sample difference.
+    pub max_error: f64,
+    /// Mean absolute sample difference.
+    pub mean_error: f64,
+    /// Root mean square error.
+    pub rmse: f64,
+    /// Percentage of samples that differ.
+    pub diff_pixels_percent: f64,
+    /// Whether the images are identical.
+    pub lossless: bool,
+    /// Overall quality rating.
+    pub overall_quality: String,
+}
+
+impl From<&QualityReport> for QualityMetrics {
+    fn from(report: &QualityReport) -> Self {
+        Self {
+            psnr_db: report.psnr.psnr_db,
+            ssim: report.ssim.ssim,
+            max_error: report.max_error as f64,
+            mean_error: report.mean_error,
+            rmse: report.rmse,
+            diff_pixels_percent: report.diff_pixels_percent,
+            lossless: report.is_lossless(),
+            overall_quality: report.overall_quality().to_string(),
+        }
+    }
+}
+
+/// Result of `compress` or `analyze`.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct CompressResult {
+    /// Input file path.
+    pub source_path: String,
+    /// Output file path (`compress` only).
+    pub output_path: Option<String>,
+    /// Modality of the input.
+    pub modality: String,
+    /// Original pixel data size in bytes.
+    pub original_size: f64,
+    /// Compressed pixel data size in bytes.
+    pub compressed_size: f64,
+    /// Compression ratio.
+    pub compression_ratio: f64,
+    /// Time taken in milliseconds.
+    pub compression_time_ms: f64,
+    /// Whether the compression was lossless.
+    pub lossless: bool,
+    /// Codec used.
+    pub codec: String,
+    /// SHA-256 of the source pixel data.
+    pub pixel_sha256: String,
+    /// SOP Instance UID of the input (if present).
+    pub sop_instance_uid: Option<String>,
+    /// Warnings, e.g. policy overrides.
+    pub warnings: Vec<String>,
+    /// Quality metrics (if `measureQuality` was set).
+    pub quality: Option<QualityMetrics>,
+}
+
+impl From<&CompressionResult> for CompressResult {
+    fn from(result: &CompressionResult) -> Self {
+        Self {
+            source_path: result.source_path.display().to_string(),
+            output_path: result.output_path.as_ref().map(|path| path.display().to_string()),
+            modality: result.modality.to_string(),
+            original_size: result.original_size as f64,
+            compressed_size: result.compressed_size as f64,
+            compression_ratio: result.compression_ratio,
+            compression_time_ms: result.compression_time_ms as f64,
+            lossless: result.is_lossless,
+            codec: result.codec_name.clone(),
+            pixel_sha256: result.pixel_sha256.clone(),
+            sop_instance_uid: result.sop_instance_uid.clone(),
+            warnings: result.warnings.clone(),
+            quality: result.quality.as_ref().map(QualityMetrics::from),
+        }
+    }
+}
+
+/// Run `f`, converting errors and panics into JavaScript errors.
+fn guard<T>(f: impl FnOnce() -> Result<T>) -> napi::Result<T> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => Err(napi::Error::from_reason(e.to_string())),
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            Err(napi::Error::from_reason(format!("Internal error: {}", message)))
+        }
+    }
+}
+
+/// Forwards pipeline progress to a JavaScript callback.
+struct NodeProgress(Option<ProgressCallback>);
+
+impl ProgressHandler for NodeProgress {
+    fn on_progress(&self, event: &ProgressEvent) {
+        if let Some(callback) = &self.0 {
+            let progress = Progress {
+                phase: format!("{:?}", event.phase).to_ascii_lowercase(),
+                fraction: event.file_progress,
+            };
+            callback.call(progress, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+    }
+
+    fn on_error(&self, _error: &MedImgError, _file: Option<&Path>) {}
+
+    fn on_complete(&self, _stats: &BatchStats) {}
+}
+
+/// Compression of one file on the libuv thread pool.
+pub struct CompressTask {
+    pipeline: CompressionPipeline,
+    input: PathBuf,
+    /// `None` to analyze without writing.
+    output: Option<PathBuf>,
+    progress: NodeProgress,
+}
+
+impl Task for CompressTask {
+    type Output = CompressionResult;
+    type JsValue = CompressResult;
+
+    fn compute(&mut self) -> napi::Result<CompressionResult> {
+        guard(|| match &self.output {
+            Some(output) => self.pipeline.compress_file_to_with_progress(&self.input, output, &self.progress),
+            None => self.pipeline.compress_file_with_progress(&self.input, &self.progress),
+        })
+    }
+
+    fn resolve(&mut self, _env: Env, result: CompressionResult) -> napi::Result<CompressResult> {
+        Ok(CompressResult::from(&result))
+    }
+
+    fn finally(&mut self, _env: Env) -> napi::Result<()> {
+        // Release the callback so it no longer keeps the event loop alive
+        self.progress.0 = None;
+        Ok(())
+    }
+}
+
+/// Comparison of two files on the libuv thread pool.
+pub struct MetricsTask {
+    original: PathBuf,
+    compressed: PathBuf,
+}
+
+impl Task for MetricsTask {
+    type Output = QualityReport;
+    type JsValue = QualityMetrics;
+
+    fn compute(&mut self) -> napi::Result<QualityReport> {
+        guard(|| compare(&self.original, &self.compressed))
+    }
+
+    fn resolve(&mut self, _env: Env, report: QualityReport) -> napi::Result<QualityMetrics> {
+        Ok(QualityMetrics::from(&report))
+    }
+}
+
+/// Decode and compare an original and a compressed file.
+fn compare(original: &Path, compressed: &Path) -> Result<QualityReport> {
+    let original = DicomFile::open(original)?.decode_image_data()?;
+    let compressed = DicomFile::open(compressed)?.decode_image_data()?;
+    ImageComparator::new().compare(&original, &compressed)
+}
+
+/// Task compressing `input` to `output`, or analyzing it.
+fn compress_task(
+    input: String,
+    output: Option<String>,
+    options: Option<CompressOptions>,
+    on_progress: Option<ProgressCallback>,
+) -> napi::Result<AsyncTask<CompressTask>> {
+    let pipeline = guard(|| options.unwrap_or_default().pipeline())?;
+    Ok(AsyncTask::new(CompressTask {
+        pipeline,
+        input: PathBuf::from(input),
+        output: output.map(PathBuf::from),
+        progress: NodeProgress(on_progress),
+    }))
+}
+
+/// Compress a DICOM file to `output`.
+#[napi(ts_args_type = "input: string, output: string, options?: CompressOptions, onProgress?: (progress: Progress) => void")]
+pub fn compress(
+    input: String,
+    output: String,
+    options: Option<CompressOptions>,
+    on_progress: Option<ProgressCallback>,
+) -> napi::Result<AsyncTask<CompressTask>> {
+    compress_task(input, Some(output), options, on_progress)
+}
+
+/// Compress a DICOM file without writing it, for the statistics.
+#[napi(ts_args_type = "input: string, options?: CompressOptions, onProgress?: (progress: Progress) => void")]
+pub fn analyze(
+    input: String,
+    options: Option<CompressOptions>,
+    on_progress: Option<ProgressCallback>,
+) -> napi::Result<AsyncTask<CompressTask>> {
+    compress_task(input, None, options, on_progress)
+}
+
+/// Compare a compressed DICOM file with its original.
+#[napi]
+pub fn metrics(original: String, compressed: String) -> AsyncTask<MetricsTask> {
+    AsyncTask::new(MetricsTask {
+        original: PathBuf::from(original),
+        compressed: PathBuf::from(compressed),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::dicom::testing;
+
+    #[test]
+    fn test_options_config() {
+        let options = CompressOptions {
+            codec: Some("jpeg2000".into()),
+            mode: Some("lossy".into()),
+            ratio: Some(12.0),
+            ..Default::default()
+        };
+        let config = options.config().unwrap();
+        assert_eq!(config.codec, CompressionCodec::Jpeg2000);
+        assert_eq!(config.mode, CompressionMode::Lossy);
+        assert_eq!(config.target_ratio, Some(12.0));
+
+        let invalid = CompressOptions {
+            mode: Some("fast".into()),
+            ..Default::default()
+        };
+        assert!(matches!(invalid.config(), Err(MedImgError::Config(_))));
+        let invalid = CompressOptions {
+            near: Some(70_000),
+            ..Default::default()
+        };
+        assert!(invalid.config().is_err());
+    }
+
+    #[test]
+    fn test_compress_and_metrics_tasks() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let output = dir.path().join("out.dcm");
+        let pixels = testing::gradient(16, 8);
+        testing::write_grayscale(&input, 16, 8, "CT", &pixels);
+
+        let options = CompressOptions {
+            codec: Some("jpeg-ls".into()),
+            measure_quality: Some(true),
+            ..Default::default()
+        };
+        let mut task = CompressTask {
+            pipeline: options.pipeline().unwrap(),
+            input: input.clone(),
+            output: Some(output.clone()),
+            progress: NodeProgress(None),
+        };
+        let result = CompressResult::from(&task.compute().unwrap());
+        assert!(result.lossless);
+        assert_eq!(result.output_path, Some(output.display().to_string()));
+        assert!(result.quality.unwrap().lossless);
+
+        let mut task = MetricsTask {
+            original: input,
+            compressed: output,
+        };
+        let metrics = QualityMetrics::from(&task.compute().unwrap());
+        assert!(metrics.lossless);
+
+        task.compressed = dir.path().join("missing.dcm");
+        assert!(task.compute().is_err());
+    }
+}
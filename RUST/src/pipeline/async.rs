@@ -0,0 +1,124 @@
+//! Async variants of the pipeline entry points (`tokio` feature).
+//!
+//! Codec work is CPU-bound, so each call runs the blocking pipeline on
+//! tokio's blocking thread pool (`spawn_blocking`) and awaits the result,
+//! leaving the runtime's worker threads free.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{CompressionPipeline, CompressionResult};
+use crate::error::Result;
+use crate::storage::Storage;
+
+impl CompressionPipeline {
+    /// Compress a single DICOM file without blocking the async runtime.
+    ///
+    /// See [`compress_file`](Self::compress_file).
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let pipeline = CompressionPipeline::new(config);
+    /// let result = pipeline.compress_file_async("input.dcm").await?;
+    /// ```
+    pub async fn compress_file_async<P: AsRef<Path>>(&self, input_path: P) -> Result<CompressionResult> {
+        let pipeline = self.clone();
+        let input_path = input_path.as_ref().to_path_buf();
+        tokio::task::spawn_blocking(move || pipeline.compress_file(&input_path)).await?
+    }
+
+    /// Compress a single DICOM file to `output_path` without blocking the
+    /// async runtime.
+    ///
+    /// See [`compress_file_to`](Self::compress_file_to).
+    pub async fn compress_file_to_async<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        input_path: P,
+        output_path: Q,
+    ) -> Result<CompressionResult> {
+        let pipeline = self.clone();
+        let input_path = input_path.as_ref().to_path_buf();
+        let output_path = output_path.as_ref().to_path_buf();
+        tokio::task::spawn_blocking(move || pipeline.compress_file_to(&input_path, &output_path)).await?
+    }
+
+    /// Compress a stored object to another without blocking the async
+    /// runtime.
+    ///
+    /// See [`compress_object_to`](Self::compress_object_to).
+    pub async fn compress_object_to_async(
+        &self,
+        input: Arc<dyn Storage>,
+        key: String,
+        output: Arc<dyn Storage>,
+        output_key: String,
+    ) -> Result<CompressionResult> {
+        let pipeline = self.clone();
+        tokio::task::spawn_blocking(move || {
+            pipeline.compress_object_to(input.as_ref(), &key, output.as_ref(), &output_key)
+        })
+        .await?
+    }
+
+    /// Compress a DICOM file held in memory without blocking the async
+    /// runtime, returning the encapsulated file and the result.
+    ///
+    /// See [`compress_stream`](Self::compress_stream).
+    pub async fn compress_bytes_async(&self, input: Vec<u8>) -> Result<(Vec<u8>, CompressionResult)> {
+        let pipeline = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut output = Vec::new();
+            let result = pipeline.compress_stream(input.as_slice(), &mut output)?;
+            Ok((output, result))
+        })
+        .await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::config::{CompressionCodec, CompressionConfig};
+    use crate::dicom::{testing, DicomFile};
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_compress_file_async() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let output = dir.path().join("out.dcm");
+        let pixels = testing::gradient(16, 8);
+        testing::write_grayscale(&input, 16, 8, "CT", &pixels);
+
+        let pipeline = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::JpegLs));
+        let result = block_on(pipeline.compress_file_to_async(&input, &output)).unwrap();
+        assert_eq!(result.output_path.as_deref(), Some(output.as_path()));
+        let written = DicomFile::open(&output).unwrap();
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, pixels);
+
+        let (encoded, _) = block_on(pipeline.compress_bytes_async(std::fs::read(&input).unwrap())).unwrap();
+        let written = DicomFile::from_reader(encoded.as_slice()).unwrap();
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, pixels);
+
+        assert!(block_on(pipeline.compress_file_async(dir.path().join("missing.dcm"))).is_err());
+
+        let storage: Arc<dyn Storage> = Arc::new(crate::storage::LocalStorage::new(dir.path()));
+        let result = block_on(pipeline.compress_object_to_async(
+            storage.clone(),
+            "in.dcm".into(),
+            storage,
+            "objects/out.dcm".into(),
+        ))
+        .unwrap();
+        assert_eq!(result.output_path, Some(dir.path().join("objects/out.dcm")));
+    }
+}
@@ -3,36 +3,97 @@
 //! This module orchestrates the compression workflow, handling single files
 //! and batch operations with progress reporting.
 
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::time::Instant;
 
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::audit::access;
 use crate::codec::{Codec, CodecFactory};
-use crate::config::{CompressionConfig, CompressionMode};
-use crate::dicom::{DicomFile, DicomMetadata};
+use crate::config::{CompressionCodec, CompressionConfig, CompressionMode, Modality};
+use crate::dicom::{DicomFile, DicomMetadata, DicomWriter};
 use crate::error::{MedImgError, Result};
+use crate::memory::MemoryAccountant;
+use crate::metrics::{ImageComparator, QualityReport, QualityStats};
+use crate::pixel::Photometric;
+use crate::progress::{NullProgress, ProgressEvent, ProgressHandler};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::storage::Storage;
 use crate::ImageData;
 
+#[cfg(feature = "tokio")]
+mod r#async;
+
+/// Log target for safety-relevant events such as overridden modality checks.
+pub const AUDIT_LOG_TARGET: &str = "medimg::audit";
+
+/// Time spent in each phase of compressing a single file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    /// Reading, validating, and extracting pixel data (ms).
+    pub read_ms: u64,
+    /// Encoding pixel data (ms).
+    pub encode_ms: u64,
+    /// Round-trip verification and quality measurement (ms).
+    pub verify_ms: u64,
+    /// Writing the output file (ms).
+    pub write_ms: u64,
+}
+
+impl PhaseTimings {
+    /// Total time across all phases (ms).
+    pub fn total_ms(&self) -> u64 {
+        self.read_ms + self.encode_ms + self.verify_ms + self.write_ms
+    }
+}
+
+impl std::fmt::Display for PhaseTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "read {}ms, encode {}ms, verify {}ms, write {}ms",
+            self.read_ms, self.encode_ms, self.verify_ms, self.write_ms
+        )
+    }
+}
+
 /// Result of a compression operation.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct CompressionResult {
     /// Original file path.
     pub source_path: PathBuf,
     /// Output file path (if written).
     pub output_path: Option<PathBuf>,
+    /// Modality of the source image.
+    pub modality: Modality,
     /// Original size in bytes.
     pub original_size: usize,
-    /// Compressed size in bytes.
+    /// Compressed size in bytes (the encoded pixel data only).
     pub compressed_size: usize,
+    /// Size in bytes of the DICOM output as written (if written).
+    pub output_size: Option<usize>,
     /// Compression ratio.
     pub compression_ratio: f64,
     /// Time taken for compression in milliseconds.
     pub compression_time_ms: u64,
+    /// Per-phase breakdown of the compression time.
+    pub timings: PhaseTimings,
     /// Whether compression was lossless.
     pub is_lossless: bool,
     /// Codec used.
     pub codec_name: String,
     /// Any warnings generated.
     pub warnings: Vec<String>,
+    /// Quality metrics from a round-trip decode (if measured).
+    pub quality: Option<QualityReport>,
+    /// SHA-256 of the source pixel data (see [`ImageData::sha256`]).
+    pub pixel_sha256: String,
+    /// SOP Instance UID of the source (if present).
+    pub sop_instance_uid: Option<String>,
+    /// Whether the lossless round trip was decoded and verified.
+    pub verified: bool,
 }
 
 impl CompressionResult {
@@ -47,7 +108,7 @@ impl CompressionResult {
 }
 
 /// Statistics for batch compression operations.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize)]
 pub struct BatchStats {
     /// Total files processed.
     pub total_files: usize,
@@ -63,6 +124,8 @@ pub struct BatchStats {
     pub total_compressed_bytes: usize,
     /// Total processing time in milliseconds.
     pub total_time_ms: u64,
+    /// Aggregate quality distribution (when quality gating is enabled).
+    pub quality: Option<QualityStats>,
 }
 
 impl BatchStats {
@@ -86,11 +149,17 @@ impl BatchStats {
 }
 
 /// Compression pipeline for processing DICOM files.
+#[derive(Clone)]
 pub struct CompressionPipeline {
     /// Compression configuration.
     config: CompressionConfig,
     /// Whether to perform dry-run (no actual file writing).
     dry_run: bool,
+    /// Whether to measure quality by round-trip decode.
+    measure_quality: bool,
+    /// Whether to memory-map native pixel data of input files.
+    #[cfg(feature = "mmap")]
+    memory_map: bool,
 }
 
 impl CompressionPipeline {
@@ -99,6 +168,9 @@ impl CompressionPipeline {
         Self {
             config,
             dry_run: false,
+            measure_quality: false,
+            #[cfg(feature = "mmap")]
+            memory_map: false,
         }
     }
 
@@ -108,27 +180,259 @@ impl CompressionPipeline {
         self
     }
 
+    /// Enable quality measurement (PSNR/SSIM) by round-trip decode.
+    pub fn measure_quality(mut self, measure: bool) -> Self {
+        self.measure_quality = measure;
+        self
+    }
+
+    /// Memory-map the native pixel data of input files instead of reading
+    /// it (see [`DicomFile::open_mapped`]).
+    #[cfg(feature = "mmap")]
+    pub fn memory_map(mut self, memory_map: bool) -> Self {
+        self.memory_map = memory_map;
+        self
+    }
+
+    /// Open an input file, mapped if enabled.
+    fn open(&self, path: &Path) -> Result<DicomFile> {
+        #[cfg(feature = "mmap")]
+        if self.memory_map {
+            return DicomFile::open_mapped(path);
+        }
+        DicomFile::open(path)
+    }
+
     /// Compress a single DICOM file.
     pub fn compress_file<P: AsRef<Path>>(&self, input_path: P) -> Result<CompressionResult> {
-        let input_path = input_path.as_ref();
+        self.compress_file_with_progress(input_path, &NullProgress)
+    }
+
+    /// Compress a single DICOM file, reporting encode progress.
+    ///
+    /// Codec progress (per tile, stage, strip, or frame) is forwarded to
+    /// `progress` as `Encoding` events carrying `file_progress` and the
+    /// pixel bytes encoded so far.
+    pub fn compress_file_with_progress<P: AsRef<Path>>(
+        &self,
+        input_path: P,
+        progress: &dyn ProgressHandler,
+    ) -> Result<CompressionResult> {
+        self.compress(Source::Path(input_path.as_ref()), Sink::None, progress)
+    }
+
+    /// Compress a single DICOM file and write the result to `output_path`.
+    ///
+    /// The destination is checked for writability before any work is done.
+    /// In dry-run mode nothing is written and `output_path` is left unset in
+    /// the result.
+    pub fn compress_file_to<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        input_path: P,
+        output_path: Q,
+    ) -> Result<CompressionResult> {
+        self.compress_file_to_with_progress(input_path, output_path, &NullProgress)
+    }
+
+    /// Compress a single DICOM file to `output_path`, reporting encode
+    /// progress.
+    ///
+    /// See [`compress_file_to`](Self::compress_file_to) and
+    /// [`compress_file_with_progress`](Self::compress_file_with_progress).
+    pub fn compress_file_to_with_progress<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        input_path: P,
+        output_path: Q,
+        progress: &dyn ProgressHandler,
+    ) -> Result<CompressionResult> {
+        let output_path = output_path.as_ref();
+        ensure_writable(output_path)?;
+        self.compress(Source::Path(input_path.as_ref()), Sink::Path(output_path), progress)
+    }
+
+    /// Compress a dataset already in memory and write the result to
+    /// `output_path`.
+    ///
+    /// Used when the dataset was modified before compression (e.g.
+    /// de-identified). `source_path` is only used for reporting.
+    pub fn compress_dicom_to<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        dicom: DicomFile,
+        source_path: P,
+        output_path: Q,
+    ) -> Result<CompressionResult> {
+        let output_path = output_path.as_ref();
+        ensure_writable(output_path)?;
+        self.compress(
+            Source::Loaded(source_path.as_ref(), Box::new(dicom)),
+            Sink::Path(output_path),
+            &NullProgress,
+        )
+    }
+
+    /// Compress a DICOM stream and write the encapsulated result to `output`.
+    ///
+    /// Used for shell pipelines (`-i - -o -`). The result's `source_path`
+    /// and `output_path` are reported as `-`. In dry-run mode nothing is
+    /// written.
+    pub fn compress_stream<R: Read, W: Write>(
+        &self,
+        input: R,
+        output: W,
+    ) -> Result<CompressionResult> {
+        self.compress_stream_with_progress(input, output, &NullProgress)
+    }
+
+    /// Compress a DICOM stream, reporting encode progress.
+    ///
+    /// See [`compress_stream`](Self::compress_stream) and
+    /// [`compress_file_with_progress`](Self::compress_file_with_progress).
+    pub fn compress_stream_with_progress<R: Read, W: Write>(
+        &self,
+        mut input: R,
+        mut output: W,
+        progress: &dyn ProgressHandler,
+    ) -> Result<CompressionResult> {
+        self.compress(
+            Source::Reader(&mut input),
+            Sink::Writer(&mut output),
+            progress,
+        )
+    }
+
+    /// Compress the object at `key` in `input` and write the result to
+    /// `output_key` in `output`.
+    ///
+    /// Local inputs are opened directly (mapped if enabled); other objects
+    /// are read into memory first. The result reports both objects by their
+    /// [`uri`](Storage::uri). In dry-run mode nothing is written.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn compress_object_to(
+        &self,
+        input: &dyn Storage,
+        key: &str,
+        output: &dyn Storage,
+        output_key: &str,
+    ) -> Result<CompressionResult> {
+        self.compress_object_to_with_progress(input, key, output, output_key, &NullProgress)
+    }
+
+    /// Compress an object to `output_key` in `output`, reporting encode
+    /// progress.
+    ///
+    /// See [`compress_object_to`](Self::compress_object_to).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn compress_object_to_with_progress(
+        &self,
+        input: &dyn Storage,
+        key: &str,
+        output: &dyn Storage,
+        output_key: &str,
+        progress: &dyn ProgressHandler,
+    ) -> Result<CompressionResult> {
+        self.compress_object(input, key, Some((output, output_key)), progress)
+    }
+
+    /// Compress an object without writing the result, reporting encode
+    /// progress.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn compress_object_with_progress(
+        &self,
+        input: &dyn Storage,
+        key: &str,
+        progress: &dyn ProgressHandler,
+    ) -> Result<CompressionResult> {
+        self.compress_object(input, key, None, progress)
+    }
+
+    /// Compress an object, optionally writing the result to another.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn compress_object(
+        &self,
+        input: &dyn Storage,
+        key: &str,
+        output: Option<(&dyn Storage, &str)>,
+        progress: &dyn ProgressHandler,
+    ) -> Result<CompressionResult> {
+        let local_path = input.local_path(key);
+        let uri = PathBuf::from(input.uri(key));
+        let source = match &local_path {
+            Some(path) => Source::Path(path),
+            None => {
+                let data = input.read(key)?;
+                let dicom = DicomFile::from_reader(data.as_slice()).map_err(|e| e.with_file(&uri))?;
+                Source::Loaded(&uri, Box::new(dicom))
+            }
+        };
+
+        let sink = match output {
+            Some((output, output_key)) => Sink::Object(output, output_key),
+            None => Sink::None,
+        };
+        self.compress(source, sink, progress)
+    }
+
+    /// Compress a file, optionally writing the encapsulated result.
+    fn compress(
+        &self,
+        source: Source<'_>,
+        sink: Sink<'_>,
+        progress: &dyn ProgressHandler,
+    ) -> Result<CompressionResult> {
+        let input_path = source.path();
         let start = Instant::now();
         let mut warnings = Vec::new();
+        let mut timings = PhaseTimings::default();
+
+        // Sizes and durations are recorded as i64, which OpenTelemetry
+        // exports as numbers (unsigned values become strings)
+        let span = tracing::info_span!(
+            "compress_file",
+            file = %input_path.display(),
+            study_uid_hash = tracing::field::Empty,
+            codec = tracing::field::Empty,
+            mode = tracing::field::Empty,
+            original_size = tracing::field::Empty,
+            compressed_size = tracing::field::Empty,
+            ratio = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        let _guard = span.enter();
 
         log::info!("Processing: {}", input_path.display());
 
         // Open DICOM file
-        let dicom_file = DicomFile::open(input_path)?;
-
-        // Validate against modality constraints
-        if let Err(e) = self
-            .config
-            .validate_for_modality(dicom_file.modality())
-        {
-            if !self.config.override_safety_checks {
-                return Err(MedImgError::Validation(e));
+        let mut dicom_file = {
+            let _phase = tracing::debug_span!("phase", phase = "read").entered();
+            match source {
+                Source::Path(path) => self.open(path)?,
+                Source::Reader(reader) => DicomFile::from_reader(reader)?,
+                Source::Loaded(_, dicom) => *dicom,
             }
-            warnings.push(format!("Safety check overridden: {}", e));
+        };
+        access::read(input_path, &dicom_file.metadata);
+        if let Some(hash) = dicom_file.metadata.study_uid_hash() {
+            span.record("study_uid_hash", hash.as_str());
+        }
+
+        // Resolve per-modality settings and validate against modality constraints
+        let config = self.config.for_modality(dicom_file.modality());
+        span.record("codec", tracing::field::debug(config.codec));
+        span.record("mode", tracing::field::debug(config.mode));
+        config
+            .validate()
+            .map_err(|problems| MedImgError::invalid_config(&problems))?;
+        if dicom_file.is_video() {
+            return self.pass_through(dicom_file, input_path, sink, start, &span);
         }
+        let config = config
+            .with_bits_per_pixel(stored_bits_per_pixel(&dicom_file.metadata))
+            .resolve_near(dicom_file.metadata.bits_stored, dicom_file.metadata.rescale_slope)
+            .map_err(|problem| MedImgError::invalid_config(&[problem]))?;
+        if let Some(warning) = check_policy(&config, input_path, &dicom_file.metadata)? {
+            warnings.push(warning);
+        }
+        warnings.extend(check_conformance(&config, input_path));
 
         // Check if already compressed
         if dicom_file.is_compressed() {
@@ -138,12 +442,20 @@ impl CompressionPipeline {
             ));
         }
 
-        // Extract image data
-        let image_data = dicom_file.to_image_data()?;
+        // Wait for room under the memory ceiling before decoding
+        let _reservation = MemoryAccountant::global().reserve(self.working_set(&config, &dicom_file.metadata));
+
+        // Move the image data out of the file (decoding compressed sources)
+        // so the pixels are not held twice while encoding
+        let image_data = dicom_file.take_image_data()?;
         let original_size = image_data.pixel_data.len();
+        let pixel_sha256 = image_data.sha256();
+        let image_data = encodable_photometric(image_data, &mut dicom_file.metadata)?;
+        span.record("original_size", original_size as i64);
+        timings.read_ms = start.elapsed().as_millis() as u64;
 
         // Create codec and compress
-        let codec = CodecFactory::for_config(&self.config);
+        let codec = CodecFactory::for_config(&config)?;
 
         if !codec.can_encode(&image_data) {
             return Err(MedImgError::Codec(format!(
@@ -155,32 +467,313 @@ impl CompressionPipeline {
             )));
         }
 
-        let compressed_data = codec.encode(&image_data, &self.config)?;
-        let compressed_size = compressed_data.len();
+        let encode_start = Instant::now();
+        let compressed_data = {
+            let _phase = tracing::debug_span!("phase", phase = "encode").entered();
+            let report = |fraction: f64| {
+                let encoded = (fraction * original_size as f64) as u64;
+                let event =
+                    ProgressEvent::encoding(input_path, fraction).with_bytes(encoded, original_size as u64);
+                progress.on_progress(&event);
+            };
+            codec.encode_frames(&image_data, &config, Some(&report))?
+        };
+        let compressed_size = compressed_data.iter().map(Vec::len).sum::<usize>();
+        span.record("compressed_size", compressed_size as i64);
+        timings.encode_ms = encode_start.elapsed().as_millis() as u64;
+        let verify_start = Instant::now();
 
         // Verify compression if enabled
-        if self.config.verify_compression && self.config.mode == CompressionMode::Lossless {
-            self.verify_lossless(&codec, &compressed_data, &image_data)?;
+        let verified = config.verify_compression && config.mode == CompressionMode::Lossless;
+        if verified {
+            let _phase = tracing::debug_span!("phase", phase = "verify").entered();
+            self.verify_lossless(codec.as_ref(), &compressed_data, &image_data)?;
+        }
+
+        let quality = if self.measure_quality {
+            let _phase = tracing::debug_span!("phase", phase = "measure").entered();
+            Some(self.measure(codec.as_ref(), &compressed_data, &image_data)?)
+        } else {
+            None
+        };
+        timings.verify_ms = verify_start.elapsed().as_millis() as u64;
+
+        let write_start = Instant::now();
+        let written = match sink {
+            Sink::None => None,
+            _ if self.dry_run => None,
+            sink => {
+                let _phase = tracing::debug_span!("phase", phase = "write").entered();
+                let lossless = config.mode == CompressionMode::Lossless;
+                let target_ts = codec.transfer_syntax_uid(lossless).ok_or_else(|| {
+                    MedImgError::Config(format!(
+                        "Codec {} has no transfer syntax for {:?} mode",
+                        codec.info().name,
+                        config.mode
+                    ))
+                })?;
+                let writer = DicomWriter::new(dicom_file.metadata.clone());
+                sink.write(|to| writer.write_to(&dicom_file, &compressed_data, target_ts, to))?
+            }
+        };
+        let (written, output_size) = written.unzip();
+        if let Some(path) = &written {
+            access::written(path, &dicom_file.metadata);
         }
+        timings.write_ms = write_start.elapsed().as_millis() as u64;
 
         let compression_time_ms = start.elapsed().as_millis() as u64;
+        let compression_ratio = original_size as f64 / compressed_size as f64;
+        span.record("ratio", compression_ratio);
+        span.record("duration_ms", compression_time_ms as i64);
+        log::debug!(
+            "Compressed {}: {} -> {} bytes in {} ms ({})",
+            input_path.display(),
+            original_size,
+            compressed_size,
+            compression_time_ms,
+            timings
+        );
+
+        Ok(CompressionResult {
+            source_path: input_path.to_path_buf(),
+            output_path: written,
+            modality: dicom_file.modality().clone(),
+            original_size,
+            compressed_size,
+            output_size,
+            compression_ratio,
+            compression_time_ms,
+            timings,
+            is_lossless: config.mode == CompressionMode::Lossless,
+            codec_name: codec.info().name.to_string(),
+            warnings,
+            quality,
+            pixel_sha256,
+            sop_instance_uid: dicom_file.metadata.sop_instance_uid.clone(),
+            verified,
+        })
+    }
+
+    /// Copy a video instance to `sink` with its fragments intact.
+    ///
+    /// MPEG and HEVC frames are already lossy and depend on each other;
+    /// decoding and recompressing them would lose detail without saving
+    /// space.
+    fn pass_through(
+        &self,
+        dicom_file: DicomFile,
+        input_path: &Path,
+        sink: Sink<'_>,
+        start: Instant,
+        span: &tracing::Span,
+    ) -> Result<CompressionResult> {
+        let stream = dicom_file.get_encapsulated_data()?;
+        let size = stream.len();
+        let name = crate::dicom::utils::transfer_syntax_name(&dicom_file.metadata.transfer_syntax);
+        let pixel_sha256 = Sha256::digest(&stream).iter().map(|byte| format!("{:02x}", byte)).collect();
+        drop(stream);
+        let mut timings = PhaseTimings {
+            read_ms: start.elapsed().as_millis() as u64,
+            ..PhaseTimings::default()
+        };
+        log::info!("Passing through {} video: {}", name, input_path.display());
+
+        let write_start = Instant::now();
+        let written = if self.dry_run {
+            None
+        } else {
+            let _phase = tracing::debug_span!("phase", phase = "write").entered();
+            sink.write(|to| {
+                dicom_file.inner().write_all(to)?;
+                Ok(())
+            })?
+        };
+        let (written, output_size) = written.unzip();
+        if let Some(path) = &written {
+            access::written(path, &dicom_file.metadata);
+        }
+        timings.write_ms = write_start.elapsed().as_millis() as u64;
+
+        let compression_time_ms = start.elapsed().as_millis() as u64;
+        span.record("original_size", size as i64);
+        span.record("compressed_size", size as i64);
+        span.record("ratio", 1.0);
+        span.record("duration_ms", compression_time_ms as i64);
+        Ok(CompressionResult {
+            source_path: input_path.to_path_buf(),
+            output_path: written,
+            modality: dicom_file.modality().clone(),
+            original_size: size,
+            compressed_size: size,
+            output_size,
+            compression_ratio: 1.0,
+            compression_time_ms,
+            timings,
+            is_lossless: false,
+            codec_name: name.to_string(),
+            warnings: vec![format!("{} video passed through without recompression", name)],
+            quality: None,
+            pixel_sha256,
+            sop_instance_uid: dicom_file.metadata.sop_instance_uid.clone(),
+            verified: false,
+        })
+    }
+
+    /// Transcode a DICOM file to the configured codec and write the result.
+    ///
+    /// The source pixel data is decoded from its transfer syntax (native,
+    /// JPEG 2000, or JPEG-LS), re-encoded, and written to `output_path`
+    /// with updated transfer syntax and lossy compression attributes.
+    pub fn transcode_file<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        input_path: P,
+        output_path: Q,
+    ) -> Result<CompressionResult> {
+        let input_path = input_path.as_ref();
+        let output_path = output_path.as_ref();
+        let start = Instant::now();
+        let mut warnings = Vec::new();
+        let mut timings = PhaseTimings::default();
+
+        let span = tracing::info_span!(
+            "transcode_file",
+            file = %input_path.display(),
+            study_uid_hash = tracing::field::Empty,
+            codec = tracing::field::Empty,
+            mode = tracing::field::Empty,
+            original_size = tracing::field::Empty,
+            compressed_size = tracing::field::Empty,
+            ratio = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        let _guard = span.enter();
+
+        let mut dicom_file = self.open(input_path)?;
+        access::read(input_path, &dicom_file.metadata);
+        if let Some(hash) = dicom_file.metadata.study_uid_hash() {
+            span.record("study_uid_hash", hash.as_str());
+        }
+
+        let config = self.config.for_modality(dicom_file.modality());
+        span.record("codec", tracing::field::debug(config.codec));
+        span.record("mode", tracing::field::debug(config.mode));
+        config
+            .validate()
+            .map_err(|problems| MedImgError::invalid_config(&problems))?;
+        let config = config
+            .with_bits_per_pixel(stored_bits_per_pixel(&dicom_file.metadata))
+            .resolve_near(dicom_file.metadata.bits_stored, dicom_file.metadata.rescale_slope)
+            .map_err(|problem| MedImgError::invalid_config(&[problem]))?;
+        if let Some(warning) = check_policy(&config, input_path, &dicom_file.metadata)? {
+            warnings.push(warning);
+        }
+        warnings.extend(check_conformance(&config, input_path));
+
+        let source_ts = dicom_file.metadata.transfer_syntax.clone();
+        let _reservation = MemoryAccountant::global().reserve(self.working_set(&config, &dicom_file.metadata));
+        let image_data = dicom_file.take_image_data()?;
+        let original_size = image_data.pixel_data.len();
+        let pixel_sha256 = image_data.sha256();
+        let image_data = encodable_photometric(image_data, &mut dicom_file.metadata)?;
+        timings.read_ms = start.elapsed().as_millis() as u64;
+
+        let codec = CodecFactory::for_config(&config)?;
+        let lossless = config.mode == CompressionMode::Lossless;
+        let target_ts = codec.transfer_syntax_uid(lossless).ok_or_else(|| {
+            MedImgError::Config(format!(
+                "Codec {} has no transfer syntax for {:?} mode",
+                codec.info().name,
+                config.mode
+            ))
+        })?;
+
+        if !crate::dicom::utils::is_lossless_transfer_syntax(&source_ts) {
+            warnings.push(format!(
+                "Source is lossy ({}); transcoding cannot restore lost detail",
+                crate::dicom::utils::transfer_syntax_name(&source_ts)
+            ));
+        }
+
+        if !codec.can_encode(&image_data) {
+            return Err(MedImgError::Codec(format!(
+                "Codec {} cannot encode this image ({}x{}, {} bits)",
+                codec.info().name,
+                image_data.width,
+                image_data.height,
+                image_data.bits_per_sample
+            )));
+        }
+
+        let encode_start = Instant::now();
+        let compressed_data = codec.encode_frames(&image_data, &config, None)?;
+        timings.encode_ms = encode_start.elapsed().as_millis() as u64;
 
+        let verify_start = Instant::now();
+        let verified = config.verify_compression && lossless;
+        if verified {
+            self.verify_lossless(codec.as_ref(), &compressed_data, &image_data)?;
+        }
+        let quality = if self.measure_quality {
+            Some(self.measure(codec.as_ref(), &compressed_data, &image_data)?)
+        } else {
+            None
+        };
+        timings.verify_ms = verify_start.elapsed().as_millis() as u64;
+
+        let write_start = Instant::now();
+        let (written, output_size) = if self.dry_run {
+            (None, None)
+        } else {
+            DicomWriter::new(dicom_file.metadata.clone()).write(
+                &dicom_file,
+                &compressed_data,
+                target_ts,
+                output_path,
+            )?;
+            access::written(output_path, &dicom_file.metadata);
+            let size = std::fs::metadata(output_path)?.len() as usize;
+            (Some(output_path.to_path_buf()), Some(size))
+        };
+        timings.write_ms = write_start.elapsed().as_millis() as u64;
+
+        log::info!(
+            "Transcoded {} from {} to {}",
+            input_path.display(),
+            crate::dicom::utils::transfer_syntax_name(&source_ts),
+            crate::dicom::utils::transfer_syntax_name(target_ts)
+        );
+
+        let compressed_size = compressed_data.iter().map(Vec::len).sum::<usize>();
+        let compression_ratio = original_size as f64 / compressed_size as f64;
+        let compression_time_ms = start.elapsed().as_millis() as u64;
+        span.record("original_size", original_size as i64);
+        span.record("compressed_size", compressed_size as i64);
+        span.record("ratio", compression_ratio);
+        span.record("duration_ms", compression_time_ms as i64);
         Ok(CompressionResult {
             source_path: input_path.to_path_buf(),
-            output_path: None, // MVP doesn't write files yet
+            output_path: written,
+            modality: dicom_file.modality().clone(),
             original_size,
             compressed_size,
-            compression_ratio: original_size as f64 / compressed_size as f64,
+            output_size,
+            compression_ratio,
             compression_time_ms,
-            is_lossless: self.config.mode == CompressionMode::Lossless,
+            timings,
+            is_lossless: lossless,
             codec_name: codec.info().name.to_string(),
             warnings,
+            quality,
+            pixel_sha256,
+            sop_instance_uid: dicom_file.metadata.sop_instance_uid.clone(),
+            verified,
         })
     }
 
-    /// Compress an in-memory image.
+    /// Compress an in-memory single-frame image.
     pub fn compress_image(&self, image: &ImageData) -> Result<Vec<u8>> {
-        let codec = CodecFactory::for_config(&self.config);
+        let codec = CodecFactory::for_config(&self.config)?;
 
         if !codec.can_encode(image) {
             return Err(MedImgError::Codec(format!(
@@ -189,10 +782,10 @@ impl CompressionPipeline {
             )));
         }
 
-        let compressed = codec.encode(image, &self.config)?;
+        let compressed = codec.encode(image, &self.config, None)?;
 
         if self.config.verify_compression && self.config.mode == CompressionMode::Lossless {
-            self.verify_lossless(&codec, &compressed, image)?;
+            self.verify_lossless(codec.as_ref(), std::slice::from_ref(&compressed), image)?;
         }
 
         Ok(compressed)
@@ -200,7 +793,7 @@ impl CompressionPipeline {
 
     /// Decompress data back to image.
     pub fn decompress(&self, data: &[u8], metadata: &DicomMetadata) -> Result<ImageData> {
-        let codec = CodecFactory::for_config(&self.config);
+        let codec = CodecFactory::for_config(&self.config)?;
 
         codec.decode(
             data,
@@ -211,14 +804,31 @@ impl CompressionPipeline {
         )
     }
 
-    /// Verify lossless compression by round-trip decode.
+    /// Peak bytes of pixel buffers while compressing a file: the decoded
+    /// image, its codestreams (at most as large), and a second decode when
+    /// verifying or measuring quality.
+    fn working_set(&self, config: &CompressionConfig, metadata: &DicomMetadata) -> u64 {
+        let image = [
+            metadata.width,
+            metadata.height,
+            u32::from(metadata.samples_per_pixel),
+            u32::from(metadata.bits_allocated.div_ceil(8)),
+            metadata.number_of_frames.max(1),
+        ]
+        .into_iter()
+        .fold(1u64, |bytes, factor| bytes.saturating_mul(u64::from(factor)));
+        let copies = if config.verify_compression || self.measure_quality { 3 } else { 2 };
+        image.saturating_mul(copies)
+    }
+
+    /// Verify lossless compression by round-trip decode of every frame.
     fn verify_lossless(
         &self,
-        codec: &Box<dyn Codec>,
-        compressed: &[u8],
+        codec: &dyn Codec,
+        compressed: &[Vec<u8>],
         original: &ImageData,
     ) -> Result<()> {
-        let decoded = codec.decode(
+        let decoded = codec.decode_frames(
             compressed,
             original.width,
             original.height,
@@ -226,26 +836,218 @@ impl CompressionPipeline {
             original.samples_per_pixel,
         )?;
 
-        if decoded.pixel_data != original.pixel_data {
-            return Err(MedImgError::Validation(
-                "Lossless verification failed: decoded data differs from original".into(),
-            ));
-        }
+        ImageComparator::new().verify_identical(original, &decoded)?;
 
-        log::debug!("Lossless verification passed");
+        log::debug!("Lossless verification passed (pixel SHA-256 {})", decoded.sha256());
         Ok(())
     }
 
+    /// Measure quality of compressed frames against the original image.
+    fn measure(
+        &self,
+        codec: &dyn Codec,
+        compressed: &[Vec<u8>],
+        original: &ImageData,
+    ) -> Result<QualityReport> {
+        let decoded = codec.decode_frames(
+            compressed,
+            original.width,
+            original.height,
+            original.bits_per_sample,
+            original.samples_per_pixel,
+        )?;
+
+        ImageComparator::new().compare(original, &decoded)
+    }
+
     /// Get compression statistics without writing files.
     pub fn analyze<P: AsRef<Path>>(&self, input_path: P) -> Result<CompressionResult> {
         self.compress_file(input_path)
     }
 }
 
+/// Check the resolved configuration against the file's modality and SOP
+/// class.
+///
+/// Overrides are written to the audit log with the file and reason and
+/// returned as a warning for the result.
+fn check_policy(
+    config: &CompressionConfig,
+    path: &Path,
+    metadata: &DicomMetadata,
+) -> Result<Option<String>> {
+    let warning =
+        config.validate_for_image(&metadata.modality, metadata.sop_class_uid.as_deref())?;
+    if let Some(warning) = &warning {
+        log::warn!(target: AUDIT_LOG_TARGET, "{}: {}", path.display(), warning);
+    }
+    Ok(warning)
+}
+
+/// Warn when JPEG 2000 output will come from the built-in encoder, whose
+/// codestreams other decoders cannot read.
+fn check_conformance(config: &CompressionConfig, path: &Path) -> Option<String> {
+    if config.codec != CompressionCodec::Jpeg2000 || config.jpeg2000_encoder.is_conformant() {
+        return None;
+    }
+    let warning = "JPEG 2000 written by the built-in encoder, which other decoders cannot read \
+                   (build with the openjpeg feature for conformant codestreams)";
+    log::warn!("{}: {}", path.display(), warning);
+    Some(warning.to_string())
+}
+
+/// Convert samples the codecs cannot carry as stored, and record the
+/// resulting Photometric Interpretation in `metadata` for the writer.
+///
+/// The codecs compress full-resolution components without a color
+/// transform, so YBR_FULL_422 (subsampled chroma in native data) becomes
+/// YBR_FULL; this keeps the values and only upsamples chroma.
+fn encodable_photometric(image: ImageData, metadata: &mut DicomMetadata) -> Result<ImageData> {
+    let image = match image.photometric() {
+        Some(Photometric::YbrFull422) => image.convert_photometric(Photometric::YbrFull)?,
+        _ => image,
+    };
+    metadata.photometric_interpretation = image.photometric_interpretation.clone();
+    Ok(image)
+}
+
+/// Bits per pixel as stored in the file's pixel data.
+fn stored_bits_per_pixel(metadata: &DicomMetadata) -> f32 {
+    f32::from(metadata.bits_allocated) * f32::from(metadata.samples_per_pixel)
+}
+
+/// Path reported for stdin/stdout streams.
+const STREAM_PATH: &str = "-";
+
+/// Input to a compression run.
+enum Source<'a> {
+    /// DICOM file on disk.
+    Path(&'a Path),
+    /// DICOM byte stream.
+    Reader(&'a mut dyn Read),
+    /// Dataset already in memory, reported under its original path.
+    Loaded(&'a Path, Box<DicomFile>),
+}
+
+impl<'a> Source<'a> {
+    /// Path used for logging and results.
+    fn path(&self) -> &'a Path {
+        match self {
+            Source::Path(path) | Source::Loaded(path, _) => path,
+            Source::Reader(_) => Path::new(STREAM_PATH),
+        }
+    }
+}
+
+/// Destination for the compressed DICOM output.
+enum Sink<'a> {
+    /// Measure only; nothing is written.
+    None,
+    /// Write a DICOM file.
+    Path(&'a Path),
+    /// Write to a byte stream.
+    Writer(&'a mut dyn Write),
+    /// Write an object to storage.
+    #[cfg(not(target_arch = "wasm32"))]
+    Object(&'a dyn Storage, &'a str),
+}
+
+impl Sink<'_> {
+    /// Write the output with `write`, returning where it went and how many
+    /// bytes were written (`None` for [`Sink::None`]).
+    fn write(self, write: impl FnOnce(&mut dyn Write) -> Result<()>) -> Result<Option<(PathBuf, usize)>> {
+        match self {
+            Sink::None => Ok(None),
+            Sink::Path(path) => {
+                let file = std::fs::File::create(path).map_err(|e| MedImgError::from(e).with_file(path))?;
+                let mut file = Counted::new(std::io::BufWriter::new(file));
+                write(&mut file)?;
+                file.flush()?;
+                Ok(Some((path.to_path_buf(), file.count)))
+            }
+            Sink::Writer(to) => {
+                let mut to = Counted::new(to);
+                write(&mut to)?;
+                to.flush()?;
+                Ok(Some((PathBuf::from(STREAM_PATH), to.count)))
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Sink::Object(output, key) => {
+                // Built in memory so each backend sees one whole-object write
+                let mut buffer = Vec::new();
+                write(&mut buffer)?;
+                output.write(key, &buffer)?;
+                Ok(Some((PathBuf::from(output.uri(key)), buffer.len())))
+            }
+        }
+    }
+}
+
+/// Writer that counts the bytes passed through to `inner`.
+struct Counted<W> {
+    inner: W,
+    count: usize,
+}
+
+impl<W: Write> Counted<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, count: 0 }
+    }
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Check that `path` can be created or overwritten.
+fn ensure_writable(path: &Path) -> Result<()> {
+    use std::io::{Error, ErrorKind};
+
+    let not_writable = |kind: ErrorKind, reason: &str| {
+        MedImgError::Io(Error::new(
+            kind,
+            format!("Cannot write {}: {}", path.display(), reason),
+        ))
+    };
+
+    if path.is_dir() {
+        return Err(not_writable(ErrorKind::InvalidInput, "path is a directory"));
+    }
+    if let Ok(meta) = std::fs::metadata(path) {
+        if meta.permissions().readonly() {
+            return Err(not_writable(ErrorKind::PermissionDenied, "file is read-only"));
+        }
+    }
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match std::fs::metadata(parent) {
+        Ok(meta) if !meta.is_dir() => {
+            Err(not_writable(ErrorKind::InvalidInput, "parent is not a directory"))
+        }
+        Ok(meta) if meta.permissions().readonly() => {
+            Err(not_writable(ErrorKind::PermissionDenied, "directory is read-only"))
+        }
+        Ok(_) => Ok(()),
+        Err(_) => Err(not_writable(ErrorKind::NotFound, "directory does not exist")),
+    }
+}
+
 /// Builder for creating compression pipelines with custom settings.
 pub struct PipelineBuilder {
     config: CompressionConfig,
     dry_run: bool,
+    measure_quality: bool,
 }
 
 impl PipelineBuilder {
@@ -254,6 +1056,7 @@ impl PipelineBuilder {
         Self {
             config: CompressionConfig::default(),
             dry_run: false,
+            measure_quality: false,
         }
     }
 
@@ -269,11 +1072,20 @@ impl PipelineBuilder {
         self
     }
 
+    /// Enable or disable quality measurement.
+    pub fn measure_quality(mut self, measure: bool) -> Self {
+        self.measure_quality = measure;
+        self
+    }
+
     /// Build the compression pipeline.
     pub fn build(self) -> CompressionPipeline {
         CompressionPipeline {
             config: self.config,
             dry_run: self.dry_run,
+            measure_quality: self.measure_quality,
+            #[cfg(feature = "mmap")]
+            memory_map: false,
         }
     }
 }
@@ -283,3 +1095,265 @@ impl Default for PipelineBuilder {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{transfer_syntax, Jpeg2000Encoder};
+    use crate::dicom::testing;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_transcode_chain() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let jls = dir.path().join("jls.dcm");
+        let j2k = dir.path().join("j2k.dcm");
+        let pixels = testing::gradient(32, 24);
+        testing::write_grayscale(&input, 32, 24, "CR", &pixels);
+
+        let to_jls = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::JpegLs));
+        let result = to_jls.transcode_file(&input, &jls).unwrap();
+        assert_eq!(result.output_path.as_deref(), Some(jls.as_path()));
+        assert_eq!(result.output_size, Some(std::fs::metadata(&jls).unwrap().len() as usize));
+
+        // Compressed source is decoded before re-encoding
+        let to_j2k = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::Jpeg2000));
+        to_j2k.transcode_file(&jls, &j2k).unwrap();
+
+        let written = DicomFile::open(&j2k).unwrap();
+        assert_eq!(written.metadata.transfer_syntax, transfer_syntax::JPEG_2000_LOSSLESS);
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, pixels);
+    }
+
+    #[test]
+    fn test_access_trail() {
+        use std::sync::Arc;
+
+        use crate::audit::access::{AccessAction, AccessLog, MemorySink};
+
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let output = dir.path().join("out.dcm");
+        testing::write_grayscale(&input, 8, 8, "CR", &testing::gradient(8, 8));
+
+        let sink = Arc::new(MemorySink::new());
+        AccessLog::new("svc-test").sink(sink.clone()).install();
+        let pipeline = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::JpegLs));
+        let result = pipeline.compress_file_to(&input, &output);
+        AccessLog::uninstall();
+        result.unwrap();
+
+        // Other tests may run pipelines while the log is installed
+        let events: Vec<_> = sink
+            .events()
+            .into_iter()
+            .filter(|event| event.resource.starts_with(&dir.path().display().to_string()))
+            .map(|event| (event.action, PathBuf::from(event.resource), event.identifiers.patient_id))
+            .collect();
+        let patient = Some(AccessLog::new("").hash("TEST001"));
+        assert_eq!(
+            events,
+            [(AccessAction::Read, input, patient.clone()), (AccessAction::Write, output, patient)]
+        );
+    }
+
+    #[test]
+    fn test_compress_file_to_writes_output() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let output = dir.path().join("out.dcm");
+        let pixels = testing::gradient(16, 16);
+        testing::write_grayscale(&input, 16, 16, "CR", &pixels);
+
+        let pipeline = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::JpegLs));
+        let result = pipeline.compress_file_to(&input, &output).unwrap();
+        assert_eq!(result.output_path.as_deref(), Some(output.as_path()));
+        assert_eq!(result.pixel_sha256, ImageData::new(16, 16, 8, 1, pixels.clone()).sha256());
+        // The Part 10 file is larger than the codestream it wraps
+        let file_size = std::fs::metadata(&output).unwrap().len() as usize;
+        assert_eq!(result.output_size, Some(file_size));
+        assert!(file_size > result.compressed_size);
+
+        let written = DicomFile::open(&output).unwrap();
+        assert_eq!(written.metadata.transfer_syntax, transfer_syntax::JPEG_LS_LOSSLESS);
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, pixels);
+
+        // Dry run reports the result without writing
+        let skipped = dir.path().join("skipped.dcm");
+        let result = pipeline.dry_run(true).compress_file_to(&input, &skipped).unwrap();
+        assert!(result.output_path.is_none());
+        assert!(result.output_size.is_none());
+        assert!(!skipped.exists());
+    }
+
+    #[test]
+    fn test_builtin_jpeg2000_warning() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        testing::write_grayscale(&input, 16, 16, "CR", &testing::gradient(16, 16));
+        let warned = |jpeg2000_encoder| {
+            let config = CompressionConfig {
+                jpeg2000_encoder,
+                ..CompressionConfig::lossless(CompressionCodec::Jpeg2000)
+            };
+            let result = CompressionPipeline::new(config).compress_file(&input).unwrap();
+            result.warnings.iter().any(|warning| warning.contains("built-in encoder"))
+        };
+        assert!(warned(Jpeg2000Encoder::Builtin));
+        assert_eq!(warned(Jpeg2000Encoder::Auto), !cfg!(feature = "openjpeg"));
+    }
+
+    #[test]
+    fn test_video_passes_through() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("echo.dcm");
+        let output = dir.path().join("out.dcm");
+        let fragments: [&[u8]; 2] = [b"\x00\x00\x01\xb3first", b"second\x00"];
+        testing::write_video(&input, transfer_syntax::MPEG2_MAIN_PROFILE_MAIN_LEVEL, 16, 16, 30, &fragments);
+
+        let pipeline = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::JpegLs));
+        let result = pipeline.compress_file_to(&input, &output).unwrap();
+        assert_eq!(result.compression_ratio, 1.0);
+        assert!(!result.is_lossless);
+        assert!(result.warnings.iter().any(|warning| warning.contains("passed through")));
+
+        let original = DicomFile::open(&input).unwrap();
+        let written = DicomFile::open(&output).unwrap();
+        assert_eq!(written.metadata.transfer_syntax, transfer_syntax::MPEG2_MAIN_PROFILE_MAIN_LEVEL);
+        assert_eq!(written.metadata.sop_instance_uid, original.metadata.sop_instance_uid);
+        assert_eq!(written.get_encapsulated_data().unwrap(), original.get_encapsulated_data().unwrap());
+    }
+
+    #[test]
+    fn test_compress_multiframe() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let output = dir.path().join("out.dcm");
+        let pixels: Vec<u8> = (0..3).flat_map(|_| testing::gradient(16, 16)).collect();
+        testing::write_frames(&input, 16, 16, 3, "XA", &pixels);
+
+        let pipeline = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::Jpeg2000))
+            .measure_quality(true);
+        let result = pipeline.compress_file_to(&input, &output).unwrap();
+        assert_eq!(result.original_size, pixels.len());
+        assert_eq!(result.quality.unwrap().total_pixels, pixels.len());
+
+        // One fragment per frame
+        let written = DicomFile::open(&output).unwrap();
+        assert_eq!(written.get_encapsulated_frames().unwrap().len(), 3);
+        let decoded = written.decode_image_data().unwrap();
+        assert_eq!(decoded.number_of_frames, 3);
+        assert_eq!(decoded.pixel_data, pixels);
+    }
+
+    #[test]
+    fn test_compress_upsamples_ybr_422() {
+        let dir = TempDir::new().unwrap();
+        let gray = dir.path().join("gray.dcm");
+        let input = dir.path().join("in.dcm");
+        let output = dir.path().join("out.dcm");
+        testing::write_grayscale(&gray, 2, 1, "XC", &[0, 0]);
+
+        // Native YBR_FULL_422: Y1 Y2 Cb Cr for the pixel pair
+        let mut source = DicomFile::open(&gray).unwrap();
+        source.metadata.samples_per_pixel = 3;
+        source.metadata.photometric_interpretation = "YBR_FULL_422".into();
+        source.inner_mut().put(dicom::core::DataElement::new(
+            dicom::dictionary_std::tags::SAMPLES_PER_PIXEL,
+            dicom::core::VR::US,
+            dicom::core::PrimitiveValue::from(3u16),
+        ));
+        DicomWriter::new(source.metadata.clone())
+            .write(&source, &[vec![10, 20, 100, 200]], transfer_syntax::EXPLICIT_VR_LITTLE_ENDIAN, &input)
+            .unwrap();
+
+        let pipeline = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::Jpeg2000));
+        let result = pipeline.compress_file_to(&input, &output).unwrap();
+        assert_eq!(result.original_size, 4);
+
+        let written = DicomFile::open(&output).unwrap();
+        assert_eq!(written.metadata.photometric_interpretation, "YBR_FULL");
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, vec![10, 100, 200, 20, 100, 200]);
+    }
+
+    #[test]
+    fn test_compress_stream_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let pixels = testing::gradient(16, 8);
+        testing::write_grayscale(&input, 16, 8, "CT", &pixels);
+
+        let pipeline = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::JpegLs));
+        let mut output = Vec::new();
+        let result = pipeline
+            .compress_stream(std::fs::File::open(&input).unwrap(), &mut output)
+            .unwrap();
+        assert_eq!(result.source_path, PathBuf::from("-"));
+        assert_eq!(result.output_path, Some(PathBuf::from("-")));
+        assert_eq!(result.output_size, Some(output.len()));
+
+        let written = DicomFile::from_reader(output.as_slice()).unwrap();
+        assert_eq!(written.metadata.transfer_syntax, transfer_syntax::JPEG_LS_LOSSLESS);
+        assert_eq!(written.decode_image_data().unwrap().pixel_data, pixels);
+    }
+
+    #[test]
+    fn test_compress_file_to_unwritable_destination() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        testing::write_grayscale(&input, 8, 8, "CR", &testing::gradient(8, 8));
+
+        let pipeline = CompressionPipeline::new(CompressionConfig::lossless(CompressionCodec::JpegLs));
+        let missing = dir.path().join("missing").join("out.dcm");
+
+        assert!(matches!(
+            pipeline.compress_file_to(&input, &missing),
+            Err(MedImgError::Io(_))
+        ));
+        assert!(matches!(
+            pipeline.compress_file_to(&input, dir.path()),
+            Err(MedImgError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_transcode_lossy_requires_override() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in.dcm");
+        let output = dir.path().join("out.dcm");
+        testing::write_grayscale(&input, 8, 8, "MG", &testing::gradient(8, 8));
+
+        let pipeline = CompressionPipeline::new(CompressionConfig::lossy(CompressionCodec::Jpeg2000, 10.0));
+        let result = pipeline.transcode_file(&input, &output);
+
+        assert!(matches!(result, Err(MedImgError::PolicyViolation(_))));
+        assert!(!output.exists());
+    }
+
+    #[test]
+    fn test_per_modality_config() {
+        let dir = TempDir::new().unwrap();
+        let ct = dir.path().join("ct.dcm");
+        let us = dir.path().join("us.dcm");
+        testing::write_grayscale(&ct, 16, 16, "CT", &testing::gradient(16, 16));
+        testing::write_grayscale(&us, 16, 16, "US", &testing::gradient(16, 16));
+
+        let us_config = CompressionConfig {
+            mode: CompressionMode::NearLossless,
+            near_lossless_error: 2,
+            ..CompressionConfig::lossless(CompressionCodec::JpegLs)
+        };
+        let config = CompressionConfig::lossless(CompressionCodec::Jpeg2000)
+            .per_modality([(Modality::US, us_config)].into_iter().collect());
+        let pipeline = CompressionPipeline::new(config).dry_run(true);
+
+        let ct_result = pipeline.compress_file(&ct).unwrap();
+        assert_eq!(ct_result.codec_name, "JPEG 2000");
+        assert!(ct_result.is_lossless);
+
+        let us_result = pipeline.compress_file(&us).unwrap();
+        assert_eq!(us_result.codec_name, "JPEG-LS");
+        assert!(!us_result.is_lossless);
+    }
+}
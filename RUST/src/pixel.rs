@@ -0,0 +1,265 @@
+//! Typed access to pixel data.
+//!
+//! [`ImageData`](crate::ImageData) stores samples as little-endian bytes in
+//! one- or two-byte containers. [`PixelBuffer`] holds them decoded as `u8`,
+//! `u16`, or `i16` (see [`ImageData::as_u16`](crate::ImageData::as_u16) and
+//! friends), so consumers index samples instead of reassembling byte pairs.
+//!
+//! With the `ndarray` feature, images also convert to and from `ndarray`
+//! arrays (`ImageData::to_array2`, `to_array4`, and `from_array`); with
+//! `image-interop`, to and from `image::DynamicImage`
+//! (`ImageData::to_dynamic_image` and `from_dynamic_image`).
+
+use crate::error::Result;
+use crate::ImageData;
+
+mod color;
+mod depth;
+mod hash;
+mod resample;
+mod window;
+
+#[cfg(feature = "ndarray")]
+mod array;
+#[cfg(feature = "image-interop")]
+mod dynamic;
+
+pub use color::{rgb_to_ybr, ybr_to_rgb, Photometric};
+pub use depth::{pack_bits, unpack_bits, BitLayout};
+pub use resample::{PyramidBuilder, ResampleFilter};
+pub use window::{VoiFunction, WindowLevel};
+
+/// A pixel sample type stored little-endian.
+pub trait Sample: Copy + Default + PartialEq + std::fmt::Debug + Send + Sync + 'static {
+    /// Container size in bytes.
+    const BYTES: usize;
+
+    /// Whether samples of this type are signed.
+    const SIGNED: bool;
+
+    /// Decode a sample from `BYTES` little-endian bytes.
+    fn from_le(bytes: &[u8]) -> Self;
+
+    /// Append the sample as little-endian bytes.
+    fn extend_le(self, out: &mut Vec<u8>);
+
+    /// Read `image`'s samples as this type (see
+    /// [`ImageData::as_u8`](crate::ImageData::as_u8) and friends).
+    fn read(image: &ImageData) -> Result<PixelBuffer<Self>>;
+}
+
+impl Sample for u8 {
+    const BYTES: usize = 1;
+    const SIGNED: bool = false;
+
+    fn from_le(bytes: &[u8]) -> Self {
+        bytes[0]
+    }
+
+    fn extend_le(self, out: &mut Vec<u8>) {
+        out.push(self);
+    }
+
+    fn read(image: &ImageData) -> Result<PixelBuffer<Self>> {
+        image.as_u8()
+    }
+}
+
+impl Sample for u16 {
+    const BYTES: usize = 2;
+    const SIGNED: bool = false;
+
+    fn from_le(bytes: &[u8]) -> Self {
+        u16::from_le_bytes([bytes[0], bytes[1]])
+    }
+
+    fn extend_le(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn read(image: &ImageData) -> Result<PixelBuffer<Self>> {
+        image.as_u16()
+    }
+}
+
+impl Sample for i16 {
+    const BYTES: usize = 2;
+    const SIGNED: bool = true;
+
+    fn from_le(bytes: &[u8]) -> Self {
+        i16::from_le_bytes([bytes[0], bytes[1]])
+    }
+
+    fn extend_le(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+
+    fn read(image: &ImageData) -> Result<PixelBuffer<Self>> {
+        image.as_i16()
+    }
+}
+
+/// Decode little-endian samples, ignoring a trailing partial sample.
+pub fn decode_le<T: Sample>(bytes: &[u8]) -> Vec<T> {
+    bytes.chunks_exact(T::BYTES).map(T::from_le).collect()
+}
+
+/// Encode samples as little-endian bytes.
+pub fn encode_le<T: Sample>(samples: &[T]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(samples.len() * T::BYTES);
+    for &sample in samples {
+        sample.extend_le(&mut bytes);
+    }
+    bytes
+}
+
+/// Encode stored values as little-endian containers of
+/// `bits.div_ceil(8)` bytes, keeping the low `bits` bits.
+pub(crate) fn encode_stored(values: &[i64], bits: u16) -> Vec<u8> {
+    let container = bits.div_ceil(8) as usize;
+    let mask = u64::MAX >> (64 - u32::from(bits));
+    let mut bytes = Vec::with_capacity(values.len() * container);
+    for &value in values {
+        bytes.extend_from_slice(&(value as u64 & mask).to_le_bytes()[..container]);
+    }
+    bytes
+}
+
+/// Decoded samples of an image, interleaved by component, row by row and
+/// frame by frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PixelBuffer<T> {
+    /// Image width in pixels.
+    pub width: u32,
+    /// Image height in pixels.
+    pub height: u32,
+    /// Samples per pixel.
+    pub samples_per_pixel: u16,
+    /// Number of frames.
+    pub number_of_frames: u32,
+    /// The samples.
+    pub samples: Vec<T>,
+}
+
+impl<T> PixelBuffer<T> {
+    /// A buffer with the same layout holding `samples`.
+    pub fn with_samples<U>(&self, samples: Vec<U>) -> PixelBuffer<U> {
+        PixelBuffer {
+            width: self.width,
+            height: self.height,
+            samples_per_pixel: self.samples_per_pixel,
+            number_of_frames: self.number_of_frames,
+            samples,
+        }
+    }
+}
+
+impl<T: Sample> PixelBuffer<T> {
+    /// Number of samples in one frame.
+    pub fn frame_len(&self) -> usize {
+        self.width as usize * self.height as usize * self.samples_per_pixel as usize
+    }
+
+    /// Samples of frame `index`, if present.
+    pub fn frame(&self, index: u32) -> Option<&[T]> {
+        let len = self.frame_len();
+        let start = index as usize * len;
+        self.samples.get(start..start + len)
+    }
+
+    /// Samples of row `y` of frame `frame`, if present.
+    pub fn row(&self, frame: u32, y: u32) -> Option<&[T]> {
+        let len = self.width as usize * self.samples_per_pixel as usize;
+        let start = y as usize * len;
+        self.frame(frame)?.get(start..start + len)
+    }
+
+    /// Encode the samples as little-endian bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_le(&self.samples)
+    }
+
+    /// Encode as an image with `bits_per_sample` significant bits,
+    /// MONOCHROME2 for one sample per pixel and RGB for three.
+    pub fn to_image_data(&self, bits_per_sample: u16) -> ImageData {
+        let mut image = ImageData::new(
+            self.width,
+            self.height,
+            bits_per_sample,
+            self.samples_per_pixel,
+            self.to_bytes(),
+        );
+        image.number_of_frames = self.number_of_frames;
+        image.is_signed = T::SIGNED;
+        image.photometric_interpretation = match self.samples_per_pixel {
+            1 => "MONOCHROME2".into(),
+            3 => "RGB".into(),
+            _ => String::new(),
+        };
+        image
+    }
+}
+
+/// Pixel values after a modality rescale (see
+/// [`ImageData::apply_rescale`](crate::ImageData::apply_rescale)).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rescaled {
+    /// Stored values shifted by an integral intercept (unit slope).
+    Shifted(PixelBuffer<i32>),
+    /// Stored values under a general slope and intercept.
+    Float(PixelBuffer<f64>),
+}
+
+impl Rescaled {
+    /// The values as `f64`, in sample order.
+    pub fn to_f64(&self) -> Vec<f64> {
+        match self {
+            Rescaled::Shifted(buffer) => buffer.samples.iter().map(|&v| f64::from(v)).collect(),
+            Rescaled::Float(buffer) => buffer.samples.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_views() {
+        let image = ImageData::new(2, 1, 16, 1, vec![0x34, 0x12, 0xFF, 0xFF]);
+        assert_eq!(image.as_u16().unwrap().samples, vec![0x1234, 0xFFFF]);
+        assert!(image.as_u8().is_err());
+        // Unsigned data has no signed view
+        assert!(image.as_i16().is_err());
+
+        // 8-bit data widens
+        let bytes = ImageData::new(2, 1, 8, 1, vec![7, 200]);
+        assert_eq!(bytes.as_u8().unwrap().samples, vec![7, 200]);
+        assert_eq!(bytes.as_u16().unwrap().samples, vec![7, 200]);
+
+        // Signed samples extend from the stored bit depth
+        let mut signed = ImageData::new(3, 1, 12, 1, vec![0xFF, 0x0F, 0x00, 0x08, 0x05, 0x00]);
+        signed.is_signed = true;
+        assert_eq!(signed.as_i16().unwrap().samples, vec![-1, -2048, 5]);
+
+        // Sizes are checked
+        assert!(ImageData::new(2, 2, 16, 1, vec![0; 6]).as_u16().is_err());
+    }
+
+    #[test]
+    fn test_pixel_buffer_layout() {
+        let image = ImageData {
+            number_of_frames: 2,
+            ..ImageData::new(2, 2, 16, 1, encode_le::<u16>(&[1, 2, 3, 4, 5, 6, 7, 8]))
+        };
+        let buffer = image.as_u16().unwrap();
+        assert_eq!(buffer.frame(1), Some(&[5, 6, 7, 8][..]));
+        assert_eq!(buffer.row(1, 0), Some(&[5, 6][..]));
+        assert_eq!(buffer.frame(2), None);
+        assert_eq!(buffer.to_bytes(), image.pixel_data);
+        let rebuilt = buffer.to_image_data(16);
+        assert_eq!((rebuilt.number_of_frames, rebuilt.pixel_data), (2, image.pixel_data));
+        assert_eq!(rebuilt.photometric_interpretation, "MONOCHROME2");
+        assert_eq!(decode_le::<u16>(&[1, 0, 2]), vec![1]);
+    }
+}
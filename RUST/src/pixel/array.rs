@@ -0,0 +1,145 @@
+//! Conversions between [`ImageData`] and `ndarray` arrays.
+//!
+//! Arrays are indexed `[row, column]` for single-frame grayscale images and
+//! `[frame, row, column, sample]` in general, matching the interleaved
+//! layout of the pixel data.
+
+use ndarray::{Array2, Array4, ArrayBase, Data, Dimension};
+
+use super::{PixelBuffer, Sample};
+use crate::error::{MedImgError, Result};
+use crate::ImageData;
+
+impl ImageData {
+    /// Samples of a single-frame, single-sample image as a `[row, column]`
+    /// array.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` if the image has several frames or samples per
+    /// pixel, or cannot be read as `T` (see [`as_u16`](Self::as_u16) and
+    /// friends).
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let pixels = dicom.decode_image_data()?.to_array2::<u16>()?;
+    /// let mean = pixels.mapv(f64::from).mean();
+    /// ```
+    pub fn to_array2<T: Sample>(&self) -> Result<Array2<T>> {
+        if self.number_of_frames.max(1) != 1 || self.samples_per_pixel != 1 {
+            return Err(MedImgError::ImageData(format!(
+                "Cannot view {} frame(s) of {} sample(s) per pixel as a 2-D array (use to_array4)",
+                self.number_of_frames, self.samples_per_pixel
+            )));
+        }
+        let buffer = T::read(self)?;
+        Array2::from_shape_vec((self.height as usize, self.width as usize), buffer.samples)
+            .map_err(|e| MedImgError::ImageData(format!("Cannot shape pixel array: {}", e)))
+    }
+
+    /// Samples as a `[frame, row, column, sample]` array.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` if the image cannot be read as `T`.
+    pub fn to_array4<T: Sample>(&self) -> Result<Array4<T>> {
+        let buffer = T::read(self)?;
+        let shape = (
+            buffer.number_of_frames as usize,
+            buffer.height as usize,
+            buffer.width as usize,
+            buffer.samples_per_pixel as usize,
+        );
+        Array4::from_shape_vec(shape, buffer.samples)
+            .map_err(|e| MedImgError::ImageData(format!("Cannot shape pixel array: {}", e)))
+    }
+
+    /// Build an image from an array indexed `[row, column]`,
+    /// `[row, column, sample]`, or `[frame, row, column, sample]`.
+    ///
+    /// `bits_per_sample` is the number of significant bits; `i16` arrays
+    /// produce signed images.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` for other dimensionalities, dimensions that do
+    /// not fit the image header, or a bit depth wider than `T`.
+    pub fn from_array<T, S, D>(array: &ArrayBase<S, D>, bits_per_sample: u16) -> Result<Self>
+    where
+        T: Sample,
+        S: Data<Elem = T>,
+        D: Dimension,
+    {
+        if bits_per_sample == 0 || bits_per_sample as usize > T::BYTES * 8 {
+            return Err(MedImgError::ImageData(format!(
+                "{} bits per sample do not fit {}-byte samples",
+                bits_per_sample,
+                T::BYTES
+            )));
+        }
+        let (frames, rows, columns, samples) = match *array.shape() {
+            [rows, columns] => (1, rows, columns, 1),
+            [rows, columns, samples] => (1, rows, columns, samples),
+            [frames, rows, columns, samples] => (frames, rows, columns, samples),
+            ref shape => {
+                return Err(MedImgError::ImageData(format!(
+                    "Cannot build an image from a {}-D array",
+                    shape.len()
+                )))
+            }
+        };
+
+        let dimension = |value: usize, name: &str| {
+            value.try_into().map_err(|_| {
+                MedImgError::ImageData(format!("Array {} {} is out of range", name, value))
+            })
+        };
+        let buffer = PixelBuffer {
+            width: dimension(columns, "columns")?,
+            height: dimension(rows, "rows")?,
+            samples_per_pixel: u16::try_from(samples).map_err(|_| {
+                MedImgError::ImageData(format!("Array samples {} is out of range", samples))
+            })?,
+            number_of_frames: dimension(frames, "frames")?,
+            samples: array.iter().copied().collect(),
+        };
+        Ok(buffer.to_image_data(bits_per_sample))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::{arr2, Array3};
+
+    use crate::ImageData;
+
+    #[test]
+    fn test_array_round_trip() {
+        let pixels = arr2(&[[1u16, 2, 3], [4, 5, 4095]]);
+        let image = ImageData::from_array(&pixels, 12).unwrap();
+        assert_eq!((image.width, image.height, image.bits_per_sample), (3, 2, 12));
+        assert_eq!(image.to_array2::<u16>().unwrap(), pixels);
+        assert!(image.to_array2::<u8>().is_err());
+
+        // Transposed views are copied in logical order
+        let transposed = ImageData::from_array(&pixels.t(), 12).unwrap();
+        assert_eq!(transposed.to_array2::<u16>().unwrap(), pixels.t());
+
+        // Signed arrays produce signed images
+        let signed = ImageData::from_array(&arr2(&[[-1000i16, 1000]]), 16).unwrap();
+        assert!(signed.is_signed);
+        assert_eq!(signed.to_array2::<i16>().unwrap()[[0, 0]], -1000);
+
+        // RGB images keep samples on the last axis
+        let rgb = Array3::from_shape_fn((2, 2, 3), |(y, x, c)| (y * 6 + x * 3 + c) as u8);
+        let image = ImageData::from_array(&rgb, 8).unwrap();
+        assert_eq!(image.photometric_interpretation, "RGB");
+        assert!(image.to_array2::<u8>().is_err());
+        let array = image.to_array4::<u8>().unwrap();
+        assert_eq!(array.shape(), &[1, 2, 2, 3]);
+        assert_eq!(array[[0, 1, 0, 2]], 8);
+
+        assert!(ImageData::from_array(&pixels, 17).is_err());
+    }
+}
@@ -0,0 +1,304 @@
+//! Photometric Interpretation conversions.
+//!
+//! Grayscale images convert between MONOCHROME1 and MONOCHROME2 by
+//! inverting over the stored range. Color images convert between RGB and
+//! YBR_FULL with the full-range ITU-R BT.601 matrix of PS3.3 C.7.6.3.1.2;
+//! YBR_FULL_422 is accepted as a source, including native data with
+//! horizontally subsampled chroma.
+//!
+//! YBR_ICT and YBR_RCT only describe the JPEG 2000 component transform: a
+//! decoder undoes it, so decoded pixel data is RGB (see
+//! [`Photometric::decoded`]).
+
+use super::{encode_stored, PixelBuffer};
+use crate::error::{MedImgError, Result};
+use crate::ImageData;
+
+/// Photometric Interpretation (0028,0004).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Photometric {
+    /// Grayscale, minimum value displayed as white.
+    Monochrome1,
+    /// Grayscale, minimum value displayed as black.
+    Monochrome2,
+    /// Indices into a palette color lookup table.
+    PaletteColor,
+    /// Red, green, and blue samples.
+    Rgb,
+    /// Full-range luminance and chrominance.
+    YbrFull,
+    /// YBR_FULL with chrominance subsampled horizontally by two.
+    YbrFull422,
+    /// JPEG 2000 irreversible component transform.
+    YbrIct,
+    /// JPEG 2000 reversible component transform.
+    YbrRct,
+}
+
+impl Photometric {
+    /// Parse a Photometric Interpretation value.
+    pub fn from_dicom(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "MONOCHROME1" => Some(Photometric::Monochrome1),
+            "MONOCHROME2" => Some(Photometric::Monochrome2),
+            "PALETTE COLOR" => Some(Photometric::PaletteColor),
+            "RGB" => Some(Photometric::Rgb),
+            "YBR_FULL" => Some(Photometric::YbrFull),
+            "YBR_FULL_422" => Some(Photometric::YbrFull422),
+            "YBR_ICT" => Some(Photometric::YbrIct),
+            "YBR_RCT" => Some(Photometric::YbrRct),
+            _ => None,
+        }
+    }
+
+    /// The DICOM code string.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Photometric::Monochrome1 => "MONOCHROME1",
+            Photometric::Monochrome2 => "MONOCHROME2",
+            Photometric::PaletteColor => "PALETTE COLOR",
+            Photometric::Rgb => "RGB",
+            Photometric::YbrFull => "YBR_FULL",
+            Photometric::YbrFull422 => "YBR_FULL_422",
+            Photometric::YbrIct => "YBR_ICT",
+            Photometric::YbrRct => "YBR_RCT",
+        }
+    }
+
+    /// Whether this is MONOCHROME1 or MONOCHROME2.
+    pub fn is_monochrome(self) -> bool {
+        matches!(self, Photometric::Monochrome1 | Photometric::Monochrome2)
+    }
+
+    /// Whether samples are luminance and chrominance.
+    pub fn is_ybr(self) -> bool {
+        matches!(
+            self,
+            Photometric::YbrFull | Photometric::YbrFull422 | Photometric::YbrIct | Photometric::YbrRct
+        )
+    }
+
+    /// Interpretation of pixel data decoded from a codestream labelled
+    /// `self`: RGB for the JPEG 2000 component transforms, unchanged
+    /// otherwise.
+    pub fn decoded(self) -> Self {
+        match self {
+            Photometric::YbrIct | Photometric::YbrRct => Photometric::Rgb,
+            other => other,
+        }
+    }
+}
+
+/// Convert a full-range YBR pixel with samples up to `max_value` to RGB.
+pub fn ybr_to_rgb([y, cb, cr]: [f64; 3], max_value: f64) -> [f64; 3] {
+    let half = (max_value + 1.0) / 2.0;
+    let (cb, cr) = (cb - half, cr - half);
+    [
+        y + 1.402 * cr,
+        y - 0.344136 * cb - 0.714136 * cr,
+        y + 1.772 * cb,
+    ]
+}
+
+/// Convert an RGB pixel with samples up to `max_value` to full-range YBR.
+pub fn rgb_to_ybr([r, g, b]: [f64; 3], max_value: f64) -> [f64; 3] {
+    let half = (max_value + 1.0) / 2.0;
+    [
+        0.299 * r + 0.587 * g + 0.114 * b,
+        -0.168736 * r - 0.331264 * g + 0.5 * b + half,
+        0.5 * r - 0.418688 * g - 0.081312 * b + half,
+    ]
+}
+
+impl ImageData {
+    /// The Photometric Interpretation, if recognized.
+    pub fn photometric(&self) -> Option<Photometric> {
+        Photometric::from_dicom(&self.photometric_interpretation)
+    }
+
+    /// Convert the samples to `target` and relabel the image.
+    ///
+    /// MONOCHROME1 and MONOCHROME2 convert into each other; RGB, YBR_FULL,
+    /// and YBR_FULL_422 convert to RGB or YBR_FULL. YBR_FULL_422 and
+    /// YBR_FULL hold the same values once chroma is upsampled, so that
+    /// conversion is exact; RGB and YBR_FULL round to the nearest value.
+    /// Converting to the current interpretation returns a copy.
+    ///
+    /// Color images must be interleaved (Planar Configuration 0).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` for unrecognized or unsupported conversions,
+    /// signed color samples, or a pixel data size mismatch.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let rgb = image.convert_photometric(Photometric::Rgb)?;
+    /// assert_eq!(rgb.photometric_interpretation, "RGB");
+    /// ```
+    pub fn convert_photometric(&self, target: Photometric) -> Result<ImageData> {
+        let source = self.photometric().ok_or_else(|| {
+            MedImgError::ImageData(format!(
+                "Unknown photometric interpretation '{}'",
+                self.photometric_interpretation
+            ))
+        })?;
+        if source == target {
+            return Ok(self.clone());
+        }
+
+        let unsupported = || {
+            MedImgError::ImageData(format!(
+                "Cannot convert {} to {}",
+                source.as_str(),
+                target.as_str()
+            ))
+        };
+        let image = match (source, target) {
+            (Photometric::Monochrome1, Photometric::Monochrome2)
+            | (Photometric::Monochrome2, Photometric::Monochrome1) => self.inverted()?,
+            (Photometric::YbrFull422, Photometric::YbrFull) => self.upsampled_422()?,
+            (Photometric::YbrFull422, Photometric::Rgb) => self.upsampled_422()?.color_converted(ybr_to_rgb)?,
+            (Photometric::YbrFull, Photometric::Rgb) => self.color_converted(ybr_to_rgb)?,
+            (Photometric::Rgb, Photometric::YbrFull) => self.color_converted(rgb_to_ybr)?,
+            _ => return Err(unsupported()),
+        };
+
+        Ok(ImageData {
+            photometric_interpretation: target.as_str().into(),
+            ..image
+        })
+    }
+
+    /// Grayscale samples inverted over the stored range.
+    fn inverted(&self) -> Result<ImageData> {
+        if self.samples_per_pixel != 1 {
+            return Err(MedImgError::ImageData(format!(
+                "Cannot invert {} samples per pixel",
+                self.samples_per_pixel
+            )));
+        }
+        let stored = self.stored_values()?;
+        let max = (1i64 << self.bits_per_sample) - 1;
+        let inverted: Vec<i64> = stored
+            .samples
+            .iter()
+            // Two's complement NOT maps [min, max] onto [max, min]
+            .map(|&v| if self.is_signed { -1 - v } else { max - v })
+            .collect();
+        Ok(self.with_stored(&stored.with_samples(inverted)))
+    }
+
+    /// Apply `convert` to each pixel of an unsigned three-sample image.
+    fn color_converted(&self, convert: fn([f64; 3], f64) -> [f64; 3]) -> Result<ImageData> {
+        if self.samples_per_pixel != 3 || self.is_signed {
+            return Err(MedImgError::ImageData(format!(
+                "Color conversion needs 3 unsigned samples per pixel, got {}{}",
+                self.samples_per_pixel,
+                if self.is_signed { " signed" } else { "" }
+            )));
+        }
+        let stored = self.stored_values()?;
+        let max = (1i64 << self.bits_per_sample) - 1;
+        let converted: Vec<i64> = stored
+            .samples
+            .chunks_exact(3)
+            .flat_map(|p| {
+                convert([p[0] as f64, p[1] as f64, p[2] as f64], max as f64)
+                    .map(|v| (v.round() as i64).clamp(0, max))
+            })
+            .collect();
+        Ok(self.with_stored(&stored.with_samples(converted)))
+    }
+
+    /// YBR_FULL_422 with chroma at full resolution. Native pixel data
+    /// stores two luminance samples and one chrominance pair per two
+    /// pixels (Y1 Y2 Cb Cr); data already at full size is returned as is.
+    fn upsampled_422(&self) -> Result<ImageData> {
+        let container = self.bits_per_sample.div_ceil(8) as usize;
+        let pixels = self.width as usize * self.height as usize * self.number_of_frames.max(1) as usize;
+        if self.samples_per_pixel != 3 || self.pixel_data.len() != pixels * 2 * container {
+            return Ok(self.packed().into_owned());
+        }
+        if !self.width.is_multiple_of(2) {
+            return Err(MedImgError::ImageData(format!(
+                "YBR_FULL_422 needs an even width, got {}",
+                self.width
+            )));
+        }
+
+        let mut pixel_data = Vec::with_capacity(pixels * 3 * container);
+        for group in self.pixel_data.chunks_exact(4 * container) {
+            let [y1, y2, cb, cr] = [0, 1, 2, 3].map(|i| &group[i * container..(i + 1) * container]);
+            for sample in [y1, cb, cr, y2, cb, cr] {
+                pixel_data.extend_from_slice(sample);
+            }
+        }
+        Ok(ImageData {
+            pixel_data: pixel_data.into(),
+            row_stride: None,
+            ..self.clone()
+        })
+    }
+
+    /// This image's layout holding `stored` values.
+    fn with_stored(&self, stored: &PixelBuffer<i64>) -> ImageData {
+        ImageData {
+            pixel_data: encode_stored(&stored.samples, self.bits_per_sample).into(),
+            row_stride: None,
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invert_monochrome() {
+        let mut image = ImageData::new(3, 1, 12, 1, crate::pixel::encode_le::<u16>(&[0, 100, 4095]));
+        image.photometric_interpretation = "MONOCHROME1".into();
+        let inverted = image.convert_photometric(Photometric::Monochrome2).unwrap();
+        assert_eq!(inverted.photometric_interpretation, "MONOCHROME2");
+        assert_eq!(inverted.as_u16().unwrap().samples, vec![4095, 3995, 0]);
+        assert_eq!(inverted.convert_photometric(Photometric::Monochrome1).unwrap().pixel_data, image.pixel_data);
+
+        let mut signed = ImageData::new(2, 1, 16, 1, crate::pixel::encode_le::<i16>(&[-32768, 1000]));
+        signed.is_signed = true;
+        signed.photometric_interpretation = "MONOCHROME2".into();
+        let inverted = signed.convert_photometric(Photometric::Monochrome1).unwrap();
+        assert_eq!(inverted.as_i16().unwrap().samples, vec![32767, -1001]);
+        assert!(signed.convert_photometric(Photometric::Rgb).is_err());
+    }
+
+    #[test]
+    fn test_rgb_ybr() {
+        let mut rgb = ImageData::new(2, 1, 8, 3, vec![255, 255, 255, 255, 0, 0]);
+        rgb.photometric_interpretation = "RGB".into();
+        let ybr = rgb.convert_photometric(Photometric::YbrFull).unwrap();
+        assert_eq!(ybr.photometric_interpretation, "YBR_FULL");
+        assert_eq!(&ybr.pixel_data[..3], &[255, 128, 128]);
+        assert_eq!(&ybr.pixel_data[3..], &[76, 85, 255]);
+
+        let back = ybr.convert_photometric(Photometric::Rgb).unwrap();
+        for (a, b) in back.pixel_data.iter().zip(rgb.pixel_data.iter()) {
+            assert!(a.abs_diff(*b) <= 1);
+        }
+        assert_eq!(Photometric::YbrRct.decoded(), Photometric::Rgb);
+        assert!(rgb.convert_photometric(Photometric::YbrIct).is_err());
+    }
+
+    #[test]
+    fn test_upsample_422() {
+        // Y1 Y2 Cb Cr for two pixels
+        let mut native = ImageData::new(2, 1, 8, 3, vec![10, 20, 128, 128]);
+        native.photometric_interpretation = "YBR_FULL_422".into();
+        let full = native.convert_photometric(Photometric::YbrFull).unwrap();
+        assert_eq!(full.pixel_data, vec![10, 128, 128, 20, 128, 128]);
+        assert!(full.validate().is_ok());
+        let rgb = native.convert_photometric(Photometric::Rgb).unwrap();
+        assert_eq!(rgb.pixel_data, vec![10, 10, 10, 20, 20, 20]);
+    }
+}
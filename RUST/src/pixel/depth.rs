@@ -0,0 +1,280 @@
+//! Bit-depth conversion and bit packing.
+//!
+//! DICOM describes each sample with BitsAllocated (container size),
+//! BitsStored (significant bits), and HighBit (most significant bit of the
+//! value within the container). [`ImageData`] keeps values in the low bits
+//! of byte-aligned containers, so [`BitLayout::unpack`] moves samples from
+//! any valid layout, including non-byte-aligned ones such as packed 12-bit
+//! data, into that form.
+
+use super::{encode_stored, PixelBuffer};
+use crate::error::{MedImgError, Result};
+use crate::ImageData;
+
+/// How samples are laid out in native pixel data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitLayout {
+    /// Bits allocated per sample (container size).
+    pub bits_allocated: u16,
+    /// Significant bits per sample.
+    pub bits_stored: u16,
+    /// Position of the most significant stored bit.
+    pub high_bit: u16,
+}
+
+impl BitLayout {
+    /// Layout with the stored bits at the bottom of the container.
+    pub fn new(bits_allocated: u16, bits_stored: u16) -> Self {
+        Self {
+            bits_allocated,
+            bits_stored,
+            high_bit: bits_stored.saturating_sub(1),
+        }
+    }
+
+    /// Check that BitsStored fits BitsAllocated and HighBit places the
+    /// stored bits inside the container.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` describing the inconsistency.
+    pub fn validate(&self) -> Result<()> {
+        let Self { bits_allocated, bits_stored, high_bit } = *self;
+        let problem = if bits_allocated == 0 || bits_allocated > 32 {
+            Some(format!("BitsAllocated {} is outside 1-32", bits_allocated))
+        } else if bits_stored == 0 || bits_stored > bits_allocated {
+            Some(format!(
+                "BitsStored {} does not fit BitsAllocated {}",
+                bits_stored, bits_allocated
+            ))
+        } else if high_bit >= bits_allocated || high_bit + 1 < bits_stored {
+            Some(format!(
+                "HighBit {} does not place {} stored bits within {} allocated",
+                high_bit, bits_stored, bits_allocated
+            ))
+        } else {
+            None
+        };
+        match problem {
+            Some(problem) => Err(MedImgError::ImageData(problem)),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether samples already sit in the low bits of byte-aligned
+    /// containers of the size [`ImageData`] expects for `bits_stored`.
+    pub fn is_normalized(&self) -> bool {
+        self.bits_allocated.is_multiple_of(8)
+            && self.bits_allocated == self.bits_stored.div_ceil(8) * 8
+            && self.high_bit + 1 == self.bits_stored
+    }
+
+    /// Extract `count` samples laid out this way into little-endian
+    /// containers of `bits_stored.div_ceil(8)` bytes, stored bits at the
+    /// bottom and bits outside BitsStored cleared.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` for an invalid layout or if `data` holds fewer
+    /// than `count` samples.
+    pub fn unpack(&self, data: &[u8], count: usize) -> Result<Vec<u8>> {
+        self.validate()?;
+        let needed = (count * self.bits_allocated as usize).div_ceil(8);
+        if data.len() < needed {
+            return Err(MedImgError::ImageData(format!(
+                "Pixel data too short: {} samples of {} bits need {} bytes, got {}",
+                count,
+                self.bits_allocated,
+                needed,
+                data.len()
+            )));
+        }
+
+        let shift = u32::from(self.high_bit + 1 - self.bits_stored);
+        let mask = u64::MAX >> (64 - u32::from(self.bits_stored));
+        let container = self.bits_stored.div_ceil(8) as usize;
+        let mut out = Vec::with_capacity(count * container);
+        for index in 0..count {
+            let raw = read_bits(data, index * self.bits_allocated as usize, self.bits_allocated);
+            let value = (raw >> shift) & mask;
+            out.extend_from_slice(&value.to_le_bytes()[..container]);
+        }
+        Ok(out)
+    }
+}
+
+/// Read `bits` bits (at most 32) starting at bit `offset`, least
+/// significant bit first.
+fn read_bits(data: &[u8], offset: usize, bits: u16) -> u64 {
+    let first = offset / 8;
+    let last = (offset + bits as usize).div_ceil(8);
+    let word = data[first..last]
+        .iter()
+        .rev()
+        .fold(0u64, |acc, &b| acc << 8 | u64::from(b));
+    (word >> (offset % 8)) & (u64::MAX >> (64 - u32::from(bits)))
+}
+
+/// Pack samples of `bits` bits (1-16) into a contiguous bit stream, least
+/// significant bit first, as DICOM does for non-byte-aligned BitsAllocated.
+/// Bits above `bits` are dropped.
+///
+/// # Errors
+///
+/// Returns `ImageData` if `bits` is outside 1-16.
+pub fn pack_bits(samples: &[u16], bits: u16) -> Result<Vec<u8>> {
+    check_width(bits)?;
+    let mut out = vec![0u8; (samples.len() * bits as usize).div_ceil(8)];
+    let mask = (1u32 << bits) - 1;
+    for (index, &sample) in samples.iter().enumerate() {
+        let offset = index * bits as usize;
+        let value = (u32::from(sample) & mask) << (offset % 8);
+        for (i, byte) in value.to_le_bytes().iter().enumerate() {
+            if let Some(target) = out.get_mut(offset / 8 + i) {
+                *target |= byte;
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Unpack `count` samples of `bits` bits (1-16) from a bit stream written
+/// by [`pack_bits`].
+///
+/// # Errors
+///
+/// Returns `ImageData` if `bits` is outside 1-16 or `data` is too short.
+pub fn unpack_bits(data: &[u8], bits: u16, count: usize) -> Result<Vec<u16>> {
+    check_width(bits)?;
+    let bytes = BitLayout::new(bits, bits).unpack(data, count)?;
+    Ok(match bits {
+        1..=8 => bytes.into_iter().map(u16::from).collect(),
+        _ => super::decode_le(&bytes),
+    })
+}
+
+/// Reject sample widths outside 1-16 bits.
+fn check_width(bits: u16) -> Result<()> {
+    if bits == 0 || bits > 16 {
+        return Err(MedImgError::ImageData(format!(
+            "Sample width {} bits is outside 1-16",
+            bits
+        )));
+    }
+    Ok(())
+}
+
+impl ImageData {
+    /// Convert to `bits` bits per sample (1-16), scaling values to the new
+    /// range: unsigned samples so that full scale maps to full scale
+    /// (255 at 8 bits becomes 4095 at 12), signed samples by powers of two.
+    /// Narrowing rounds to nearest.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` if either depth is outside 1-16 bits or the
+    /// pixel data size does not match.
+    pub fn convert_depth(&self, bits: u16) -> Result<ImageData> {
+        check_width(bits)?;
+        check_width(self.bits_per_sample)?;
+        let stored = self.stored_values()?;
+
+        let (from, to) = (i32::from(self.bits_per_sample), i32::from(bits));
+        let convert = |v: i64| -> i64 {
+            if self.is_signed {
+                let (min, max) = (-(1i64 << (to - 1)), (1i64 << (to - 1)) - 1);
+                let scaled = if to >= from {
+                    v << (to - from)
+                } else {
+                    (v as f64 / f64::from(1 << (from - to))).round() as i64
+                };
+                scaled.clamp(min, max)
+            } else {
+                let (from_max, to_max) = ((1u64 << from) - 1, (1u64 << to) - 1);
+                (v as f64 * to_max as f64 / from_max as f64).round() as i64
+            }
+        };
+
+        let converted: Vec<i64> = stored.samples.iter().map(|&v| convert(v)).collect();
+
+        Ok(ImageData {
+            bits_per_sample: bits,
+            pixel_data: encode_stored(&converted, bits).into(),
+            row_stride: None,
+            ..self.clone()
+        })
+    }
+
+    /// Unsigned samples of any depth up to 16 bits scaled to 8 bits.
+    ///
+    /// # Errors
+    ///
+    /// As for [`convert_depth`](Self::convert_depth), and `ImageData` for
+    /// signed images.
+    pub fn to_u8_scaled(&self) -> Result<PixelBuffer<u8>> {
+        if self.is_signed {
+            return Err(MedImgError::ImageData(
+                "Cannot scale signed samples to u8".into(),
+            ));
+        }
+        self.convert_depth(8)?.as_u8()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_unpack_bits() {
+        // Two 12-bit samples fill three bytes, low bits first
+        let packed = pack_bits(&[0xABC, 0x123], 12).unwrap();
+        assert_eq!(packed, vec![0xBC, 0x3A, 0x12]);
+        assert_eq!(unpack_bits(&packed, 12, 2).unwrap(), vec![0xABC, 0x123]);
+
+        let bitmap = pack_bits(&[1, 0, 1, 1, 0, 0, 0, 0, 1], 1).unwrap();
+        assert_eq!(bitmap, vec![0b0000_1101, 0b1]);
+        assert_eq!(unpack_bits(&bitmap, 1, 9).unwrap(), vec![1, 0, 1, 1, 0, 0, 0, 0, 1]);
+
+        let ten = [0, 1023, 512, 7, 900];
+        assert_eq!(unpack_bits(&pack_bits(&ten, 10).unwrap(), 10, 5).unwrap(), ten);
+        assert!(unpack_bits(&packed, 12, 3).is_err());
+        assert!(pack_bits(&[0], 17).is_err());
+    }
+
+    #[test]
+    fn test_bit_layout() {
+        assert!(BitLayout::new(16, 12).validate().is_ok());
+        assert!(BitLayout::new(16, 12).is_normalized());
+        assert!(!BitLayout::new(16, 8).is_normalized());
+        assert!(BitLayout::new(8, 12).validate().is_err());
+        let bad_high_bit = BitLayout { bits_allocated: 16, bits_stored: 12, high_bit: 16 };
+        assert!(bad_high_bit.validate().is_err());
+
+        // 12 stored bits at the top of 16-bit containers
+        let shifted = BitLayout { bits_allocated: 16, bits_stored: 12, high_bit: 15 };
+        let data = [0xF0, 0xFF, 0x10, 0x00];
+        assert_eq!(shifted.unpack(&data, 2).unwrap(), vec![0xFF, 0x0F, 0x01, 0x00]);
+
+        // Overlay bits above BitsStored are cleared
+        let overlay = BitLayout::new(16, 10);
+        assert_eq!(overlay.unpack(&[0xFF, 0xFF], 1).unwrap(), vec![0xFF, 0x03]);
+    }
+
+    #[test]
+    fn test_convert_depth() {
+        let image = ImageData::new(3, 1, 8, 1, vec![0, 128, 255]);
+        let twelve = image.convert_depth(12).unwrap();
+        assert_eq!(twelve.as_u16().unwrap().samples, vec![0, 2056, 4095]);
+        assert_eq!(twelve.convert_depth(8).unwrap().pixel_data, image.pixel_data);
+        assert_eq!(twelve.to_u8_scaled().unwrap().samples, vec![0, 128, 255]);
+
+        let mut signed = ImageData::new(2, 1, 12, 1, vec![0x00, 0x08, 0xFF, 0x07]);
+        signed.is_signed = true;
+        let wide = signed.convert_depth(16).unwrap();
+        assert_eq!(wide.as_i16().unwrap().samples, vec![-32768, 32752]);
+        let narrow = signed.convert_depth(8).unwrap();
+        assert_eq!(narrow.as_i16().unwrap().samples, vec![-128, 127]);
+        assert!(signed.to_u8_scaled().is_err());
+    }
+}
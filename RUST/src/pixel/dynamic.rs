@@ -0,0 +1,134 @@
+//! Conversions between [`ImageData`] and `image::DynamicImage`.
+//!
+//! Samples are converted as stored: no rescale, window, or inversion is
+//! applied. For display-ready previews of DICOM images use
+//! [`export`](crate::export) instead.
+
+use image::{DynamicImage, ImageBuffer};
+
+use super::{PixelBuffer, Sample};
+use crate::error::{MedImgError, Result};
+use crate::ImageData;
+
+impl ImageData {
+    /// Convert a single-frame, unsigned grayscale or RGB image to a
+    /// `DynamicImage`: 8-bit images to `Luma8`/`Rgb8`, deeper ones up to
+    /// 16 bits to `Luma16`/`Rgb16`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` for multi-frame or signed images, samples per
+    /// pixel other than 1 or 3, samples wider than 16 bits, or a pixel data
+    /// size mismatch.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let image = dicom.decode_image_data()?;
+    /// image.frame_data(0)?.to_dynamic_image()?.save("frame.png")?;
+    /// ```
+    pub fn to_dynamic_image(&self) -> Result<DynamicImage> {
+        if self.number_of_frames.max(1) != 1 {
+            return Err(MedImgError::ImageData(format!(
+                "Cannot convert {} frames to one image (use frame_data)",
+                self.number_of_frames
+            )));
+        }
+        if self.is_signed {
+            return Err(MedImgError::ImageData(
+                "Cannot convert signed pixel data to an image (use export to window it)".into(),
+            ));
+        }
+
+        let too_short = || MedImgError::ImageData("Pixel data too short for image".into());
+        let (w, h) = (self.width, self.height);
+        match (self.samples_per_pixel, self.bits_per_sample <= 8) {
+            (1, true) => ImageBuffer::from_raw(w, h, self.as_u8()?.samples)
+                .map(DynamicImage::ImageLuma8)
+                .ok_or_else(too_short),
+            (1, false) => ImageBuffer::from_raw(w, h, self.as_u16()?.samples)
+                .map(DynamicImage::ImageLuma16)
+                .ok_or_else(too_short),
+            (3, true) => ImageBuffer::from_raw(w, h, self.as_u8()?.samples)
+                .map(DynamicImage::ImageRgb8)
+                .ok_or_else(too_short),
+            (3, false) => ImageBuffer::from_raw(w, h, self.as_u16()?.samples)
+                .map(DynamicImage::ImageRgb16)
+                .ok_or_else(too_short),
+            (samples, _) => Err(MedImgError::ImageData(format!(
+                "Cannot convert {} samples per pixel to an image",
+                samples
+            ))),
+        }
+    }
+
+    /// Build an image from an 8- or 16-bit grayscale or RGB
+    /// `DynamicImage`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` for other pixel types (alpha channels and
+    /// floating point); convert those first, e.g. with
+    /// `DynamicImage::to_rgb8`.
+    pub fn from_dynamic_image(image: &DynamicImage) -> Result<Self> {
+        match image {
+            DynamicImage::ImageLuma8(buffer) => Ok(from_samples(image, 1, 8, buffer.as_raw())),
+            DynamicImage::ImageLuma16(buffer) => Ok(from_samples(image, 1, 16, buffer.as_raw())),
+            DynamicImage::ImageRgb8(buffer) => Ok(from_samples(image, 3, 8, buffer.as_raw())),
+            DynamicImage::ImageRgb16(buffer) => Ok(from_samples(image, 3, 16, buffer.as_raw())),
+            other => Err(MedImgError::ImageData(format!(
+                "Cannot build an image from {:?} pixels",
+                other.color()
+            ))),
+        }
+    }
+}
+
+/// Image with `image`'s dimensions and the given samples.
+fn from_samples<T: Sample>(
+    image: &DynamicImage,
+    samples_per_pixel: u16,
+    bits_per_sample: u16,
+    samples: &[T],
+) -> ImageData {
+    PixelBuffer {
+        width: image.width(),
+        height: image.height(),
+        samples_per_pixel,
+        number_of_frames: 1,
+        samples: samples.to_vec(),
+    }
+    .to_image_data(bits_per_sample)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Rgb, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn test_dynamic_image_round_trip() {
+        // 12-bit grayscale maps to Luma16 with values as stored
+        let gray = ImageData::new(2, 1, 12, 1, vec![0xFF, 0x0F, 0x01, 0x00]);
+        let dynamic = gray.to_dynamic_image().unwrap();
+        assert_eq!(dynamic.as_luma16().unwrap().as_raw(), &vec![4095, 1]);
+        let back = ImageData::from_dynamic_image(&dynamic).unwrap();
+        assert_eq!((back.bits_per_sample, back.pixel_data), (16, gray.pixel_data));
+
+        let rgb = DynamicImage::ImageRgb8(RgbImage::from_pixel(2, 2, Rgb([1, 2, 3])));
+        let image = ImageData::from_dynamic_image(&rgb).unwrap();
+        assert_eq!((image.samples_per_pixel, image.photometric_interpretation.as_str()), (3, "RGB"));
+        assert_eq!(image.to_dynamic_image().unwrap(), rgb);
+
+        let luma = DynamicImage::ImageLuma8(GrayImage::new(3, 2));
+        assert_eq!(ImageData::from_dynamic_image(&luma).unwrap().expected_size(), 6);
+        assert!(ImageData::from_dynamic_image(&luma.to_luma_alpha8().into()).is_err());
+
+        let mut signed = ImageData::new(1, 1, 16, 1, vec![0, 0]);
+        signed.is_signed = true;
+        assert!(signed.to_dynamic_image().is_err());
+        let frames = ImageData { number_of_frames: 2, ..ImageData::new(1, 1, 8, 1, vec![0, 0]) };
+        assert!(frames.to_dynamic_image().is_err());
+    }
+}
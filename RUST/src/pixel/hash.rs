@@ -0,0 +1,66 @@
+//! Content hashes of pixel data.
+//!
+//! Hashes cover the packed rows of every frame, so a strided view and its
+//! packed copy hash the same. SHA-256 is for records that must stand up
+//! later (manifests, audit trail); xxHash3 is a fast check within a run.
+
+use sha2::{Digest, Sha256};
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::ImageData;
+
+impl ImageData {
+    /// SHA-256 of the pixel data as lowercase hex.
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let before = image.sha256();
+    /// let decoded = codec.decode_frames(&frames, image.width, image.height, image.bits_per_sample, image.samples_per_pixel)?;
+    /// assert_eq!(decoded.sha256(), before);
+    /// ```
+    pub fn sha256(&self) -> String {
+        let mut hasher = Sha256::new();
+        for row in self.rows() {
+            hasher.update(row);
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// 64-bit xxHash3 of the pixel data; not for records that must resist
+    /// tampering (use [`sha256`](Self::sha256)).
+    pub fn xxh3(&self) -> u64 {
+        let mut hasher = Xxh3::new();
+        for row in self.rows() {
+            hasher.update(row);
+        }
+        hasher.digest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ImageData, Rect};
+
+    #[test]
+    fn test_pixel_hashes() {
+        let empty = ImageData::new(0, 0, 8, 1, Vec::new());
+        assert_eq!(
+            empty.sha256(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+
+        // Views hash like their packed pixels
+        let image = ImageData::new(4, 3, 8, 1, (0..12).collect::<Vec<u8>>());
+        let crop = image.crop(Rect::new(1, 1, 2, 2)).unwrap();
+        let packed = ImageData::new(2, 2, 8, 1, vec![5, 6, 9, 10]);
+        assert_eq!(crop.sha256(), packed.sha256());
+        assert_eq!(crop.xxh3(), packed.xxh3());
+        assert_ne!(image.sha256(), packed.sha256());
+        assert_ne!(image.xxh3(), packed.xxh3());
+    }
+}
@@ -0,0 +1,304 @@
+//! Downsampling and multi-resolution pyramids.
+//!
+//! Resampling works on stored values, separably (rows, then columns), and
+//! rounds back to the image's bit depth. Used for icon images, preview
+//! export, and tiled whole-slide output.
+
+use std::str::FromStr;
+
+use super::encode_stored;
+use crate::error::{MedImgError, Result};
+use crate::ImageData;
+
+/// Lobes of the Lanczos kernel.
+const LANCZOS_LOBES: f64 = 3.0;
+
+/// Resampling filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResampleFilter {
+    /// Mean of each `factor` x `factor` block. Fast, never overshoots.
+    #[default]
+    Box,
+    /// Lanczos (3 lobes). Sharper, but may ring at edges; results are
+    /// clamped to the stored range.
+    Lanczos3,
+}
+
+impl FromStr for ResampleFilter {
+    type Err = String;
+
+    /// Parse `box` or `lanczos` (`lanczos3`).
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "box" => Ok(ResampleFilter::Box),
+            "lanczos" | "lanczos3" => Ok(ResampleFilter::Lanczos3),
+            other => Err(format!("unknown filter '{}' (use box or lanczos)", other)),
+        }
+    }
+}
+
+/// Contributions of source samples to each destination sample along one
+/// axis, as `(source index, weight)` pairs with weights summing to one.
+fn axis_weights(src_len: usize, factor: u32, filter: ResampleFilter) -> Vec<Vec<(usize, f64)>> {
+    let f = factor as usize;
+    let dst_len = src_len.div_ceil(f);
+    (0..dst_len)
+        .map(|i| match filter {
+            ResampleFilter::Box => {
+                let taps = i * f..((i + 1) * f).min(src_len);
+                let weight = 1.0 / taps.len() as f64;
+                taps.map(|j| (j, weight)).collect()
+            }
+            ResampleFilter::Lanczos3 => {
+                let scale = f64::from(factor);
+                let center = (i as f64 + 0.5) * scale - 0.5;
+                let support = LANCZOS_LOBES * scale;
+                let first = (center - support).floor() as i64 + 1;
+                let last = (center + support).ceil() as i64 - 1;
+                let mut taps: Vec<(usize, f64)> = (first..=last)
+                    .map(|j| {
+                        // Edges are extended by repeating the border sample
+                        let index = j.clamp(0, src_len as i64 - 1) as usize;
+                        (index, lanczos((j as f64 - center) / scale))
+                    })
+                    .collect();
+                let sum: f64 = taps.iter().map(|&(_, w)| w).sum();
+                taps.iter_mut().for_each(|(_, w)| *w /= sum);
+                taps
+            }
+        })
+        .collect()
+}
+
+/// Lanczos kernel: `sinc(x) * sinc(x / a)` within `a` lobes.
+fn lanczos(x: f64) -> f64 {
+    if x == 0.0 {
+        return 1.0;
+    }
+    if x.abs() >= LANCZOS_LOBES {
+        return 0.0;
+    }
+    let px = std::f64::consts::PI * x;
+    LANCZOS_LOBES * px.sin() * (px / LANCZOS_LOBES).sin() / (px * px)
+}
+
+impl ImageData {
+    /// Reduce each dimension by `factor` (rounding up) with a box filter.
+    ///
+    /// # Errors
+    ///
+    /// As for [`downsample_with`](Self::downsample_with).
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let icon = image.downsample(8)?;
+    /// ```
+    pub fn downsample(&self, factor: u32) -> Result<ImageData> {
+        self.downsample_with(factor, ResampleFilter::Box)
+    }
+
+    /// Reduce each dimension by `factor` (rounding up) with `filter`.
+    /// Frames are resampled independently; a factor of 1 returns a packed
+    /// copy.
+    ///
+    /// Color images must be interleaved (Planar Configuration 0).
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` for a zero factor, samples wider than 32 bits,
+    /// or a pixel data size mismatch.
+    pub fn downsample_with(&self, factor: u32, filter: ResampleFilter) -> Result<ImageData> {
+        if factor == 0 {
+            return Err(MedImgError::ImageData("Downsampling factor must be at least 1".into()));
+        }
+        let stored = self.stored_values()?;
+        if factor == 1 {
+            return Ok(self.packed().into_owned());
+        }
+
+        let (width, height) = (self.width as usize, self.height as usize);
+        let spp = self.samples_per_pixel as usize;
+        let columns = axis_weights(width, factor, filter);
+        let rows = axis_weights(height, factor, filter);
+        let (out_width, out_height) = (columns.len(), rows.len());
+
+        let bits = self.bits_per_sample;
+        let (min, max) = if self.is_signed {
+            (-(1i64 << (bits - 1)), (1i64 << (bits - 1)) - 1)
+        } else {
+            (0, (1i64 << bits) - 1)
+        };
+
+        let frame_len = width * height * spp;
+        let mut values = Vec::with_capacity(out_width * out_height * spp * stored.number_of_frames as usize);
+        let mut horizontal = vec![0.0; out_width * height * spp];
+        for frame in stored.samples.chunks_exact(frame_len.max(1)) {
+            for y in 0..height {
+                let row = &frame[y * width * spp..(y + 1) * width * spp];
+                for (x, taps) in columns.iter().enumerate() {
+                    for c in 0..spp {
+                        horizontal[(y * out_width + x) * spp + c] =
+                            taps.iter().map(|&(j, w)| row[j * spp + c] as f64 * w).sum();
+                    }
+                }
+            }
+            for taps in &rows {
+                for i in 0..out_width * spp {
+                    let value: f64 = taps.iter().map(|&(j, w)| horizontal[j * out_width * spp + i] * w).sum();
+                    values.push((value.round() as i64).clamp(min, max));
+                }
+            }
+        }
+
+        Ok(ImageData {
+            width: out_width as u32,
+            height: out_height as u32,
+            pixel_data: encode_stored(&values, bits).into(),
+            row_stride: None,
+            ..self.clone()
+        })
+    }
+}
+
+/// Builds a resolution pyramid by repeated downsampling.
+///
+/// Level 0 is the full-resolution image; each following level is the
+/// previous one reduced by `factor`, until the larger dimension is at most
+/// `min_size` or `max_levels` levels exist.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let levels = PyramidBuilder::new().min_size(256).filter(ResampleFilter::Lanczos3).build(&image)?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct PyramidBuilder {
+    factor: u32,
+    filter: ResampleFilter,
+    min_size: u32,
+    max_levels: Option<usize>,
+}
+
+impl PyramidBuilder {
+    /// Halve each level down to a single pixel, with a box filter.
+    pub fn new() -> Self {
+        Self {
+            factor: 2,
+            filter: ResampleFilter::Box,
+            min_size: 1,
+            max_levels: None,
+        }
+    }
+
+    /// Set the reduction between levels (at least 2).
+    pub fn factor(mut self, factor: u32) -> Self {
+        self.factor = factor;
+        self
+    }
+
+    /// Set the resampling filter.
+    pub fn filter(mut self, filter: ResampleFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Stop once the larger dimension is at most `min_size`.
+    pub fn min_size(mut self, min_size: u32) -> Self {
+        self.min_size = min_size.max(1);
+        self
+    }
+
+    /// Limit the number of levels, including level 0.
+    pub fn max_levels(mut self, max_levels: usize) -> Self {
+        self.max_levels = Some(max_levels);
+        self
+    }
+
+    /// Build the levels of `image`, full resolution first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` for a factor below 2, and errors from
+    /// [`ImageData::downsample_with`].
+    pub fn build(&self, image: &ImageData) -> Result<Vec<ImageData>> {
+        if self.factor < 2 {
+            return Err(MedImgError::ImageData(format!(
+                "Pyramid factor must be at least 2, got {}",
+                self.factor
+            )));
+        }
+        image.validate()?;
+
+        let mut levels = vec![image.packed().into_owned()];
+        while let Some(last) = levels.last() {
+            if last.width.max(last.height) <= self.min_size
+                || self.max_levels.is_some_and(|max| levels.len() >= max)
+            {
+                break;
+            }
+            let next = last.downsample_with(self.factor, self.filter)?;
+            levels.push(next);
+        }
+        Ok(levels)
+    }
+}
+
+impl Default for PyramidBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pixel::encode_le;
+
+    #[test]
+    fn test_downsample_box() {
+        // 3x2 image: the last column forms a partial block
+        let image = ImageData::new(3, 2, 8, 1, vec![0, 10, 100, 20, 30, 200]);
+        let half = image.downsample(2).unwrap();
+        assert_eq!((half.width, half.height), (2, 1));
+        assert_eq!(half.pixel_data, vec![15, 150]);
+        assert_eq!(image.downsample(1).unwrap().pixel_data, image.pixel_data);
+        assert!(image.downsample(0).is_err());
+
+        // Signed multi-frame RGB keeps its layout
+        let mut rgb = ImageData::new(2, 1, 16, 3, encode_le::<i16>(&[-100, 0, 10, 100, 0, 30, -4, 4, 8, -6, 6, 10]));
+        rgb.is_signed = true;
+        rgb.number_of_frames = 2;
+        let small = rgb.downsample(2).unwrap();
+        assert_eq!(small.as_i16().unwrap().samples, vec![0, 0, 20, -5, 5, 9]);
+    }
+
+    #[test]
+    fn test_downsample_lanczos() {
+        // Flat regions stay flat; edges are clamped to the stored range
+        let flat = ImageData::new(8, 8, 12, 1, encode_le::<u16>(&[1000; 64]));
+        let small = flat.downsample_with(4, ResampleFilter::Lanczos3).unwrap();
+        assert_eq!(small.as_u16().unwrap().samples, vec![1000; 4]);
+
+        let edge: Vec<u8> = (0..8).map(|x| if x < 4 { 0 } else { 255 }).collect();
+        let step = ImageData::new(8, 1, 8, 1, edge);
+        let half = step.downsample_with(2, ResampleFilter::Lanczos3).unwrap();
+        assert_eq!(half.pixel_data.len(), 4);
+        assert!(half.pixel_data[0] < 10 && half.pixel_data[3] > 245);
+        assert_eq!("lanczos".parse::<ResampleFilter>(), Ok(ResampleFilter::Lanczos3));
+    }
+
+    #[test]
+    fn test_pyramid() {
+        let image = ImageData::new(10, 4, 8, 1, vec![7; 40]);
+        let levels = PyramidBuilder::new().build(&image).unwrap();
+        let sizes: Vec<_> = levels.iter().map(|l| (l.width, l.height)).collect();
+        assert_eq!(sizes, vec![(10, 4), (5, 2), (3, 1), (2, 1), (1, 1)]);
+        assert!(levels.iter().all(|l| l.pixel_data.iter().all(|&v| v == 7)));
+
+        let capped = PyramidBuilder::new().min_size(4).max_levels(5).build(&image).unwrap();
+        assert_eq!(capped.len(), 3);
+        assert_eq!(PyramidBuilder::new().max_levels(2).build(&image).unwrap().len(), 2);
+        assert!(PyramidBuilder::new().factor(1).build(&image).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//! VOI windowing: rescaled values to display values.
+//!
+//! Implements the VOI LUT functions of PS3.3 C.11.2.1.2, shared by the
+//! preview exporter and anything else that needs display-referenced
+//! values.
+
+use serde::{Deserialize, Serialize};
+
+use super::PixelBuffer;
+use crate::error::{MedImgError, Result};
+use crate::ImageData;
+
+/// VOI LUT function (VOI LUT Function attribute, 0028,1056).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum VoiFunction {
+    /// Linear ramp between the window edges (the DICOM default).
+    #[default]
+    Linear,
+    /// Linear ramp without the half-value offsets of `Linear`.
+    LinearExact,
+    /// Logistic curve centered on the window center.
+    Sigmoid,
+}
+
+impl VoiFunction {
+    /// Parse a VOI LUT Function value (`LINEAR`, `LINEAR_EXACT`, `SIGMOID`).
+    pub fn from_dicom(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "LINEAR" => Some(VoiFunction::Linear),
+            "LINEAR_EXACT" => Some(VoiFunction::LinearExact),
+            "SIGMOID" => Some(VoiFunction::Sigmoid),
+            _ => None,
+        }
+    }
+}
+
+/// A VOI window (center/width) in rescaled units, e.g. HU for CT.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowLevel {
+    /// Window center.
+    pub center: f64,
+    /// Window width.
+    pub width: f64,
+    /// VOI LUT function.
+    #[serde(default)]
+    pub function: VoiFunction,
+}
+
+impl WindowLevel {
+    /// Linear window.
+    pub fn new(center: f64, width: f64) -> Self {
+        Self {
+            center,
+            width,
+            function: VoiFunction::Linear,
+        }
+    }
+
+    /// Sigmoid window.
+    pub fn sigmoid(center: f64, width: f64) -> Self {
+        Self {
+            function: VoiFunction::Sigmoid,
+            ..Self::new(center, width)
+        }
+    }
+
+    /// Linear window spanning `values` from minimum to maximum.
+    pub fn min_max(values: &[f64]) -> Self {
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        if min > max {
+            return Self::new(0.5, 1.0);
+        }
+        Self::new((min + max) / 2.0 + 0.5, max - min + 1.0)
+    }
+
+    /// Map a rescaled value to a display value in `[0, 1]`.
+    pub fn apply(&self, value: f64) -> f64 {
+        let (c, w) = (self.center, self.width);
+        let y = match self.function {
+            VoiFunction::Linear => {
+                if value <= c - 0.5 - (w - 1.0) / 2.0 {
+                    0.0
+                } else if value > c - 0.5 + (w - 1.0) / 2.0 {
+                    1.0
+                } else {
+                    (value - (c - 0.5)) / (w - 1.0) + 0.5
+                }
+            }
+            VoiFunction::LinearExact => (value - c) / w + 0.5,
+            VoiFunction::Sigmoid => 1.0 / (1.0 + (-4.0 * (value - c) / w).exp()),
+        };
+        y.clamp(0.0, 1.0)
+    }
+
+    /// Map a rescaled value to an 8-bit display value.
+    pub fn to_u8(&self, value: f64) -> u8 {
+        (self.apply(value) * f64::from(u8::MAX)).round() as u8
+    }
+
+    /// Check the width against the function's minimum (1 for `Linear`,
+    /// above 0 otherwise).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Config` if the width is too small.
+    pub fn validate(&self) -> Result<()> {
+        let minimum_ok = match self.function {
+            VoiFunction::Linear => self.width >= 1.0,
+            VoiFunction::LinearExact | VoiFunction::Sigmoid => self.width > 0.0,
+        };
+        if !minimum_ok || !self.center.is_finite() {
+            return Err(MedImgError::Config(format!(
+                "Invalid {:?} window: center {}, width {}",
+                self.function, self.center, self.width
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl ImageData {
+    /// Map a grayscale image's stored values through the modality rescale
+    /// and `window` to 8-bit display values, inverting MONOCHROME1.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ImageData` for images with more than one sample per pixel,
+    /// `Config` for an invalid window, and errors from
+    /// [`apply_rescale`](Self::apply_rescale).
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// let display = image.to_display_u8(1.0, -1024.0, &WindowLevel::new(40.0, 400.0))?;
+    /// ```
+    pub fn to_display_u8(
+        &self,
+        slope: f64,
+        intercept: f64,
+        window: &WindowLevel,
+    ) -> Result<PixelBuffer<u8>> {
+        if self.samples_per_pixel != 1 {
+            return Err(MedImgError::ImageData(format!(
+                "Cannot window {} samples per pixel",
+                self.samples_per_pixel
+            )));
+        }
+        window.validate()?;
+
+        let invert = self.photometric_interpretation.trim() == "MONOCHROME1";
+        let samples = self
+            .apply_rescale(slope, intercept)?
+            .to_f64()
+            .into_iter()
+            .map(|v| {
+                let display = window.to_u8(v);
+                if invert {
+                    u8::MAX - display
+                } else {
+                    display
+                }
+            })
+            .collect();
+
+        Ok(PixelBuffer {
+            width: self.width,
+            height: self.height,
+            samples_per_pixel: 1,
+            number_of_frames: self.number_of_frames.max(1),
+            samples,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_voi_functions() {
+        // PS3.3 linear: the window spans [c - 0.5 - (w-1)/2, c - 0.5 + (w-1)/2]
+        let linear = WindowLevel::new(100.5, 101.0);
+        assert_eq!(linear.apply(0.0), 0.0);
+        assert_eq!(linear.apply(100.0), 0.5);
+        assert_eq!(linear.apply(200.0), 1.0);
+        assert_eq!(linear.to_u8(125.0), 191);
+
+        let sigmoid = WindowLevel::sigmoid(40.0, 400.0);
+        assert_eq!(sigmoid.apply(40.0), 0.5);
+        assert!(sigmoid.apply(-1000.0) < 0.01 && sigmoid.apply(1000.0) > 0.99);
+
+        let exact = WindowLevel { function: VoiFunction::LinearExact, ..WindowLevel::new(0.0, 10.0) };
+        assert_eq!(exact.apply(2.5), 0.75);
+        assert_eq!(VoiFunction::from_dicom(" sigmoid"), Some(VoiFunction::Sigmoid));
+
+        let range = WindowLevel::min_max(&[10.0, 20.0, 30.0]);
+        assert_eq!((range.apply(10.0), range.apply(20.0), range.apply(30.0)), (0.0, 0.5, 1.0));
+        assert!(WindowLevel::new(0.0, 0.5).validate().is_err());
+    }
+
+    #[test]
+    fn test_display_u8() {
+        // CT in HU: air, water, bone under a soft-tissue window
+        let ct = ImageData::new(3, 1, 16, 1, crate::pixel::encode_le::<u16>(&[24, 1024, 2024]));
+        let display = ct.to_display_u8(1.0, -1024.0, &WindowLevel::new(40.0, 400.0)).unwrap();
+        assert_eq!(display.samples[0], 0);
+        assert_eq!(display.samples[2], 255);
+        assert!((100..=110).contains(&display.samples[1]));
+
+        let mut inverted = ct.clone();
+        inverted.photometric_interpretation = "MONOCHROME1".into();
+        let display = inverted.to_display_u8(1.0, -1024.0, &WindowLevel::new(40.0, 400.0)).unwrap();
+        assert_eq!(display.samples[0], 255);
+
+        let rgb = ImageData::new(1, 1, 8, 3, vec![0; 3]);
+        assert!(rgb.to_display_u8(1.0, 0.0, &WindowLevel::new(0.0, 1.0)).is_err());
+    }
+}
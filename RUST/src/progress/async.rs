@@ -0,0 +1,212 @@
+//! Async progress reporting.
+//!
+//! Provides a progress handler whose receiver implements
+//! `futures::Stream<Item = ProgressEvent>`, so async services can consume
+//! progress events without bridging blocking channels by hand.
+
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::error::MedImgError;
+use crate::pipeline::BatchStats;
+
+use super::cancel::CancellationToken;
+use super::handler::{ProgressEvent, ProgressHandler, ProgressPhase};
+
+/// Async progress handler.
+///
+/// Sends progress events to an unbounded tokio channel. Sending never
+/// blocks, so it is safe to call from rayon worker threads.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use futures::StreamExt;
+/// use medimg_compress::progress::r#async::AsyncProgress;
+///
+/// let (progress, mut stream) = AsyncProgress::new();
+///
+/// let processor = BatchProcessor::new(config, progress);
+/// tokio::task::spawn_blocking(move || processor.process_directory(&dir));
+///
+/// while let Some(event) = stream.next().await {
+///     println!("Progress: {:.1}%", event.overall_progress * 100.0);
+/// }
+/// ```
+pub struct AsyncProgress {
+    /// Channel sender for progress events.
+    sender: UnboundedSender<ProgressEvent>,
+
+    /// Cancellation token.
+    cancelled: CancellationToken,
+}
+
+impl AsyncProgress {
+    /// Create a new async progress handler.
+    ///
+    /// Returns the progress handler and a stream of progress events.
+    pub fn new() -> (Self, ProgressStream) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let cancelled = CancellationToken::new();
+
+        let handler = Self {
+            sender,
+            cancelled: cancelled.clone(),
+        };
+
+        let stream = ProgressStream {
+            receiver,
+            cancelled,
+        };
+
+        (handler, stream)
+    }
+
+    /// Request cancellation.
+    pub fn cancel(&self) {
+        self.cancelled.cancel();
+    }
+
+    /// Get a handle to the cancellation token shared with the receiver.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+}
+
+impl ProgressHandler for AsyncProgress {
+    fn on_progress(&self, event: &ProgressEvent) {
+        // Ignore send errors (stream may have been dropped)
+        let _ = self.sender.send(event.clone());
+    }
+
+    fn on_error(&self, error: &MedImgError, file: Option<&Path>) {
+        let event = ProgressEvent::error(error, file);
+        let _ = self.sender.send(event);
+    }
+
+    fn on_complete(&self, stats: &BatchStats) {
+        let event = ProgressEvent::complete(stats.total_files, stats.total_original_bytes as u64);
+        let _ = self.sender.send(event);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.is_cancelled()
+    }
+}
+
+/// Stream of progress events.
+///
+/// Ends when the handler is dropped. Use [`ProgressStream::until_complete`]
+/// to also end after the batch completion event.
+pub struct ProgressStream {
+    /// The underlying channel receiver.
+    receiver: UnboundedReceiver<ProgressEvent>,
+
+    /// Shared cancellation token.
+    cancelled: CancellationToken,
+}
+
+impl ProgressStream {
+    /// Request cancellation of the operation.
+    pub fn cancel(&self) {
+        self.cancelled.cancel();
+    }
+
+    /// Check if cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.is_cancelled()
+    }
+
+    /// Receive the next event.
+    pub async fn recv(&mut self) -> Option<ProgressEvent> {
+        self.receiver.recv().await
+    }
+
+    /// End the stream after the batch completion event.
+    ///
+    /// Per-file failure events do not end the stream.
+    pub fn until_complete(self) -> UntilComplete {
+        UntilComplete {
+            inner: self,
+            done: false,
+        }
+    }
+}
+
+impl Stream for ProgressStream {
+    type Item = ProgressEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// Stream adapter that ends after the batch completion event.
+pub struct UntilComplete {
+    inner: ProgressStream,
+    done: bool,
+}
+
+impl Stream for UntilComplete {
+    type Item = ProgressEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+
+        let poll = Pin::new(&mut self.inner).poll_next(cx);
+        if let Poll::Ready(Some(ref event)) = poll {
+            if event.phase == ProgressPhase::Complete && event.current_file.is_none() {
+                self.done = true;
+            }
+        }
+        poll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    #[test]
+    fn test_async_progress_stream() {
+        let (progress, stream) = AsyncProgress::new();
+
+        progress.on_progress(&ProgressEvent::new(ProgressPhase::Reading));
+        progress.on_progress(&ProgressEvent::new(ProgressPhase::Encoding));
+        drop(progress);
+
+        let events: Vec<_> = block_on(stream.collect());
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].phase, ProgressPhase::Encoding);
+    }
+
+    #[test]
+    fn test_async_progress_until_complete() {
+        let (progress, stream) = AsyncProgress::new();
+
+        progress.on_progress(&ProgressEvent::new(ProgressPhase::Encoding));
+        progress.on_complete(&BatchStats::default());
+        progress.on_progress(&ProgressEvent::new(ProgressPhase::Reading));
+
+        // Handler is still alive, but the stream ends at completion
+        let events: Vec<_> = block_on(stream.until_complete().collect());
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].phase, ProgressPhase::Complete);
+    }
+
+    #[test]
+    fn test_async_progress_cancellation() {
+        let (progress, stream) = AsyncProgress::new();
+
+        stream.cancel();
+        assert!(progress.is_cancelled());
+    }
+}
@@ -1,14 +1,23 @@
 //! Callback-based progress reporting.
 
 use std::path::Path;
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use crate::error::MedImgError;
 use crate::pipeline::BatchStats;
 
+use super::cancel::CancellationToken;
 use super::handler::{ProgressEvent, ProgressHandler};
 
+/// Shared progress event callback.
+type EventCallback = Arc<dyn Fn(ProgressEvent) + Send + Sync>;
+
+/// Shared error callback.
+type ErrorCallback = Arc<dyn Fn(&MedImgError, Option<&Path>) + Send + Sync>;
+
+/// Shared completion callback.
+type CompleteCallback = Arc<dyn Fn(&BatchStats) + Send + Sync>;
+
 /// A progress handler that invokes a callback function.
 ///
 /// # Type Parameters
@@ -36,13 +45,13 @@ where
     callback: F,
 
     /// Error callback (optional).
-    error_callback: Option<Arc<dyn Fn(&MedImgError, Option<&Path>) + Send + Sync>>,
+    error_callback: Option<ErrorCallback>,
 
     /// Completion callback (optional).
-    complete_callback: Option<Arc<dyn Fn(&BatchStats) + Send + Sync>>,
+    complete_callback: Option<CompleteCallback>,
 
-    /// Cancellation flag.
-    cancelled: AtomicBool,
+    /// Cancellation token.
+    cancelled: CancellationToken,
 }
 
 impl<F> CallbackProgress<F>
@@ -59,10 +68,21 @@ where
             callback,
             error_callback: None,
             complete_callback: None,
-            cancelled: AtomicBool::new(false),
+            cancelled: CancellationToken::new(),
         }
     }
 
+    /// Use a shared cancellation token.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancelled = token;
+        self
+    }
+
+    /// Get a handle to this handler's cancellation token.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+
     /// Set an error callback.
     pub fn on_error<E>(mut self, callback: E) -> Self
     where
@@ -85,12 +105,12 @@ where
     ///
     /// The operation will stop after completing the current file.
     pub fn cancel(&self) {
-        self.cancelled.store(true, Ordering::SeqCst);
+        self.cancelled.cancel();
     }
 
     /// Reset the cancellation flag.
     pub fn reset(&self) {
-        self.cancelled.store(false, Ordering::SeqCst);
+        self.cancelled.reset();
     }
 }
 
@@ -115,7 +135,7 @@ where
     }
 
     fn is_cancelled(&self) -> bool {
-        self.cancelled.load(Ordering::SeqCst)
+        self.cancelled.is_cancelled()
     }
 }
 
@@ -133,9 +153,10 @@ where
 ///     .build();
 /// ```
 pub struct CallbackProgressBuilder {
-    progress_callback: Option<Arc<dyn Fn(ProgressEvent) + Send + Sync>>,
-    error_callback: Option<Arc<dyn Fn(&MedImgError, Option<&Path>) + Send + Sync>>,
-    complete_callback: Option<Arc<dyn Fn(&BatchStats) + Send + Sync>>,
+    progress_callback: Option<EventCallback>,
+    error_callback: Option<ErrorCallback>,
+    complete_callback: Option<CompleteCallback>,
+    cancelled: Option<CancellationToken>,
 }
 
 impl Default for CallbackProgressBuilder {
@@ -151,6 +172,7 @@ impl CallbackProgressBuilder {
             progress_callback: None,
             error_callback: None,
             complete_callback: None,
+            cancelled: None,
         }
     }
 
@@ -181,34 +203,45 @@ impl CallbackProgressBuilder {
         self
     }
 
+    /// Use a shared cancellation token.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancelled = Some(token);
+        self
+    }
+
     /// Build the progress handler.
     pub fn build(self) -> BuiltCallbackProgress {
         BuiltCallbackProgress {
             progress_callback: self.progress_callback,
             error_callback: self.error_callback,
             complete_callback: self.complete_callback,
-            cancelled: AtomicBool::new(false),
+            cancelled: self.cancelled.unwrap_or_default(),
         }
     }
 }
 
 /// A progress handler built from CallbackProgressBuilder.
 pub struct BuiltCallbackProgress {
-    progress_callback: Option<Arc<dyn Fn(ProgressEvent) + Send + Sync>>,
-    error_callback: Option<Arc<dyn Fn(&MedImgError, Option<&Path>) + Send + Sync>>,
-    complete_callback: Option<Arc<dyn Fn(&BatchStats) + Send + Sync>>,
-    cancelled: AtomicBool,
+    progress_callback: Option<EventCallback>,
+    error_callback: Option<ErrorCallback>,
+    complete_callback: Option<CompleteCallback>,
+    cancelled: CancellationToken,
 }
 
 impl BuiltCallbackProgress {
     /// Request cancellation.
     pub fn cancel(&self) {
-        self.cancelled.store(true, Ordering::SeqCst);
+        self.cancelled.cancel();
     }
 
     /// Reset the cancellation flag.
     pub fn reset(&self) {
-        self.cancelled.store(false, Ordering::SeqCst);
+        self.cancelled.reset();
+    }
+
+    /// Get a handle to this handler's cancellation token.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
     }
 }
 
@@ -232,14 +265,14 @@ impl ProgressHandler for BuiltCallbackProgress {
     }
 
     fn is_cancelled(&self) -> bool {
-        self.cancelled.load(Ordering::SeqCst)
+        self.cancelled.is_cancelled()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
     fn test_callback_progress_new() {
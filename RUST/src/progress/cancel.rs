@@ -0,0 +1,68 @@
+//! Shared cancellation handle.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A cloneable handle for cancelling an operation from any thread.
+///
+/// All clones share the same flag, so a host application can keep one
+/// handle and pass clones to progress handlers, batch processors, and
+/// schedulers.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::progress::{CancellationToken, TerminalProgress};
+///
+/// let token = CancellationToken::new();
+/// let processor = BatchProcessor::new(config, TerminalProgress::new())
+///     .with_cancellation(token.clone());
+///
+/// // Later, from another thread:
+/// token.cancel();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    /// Shared cancellation flag.
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a new, uncancelled token.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Check if cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Clear the cancellation flag for every clone of this token.
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancellation_token_shared_across_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+
+        std::thread::spawn(move || clone.cancel()).join().unwrap();
+        assert!(token.is_cancelled());
+
+        token.reset();
+        assert!(!token.is_cancelled());
+    }
+}
@@ -5,14 +5,13 @@
 //! in a separate thread.
 
 use std::path::Path;
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
-use std::sync::Arc;
 use std::time::Duration;
 
 use crate::error::MedImgError;
 use crate::pipeline::BatchStats;
 
+use super::cancel::CancellationToken;
 use super::handler::{ProgressEvent, ProgressHandler};
 
 /// Channel-based progress handler.
@@ -45,8 +44,8 @@ pub struct ChannelProgress {
     /// Channel sender for progress events.
     sender: Sender<ProgressEvent>,
 
-    /// Cancellation flag.
-    cancelled: Arc<AtomicBool>,
+    /// Cancellation token.
+    cancelled: CancellationToken,
 }
 
 impl ChannelProgress {
@@ -55,7 +54,7 @@ impl ChannelProgress {
     /// Returns the progress handler and a receiver for progress events.
     pub fn new() -> (Self, ProgressReceiver) {
         let (sender, receiver) = mpsc::channel();
-        let cancelled = Arc::new(AtomicBool::new(false));
+        let cancelled = CancellationToken::new();
 
         let handler = Self {
             sender,
@@ -79,7 +78,7 @@ impl ChannelProgress {
     /// * `capacity` - Maximum number of events to buffer
     pub fn bounded(capacity: usize) -> (Self, ProgressReceiver) {
         let (sync_sender, receiver) = mpsc::sync_channel::<ProgressEvent>(capacity);
-        let cancelled = Arc::new(AtomicBool::new(false));
+        let cancelled = CancellationToken::new();
 
         // Create a bridge channel for the handler
         let (bridge_sender, bridge_receiver) = mpsc::channel::<ProgressEvent>();
@@ -108,7 +107,12 @@ impl ChannelProgress {
 
     /// Request cancellation.
     pub fn cancel(&self) {
-        self.cancelled.store(true, Ordering::SeqCst);
+        self.cancelled.cancel();
+    }
+
+    /// Get a handle to the cancellation token shared with the receiver.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
     }
 }
 
@@ -119,8 +123,7 @@ impl ProgressHandler for ChannelProgress {
     }
 
     fn on_error(&self, error: &MedImgError, file: Option<&Path>) {
-        let mut event = ProgressEvent::failed(error.to_string());
-        event.current_file = file.map(|p| p.to_path_buf());
+        let event = ProgressEvent::error(error, file);
         let _ = self.sender.send(event);
     }
 
@@ -130,7 +133,7 @@ impl ProgressHandler for ChannelProgress {
     }
 
     fn is_cancelled(&self) -> bool {
-        self.cancelled.load(Ordering::SeqCst)
+        self.cancelled.is_cancelled()
     }
 }
 
@@ -141,8 +144,8 @@ pub struct ProgressReceiver {
     /// The underlying channel receiver.
     receiver: Receiver<ProgressEvent>,
 
-    /// Shared cancellation flag.
-    cancelled: Arc<AtomicBool>,
+    /// Shared cancellation token.
+    cancelled: CancellationToken,
 }
 
 impl ProgressReceiver {
@@ -173,12 +176,12 @@ impl ProgressReceiver {
 
     /// Request cancellation of the operation.
     pub fn cancel(&self) {
-        self.cancelled.store(true, Ordering::SeqCst);
+        self.cancelled.cancel();
     }
 
     /// Check if cancellation was requested.
     pub fn is_cancelled(&self) -> bool {
-        self.cancelled.load(Ordering::SeqCst)
+        self.cancelled.is_cancelled()
     }
 
     /// Collect all events until completion or error.
@@ -270,6 +273,7 @@ mod tests {
             total_original_bytes: 1000,
             total_compressed_bytes: 500,
             total_time_ms: 100,
+            quality: None,
         };
 
         progress.on_complete(&stats);
@@ -0,0 +1,315 @@
+//! File-based progress logging.
+//!
+//! Appends progress, error, and completion records as JSON lines to a log
+//! file, rotating it by size. Intended for unattended batch runs where no
+//! terminal is attached.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::error::{ErrorRecord, MedImgError, Result};
+use crate::pipeline::BatchStats;
+
+use super::cancel::CancellationToken;
+use super::handler::{ProgressEvent, ProgressHandler};
+
+/// A single JSONL record.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum LogRecord<'a> {
+    /// A progress event.
+    Progress {
+        timestamp_ms: u64,
+        #[serde(flatten)]
+        event: &'a ProgressEvent,
+    },
+    /// A per-file or batch error.
+    Error {
+        timestamp_ms: u64,
+        #[serde(flatten)]
+        error: ErrorRecord,
+    },
+    /// Batch completion summary.
+    Complete {
+        timestamp_ms: u64,
+        total_files: usize,
+        successful: usize,
+        failed: usize,
+        skipped: usize,
+        original_bytes: usize,
+        compressed_bytes: usize,
+        time_ms: u64,
+    },
+}
+
+/// Open log file and its current size.
+struct LogFile {
+    /// Open file handle (append mode).
+    file: File,
+
+    /// Current size in bytes.
+    size: u64,
+}
+
+/// A progress handler that appends JSONL records to a rotating log file.
+///
+/// When writing a record would grow the file beyond `max_bytes`, the file
+/// is rotated: `run.log` becomes `run.log.1`, `run.log.1` becomes
+/// `run.log.2`, and so on, keeping at most `max_files` rotated files.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::progress::FileLogProgress;
+///
+/// let progress = FileLogProgress::new("/var/log/medimg/batch.jsonl")?
+///     .max_bytes(50 * 1024 * 1024)
+///     .max_files(10);
+///
+/// let processor = BatchProcessor::new(config, progress);
+/// ```
+pub struct FileLogProgress {
+    /// Path of the active log file.
+    path: PathBuf,
+
+    /// Size threshold that triggers rotation.
+    max_bytes: u64,
+
+    /// Number of rotated files to keep.
+    max_files: usize,
+
+    /// Active log file.
+    log: Mutex<LogFile>,
+
+    /// Cancellation token.
+    cancelled: CancellationToken,
+}
+
+impl FileLogProgress {
+    /// Default rotation threshold (10 MiB).
+    pub const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+    /// Default number of rotated files to keep.
+    pub const DEFAULT_MAX_FILES: usize = 5;
+
+    /// Open (or create) a log file for appending.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let log = Self::open(&path)?;
+
+        Ok(Self {
+            path,
+            max_bytes: Self::DEFAULT_MAX_BYTES,
+            max_files: Self::DEFAULT_MAX_FILES,
+            log: Mutex::new(log),
+            cancelled: CancellationToken::new(),
+        })
+    }
+
+    /// Set the size threshold that triggers rotation.
+    pub fn max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes.max(1);
+        self
+    }
+
+    /// Set the number of rotated files to keep.
+    pub fn max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    /// Use a shared cancellation token.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancelled = token;
+        self
+    }
+
+    /// Request cancellation.
+    pub fn cancel(&self) {
+        self.cancelled.cancel();
+    }
+
+    /// Get the path of the active log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Open the log file in append mode.
+    fn open(path: &Path) -> Result<LogFile> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(LogFile { file, size })
+    }
+
+    /// Path of the `index`-th rotated file.
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    /// Shift rotated files up by one and start a fresh log file.
+    fn rotate(&self, log: &mut LogFile) -> Result<()> {
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = self.rotated_path(self.max_files);
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        *log = Self::open(&self.path)?;
+        Ok(())
+    }
+
+    /// Serialize and append a record, rotating first if needed.
+    fn write(&self, record: &LogRecord<'_>) -> Result<()> {
+        let mut line = serde_json::to_vec(record)
+            .map_err(|e| MedImgError::Internal(format!("Failed to serialize log record: {}", e)))?;
+        line.push(b'\n');
+
+        let mut log = self
+            .log
+            .lock()
+            .map_err(|_| MedImgError::Internal("Log file lock poisoned".into()))?;
+
+        if log.size > 0 && log.size + line.len() as u64 > self.max_bytes {
+            self.rotate(&mut log)?;
+        }
+
+        log.file.write_all(&line)?;
+        log.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Append a record, reporting failures through `log`.
+    fn append(&self, record: LogRecord<'_>) {
+        if let Err(e) = self.write(&record) {
+            log::warn!("Failed to write progress log {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Milliseconds since the Unix epoch.
+fn timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+impl ProgressHandler for FileLogProgress {
+    fn on_progress(&self, event: &ProgressEvent) {
+        self.append(LogRecord::Progress {
+            timestamp_ms: timestamp_ms(),
+            event,
+        });
+    }
+
+    fn on_error(&self, error: &MedImgError, file: Option<&Path>) {
+        self.append(LogRecord::Error {
+            timestamp_ms: timestamp_ms(),
+            error: ErrorRecord::new(error, file),
+        });
+    }
+
+    fn on_complete(&self, stats: &BatchStats) {
+        self.append(LogRecord::Complete {
+            timestamp_ms: timestamp_ms(),
+            total_files: stats.total_files,
+            successful: stats.successful,
+            failed: stats.failed,
+            skipped: stats.skipped,
+            original_bytes: stats.total_original_bytes,
+            compressed_bytes: stats.total_compressed_bytes,
+            time_ms: stats.total_time_ms,
+        });
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.is_cancelled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::ProgressPhase;
+    use tempfile::TempDir;
+
+    fn read_lines(path: &Path) -> Vec<serde_json::Value> {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_file_log_records() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("batch.jsonl");
+        let progress = FileLogProgress::new(&path).unwrap();
+
+        progress.on_progress(&ProgressEvent::reading(Path::new("/test/a.dcm")));
+        progress.on_error(
+            &MedImgError::Internal("boom".into()),
+            Some(Path::new("/test/a.dcm")),
+        );
+        progress.on_complete(&BatchStats {
+            total_files: 1,
+            failed: 1,
+            ..Default::default()
+        });
+
+        let lines = read_lines(&path);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["kind"], "progress");
+        assert_eq!(lines[0]["phase"], "Reading");
+        assert_eq!(lines[1]["kind"], "error");
+        assert_eq!(lines[1]["file"], "/test/a.dcm");
+        assert_eq!(lines[1]["code"], "MI-INT-001");
+        assert_eq!(lines[2]["kind"], "complete");
+        assert_eq!(lines[2]["failed"], 1);
+
+        let event: ProgressEvent = serde_json::from_value(lines[0].clone()).unwrap();
+        assert_eq!(event.phase, ProgressPhase::Reading);
+    }
+
+    #[test]
+    fn test_file_log_rotation() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("batch.jsonl");
+        let progress = FileLogProgress::new(&path)
+            .unwrap()
+            .max_bytes(1)
+            .max_files(2);
+
+        for i in 0..4 {
+            progress.on_progress(&ProgressEvent::discovery(format!("event {}", i)));
+        }
+
+        // Each record exceeds the threshold, so every write rotates
+        assert_eq!(read_lines(&path)[0]["message"], "event 3");
+        assert_eq!(read_lines(&progress.rotated_path(1))[0]["message"], "event 2");
+        assert_eq!(read_lines(&progress.rotated_path(2))[0]["message"], "event 1");
+        assert!(!progress.rotated_path(3).exists());
+    }
+}
@@ -1,11 +1,12 @@
 //! Progress handler trait and related types.
 
-use crate::error::MedImgError;
-use crate::pipeline::BatchStats;
+use crate::error::{ErrorRecord, MedImgError};
+use crate::pipeline::{BatchStats, PhaseTimings};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 /// Phase of compression operation.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ProgressPhase {
     /// Discovering files to process.
     Discovery,
@@ -50,7 +51,11 @@ impl std::fmt::Display for ProgressPhase {
 }
 
 /// Progress event emitted during compression operations.
-#[derive(Debug, Clone)]
+///
+/// Serializable so events can be forwarded across process boundaries
+/// (e.g. JSON lines over a pipe). Missing fields deserialize to defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ProgressEvent {
     /// Current phase of operation.
     pub phase: ProgressPhase,
@@ -82,6 +87,12 @@ pub struct ProgressEvent {
     /// Estimated time remaining in seconds.
     pub eta_seconds: Option<f64>,
 
+    /// Per-phase durations for the current file (on per-file completion).
+    pub timings: Option<PhaseTimings>,
+
+    /// Error details (on failure events from `on_error`).
+    pub error: Option<ErrorRecord>,
+
     /// Status message.
     pub message: String,
 }
@@ -99,6 +110,8 @@ impl Default for ProgressEvent {
             total_bytes: None,
             throughput_bps: 0.0,
             eta_seconds: None,
+            timings: None,
+            error: None,
             message: String::new(),
         }
     }
@@ -165,6 +178,15 @@ impl ProgressEvent {
         }
     }
 
+    /// Create a failure event for `error` in `file`, with its error record.
+    pub fn error(error: &MedImgError, file: Option<&Path>) -> Self {
+        Self {
+            current_file: file.map(Path::to_path_buf),
+            error: Some(ErrorRecord::new(error, file)),
+            ..Self::failed(error.to_string())
+        }
+    }
+
     /// Set batch progress information.
     pub fn with_batch_progress(
         mut self,
@@ -183,12 +205,25 @@ impl ProgressEvent {
         self
     }
 
+    /// Set the bytes processed out of `total_bytes`.
+    pub fn with_bytes(mut self, bytes_processed: u64, total_bytes: u64) -> Self {
+        self.bytes_processed = bytes_processed;
+        self.total_bytes = Some(total_bytes);
+        self
+    }
+
     /// Set throughput and ETA.
     pub fn with_timing(mut self, throughput_bps: f64, eta_seconds: Option<f64>) -> Self {
         self.throughput_bps = throughput_bps;
         self.eta_seconds = eta_seconds;
         self
     }
+
+    /// Set per-phase durations for the current file.
+    pub fn with_phase_timings(mut self, timings: PhaseTimings) -> Self {
+        self.timings = Some(timings);
+        self
+    }
 }
 
 impl std::fmt::Display for ProgressEvent {
@@ -317,6 +352,62 @@ mod tests {
         assert!((event.overall_progress - 0.5).abs() < 0.001);
     }
 
+    #[test]
+    fn test_progress_event_serde_roundtrip() {
+        let event = ProgressEvent::encoding(Path::new("/test/file.dcm"), 0.25)
+            .with_batch_progress(2, 4, 1024, Some(4096))
+            .with_timing(512.0, Some(6.0));
+
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains("\"phase\":\"Encoding\""));
+
+        let decoded: ProgressEvent = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.phase, ProgressPhase::Encoding);
+        assert_eq!(decoded.current_file, event.current_file);
+        assert_eq!(decoded.total_files, Some(4));
+        assert_eq!(decoded.eta_seconds, Some(6.0));
+    }
+
+    #[test]
+    fn test_progress_event_error_record() {
+        let error = MedImgError::Codec("truncated codestream".into());
+        let event = ProgressEvent::error(&error, Some(Path::new("/test/file.dcm")));
+        assert_eq!(event.phase, ProgressPhase::Failed);
+        assert_eq!(event.message, "Codec error: truncated codestream");
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["error"]["code"], "MI-COD-001");
+        assert_eq!(json["error"]["file"], "/test/file.dcm");
+
+        let decoded: ProgressEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.error, event.error);
+    }
+
+    #[test]
+    fn test_progress_event_phase_timings() {
+        let timings = PhaseTimings {
+            read_ms: 120,
+            encode_ms: 30,
+            verify_ms: 10,
+            write_ms: 5,
+        };
+        let event = ProgressEvent::new(ProgressPhase::Complete).with_phase_timings(timings);
+
+        let json = serde_json::to_string(&event).unwrap();
+        let decoded: ProgressEvent = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.timings, Some(timings));
+        assert_eq!(decoded.timings.unwrap().total_ms(), 165);
+    }
+
+    #[test]
+    fn test_progress_event_deserialize_partial() {
+        let decoded: ProgressEvent =
+            serde_json::from_str(r#"{"phase":"Complete","message":"done"}"#).unwrap();
+        assert_eq!(decoded.phase, ProgressPhase::Complete);
+        assert_eq!(decoded.message, "done");
+        assert_eq!(decoded.completed_files, 0);
+    }
+
     #[test]
     fn test_progress_event_display() {
         let event = ProgressEvent::new(ProgressPhase::Encoding)
@@ -3,7 +3,14 @@
 //! This module provides a flexible progress reporting API that supports:
 //! - Callback-based progress reporting
 //! - Channel-based progress for async workflows
-//! - Cancellation support
+//! - Terminal progress bars (not on wasm32)
+//! - JSONL log files with size-based rotation
+//! - Structured `tracing` events
+//! - Fan-out to several handlers at once
+//! - Rate limiting of high-frequency events
+//! - Async streams of progress events (`tokio` feature)
+//! - HTTP webhooks (`webhook` feature)
+//! - Cancellation support via a shared [`CancellationToken`]
 //!
 //! # Example
 //!
@@ -21,11 +28,34 @@
 
 mod handler;
 mod callback;
+mod cancel;
 mod channel;
+mod file_log;
+mod multi;
+#[cfg(not(target_arch = "wasm32"))]
+mod terminal;
+mod throttle;
+mod trace;
+
+#[cfg(feature = "tokio")]
+pub mod r#async;
+
+#[cfg(feature = "webhook")]
+mod webhook;
 
 pub use handler::{ProgressEvent, ProgressHandler, ProgressPhase, NullProgress};
 pub use callback::{CallbackProgress, CallbackProgressBuilder, BuiltCallbackProgress};
+pub use cancel::CancellationToken;
 pub use channel::{ChannelProgress, ProgressReceiver};
+pub use file_log::FileLogProgress;
+pub use multi::MultiProgress;
+#[cfg(not(target_arch = "wasm32"))]
+pub use terminal::TerminalProgress;
+pub use throttle::ThrottledProgress;
+pub use trace::{TracingProgress, PROGRESS_TARGET};
+
+#[cfg(feature = "webhook")]
+pub use webhook::{WebhookProgress, WebhookProgressBuilder};
 
 #[cfg(test)]
 mod tests {
@@ -48,6 +78,8 @@ mod tests {
             total_bytes: Some(2048),
             throughput_bps: 100.0,
             eta_seconds: Some(10.0),
+            timings: None,
+            error: None,
             message: "Processing...".into(),
         };
 
@@ -0,0 +1,125 @@
+//! Composite progress reporting.
+//!
+//! Fans events out to several handlers so a single batch run can drive,
+//! for example, a terminal bar, a log file, and a channel at once.
+
+use std::path::Path;
+
+use crate::error::MedImgError;
+use crate::pipeline::BatchStats;
+
+use super::handler::{ProgressEvent, ProgressHandler};
+
+/// A progress handler that forwards every event to a list of handlers.
+///
+/// Handlers are called in the order they were added. The operation is
+/// cancelled as soon as any inner handler requests cancellation.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::progress::{ChannelProgress, MultiProgress, TerminalProgress};
+///
+/// let (channel, receiver) = ChannelProgress::new();
+/// let progress = MultiProgress::new()
+///     .with(TerminalProgress::new())
+///     .with(channel);
+///
+/// let processor = BatchProcessor::new(config, progress);
+/// ```
+#[derive(Default)]
+pub struct MultiProgress {
+    /// Inner handlers.
+    handlers: Vec<Box<dyn ProgressHandler>>,
+}
+
+impl MultiProgress {
+    /// Create an empty composite handler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a handler (builder style).
+    pub fn with<H: ProgressHandler + 'static>(mut self, handler: H) -> Self {
+        self.push(handler);
+        self
+    }
+
+    /// Add a handler.
+    pub fn push<H: ProgressHandler + 'static>(&mut self, handler: H) {
+        self.handlers.push(Box::new(handler));
+    }
+
+    /// Number of inner handlers.
+    pub fn len(&self) -> usize {
+        self.handlers.len()
+    }
+
+    /// Check if there are no inner handlers.
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+}
+
+impl ProgressHandler for MultiProgress {
+    fn on_progress(&self, event: &ProgressEvent) {
+        for handler in &self.handlers {
+            handler.on_progress(event);
+        }
+    }
+
+    fn on_error(&self, error: &MedImgError, file: Option<&Path>) {
+        for handler in &self.handlers {
+            handler.on_error(error, file);
+        }
+    }
+
+    fn on_complete(&self, stats: &BatchStats) {
+        for handler in &self.handlers {
+            handler.on_complete(stats);
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.handlers.iter().any(|h| h.is_cancelled())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::{CallbackProgress, ChannelProgress, NullProgress};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_multi_progress_fans_out() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let count_clone = count.clone();
+        let (channel, receiver) = ChannelProgress::new();
+
+        let progress = MultiProgress::new()
+            .with(CallbackProgress::new(move |_| {
+                count_clone.fetch_add(1, Ordering::SeqCst);
+            }))
+            .with(channel);
+        assert_eq!(progress.len(), 2);
+
+        progress.on_progress(&ProgressEvent::discovery("Scanning"));
+
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert_eq!(receiver.try_recv().unwrap().message, "Scanning");
+    }
+
+    #[test]
+    fn test_multi_progress_cancelled_by_any() {
+        let cancellable = CallbackProgress::new(|_| {});
+        cancellable.cancel();
+
+        let progress = MultiProgress::new().with(NullProgress);
+        assert!(!progress.is_cancelled());
+
+        let progress = progress.with(cancellable);
+        assert!(progress.is_cancelled());
+    }
+}
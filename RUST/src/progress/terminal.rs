@@ -0,0 +1,286 @@
+//! Terminal progress bar reporting.
+//!
+//! Renders a live progress bar with file counts, throughput, and ETA using
+//! `indicatif`. A single-file variant tracks encode progress of one large
+//! file instead.
+
+use std::path::Path;
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::error::MedImgError;
+use crate::pipeline::BatchStats;
+
+use super::cancel::CancellationToken;
+use super::handler::{ProgressEvent, ProgressHandler, ProgressPhase};
+
+/// Template for the progress bar line.
+const BAR_TEMPLATE: &str =
+    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} files {msg}";
+
+/// Template for the single-file progress bar line.
+const FILE_BAR_TEMPLATE: &str =
+    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {percent}% {bytes_per_sec} ETA {eta} {msg}";
+
+/// A progress handler that renders a terminal progress bar.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::batch::BatchProcessor;
+/// use medimg_compress::progress::TerminalProgress;
+///
+/// let processor = BatchProcessor::new(config, TerminalProgress::new());
+/// let stats = processor.process_directory(Path::new("./dicom_files"))?;
+/// ```
+pub struct TerminalProgress {
+    /// The underlying progress bar.
+    bar: ProgressBar,
+
+    /// Cancellation token.
+    cancelled: CancellationToken,
+
+    /// Track encode progress of one file rather than file counts.
+    single_file: bool,
+}
+
+impl Default for TerminalProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TerminalProgress {
+    /// Create a progress bar drawing to stderr.
+    pub fn new() -> Self {
+        Self::with_bar(ProgressBar::new(0))
+    }
+
+    /// Create a progress bar that tracks state without drawing.
+    pub fn hidden() -> Self {
+        Self::with_bar(ProgressBar::hidden())
+    }
+
+    /// Create a progress bar showing encode progress of a single file.
+    ///
+    /// The bar advances with the `file_progress` of `Encoding` events,
+    /// measured in pixel bytes when the events carry `total_bytes`.
+    pub fn for_file() -> Self {
+        Self {
+            single_file: true,
+            ..Self::with_bar_and_template(ProgressBar::new(100), FILE_BAR_TEMPLATE)
+        }
+    }
+
+    /// Create a handler around an existing progress bar.
+    fn with_bar(bar: ProgressBar) -> Self {
+        Self::with_bar_and_template(bar, BAR_TEMPLATE)
+    }
+
+    /// Create a handler around an existing progress bar and line template.
+    fn with_bar_and_template(bar: ProgressBar, template: &str) -> Self {
+        let style = ProgressStyle::with_template(template)
+            .unwrap_or_else(|_| ProgressStyle::default_bar())
+            .progress_chars("=> ");
+
+        Self {
+            bar: bar.with_style(style),
+            cancelled: CancellationToken::new(),
+            single_file: false,
+        }
+    }
+
+    /// Get the underlying progress bar.
+    pub fn bar(&self) -> &ProgressBar {
+        &self.bar
+    }
+
+    /// Request cancellation.
+    pub fn cancel(&self) {
+        self.cancelled.cancel();
+    }
+
+    /// Use a shared cancellation token.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancelled = token;
+        self
+    }
+
+    /// Get a handle to this handler's cancellation token.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+
+    /// Format throughput and ETA for the status message.
+    fn timing_message(event: &ProgressEvent) -> String {
+        let mut parts = Vec::new();
+
+        if event.throughput_bps > 0.0 {
+            parts.push(format!("{:.2} MB/s", event.throughput_bps / 1_000_000.0));
+        }
+
+        if let Some(eta) = event.eta_seconds {
+            parts.push(format!("ETA {}", format_duration(eta)));
+        }
+
+        if let Some(name) = event.current_file.as_ref().and_then(|p| p.file_name()) {
+            parts.push(name.to_string_lossy().to_string());
+        }
+
+        parts.join(" | ")
+    }
+}
+
+impl ProgressHandler for TerminalProgress {
+    fn on_progress(&self, event: &ProgressEvent) {
+        if self.single_file {
+            if event.phase == ProgressPhase::Encoding {
+                let length = event.total_bytes.unwrap_or(100);
+                if self.bar.length() != Some(length) {
+                    self.bar.set_length(length);
+                }
+                let position = (event.file_progress.clamp(0.0, 1.0) * length as f64).round();
+                self.bar.set_position(position as u64);
+            }
+            self.bar.set_message(Self::timing_message(event));
+            return;
+        }
+
+        if let Some(total) = event.total_files {
+            if self.bar.length() != Some(total as u64) {
+                self.bar.set_length(total as u64);
+            }
+        }
+
+        if event.phase == ProgressPhase::Complete && event.current_file.is_some() {
+            self.bar.inc(1);
+        }
+
+        self.bar.set_message(Self::timing_message(event));
+    }
+
+    fn on_error(&self, error: &MedImgError, file: Option<&Path>) {
+        let message = match file {
+            Some(path) => format!("Error: {} ({})", error, path.display()),
+            None => format!("Error: {}", error),
+        };
+
+        // A hidden bar (e.g. stderr is not a terminal) swallows println output
+        if self.bar.is_hidden() {
+            eprintln!("{}", message);
+        } else {
+            self.bar.println(message);
+        }
+
+        if file.is_some() {
+            self.bar.inc(1);
+        }
+    }
+
+    fn on_complete(&self, stats: &BatchStats) {
+        self.bar.finish_with_message(format!(
+            "done: {} succeeded, {} failed, {:.2}:1 overall",
+            stats.successful,
+            stats.failed,
+            stats.overall_ratio()
+        ));
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.is_cancelled()
+    }
+}
+
+/// Format seconds as `h:mm:ss` or `m:ss`.
+fn format_duration(seconds: f64) -> String {
+    let total = seconds.max(0.0).round() as u64;
+    let (h, m, s) = (total / 3600, (total % 3600) / 60, total % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_terminal_progress_counts_files() {
+        let progress = TerminalProgress::hidden();
+
+        progress.on_progress(&ProgressEvent {
+            total_files: Some(3),
+            ..Default::default()
+        });
+        assert_eq!(progress.bar().length(), Some(3));
+
+        let done = ProgressEvent {
+            phase: ProgressPhase::Complete,
+            current_file: Some(PathBuf::from("/test/a.dcm")),
+            ..Default::default()
+        };
+        progress.on_progress(&done);
+        progress.on_error(
+            &MedImgError::Internal("test".into()),
+            Some(Path::new("/test/b.dcm")),
+        );
+        progress.on_error(&MedImgError::Internal("test".into()), None);
+
+        assert_eq!(progress.bar().position(), 2);
+    }
+
+    #[test]
+    fn test_single_file_progress() {
+        let progress = TerminalProgress {
+            single_file: true,
+            ..TerminalProgress::hidden()
+        };
+        progress.bar().set_length(100);
+
+        progress.on_progress(&ProgressEvent::encoding(Path::new("/test/a.dcm"), 0.42));
+        assert_eq!(progress.bar().position(), 42);
+
+        // Pixel bytes drive the rate and ETA when the event carries them
+        let event = ProgressEvent::encoding(Path::new("/test/a.dcm"), 0.42).with_bytes(4200, 10_000);
+        progress.on_progress(&event);
+        assert_eq!((progress.bar().position(), progress.bar().length()), (4200, Some(10_000)));
+
+        // File completion events do not count files in single-file mode
+        progress.on_progress(&ProgressEvent {
+            phase: ProgressPhase::Complete,
+            current_file: Some(PathBuf::from("/test/a.dcm")),
+            ..Default::default()
+        });
+        assert_eq!(progress.bar().position(), 4200);
+    }
+
+    #[test]
+    fn test_timing_message() {
+        let event = ProgressEvent {
+            current_file: Some(PathBuf::from("/test/a.dcm")),
+            throughput_bps: 2_500_000.0,
+            eta_seconds: Some(125.0),
+            ..Default::default()
+        };
+
+        let message = TerminalProgress::timing_message(&event);
+        assert_eq!(message, "2.50 MB/s | ETA 2:05 | a.dcm");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(5.0), "0:05");
+        assert_eq!(format_duration(3725.0), "1:02:05");
+    }
+
+    #[test]
+    fn test_terminal_progress_cancellation() {
+        let progress = TerminalProgress::hidden();
+        assert!(!progress.is_cancelled());
+        progress.cancel();
+        assert!(progress.is_cancelled());
+    }
+}
@@ -0,0 +1,178 @@
+//! Rate-limited progress reporting.
+//!
+//! Batches of many small files can emit thousands of events per second,
+//! more than a GUI callback can redraw. [`ThrottledProgress`] coalesces
+//! intermediate events so the inner handler sees at most N per second.
+
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::MedImgError;
+use crate::pipeline::BatchStats;
+
+use super::handler::{ProgressEvent, ProgressHandler};
+
+/// A progress handler wrapper that limits how often events are delivered.
+///
+/// Intermediate events arriving faster than the limit are coalesced: only
+/// the most recent one is kept and delivered once the interval has passed
+/// (or on [`flush`](Self::flush)). Terminal events (`Complete`/`Failed`),
+/// errors, and batch completion are always delivered immediately.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::progress::{CallbackProgress, ThrottledProgress};
+///
+/// let progress = ThrottledProgress::new(
+///     CallbackProgress::new(|event| update_gui(event)),
+///     10, // at most 10 updates per second
+/// );
+/// let processor = BatchProcessor::new(config, progress);
+/// ```
+pub struct ThrottledProgress<H: ProgressHandler> {
+    /// Wrapped handler.
+    inner: H,
+
+    /// Minimum time between delivered intermediate events.
+    interval: Duration,
+
+    /// Delivery state.
+    state: Mutex<ThrottleState>,
+}
+
+/// Mutable throttling state.
+#[derive(Default)]
+struct ThrottleState {
+    /// When an intermediate event was last delivered.
+    last_emit: Option<Instant>,
+
+    /// Most recent event held back by the rate limit.
+    pending: Option<ProgressEvent>,
+}
+
+impl<H: ProgressHandler> ThrottledProgress<H> {
+    /// Wrap `inner`, delivering at most `max_per_second` intermediate events.
+    ///
+    /// A rate of zero is treated as one event per second.
+    pub fn new(inner: H, max_per_second: u32) -> Self {
+        Self {
+            inner,
+            interval: Duration::from_secs(1) / max_per_second.max(1),
+            state: Mutex::new(ThrottleState::default()),
+        }
+    }
+
+    /// Get the wrapped handler.
+    pub fn inner(&self) -> &H {
+        &self.inner
+    }
+
+    /// Deliver the most recent held-back event, if any.
+    pub fn flush(&self) {
+        let pending = self.state.lock().ok().and_then(|mut s| s.pending.take());
+        if let Some(event) = pending {
+            self.inner.on_progress(&event);
+        }
+    }
+
+    /// Drop any held-back event; it is superseded by a terminal event.
+    fn discard_pending(&self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.pending = None;
+        }
+    }
+}
+
+impl<H: ProgressHandler> ProgressHandler for ThrottledProgress<H> {
+    fn on_progress(&self, event: &ProgressEvent) {
+        if event.phase.is_terminal() {
+            self.discard_pending();
+            self.inner.on_progress(event);
+            return;
+        }
+
+        let deliver = match self.state.lock() {
+            Ok(mut state) => {
+                let now = Instant::now();
+                let due = state
+                    .last_emit
+                    .is_none_or(|last| now.duration_since(last) >= self.interval);
+                if due {
+                    state.last_emit = Some(now);
+                    state.pending = None;
+                } else {
+                    state.pending = Some(event.clone());
+                }
+                due
+            }
+            Err(_) => true,
+        };
+
+        if deliver {
+            self.inner.on_progress(event);
+        }
+    }
+
+    fn on_error(&self, error: &MedImgError, file: Option<&Path>) {
+        self.inner.on_error(error, file);
+    }
+
+    fn on_complete(&self, stats: &BatchStats) {
+        self.discard_pending();
+        self.inner.on_complete(stats);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::{ChannelProgress, ProgressPhase};
+
+    #[test]
+    fn test_throttled_progress_coalesces() {
+        let (channel, receiver) = ChannelProgress::new();
+        let progress = ThrottledProgress::new(channel, 1);
+
+        for i in 0..10 {
+            progress.on_progress(&ProgressEvent {
+                phase: ProgressPhase::Encoding,
+                message: format!("event {}", i),
+                ..Default::default()
+            });
+        }
+
+        assert_eq!(receiver.try_recv().unwrap().message, "event 0");
+        assert!(receiver.try_recv().is_err());
+
+        progress.flush();
+        assert_eq!(receiver.try_recv().unwrap().message, "event 9");
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_throttled_progress_always_delivers_terminal() {
+        let (channel, receiver) = ChannelProgress::new();
+        let progress = ThrottledProgress::new(channel, 1);
+
+        progress.on_progress(&ProgressEvent::new(ProgressPhase::Reading));
+        progress.on_progress(&ProgressEvent::new(ProgressPhase::Encoding));
+        progress.on_progress(&ProgressEvent::new(ProgressPhase::Complete));
+        progress.on_progress(&ProgressEvent::failed("boom"));
+
+        let phases: Vec<_> = receiver.try_iter().map(|e| e.phase).collect();
+        assert_eq!(
+            phases,
+            vec![ProgressPhase::Reading, ProgressPhase::Complete, ProgressPhase::Failed]
+        );
+
+        // The coalesced Encoding event was superseded by the terminal events
+        progress.flush();
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,164 @@
+//! Tracing-based progress reporting.
+//!
+//! Emits progress, error, and completion events as structured `tracing`
+//! events, so any installed subscriber (fmt, JSON, OpenTelemetry) receives
+//! them without custom callbacks.
+
+use std::path::Path;
+
+use crate::error::MedImgError;
+use crate::pipeline::BatchStats;
+
+use super::cancel::CancellationToken;
+use super::handler::{ProgressEvent, ProgressHandler};
+
+/// Target used for all events emitted by [`TracingProgress`].
+pub const PROGRESS_TARGET: &str = "medimg::progress";
+
+/// A progress handler that emits `tracing` events.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::progress::TracingProgress;
+///
+/// tracing_subscriber::fmt().json().init();
+/// let processor = BatchProcessor::new(config, TracingProgress::new());
+/// ```
+#[derive(Debug, Default)]
+pub struct TracingProgress {
+    /// Cancellation token.
+    cancelled: CancellationToken,
+}
+
+impl TracingProgress {
+    /// Create a new tracing progress handler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation.
+    pub fn cancel(&self) {
+        self.cancelled.cancel();
+    }
+
+    /// Use a shared cancellation token.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancelled = token;
+        self
+    }
+
+    /// Get a handle to this handler's cancellation token.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+}
+
+impl ProgressHandler for TracingProgress {
+    fn on_progress(&self, event: &ProgressEvent) {
+        tracing::info!(
+            target: PROGRESS_TARGET,
+            phase = %event.phase,
+            file = event.current_file.as_ref().map(|p| p.display().to_string()),
+            completed_files = event.completed_files,
+            total_files = event.total_files,
+            overall_progress = event.overall_progress,
+            bytes_processed = event.bytes_processed,
+            throughput_bps = event.throughput_bps,
+            eta_seconds = event.eta_seconds,
+            "{}",
+            event.message
+        );
+    }
+
+    fn on_error(&self, error: &MedImgError, file: Option<&Path>) {
+        tracing::error!(
+            target: PROGRESS_TARGET,
+            file = file.map(|p| p.display().to_string()),
+            error = %error,
+            "compression failed"
+        );
+    }
+
+    fn on_complete(&self, stats: &BatchStats) {
+        tracing::info!(
+            target: PROGRESS_TARGET,
+            total_files = stats.total_files,
+            successful = stats.successful,
+            failed = stats.failed,
+            skipped = stats.skipped,
+            original_bytes = stats.total_original_bytes,
+            compressed_bytes = stats.total_compressed_bytes,
+            ratio = stats.overall_ratio(),
+            duration_ms = stats.total_time_ms,
+            "batch complete"
+        );
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.is_cancelled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    /// Subscriber that counts events emitted under the progress target.
+    struct CountingSubscriber {
+        events: Arc<AtomicUsize>,
+    }
+
+    impl Subscriber for CountingSubscriber {
+        fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            if event.metadata().target() == PROGRESS_TARGET {
+                self.events.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        fn enter(&self, _span: &Id) {}
+
+        fn exit(&self, _span: &Id) {}
+    }
+
+    #[test]
+    fn test_tracing_progress_emits_events() {
+        let events = Arc::new(AtomicUsize::new(0));
+        let subscriber = CountingSubscriber {
+            events: events.clone(),
+        };
+
+        tracing::subscriber::with_default(subscriber, || {
+            let progress = TracingProgress::new();
+            progress.on_progress(&ProgressEvent::default());
+            progress.on_error(&MedImgError::Internal("test".into()), None);
+            progress.on_complete(&BatchStats::default());
+        });
+
+        assert_eq!(events.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_tracing_progress_cancellation() {
+        let progress = TracingProgress::new();
+        assert!(!progress.is_cancelled());
+        progress.cancel();
+        assert!(progress.is_cancelled());
+    }
+}
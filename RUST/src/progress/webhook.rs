@@ -0,0 +1,347 @@
+//! HTTP webhook progress reporting.
+//!
+//! POSTs batched progress and completion events as JSON to a configurable
+//! URL, so workflow engines can track long-running migrations without
+//! polling. Requests are sent from a background thread so slow endpoints
+//! do not stall compression workers.
+
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+
+use crate::error::{MedImgError, Result};
+use crate::pipeline::BatchStats;
+
+use super::cancel::CancellationToken;
+use super::handler::{ProgressEvent, ProgressHandler};
+
+/// Sends a JSON payload to the endpoint.
+type Transport = Box<dyn Fn(&serde_json::Value) -> Result<()> + Send>;
+
+/// Message from the handler to the delivery thread.
+enum Message {
+    /// A progress (or error) event to batch.
+    Event(ProgressEvent),
+    /// Batch completion summary; flushes immediately.
+    Complete(serde_json::Value),
+}
+
+/// A progress handler that POSTs batched events to an HTTP endpoint.
+///
+/// Events are collected and sent as `{"events": [...]}` once `batch_size`
+/// events are pending or `flush_interval` has elapsed. Batch completion
+/// flushes pending events together with a `"complete"` summary. Delivery
+/// failures are logged and do not affect compression.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::progress::WebhookProgress;
+///
+/// let progress = WebhookProgress::builder("https://workflow.example.org/hooks/migration")
+///     .header("Authorization", "Bearer <token>")
+///     .batch_size(100)
+///     .build();
+///
+/// let processor = BatchProcessor::new(config, progress);
+/// ```
+pub struct WebhookProgress {
+    /// Channel to the delivery thread.
+    sender: Option<Sender<Message>>,
+
+    /// Delivery thread, joined on drop to flush pending events.
+    worker: Option<JoinHandle<()>>,
+
+    /// Cancellation token.
+    cancelled: CancellationToken,
+}
+
+/// Builder for [`WebhookProgress`].
+pub struct WebhookProgressBuilder {
+    url: String,
+    headers: Vec<(String, String)>,
+    batch_size: usize,
+    flush_interval: Duration,
+    timeout: Duration,
+    cancelled: Option<CancellationToken>,
+}
+
+impl WebhookProgressBuilder {
+    /// Add an HTTP header to every request.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Maximum number of events per request.
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size.max(1);
+        self
+    }
+
+    /// Maximum time events are held before being sent.
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Timeout for each HTTP request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Use a shared cancellation token.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancelled = Some(token);
+        self
+    }
+
+    /// Build the handler and start its delivery thread.
+    pub fn build(self) -> WebhookProgress {
+        let Self {
+            url,
+            headers,
+            batch_size,
+            flush_interval,
+            timeout,
+            cancelled,
+        } = self;
+
+        let agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        let transport: Transport = Box::new(move |payload| {
+            let mut request = agent.post(&url).set("Content-Type", "application/json");
+            for (name, value) in &headers {
+                request = request.set(name, value);
+            }
+            request
+                .send_string(&payload.to_string())
+                .map(|_| ())
+                .map_err(request_error)
+        });
+
+        WebhookProgress::spawn(transport, batch_size, flush_interval, cancelled.unwrap_or_default())
+    }
+}
+
+/// Classify a failed request, so timeouts are reported as such.
+fn request_error(error: ureq::Error) -> MedImgError {
+    let message = format!("Webhook request failed: {}", error);
+    let mut source = std::error::Error::source(&error);
+    while let Some(err) = source {
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            if matches!(io.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock) {
+                return MedImgError::Timeout(message);
+            }
+        }
+        source = err.source();
+    }
+    MedImgError::Internal(message)
+}
+
+impl WebhookProgress {
+    /// Default maximum number of events per request.
+    pub const DEFAULT_BATCH_SIZE: usize = 50;
+
+    /// Default maximum time events are held before being sent.
+    pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+    /// Default HTTP request timeout.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Create a handler posting to `url` with default settings.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self::builder(url).build()
+    }
+
+    /// Create a builder for a handler posting to `url`.
+    pub fn builder(url: impl Into<String>) -> WebhookProgressBuilder {
+        WebhookProgressBuilder {
+            url: url.into(),
+            headers: Vec::new(),
+            batch_size: Self::DEFAULT_BATCH_SIZE,
+            flush_interval: Self::DEFAULT_FLUSH_INTERVAL,
+            timeout: Self::DEFAULT_TIMEOUT,
+            cancelled: None,
+        }
+    }
+
+    /// Start the delivery thread.
+    fn spawn(
+        transport: Transport,
+        batch_size: usize,
+        flush_interval: Duration,
+        cancelled: CancellationToken,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel::<Message>();
+
+        let worker = std::thread::spawn(move || {
+            let mut pending: Vec<ProgressEvent> = Vec::new();
+            let mut last_flush = Instant::now();
+
+            let send = |events: &mut Vec<ProgressEvent>, complete: Option<serde_json::Value>| {
+                if events.is_empty() && complete.is_none() {
+                    return;
+                }
+                let mut payload = json!({ "events": std::mem::take(events) });
+                if let Some(summary) = complete {
+                    payload["complete"] = summary;
+                }
+                if let Err(e) = transport(&payload) {
+                    log::warn!("{}", e);
+                }
+            };
+
+            loop {
+                let wait = flush_interval.saturating_sub(last_flush.elapsed());
+                match receiver.recv_timeout(wait) {
+                    Ok(Message::Event(event)) => {
+                        pending.push(event);
+                        if pending.len() >= batch_size {
+                            send(&mut pending, None);
+                            last_flush = Instant::now();
+                        }
+                    }
+                    Ok(Message::Complete(summary)) => {
+                        send(&mut pending, Some(summary));
+                        last_flush = Instant::now();
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        send(&mut pending, None);
+                        last_flush = Instant::now();
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        send(&mut pending, None);
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self {
+            sender: Some(sender),
+            worker: Some(worker),
+            cancelled,
+        }
+    }
+
+    /// Request cancellation.
+    pub fn cancel(&self) {
+        self.cancelled.cancel();
+    }
+
+    /// Get a handle to this handler's cancellation token.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+
+    /// Queue a message for the delivery thread.
+    fn send(&self, message: Message) {
+        if let Some(ref sender) = self.sender {
+            // Ignore send errors (delivery thread has exited)
+            let _ = sender.send(message);
+        }
+    }
+}
+
+impl Drop for WebhookProgress {
+    fn drop(&mut self) {
+        // Closing the channel makes the delivery thread flush and exit
+        self.sender.take();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl ProgressHandler for WebhookProgress {
+    fn on_progress(&self, event: &ProgressEvent) {
+        self.send(Message::Event(event.clone()));
+    }
+
+    fn on_error(&self, error: &MedImgError, file: Option<&Path>) {
+        let event = ProgressEvent::error(error, file);
+        self.send(Message::Event(event));
+    }
+
+    fn on_complete(&self, stats: &BatchStats) {
+        self.send(Message::Complete(json!({
+            "total_files": stats.total_files,
+            "successful": stats.successful,
+            "failed": stats.failed,
+            "skipped": stats.skipped,
+            "original_bytes": stats.total_original_bytes,
+            "compressed_bytes": stats.total_compressed_bytes,
+            "time_ms": stats.total_time_ms,
+        })));
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.is_cancelled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn recording(batch_size: usize) -> (WebhookProgress, Arc<Mutex<Vec<serde_json::Value>>>) {
+        let payloads = Arc::new(Mutex::new(Vec::new()));
+        let sink = payloads.clone();
+        let transport: Transport = Box::new(move |payload| {
+            sink.lock().unwrap().push(payload.clone());
+            Ok(())
+        });
+
+        let progress = WebhookProgress::spawn(
+            transport,
+            batch_size,
+            Duration::from_secs(60),
+            CancellationToken::new(),
+        );
+        (progress, payloads)
+    }
+
+    #[test]
+    fn test_webhook_batches_events() {
+        let (progress, payloads) = recording(2);
+
+        for i in 0..5 {
+            progress.on_progress(&ProgressEvent::discovery(format!("event {}", i)));
+        }
+        drop(progress);
+
+        let payloads = payloads.lock().unwrap();
+        let sizes: Vec<usize> = payloads
+            .iter()
+            .map(|p| p["events"].as_array().unwrap().len())
+            .collect();
+        assert_eq!(sizes, vec![2, 2, 1]);
+        assert_eq!(payloads[2]["events"][0]["message"], "event 4");
+    }
+
+    #[test]
+    fn test_webhook_completion_flushes() {
+        let (progress, payloads) = recording(100);
+
+        progress.on_error(
+            &MedImgError::Internal("boom".into()),
+            Some(Path::new("/test/a.dcm")),
+        );
+        progress.on_complete(&BatchStats {
+            total_files: 1,
+            failed: 1,
+            ..Default::default()
+        });
+        drop(progress);
+
+        let payloads = payloads.lock().unwrap();
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0]["events"][0]["phase"], "Failed");
+        assert_eq!(payloads[0]["complete"]["failed"], 1);
+    }
+}
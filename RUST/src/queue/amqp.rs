@@ -0,0 +1,121 @@
+//! AMQP 0.9.1 broker (`amqp` feature).
+
+use std::time::{Duration, Instant};
+
+use futures::executor::block_on;
+use lapin::options::{
+    BasicAckOptions, BasicGetOptions, BasicNackOptions, BasicPublishOptions, ConfirmSelectOptions,
+    QueueDeclareOptions,
+};
+use lapin::publisher_confirm::Confirmation;
+use lapin::types::FieldTable;
+use lapin::{BasicProperties, Channel, Connection, ConnectionProperties};
+
+use super::{Broker, BrokerConfig, Message};
+use crate::error::{MedImgError, Result};
+
+/// Interval between polls of an empty task queue.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Delivery mode of messages that survive a broker restart.
+const PERSISTENT: u8 = 2;
+
+/// Broker error.
+fn broker_error(action: &str, e: lapin::Error) -> MedImgError {
+    MedImgError::Storage(format!("AMQP {} failed: {}", action, e))
+}
+
+/// AMQP broker: tasks and events are durable queues, published to through
+/// the default exchange with confirms.
+pub struct AmqpBroker {
+    /// Kept open for the channel's lifetime.
+    _connection: Connection,
+    channel: Channel,
+    tasks: String,
+    events: String,
+}
+
+impl AmqpBroker {
+    /// Connect and declare the task and event queues.
+    pub fn connect(config: &BrokerConfig) -> Result<Self> {
+        block_on(async {
+            let connection = Connection::connect(&config.url, ConnectionProperties::default())
+                .await
+                .map_err(|e| broker_error("connect", e))?;
+            let channel = connection
+                .create_channel()
+                .await
+                .map_err(|e| broker_error("channel", e))?;
+            channel
+                .confirm_select(ConfirmSelectOptions::default())
+                .await
+                .map_err(|e| broker_error("confirm", e))?;
+            let durable = QueueDeclareOptions {
+                durable: true,
+                ..Default::default()
+            };
+            for queue in [&config.tasks, &config.events] {
+                channel
+                    .queue_declare(queue, durable, FieldTable::default())
+                    .await
+                    .map_err(|e| broker_error("queue declare", e))?;
+            }
+            Ok(Self {
+                _connection: connection,
+                channel,
+                tasks: config.tasks.clone(),
+                events: config.events.clone(),
+            })
+        })
+    }
+}
+
+impl Broker for AmqpBroker {
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Message>> {
+        let start = Instant::now();
+        loop {
+            let got = block_on(self.channel.basic_get(&self.tasks, BasicGetOptions::default()))
+                .map_err(|e| broker_error("get", e))?;
+            if let Some(got) = got {
+                return Ok(Some(Message {
+                    payload: got.delivery.data,
+                    tag: got.delivery.delivery_tag,
+                }));
+            }
+            if start.elapsed() >= timeout {
+                return Ok(None);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn ack(&mut self, message: &Message) -> Result<()> {
+        block_on(self.channel.basic_ack(message.tag, BasicAckOptions::default()))
+            .map_err(|e| broker_error("ack", e))
+    }
+
+    fn reject(&mut self, message: &Message) -> Result<()> {
+        let requeue = BasicNackOptions {
+            requeue: true,
+            ..Default::default()
+        };
+        block_on(self.channel.basic_nack(message.tag, requeue)).map_err(|e| broker_error("nack", e))
+    }
+
+    fn publish(&mut self, payload: &[u8]) -> Result<()> {
+        let properties = BasicProperties::default()
+            .with_content_type("application/json".into())
+            .with_delivery_mode(PERSISTENT);
+        let confirmation = block_on(async {
+            self.channel
+                .basic_publish("", &self.events, BasicPublishOptions::default(), payload, properties)
+                .await?
+                .await
+        })
+        .map_err(|e| broker_error("publish", e))?;
+        match confirmation {
+            Confirmation::Nack(_) => Err(MedImgError::Storage("AMQP broker rejected the event".into())),
+            _ => Ok(()),
+        }
+    }
+}
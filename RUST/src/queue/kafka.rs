@@ -0,0 +1,115 @@
+//! Kafka broker (`kafka` feature).
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+use rdkafka::error::KafkaError;
+use rdkafka::producer::{BaseProducer, BaseRecord, Producer};
+use rdkafka::{Message as _, Offset, TopicPartitionList};
+
+use super::{Broker, BrokerConfig, Message};
+use crate::error::{MedImgError, Result};
+
+/// Longest a publish waits for the broker to acknowledge the event.
+const PUBLISH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Broker error.
+fn broker_error(action: &str, e: KafkaError) -> MedImgError {
+    MedImgError::Storage(format!("Kafka {} failed: {}", action, e))
+}
+
+/// Kafka broker: workers share a consumer group on the task topic, and
+/// offsets are committed only once a task's event is published.
+pub struct KafkaBroker {
+    consumer: BaseConsumer,
+    producer: BaseProducer,
+    tasks: String,
+    events: String,
+    /// Partition and offset of each received, unacknowledged task.
+    pending: HashMap<u64, (i32, i64)>,
+    next_tag: u64,
+}
+
+impl KafkaBroker {
+    /// Connect to the bootstrap servers in `kafka://host:port[,host:port...]`
+    /// and subscribe to the task topic.
+    pub fn connect(config: &BrokerConfig) -> Result<Self> {
+        let servers = config.url.trim_start_matches("kafka://").trim_end_matches('/');
+        let consumer: BaseConsumer = ClientConfig::new()
+            .set("bootstrap.servers", servers)
+            .set("group.id", &config.group)
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "earliest")
+            .create()
+            .map_err(|e| broker_error("connect", e))?;
+        consumer
+            .subscribe(&[&config.tasks])
+            .map_err(|e| broker_error("subscribe", e))?;
+        let producer: BaseProducer = ClientConfig::new()
+            .set("bootstrap.servers", servers)
+            .set("acks", "all")
+            .create()
+            .map_err(|e| broker_error("connect", e))?;
+        Ok(Self {
+            consumer,
+            producer,
+            tasks: config.tasks.clone(),
+            events: config.events.clone(),
+            pending: HashMap::new(),
+            next_tag: 0,
+        })
+    }
+
+    /// Take the partition and offset of a received task.
+    fn take_pending(&mut self, message: &Message) -> Result<(i32, i64)> {
+        self.pending
+            .remove(&message.tag)
+            .ok_or_else(|| MedImgError::Internal(format!("Unknown Kafka delivery {}", message.tag)))
+    }
+}
+
+impl Broker for KafkaBroker {
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Message>> {
+        let Some(received) = self.consumer.poll(timeout) else {
+            return Ok(None);
+        };
+        let received = received.map_err(|e| broker_error("poll", e))?;
+        self.next_tag += 1;
+        self.pending
+            .insert(self.next_tag, (received.partition(), received.offset()));
+        Ok(Some(Message {
+            payload: received.payload().unwrap_or_default().to_vec(),
+            tag: self.next_tag,
+        }))
+    }
+
+    fn ack(&mut self, message: &Message) -> Result<()> {
+        let (partition, offset) = self.take_pending(message)?;
+        let mut offsets = TopicPartitionList::new();
+        offsets
+            .add_partition_offset(&self.tasks, partition, Offset::Offset(offset + 1))
+            .map_err(|e| broker_error("commit", e))?;
+        self.consumer
+            .commit(&offsets, CommitMode::Sync)
+            .map_err(|e| broker_error("commit", e))
+    }
+
+    fn reject(&mut self, message: &Message) -> Result<()> {
+        // Rewind so the task is read again
+        let (partition, offset) = self.take_pending(message)?;
+        self.consumer
+            .seek(&self.tasks, partition, Offset::Offset(offset), PUBLISH_TIMEOUT)
+            .map_err(|e| broker_error("seek", e))
+    }
+
+    fn publish(&mut self, payload: &[u8]) -> Result<()> {
+        self.producer
+            .send(BaseRecord::<(), [u8]>::to(&self.events).payload(payload))
+            .map_err(|(e, _)| broker_error("publish", e))?;
+        self.producer
+            .flush(PUBLISH_TIMEOUT)
+            .map_err(|e| broker_error("publish", e))
+    }
+}
@@ -0,0 +1,470 @@
+//! Message-queue worker mode.
+//!
+//! A [`QueueWorker`] consumes compression tasks from a message broker,
+//! compresses each with the pipeline, and publishes an event with the
+//! result and quality metrics. Any number of workers can consume from the
+//! same queue, so archive migrations scale out by starting more of them.
+//!
+//! Brokers are chosen by URL scheme (see [`connect`]):
+//!
+//! - `amqp://` / `amqps://`: RabbitMQ and other AMQP 0.9.1 brokers
+//!   (`amqp` feature); tasks and events are durable queues
+//! - `nats://`: NATS (`nats` feature); workers share a queue group
+//! - `kafka://host:port[,host:port...]`: Kafka (`kafka` feature); workers
+//!   share a consumer group
+//!
+//! A task is a JSON object naming files on storage shared by the workers:
+//!
+//! ```json
+//! {"id": "study-42/1", "input": "/archive/in/1.dcm", "output": "/archive/out/1.dcm",
+//!  "options": {"codec": "jpeg-ls"}}
+//! ```
+//!
+//! `options` takes the same `codec`, `mode`, `ratio`, `bpp`, and `near`
+//! settings as the HTTP service; modality safety checks always apply. Each
+//! task produces one [`TaskEvent`] on the events queue, and is
+//! acknowledged once its event is published, so a worker that dies
+//! mid-task leaves the task to another worker (except on NATS, which does
+//! not redeliver).
+
+#[cfg(feature = "amqp")]
+mod amqp;
+#[cfg(feature = "kafka")]
+mod kafka;
+#[cfg(feature = "nats")]
+mod nats;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::CompressionConfig;
+use crate::error::{ErrorRecord, MedImgError, Result};
+#[cfg(feature = "prometheus")]
+use crate::monitoring::OperationalMetrics;
+use crate::pipeline::{BatchStats, CompressionPipeline, CompressionResult};
+use crate::progress::CancellationToken;
+use crate::server::request_config;
+
+#[cfg(feature = "amqp")]
+pub use amqp::AmqpBroker;
+#[cfg(feature = "kafka")]
+pub use kafka::KafkaBroker;
+#[cfg(feature = "nats")]
+pub use nats::NatsBroker;
+
+/// Default queue, subject, or topic tasks are read from.
+pub const DEFAULT_TASKS: &str = "medimg.tasks";
+
+/// Default queue, subject, or topic events are published to.
+pub const DEFAULT_EVENTS: &str = "medimg.events";
+
+/// Default consumer group (NATS queue group, Kafka group id).
+pub const DEFAULT_GROUP: &str = "medimg-workers";
+
+/// Longest a worker waits for a message before checking for cancellation.
+const RECEIVE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Broker connection settings.
+#[derive(Debug, Clone)]
+pub struct BrokerConfig {
+    /// Broker URL; the scheme selects the broker.
+    pub url: String,
+    /// Queue, subject, or topic tasks are read from.
+    pub tasks: String,
+    /// Queue, subject, or topic events are published to.
+    pub events: String,
+    /// Consumer group shared by the workers (unused for AMQP, where
+    /// workers share the task queue).
+    pub group: String,
+}
+
+impl BrokerConfig {
+    /// Settings for `url` with the default queue and group names.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            tasks: DEFAULT_TASKS.to_string(),
+            events: DEFAULT_EVENTS.to_string(),
+            group: DEFAULT_GROUP.to_string(),
+        }
+    }
+}
+
+/// A message received from a broker.
+#[derive(Debug, Clone)]
+pub struct Message {
+    /// Message body.
+    pub payload: Vec<u8>,
+    /// Broker-specific delivery identifier, passed back to
+    /// [`Broker::ack`] and [`Broker::reject`].
+    pub tag: u64,
+}
+
+/// A message broker a worker consumes tasks from and publishes events to.
+pub trait Broker: Send {
+    /// Wait up to `timeout` for the next task.
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Message>>;
+
+    /// Acknowledge a task, removing it from the queue.
+    fn ack(&mut self, message: &Message) -> Result<()>;
+
+    /// Return a task to the queue for another worker.
+    fn reject(&mut self, message: &Message) -> Result<()>;
+
+    /// Publish an event.
+    fn publish(&mut self, payload: &[u8]) -> Result<()>;
+}
+
+/// Connect to the broker named by the URL scheme of `config.url`.
+///
+/// # Errors
+///
+/// Returns `Config` for an unknown scheme or one whose feature is not
+/// enabled, and `Storage` if the broker cannot be reached.
+pub fn connect(config: &BrokerConfig) -> Result<Box<dyn Broker>> {
+    let scheme = config.url.split_once("://").map(|(scheme, _)| scheme).unwrap_or_default();
+    match scheme {
+        #[cfg(feature = "amqp")]
+        "amqp" | "amqps" => return Ok(Box::new(AmqpBroker::connect(config)?)),
+        #[cfg(feature = "nats")]
+        "nats" => return Ok(Box::new(NatsBroker::connect(config)?)),
+        #[cfg(feature = "kafka")]
+        "kafka" => return Ok(Box::new(KafkaBroker::connect(config)?)),
+        _ => {}
+    }
+    let feature = match scheme {
+        "amqp" | "amqps" => "amqp",
+        "nats" => "nats",
+        "kafka" => "kafka",
+        _ => {
+            return Err(MedImgError::Config(format!(
+                "Unsupported broker URL '{}' (expected amqp://, nats://, or kafka://)",
+                config.url
+            )))
+        }
+    };
+    Err(MedImgError::Config(format!(
+        "{}:// brokers need the `{}` feature",
+        scheme, feature
+    )))
+}
+
+/// A compression task.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    /// Task identifier, echoed in the event.
+    pub id: String,
+    /// File to compress.
+    pub input: PathBuf,
+    /// Where to write the compressed file.
+    pub output: PathBuf,
+    /// Overrides of the worker's settings (`codec`, `mode`, `ratio`, `bpp`,
+    /// `near`).
+    #[serde(default)]
+    pub options: HashMap<String, String>,
+}
+
+/// Outcome of a task.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    /// Compressed and written.
+    Completed,
+    /// Not compressed; see the event's error.
+    Failed,
+}
+
+/// Event published for each task.
+#[derive(Debug, Serialize)]
+pub struct TaskEvent<'a> {
+    /// Task identifier (`None` if the task could not be parsed).
+    pub id: Option<&'a str>,
+    /// Outcome.
+    pub status: TaskStatus,
+    /// Input file.
+    pub input: Option<&'a PathBuf>,
+    /// Compressed file, once completed.
+    pub output: Option<&'a PathBuf>,
+    /// Compression result, including quality metrics when measured.
+    pub result: Option<&'a CompressionResult>,
+    /// Error, if the task failed.
+    pub error: Option<ErrorRecord>,
+}
+
+/// Worker consuming compression tasks from a broker.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::queue::{self, BrokerConfig, QueueWorker};
+///
+/// let broker = queue::connect(&BrokerConfig::new("amqp://localhost:5672"))?;
+/// let stats = QueueWorker::new(CompressionConfig::default(), broker).run()?;
+/// ```
+pub struct QueueWorker {
+    /// Default compression settings.
+    config: CompressionConfig,
+
+    /// Broker tasks are read from and events published to.
+    broker: Box<dyn Broker>,
+
+    /// Whether events include quality metrics.
+    measure_quality: bool,
+
+    /// Operational metrics updated as tasks finish.
+    #[cfg(feature = "prometheus")]
+    metrics: Option<OperationalMetrics>,
+
+    /// Cancellation token; stops the worker.
+    cancelled: CancellationToken,
+}
+
+impl QueueWorker {
+    /// Create a worker with default compression settings.
+    pub fn new(config: CompressionConfig, broker: Box<dyn Broker>) -> Self {
+        Self {
+            config,
+            broker,
+            measure_quality: true,
+            #[cfg(feature = "prometheus")]
+            metrics: None,
+            cancelled: CancellationToken::new(),
+        }
+    }
+
+    /// Set whether events include quality metrics (on by default; measuring
+    /// decodes each compressed file again).
+    pub fn measure_quality(mut self, measure: bool) -> Self {
+        self.measure_quality = measure;
+        self
+    }
+
+    /// Record finished tasks in `metrics`.
+    #[cfg(feature = "prometheus")]
+    pub fn metrics(mut self, metrics: OperationalMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Use a shared cancellation token.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancelled = token;
+        self
+    }
+
+    /// Get a handle to this worker's cancellation token.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+
+    /// Process tasks until cancelled.
+    ///
+    /// The task in progress is finished before returning.
+    ///
+    /// # Errors
+    ///
+    /// Returns the broker's error if receiving, publishing, or
+    /// acknowledging fails; unacknowledged tasks are redelivered.
+    pub fn run(mut self) -> Result<BatchStats> {
+        let start = Instant::now();
+        let mut stats = BatchStats::default();
+
+        while !self.cancelled.is_cancelled() {
+            let Some(message) = self.broker.receive(RECEIVE_TIMEOUT)? else {
+                continue;
+            };
+            let (status, original, compressed) = match self.process(&message) {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    // The event was not published; leave the task for a retry
+                    if let Err(reject) = self.broker.reject(&message) {
+                        log::warn!("Failed to return task to the queue: {}", reject);
+                    }
+                    return Err(e);
+                }
+            };
+            self.broker.ack(&message)?;
+
+            stats.total_files += 1;
+            match status {
+                TaskStatus::Completed => {
+                    stats.successful += 1;
+                    stats.total_original_bytes += original;
+                    stats.total_compressed_bytes += compressed;
+                }
+                TaskStatus::Failed => stats.failed += 1,
+            }
+        }
+
+        stats.total_time_ms = start.elapsed().as_millis() as u64;
+        Ok(stats)
+    }
+
+    /// Compress one task and publish its event, returning the status and
+    /// original and compressed sizes.
+    fn process(&mut self, message: &Message) -> Result<(TaskStatus, usize, usize)> {
+        let task: Task = match serde_json::from_slice(&message.payload) {
+            Ok(task) => task,
+            Err(e) => {
+                let e = MedImgError::InvalidFormat(format!("Invalid task: {}", e));
+                log::warn!("{}", e);
+                self.publish(&TaskEvent {
+                    id: None,
+                    status: TaskStatus::Failed,
+                    input: None,
+                    output: None,
+                    result: None,
+                    error: Some(ErrorRecord::new(&e, None)),
+                })?;
+                return Ok((TaskStatus::Failed, 0, 0));
+            }
+        };
+
+        let outcome = request_config(&self.config, &task.options).and_then(|config| {
+            CompressionPipeline::new(config)
+                .measure_quality(self.measure_quality)
+                .compress_file_to(&task.input, &task.output)
+        });
+        #[cfg(feature = "prometheus")]
+        if let Some(ref metrics) = self.metrics {
+            metrics.record(&outcome);
+        }
+
+        match outcome {
+            Ok(result) => {
+                log::info!("Task {} completed ({:.2}:1)", task.id, result.compression_ratio);
+                self.publish(&TaskEvent {
+                    id: Some(&task.id),
+                    status: TaskStatus::Completed,
+                    input: Some(&task.input),
+                    output: Some(&task.output),
+                    result: Some(&result),
+                    error: None,
+                })?;
+                Ok((TaskStatus::Completed, result.original_size, result.compressed_size))
+            }
+            Err(e) => {
+                log::warn!("Task {} failed: {}", task.id, e);
+                self.publish(&TaskEvent {
+                    id: Some(&task.id),
+                    status: TaskStatus::Failed,
+                    input: Some(&task.input),
+                    output: None,
+                    result: None,
+                    error: Some(ErrorRecord::new(&e, Some(&task.input))),
+                })?;
+                Ok((TaskStatus::Failed, 0, 0))
+            }
+        }
+    }
+
+    /// Serialize and publish an event.
+    fn publish(&mut self, event: &TaskEvent<'_>) -> Result<()> {
+        let payload = serde_json::to_vec(event)
+            .map_err(|e| MedImgError::Internal(format!("Failed to serialize event: {}", e)))?;
+        self.broker.publish(&payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CompressionCodec;
+    use crate::dicom::{testing, DicomFile};
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    /// In-memory broker; cancels the worker once the tasks run out.
+    #[derive(Default)]
+    struct MemoryBroker {
+        tasks: VecDeque<Vec<u8>>,
+        events: Arc<Mutex<Vec<serde_json::Value>>>,
+        acked: Arc<Mutex<Vec<u64>>>,
+        stop: CancellationToken,
+        next_tag: u64,
+    }
+
+    impl Broker for MemoryBroker {
+        fn receive(&mut self, _timeout: Duration) -> Result<Option<Message>> {
+            let Some(payload) = self.tasks.pop_front() else {
+                self.stop.cancel();
+                return Ok(None);
+            };
+            self.next_tag += 1;
+            Ok(Some(Message {
+                payload,
+                tag: self.next_tag,
+            }))
+        }
+
+        fn ack(&mut self, message: &Message) -> Result<()> {
+            self.acked.lock().unwrap().push(message.tag);
+            Ok(())
+        }
+
+        fn reject(&mut self, message: &Message) -> Result<()> {
+            self.tasks.push_front(message.payload.clone());
+            Ok(())
+        }
+
+        fn publish(&mut self, payload: &[u8]) -> Result<()> {
+            self.events.lock().unwrap().push(serde_json::from_slice(payload).unwrap());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_worker_processes_tasks() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("ct.dcm");
+        let pixels = testing::gradient(16, 16);
+        testing::write_grayscale(&input, 16, 16, "CT", &pixels);
+        let output = dir.path().join("out.dcm");
+
+        let task = |id: &str, options: serde_json::Value| {
+            serde_json::to_vec(&serde_json::json!({
+                "id": id, "input": input, "output": output, "options": options,
+            }))
+            .unwrap()
+        };
+        let broker = MemoryBroker {
+            tasks: [
+                task("ok", serde_json::json!({ "codec": "jpeg-ls" })),
+                task("bad-codec", serde_json::json!({ "codec": "gif" })),
+                b"not json".to_vec(),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let (events, acked, stop) = (broker.events.clone(), broker.acked.clone(), broker.stop.clone());
+
+        let stats = QueueWorker::new(CompressionConfig::lossless(CompressionCodec::Jpeg2000), Box::new(broker))
+            .with_cancellation(stop)
+            .run()
+            .unwrap();
+        assert_eq!((stats.total_files, stats.successful, stats.failed), (3, 1, 2));
+        assert_eq!(*acked.lock().unwrap(), vec![1, 2, 3]);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events[0]["id"], "ok");
+        assert_eq!(events[0]["status"], "completed");
+        assert_eq!(events[0]["result"]["codec_name"], "JPEG-LS");
+        assert!(events[0]["result"]["quality"].is_object());
+        assert_eq!(events[1]["status"], "failed");
+        assert_eq!(events[1]["error"]["code"], "MI-CFG-001");
+        assert_eq!(events[2]["id"], serde_json::Value::Null);
+
+        let compressed = DicomFile::open(&output).unwrap();
+        assert_eq!(compressed.decode_image_data().unwrap().pixel_data, pixels);
+    }
+
+    #[test]
+    fn test_connect_rejects_unknown_scheme() {
+        let err = connect(&BrokerConfig::new("mqtt://localhost")).err().unwrap();
+        assert!(matches!(err, MedImgError::Config(_)));
+    }
+}
@@ -0,0 +1,84 @@
+//! NATS broker (`nats` feature).
+
+use std::time::Duration;
+
+use async_nats::{Client, Subscriber};
+use futures::StreamExt;
+use tokio::runtime::Runtime;
+
+use super::{Broker, BrokerConfig, Message};
+use crate::error::{MedImgError, Result};
+
+/// Broker error.
+fn broker_error(action: &str, e: impl std::fmt::Display) -> MedImgError {
+    MedImgError::Storage(format!("NATS {} failed: {}", action, e))
+}
+
+/// NATS broker: workers subscribe to the task subject in one queue group,
+/// so each task goes to a single worker.
+///
+/// Core NATS does not redeliver, so acknowledging and rejecting are
+/// no-ops; a task whose worker dies is lost and must be resubmitted.
+pub struct NatsBroker {
+    runtime: Runtime,
+    client: Client,
+    subscriber: Subscriber,
+    events: String,
+}
+
+impl NatsBroker {
+    /// Connect and join the queue group on the task subject.
+    pub fn connect(config: &BrokerConfig) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+        let (client, subscriber) = runtime.block_on(async {
+            let client = async_nats::connect(&config.url)
+                .await
+                .map_err(|e| broker_error("connect", e))?;
+            let subscriber = client
+                .queue_subscribe(config.tasks.clone(), config.group.clone())
+                .await
+                .map_err(|e| broker_error("subscribe", e))?;
+            Ok::<_, MedImgError>((client, subscriber))
+        })?;
+        Ok(Self {
+            runtime,
+            client,
+            subscriber,
+            events: config.events.clone(),
+        })
+    }
+}
+
+impl Broker for NatsBroker {
+    fn receive(&mut self, timeout: Duration) -> Result<Option<Message>> {
+        let next = self
+            .runtime
+            .block_on(async { tokio::time::timeout(timeout, self.subscriber.next()).await });
+        match next {
+            Ok(Some(message)) => Ok(Some(Message {
+                payload: message.payload.to_vec(),
+                tag: 0,
+            })),
+            Ok(None) => Err(MedImgError::Storage("NATS subscription closed".into())),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn ack(&mut self, _message: &Message) -> Result<()> {
+        Ok(())
+    }
+
+    fn reject(&mut self, _message: &Message) -> Result<()> {
+        Ok(())
+    }
+
+    fn publish(&mut self, payload: &[u8]) -> Result<()> {
+        self.runtime.block_on(async {
+            self.client
+                .publish(self.events.clone(), payload.to_vec().into())
+                .await
+                .map_err(|e| broker_error("publish", e))?;
+            self.client.flush().await.map_err(|e| broker_error("flush", e))
+        })
+    }
+}
@@ -0,0 +1,790 @@
+//! REST job API (`rest` feature).
+//!
+//! A long-running compression service: jobs are queued, processed by a
+//! pool of worker threads, and recorded in a state directory so they
+//! survive restarts.
+//!
+//! - `POST /jobs`: submit a job for a file on the server, with a JSON body
+//!   `{"path": "..."}` relative to the configured input root
+//! - `POST /jobs/upload`: submit a job for the DICOM file in the request
+//!   body
+//! - `GET /jobs`: list jobs, oldest first
+//! - `GET /jobs/{id}`: the job, with its current phase and progress
+//! - `GET /jobs/{id}/result`: the compressed file (`application/dicom`)
+//! - `GET /jobs/{id}/report`: the compression result as JSON
+//! - `DELETE /jobs/{id}`: cancel a job and delete its files
+//! - `GET /health`: liveness check
+//! - `GET /metrics`: operational metrics, including the queue depth, when
+//!   enabled with [`JobServer::metrics`] (`prometheus` feature)
+//!
+//! Both submit endpoints answer `202 Accepted` with the job and take the
+//! same `codec`, `mode`, `ratio`, `bpp`, and `near` query parameters as
+//! the one-shot service; modality safety checks always apply.
+//!
+//! The state directory holds `{id}.json` (the job record), `{id}.in.dcm`
+//! (uploads), and `{id}.dcm` (the result). Jobs that were queued or
+//! running when the server stopped are queued again on start.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, Path as UrlPath, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+
+use super::{request_config, status_for_error, DEFAULT_MAX_BODY_BYTES};
+use crate::config::CompressionConfig;
+use crate::error::{ErrorRecord, MedImgError, Result};
+#[cfg(feature = "prometheus")]
+use crate::monitoring::{self, OperationalMetrics};
+use crate::pipeline::{BatchStats, CompressionPipeline};
+use crate::progress::{CancellationToken, ProgressEvent, ProgressHandler, ProgressPhase};
+
+/// Interval at which idle workers and the server check for shutdown.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Status and message of a rejected request.
+type Rejection = (StatusCode, &'static str);
+
+/// State of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// Waiting for a worker.
+    Queued,
+    /// Being compressed.
+    Running,
+    /// Compressed; the result is available.
+    Completed,
+    /// Compression failed; see the job's error.
+    Failed,
+}
+
+/// A compression job, as stored in `{id}.json` and returned by the API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    /// Job identifier.
+    pub id: String,
+    /// Current state.
+    pub status: JobStatus,
+    /// Input file (the upload, for uploaded jobs).
+    pub input: PathBuf,
+    /// Whether the input was uploaded, and is deleted with the job.
+    pub uploaded: bool,
+    /// Compression settings, with the request's overrides applied.
+    pub config: CompressionConfig,
+    /// Submission time (seconds since the Unix epoch).
+    pub submitted_at: u64,
+    /// Completion time (seconds since the Unix epoch).
+    #[serde(default)]
+    pub finished_at: Option<u64>,
+    /// Current pipeline phase while running.
+    #[serde(default)]
+    pub phase: Option<ProgressPhase>,
+    /// Progress within the file (0.0 to 1.0).
+    #[serde(default)]
+    pub progress: f64,
+    /// Compression result, once completed.
+    #[serde(default)]
+    pub result: Option<serde_json::Value>,
+    /// Error, if the job failed.
+    #[serde(default)]
+    pub error: Option<ErrorRecord>,
+}
+
+/// A job and its cancellation token.
+struct Entry {
+    job: Job,
+    cancelled: CancellationToken,
+}
+
+/// Body of `POST /jobs`.
+#[derive(Deserialize)]
+struct SubmitRequest {
+    /// Input file, relative to the input root.
+    path: PathBuf,
+}
+
+/// State shared by the HTTP handlers and the workers.
+struct Shared {
+    defaults: CompressionConfig,
+    state_dir: PathBuf,
+    input_root: Option<PathBuf>,
+    jobs: Mutex<HashMap<String, Entry>>,
+    queue: Mutex<VecDeque<String>>,
+    available: Condvar,
+    #[cfg(feature = "prometheus")]
+    metrics: Option<OperationalMetrics>,
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// New unique job identifier.
+fn new_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let seed = format!("{}-{}-{}", std::process::id(), nanos, COUNTER.fetch_add(1, Ordering::Relaxed));
+    format!("{:016x}", xxhash_rust::xxh3::xxh3_64(seed.as_bytes()))
+}
+
+impl Shared {
+    /// Load the jobs recorded in the state directory, queueing unfinished
+    /// ones again.
+    fn load(defaults: CompressionConfig, state_dir: PathBuf, input_root: Option<PathBuf>) -> Result<Self> {
+        std::fs::create_dir_all(&state_dir).map_err(|e| MedImgError::from(e).with_file(&state_dir))?;
+
+        let mut jobs = HashMap::new();
+        let mut pending = Vec::new();
+        for entry in std::fs::read_dir(&state_dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let record = std::fs::read(&path)
+                .map_err(MedImgError::from)
+                .and_then(|data| {
+                    serde_json::from_slice::<Job>(&data)
+                        .map_err(|e| MedImgError::InvalidFormat(format!("Invalid job record: {}", e)))
+                });
+            let mut job = match record {
+                Ok(job) => job,
+                Err(e) => {
+                    log::warn!("Skipping {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if matches!(job.status, JobStatus::Queued | JobStatus::Running) {
+                job.status = JobStatus::Queued;
+                job.phase = None;
+                job.progress = 0.0;
+                pending.push((job.submitted_at, job.id.clone()));
+            }
+            let cancelled = CancellationToken::new();
+            jobs.insert(job.id.clone(), Entry { job, cancelled });
+        }
+        pending.sort();
+        if !pending.is_empty() {
+            log::info!("Resuming {} unfinished job(s)", pending.len());
+        }
+
+        Ok(Self {
+            defaults,
+            state_dir,
+            input_root,
+            jobs: Mutex::new(jobs),
+            queue: Mutex::new(pending.into_iter().map(|(_, id)| id).collect()),
+            available: Condvar::new(),
+            #[cfg(feature = "prometheus")]
+            metrics: None,
+        })
+    }
+
+    /// Path of a job file with the given suffix.
+    fn file(&self, id: &str, suffix: &str) -> PathBuf {
+        self.state_dir.join(format!("{}.{}", id, suffix))
+    }
+
+    /// Write a job record, replacing the previous one atomically.
+    fn persist(&self, job: &Job) {
+        let path = self.file(&job.id, "json");
+        let temp = self.file(&job.id, "json.tmp");
+        let written = serde_json::to_vec_pretty(job)
+            .map_err(std::io::Error::other)
+            .and_then(|data| std::fs::write(&temp, data))
+            .and_then(|()| std::fs::rename(&temp, &path));
+        if let Err(e) = written {
+            log::warn!("Failed to record job {}: {}", job.id, e);
+        }
+    }
+
+    /// Record and queue a new job.
+    fn submit(&self, id: String, input: PathBuf, uploaded: bool, config: CompressionConfig) -> Job {
+        let job = Job {
+            id: id.clone(),
+            status: JobStatus::Queued,
+            input,
+            uploaded,
+            config,
+            submitted_at: now(),
+            finished_at: None,
+            phase: None,
+            progress: 0.0,
+            result: None,
+            error: None,
+        };
+        self.persist(&job);
+        self.jobs.lock().unwrap().insert(
+            id.clone(),
+            Entry {
+                job: job.clone(),
+                cancelled: CancellationToken::new(),
+            },
+        );
+        let mut queue = self.queue.lock().unwrap();
+        queue.push_back(id);
+        self.set_queue_depth(queue.len());
+        drop(queue);
+        self.available.notify_one();
+        log::info!("Queued job {} for {}", job.id, job.input.display());
+        job
+    }
+
+    /// Process queued jobs until `stop` is cancelled.
+    fn work(&self, stop: &CancellationToken) {
+        while let Some(id) = self.next(stop) {
+            self.run(&id);
+        }
+    }
+
+    /// Wait for the next queued job.
+    fn next(&self, stop: &CancellationToken) -> Option<String> {
+        let mut queue = self.queue.lock().unwrap();
+        loop {
+            if stop.is_cancelled() {
+                return None;
+            }
+            if let Some(id) = queue.pop_front() {
+                self.set_queue_depth(queue.len());
+                return Some(id);
+            }
+            queue = self.available.wait_timeout(queue, POLL_INTERVAL).unwrap().0;
+        }
+    }
+
+    /// Publish the number of queued jobs.
+    #[cfg_attr(not(feature = "prometheus"), allow(unused_variables))]
+    fn set_queue_depth(&self, depth: usize) {
+        #[cfg(feature = "prometheus")]
+        if let Some(ref metrics) = self.metrics {
+            metrics.set_queue_depth(depth);
+        }
+    }
+
+    /// Compress a job's input and record the outcome.
+    fn run(&self, id: &str) {
+        let (input, config, cancelled) = {
+            let mut jobs = self.jobs.lock().unwrap();
+            // Cancelled or deleted while queued
+            let Some(entry) = jobs.get_mut(id).filter(|e| e.job.status == JobStatus::Queued) else {
+                return;
+            };
+            entry.job.status = JobStatus::Running;
+            self.persist(&entry.job);
+            (entry.job.input.clone(), entry.job.config.clone(), entry.cancelled.clone())
+        };
+
+        let output = self.file(id, "dcm");
+        let progress = JobProgress {
+            shared: self,
+            id,
+            cancelled: cancelled.clone(),
+        };
+        let outcome = CompressionPipeline::new(config).compress_file_to_with_progress(&input, &output, &progress);
+
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(entry) = jobs.get_mut(id).filter(|_| !cancelled.is_cancelled()) else {
+            let _ = std::fs::remove_file(&output);
+            return;
+        };
+        #[cfg(feature = "prometheus")]
+        if let Some(ref metrics) = self.metrics {
+            metrics.record(&outcome);
+        }
+        let job = &mut entry.job;
+        match outcome {
+            Ok(result) => {
+                log::info!("Job {} completed ({:.2}:1)", id, result.compression_ratio);
+                job.status = JobStatus::Completed;
+                job.progress = 1.0;
+                job.result = serde_json::to_value(&result).ok();
+            }
+            Err(e) => {
+                log::warn!("Job {} failed: {}", id, e);
+                job.status = JobStatus::Failed;
+                job.error = Some(ErrorRecord::new(&e, Some(&input)));
+            }
+        }
+        job.phase = None;
+        job.finished_at = Some(now());
+        self.persist(job);
+    }
+
+    /// Cancel a job and delete its files.
+    fn delete(&self, id: &str) -> bool {
+        let Some(entry) = self.jobs.lock().unwrap().remove(id) else {
+            return false;
+        };
+        entry.cancelled.cancel();
+        for suffix in ["json", "dcm"] {
+            let _ = std::fs::remove_file(self.file(id, suffix));
+        }
+        if entry.job.uploaded {
+            let _ = std::fs::remove_file(&entry.job.input);
+        }
+        log::info!("Deleted job {}", id);
+        true
+    }
+
+    /// Resolve a submitted path within the input root.
+    fn resolve(&self, path: &Path) -> std::result::Result<PathBuf, Rejection> {
+        let Some(root) = &self.input_root else {
+            return Err((
+                StatusCode::FORBIDDEN,
+                "File references are disabled; upload the file to /jobs/upload",
+            ));
+        };
+        let not_found = (StatusCode::NOT_FOUND, "Input file not found");
+        let root = root.canonicalize().map_err(|_| not_found)?;
+        let resolved = root.join(path).canonicalize().map_err(|_| not_found)?;
+        if !resolved.starts_with(&root) {
+            return Err((StatusCode::FORBIDDEN, "Path is outside the input root"));
+        }
+        if !resolved.is_file() {
+            return Err(not_found);
+        }
+        Ok(resolved)
+    }
+}
+
+/// Records a running job's phase and progress.
+struct JobProgress<'a> {
+    shared: &'a Shared,
+    id: &'a str,
+    cancelled: CancellationToken,
+}
+
+impl ProgressHandler for JobProgress<'_> {
+    fn on_progress(&self, event: &ProgressEvent) {
+        if let Some(entry) = self.shared.jobs.lock().unwrap().get_mut(self.id) {
+            entry.job.phase = Some(event.phase);
+            entry.job.progress = event.file_progress;
+        }
+    }
+
+    fn on_error(&self, _error: &MedImgError, _file: Option<&Path>) {}
+
+    fn on_complete(&self, _stats: &BatchStats) {}
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.is_cancelled()
+    }
+}
+
+/// JSON error response (`{"error": "..."}`).
+fn error((status, message): Rejection) -> Response {
+    (status, Json(serde_json::json!({ "error": message }))).into_response()
+}
+
+/// Error response for a library error, with its code and category.
+fn from_error(e: &MedImgError) -> Response {
+    let status = StatusCode::from_u16(status_for_error(e)).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let body = serde_json::json!({
+        "error": e.to_string(),
+        "code": e.code(),
+        "category": e.category(),
+    });
+    (status, Json(body)).into_response()
+}
+
+/// Look up a job, or answer 404.
+fn find(shared: &Shared, id: &str) -> std::result::Result<Job, Rejection> {
+    shared
+        .jobs
+        .lock()
+        .unwrap()
+        .get(id)
+        .map(|entry| entry.job.clone())
+        .ok_or((StatusCode::NOT_FOUND, "Unknown job"))
+}
+
+/// Look up a completed job, or answer 404/409.
+fn completed(shared: &Shared, id: &str) -> std::result::Result<Job, Rejection> {
+    let job = find(shared, id)?;
+    if job.status != JobStatus::Completed {
+        return Err((StatusCode::CONFLICT, "Job has not completed"));
+    }
+    Ok(job)
+}
+
+async fn submit_path(
+    State(shared): State<Arc<Shared>>,
+    Query(query): Query<HashMap<String, String>>,
+    Json(request): Json<SubmitRequest>,
+) -> Response {
+    let config = match request_config(&shared.defaults, &query) {
+        Ok(config) => config,
+        Err(e) => return from_error(&e),
+    };
+    let input = match shared.resolve(&request.path) {
+        Ok(input) => input,
+        Err(rejection) => return error(rejection),
+    };
+    let job = shared.submit(new_id(), input, false, config);
+    (StatusCode::ACCEPTED, Json(job)).into_response()
+}
+
+async fn submit_upload(
+    State(shared): State<Arc<Shared>>,
+    Query(query): Query<HashMap<String, String>>,
+    body: Bytes,
+) -> Response {
+    if body.is_empty() {
+        return error((StatusCode::BAD_REQUEST, "Request body must be a DICOM file"));
+    }
+    let config = match request_config(&shared.defaults, &query) {
+        Ok(config) => config,
+        Err(e) => return from_error(&e),
+    };
+    let id = new_id();
+    let input = shared.file(&id, "in.dcm");
+    if let Err(e) = std::fs::write(&input, &body) {
+        return from_error(&MedImgError::from(e).with_file(&input));
+    }
+    let job = shared.submit(id, input, true, config);
+    (StatusCode::ACCEPTED, Json(job)).into_response()
+}
+
+async fn list(State(shared): State<Arc<Shared>>) -> Response {
+    let mut jobs: Vec<Job> = shared.jobs.lock().unwrap().values().map(|e| e.job.clone()).collect();
+    jobs.sort_by(|a, b| (a.submitted_at, &a.id).cmp(&(b.submitted_at, &b.id)));
+    Json(jobs).into_response()
+}
+
+async fn status(State(shared): State<Arc<Shared>>, UrlPath(id): UrlPath<String>) -> Response {
+    match find(&shared, &id) {
+        Ok(job) => Json(job).into_response(),
+        Err(rejection) => error(rejection),
+    }
+}
+
+async fn result(State(shared): State<Arc<Shared>>, UrlPath(id): UrlPath<String>) -> Response {
+    if let Err(rejection) = completed(&shared, &id) {
+        return error(rejection);
+    }
+    let path = shared.file(&id, "dcm");
+    match std::fs::read(&path) {
+        Ok(data) => ([(header::CONTENT_TYPE, "application/dicom")], data).into_response(),
+        Err(e) => from_error(&MedImgError::from(e).with_file(&path)),
+    }
+}
+
+async fn report(State(shared): State<Arc<Shared>>, UrlPath(id): UrlPath<String>) -> Response {
+    match completed(&shared, &id) {
+        Ok(job) => Json(job.result).into_response(),
+        Err(rejection) => error(rejection),
+    }
+}
+
+#[cfg(feature = "prometheus")]
+async fn metrics(State(shared): State<Arc<Shared>>) -> Response {
+    match &shared.metrics {
+        Some(metrics) => ([(header::CONTENT_TYPE, monitoring::CONTENT_TYPE)], metrics.render()).into_response(),
+        None => error((StatusCode::NOT_FOUND, "Metrics are disabled")),
+    }
+}
+
+async fn delete(State(shared): State<Arc<Shared>>, UrlPath(id): UrlPath<String>) -> Response {
+    if shared.delete(&id) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        error((StatusCode::NOT_FOUND, "Unknown job"))
+    }
+}
+
+/// Routes of the job API.
+fn router(shared: Arc<Shared>, max_body_bytes: usize) -> Router {
+    let router = Router::new()
+        .route("/jobs", post(submit_path).get(list))
+        .route("/jobs/upload", post(submit_upload))
+        .route("/jobs/:id", get(status).delete(delete))
+        .route("/jobs/:id/result", get(result))
+        .route("/jobs/:id/report", get(report))
+        .route("/health", get(|| async { Json(serde_json::json!({ "status": "ok" })) }));
+    #[cfg(feature = "prometheus")]
+    let router = router.route("/metrics", get(metrics));
+    router
+        .layer(DefaultBodyLimit::max(max_body_bytes))
+        .with_state(shared)
+}
+
+/// REST compression service with a persistent job queue.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::server::JobServer;
+///
+/// let listener = std::net::TcpListener::bind("127.0.0.1:8080")?;
+/// JobServer::new(CompressionConfig::default(), "/var/lib/medimg/jobs".into())
+///     .input_root("/data/incoming".into())
+///     .serve(listener)?;
+/// ```
+pub struct JobServer {
+    /// Default compression settings.
+    config: CompressionConfig,
+
+    /// Directory job records and files are kept in.
+    state_dir: PathBuf,
+
+    /// Directory file references are resolved in; `None` accepts uploads
+    /// only.
+    input_root: Option<PathBuf>,
+
+    /// Number of jobs processed at once.
+    max_parallel: usize,
+
+    /// Largest accepted upload.
+    max_body_bytes: usize,
+
+    /// Operational metrics served at `/metrics`.
+    #[cfg(feature = "prometheus")]
+    metrics: Option<OperationalMetrics>,
+
+    /// Cancellation token; stops the server.
+    cancelled: CancellationToken,
+}
+
+impl JobServer {
+    /// Create a server keeping its jobs in `state_dir`.
+    pub fn new(config: CompressionConfig, state_dir: PathBuf) -> Self {
+        Self {
+            config,
+            state_dir,
+            input_root: None,
+            max_parallel: num_cpus::get(),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            #[cfg(feature = "prometheus")]
+            metrics: None,
+            cancelled: CancellationToken::new(),
+        }
+    }
+
+    /// Accept jobs for files under `dir` (`POST /jobs`). Without an input
+    /// root, only uploads are accepted.
+    pub fn input_root(mut self, dir: PathBuf) -> Self {
+        self.input_root = Some(dir);
+        self
+    }
+
+    /// Set the number of jobs processed at once.
+    pub fn max_parallel(mut self, jobs: usize) -> Self {
+        self.max_parallel = jobs.max(1);
+        self
+    }
+
+    /// Set the largest accepted upload.
+    pub fn max_body_bytes(mut self, bytes: usize) -> Self {
+        self.max_body_bytes = bytes;
+        self
+    }
+
+    /// Record finished jobs and the queue depth in `metrics` and serve
+    /// them at `GET /metrics`.
+    #[cfg(feature = "prometheus")]
+    pub fn metrics(mut self, metrics: OperationalMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Use a shared cancellation token.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancelled = token;
+        self
+    }
+
+    /// Get a handle to this server's cancellation token.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+
+    /// Serve requests and process jobs until cancelled.
+    ///
+    /// On cancellation, no new requests are accepted, and running jobs are
+    /// finished before returning; queued jobs stay queued for the next
+    /// start.
+    pub fn serve(&self, listener: TcpListener) -> Result<()> {
+        #[cfg_attr(not(feature = "prometheus"), allow(unused_mut))]
+        let mut shared = Shared::load(self.config.clone(), self.state_dir.clone(), self.input_root.clone())?;
+        #[cfg(feature = "prometheus")]
+        {
+            shared.metrics = self.metrics.clone();
+            shared.set_queue_depth(shared.queue.lock().unwrap().len());
+        }
+        let shared = Arc::new(shared);
+        listener.set_nonblocking(true)?;
+        let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+        let stop = CancellationToken::new();
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.max_parallel {
+                let (shared, stop) = (&shared, &stop);
+                scope.spawn(move || shared.work(stop));
+            }
+
+            let served = runtime.block_on(async {
+                let listener = tokio::net::TcpListener::from_std(listener)?;
+                let cancelled = self.cancelled.clone();
+                let shutdown = async move {
+                    while !cancelled.is_cancelled() {
+                        tokio::time::sleep(POLL_INTERVAL).await;
+                    }
+                };
+                axum::serve(listener, router(shared.clone(), self.max_body_bytes))
+                    .with_graceful_shutdown(shutdown)
+                    .await
+            });
+            stop.cancel();
+            served.map_err(MedImgError::from)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CompressionCodec;
+    use crate::dicom::{testing, DicomFile};
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use tempfile::TempDir;
+
+    /// Send a request and return (status, body).
+    fn send(addr: SocketAddr, method: &str, target: &str, body: &[u8]) -> (u16, Vec<u8>) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: test\r\nConnection: close\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\n\r\n",
+            method,
+            target,
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(body).unwrap();
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let split = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let status = String::from_utf8_lossy(&response[9..12]).parse().unwrap();
+        (status, response[split + 4..].to_vec())
+    }
+
+    fn json(body: &[u8]) -> serde_json::Value {
+        serde_json::from_slice(body).unwrap()
+    }
+
+    /// Poll a job until it finishes.
+    fn wait(addr: SocketAddr, id: &str) -> serde_json::Value {
+        for _ in 0..200 {
+            let job = json(&send(addr, "GET", &format!("/jobs/{}", id), b"").1);
+            if !matches!(job["status"].as_str(), Some("queued" | "running")) {
+                return job;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        panic!("job {} did not finish", id);
+    }
+
+    #[test]
+    fn test_job_lifecycle() {
+        let dir = TempDir::new().unwrap();
+        let inputs = dir.path().join("incoming");
+        std::fs::create_dir(&inputs).unwrap();
+        let pixels = testing::gradient(16, 16);
+        testing::write_grayscale(&inputs.join("ct.dcm"), 16, 16, "CT", &pixels);
+        let state_dir = dir.path().join("state");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = JobServer::new(CompressionConfig::lossless(CompressionCodec::Jpeg2000), state_dir.clone())
+            .input_root(inputs.clone())
+            .max_parallel(2);
+        let token = server.cancellation_token();
+
+        std::thread::scope(|scope| {
+            let handle = scope.spawn(|| server.serve(listener));
+            let ready = (0..100).any(|_| {
+                std::thread::sleep(Duration::from_millis(20));
+                TcpStream::connect(addr).is_ok()
+            });
+            assert!(ready);
+
+            // File reference
+            let (status, body) = send(addr, "POST", "/jobs?codec=jpeg-ls", br#"{"path": "ct.dcm"}"#);
+            assert_eq!(status, 202);
+            let id = json(&body)["id"].as_str().unwrap().to_string();
+            let job = wait(addr, &id);
+            assert_eq!(job["status"], "completed");
+            assert_eq!(job["progress"], 1.0);
+
+            let (status, body) = send(addr, "GET", &format!("/jobs/{}/result", id), b"");
+            assert_eq!(status, 200);
+            let compressed = DicomFile::from_reader(body.as_slice()).unwrap();
+            assert_eq!(compressed.decode_image_data().unwrap().pixel_data, pixels);
+            let report = json(&send(addr, "GET", &format!("/jobs/{}/report", id), b"").1);
+            assert_eq!(report["is_lossless"], true);
+
+            // Upload of something that is not DICOM
+            let (status, body) = send(addr, "POST", "/jobs/upload", b"not a dicom file");
+            assert_eq!(status, 202);
+            let failed = json(&body)["id"].as_str().unwrap().to_string();
+            assert_eq!(wait(addr, &failed)["status"], "failed");
+            assert_eq!(send(addr, "GET", &format!("/jobs/{}/result", failed), b"").0, 409);
+
+            // Paths stay within the input root
+            assert_eq!(send(addr, "POST", "/jobs", br#"{"path": "../state"}"#).0, 403);
+            assert_eq!(send(addr, "POST", "/jobs", br#"{"path": "missing.dcm"}"#).0, 404);
+            assert_eq!(send(addr, "POST", "/jobs?codec=gif", br#"{"path": "ct.dcm"}"#).0, 400);
+
+            let listed = json(&send(addr, "GET", "/jobs", b"").1);
+            assert_eq!(listed.as_array().unwrap().len(), 2);
+
+            assert_eq!(send(addr, "DELETE", &format!("/jobs/{}", failed), b"").0, 204);
+            assert_eq!(send(addr, "GET", &format!("/jobs/{}", failed), b"").0, 404);
+
+            token.cancel();
+            handle.join().unwrap().unwrap();
+        });
+
+        // Records survive a restart
+        let shared = Shared::load(CompressionConfig::default(), state_dir, None).unwrap();
+        let jobs = shared.jobs.lock().unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert!(jobs.values().all(|e| e.job.status == JobStatus::Completed));
+    }
+
+    #[test]
+    fn test_unfinished_jobs_are_requeued() {
+        let dir = TempDir::new().unwrap();
+        let shared = Shared::load(CompressionConfig::default(), dir.path().to_path_buf(), None).unwrap();
+        let job = shared.submit("a".into(), dir.path().join("in.dcm"), true, CompressionConfig::default());
+        shared.persist(&Job {
+            status: JobStatus::Running,
+            progress: 0.5,
+            ..job
+        });
+
+        let reloaded = Shared::load(CompressionConfig::default(), dir.path().to_path_buf(), None).unwrap();
+        assert_eq!(reloaded.queue.lock().unwrap().front().map(String::as_str), Some("a"));
+        let jobs = reloaded.jobs.lock().unwrap();
+        assert_eq!(jobs["a"].job.status, JobStatus::Queued);
+        assert_eq!(jobs["a"].job.progress, 0.0);
+    }
+}
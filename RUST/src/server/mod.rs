@@ -0,0 +1,756 @@
+//! Local HTTP compression service.
+//!
+//! A minimal HTTP/1.1 server so other services can compress DICOM files
+//! without linking Rust:
+//!
+//! - `POST /compress`: request body is a DICOM file; the response body is
+//!   the compressed DICOM file (`application/dicom`), with the result in
+//!   `X-Original-Size`, `X-Compressed-Size`, and `X-Compression-Ratio`
+//! - `POST /analyze`: same input; responds with the compression result as
+//!   JSON without returning the compressed file
+//! - `GET /health`: liveness check
+//! - `GET /metrics`: operational metrics in the Prometheus text format,
+//!   when enabled with [`CompressionServer::metrics`] (`prometheus` feature)
+//!
+//! The compression settings are the server defaults, optionally overridden
+//! per request with the `codec`, `mode`, `ratio`, `bpp`, and `near` query
+//! parameters (e.g. `/compress?codec=jpeg-ls`). Modality safety checks
+//! always apply and cannot be overridden over HTTP.
+//!
+//! Each connection handles a single request and is then closed. Requests
+//! must carry a `Content-Length`; chunked uploads are rejected. Bodies are
+//! read as they arrive (never preallocated from the declared length), at
+//! most [`DEFAULT_MAX_CONNECTIONS`] connections are served at once, and a
+//! request must arrive completely within [`REQUEST_DEADLINE`], so idle or
+//! slow clients cannot hold memory or threads indefinitely.
+//!
+//! For long-running deployments, the `rest` feature adds [`JobServer`], a
+//! job API with a persistent queue (see the [`jobs`] module).
+
+#[cfg(feature = "rest")]
+pub mod jobs;
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Cursor, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::config::{CompressionCodec, CompressionConfig, CompressionMode, CompressionOverrides};
+use crate::error::{MedImgError, Result};
+use crate::memory::MemoryAccountant;
+#[cfg(feature = "prometheus")]
+use crate::monitoring::{self, OperationalMetrics};
+use crate::pipeline::{CompressionPipeline, PipelineBuilder};
+use crate::progress::CancellationToken;
+
+#[cfg(feature = "rest")]
+pub use jobs::{Job, JobServer, JobStatus};
+
+/// Default maximum request body size (256 MiB).
+pub const DEFAULT_MAX_BODY_BYTES: usize = 256 << 20;
+
+/// Default number of connections served at once; further connections
+/// are answered with 503.
+pub const DEFAULT_MAX_CONNECTIONS: usize = 32;
+
+/// Time to wait for a slow client before dropping the connection.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Time allowed to receive a whole request, headers and body.
+pub const REQUEST_DEADLINE: Duration = Duration::from_secs(300);
+
+/// Time spent answering a connection over the limit, or waiting for the
+/// rest of a refused request.
+const REJECT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Most header lines accepted in a request.
+const MAX_HEADERS: usize = 100;
+
+/// Interval at which the accept loop checks for cancellation.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Longest accepted request line or header line.
+const MAX_HEADER_LINE: usize = 8 * 1024;
+
+/// Most unread request bytes discarded before closing a refused
+/// connection.
+const MAX_DRAIN_BYTES: u64 = 64 * 1024;
+
+/// A parsed HTTP request.
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// An HTTP response.
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    /// JSON response with the given status.
+    fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => Self {
+                status,
+                content_type: "application/json",
+                headers: Vec::new(),
+                body,
+            },
+            Err(e) => Self::error(500, &format!("Failed to serialize response: {}", e)),
+        }
+    }
+
+    /// JSON error response (`{"error": "..."}`).
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message }))
+    }
+
+    /// Error response for a library error, with its code and category
+    /// (`{"error": "...", "code": "MI-...", "category": "..."}`).
+    fn from_error(error: &MedImgError) -> Self {
+        Self::json(
+            status_for_error(error),
+            &serde_json::json!({
+                "error": error.to_string(),
+                "code": error.code(),
+                "category": error.category(),
+            }),
+        )
+    }
+}
+
+/// HTTP status code for a library error.
+fn status_for_error(error: &MedImgError) -> u16 {
+    match error {
+        MedImgError::Dicom(_)
+        | MedImgError::DicomSource { .. }
+        | MedImgError::CodecParse(_)
+        | MedImgError::InvalidFormat(_)
+        | MedImgError::ImageData(_)
+        | MedImgError::Config(_)
+        | MedImgError::Validation(_) => 400,
+        MedImgError::LimitExceeded(_) => 413,
+        MedImgError::UnsupportedTransferSyntax(_) => 415,
+        MedImgError::CompressionConstraint(_) | MedImgError::PolicyViolation(_) => 422,
+        MedImgError::Timeout(_) | MedImgError::Cancelled(_) => 503,
+        MedImgError::Context { source, .. } => status_for_error(source),
+        _ => 500,
+    }
+}
+
+/// Reason phrase for the status codes this server sends.
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Entity",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+/// HTTP compression service.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use medimg_compress::server::CompressionServer;
+///
+/// let listener = std::net::TcpListener::bind("127.0.0.1:8080")?;
+/// CompressionServer::new(CompressionConfig::default()).serve(listener)?;
+/// ```
+pub struct CompressionServer {
+    /// Default compression settings.
+    config: CompressionConfig,
+
+    /// Largest accepted request body.
+    max_body_bytes: usize,
+
+    /// Most connections served at once.
+    max_connections: usize,
+
+    /// Operational metrics served at `/metrics`.
+    #[cfg(feature = "prometheus")]
+    metrics: Option<OperationalMetrics>,
+
+    /// Cancellation token; stops the accept loop.
+    cancelled: CancellationToken,
+}
+
+impl CompressionServer {
+    /// Create a server with default compression settings.
+    pub fn new(config: CompressionConfig) -> Self {
+        Self {
+            config,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            #[cfg(feature = "prometheus")]
+            metrics: None,
+            cancelled: CancellationToken::new(),
+        }
+    }
+
+    /// Set the largest accepted request body.
+    pub fn max_body_bytes(mut self, bytes: usize) -> Self {
+        self.max_body_bytes = bytes;
+        self
+    }
+
+    /// Set the most connections served at once (at least 1).
+    pub fn max_connections(mut self, connections: usize) -> Self {
+        self.max_connections = connections.max(1);
+        self
+    }
+
+    /// Record compressed files and failures in `metrics` and serve them at
+    /// `GET /metrics`.
+    #[cfg(feature = "prometheus")]
+    pub fn metrics(mut self, metrics: OperationalMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Use a shared cancellation token.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancelled = token;
+        self
+    }
+
+    /// Get a handle to this server's cancellation token.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancelled.clone()
+    }
+
+    /// Serve requests until cancelled.
+    ///
+    /// Each connection is handled on its own thread, up to
+    /// [`max_connections`](Self::max_connections) at once; connections
+    /// over the limit are answered with 503 and closed. On cancellation, no
+    /// new connections are accepted and in-flight requests are finished
+    /// before returning.
+    pub fn serve(&self, listener: TcpListener) -> Result<()> {
+        listener.set_nonblocking(true)?;
+        let active = AtomicUsize::new(0);
+
+        std::thread::scope(|scope| {
+            while !self.cancelled.is_cancelled() {
+                match listener.accept() {
+                    Ok((stream, peer)) => {
+                        if active.fetch_add(1, Ordering::AcqRel) >= self.max_connections {
+                            active.fetch_sub(1, Ordering::AcqRel);
+                            let limit = self.max_connections;
+                            log::warn!("Rejecting connection from {}: {} connections active", peer, limit);
+                            reject_busy(stream);
+                            continue;
+                        }
+                        log::debug!("Connection from {}", peer);
+                        let active = &active;
+                        scope.spawn(move || {
+                            if let Err(e) = self.handle(stream) {
+                                log::warn!("Connection from {} failed: {}", peer, e);
+                            }
+                            active.fetch_sub(1, Ordering::AcqRel);
+                        });
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                    }
+                    Err(e) => log::warn!("Accept failed: {}", e),
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Read one request from a connection and write the response.
+    fn handle(&self, stream: TcpStream) -> Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(Deadline {
+            stream,
+            deadline: Instant::now() + REQUEST_DEADLINE,
+        });
+
+        match self.read_request(&mut reader, &mut writer) {
+            Ok(request) => {
+                log::info!("{} {}", request.method, request.path);
+                write_response(&mut writer, &self.route(&request))
+            }
+            Err(response) => {
+                write_response(&mut writer, &response)?;
+                close_unread(&writer);
+                Ok(())
+            }
+        }
+    }
+
+    /// Parse a request, answering `Expect: 100-continue` before the body.
+    fn read_request<R: BufRead, W: Write>(
+        &self,
+        reader: &mut R,
+        writer: &mut W,
+    ) -> std::result::Result<Request, Response> {
+        let bad_request = |message: &str| Response::error(400, message);
+
+        let request_line = read_line(reader).map_err(|_| bad_request("Malformed request line"))?;
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Err(bad_request("Malformed request line"));
+        };
+
+        let mut headers = HashMap::new();
+        loop {
+            let line = read_line(reader).map_err(|_| bad_request("Malformed header"))?;
+            if line.is_empty() {
+                break;
+            }
+            if headers.len() == MAX_HEADERS {
+                return Err(Response::error(431, &format!("More than {} headers", MAX_HEADERS)));
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| bad_request("Malformed header"))?;
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+
+        if headers
+            .get("transfer-encoding")
+            .is_some_and(|v| !v.eq_ignore_ascii_case("identity"))
+        {
+            return Err(Response::error(411, "Chunked uploads are not supported; send Content-Length"));
+        }
+
+        let length = match headers.get("content-length") {
+            Some(value) => value
+                .parse::<usize>()
+                .map_err(|_| bad_request("Invalid Content-Length"))?,
+            None => 0,
+        };
+        if length > self.max_body_bytes {
+            return Err(Response::error(
+                413,
+                &format!("Request body exceeds {} bytes", self.max_body_bytes),
+            ));
+        }
+
+        if headers
+            .get("expect")
+            .is_some_and(|v| v.eq_ignore_ascii_case("100-continue"))
+        {
+            writer
+                .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+                .map_err(|_| bad_request("Connection closed"))?;
+        }
+
+        // Released once the body is read: the pipeline reserves its own
+        // working set, and holding both could wait on itself
+        let _reservation = (length > 0).then(|| MemoryAccountant::global().reserve(length as u64));
+
+        // Grows as the body arrives: a client declaring a large body and
+        // sending nothing holds no memory
+        let mut body = Vec::new();
+        let read = reader.take(length as u64).read_to_end(&mut body);
+        if read.is_err() || body.len() != length {
+            return Err(bad_request("Request body shorter than Content-Length"));
+        }
+
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        Ok(Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            query: parse_query(query),
+            body,
+        })
+    }
+
+    /// Dispatch a request to its endpoint.
+    fn route(&self, request: &Request) -> Response {
+        let endpoint = match request.path.as_str() {
+            "/compress" | "/analyze" if request.method != "POST" => {
+                return Response::error(405, "Use POST with a DICOM file as the body");
+            }
+            "/compress" => Self::compress,
+            "/analyze" => Self::analyze,
+            "/health" => {
+                return Response::json(200, &serde_json::json!({ "status": "ok" }));
+            }
+            #[cfg(feature = "prometheus")]
+            "/metrics" if self.metrics.is_some() => {
+                let metrics = self.metrics.as_ref().map(OperationalMetrics::render);
+                return Response {
+                    status: 200,
+                    content_type: monitoring::CONTENT_TYPE,
+                    headers: Vec::new(),
+                    body: metrics.unwrap_or_default().into_bytes(),
+                };
+            }
+            _ => return Response::error(404, "Unknown endpoint"),
+        };
+
+        if request.body.is_empty() {
+            return Response::error(400, "Request body must be a DICOM file");
+        }
+
+        match request_config(&self.config, &request.query) {
+            Ok(config) => endpoint(self, config, &request.body),
+            Err(e) => Response::from_error(&e),
+        }
+    }
+
+    /// Compress the request body and return the compressed file.
+    fn compress(&self, config: CompressionConfig, body: &[u8]) -> Response {
+        let mut output = Vec::new();
+        let result = CompressionPipeline::new(config).compress_stream(Cursor::new(body), &mut output);
+        #[cfg(feature = "prometheus")]
+        if let Some(ref metrics) = self.metrics {
+            metrics.record(&result);
+        }
+
+        match result {
+            Ok(result) => Response {
+                status: 200,
+                content_type: "application/dicom",
+                headers: vec![
+                    ("X-Original-Size", result.original_size.to_string()),
+                    ("X-Compressed-Size", result.compressed_size.to_string()),
+                    ("X-Compression-Ratio", format!("{:.4}", result.compression_ratio)),
+                ],
+                body: output,
+            },
+            Err(e) => Response::from_error(&e),
+        }
+    }
+
+    /// Analyze the request body without returning the compressed file.
+    fn analyze(&self, config: CompressionConfig, body: &[u8]) -> Response {
+        let pipeline = PipelineBuilder::new().config(config).dry_run(true).build();
+        match pipeline.compress_stream(Cursor::new(body), std::io::sink()) {
+            Ok(result) => Response::json(200, &result),
+            Err(e) => Response::from_error(&e),
+        }
+    }
+}
+
+/// Apply query parameter overrides to the default settings.
+pub(crate) fn request_config(
+    defaults: &CompressionConfig,
+    query: &HashMap<String, String>,
+) -> Result<CompressionConfig> {
+    let invalid = |name: &str, value: &str| {
+        MedImgError::Config(format!("Invalid value '{}' for '{}'", value, name))
+    };
+
+    let mut overrides = CompressionOverrides::default();
+    for (name, value) in query {
+        match name.as_str() {
+            "codec" => {
+                overrides.codec = Some(match value.as_str() {
+                    "jpeg2000" => CompressionCodec::Jpeg2000,
+                    "jpeg-ls" => CompressionCodec::JpegLs,
+                    _ => return Err(invalid(name, value)),
+                })
+            }
+            "mode" => {
+                overrides.mode = Some(match value.as_str() {
+                    "lossless" => CompressionMode::Lossless,
+                    "lossy" => CompressionMode::Lossy,
+                    "near-lossless" => CompressionMode::NearLossless,
+                    _ => return Err(invalid(name, value)),
+                })
+            }
+            "ratio" => {
+                overrides.target_ratio = Some(value.parse().map_err(|_| invalid(name, value))?)
+            }
+            "bpp" => {
+                overrides.target_bpp = Some(value.parse().map_err(|_| invalid(name, value))?)
+            }
+            "near" => {
+                overrides.near_lossless_error =
+                    Some(value.parse().map_err(|_| invalid(name, value))?)
+            }
+            _ => {
+                return Err(MedImgError::Config(format!(
+                    "Unknown query parameter '{}'",
+                    name
+                )))
+            }
+        }
+    }
+
+    // A mode override drops the server's ratio/NEAR unless given again
+    let mut config = defaults.clone().merge(&overrides).with_mode_defaults();
+    // Safety overrides are a local operator decision, never a remote one
+    config.override_safety_checks = None;
+    Ok(config)
+}
+
+/// Read a CRLF- or LF-terminated line, bounded by `MAX_HEADER_LINE`.
+fn read_line<R: BufRead>(reader: &mut R) -> std::io::Result<String> {
+    let mut line = String::new();
+    let read = reader.take(MAX_HEADER_LINE as u64).read_line(&mut line)?;
+    if read == 0 || !line.ends_with('\n') {
+        return Err(std::io::ErrorKind::InvalidData.into());
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Parse `a=1&b=2` query strings (values are not percent-decoded).
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (name.to_string(), value.to_string())
+        })
+        .collect()
+}
+
+/// A connection whose reads fail once `deadline` has passed.
+struct Deadline {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(std::io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(remaining.min(READ_TIMEOUT)))?;
+        self.stream.read(buf)
+    }
+}
+
+/// Answer a connection over the limit with 503 and close it.
+fn reject_busy(stream: TcpStream) {
+    let response = Response::error(503, "Too many connections; retry later");
+    let written = stream
+        .set_nonblocking(false)
+        .and_then(|()| stream.set_write_timeout(Some(REJECT_TIMEOUT)))
+        .map_err(MedImgError::from)
+        .and_then(|()| write_response(&mut &stream, &response));
+    if let Err(e) = written {
+        log::debug!("Failed to reject connection: {}", e);
+        return;
+    }
+    close_unread(&stream);
+}
+
+/// Close a connection after its response, discarding a bounded amount of
+/// the request still in flight.
+///
+/// Closing with the request unread resets the connection, which can
+/// discard the response before the client reads it.
+fn close_unread(stream: &TcpStream) {
+    let _ = stream.shutdown(std::net::Shutdown::Write);
+    let _ = stream.set_read_timeout(Some(REJECT_TIMEOUT));
+    let _ = std::io::copy(&mut stream.take(MAX_DRAIN_BYTES), &mut std::io::sink());
+}
+
+/// Serialize a response to the connection.
+fn write_response<W: Write>(writer: &mut W, response: &Response) -> Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason_phrase(response.status),
+        response.content_type,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    writer.write_all(head.as_bytes())?;
+    writer.write_all(&response.body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dicom::{testing, DicomFile};
+    use std::net::SocketAddr;
+    use tempfile::TempDir;
+
+    /// Send a raw request and return (status, headers+body split).
+    fn send(addr: SocketAddr, method: &str, target: &str, body: &[u8]) -> (u16, String, Vec<u8>) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\n\r\n",
+            method,
+            target,
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(body).unwrap();
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let split = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let head = String::from_utf8(response[..split].to_vec()).unwrap();
+        let status = head[9..12].parse().unwrap();
+        (status, head, response[split + 4..].to_vec())
+    }
+
+    fn sample(dir: &TempDir, modality: &str) -> Vec<u8> {
+        let path = dir.path().join(format!("{}.dcm", modality));
+        testing::write_grayscale(&path, 16, 16, modality, &testing::gradient(16, 16));
+        std::fs::read(path).unwrap()
+    }
+
+    #[test]
+    fn test_server_endpoints() {
+        let dir = TempDir::new().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = CompressionServer::new(CompressionConfig::lossless(CompressionCodec::Jpeg2000));
+        let token = server.cancellation_token();
+        let handle = std::thread::spawn(move || server.serve(listener));
+
+        let (status, _, body) = send(addr, "GET", "/health", b"");
+        assert_eq!(status, 200);
+        assert_eq!(body, br#"{"status":"ok"}"#);
+
+        let input = sample(&dir, "CR");
+        let (status, head, body) = send(addr, "POST", "/compress?codec=jpeg-ls", &input);
+        assert_eq!(status, 200);
+        assert!(head.contains("X-Compression-Ratio"));
+        let compressed = DicomFile::from_reader(Cursor::new(body)).unwrap();
+        assert!(compressed.is_compressed());
+        assert_eq!(compressed.decode_image_data().unwrap().pixel_data, testing::gradient(16, 16));
+
+        let (status, _, body) = send(addr, "POST", "/analyze", &input);
+        assert_eq!(status, 200);
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["output_path"], serde_json::Value::Null);
+
+        // Lossy mammography violates policy
+        let (status, _, _) = send(addr, "POST", "/compress?mode=lossy&ratio=10", &sample(&dir, "MG"));
+        assert_eq!(status, 422);
+
+        assert_eq!(send(addr, "POST", "/compress?codec=gif", &input).0, 400);
+        assert_eq!(send(addr, "GET", "/compress", b"").0, 405);
+        assert_eq!(send(addr, "GET", "/nope", b"").0, 404);
+
+        token.cancel();
+        handle.join().unwrap().unwrap();
+    }
+
+    #[cfg(feature = "prometheus")]
+    #[test]
+    fn test_metrics_endpoint() {
+        let dir = TempDir::new().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let metrics = OperationalMetrics::new();
+        let server = CompressionServer::new(CompressionConfig::lossless(CompressionCodec::JpegLs))
+            .metrics(metrics.clone());
+        let token = server.cancellation_token();
+        let handle = std::thread::spawn(move || server.serve(listener));
+
+        assert_eq!(send(addr, "POST", "/compress", &sample(&dir, "CT")).0, 200);
+        assert_eq!(send(addr, "POST", "/compress", b"not a dicom file").0, 400);
+
+        let (status, head, body) = send(addr, "GET", "/metrics", b"");
+        assert_eq!(status, 200);
+        assert!(head.contains(monitoring::CONTENT_TYPE));
+        let text = String::from_utf8(body).unwrap();
+        assert!(text.contains("medimg_files_processed_total 1"));
+        assert!(text.contains("medimg_failures_total{code="));
+
+        token.cancel();
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_body_limit() {
+        let server = CompressionServer::new(CompressionConfig::default()).max_body_bytes(4);
+        let raw = b"POST /compress HTTP/1.1\r\nContent-Length: 10\r\n\r\n0123456789";
+        let mut reader = BufReader::new(&raw[..]);
+        let rejected = server.read_request(&mut reader, &mut Vec::new()).err().unwrap();
+        assert_eq!(rejected.status, 413);
+
+        // The response reaches a client still sending its body
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let token = server.cancellation_token();
+        let handle = std::thread::spawn(move || server.serve(listener));
+        let (status, head, _) = send(addr, "POST", "/compress", &vec![0; 32 * 1024]);
+        assert_eq!(status, 413);
+        assert!(head.contains("Connection: close"));
+
+        token.cancel();
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_request_limits() {
+        let server = CompressionServer::new(CompressionConfig::default());
+
+        // A declared length is not allocated before the body arrives
+        let raw = b"POST /compress HTTP/1.1\r\nContent-Length: 100000000\r\n\r\nabc";
+        let rejected = server.read_request(&mut BufReader::new(&raw[..]), &mut Vec::new()).err().unwrap();
+        assert_eq!(rejected.status, 400);
+
+        let mut raw = b"GET /health HTTP/1.1\r\n".to_vec();
+        for i in 0..=MAX_HEADERS {
+            raw.extend_from_slice(format!("X-Header-{}: {}\r\n", i, i).as_bytes());
+        }
+        raw.extend_from_slice(b"\r\n");
+        let rejected = server.read_request(&mut BufReader::new(&raw[..]), &mut Vec::new()).err().unwrap();
+        assert_eq!(rejected.status, 431);
+    }
+
+    #[test]
+    fn test_connection_limit() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = CompressionServer::new(CompressionConfig::default()).max_connections(1);
+        let token = server.cancellation_token();
+        let handle = std::thread::spawn(move || server.serve(listener));
+
+        // An idle client holds the only slot
+        let mut idle = TcpStream::connect(addr).unwrap();
+        idle.write_all(b"POST /compress HTTP/1.1\r\n").unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(send(addr, "GET", "/health", b"").0, 503);
+
+        drop(idle);
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(send(addr, "GET", "/health", b"").0, 200);
+
+        token.cancel();
+        handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = parse_query("codec=jpeg-ls&near=2&flag");
+        assert_eq!(query["codec"], "jpeg-ls");
+        assert_eq!(query["near"], "2");
+        assert_eq!(query["flag"], "");
+    }
+}
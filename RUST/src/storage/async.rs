@@ -0,0 +1,66 @@
+//! Async storage access (`tokio` feature).
+
+use std::sync::Arc;
+
+use super::{ObjectInfo, Storage};
+use crate::error::Result;
+
+/// A [`Storage`] usable from async code.
+///
+/// Each call runs the blocking backend on tokio's blocking thread pool
+/// (`spawn_blocking`), like the pipeline's async entry points.
+#[derive(Clone)]
+pub struct AsyncStorage {
+    storage: Arc<dyn Storage>,
+}
+
+impl AsyncStorage {
+    /// Wrap a storage backend.
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+
+    /// The wrapped backend.
+    pub fn storage(&self) -> &Arc<dyn Storage> {
+        &self.storage
+    }
+
+    /// Read an object.
+    pub async fn read(&self, key: &str) -> Result<Vec<u8>> {
+        let storage = self.storage.clone();
+        let key = key.to_string();
+        tokio::task::spawn_blocking(move || storage.read(&key)).await?
+    }
+
+    /// Create or replace an object.
+    pub async fn write(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        let storage = self.storage.clone();
+        let key = key.to_string();
+        tokio::task::spawn_blocking(move || storage.write(&key, &data)).await?
+    }
+
+    /// List the objects under the `prefix` directory.
+    pub async fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        let storage = self.storage.clone();
+        let prefix = prefix.to_string();
+        tokio::task::spawn_blocking(move || storage.list(&prefix)).await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::LocalStorage;
+
+    #[test]
+    fn test_async_storage() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let storage = AsyncStorage::new(Arc::new(LocalStorage::new(dir.path())));
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async {
+            storage.write("a/b.dcm", b"data".to_vec()).await.unwrap();
+            assert_eq!(storage.read("a/b.dcm").await.unwrap(), b"data");
+            assert_eq!(storage.list("a").await.unwrap().len(), 1);
+        });
+    }
+}
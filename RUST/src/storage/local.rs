@@ -0,0 +1,113 @@
+//! Local file system storage.
+
+use std::path::{Path, PathBuf};
+
+use super::{ObjectInfo, Storage};
+use crate::error::{MedImgError, Result};
+
+/// Storage in a local directory: keys are paths relative to the root.
+///
+/// A root of `""` makes keys plain (relative or absolute) paths. Files
+/// whose names are not valid UTF-8 cannot be keyed and are skipped when
+/// listing; symbolic links are not followed.
+#[derive(Debug, Clone)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    /// Storage rooted at `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of the object at `key`.
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+
+    fn list_recursive(&self, dir: &Path, key: &str, objects: &mut Vec<ObjectInfo>) -> Result<()> {
+        let entries = std::fs::read_dir(dir).map_err(|e| MedImgError::from(e).with_file(dir))?;
+        for entry in entries {
+            let entry = entry?;
+            let Some(name) = entry.file_name().to_str().map(|name| super::join_key(key, name)) else {
+                continue;
+            };
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                self.list_recursive(&entry.path(), &name, objects)?;
+            } else if metadata.is_file() {
+                objects.push(ObjectInfo {
+                    key: name,
+                    size: metadata.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Storage for LocalStorage {
+    fn read(&self, key: &str) -> Result<Vec<u8>> {
+        let path = self.path(key);
+        std::fs::read(&path).map_err(|e| MedImgError::from(e).with_file(path))
+    }
+
+    fn write(&self, key: &str, data: &[u8]) -> Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| MedImgError::from(e).with_file(parent))?;
+        }
+        std::fs::write(&path, data).map_err(|e| MedImgError::from(e).with_file(path))
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        let mut objects = Vec::new();
+        self.list_recursive(&self.path(prefix), prefix.trim_matches('/'), &mut objects)?;
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    fn uri(&self, key: &str) -> String {
+        self.path(key).display().to_string()
+    }
+
+    fn local_path(&self, key: &str) -> Option<PathBuf> {
+        Some(self.path(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_storage() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let storage = LocalStorage::new(dir.path());
+        storage.write("study/series/a.dcm", b"aaa").unwrap();
+        storage.write("study/b.dcm", b"b").unwrap();
+        storage.write("c.dcm", b"").unwrap();
+
+        assert_eq!(storage.read("study/series/a.dcm").unwrap(), b"aaa");
+        assert!(dir.path().join("study/b.dcm").is_file());
+
+        let keys: Vec<_> = storage.list("").unwrap().into_iter().map(|o| o.key).collect();
+        assert_eq!(keys, ["c.dcm", "study/b.dcm", "study/series/a.dcm"]);
+        assert_eq!(
+            storage.list("study/series").unwrap(),
+            [ObjectInfo {
+                key: "study/series/a.dcm".into(),
+                size: 3
+            }]
+        );
+
+        assert!(matches!(storage.read("missing.dcm"), Err(e) if e.context().is_some()));
+        assert!(storage.list("missing").is_err());
+    }
+}
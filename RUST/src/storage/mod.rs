@@ -0,0 +1,161 @@
+//! Storage backends for pipeline input/output and batch discovery.
+//!
+//! A [`Storage`] holds objects under `/`-separated keys, like a directory
+//! tree or a cloud bucket:
+//!
+//! - [`LocalStorage`]: a local directory
+//! - [`ObjectStorage`]: Amazon S3 (`s3` feature), Google Cloud Storage
+//!   (`gcs` feature), or Azure Blob Storage (`azure` feature)
+//!
+//! [`open`] picks the backend from a location such as `/data/dicom`,
+//! `s3://bucket/prefix`, `gs://bucket/prefix`, or `az://container/prefix`,
+//! rooted so keys are relative to the location.
+//! Cloud credentials and regions come from the usual environment variables
+//! (`AWS_*`, `GOOGLE_*`, `AZURE_STORAGE_*`).
+//!
+//! With the `tokio` feature, [`AsyncStorage`] wraps any backend for use
+//! from async code.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use medimg_compress::storage;
+//!
+//! let input = storage::open("s3://archive/incoming")?;
+//! let output = storage::open("s3://archive/compressed")?;
+//! let stats = BatchProcessor::without_progress(config)
+//!     .output_storage(output)
+//!     .process_storage(input.as_ref(), "")?;
+//! ```
+
+#[cfg(feature = "tokio")]
+mod r#async;
+mod local;
+#[cfg(any(feature = "s3", feature = "gcs", feature = "azure"))]
+mod object;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::error::{MedImgError, Result};
+
+#[cfg(feature = "tokio")]
+pub use r#async::AsyncStorage;
+pub use local::LocalStorage;
+#[cfg(any(feature = "s3", feature = "gcs", feature = "azure"))]
+pub use object::ObjectStorage;
+
+/// An object listed by [`Storage::list`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectInfo {
+    /// Object key.
+    pub key: String,
+    /// Size in bytes.
+    pub size: u64,
+}
+
+/// Object storage.
+pub trait Storage: Send + Sync {
+    /// Read an object.
+    fn read(&self, key: &str) -> Result<Vec<u8>>;
+
+    /// Create or replace an object.
+    fn write(&self, key: &str, data: &[u8]) -> Result<()>;
+
+    /// List the objects under the `prefix` directory (`""` for all),
+    /// recursively, sorted by key.
+    fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>>;
+
+    /// Location of an object for logs and results (a file path or URL).
+    fn uri(&self, key: &str) -> String;
+
+    /// Path of an object on the local file system, if it has one.
+    ///
+    /// Lets the pipeline read local inputs directly (and memory-map them).
+    fn local_path(&self, _key: &str) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// Whether `location` is a URL rather than a local path.
+pub fn is_url(location: &str) -> bool {
+    location.contains("://")
+}
+
+/// Open the storage at `location`, rooted there.
+///
+/// Local paths open as a [`LocalStorage`]; `s3://bucket/prefix`,
+/// `gs://bucket/prefix`, and `az://container/prefix` open the bucket or
+/// container with keys relative to `prefix`.
+///
+/// # Errors
+///
+/// Returns `Config` for an unknown scheme or one whose feature is not
+/// enabled, or if the backend cannot be configured.
+pub fn open(location: &str) -> Result<Arc<dyn Storage>> {
+    let Some((scheme, rest)) = location.split_once("://") else {
+        return Ok(Arc::new(LocalStorage::new(location)));
+    };
+    let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+    open_bucket(scheme, bucket, prefix)
+}
+
+/// Open a cloud bucket or container, rooted at `prefix`.
+#[cfg_attr(
+    not(any(feature = "s3", feature = "gcs", feature = "azure")),
+    allow(unused_variables)
+)]
+fn open_bucket(scheme: &str, bucket: &str, prefix: &str) -> Result<Arc<dyn Storage>> {
+    match scheme {
+        #[cfg(feature = "s3")]
+        "s3" => return Ok(Arc::new(ObjectStorage::s3(bucket)?.with_root(prefix))),
+        #[cfg(feature = "gcs")]
+        "gs" => return Ok(Arc::new(ObjectStorage::gcs(bucket)?.with_root(prefix))),
+        #[cfg(feature = "azure")]
+        "az" | "azure" => return Ok(Arc::new(ObjectStorage::azure(bucket)?.with_root(prefix))),
+        _ => {}
+    }
+    let feature = match scheme {
+        "s3" => "s3",
+        "gs" => "gcs",
+        "az" | "azure" => "azure",
+        _ => {
+            return Err(MedImgError::Config(format!(
+                "Unsupported storage scheme '{}://' (expected a path, s3://, gs://, or az://)",
+                scheme
+            )))
+        }
+    };
+    Err(MedImgError::Config(format!(
+        "{}:// locations need the `{}` feature",
+        scheme, feature
+    )))
+}
+
+/// Join a key prefix and a relative key.
+pub(crate) fn join_key(prefix: &str, key: &str) -> String {
+    match (prefix.trim_end_matches('/'), key.trim_start_matches('/')) {
+        ("", key) => key.to_string(),
+        (prefix, "") => prefix.to_string(),
+        (prefix, key) => format!("{}/{}", prefix, key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_locations() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let storage = open(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(storage.local_path("a.dcm"), Some(dir.path().join("a.dcm")));
+
+        assert!(matches!(open("ftp://host/dir"), Err(MedImgError::Config(_))));
+        #[cfg(not(feature = "s3"))]
+        assert!(matches!(open("s3://bucket/prefix"), Err(MedImgError::Config(_))));
+
+        assert_eq!(join_key("", "a/b"), "a/b");
+        assert_eq!(join_key("in/", "a"), "in/a");
+    }
+}
@@ -0,0 +1,183 @@
+//! Cloud object storage (`s3`, `gcs`, and `azure` features).
+
+use std::sync::Arc;
+
+use futures::TryStreamExt;
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, PutPayload};
+use tokio::runtime::Runtime;
+
+use super::{ObjectInfo, Storage};
+use crate::error::{MedImgError, Result};
+
+/// Storage error.
+fn storage_error(action: &str, key: &str, e: object_store::Error) -> MedImgError {
+    MedImgError::Storage(format!("Object storage {} of '{}' failed: {}", action, key, e))
+}
+
+/// Storage in a cloud bucket or container.
+///
+/// Requests run on the storage's own tokio runtime, so the blocking
+/// [`Storage`] methods must not be called from async code; wrap the
+/// storage in an [`AsyncStorage`](super::AsyncStorage) there.
+pub struct ObjectStorage {
+    store: Arc<dyn ObjectStore>,
+    /// URL of the bucket, for [`Storage::uri`].
+    url: String,
+    /// Key prefix of every object (no leading or trailing `/`).
+    root: String,
+    /// `Some` until dropped.
+    runtime: Option<Runtime>,
+}
+
+impl ObjectStorage {
+    /// Storage backed by `store`, whose objects are at `url/key`.
+    pub fn new(store: Arc<dyn ObjectStore>, url: impl Into<String>) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+        Ok(Self {
+            store,
+            url: url.into().trim_end_matches('/').to_string(),
+            root: String::new(),
+            runtime: Some(runtime),
+        })
+    }
+
+    /// Root the storage at `prefix`, so keys are relative to it.
+    pub fn with_root(mut self, prefix: &str) -> Self {
+        self.root = super::join_key(&self.root, prefix.trim_matches('/'));
+        self
+    }
+
+    /// Amazon S3 bucket, configured from the `AWS_*` environment variables.
+    #[cfg(feature = "s3")]
+    pub fn s3(bucket: &str) -> Result<Self> {
+        let store = object_store::aws::AmazonS3Builder::from_env()
+            .with_bucket_name(bucket)
+            .build()
+            .map_err(|e| MedImgError::Config(format!("S3 bucket '{}': {}", bucket, e)))?;
+        Self::new(Arc::new(store), format!("s3://{}", bucket))
+    }
+
+    /// Google Cloud Storage bucket, configured from the `GOOGLE_*`
+    /// environment variables.
+    #[cfg(feature = "gcs")]
+    pub fn gcs(bucket: &str) -> Result<Self> {
+        let store = object_store::gcp::GoogleCloudStorageBuilder::from_env()
+            .with_bucket_name(bucket)
+            .build()
+            .map_err(|e| MedImgError::Config(format!("GCS bucket '{}': {}", bucket, e)))?;
+        Self::new(Arc::new(store), format!("gs://{}", bucket))
+    }
+
+    /// Azure Blob Storage container, configured from the `AZURE_STORAGE_*`
+    /// environment variables (including the account name).
+    #[cfg(feature = "azure")]
+    pub fn azure(container: &str) -> Result<Self> {
+        let store = object_store::azure::MicrosoftAzureBuilder::from_env()
+            .with_container_name(container)
+            .build()
+            .map_err(|e| MedImgError::Config(format!("Azure container '{}': {}", container, e)))?;
+        Self::new(Arc::new(store), format!("az://{}", container))
+    }
+
+    /// Location of the object at `key`.
+    fn location(&self, key: &str) -> ObjectPath {
+        ObjectPath::from(super::join_key(&self.root, key))
+    }
+
+    fn runtime(&self) -> &Runtime {
+        self.runtime.as_ref().expect("runtime is only taken on drop")
+    }
+}
+
+impl Drop for ObjectStorage {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which panics inside async code
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl Storage for ObjectStorage {
+    fn read(&self, key: &str) -> Result<Vec<u8>> {
+        let location = self.location(key);
+        let bytes = self
+            .runtime()
+            .block_on(async { self.store.get(&location).await?.bytes().await })
+            .map_err(|e| storage_error("read", key, e))?;
+        Ok(bytes.to_vec())
+    }
+
+    fn write(&self, key: &str, data: &[u8]) -> Result<()> {
+        let location = self.location(key);
+        self.runtime()
+            .block_on(self.store.put(&location, PutPayload::from(data.to_vec())))
+            .map_err(|e| storage_error("write", key, e))?;
+        Ok(())
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<ObjectInfo>> {
+        let location = self.location(prefix);
+        let list_prefix = (!location.as_ref().is_empty()).then_some(&location);
+        let root = match self.root.as_str() {
+            "" => String::new(),
+            root => format!("{}/", root),
+        };
+        let mut objects: Vec<ObjectInfo> = self
+            .runtime()
+            .block_on(
+                self.store
+                    .list(list_prefix)
+                    .map_ok(|meta| ObjectInfo {
+                        key: meta
+                            .location
+                            .as_ref()
+                            .strip_prefix(root.as_str())
+                            .unwrap_or(meta.location.as_ref())
+                            .to_string(),
+                        size: meta.size as u64,
+                    })
+                    .try_collect(),
+            )
+            .map_err(|e| storage_error("list", location.as_ref(), e))?;
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    fn uri(&self, key: &str) -> String {
+        format!("{}/{}", self.url, super::join_key(&self.root, key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use object_store::memory::InMemory;
+
+    use super::*;
+
+    #[test]
+    fn test_object_storage() {
+        let storage = ObjectStorage::new(Arc::new(InMemory::new()), "mem://bucket/").unwrap();
+        storage.write("in/study/a.dcm", b"aaa").unwrap();
+        storage.write("in/b.dcm", b"b").unwrap();
+        storage.write("other.dcm", b"").unwrap();
+
+        assert_eq!(storage.read("in/study/a.dcm").unwrap(), b"aaa");
+        let keys: Vec<_> = storage.list("in").unwrap().into_iter().map(|o| o.key).collect();
+        assert_eq!(keys, ["in/b.dcm", "in/study/a.dcm"]);
+        assert_eq!(storage.list("").unwrap().len(), 3);
+        assert_eq!(storage.uri("in/b.dcm"), "mem://bucket/in/b.dcm");
+
+        assert!(matches!(storage.read("missing.dcm"), Err(MedImgError::Storage(_))));
+
+        let store: Arc<dyn ObjectStore> = storage.store.clone();
+        let rooted = ObjectStorage::new(store, "mem://bucket").unwrap().with_root("/in/");
+        assert_eq!(rooted.read("b.dcm").unwrap(), b"b");
+        let keys: Vec<_> = rooted.list("").unwrap().into_iter().map(|o| o.key).collect();
+        assert_eq!(keys, ["b.dcm", "study/a.dcm"]);
+        rooted.write("out/c.dcm", b"c").unwrap();
+        assert_eq!(storage.read("in/out/c.dcm").unwrap(), b"c");
+        assert_eq!(rooted.uri("b.dcm"), "mem://bucket/in/b.dcm");
+    }
+}
@@ -0,0 +1,238 @@
+//! OpenTelemetry trace export (`otlp` feature).
+//!
+//! The pipeline and batch processor already open `tracing` spans;
+//! [`OtlpExporter`] installs a subscriber that sends them to an OTLP
+//! collector over gRPC, so compression latency shows up next to other
+//! services in the same trace backend:
+//!
+//! - `batch`: `total_files`, `successful`, `failed`, `ratio`, `duration_ms`
+//! - `batch_job`: one per file, parent of the file span
+//! - `compress_file` / `transcode_file`: `study_uid_hash`, `codec`, `mode`,
+//!   `original_size`, `compressed_size`, `ratio`, `duration_ms`
+//! - `phase`: read, encode, verify, measure, and write steps
+//!
+//! Studies are identified by [`DicomMetadata::study_uid_hash`], never by
+//! their UID. File spans also carry the input path in `file`; keep paths
+//! free of patient identifiers when exporting outside the PHI boundary.
+//!
+//! [`DicomMetadata::study_uid_hash`]: crate::dicom::DicomMetadata::study_uid_hash
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use medimg_compress::telemetry::OtlpExporter;
+//!
+//! let _telemetry = OtlpExporter::new("http://otel-collector:4317").install()?;
+//! let stats = BatchProcessor::without_progress(config).process_directory(&input_dir)?;
+//! // Dropping the guard flushes the remaining spans
+//! ```
+
+use std::time::Duration;
+
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::Resource;
+use tokio::runtime::Runtime;
+use tracing::Subscriber;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::error::{MedImgError, Result};
+
+/// Default collector endpoint (OTLP/gRPC).
+pub const DEFAULT_ENDPOINT: &str = "http://localhost:4317";
+
+/// Default `service.name` resource attribute.
+pub const DEFAULT_SERVICE_NAME: &str = "medimg-compress";
+
+/// Default timeout of each export request.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time allowed for in-flight exports when the guard is dropped.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Name of the instrumentation scope of exported spans.
+const SCOPE: &str = "medimg_compress";
+
+/// Exports the library's spans to an OTLP collector.
+pub struct OtlpExporter {
+    /// Collector endpoint.
+    endpoint: String,
+    /// `service.name` resource attribute.
+    service_name: String,
+    /// Timeout of each export request.
+    timeout: Duration,
+}
+
+impl OtlpExporter {
+    /// Exporter sending to the collector at `endpoint` (e.g.
+    /// `http://otel-collector:4317`).
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            service_name: DEFAULT_SERVICE_NAME.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Set the `service.name` the spans are reported under.
+    pub fn service_name(mut self, name: impl Into<String>) -> Self {
+        self.service_name = name.into();
+        self
+    }
+
+    /// Set the timeout of each export request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Install the exporter as the global `tracing` subscriber.
+    ///
+    /// Spans are batched and exported in the background until the returned
+    /// guard is dropped, which flushes the rest.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Config` if the exporter cannot be built or another
+    /// subscriber is already installed.
+    pub fn install(self) -> Result<TelemetryGuard> {
+        // The batch processor and gRPC channel run on their own runtime, so
+        // callers need not be async
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("medimg-otlp")
+            .enable_all()
+            .build()?;
+        let provider = {
+            let _entered = runtime.enter();
+            let exporter = SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(self.endpoint.as_str())
+                .with_timeout(self.timeout)
+                .build()
+                .map_err(|e| MedImgError::Config(format!("OTLP exporter for '{}': {}", self.endpoint, e)))?;
+            TracerProvider::builder()
+                .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+                .with_resource(resource(&self.service_name))
+                .build()
+        };
+
+        let subscriber = tracing_subscriber::registry().with(layer(&provider));
+        tracing::subscriber::set_global_default(subscriber)
+            .map_err(|_| MedImgError::Config("A tracing subscriber is already installed".into()))?;
+        log::debug!("Exporting traces to {}", self.endpoint);
+
+        Ok(TelemetryGuard {
+            provider,
+            runtime: Some(runtime),
+        })
+    }
+}
+
+/// Keeps the exporter running; dropping it flushes the remaining spans.
+pub struct TelemetryGuard {
+    provider: TracerProvider,
+    /// `Some` until dropped.
+    runtime: Option<Runtime>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.provider.shutdown() {
+            log::warn!("Failed to flush traces: {}", e);
+        }
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_timeout(SHUTDOWN_TIMEOUT);
+        }
+    }
+}
+
+/// Resource attributes of exported spans.
+fn resource(service_name: &str) -> Resource {
+    Resource::new_with_defaults([
+        KeyValue::new("service.name", service_name.to_string()),
+        KeyValue::new("service.version", crate::version::VERSION),
+    ])
+}
+
+/// Layer sending spans (including `debug` phase spans) to `provider`.
+fn layer<S>(provider: &TracerProvider) -> impl Layer<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer(SCOPE))
+        .with_filter(LevelFilter::DEBUG)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use futures::future::BoxFuture;
+    use opentelemetry::Value;
+    use opentelemetry_sdk::export::trace::{ExportResult, SpanData};
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::batch::BatchProcessor;
+    use crate::config::{CompressionCodec, CompressionConfig};
+    use crate::dicom::testing;
+
+    /// Keeps exported spans for inspection.
+    #[derive(Debug, Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<SpanData>>>);
+
+    impl opentelemetry_sdk::export::trace::SpanExporter for Captured {
+        fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+            self.0.lock().unwrap().extend(batch);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn attribute<'a>(span: &'a SpanData, key: &str) -> Option<&'a Value> {
+        span.attributes.iter().find(|kv| kv.key.as_str() == key).map(|kv| &kv.value)
+    }
+
+    #[test]
+    fn test_exports_pipeline_and_batch_spans() {
+        let dir = TempDir::new().unwrap();
+        let input = dir.path().join("in");
+        std::fs::create_dir(&input).unwrap();
+        testing::write_grayscale(&input.join("a.dcm"), 16, 8, "CT", &testing::gradient(16, 8));
+
+        let captured = Captured::default();
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(captured.clone())
+            .with_resource(resource("test"))
+            .build();
+        let subscriber = tracing_subscriber::registry().with(layer(&provider));
+        let stats = tracing::subscriber::with_default(subscriber, || {
+            BatchProcessor::without_progress(CompressionConfig::lossless(CompressionCodec::JpegLs))
+                .max_parallel(1)
+                .output_dir(dir.path().join("out"))
+                .process_directory(&input)
+                .unwrap()
+        });
+        assert_eq!(stats.successful, 1);
+        let _ = provider.shutdown();
+
+        let spans = captured.0.lock().unwrap();
+        let file = spans.iter().find(|span| span.name == "compress_file").unwrap();
+        assert_eq!(attribute(file, "codec").map(Value::as_str).as_deref(), Some("JpegLs"));
+        assert!(matches!(attribute(file, "ratio"), Some(Value::F64(ratio)) if *ratio > 0.0));
+        assert!(attribute(file, "duration_ms").is_some());
+        let batch = spans.iter().find(|span| span.name == "batch").unwrap();
+        assert_eq!(attribute(batch, "successful"), Some(&Value::I64(1)));
+        // The file span nests under its job, which nests under the batch
+        let job = spans.iter().find(|span| span.name == "batch_job").unwrap();
+        assert_eq!(file.parent_span_id, job.span_context.span_id());
+        assert_eq!(job.parent_span_id, batch.span_context.span_id());
+        assert!(spans.iter().any(|span| span.name == "phase"));
+    }
+}
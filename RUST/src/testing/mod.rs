@@ -0,0 +1,163 @@
+//! Synthetic phantoms and a self-test of the installed codecs.
+//!
+//! [`phantoms`] generates a small corpus that stresses what real studies
+//! stress: smooth 16-bit ramps, CT slices with correlated noise, burned-in
+//! text, multi-frame cines, and color ultrasound. [`self_test`] encodes
+//! and decodes each phantom with every codec/mode scenario and checks the
+//! mode's guarantee, so a deployment can confirm that its build (native
+//! libraries, plugins, CPU features) reconstructs images correctly before
+//! it touches patient data.
+//!
+//! ```rust,ignore
+//! use medimg_compress::codec::bench::default_scenarios;
+//! use medimg_compress::testing::{phantoms, self_test};
+//!
+//! let cases = self_test(&default_scenarios(10.0, 2), &phantoms(256));
+//! assert!(cases.iter().all(|case| case.passed));
+//! ```
+
+mod phantom;
+
+pub use phantom::{cine, ct_slice, doppler, gradient, phantoms, text_overlay, Phantom};
+
+use serde::Serialize;
+
+use crate::codec::CodecFactory;
+use crate::config::{CompressionConfig, CompressionMode};
+use crate::error::{MedImgError, Result};
+use crate::metrics::ImageComparator;
+use crate::ImageData;
+
+/// Outcome of one phantom with one codec/mode scenario.
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestCase {
+    /// Phantom name.
+    pub phantom: String,
+    /// Codec name.
+    pub codec: String,
+    /// Compression mode.
+    pub mode: CompressionMode,
+    /// NEAR for near-lossless, target ratio for lossy.
+    pub parameter: Option<f64>,
+    /// Frames encoded.
+    pub frames: u32,
+    /// Compression ratio achieved.
+    pub ratio: f64,
+    /// Largest absolute pixel error after decoding.
+    pub max_error: u64,
+    /// PSNR in dB (`None` if reconstruction was exact).
+    pub psnr_db: Option<f64>,
+    /// Whether the decoded image met the mode's guarantee.
+    pub passed: bool,
+    /// Why the case failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Round-trip every phantom through every scenario.
+///
+/// Lossless scenarios must reconstruct every sample exactly and
+/// near-lossless ones within NEAR; lossy scenarios must decode to an image
+/// of the original geometry. Failures are reported in the cases, not
+/// returned.
+pub fn self_test(scenarios: &[CompressionConfig], phantoms: &[Phantom]) -> Vec<SelfTestCase> {
+    scenarios
+        .iter()
+        .flat_map(|config| phantoms.iter().map(move |phantom| run_case(config, phantom)))
+        .collect()
+}
+
+fn run_case(config: &CompressionConfig, phantom: &Phantom) -> SelfTestCase {
+    let image = &phantom.image;
+    let mut case = SelfTestCase {
+        phantom: phantom.name.to_string(),
+        codec: format!("{:?}", config.codec),
+        mode: config.mode,
+        parameter: match config.mode {
+            CompressionMode::Lossless => None,
+            CompressionMode::NearLossless => Some(config.near_lossless_error as f64),
+            CompressionMode::Lossy => config.target_ratio.map(f64::from),
+        },
+        frames: image.number_of_frames.max(1),
+        ratio: 0.0,
+        max_error: 0,
+        psnr_db: None,
+        passed: false,
+        error: None,
+    };
+    if let Err(e) = round_trip(config, image, &mut case) {
+        log::warn!("Self-test {} {} {:?} failed: {}", case.phantom, case.codec, case.mode, e);
+        case.error = Some(e.to_string());
+    }
+    case
+}
+
+/// Encode and decode `image`, filling in the measurements of `case`.
+fn round_trip(config: &CompressionConfig, image: &ImageData, case: &mut SelfTestCase) -> Result<()> {
+    let codec = CodecFactory::for_config(config)?;
+    case.codec = codec.info().name.to_string();
+    let encoded = codec.encode_frames(image, config, None)?;
+    let compressed: usize = encoded.iter().map(Vec::len).sum();
+    if compressed > 0 {
+        case.ratio = image.pixel_data.len() as f64 / compressed as f64;
+    }
+
+    let mut decoded = codec.decode_frames(
+        &encoded,
+        image.width,
+        image.height,
+        image.bits_per_sample,
+        image.samples_per_pixel,
+    )?;
+    decoded.is_signed = image.is_signed;
+    let report = ImageComparator::new().compare(image, &decoded)?;
+    case.max_error = report.max_error;
+    if report.max_error > 0 {
+        case.psnr_db = Some(report.psnr.psnr_db);
+    }
+
+    let bound = match config.mode {
+        CompressionMode::Lossless => Some(0),
+        CompressionMode::NearLossless => Some(u64::from(config.near_lossless_error)),
+        CompressionMode::Lossy => None,
+    };
+    if let Some(bound) = bound.filter(|&bound| report.max_error > bound) {
+        return Err(MedImgError::VerificationFailed(format!(
+            "maximum error {} exceeds the bound of {}",
+            report.max_error, bound
+        )));
+    }
+    case.passed = true;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::bench::default_scenarios;
+
+    #[test]
+    fn test_self_test_passes() {
+        let cases = self_test(&default_scenarios(10.0, 3), &phantoms(64));
+        assert_eq!(cases.len(), 4 * 5);
+        for case in &cases {
+            assert!(case.passed, "{} {} {:?}: {:?}", case.phantom, case.codec, case.mode, case.error);
+        }
+        let near = cases.iter().filter(|case| case.mode == CompressionMode::NearLossless);
+        assert!(near.clone().all(|case| case.max_error <= 3));
+        assert!(near.clone().any(|case| case.max_error > 0));
+        let cine = cases.iter().find(|case| case.phantom == "cine").unwrap();
+        assert_eq!(cine.frames, 8);
+    }
+
+    #[test]
+    fn test_self_test_reports_failures() {
+        let config = CompressionConfig {
+            encoder_plugin: Some("/nonexistent/encoder".into()),
+            ..CompressionConfig::default()
+        };
+        let cases = self_test(&[config], &phantoms(64)[..1]);
+        assert!(!cases[0].passed);
+        assert!(cases[0].error.is_some());
+    }
+}
@@ -0,0 +1,386 @@
+//! Synthetic phantom images.
+//!
+//! Every phantom is deterministic: the same size always yields the same
+//! pixels, so a failing self-test can be reproduced exactly.
+
+use crate::ImageData;
+
+/// A named synthetic image.
+#[derive(Debug, Clone)]
+pub struct Phantom {
+    /// Short name (e.g. `ct-noise`).
+    pub name: &'static str,
+    /// What the phantom exercises.
+    pub description: &'static str,
+    /// The pixels.
+    pub image: ImageData,
+}
+
+/// The standard phantom set at `size`x`size` pixels (at least 64).
+pub fn phantoms(size: u32) -> Vec<Phantom> {
+    let size = size.max(64);
+    vec![
+        Phantom {
+            name: "gradient",
+            description: "16-bit diagonal ramp over the full sample range",
+            image: gradient(size, size),
+        },
+        Phantom {
+            name: "ct-noise",
+            description: "12-bit CT slice with correlated quantum noise",
+            image: ct_slice(size, size, 12.0),
+        },
+        Phantom {
+            name: "text",
+            description: "8-bit radiograph with burned-in annotations",
+            image: text_overlay(size, size),
+        },
+        Phantom {
+            name: "cine",
+            description: "8-bit multi-frame ultrasound cine with a moving target",
+            image: cine(size, size, 8),
+        },
+        Phantom {
+            name: "doppler",
+            description: "8-bit RGB ultrasound with a color flow box",
+            image: doppler(size, size),
+        },
+    ]
+}
+
+/// Diagonal ramp from 0 to 65535 with a shallow radial ripple.
+pub fn gradient(width: u32, height: u32) -> ImageData {
+    let span = (width + height).saturating_sub(2).max(1) as f64;
+    let (cx, cy) = (width as f64 / 2.0, height as f64 / 2.0);
+    let pixels: Vec<u8> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            let ramp = (x + y) as f64 / span;
+            let r = ((x as f64 - cx).hypot(y as f64 - cy)) / cx.max(1.0);
+            let ripple = (r * 12.0).sin() * 0.01;
+            (((ramp + ripple).clamp(0.0, 1.0) * 65535.0).round() as u16).to_le_bytes()
+        })
+        .collect();
+    monochrome(width, height, 16, 1, pixels)
+}
+
+/// Axial CT slice of a chest, stored as HU + 1024 in 12 bits.
+///
+/// Noise is white Gaussian noise smoothed by a reconstruction-like kernel
+/// and rescaled to `noise_hu` standard deviation, so neighbouring pixels
+/// are correlated as in filtered back-projection. Outside the
+/// reconstruction circle the value is 0 (-1024 HU), as scanners pad it.
+pub fn ct_slice(width: u32, height: u32, noise_hu: f64) -> ImageData {
+    let (w, h) = (width as usize, height as usize);
+    let noise = correlated_noise(w, h, 0x00C7_5EED);
+    let mut pixels = Vec::with_capacity(w * h * 2);
+    for y in 0..h {
+        for x in 0..w {
+            let u = 2.0 * x as f64 / w as f64 - 1.0;
+            let v = 2.0 * y as f64 / h as f64 - 1.0;
+            let stored = if u * u + v * v > 0.98 * 0.98 {
+                0
+            } else {
+                let hu = chest_hu(u, v) + noise[y * w + x] * noise_hu;
+                (hu + 1024.0).round().clamp(0.0, 4095.0) as u16
+            };
+            pixels.extend_from_slice(&stored.to_le_bytes());
+        }
+    }
+    monochrome(width, height, 16, 1, pixels)
+}
+
+/// Attenuation in HU of the chest phantom at normalized (`u`, `v`).
+fn chest_hu(u: f64, v: f64) -> f64 {
+    let inside = |cx: f64, cy: f64, rx: f64, ry: f64| {
+        let (du, dv) = ((u - cx) / rx, (v - cy) / ry);
+        du * du + dv * dv <= 1.0
+    };
+    if !inside(0.0, 0.0, 0.85, 0.65) {
+        // Table under the patient
+        return if (0.72..0.76).contains(&v) && u.abs() < 0.9 {
+            300.0
+        } else {
+            -1000.0
+        };
+    }
+    if inside(0.0, 0.42, 0.04, 0.04) {
+        20.0
+    } else if inside(0.0, 0.42, 0.1, 0.1) {
+        700.0
+    } else if inside(0.1, -0.05, 0.18, 0.18) {
+        150.0
+    } else if inside(-0.38, -0.05, 0.25, 0.38) || inside(0.38, -0.05, 0.25, 0.38) {
+        -850.0
+    } else if !inside(0.0, 0.0, 0.78, 0.58) {
+        -100.0
+    } else {
+        40.0
+    }
+}
+
+/// Chest radiograph-like background with white annotation text in the
+/// corners, the sharp edges lossy coders smear first.
+pub fn text_overlay(width: u32, height: u32) -> ImageData {
+    let (w, h) = (width as usize, height as usize);
+    let noise = correlated_noise(w, h, 0x7E47);
+    let mut pixels: Vec<u8> = (0..w * h)
+        .map(|i| {
+            let (u, v) = (2.0 * (i % w) as f64 / w as f64 - 1.0, 2.0 * (i / w) as f64 / h as f64 - 1.0);
+            let body = (1.0 - (u * u * 1.4 + v * v)).max(0.0);
+            (body * 150.0 + 20.0 + noise[i] * 4.0).round().clamp(0.0, 255.0) as u8
+        })
+        .collect();
+    let scale = (w / 128).max(1);
+    let line = 9 * scale;
+    let lines = ["PATIENT: PHANTOM", "KVP 120  MAS 2.5", "R"];
+    for (row, text) in lines.iter().enumerate() {
+        burn_text(&mut pixels, w, h, (2 * scale, 2 * scale + row * line), scale, text, 255);
+    }
+    burn_text(&mut pixels, w, h, (2 * scale, h.saturating_sub(line)), scale, "SERIES 1/1", 255);
+    monochrome(width, height, 8, 1, pixels)
+}
+
+/// Ultrasound-like cine: speckle background with a bright target
+/// circling the center and the frame number burned in.
+pub fn cine(width: u32, height: u32, frames: u32) -> ImageData {
+    let (w, h) = (width as usize, height as usize);
+    let frames = frames.max(1);
+    let scale = (w / 128).max(1);
+    let mut pixels = Vec::with_capacity(w * h * frames as usize);
+    for frame in 0..frames {
+        let angle = std::f64::consts::TAU * frame as f64 / frames as f64;
+        let (tx, ty) = (0.5 + 0.25 * angle.cos(), 0.5 + 0.25 * angle.sin());
+        let mut plane: Vec<u8> = (0..w * h)
+            .map(|i| {
+                let (x, y) = ((i % w) as f64 / w as f64, (i / w) as f64 / h as f64);
+                let target = (-((x - tx).powi(2) + (y - ty).powi(2)) / 0.004).exp();
+                let speckle = speckle(i as u32 ^ (frame << 24));
+                (speckle * 60.0 + target * 180.0).round().clamp(0.0, 255.0) as u8
+            })
+            .collect();
+        let label = format!("FRAME {}", frame + 1);
+        burn_text(&mut plane, w, h, (2 * scale, 2 * scale), scale, &label, 255);
+        pixels.extend(plane);
+    }
+    let mut image = monochrome(width, height, 8, 1, pixels);
+    image.number_of_frames = frames;
+    image
+}
+
+/// RGB ultrasound: grayscale speckle sector with a color flow box of red
+/// (towards the probe) and blue (away) velocities.
+pub fn doppler(width: u32, height: u32) -> ImageData {
+    let (w, h) = (width as usize, height as usize);
+    let mut pixels = Vec::with_capacity(w * h * 3);
+    for y in 0..h {
+        for x in 0..w {
+            let (u, v) = (x as f64 / w as f64 - 0.5, y as f64 / h as f64);
+            let in_sector = v > 0.05 && u.abs() < v * 0.6 && v < 0.95;
+            let gray = if in_sector {
+                (speckle((y * w + x) as u32) * 90.0).round().min(255.0) as u8
+            } else {
+                0
+            };
+            let in_box = in_sector && (0.3..0.6).contains(&v) && u.abs() < 0.15;
+            let rgb = if in_box {
+                let flow = ((u / 0.15) * std::f64::consts::PI).sin();
+                let level = (flow.abs() * 200.0 + 55.0) as u8;
+                if flow >= 0.0 {
+                    [level, level / 4, 0]
+                } else {
+                    [0, level / 4, level]
+                }
+            } else {
+                [gray; 3]
+            };
+            pixels.extend_from_slice(&rgb);
+        }
+    }
+    let mut image = ImageData::new(width, height, 8, 3, pixels);
+    image.photometric_interpretation = "RGB".into();
+    image
+}
+
+fn monochrome(width: u32, height: u32, bits: u16, samples: u16, pixels: Vec<u8>) -> ImageData {
+    let mut image = ImageData::new(width, height, bits, samples, pixels);
+    image.photometric_interpretation = "MONOCHROME2".into();
+    image
+}
+
+/// Integer hash (lowbias32) used as a deterministic noise source.
+fn hash(mut h: u32) -> u32 {
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    h
+}
+
+/// Uniform sample in (0, 1].
+fn uniform(key: u32) -> f64 {
+    (f64::from(hash(key)) + 1.0) / 4_294_967_296.0
+}
+
+/// Standard normal sample (Box-Muller).
+fn gaussian(key: u32) -> f64 {
+    let (a, b) = (uniform(key.wrapping_mul(2)), uniform(key.wrapping_mul(2).wrapping_add(1)));
+    (-2.0 * a.ln()).sqrt() * (std::f64::consts::TAU * b).cos()
+}
+
+/// Rayleigh-distributed speckle with unit mean-ish amplitude.
+fn speckle(key: u32) -> f64 {
+    (-2.0 * uniform(key ^ 0x5BEC_C1E5).ln()).sqrt() * 0.8
+}
+
+/// Gaussian noise smoothed by a separable [1 2 1] kernel, renormalized to
+/// unit standard deviation.
+fn correlated_noise(width: usize, height: usize, seed: u32) -> Vec<f64> {
+    let white: Vec<f64> = (0..width * height)
+        .map(|i| gaussian((i as u32).wrapping_add(seed.wrapping_mul(0x9E37_79B9))))
+        .collect();
+    let smooth = |data: &[f64], step: usize, len: usize, index: usize, pos: usize| {
+        let before = if pos > 0 { data[index - step] } else { data[index] };
+        let after = if pos + 1 < len { data[index + step] } else { data[index] };
+        (before + 2.0 * data[index] + after) / 4.0
+    };
+    let rows: Vec<f64> = (0..width * height)
+        .map(|i| smooth(&white, 1, width, i, i % width))
+        .collect();
+    let mut noise: Vec<f64> = (0..width * height)
+        .map(|i| smooth(&rows, width, height, i, i / width))
+        .collect();
+    let variance = noise.iter().map(|n| n * n).sum::<f64>() / noise.len().max(1) as f64;
+    let scale = if variance > 0.0 { variance.sqrt().recip() } else { 0.0 };
+    noise.iter_mut().for_each(|n| *n *= scale);
+    noise
+}
+
+/// Draw `text` into an 8-bit plane with its top-left corner at `origin`,
+/// each font pixel `scale` pixels square. Text running off the plane is
+/// clipped.
+fn burn_text(
+    plane: &mut [u8],
+    width: usize,
+    height: usize,
+    origin: (usize, usize),
+    scale: usize,
+    text: &str,
+    value: u8,
+) {
+    for (index, ch) in text.chars().enumerate() {
+        let left = origin.0 + index * 6 * scale;
+        for (row, bits) in glyph(ch).iter().enumerate() {
+            for col in 0..5 {
+                if bits & (0x10 >> col) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let (x, y) = (left + col * scale + dx, origin.1 + row * scale + dy);
+                        if x < width && y < height {
+                            plane[y * width + x] = value;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Rows of a 5x7 glyph, most significant of the low 5 bits leftmost.
+/// Characters without a glyph draw as a solid block.
+fn glyph(ch: char) -> [u8; 7] {
+    match ch.to_ascii_uppercase() {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        ' ' => [0; 7],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        _ => [0x1F; 7],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phantoms_are_valid_and_deterministic() {
+        let set = phantoms(96);
+        assert_eq!(set.len(), 5);
+        for phantom in &set {
+            assert!(phantom.image.validate().is_ok(), "{}", phantom.name);
+        }
+        assert_eq!(set[3].image.number_of_frames, 8);
+        assert_eq!(set[4].image.samples_per_pixel, 3);
+        assert_eq!(phantoms(96)[1].image.pixel_data, set[1].image.pixel_data);
+    }
+
+    #[test]
+    fn test_ct_noise_statistics() {
+        let (size, sigma) = (128, 12.0);
+        let noisy = ct_slice(size, size, sigma).as_u16().unwrap().samples;
+        let clean = ct_slice(size, size, 0.0).as_u16().unwrap().samples;
+        // Soft tissue between the lungs: constant 40 HU without noise
+        let region: Vec<f64> = (32..38)
+            .flat_map(|y| (60..68).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let i = y * size as usize + x;
+                assert_eq!(clean[i], 1064);
+                f64::from(noisy[i]) - f64::from(clean[i])
+            })
+            .collect();
+        let mean = region.iter().sum::<f64>() / region.len() as f64;
+        let sd = (region.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / region.len() as f64).sqrt();
+        assert!(mean.abs() < 4.0, "mean {}", mean);
+        assert!((6.0..20.0).contains(&sd), "sd {}", sd);
+        // Padding outside the reconstruction circle
+        assert_eq!(noisy[0], 0);
+    }
+
+    #[test]
+    fn test_text_is_burned_in() {
+        let image = text_overlay(128, 128);
+        // Top row of the "P" in the first line, at scale 1
+        let row = &image.pixel_data[2 * 128 + 2..2 * 128 + 6];
+        assert_eq!(row, &[255, 255, 255, 255]);
+    }
+}
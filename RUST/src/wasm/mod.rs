@@ -0,0 +1,66 @@
+//! JavaScript bindings for decoding in the browser (wasm32 only).
+//!
+//! Build with `wasm-pack build --target web` (or `cargo build --target
+//! wasm32-unknown-unknown` plus `wasm-bindgen`). Only the decode path is
+//! available on wasm32: DICOM parsing, JPEG 2000/JPEG-LS decode,
+//! windowing, and PNG rendering. Files arrive as byte arrays (e.g. from
+//! `fetch` or a WADO response); nothing touches the file system or spawns
+//! threads.
+
+use wasm_bindgen::prelude::*;
+
+use crate::dicom::DicomFile;
+use crate::error::MedImgError;
+use crate::export::{encode_png, PreviewOptions, Window};
+
+/// Convert a library error for JavaScript.
+fn js_error(e: MedImgError) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+/// Parse a DICOM file held in memory.
+fn parse(data: &[u8]) -> Result<DicomFile, JsError> {
+    DicomFile::from_reader(data).map_err(js_error)
+}
+
+/// Render a frame of a DICOM file as PNG, windowed with the dataset's
+/// Window Center/Width (or its value range), and downsampled so neither
+/// dimension exceeds `max_size` if given.
+#[wasm_bindgen(js_name = renderPng)]
+pub fn render_png(data: &[u8], frame: u32, max_size: Option<u32>) -> Result<Vec<u8>, JsError> {
+    let options = PreviewOptions {
+        frame,
+        max_size,
+        ..Default::default()
+    };
+    encode_png(&parse(data)?, &options).map_err(js_error)
+}
+
+/// Render a frame of a DICOM file as PNG with an explicit window, in
+/// rescaled units (e.g. HU for CT).
+#[wasm_bindgen(js_name = renderPngWindowed)]
+pub fn render_png_windowed(
+    data: &[u8],
+    frame: u32,
+    center: f64,
+    width: f64,
+    max_size: Option<u32>,
+) -> Result<Vec<u8>, JsError> {
+    let options = PreviewOptions {
+        frame,
+        window: Window::Explicit { center, width },
+        max_size,
+        ..Default::default()
+    };
+    encode_png(&parse(data)?, &options).map_err(js_error)
+}
+
+/// Decode a frame of a DICOM file to its stored sample values, packed
+/// little-endian at the image's bits allocated (e.g. a `Uint16Array`
+/// buffer for 16-bit data).
+#[wasm_bindgen(js_name = decodeFrame)]
+pub fn decode_frame(data: &[u8], frame: u32) -> Result<Vec<u8>, JsError> {
+    let image = parse(data)?.decode_image_data().map_err(js_error)?;
+    let frame = image.frame_data(frame).map_err(js_error)?;
+    Ok(frame.packed().pixel_data.to_vec())
+}